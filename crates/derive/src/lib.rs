@@ -49,6 +49,8 @@ struct MacroField {
 ///  * `all_params(&self)` returns a collection of parameters suitable for direct use with rusqlite from a certain model
 ///    struct object. Commonly used together with `replace_into()` and `insert_into()`. Certain fields can be ignored
 ///    with the `ignore_in_all_params` attribute (see below).
+///  * `try_from_row(row)` returns a new model struct from a rusqlite row, reading each field from the column with the
+///    same name. `From<&Row>` is also implemented, panicking if the conversion fails.
 ///
 /// By default, the derive macro will derive the database table name from model struct's
 /// name by converting it into snake case. This can be overridden with the `table_name` attribute
@@ -311,23 +313,29 @@ fn all_params_fn(fields: &[MacroField]) -> TokenStream {
 fn from_row_impl(ident: &Ident, gen: &Generics, fields: &[MacroField]) -> TokenStream {
     let mut field_setters = Vec::new();
 
-    for (index, field) in fields.iter().enumerate() {
+    for field in fields {
         let field_ident = &field.ident;
-        let error_message = format!(
-            "column conversion to {}.{} failed",
-            ident.to_string(),
-            field_ident.to_string()
-        );
+        let column = field_ident.to_string();
 
-        field_setters.push(quote!(#field_ident: row.get(#index).expect(#error_message)));
+        field_setters.push(quote!(#field_ident: row.get(#column)?));
     }
 
+    let panic_message = format!("failed to convert row to {}: {{}}", ident);
+
     quote!(
+        impl#gen #ident#gen {
+            /// Returns a new model from a given row, reading each field from the column with the same name. Returns an
+            /// error if a column is missing or its value can't be converted to the field's type.
+            pub fn try_from_row(row: &::rusqlite::Row) -> ::rusqlite::Result<Self> {
+                Ok(Self {
+                    #(#field_setters),*
+                })
+            }
+        }
+
         impl#gen From<&::rusqlite::Row<'_>> for #ident#gen {
             fn from(row: &::rusqlite::Row) -> Self {
-                Self {
-                    #(#field_setters),*
-                }
+                Self::try_from_row(row).unwrap_or_else(|e| panic!(#panic_message, e))
             }
         }
     )
@@ -16,6 +16,9 @@ CREATE TABLE IF NOT EXISTS "factorio_mod" (
 	"description" TEXT NOT NULL,
 	"changelog" TEXT,
 	"last_updated" TEXT NOT NULL,
+	"category" TEXT,
+	/* the tags are combined into a single string similar to game_settings' tags */
+	"tags" TEXT NOT NULL,
 	PRIMARY KEY("name")
 );
 
@@ -23,7 +26,9 @@ DROP TABLE IF EXISTS "game";
 CREATE TABLE IF NOT EXISTS "game" (
 	/* without AUTOINCREMENT, an integer primary key is aliased to SQLite's internal ROWID which functions better as a primary key and than autoincremented one */
 	"id" INTEGER PRIMARY KEY,
-	"path" TEXT NOT NULL
+	"path" BLOB NOT NULL,
+	/* an optional unique name the game can be addressed by instead of its ID */
+	"name" TEXT UNIQUE
 );
 
 DROP TABLE IF EXISTS "game_settings";
@@ -62,6 +67,22 @@ CREATE TABLE IF NOT EXISTS "game_settings" (
 	"bind_port" INTEGER NOT NULL,
 	"save_name" TEXT NOT NULL,
 	"start_behaviour" TEXT NOT NULL,
+	"mods_managed" INTEGER NOT NULL,
+	"autostart" INTEGER NOT NULL,
+	"game_version" TEXT,
+	"saves_path" BLOB,
+	"mods_path" BLOB,
+	"script_output_path" BLOB,
+	"mod_update_schedule" TEXT,
+	"settings_includes" BLOB,
+	"settings_overrides" TEXT,
+	"autosave_policy" TEXT,
+	"autosave_keep_recent" INTEGER NOT NULL,
+	"autosave_keep_daily_days" INTEGER NOT NULL,
+	"settings_extra" TEXT,
+	"settings_last_mtime" TEXT,
+	"admin_list" TEXT,
+	"whitelist" TEXT,
 	FOREIGN KEY("game") REFERENCES "game"("id")
 );
 
@@ -70,14 +91,82 @@ CREATE TABLE IF NOT EXISTS "game_mod" (
 	"game" INTEGER NOT NULL,
 	"factorio_mod" TEXT NOT NULL,
 	"mod_version" TEXT NOT NULL,
-	"mod_zip" TEXT NOT NULL,
+	"mod_zip" BLOB NOT NULL,
 	"zip_last_mtime" TEXT NOT NULL,
+	"update_policy" TEXT NOT NULL,
+	"installed_as_dependency" INTEGER NOT NULL,
 	PRIMARY KEY("game","factorio_mod"),
 	FOREIGN KEY("factorio_mod") REFERENCES "factorio_mod"("name"),
 	FOREIGN KEY("factorio_mod", "mod_version") REFERENCES "mod_release"("factorio_mod", "version"),
 	FOREIGN KEY("game") REFERENCES "game"("id")
 );
 
+DROP TABLE IF EXISTS "game_mod_policy";
+CREATE TABLE IF NOT EXISTS "game_mod_policy" (
+	"game" INTEGER PRIMARY KEY,
+	"excluded_categories" TEXT NOT NULL,
+	"excluded_tags" TEXT NOT NULL,
+	FOREIGN KEY("game") REFERENCES "game"("id")
+);
+
+DROP TABLE IF EXISTS "portal_credentials";
+CREATE TABLE IF NOT EXISTS "portal_credentials" (
+	"game" INTEGER PRIMARY KEY,
+	"username" TEXT NOT NULL,
+	"token_nonce" BLOB NOT NULL,
+	"token" BLOB NOT NULL,
+	FOREIGN KEY("game") REFERENCES "game"("id")
+);
+
+DROP TABLE IF EXISTS "map_exchange_string";
+CREATE TABLE IF NOT EXISTS "map_exchange_string" (
+	"game" INTEGER PRIMARY KEY,
+	"exchange_string" TEXT NOT NULL,
+	FOREIGN KEY("game") REFERENCES "game"("id")
+);
+
+DROP TABLE IF EXISTS "last_known_good_mods";
+CREATE TABLE IF NOT EXISTS "last_known_good_mods" (
+	"game" INTEGER PRIMARY KEY,
+	/* the mod set as a JSON modpack manifest */
+	"modpack" TEXT NOT NULL,
+	"recorded_at" TEXT NOT NULL,
+	FOREIGN KEY("game") REFERENCES "game"("id")
+);
+
+DROP TABLE IF EXISTS "game_mod_preset";
+CREATE TABLE IF NOT EXISTS "game_mod_preset" (
+	"game" INTEGER NOT NULL,
+	"name" TEXT NOT NULL,
+	/* the mods' enabled states as a JSON object keyed by the mods' names */
+	"mods" TEXT NOT NULL,
+	"created_at" TEXT NOT NULL,
+	PRIMARY KEY("game","name"),
+	FOREIGN KEY("game") REFERENCES "game"("id")
+);
+
+/* never dropped when the schema is reapplied, so the player count history survives upgrades */
+CREATE TABLE IF NOT EXISTS "player_count_sample" (
+	"game" INTEGER NOT NULL,
+	"sampled_at" TEXT NOT NULL,
+	"players" INTEGER NOT NULL,
+	PRIMARY KEY("game","sampled_at"),
+	FOREIGN KEY("game") REFERENCES "game"("id")
+);
+
+/* never dropped when the schema is reapplied, so the event history survives upgrades */
+CREATE TABLE IF NOT EXISTS "server_event" (
+	/* aliased to the ROWID like the game's ID, so it increases as events are recorded and orders them */
+	"id" INTEGER PRIMARY KEY,
+	"game" INTEGER NOT NULL,
+	"kind" TEXT NOT NULL,
+	"occurred_at" TEXT NOT NULL,
+	/* the player the event is about, NULL for crashes */
+	"username" TEXT,
+	"message" TEXT NOT NULL,
+	FOREIGN KEY("game") REFERENCES "game"("id")
+);
+
 DROP TABLE IF EXISTS "release_dependency";
 CREATE TABLE IF NOT EXISTS "release_dependency" (
 	"release_mod_name" TEXT NOT NULL,
@@ -100,3 +189,30 @@ CREATE TABLE IF NOT EXISTS "mod_release" (
 	PRIMARY KEY("factorio_mod","version"),
 	FOREIGN KEY("factorio_mod") REFERENCES "factorio_mod"("name")
 );
+
+DROP TABLE IF EXISTS "idempotency_key";
+CREATE TABLE IF NOT EXISTS "idempotency_key" (
+	"key" TEXT PRIMARY KEY,
+	"method" TEXT NOT NULL,
+	"claimed_at" TEXT NOT NULL
+);
+
+/* never dropped when the schema is reapplied, so the tokens survive upgrades and authorization stays enforced */
+CREATE TABLE IF NOT EXISTS "rpc_token" (
+	"name" TEXT PRIMARY KEY,
+	/* only the token's BLAKE2b hash is stored, the token itself is shown once when it's created */
+	"token_hash" TEXT NOT NULL UNIQUE,
+	/* the scopes are combined into a single string similar to game_settings' tags */
+	"scopes" TEXT NOT NULL,
+	"created_at" TEXT NOT NULL
+);
+
+DROP TABLE IF EXISTS "portal_response";
+CREATE TABLE IF NOT EXISTS "portal_response" (
+	/* the request URL without the credentials */
+	"url" TEXT PRIMARY KEY,
+	"etag" TEXT,
+	"last_modified" TEXT,
+	"body" TEXT NOT NULL,
+	"fetched_at" TEXT NOT NULL
+);
@@ -0,0 +1,272 @@
+//! Provides token-based authorization for the RPC services. Tokens are stored in the program store as hashes with the
+//! scopes they authorize, and requests present them as bearer tokens in their `authorization` metadata.
+//!
+//! Authorization fails closed: a request without a valid token is denied even before the first token has been created
+//! with the `token add` subcommand. Running without tokens requires explicitly disabling authorization in the config.
+
+use crate::{
+    error::{AuthError, RpcError},
+    store::{models::RpcToken, Store},
+    util::checksum,
+    Config,
+};
+use chrono::Utc;
+use rand::RngCore;
+use std::fmt;
+use strum_macros::{Display, EnumString, EnumVariantNames};
+use tonic::metadata::MetadataMap;
+
+/// The metadata key the bearer token is read from.
+const AUTHORIZATION_KEY: &str = "authorization";
+/// The prefix of the bearer token in the authorization metadata value.
+const BEARER_PREFIX: &str = "Bearer ";
+/// The amount of random bytes in a generated token.
+const TOKEN_LENGTH: usize = 32;
+/// The string used to combine a token's scopes into a single string in the program store.
+const SCOPES_SPLITTER: &str = ",";
+
+/// A set of RPC methods a token may authorize.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, EnumString, Display, EnumVariantNames)]
+pub enum Scope {
+    /// Reading the instance's and its servers' status, settings and mods.
+    #[strum(serialize = "read")]
+    Read,
+    /// Installing and updating mods, and changing the mod policies, schedules and portal credentials.
+    #[strum(serialize = "manage-mods")]
+    ManageMods,
+    /// Changing and applying server settings, and refreshing servers from their files.
+    #[strum(serialize = "manage-settings")]
+    ManageSettings,
+    /// Starting, stopping, sending commands to and backing up servers.
+    #[strum(serialize = "control-server")]
+    ControlServer,
+    /// Everything, including importing servers, restoring backups, exporting and applying modpacks and changing
+    /// instance-wide options.
+    #[strum(serialize = "admin")]
+    Admin,
+}
+
+/// A stored token's information. The token itself isn't available after it's been created.
+#[derive(Debug)]
+pub struct TokenInfo {
+    /// The token's name.
+    name: String,
+    /// The scopes the token authorizes.
+    scopes: Vec<Scope>,
+    /// The timestamp when the token was created.
+    created_at: chrono::DateTime<Utc>,
+}
+
+impl TokenInfo {
+    /// Returns a new `TokenInfo` from a given stored token.
+    fn from_store_format(token: RpcToken) -> anyhow::Result<Self> {
+        Ok(Self {
+            scopes: parse_scopes(&token.scopes)?,
+            name: token.name,
+            created_at: token.created_at,
+        })
+    }
+
+    /// Returns whether the token authorizes a given scope. The admin scope authorizes every scope.
+    pub fn authorizes(&self, scope: Scope) -> bool {
+        self.scopes.iter().any(|s| *s == scope || *s == Scope::Admin)
+    }
+}
+
+impl fmt::Display for TokenInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scopes = self.scopes.iter().map(Scope::to_string).collect::<Vec<_>>();
+        write!(
+            f,
+            "{} ({}), created {}",
+            self.name,
+            scopes.join(", "),
+            self.created_at.to_rfc3339()
+        )
+    }
+}
+
+/// Creates a new token with a given name that authorizes given scopes. Returns the token, which can't be retrieved
+/// later.
+pub async fn create_token(store: &Store, name: String, scopes: &[Scope]) -> anyhow::Result<String> {
+    if store.get_rpc_tokens().await?.iter().any(|token| token.name == name) {
+        return Err(AuthError::TokenExists(name).into());
+    }
+
+    let mut bytes = [0; TOKEN_LENGTH];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(&bytes);
+
+    store
+        .set_rpc_token(RpcToken {
+            name,
+            token_hash: checksum::blake2b_string(&token),
+            scopes: scopes
+                .iter()
+                .map(Scope::to_string)
+                .collect::<Vec<_>>()
+                .join(SCOPES_SPLITTER),
+            created_at: Utc::now(),
+        })
+        .await?;
+    Ok(token)
+}
+
+/// Returns every stored token's information.
+pub async fn list_tokens(store: &Store) -> anyhow::Result<Vec<TokenInfo>> {
+    store
+        .get_rpc_tokens()
+        .await?
+        .into_iter()
+        .map(TokenInfo::from_store_format)
+        .collect()
+}
+
+/// Revokes the token with a given name. Returns whether a token was revoked.
+pub async fn revoke_token(store: &Store, name: String) -> anyhow::Result<bool> {
+    store.remove_rpc_token(name).await
+}
+
+/// Returns whether any tokens have been created.
+pub async fn has_tokens(store: &Store) -> anyhow::Result<bool> {
+    store.has_rpc_tokens().await
+}
+
+/// Authorizes a request with given metadata for a given scope. Every request is authorized only if authorization is
+/// disabled in a given config; otherwise a request without a valid token is denied, whether or not any tokens have been
+/// created. Returns the name of the token the request was authorized with, or `None` if authorization is disabled.
+pub async fn authorize(
+    config: &Config,
+    store: &Store,
+    metadata: &MetadataMap,
+    scope: Scope,
+) -> anyhow::Result<Option<String>> {
+    if config.auth_disabled() {
+        return Ok(None);
+    }
+
+    let token = metadata
+        .get(AUTHORIZATION_KEY)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix(BEARER_PREFIX))
+        .ok_or(RpcError::Unauthorized(AuthError::MissingToken))?;
+    let info = match store.get_rpc_token_by_hash(checksum::blake2b_string(token)).await? {
+        Some(token) => TokenInfo::from_store_format(token)?,
+        None => return Err(RpcError::Unauthorized(AuthError::InvalidToken).into()),
+    };
+
+    if info.authorizes(scope) {
+        Ok(Some(info.name))
+    } else {
+        Err(RpcError::Unauthorized(AuthError::MissingScope { name: info.name, scope }).into())
+    }
+}
+
+/// Parses scopes combined into a single string.
+fn parse_scopes(scopes: &str) -> Result<Vec<Scope>, AuthError> {
+    scopes
+        .split(SCOPES_SPLITTER)
+        .filter(|scope| !scope.is_empty())
+        .map(|scope| scope.parse().map_err(|_| AuthError::NoSuchScope(scope.to_owned())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config,
+        store::{self, StoreLocation},
+    };
+    use std::io::Cursor;
+
+    /// Returns an error's RPC status code, or panics if the error isn't an `RpcError`.
+    fn status_code(error: anyhow::Error) -> tonic::Code {
+        tonic::Status::from(error.downcast_ref::<RpcError>().expect("error isn't an RpcError")).code()
+    }
+
+    #[tokio::test]
+    async fn authorize_scopes() {
+        let store = store::Builder::<String>::from_location(StoreLocation::Memory)
+            .build()
+            .await
+            .expect("failed to build test store");
+        let metadata = |token: &str| {
+            let mut metadata = MetadataMap::new();
+            metadata.insert(
+                AUTHORIZATION_KEY,
+                format!("{}{}", BEARER_PREFIX, token)
+                    .parse()
+                    .expect("failed to parse metadata value"),
+            );
+            metadata
+        };
+
+        let config = Config::default();
+
+        // requests are denied even before the first token
+        let missing = authorize(&config, &store, &MetadataMap::new(), Scope::Read)
+            .await
+            .expect_err("request without a token was authorized before the first token");
+        assert_eq!(status_code(missing), tonic::Code::Unauthenticated);
+
+        let token = create_token(&store, String::from("monitoring"), &[Scope::Read])
+            .await
+            .expect("failed to create token");
+        assert!(create_token(&store, String::from("monitoring"), &[Scope::Read])
+            .await
+            .is_err());
+
+        let caller = authorize(&config, &store, &metadata(&token), Scope::Read)
+            .await
+            .expect("read request was denied");
+        assert_eq!(caller.as_deref(), Some("monitoring"));
+        let denied = authorize(&config, &store, &metadata(&token), Scope::ControlServer)
+            .await
+            .expect_err("control request was authorized");
+        assert_eq!(status_code(denied), tonic::Code::PermissionDenied);
+
+        let missing = authorize(&config, &store, &MetadataMap::new(), Scope::Read)
+            .await
+            .expect_err("request without a token was authorized");
+        assert_eq!(status_code(missing), tonic::Code::Unauthenticated);
+        let invalid = authorize(&config, &store, &metadata("nope"), Scope::Read)
+            .await
+            .expect_err("request with an invalid token was authorized");
+        assert_eq!(status_code(invalid), tonic::Code::Unauthenticated);
+
+        let admin = create_token(&store, String::from("operator"), &[Scope::Admin])
+            .await
+            .expect("failed to create token");
+        authorize(&config, &store, &metadata(&admin), Scope::ControlServer)
+            .await
+            .expect("admin request was denied");
+
+        assert!(revoke_token(&store, String::from("monitoring"))
+            .await
+            .expect("failed to revoke token"));
+        assert!(authorize(&config, &store, &metadata(&token), Scope::Read)
+            .await
+            .is_err());
+        assert_eq!(list_tokens(&store).await.expect("failed to list tokens").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn authorize_disabled() {
+        let store = store::Builder::<String>::from_location(StoreLocation::Memory)
+            .build()
+            .await
+            .expect("failed to build test store");
+        let config = config::Builder::new()
+            .apply_config_file(&mut Cursor::new(
+                b"[network]\nlisten = []\n[auth]\ndisabled = true\n".to_vec(),
+            ))
+            .expect("failed to apply config file")
+            .build();
+
+        let caller = authorize(&config, &store, &MetadataMap::new(), Scope::Admin)
+            .await
+            .expect("request was denied with authorization disabled");
+        assert!(caller.is_none());
+    }
+}
@@ -0,0 +1,505 @@
+//! Provides functionality to back up a managed server's saves, mod list, settings and player lists into a single
+//! `tar.zst` archive, and to restore them from one.
+//!
+//! Each server's backups are kept in their own directory under the configured backup directory, named after the time
+//! they were created, with a numeric suffix such as `-2` if several are created within the same second. Mod zip
+//! archives aren't backed up, since they can be downloaded again from the mod list.
+
+use crate::{
+    error::{BackupError, RpcError},
+    factorio::{Factorio, GameStoreId},
+    util::{
+        async_status::{self, AsyncProgressChannel, AsyncProgressChannelExt},
+        TimeZone,
+    },
+};
+use chrono::{DateTime, NaiveDateTime, SubsecRound, TimeZone as _, Utc};
+use log::*;
+use std::{
+    fs::File,
+    io,
+    path::{Component, Path, PathBuf},
+};
+use tokio::{fs, task};
+
+/// The extension of backup archives.
+const BACKUP_EXTENSION: &str = ".tar.zst";
+/// The extension of backup archives while they're being written.
+const PARTIAL_EXTENSION: &str = ".partial";
+/// The prefix of backup archives while they're being written, which hides them from directory listings.
+const PARTIAL_PREFIX: &str = ".";
+/// The format of backup names, which are their creation timestamps.
+const NAME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// The separator between a backup name's timestamp and the suffix that tells apart backups created in the same second.
+const SUFFIX_SEPARATOR: char = '-';
+/// The zstd compression level of backup archives.
+const COMPRESSION_LEVEL: i32 = 3;
+/// The directory in a backup archive the saves are stored in.
+const SAVES_DIRECTORY: &str = "saves";
+/// The directory in a backup archive the mod list and mod settings are stored in.
+const MODS_DIRECTORY: &str = "mods";
+/// The files in a server's mods directory that are backed up.
+const MOD_FILES: &[&str] = &["mod-list.json", "mod-settings.dat"];
+/// The files in a server's root directory that are backed up.
+const ROOT_FILES: &[&str] = &[
+    "server-settings.json",
+    "map-gen-settings.json",
+    "map-settings.json",
+    "server-adminlist.json",
+    "server-banlist.json",
+    "server-whitelist.json",
+];
+
+/// The directories of a server the backed up files are in.
+#[derive(Debug, Clone)]
+pub struct Sources {
+    /// The server's root directory.
+    root: PathBuf,
+    /// The server's saves directory.
+    saves: PathBuf,
+    /// The server's mods directory.
+    mods: PathBuf,
+}
+
+/// A single backup of a server.
+#[derive(Debug, PartialEq)]
+pub struct Backup {
+    /// The backup's name.
+    name: String,
+    /// Timestamp when the backup was created.
+    created_at: DateTime<Utc>,
+    /// The backup archive's size in bytes.
+    size: u64,
+}
+
+impl Sources {
+    /// Returns the backup sources of a given server.
+    pub fn of(game: &Factorio) -> Self {
+        let root = game.root().to_path_buf();
+        let paths = &game.settings().paths;
+        Self {
+            saves: paths.saves(&root),
+            mods: paths.mods(&root),
+            root,
+        }
+    }
+
+    /// Returns every existing file that's backed up, paired with its path in the backup archive.
+    async fn files(&self) -> anyhow::Result<Vec<(PathBuf, PathBuf)>> {
+        let mut files = Vec::new();
+
+        if self.saves.is_dir() {
+            let mut entries = fs::read_dir(&self.saves).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_file() {
+                    files.push((entry.path(), Path::new(SAVES_DIRECTORY).join(entry.file_name())));
+                }
+            }
+        }
+
+        for file in MOD_FILES {
+            files.push((self.mods.join(file), Path::new(MODS_DIRECTORY).join(file)));
+        }
+        for file in ROOT_FILES {
+            files.push((self.root.join(file), PathBuf::from(file)));
+        }
+
+        files.retain(|(path, _)| path.is_file());
+        files.sort_by(|(_, a), (_, b)| a.cmp(b));
+        Ok(files)
+    }
+
+    /// Returns the path a given path in a backup archive is restored to, or an error if the path isn't one that's
+    /// backed up.
+    fn destination(&self, archive_path: &Path) -> Result<PathBuf, BackupError> {
+        let components = archive_path
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => Ok(name),
+                _ => Err(BackupError::UnexpectedEntry(archive_path.to_path_buf())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match components.as_slice() {
+            [directory, name] if *directory == SAVES_DIRECTORY => Ok(self.saves.join(name)),
+            [directory, name] if *directory == MODS_DIRECTORY && MOD_FILES.iter().any(|file| name == file) => {
+                Ok(self.mods.join(name))
+            }
+            [name] if ROOT_FILES.iter().any(|file| name == file) => Ok(self.root.join(name)),
+            _ => Err(BackupError::UnexpectedEntry(archive_path.to_path_buf())),
+        }
+    }
+}
+
+impl Backup {
+    /// Returns the backup's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the backup in RPC format, with its timestamp also localised to a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::Backup {
+        rpc::Backup {
+            name: self.name.clone(),
+            created_at: self.created_at.timestamp(),
+            created_at_local: time_zone.format(self.created_at),
+            size: self.size,
+        }
+    }
+}
+
+/// Returns the directory a given server's backups are kept in, under a given backup directory.
+fn server_directory(directory: &Path, server_id: GameStoreId) -> PathBuf {
+    directory.join(server_id.to_string())
+}
+
+/// Creates a new backup of a given server's files in a given backup directory. The archive is written into a uniquely
+/// named temporary file that's removed if the backup fails, and given its final name only once it's complete. Reports
+/// progress to a given progress channel. Returns the created backup.
+pub async fn create(
+    directory: &Path,
+    server_id: GameStoreId,
+    sources: &Sources,
+    prog_tx: Option<AsyncProgressChannel>,
+) -> anyhow::Result<Backup> {
+    let files = sources.files().await?;
+    let server_directory = server_directory(directory, server_id);
+    fs::create_dir_all(&server_directory).await?;
+
+    // the name only has second precision, so neither does the timestamp to match what's listed later
+    let created_at = Utc::now().trunc_subsecs(0);
+    info!(
+        "Backing up {} files of game ID {} to {}",
+        files.len(),
+        server_id,
+        server_directory.display()
+    );
+
+    let partial = tempfile::Builder::new()
+        .prefix(PARTIAL_PREFIX)
+        .suffix(PARTIAL_EXTENSION)
+        .tempfile_in(&server_directory)?;
+    let mut archive = tar::Builder::new(zstd::Encoder::new(partial, COMPRESSION_LEVEL)?);
+    let total = files.len() as u32;
+
+    for (index, (source, archive_path)) in files.into_iter().enumerate() {
+        prog_tx
+            .send_status(async_status::definite(
+                &format!("Backing up {}...", archive_path.display()),
+                index as u32,
+                total,
+            ))
+            .await?;
+
+        // the archive is moved into the blocking task and back out so progress can be reported between the files
+        archive = task::spawn_blocking(move || -> anyhow::Result<_> {
+            archive.append_path_with_name(&source, &archive_path)?;
+            Ok(archive)
+        })
+        .await??;
+    }
+
+    let partial = task::spawn_blocking(move || -> anyhow::Result<_> { Ok(archive.into_inner()?.finish()?) }).await??;
+    let (name, path) = persist(partial, &server_directory, created_at)?;
+
+    let size = fs::metadata(&path).await?.len();
+    prog_tx
+        .send_status(async_status::definite("Backup created", total, total))
+        .await?;
+    info!("Game ID {} backed up to {} ({} bytes)", server_id, path.display(), size);
+
+    Ok(Backup { name, created_at, size })
+}
+
+/// Gives a given complete temporary backup archive in a given server backup directory the name of a given creation
+/// timestamp, suffixed with the first free number if a backup with the name already exists. Returns the backup's name
+/// and path.
+fn persist(
+    mut partial: tempfile::NamedTempFile,
+    server_directory: &Path,
+    created_at: DateTime<Utc>,
+) -> anyhow::Result<(String, PathBuf)> {
+    let timestamp = created_at.format(NAME_FORMAT).to_string();
+    let mut name = timestamp.clone();
+
+    for number in 2.. {
+        let path = server_directory.join(format!("{}{}", name, BACKUP_EXTENSION));
+        // an existing backup is never replaced, even if another one is given the same name concurrently
+        match partial.persist_noclobber(&path) {
+            Ok(_) => return Ok((name, path)),
+            Err(e) if e.error.kind() == io::ErrorKind::AlreadyExists => {
+                partial = e.file;
+                name = format!("{}{}{}", timestamp, SUFFIX_SEPARATOR, number);
+            }
+            Err(e) => return Err(e.error.into()),
+        }
+    }
+
+    unreachable!("ran out of backup name suffixes")
+}
+
+/// Returns the creation timestamp in a given backup name, or `None` if the name isn't a backup name.
+fn parse_name(name: &str) -> Option<DateTime<Utc>> {
+    let timestamp = match name.split_once(SUFFIX_SEPARATOR) {
+        Some((timestamp, number)) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => timestamp,
+        Some(_) => return None,
+        None => name,
+    };
+
+    NaiveDateTime::parse_from_str(timestamp, NAME_FORMAT)
+        .ok()
+        .map(|created_at| Utc.from_utc_datetime(&created_at))
+}
+
+/// Returns a given server's backups in a given backup directory, the newest first.
+pub async fn list(directory: &Path, server_id: GameStoreId) -> anyhow::Result<Vec<Backup>> {
+    let server_directory = server_directory(directory, server_id);
+    if !server_directory.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    let mut entries = fs::read_dir(&server_directory).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name();
+        let name = match file_name.to_str().and_then(|name| name.strip_suffix(BACKUP_EXTENSION)) {
+            Some(name) => name,
+            None => continue,
+        };
+        let created_at = match parse_name(name) {
+            Some(created_at) => created_at,
+            None => {
+                debug!("Ignoring unknown file {} in backups", entry.path().display());
+                continue;
+            }
+        };
+
+        backups.push(Backup {
+            name: name.to_owned(),
+            created_at,
+            size: entry.metadata().await?.len(),
+        });
+    }
+
+    // backups created in the same second are ordered by their suffixes, which are longer the later they were created
+    backups.sort_by(|a, b| (b.created_at, b.name.len(), &b.name).cmp(&(a.created_at, a.name.len(), &a.name)));
+    Ok(backups)
+}
+
+/// Restores a given server's backup with a given name from a given backup directory, overwriting the backed up files.
+/// Files that aren't in the backup are left untouched. The server mustn't be running. Reports progress to a given
+/// progress channel.
+pub async fn restore(
+    directory: &Path,
+    server_id: GameStoreId,
+    name: &str,
+    sources: &Sources,
+    prog_tx: Option<AsyncProgressChannel>,
+) -> anyhow::Result<()> {
+    let path = archive_path(directory, server_id, name)?;
+
+    info!("Restoring game ID {} from backup {}", server_id, path.display());
+    prog_tx
+        .send_status(async_status::indefinite(&format!("Restoring backup {}...", name)))
+        .await?;
+
+    let sources = sources.clone();
+    let restored = task::spawn_blocking(move || -> anyhow::Result<usize> {
+        let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(&path)?)?);
+        let mut restored = 0;
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let destination = sources.destination(&entry.path()?)?;
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            debug!("Restoring {}", destination.display());
+            entry.unpack(&destination)?;
+            restored += 1;
+        }
+
+        Ok(restored)
+    })
+    .await??;
+
+    info!(
+        "Restored {} files of game ID {} from backup {}",
+        restored, server_id, name
+    );
+    Ok(())
+}
+
+/// Returns the existing files of a given server that restoring its backup with a given name from a given backup
+/// directory would overwrite.
+pub async fn overwritten_files(
+    directory: &Path,
+    server_id: GameStoreId,
+    name: &str,
+    sources: &Sources,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let path = archive_path(directory, server_id, name)?;
+    let sources = sources.clone();
+
+    task::spawn_blocking(move || -> anyhow::Result<Vec<PathBuf>> {
+        let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(&path)?)?);
+        let mut overwritten = Vec::new();
+
+        for entry in archive.entries()? {
+            let destination = sources.destination(&entry?.path()?)?;
+            if destination.is_file() {
+                overwritten.push(destination);
+            }
+        }
+
+        Ok(overwritten)
+    })
+    .await?
+}
+
+/// Returns the path of a given server's backup archive with a given name in a given backup directory. Returns
+/// [`RpcError::NoSuchBackup`][NoSuchBackup] if the name isn't a backup name or the backup doesn't exist.
+///
+/// [NoSuchBackup]: crate::error::RpcError::NoSuchBackup
+fn archive_path(directory: &Path, server_id: GameStoreId, name: &str) -> Result<PathBuf, RpcError> {
+    if parse_name(name).is_none() {
+        return Err(RpcError::NoSuchBackup(name.to_owned()));
+    }
+
+    let path = server_directory(directory, server_id).join(format!("{}{}", name, BACKUP_EXTENSION));
+    if !path.is_file() {
+        return Err(RpcError::NoSuchBackup(name.to_owned()));
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn create_list_restore() {
+        let server = tempfile::tempdir().expect("failed to create temporary directory");
+        let backups = tempfile::tempdir().expect("failed to create temporary directory");
+        let sources = Sources {
+            root: server.path().to_path_buf(),
+            saves: server.path().join("saves"),
+            mods: server.path().join("mods"),
+        };
+
+        fs::create_dir_all(&sources.saves).unwrap();
+        fs::create_dir_all(&sources.mods).unwrap();
+        fs::write(sources.saves.join("world.zip"), "original world").unwrap();
+        fs::write(sources.mods.join("mod-list.json"), "{\"mods\":[]}").unwrap();
+        fs::write(sources.mods.join("big-mod_1.0.0.zip"), "not backed up").unwrap();
+        fs::write(sources.root.join("server-settings.json"), "{}").unwrap();
+        fs::write(sources.root.join("server-adminlist.json"), "[\"admin\"]").unwrap();
+
+        let backup = create(backups.path(), 1, &sources, None)
+            .await
+            .expect("failed to create backup");
+        assert_eq!(
+            list(backups.path(), 1).await.expect("failed to list backups"),
+            vec![backup]
+        );
+        assert!(list(backups.path(), 2)
+            .await
+            .expect("failed to list backups")
+            .is_empty());
+
+        fs::write(sources.saves.join("world.zip"), "corrupted world").unwrap();
+        fs::remove_file(sources.root.join("server-adminlist.json")).unwrap();
+
+        let name = list(backups.path(), 1).await.expect("failed to list backups")[0]
+            .name()
+            .to_owned();
+        assert_eq!(
+            overwritten_files(backups.path(), 1, &name, &sources)
+                .await
+                .expect("failed to list overwritten files"),
+            vec![
+                sources.mods.join("mod-list.json"),
+                sources.saves.join("world.zip"),
+                sources.root.join("server-settings.json")
+            ]
+        );
+        restore(backups.path(), 1, &name, &sources, None)
+            .await
+            .expect("failed to restore backup");
+
+        assert_eq!(
+            fs::read_to_string(sources.saves.join("world.zip")).unwrap(),
+            "original world"
+        );
+        assert_eq!(
+            fs::read_to_string(sources.root.join("server-adminlist.json")).unwrap(),
+            "[\"admin\"]"
+        );
+        assert!(restore(backups.path(), 1, "../1/whatever", &sources, None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn same_second_backups() {
+        let server = tempfile::tempdir().expect("failed to create temporary directory");
+        let backups = tempfile::tempdir().expect("failed to create temporary directory");
+        let sources = Sources {
+            root: server.path().to_path_buf(),
+            saves: server.path().join("saves"),
+            mods: server.path().join("mods"),
+        };
+        let directory = server_directory(backups.path(), 1);
+        fs::create_dir_all(&directory).unwrap();
+        let created_at = Utc.ymd(2020, 9, 1).and_hms(12, 0, 0);
+
+        let mut names = Vec::new();
+        for _ in 0..3 {
+            let partial = tempfile::NamedTempFile::new_in(&directory).unwrap();
+            names.push(persist(partial, &directory, created_at).unwrap().0);
+        }
+        assert_eq!(
+            names,
+            vec!["20200901T120000Z", "20200901T120000Z-2", "20200901T120000Z-3"]
+        );
+        assert_eq!(parse_name("20200901T120000Z-3"), Some(created_at));
+        assert_eq!(parse_name("20200901T120000Z-"), None);
+        assert_eq!(parse_name("20200901T120000Z-../x"), None);
+
+        let listed = list(backups.path(), 1).await.expect("failed to list backups");
+        assert_eq!(
+            listed.iter().map(Backup::name).collect::<Vec<_>>(),
+            vec!["20200901T120000Z-3", "20200901T120000Z-2", "20200901T120000Z"]
+        );
+
+        // a failed backup leaves no temporary file behind
+        fs::write(sources.root.join("server-settings.json"), "{}").unwrap();
+        fs::create_dir_all(&sources.saves).unwrap();
+        let (prog_tx, prog_rx) = tokio::sync::mpsc::channel(1);
+        drop(prog_rx);
+        let prog_tx = AsyncProgressChannel::new(prog_tx, std::time::Duration::from_secs(0));
+        assert!(create(backups.path(), 1, &sources, Some(prog_tx)).await.is_err());
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn unexpected_entries_rejected() {
+        let sources = Sources {
+            root: PathBuf::from("/srv/factorio"),
+            saves: PathBuf::from("/srv/factorio/saves"),
+            mods: PathBuf::from("/srv/factorio/mods"),
+        };
+
+        assert_eq!(
+            sources.destination(Path::new("saves/world.zip")).unwrap(),
+            PathBuf::from("/srv/factorio/saves/world.zip")
+        );
+        assert!(sources.destination(Path::new("../etc/passwd")).is_err());
+        assert!(sources.destination(Path::new("/etc/passwd")).is_err());
+        assert!(sources.destination(Path::new("mods/some-mod_1.0.0.zip")).is_err());
+        assert!(sources.destination(Path::new("bin/x64/factorio")).is_err());
+    }
+}
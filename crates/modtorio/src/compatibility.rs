@@ -0,0 +1,81 @@
+//! Provides the RPC compatibility matrix, which maps each Modtorio release to the RPC protocol versions it supports
+//! and the minimum client version required to talk to it. The matrix is read from `compatibility.toml` in the crate
+//! root at build time.
+
+use crate::util::HumanVersion;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+/// The compatibility matrix's source, embedded at build time.
+const MATRIX_SOURCE: &str = include_str!("../compatibility.toml");
+
+lazy_static! {
+    static ref MATRIX: Matrix = toml::from_str(MATRIX_SOURCE).expect("failed to parse embedded compatibility matrix");
+}
+
+/// The compatibility matrix.
+#[derive(Debug, Deserialize)]
+struct Matrix {
+    /// Every release's compatibility information.
+    release: Vec<Release>,
+}
+
+/// A single release's compatibility information.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Release {
+    /// The release's version.
+    version: HumanVersion,
+    /// The RPC protocol versions the release supports.
+    protocol_versions: Vec<HumanVersion>,
+    /// The minimum client version required to talk to the release.
+    minimum_client_version: HumanVersion,
+}
+
+impl Release {
+    /// Returns the release's version.
+    pub fn version(&self) -> HumanVersion {
+        self.version
+    }
+
+    /// Returns the RPC protocol versions the release supports.
+    pub fn protocol_versions(&self) -> &[HumanVersion] {
+        &self.protocol_versions
+    }
+
+    /// Returns the minimum client version required to talk to the release.
+    pub fn minimum_client_version(&self) -> HumanVersion {
+        self.minimum_client_version
+    }
+}
+
+impl From<Release> for rpc::CompatibilityEntry {
+    fn from(release: Release) -> Self {
+        Self {
+            daemon_version: Some(release.version.into()),
+            protocol_versions: release.protocol_versions.into_iter().map(Into::into).collect(),
+            minimum_client_version: Some(release.minimum_client_version.into()),
+        }
+    }
+}
+
+/// Returns every release's compatibility information.
+pub fn releases() -> &'static [Release] {
+    &MATRIX.release
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_release_listed() {
+        let current: HumanVersion = crate::VERSION.parse().expect("failed to parse program version");
+        let protocol: HumanVersion = rpc::VERSION.parse().expect("failed to parse protocol version");
+
+        let release = releases()
+            .iter()
+            .find(|release| release.version() == current)
+            .expect("current release missing from compatibility matrix");
+        assert!(release.protocol_versions().contains(&protocol));
+    }
+}
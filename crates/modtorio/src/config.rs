@@ -5,15 +5,21 @@ mod file_config;
 mod opts_config;
 mod store_config;
 
-use crate::{opts::Opts, store::Store, util};
+use crate::{
+    error::ConfigError, factorio::executable::CustomEventParser, hooks::HookPoint, opts::Opts, store::Store, util,
+};
 use common::net::NetAddress;
 use env_config::EnvConfig;
 use file_config::FileConfig;
 use opts_config::OptsConfig;
 use serde::Deserialize;
-use std::io::{Read, Write};
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use store_config::StoreConfig;
-use util::{Limit, LogLevel};
+use util::{channel::OverflowStrategy, BanAction, Limit, LogFormat, LogLevel, StuckSaveAction, TimeZone};
 
 /// The default configuration file location, relative to the working directory.
 pub const DEFAULT_CONFIG_FILE_LOCATION: &str = "modtorio.toml";
@@ -21,6 +27,60 @@ pub const DEFAULT_CONFIG_FILE_LOCATION: &str = "modtorio.toml";
 pub const DEFAULT_STORE_FILE_LOCATION: &str = "modtorio.db";
 /// The default store expiry time in seconds.
 pub const DEFAULT_STORE_EXPIRY: u64 = 3600;
+/// The default time in seconds a mutating RPC request's idempotency key is remembered.
+pub const DEFAULT_IDEMPOTENCY_TTL: u64 = 86400;
+/// The default minimum interval in milliseconds between successive definite progress updates.
+pub const DEFAULT_PROGRESS_INTERVAL: u64 = 250;
+/// The default maximum amount of items sent in a single streamed RPC response message.
+pub const DEFAULT_MAX_BATCH_SIZE: u64 = 100;
+/// The default time in seconds a destructive RPC request's confirmation token is valid for.
+pub const DEFAULT_CONFIRMATION_TTL: u64 = 60;
+/// The default maximum amount of mods downloaded concurrently when updating mods or installing missing dependencies.
+pub const DEFAULT_DOWNLOAD_CONCURRENCY: u64 = 4;
+/// The default amount of requests per second made to the mod portal on average.
+pub const DEFAULT_PORTAL_RATE_LIMIT: u64 = 2;
+/// The default amount of requests made to the mod portal in a quick burst before the rate limit applies.
+pub const DEFAULT_PORTAL_BURST: u64 = 10;
+/// The default amount of refused connections from a single address within the ban window that make it an offender.
+pub const DEFAULT_BAN_THRESHOLD: u32 = 5;
+/// The default ban window in seconds.
+pub const DEFAULT_BAN_WINDOW: u64 = 60;
+/// The default time in seconds a hook script may run before it's killed.
+pub const DEFAULT_HOOK_TIMEOUT: u64 = 30;
+/// The default amount of lines or events buffered in each channel between a server executable and the program.
+pub const DEFAULT_CHANNEL_CAPACITY: u64 = 64;
+/// The default amount of times starting an autostarted server is attempted.
+pub const DEFAULT_AUTOSTART_ATTEMPTS: u32 = 5;
+/// The default delay in seconds before retrying a failed autostart for the first time.
+pub const DEFAULT_AUTOSTART_INITIAL_BACKOFF: u64 = 5;
+/// The default maximum delay in seconds between autostart attempts.
+pub const DEFAULT_AUTOSTART_MAX_BACKOFF: u64 = 300;
+/// The default time in seconds long-running tasks are given to finish when the program shuts down.
+pub const DEFAULT_SHUTDOWN_GRACE_PERIOD: u64 = 30;
+/// The default time in seconds a server may save its map before it's considered stuck.
+pub const DEFAULT_SAVING_TIMEOUT: u64 = 600;
+/// The default file mode of the Unix sockets the RPC services listen on, as an octal string.
+pub const DEFAULT_SOCKET_MODE: &str = "0660";
+/// The default size in bytes a log file may grow to before it's rotated.
+pub const DEFAULT_LOG_MAX_SIZE: u64 = 10 * 1024 * 1024;
+/// The default time in seconds after which a log file is rotated.
+pub const DEFAULT_LOG_ROTATE_INTERVAL: u64 = 86400;
+/// The default amount of rotated log files kept.
+pub const DEFAULT_LOG_RETENTION: u32 = 7;
+/// The default directory servers' backups are kept in.
+pub const DEFAULT_BACKUP_DIRECTORY: &str = "backups";
+/// The default directory the shared pool of installed Factorio versions is kept in.
+pub const DEFAULT_VERSIONS_DIRECTORY: &str = "versions";
+/// The default directory the shared cache of mods downloaded outside any server is kept in.
+pub const DEFAULT_MOD_CACHE_DIRECTORY: &str = "mod-cache";
+/// The default directory modpack manifests are exported into and applied from.
+pub const DEFAULT_MODPACK_DIRECTORY: &str = "modpacks";
+/// The default interval in seconds between samples of the running servers' player counts.
+pub const DEFAULT_PLAYER_COUNT_INTERVAL: u64 = 300;
+/// The default amount of days the player count samples are kept for.
+pub const DEFAULT_PLAYER_COUNT_RETENTION: u64 = 90;
+/// The default amount of days the servers' recorded events are kept for.
+pub const DEFAULT_EVENT_RETENTION: u64 = 90;
 
 // when running tests with cargo, they all share the same set of environment variables (cargo's)
 // and cargo runs them all in parallel. this means the tests *will* interfere with each other's
@@ -48,8 +108,38 @@ where
 /// sources.
 #[derive(Debug, Deserialize, Default)]
 pub struct Config {
+    /// The time zone timestamps are displayed in.
+    time_zone: TimeZone,
+    /// How long in seconds long-running tasks are given to finish when the program shuts down.
+    shutdown_grace_period: u64,
+    /// Whether stored games are imported only when they're first accessed instead of when the program starts.
+    lazy_load: bool,
+    /// Whether the program runs as a mod portal proxy that doesn't manage any servers.
+    portal_proxy: bool,
     /// The log level to use.
     log_level: LogLevel,
+    /// The log level server output lines that aren't recognised as game events are logged at.
+    unparsed_line_level: LogLevel,
+    /// The file the program's log is also written to. Empty disables the file output.
+    log_file: PathBuf,
+    /// The size in bytes a log file may grow to before it's rotated. 0 disables size-based rotation.
+    log_max_size: u64,
+    /// The time in seconds after which a log file is rotated. 0 disables time-based rotation.
+    log_rotate_interval: u64,
+    /// The amount of rotated log files kept.
+    log_retention: u32,
+    /// Whether rotated log files are compressed.
+    log_compress: bool,
+    /// The format the log lines are written in.
+    log_format: LogFormat,
+    /// The directory servers' backups are kept in.
+    backup_directory: PathBuf,
+    /// The directory the shared pool of installed Factorio versions is kept in.
+    versions_directory: PathBuf,
+    /// The directory the shared cache of mods downloaded outside any server is kept in.
+    mod_cache_directory: PathBuf,
+    /// The directory modpack manifests are exported into and applied from.
+    modpack_directory: PathBuf,
     /// The page size to use when requesting batched mods from the mod portal. `Limit::Unlimited` corresponds to
     /// `"max"`.
     portal_page_size: Limit,
@@ -57,10 +147,92 @@ pub struct Config {
     portal_username: String,
     /// The mod portal token.
     portal_token: String,
+    /// The hex key per-server mod portal credentials are encrypted with in the program store.
+    credential_key: String,
     /// The program store expiry in seconds.
     store_expiry: u64,
+    /// Whether the program runs in offline mode, where every network call is disabled. Only set from the command
+    /// line.
+    offline: bool,
+    /// How long in seconds a mutating RPC request's idempotency key is remembered.
+    idempotency_ttl: u64,
     /// The server listen addresses
     listen: Vec<NetAddress>,
+    /// The read-only status service listen addresses
+    status_listen: Vec<NetAddress>,
+    /// The Prometheus metrics endpoint listen addresses
+    metrics_listen: Vec<NetAddress>,
+    /// The mod release notification webhook listen addresses
+    webhook_listen: Vec<NetAddress>,
+    /// The shared secret mod release notifications must present as their bearer token.
+    webhook_secret: String,
+    /// The file mode of the Unix sockets the RPC services listen on, as an octal string.
+    socket_mode: String,
+    /// The user that owns the Unix sockets the RPC services listen on. Empty leaves the owner unchanged.
+    socket_owner: String,
+    /// The group of the Unix sockets the RPC services listen on. Empty leaves the group unchanged.
+    socket_group: String,
+    /// Whether RPC requests are authorized without a token.
+    auth_disabled: bool,
+    /// The minimum interval in milliseconds between successive definite progress updates.
+    progress_interval: u64,
+    /// The maximum amount of items sent in a single streamed RPC response message.
+    max_batch_size: u64,
+    /// How long in seconds a destructive RPC request's confirmation token is valid for.
+    confirmation_ttl: u64,
+    /// The maximum amount of mods downloaded concurrently when updating mods or installing missing dependencies.
+    download_concurrency: u64,
+    /// How many requests per second are made to the mod portal on average. 0 disables the limit.
+    portal_rate_limit: u64,
+    /// How many requests are made to the mod portal in a quick burst before the rate limit applies.
+    portal_burst: u64,
+    /// How many refused connections from a single address within the ban window make the address an offender.
+    ban_threshold: u32,
+    /// The window in seconds refused connections are counted in.
+    ban_window: u64,
+    /// The action taken when an address becomes an offender.
+    ban_action: BanAction,
+    /// The URL offenders are POSTed to with the webhook ban action.
+    ban_webhook: String,
+    /// The command run with the command ban action.
+    ban_command: String,
+    /// The script run before a server is started. Empty runs nothing.
+    hook_pre_start: PathBuf,
+    /// The script run once a server is running. Empty runs nothing.
+    hook_post_start: PathBuf,
+    /// The script run before a server is stopped. Empty runs nothing.
+    hook_pre_stop: PathBuf,
+    /// The script run after a server has shut down cleanly. Empty runs nothing.
+    hook_post_stop: PathBuf,
+    /// The script run after a server has crashed. Empty runs nothing.
+    hook_post_crash: PathBuf,
+    /// How long in seconds a hook script may run before it's killed. 0 lets it run indefinitely.
+    hook_timeout: u64,
+    /// The amount of lines or events buffered in each channel between a server executable and the program.
+    channel_capacity: u64,
+    /// What's done when a server executable's output channel is full.
+    output_overflow: OverflowStrategy,
+    /// The non-zero exit codes a server executable exiting with is considered a clean shutdown instead of a crash.
+    clean_exit_codes: Vec<i32>,
+    /// The compiled user-defined parsers that turn output lines logged by mods into game events.
+    #[serde(skip)]
+    event_parsers: Vec<CustomEventParser>,
+    /// How many times starting an autostarted server is attempted.
+    autostart_attempts: u32,
+    /// The delay in seconds before retrying a failed autostart for the first time.
+    autostart_initial_backoff: u64,
+    /// The maximum delay in seconds between autostart attempts.
+    autostart_max_backoff: u64,
+    /// How long in seconds a server may save its map before it's considered stuck. 0 disables the watchdog.
+    saving_timeout: u64,
+    /// The action taken when a server is stuck saving its map.
+    stuck_save_action: StuckSaveAction,
+    /// The interval in seconds between samples of the running servers' player counts. 0 disables sampling.
+    player_count_interval: u64,
+    /// The amount of days the player count samples are kept for. 0 keeps them forever.
+    player_count_retention: u64,
+    /// The amount of days the servers' recorded events are kept for. 0 keeps them forever.
+    event_retention: u64,
 }
 
 /// Builds new [`Config`](Config) instances.
@@ -141,11 +313,99 @@ impl Config {
         FileConfig::write_default_to_writer(writer)
     }
 
+    /// Returns the time zone timestamps are displayed in.
+    pub fn time_zone(&self) -> TimeZone {
+        self.time_zone
+    }
+
+    /// Returns how long long-running tasks are given to finish when the program shuts down.
+    pub fn shutdown_grace_period(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_period)
+    }
+
+    /// Returns whether stored games are imported only when they're first accessed instead of when the program starts.
+    pub fn lazy_load(&self) -> bool {
+        self.lazy_load
+    }
+
+    /// Returns whether the program runs as a mod portal proxy that doesn't manage any servers.
+    pub fn portal_proxy(&self) -> bool {
+        self.portal_proxy
+    }
+
     /// Retuns the log level config value.
     pub fn log_level(&self) -> LogLevel {
         self.log_level
     }
 
+    /// Returns the log level server output lines that aren't recognised as game events are logged at.
+    pub fn unparsed_line_level(&self) -> LogLevel {
+        self.unparsed_line_level
+    }
+
+    /// Returns the file the program's log is also written to, or `None` if the file output is disabled.
+    pub fn log_file(&self) -> Option<&Path> {
+        if self.log_file.as_os_str().is_empty() {
+            None
+        } else {
+            Some(&self.log_file)
+        }
+    }
+
+    /// Returns the size in bytes a log file may grow to before it's rotated, or `None` if size-based rotation is
+    /// disabled.
+    pub fn log_max_size(&self) -> Option<u64> {
+        if self.log_max_size == 0 {
+            None
+        } else {
+            Some(self.log_max_size)
+        }
+    }
+
+    /// Returns the time after which a log file is rotated, or `None` if time-based rotation is disabled.
+    pub fn log_rotate_interval(&self) -> Option<Duration> {
+        if self.log_rotate_interval == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.log_rotate_interval))
+        }
+    }
+
+    /// Returns the amount of rotated log files kept.
+    pub fn log_retention(&self) -> u32 {
+        self.log_retention
+    }
+
+    /// Returns whether rotated log files are compressed.
+    pub fn log_compress(&self) -> bool {
+        self.log_compress
+    }
+
+    /// Returns the format the log lines are written in.
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
+    /// Returns the directory servers' backups are kept in.
+    pub fn backup_directory(&self) -> &Path {
+        &self.backup_directory
+    }
+
+    /// Returns the directory the shared pool of installed Factorio versions is kept in.
+    pub fn versions_directory(&self) -> &Path {
+        &self.versions_directory
+    }
+
+    /// Returns the directory the shared cache of mods downloaded outside any server is kept in.
+    pub fn mod_cache_directory(&self) -> &Path {
+        &self.mod_cache_directory
+    }
+
+    /// Returns the directory modpack manifests are exported into and applied from.
+    pub fn modpack_directory(&self) -> &Path {
+        &self.modpack_directory
+    }
+
     /// Retuns the mod portal username config value.
     pub fn portal_username(&self) -> &str {
         &self.portal_username
@@ -156,6 +416,16 @@ impl Config {
         &self.portal_token
     }
 
+    /// Returns the hex key per-server mod portal credentials are encrypted with in the program store, or `None` if one
+    /// isn't configured.
+    pub fn credential_key(&self) -> Option<&str> {
+        if self.credential_key.is_empty() {
+            None
+        } else {
+            Some(&self.credential_key)
+        }
+    }
+
     /// Retuns the mod portal page size config value.
     pub fn portal_page_size(&self) -> Limit {
         self.portal_page_size
@@ -166,16 +436,248 @@ impl Config {
         self.store_expiry
     }
 
+    /// Returns whether the program runs in offline mode, where every network call is disabled.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Returns how long a mutating RPC request's idempotency key is remembered.
+    pub fn idempotency_ttl(&self) -> Duration {
+        Duration::from_secs(self.idempotency_ttl)
+    }
+
     /// Returns the network listen addresses
     pub fn listen(&self) -> &[NetAddress] {
         self.listen.as_slice()
     }
+
+    /// Returns the read-only status service listen addresses
+    pub fn status_listen(&self) -> &[NetAddress] {
+        self.status_listen.as_slice()
+    }
+
+    /// Returns the Prometheus metrics endpoint listen addresses.
+    pub fn metrics_listen(&self) -> &[NetAddress] {
+        self.metrics_listen.as_slice()
+    }
+
+    /// Returns the mod release notification webhook listen addresses.
+    pub fn webhook_listen(&self) -> &[NetAddress] {
+        self.webhook_listen.as_slice()
+    }
+
+    /// Returns the shared secret mod release notifications must present as their bearer token, if one is configured.
+    pub fn webhook_secret(&self) -> Option<&str> {
+        if self.webhook_secret.is_empty() {
+            None
+        } else {
+            Some(&self.webhook_secret)
+        }
+    }
+
+    /// Returns the file mode of the Unix sockets the RPC services listen on.
+    pub fn socket_mode(&self) -> Result<u32, ConfigError> {
+        util::socket::parse_mode(&self.socket_mode)
+    }
+
+    /// Returns the user that owns the Unix sockets the RPC services listen on, or `None` if the owner is left
+    /// unchanged.
+    pub fn socket_owner(&self) -> Option<&str> {
+        if self.socket_owner.is_empty() {
+            None
+        } else {
+            Some(&self.socket_owner)
+        }
+    }
+
+    /// Returns the group of the Unix sockets the RPC services listen on, or `None` if the group is left unchanged.
+    pub fn socket_group(&self) -> Option<&str> {
+        if self.socket_group.is_empty() {
+            None
+        } else {
+            Some(&self.socket_group)
+        }
+    }
+
+    /// Returns whether RPC requests are authorized without a token. Unless set, every request needs a token even if
+    /// none have been created.
+    pub fn auth_disabled(&self) -> bool {
+        self.auth_disabled
+    }
+
+    /// Returns the minimum interval between successive definite progress updates.
+    pub fn progress_interval(&self) -> Duration {
+        Duration::from_millis(self.progress_interval)
+    }
+
+    /// Returns the maximum amount of items sent in a single streamed RPC response message. Always at least 1.
+    pub fn max_batch_size(&self) -> usize {
+        self.max_batch_size.max(1) as usize
+    }
+
+    /// Returns how long a destructive RPC request's confirmation token is valid for.
+    pub fn confirmation_ttl(&self) -> Duration {
+        Duration::from_secs(self.confirmation_ttl)
+    }
+
+    /// Returns the maximum amount of mods downloaded concurrently when updating mods or installing missing
+    /// dependencies. Always at least 1.
+    pub fn download_concurrency(&self) -> usize {
+        self.download_concurrency.max(1) as usize
+    }
+
+    /// Returns how many requests per second are made to the mod portal on average, shared by every server. 0 means
+    /// the rate isn't limited.
+    pub fn portal_rate_limit(&self) -> u64 {
+        self.portal_rate_limit
+    }
+
+    /// Returns how many requests are made to the mod portal in a quick burst before the rate limit applies.
+    pub fn portal_burst(&self) -> u64 {
+        self.portal_burst
+    }
+
+    /// Returns how many refused connections from a single address within the ban window make the address an
+    /// offender. 0 means the detection is disabled.
+    pub fn ban_threshold(&self) -> u32 {
+        self.ban_threshold
+    }
+
+    /// Returns the window refused connections are counted in.
+    pub fn ban_window(&self) -> Duration {
+        Duration::from_secs(self.ban_window)
+    }
+
+    /// Returns the action taken when an address becomes an offender.
+    pub fn ban_action(&self) -> BanAction {
+        self.ban_action
+    }
+
+    /// Returns the URL offenders are POSTed to with the webhook ban action.
+    pub fn ban_webhook(&self) -> &str {
+        &self.ban_webhook
+    }
+
+    /// Returns the command run with the command ban action.
+    pub fn ban_command(&self) -> &str {
+        &self.ban_command
+    }
+
+    /// Returns the script run at a given hook point, or `None` if nothing is run at it.
+    pub fn hook_script(&self, point: HookPoint) -> Option<&Path> {
+        let script = match point {
+            HookPoint::PreStart => &self.hook_pre_start,
+            HookPoint::PostStart => &self.hook_post_start,
+            HookPoint::PreStop => &self.hook_pre_stop,
+            HookPoint::PostStop => &self.hook_post_stop,
+            HookPoint::PostCrash => &self.hook_post_crash,
+        };
+
+        if script.as_os_str().is_empty() {
+            None
+        } else {
+            Some(script)
+        }
+    }
+
+    /// Returns how long a hook script may run before it's killed, or `None` if it may run indefinitely.
+    pub fn hook_timeout(&self) -> Option<Duration> {
+        if self.hook_timeout == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.hook_timeout))
+        }
+    }
+
+    /// Returns the amount of lines or events buffered in each channel between a server executable and the program.
+    /// Always at least 1.
+    pub fn channel_capacity(&self) -> usize {
+        self.channel_capacity.max(1) as usize
+    }
+
+    /// Returns what's done when a server executable's output channel is full.
+    pub fn output_overflow(&self) -> OverflowStrategy {
+        self.output_overflow
+    }
+
+    /// Returns the non-zero exit codes a server executable exiting with is considered a clean shutdown instead of a
+    /// crash.
+    pub fn clean_exit_codes(&self) -> &[i32] {
+        &self.clean_exit_codes
+    }
+
+    /// Returns the user-defined parsers that turn output lines logged by mods into game events. The parsers are
+    /// compiled when the config file is loaded.
+    pub fn event_parsers(&self) -> &[CustomEventParser] {
+        &self.event_parsers
+    }
+
+    /// Returns how many times starting an autostarted server is attempted. Always at least 1.
+    pub fn autostart_attempts(&self) -> u32 {
+        self.autostart_attempts.max(1)
+    }
+
+    /// Returns the delay before retrying a failed autostart for the first time. Later delays double up to
+    /// [`autostart_max_backoff`](Config::autostart_max_backoff).
+    pub fn autostart_initial_backoff(&self) -> Duration {
+        Duration::from_secs(self.autostart_initial_backoff)
+    }
+
+    /// Returns the maximum delay between autostart attempts.
+    pub fn autostart_max_backoff(&self) -> Duration {
+        Duration::from_secs(self.autostart_max_backoff)
+    }
+
+    /// Returns how long a server may save its map before it's considered stuck, or `None` if the watchdog is
+    /// disabled.
+    pub fn saving_timeout(&self) -> Option<Duration> {
+        if self.saving_timeout == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.saving_timeout))
+        }
+    }
+
+    /// Returns the action taken when a server is stuck saving its map.
+    pub fn stuck_save_action(&self) -> StuckSaveAction {
+        self.stuck_save_action
+    }
+
+    /// Returns the interval between samples of the running servers' player counts, or `None` if sampling is disabled.
+    pub fn player_count_interval(&self) -> Option<Duration> {
+        if self.player_count_interval == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.player_count_interval))
+        }
+    }
+
+    /// Returns how long the player count samples are kept for, or `None` if they're kept forever.
+    pub fn player_count_retention(&self) -> Option<Duration> {
+        if self.player_count_retention == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.player_count_retention * 24 * 60 * 60))
+        }
+    }
+
+    /// Returns how long the servers' recorded events are kept for, or `None` if they're kept forever.
+    pub fn event_retention(&self) -> Option<Duration> {
+        if self.event_retention == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.event_retention * 24 * 60 * 60))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::store::{self, option};
+    use crate::{
+        factorio::executable::GameEvent,
+        store::{self, option},
+    };
     use std::{env, io::Cursor, path::PathBuf};
 
     fn temp_config_file() -> Cursor<Vec<u8>> {
@@ -186,7 +688,11 @@ portal_page_size = 5
 [store]
 expiry = 60
 [network]
-listen = ["0.0.0.0:1337", "unix:/temp/path"]"#,
+listen = ["0.0.0.0:1337", "unix:/temp/path"]
+[[executable.event_parsers]]
+name = "telemetry"
+pattern = "\\[TELEMETRY\\] (.+)$"
+severity = "debug""#,
         );
         Cursor::new(contents.into_bytes())
     }
@@ -258,5 +764,12 @@ listen = ["0.0.0.0:1337", "unix:/temp/path"]"#,
             ]
         );
         assert_eq!(config.portal_page_size, Limit::Limited(5));
+        assert_eq!(config.event_parsers().len(), 1);
+        // without a template, the whole matched text is the message
+        assert!(matches!(
+            config.event_parsers()[0].parse("1.000 [TELEMETRY] rockets=3"),
+            Some(GameEvent::Custom { name, severity, message, .. })
+            if name == "telemetry" && severity == LogLevel::Debug && message == "[TELEMETRY] rockets=3"
+        ));
     }
 }
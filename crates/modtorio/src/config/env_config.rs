@@ -13,6 +13,8 @@ pub struct EnvConfig {
     pub portal_username: Option<String>,
     /// Corresponds to the `MODTORIO_PORTAL_TOKEN` environment variable.
     pub portal_token: Option<String>,
+    /// Corresponds to the `MODTORIO_CREDENTIAL_KEY` environment variable.
+    pub credential_key: Option<String>,
 }
 
 impl ConfigSource for EnvConfig {
@@ -25,6 +27,7 @@ impl ConfigSource for EnvConfig {
         Config {
             portal_username: self.portal_username.unwrap_or(config.portal_username),
             portal_token: self.portal_token.unwrap_or(config.portal_token),
+            credential_key: self.credential_key.unwrap_or(config.credential_key),
             ..config
         }
     }
@@ -53,11 +56,13 @@ mod tests {
 
         env::set_var("MODTORIO_PORTAL_USERNAME", "env_username");
         env::set_var("MODTORIO_PORTAL_TOKEN", "env_token");
+        env::set_var("MODTORIO_CREDENTIAL_KEY", "env_key");
 
         let config = EnvConfig::new().expect("failed to create EnvConfig");
 
         assert_eq!(config.portal_username, Some(String::from("env_username")));
         assert_eq!(config.portal_token, Some(String::from("env_token")));
+        assert_eq!(config.credential_key, Some(String::from("env_key")));
     }
 
     #[test]
@@ -66,10 +71,12 @@ mod tests {
 
         env::remove_var("MODTORIO_PORTAL_USERNAME");
         env::remove_var("MODTORIO_PORTAL_TOKEN");
+        env::remove_var("MODTORIO_CREDENTIAL_KEY");
 
         let config = EnvConfig::new().expect("failed to create EnvConfig");
 
         assert_eq!(config.portal_username, None);
         assert_eq!(config.portal_token, None);
+        assert_eq!(config.credential_key, None);
     }
 }
@@ -1,14 +1,32 @@
 //! Provides the `FileConfig` object, used to access config values from a config file.
 
-use super::{Config, ConfigSource, DEFAULT_STORE_EXPIRY};
-use crate::util::{Limit, LogLevel};
+use super::{
+    Config, ConfigSource, DEFAULT_AUTOSTART_ATTEMPTS, DEFAULT_AUTOSTART_INITIAL_BACKOFF, DEFAULT_AUTOSTART_MAX_BACKOFF,
+    DEFAULT_BACKUP_DIRECTORY, DEFAULT_BAN_THRESHOLD, DEFAULT_BAN_WINDOW, DEFAULT_CHANNEL_CAPACITY,
+    DEFAULT_CONFIRMATION_TTL, DEFAULT_DOWNLOAD_CONCURRENCY, DEFAULT_EVENT_RETENTION, DEFAULT_HOOK_TIMEOUT,
+    DEFAULT_IDEMPOTENCY_TTL, DEFAULT_LOG_MAX_SIZE, DEFAULT_LOG_RETENTION, DEFAULT_LOG_ROTATE_INTERVAL,
+    DEFAULT_MAX_BATCH_SIZE, DEFAULT_MODPACK_DIRECTORY, DEFAULT_MOD_CACHE_DIRECTORY, DEFAULT_PLAYER_COUNT_INTERVAL,
+    DEFAULT_PLAYER_COUNT_RETENTION, DEFAULT_PORTAL_BURST, DEFAULT_PORTAL_RATE_LIMIT, DEFAULT_PROGRESS_INTERVAL,
+    DEFAULT_SAVING_TIMEOUT, DEFAULT_SHUTDOWN_GRACE_PERIOD, DEFAULT_SOCKET_MODE, DEFAULT_STORE_EXPIRY,
+    DEFAULT_VERSIONS_DIRECTORY,
+};
+use crate::{
+    factorio::executable::{CustomEventParser, EventParserDefinition},
+    util::{channel::OverflowStrategy, BanAction, Limit, LogFormat, LogLevel, StuckSaveAction, TimeZone},
+};
 use common::net::NetAddress;
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
+use std::{
+    io::{Read, Write},
+    path::PathBuf,
+};
 
 /// Contains the config values from a config file.
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct FileConfig {
+    /// General config options
+    #[serde(default)]
+    general: GeneralOptions,
     /// Debug config options
     #[serde(default)]
     debug: DebugOptions,
@@ -17,14 +35,70 @@ pub struct FileConfig {
     store: StoreOptions,
     /// Network config options
     network: NetworkOptions,
+    /// RPC authorization config options
+    #[serde(default)]
+    auth: AuthOptions,
+    /// Refused connection banning config options
+    #[serde(default)]
+    ban: BanOptions,
+    /// Server lifecycle hook config options
+    #[serde(default)]
+    hooks: HooksOptions,
+    /// Server executable config options
+    #[serde(default)]
+    executable: ExecutableOptions,
+    /// Server autostart config options
+    #[serde(default)]
+    autostart: AutostartOptions,
+    /// Server watchdog config options
+    #[serde(default)]
+    watchdog: WatchdogOptions,
+    /// Log file config options
+    #[serde(default)]
+    log: LogOptions,
+    /// Server backup config options
+    #[serde(default)]
+    backup: BackupOptions,
+    /// Shared Factorio version pool config options
+    #[serde(default)]
+    versions: VersionsOptions,
+    /// Shared mod cache config options
+    #[serde(default)]
+    mod_cache: ModCacheOptions,
+    /// Modpack manifest config options
+    #[serde(default)]
+    modpacks: ModpackOptions,
+    /// Player count history config options
+    #[serde(default)]
+    history: HistoryOptions,
 }
 
 /// Contains the config values from the `[general]` section of a config file.
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GeneralOptions {
+    /// The IANA time zone timestamps are displayed in.
+    #[serde(default)]
+    time_zone: TimeZone,
+    /// How long in seconds long-running tasks are given to finish when the program shuts down.
+    #[serde(default = "default_shutdown_grace_period")]
+    shutdown_grace_period: u64,
+    /// Whether stored games are imported only when they're first accessed instead of when the program starts.
+    #[serde(default)]
+    lazy_load: bool,
+    /// Whether the program runs as a mod portal proxy that doesn't manage any servers.
+    #[serde(default)]
+    portal_proxy: bool,
+}
+
+/// Contains the config values from the `[debug]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct DebugOptions {
     /// The log level to use.
     #[serde(default)]
     log_level: LogLevel,
+    /// The log level server output lines that aren't recognised as game events are logged at.
+    #[serde(default = "default_unparsed_line_level")]
+    unparsed_line_level: LogLevel,
     /// The page size to use when requesting batched mods from the mod portal. `Limit::Unlimited` corresponds to
     /// `"max"`.
     #[serde(default)]
@@ -38,13 +112,221 @@ pub struct StoreOptions {
     /// The program store expiry in seconds.
     #[serde(default)]
     expiry: u64,
+    /// How long in seconds a mutating RPC request's idempotency key is remembered.
+    #[serde(default = "default_idempotency_ttl")]
+    idempotency_ttl: u64,
 }
 
 /// Contains the config values from the `[network]` section of a config file.
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct NetworkOptions {
     /// The server listen addresses
     listen: Vec<NetAddress>,
+    /// The read-only status service listen addresses
+    #[serde(default)]
+    status_listen: Vec<NetAddress>,
+    /// The Prometheus metrics endpoint listen addresses
+    #[serde(default)]
+    metrics_listen: Vec<NetAddress>,
+    /// The mod release notification webhook listen addresses
+    #[serde(default)]
+    webhook_listen: Vec<NetAddress>,
+    /// The shared secret mod release notifications must present as their bearer token.
+    #[serde(default)]
+    webhook_secret: String,
+    /// The file mode of the Unix sockets the services listen on, as an octal string.
+    #[serde(default = "default_socket_mode")]
+    socket_mode: String,
+    /// The user that owns the Unix sockets the services listen on. Empty leaves the owner unchanged.
+    #[serde(default)]
+    socket_owner: String,
+    /// The group of the Unix sockets the services listen on. Empty leaves the group unchanged.
+    #[serde(default)]
+    socket_group: String,
+    /// The minimum interval in milliseconds between successive definite progress updates sent to RPC clients.
+    #[serde(default = "default_progress_interval")]
+    progress_interval: u64,
+    /// The maximum amount of items sent in a single streamed RPC response message.
+    #[serde(default = "default_max_batch_size")]
+    max_batch_size: u64,
+    /// How long in seconds a destructive RPC request's confirmation token is valid for.
+    #[serde(default = "default_confirmation_ttl")]
+    confirmation_ttl: u64,
+    /// The maximum amount of mods downloaded concurrently when updating mods or installing missing dependencies.
+    #[serde(default = "default_download_concurrency")]
+    download_concurrency: u64,
+    /// How many requests per second are made to the mod portal on average. 0 disables the limit.
+    #[serde(default = "default_portal_rate_limit")]
+    portal_rate_limit: u64,
+    /// How many requests are made to the mod portal in a quick burst before the rate limit applies.
+    #[serde(default = "default_portal_burst")]
+    portal_burst: u64,
+}
+
+/// Contains the config values from the `[auth]` section of a config file.
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct AuthOptions {
+    /// Whether RPC requests are authorized without a token.
+    #[serde(default)]
+    disabled: bool,
+}
+
+/// Contains the config values from the `[ban]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BanOptions {
+    /// How many refused connections from a single address within the window make the address an offender. 0 disables
+    /// the detection.
+    #[serde(default = "default_ban_threshold")]
+    threshold: u32,
+    /// The window in seconds refused connections are counted in.
+    #[serde(default = "default_ban_window")]
+    window: u64,
+    /// The action taken when an address becomes an offender.
+    #[serde(default)]
+    action: BanAction,
+    /// The URL offenders are POSTed to with the `webhook` action.
+    #[serde(default)]
+    webhook: String,
+    /// The command run with the `command` action.
+    #[serde(default)]
+    command: String,
+}
+
+/// Contains the config values from the `[hooks]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HooksOptions {
+    /// The script run before a server is started. Empty runs nothing.
+    #[serde(default)]
+    pre_start: PathBuf,
+    /// The script run once a server is running. Empty runs nothing.
+    #[serde(default)]
+    post_start: PathBuf,
+    /// The script run before a server is stopped. Empty runs nothing.
+    #[serde(default)]
+    pre_stop: PathBuf,
+    /// The script run after a server has shut down cleanly. Empty runs nothing.
+    #[serde(default)]
+    post_stop: PathBuf,
+    /// The script run after a server has crashed. Empty runs nothing.
+    #[serde(default)]
+    post_crash: PathBuf,
+    /// How long in seconds a hook script may run before it's killed. 0 lets it run indefinitely.
+    #[serde(default = "default_hook_timeout")]
+    timeout: u64,
+}
+
+/// Contains the config values from the `[executable]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExecutableOptions {
+    /// The amount of lines or events buffered in each channel between a server executable and the program.
+    #[serde(default = "default_channel_capacity")]
+    channel_capacity: u64,
+    /// What's done when a server executable's output channel is full.
+    #[serde(default)]
+    output_overflow: OverflowStrategy,
+    /// The non-zero exit codes a server executable exiting with is considered a clean shutdown instead of a crash.
+    #[serde(default)]
+    clean_exit_codes: Vec<i32>,
+    /// The user-defined parsers that turn output lines logged by mods into game events.
+    #[serde(default)]
+    event_parsers: Vec<EventParserDefinition>,
+    /// The compiled `event_parsers`, compiled when the config file is loaded.
+    #[serde(skip)]
+    compiled_event_parsers: Vec<CustomEventParser>,
+}
+
+/// Contains the config values from the `[autostart]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AutostartOptions {
+    /// How many times starting an autostarted server is attempted.
+    #[serde(default = "default_autostart_attempts")]
+    attempts: u32,
+    /// The delay in seconds before retrying a failed autostart for the first time.
+    #[serde(default = "default_autostart_initial_backoff")]
+    initial_backoff: u64,
+    /// The maximum delay in seconds between autostart attempts.
+    #[serde(default = "default_autostart_max_backoff")]
+    max_backoff: u64,
+}
+
+/// Contains the config values from the `[watchdog]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WatchdogOptions {
+    /// How long in seconds a server may save its map before it's considered stuck. 0 disables the watchdog.
+    #[serde(default = "default_saving_timeout")]
+    saving_timeout: u64,
+    /// The action taken when a server is stuck saving its map.
+    #[serde(default)]
+    stuck_save_action: StuckSaveAction,
+}
+
+/// Contains the config values from the `[log]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LogOptions {
+    /// The file the program's log is also written to. Empty disables the file output.
+    #[serde(default)]
+    file: PathBuf,
+    /// The size in bytes a log file may grow to before it's rotated. 0 disables size-based rotation.
+    #[serde(default = "default_log_max_size")]
+    max_size: u64,
+    /// The time in seconds after which a log file is rotated. 0 disables time-based rotation.
+    #[serde(default = "default_log_rotate_interval")]
+    rotate_interval: u64,
+    /// The amount of rotated log files kept.
+    #[serde(default = "default_log_retention")]
+    retention: u32,
+    /// Whether rotated log files are compressed.
+    #[serde(default = "default_log_compress")]
+    compress: bool,
+    /// The format the log lines are written in.
+    #[serde(default)]
+    format: LogFormat,
+}
+
+/// Contains the config values from the `[backup]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BackupOptions {
+    /// The directory servers' backups are kept in.
+    #[serde(default = "default_backup_directory")]
+    directory: PathBuf,
+}
+
+/// Contains the config values from the `[versions]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VersionsOptions {
+    /// The directory the shared pool of installed Factorio versions is kept in.
+    #[serde(default = "default_versions_directory")]
+    directory: PathBuf,
+}
+
+/// Contains the config values from the `[mod_cache]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModCacheOptions {
+    /// The directory the shared cache of mods downloaded outside any server is kept in.
+    #[serde(default = "default_mod_cache_directory")]
+    directory: PathBuf,
+}
+
+/// Contains the config values from the `[modpacks]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModpackOptions {
+    /// The directory modpack manifests are exported into and applied from.
+    #[serde(default = "default_modpack_directory")]
+    directory: PathBuf,
+}
+
+/// Contains the config values from the `[history]` section of a config file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HistoryOptions {
+    /// The interval in seconds between samples of the running servers' player counts. 0 disables sampling.
+    #[serde(default = "default_player_count_interval")]
+    player_count_interval: u64,
+    /// The amount of days the player count samples are kept for. 0 keeps them forever.
+    #[serde(default = "default_player_count_retention")]
+    player_count_retention: u64,
+    /// The amount of days the servers' recorded events are kept for. 0 keeps them forever.
+    #[serde(default = "default_event_retention")]
+    event_retention: u64,
 }
 
 impl ConfigSource for FileConfig {
@@ -52,9 +334,62 @@ impl ConfigSource for FileConfig {
     /// values set.
     fn apply_to_config(self, config: Config) -> Config {
         Config {
+            time_zone: self.general.time_zone,
+            shutdown_grace_period: self.general.shutdown_grace_period,
+            lazy_load: self.general.lazy_load,
+            portal_proxy: self.general.portal_proxy,
             log_level: self.debug.log_level,
+            unparsed_line_level: self.debug.unparsed_line_level,
             store_expiry: self.store.expiry,
+            idempotency_ttl: self.store.idempotency_ttl,
             listen: self.network.listen,
+            status_listen: self.network.status_listen,
+            metrics_listen: self.network.metrics_listen,
+            webhook_listen: self.network.webhook_listen,
+            webhook_secret: self.network.webhook_secret,
+            socket_mode: self.network.socket_mode,
+            socket_owner: self.network.socket_owner,
+            socket_group: self.network.socket_group,
+            auth_disabled: self.auth.disabled,
+            progress_interval: self.network.progress_interval,
+            max_batch_size: self.network.max_batch_size,
+            confirmation_ttl: self.network.confirmation_ttl,
+            download_concurrency: self.network.download_concurrency,
+            portal_rate_limit: self.network.portal_rate_limit,
+            portal_burst: self.network.portal_burst,
+            ban_threshold: self.ban.threshold,
+            ban_window: self.ban.window,
+            ban_action: self.ban.action,
+            ban_webhook: self.ban.webhook,
+            ban_command: self.ban.command,
+            hook_pre_start: self.hooks.pre_start,
+            hook_post_start: self.hooks.post_start,
+            hook_pre_stop: self.hooks.pre_stop,
+            hook_post_stop: self.hooks.post_stop,
+            hook_post_crash: self.hooks.post_crash,
+            hook_timeout: self.hooks.timeout,
+            channel_capacity: self.executable.channel_capacity,
+            output_overflow: self.executable.output_overflow,
+            clean_exit_codes: self.executable.clean_exit_codes,
+            event_parsers: self.executable.compiled_event_parsers,
+            autostart_attempts: self.autostart.attempts,
+            autostart_initial_backoff: self.autostart.initial_backoff,
+            autostart_max_backoff: self.autostart.max_backoff,
+            saving_timeout: self.watchdog.saving_timeout,
+            stuck_save_action: self.watchdog.stuck_save_action,
+            log_file: self.log.file,
+            log_max_size: self.log.max_size,
+            log_rotate_interval: self.log.rotate_interval,
+            log_retention: self.log.retention,
+            log_compress: self.log.compress,
+            log_format: self.log.format,
+            backup_directory: self.backup.directory,
+            versions_directory: self.versions.directory,
+            mod_cache_directory: self.mod_cache.directory,
+            modpack_directory: self.modpacks.directory,
+            player_count_interval: self.history.player_count_interval,
+            player_count_retention: self.history.player_count_retention,
+            event_retention: self.history.event_retention,
             portal_page_size: self.debug.portal_page_size,
             ..config
         }
@@ -69,7 +404,11 @@ impl FileConfig {
     {
         let mut file_contents = String::new();
         file.read_to_string(&mut file_contents)?;
-        Ok(toml::from_str(&file_contents)?)
+        let mut file_config: Self = toml::from_str(&file_contents)?;
+        // the parsers are compiled up front so an invalid one fails loading the config instead of starting a server
+        file_config.executable.compiled_event_parsers =
+            CustomEventParser::compile_all(&file_config.executable.event_parsers)?;
+        Ok(file_config)
     }
 
     /// Writes a config file with all values set to their config defaults to a given writer.
@@ -85,35 +424,382 @@ impl FileConfig {
     }
 }
 
+impl Default for GeneralOptions {
+    fn default() -> Self {
+        Self {
+            time_zone: TimeZone::default(),
+            shutdown_grace_period: DEFAULT_SHUTDOWN_GRACE_PERIOD,
+            lazy_load: false,
+            portal_proxy: false,
+        }
+    }
+}
+
+impl Default for DebugOptions {
+    fn default() -> Self {
+        Self {
+            log_level: LogLevel::default(),
+            unparsed_line_level: default_unparsed_line_level(),
+            portal_page_size: Limit::default(),
+        }
+    }
+}
+
 impl Default for StoreOptions {
     fn default() -> Self {
         Self {
             expiry: DEFAULT_STORE_EXPIRY,
+            idempotency_ttl: DEFAULT_IDEMPOTENCY_TTL,
+        }
+    }
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        Self {
+            listen: Vec::new(),
+            status_listen: Vec::new(),
+            metrics_listen: Vec::new(),
+            webhook_listen: Vec::new(),
+            webhook_secret: String::new(),
+            socket_mode: default_socket_mode(),
+            socket_owner: String::new(),
+            socket_group: String::new(),
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            confirmation_ttl: DEFAULT_CONFIRMATION_TTL,
+            download_concurrency: DEFAULT_DOWNLOAD_CONCURRENCY,
+            portal_rate_limit: DEFAULT_PORTAL_RATE_LIMIT,
+            portal_burst: DEFAULT_PORTAL_BURST,
+        }
+    }
+}
+
+impl Default for BanOptions {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_BAN_THRESHOLD,
+            window: DEFAULT_BAN_WINDOW,
+            action: BanAction::default(),
+            webhook: String::new(),
+            command: String::new(),
+        }
+    }
+}
+
+impl Default for HooksOptions {
+    fn default() -> Self {
+        Self {
+            pre_start: PathBuf::new(),
+            post_start: PathBuf::new(),
+            pre_stop: PathBuf::new(),
+            post_stop: PathBuf::new(),
+            post_crash: PathBuf::new(),
+            timeout: default_hook_timeout(),
+        }
+    }
+}
+
+impl Default for ExecutableOptions {
+    fn default() -> Self {
+        Self {
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            output_overflow: OverflowStrategy::default(),
+            clean_exit_codes: Vec::new(),
+            event_parsers: Vec::new(),
+            compiled_event_parsers: Vec::new(),
+        }
+    }
+}
+
+impl Default for AutostartOptions {
+    fn default() -> Self {
+        Self {
+            attempts: DEFAULT_AUTOSTART_ATTEMPTS,
+            initial_backoff: DEFAULT_AUTOSTART_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_AUTOSTART_MAX_BACKOFF,
         }
     }
 }
 
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        Self {
+            saving_timeout: DEFAULT_SAVING_TIMEOUT,
+            stuck_save_action: StuckSaveAction::default(),
+        }
+    }
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            file: PathBuf::new(),
+            max_size: DEFAULT_LOG_MAX_SIZE,
+            rotate_interval: DEFAULT_LOG_ROTATE_INTERVAL,
+            retention: DEFAULT_LOG_RETENTION,
+            compress: default_log_compress(),
+            format: LogFormat::default(),
+        }
+    }
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        Self {
+            directory: default_backup_directory(),
+        }
+    }
+}
+
+impl Default for VersionsOptions {
+    fn default() -> Self {
+        Self {
+            directory: default_versions_directory(),
+        }
+    }
+}
+
+impl Default for ModCacheOptions {
+    fn default() -> Self {
+        Self {
+            directory: default_mod_cache_directory(),
+        }
+    }
+}
+
+impl Default for ModpackOptions {
+    fn default() -> Self {
+        Self {
+            directory: default_modpack_directory(),
+        }
+    }
+}
+
+impl Default for HistoryOptions {
+    fn default() -> Self {
+        Self {
+            player_count_interval: default_player_count_interval(),
+            player_count_retention: default_player_count_retention(),
+            event_retention: default_event_retention(),
+        }
+    }
+}
+
+/// Returns the default shutdown grace period, used when the config file doesn't specify one.
+fn default_shutdown_grace_period() -> u64 {
+    DEFAULT_SHUTDOWN_GRACE_PERIOD
+}
+
+/// Returns the default log level of unparsed server output lines, used when the config file doesn't specify one.
+fn default_unparsed_line_level() -> LogLevel {
+    LogLevel::Trace
+}
+
+/// Returns the default progress update interval, used when the config file doesn't specify one.
+fn default_progress_interval() -> u64 {
+    DEFAULT_PROGRESS_INTERVAL
+}
+
+/// Returns the default socket mode, used when the config file doesn't specify one.
+fn default_socket_mode() -> String {
+    String::from(DEFAULT_SOCKET_MODE)
+}
+
+/// Returns the default idempotency key TTL, used when the config file doesn't specify one.
+fn default_idempotency_ttl() -> u64 {
+    DEFAULT_IDEMPOTENCY_TTL
+}
+
+/// Returns the default maximum batch size, used when the config file doesn't specify one.
+fn default_max_batch_size() -> u64 {
+    DEFAULT_MAX_BATCH_SIZE
+}
+
+/// Returns the default confirmation token TTL, used when the config file doesn't specify one.
+fn default_confirmation_ttl() -> u64 {
+    DEFAULT_CONFIRMATION_TTL
+}
+
+/// Returns the default download concurrency, used when the config file doesn't specify one.
+fn default_download_concurrency() -> u64 {
+    DEFAULT_DOWNLOAD_CONCURRENCY
+}
+
+/// Returns the default mod portal rate limit, used when the config file doesn't specify one.
+fn default_portal_rate_limit() -> u64 {
+    DEFAULT_PORTAL_RATE_LIMIT
+}
+
+/// Returns the default mod portal request burst, used when the config file doesn't specify one.
+fn default_portal_burst() -> u64 {
+    DEFAULT_PORTAL_BURST
+}
+
+/// Returns the default ban threshold, used when the config file doesn't specify one.
+fn default_ban_threshold() -> u32 {
+    DEFAULT_BAN_THRESHOLD
+}
+
+/// Returns the default ban window, used when the config file doesn't specify one.
+fn default_ban_window() -> u64 {
+    DEFAULT_BAN_WINDOW
+}
+
+/// Returns the default hook script timeout, used when the config file doesn't specify one.
+fn default_hook_timeout() -> u64 {
+    DEFAULT_HOOK_TIMEOUT
+}
+
+/// Returns the default executable channel capacity, used when the config file doesn't specify one.
+fn default_channel_capacity() -> u64 {
+    DEFAULT_CHANNEL_CAPACITY
+}
+
+/// Returns the default amount of autostart attempts, used when the config file doesn't specify one.
+fn default_autostart_attempts() -> u32 {
+    DEFAULT_AUTOSTART_ATTEMPTS
+}
+
+/// Returns the default initial autostart backoff, used when the config file doesn't specify one.
+fn default_autostart_initial_backoff() -> u64 {
+    DEFAULT_AUTOSTART_INITIAL_BACKOFF
+}
+
+/// Returns the default maximum autostart backoff, used when the config file doesn't specify one.
+fn default_autostart_max_backoff() -> u64 {
+    DEFAULT_AUTOSTART_MAX_BACKOFF
+}
+
+/// Returns the default saving timeout, used when the config file doesn't specify one.
+fn default_saving_timeout() -> u64 {
+    DEFAULT_SAVING_TIMEOUT
+}
+
+/// Returns the default maximum log file size, used when the config file doesn't specify one.
+fn default_log_max_size() -> u64 {
+    DEFAULT_LOG_MAX_SIZE
+}
+
+/// Returns the default log rotation interval, used when the config file doesn't specify one.
+fn default_log_rotate_interval() -> u64 {
+    DEFAULT_LOG_ROTATE_INTERVAL
+}
+
+/// Returns the default log retention, used when the config file doesn't specify one.
+fn default_log_retention() -> u32 {
+    DEFAULT_LOG_RETENTION
+}
+
+/// Returns whether rotated log files are compressed by default, used when the config file doesn't specify it.
+fn default_log_compress() -> bool {
+    true
+}
+
+/// Returns the default backup directory, used when the config file doesn't specify one.
+fn default_backup_directory() -> PathBuf {
+    PathBuf::from(DEFAULT_BACKUP_DIRECTORY)
+}
+
+/// Returns the default directory of the shared Factorio version pool, used when the config file doesn't specify one.
+fn default_versions_directory() -> PathBuf {
+    PathBuf::from(DEFAULT_VERSIONS_DIRECTORY)
+}
+
+/// Returns the default directory of the shared mod cache, used when the config file doesn't specify one.
+fn default_mod_cache_directory() -> PathBuf {
+    PathBuf::from(DEFAULT_MOD_CACHE_DIRECTORY)
+}
+
+/// Returns the default modpack directory, used when the config file doesn't specify one.
+fn default_modpack_directory() -> PathBuf {
+    PathBuf::from(DEFAULT_MODPACK_DIRECTORY)
+}
+
+/// Returns the default player count sample interval, used when the config file doesn't specify one.
+fn default_player_count_interval() -> u64 {
+    DEFAULT_PLAYER_COUNT_INTERVAL
+}
+
+/// Returns the default player count sample retention, used when the config file doesn't specify one.
+fn default_player_count_retention() -> u64 {
+    DEFAULT_PLAYER_COUNT_RETENTION
+}
+
+/// Returns the default server event retention, used when the config file doesn't specify one.
+fn default_event_retention() -> u64 {
+    DEFAULT_EVENT_RETENTION
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::GameEventError;
     use std::{io::Cursor, path::PathBuf};
 
     #[test]
     fn full() {
         let contents = String::from(
-            r#"[debug]
+            r#"[general]
+time_zone = "Europe/Helsinki"
+lazy_load = true
+[debug]
 log_level = "trace"
+unparsed_line_level = "warn"
 portal_page_size = 5
 [store]
 expiry = 60
 [network]
-listen = ["0.0.0.0:1337", "unix:/temp/path"]"#,
+listen = ["0.0.0.0:1337", "unix:/temp/path"]
+status_listen = ["unix:/temp/status"]
+metrics_listen = ["127.0.0.1:9103"]
+webhook_secret = "hunter2"
+socket_mode = "0600"
+socket_group = "modtorio"
+progress_interval = 100
+max_batch_size = 20
+[auth]
+disabled = true
+[ban]
+threshold = 3
+action = "command"
+command = "/usr/local/bin/block-address"
+[hooks]
+post_crash = "/usr/local/bin/notify-crash"
+timeout = 10
+[executable]
+output_overflow = "drop_oldest"
+clean_exit_codes = [130, 143]
+[autostart]
+attempts = 10
+max_backoff = 600
+[watchdog]
+stuck_save_action = "restart"
+[log]
+file = "/var/log/modtorio/modtorio.log"
+max_size = 1048576
+compress = false
+format = "json"
+[backup]
+directory = "/var/lib/modtorio/backups"
+[versions]
+directory = "/var/lib/modtorio/versions"
+[mod_cache]
+directory = "/var/lib/modtorio/mod-cache"
+[modpacks]
+directory = "/var/lib/modtorio/modpacks"
+[history]
+player_count_interval = 60
+event_retention = 0"#,
         );
         let mut contents = Cursor::new(contents.into_bytes());
         let config = FileConfig::new(&mut contents).expect("failed to create FileConfig");
 
+        assert_eq!(config.general.time_zone.name(), "Europe/Helsinki");
         assert_eq!(config.debug.log_level, LogLevel::Trace);
+        assert_eq!(config.debug.unparsed_line_level, LogLevel::Warn);
         assert_eq!(config.store.expiry, 60);
+        assert_eq!(config.store.idempotency_ttl, DEFAULT_IDEMPOTENCY_TTL);
         assert_eq!(
             config.network.listen,
             vec![
@@ -124,9 +810,88 @@ listen = ["0.0.0.0:1337", "unix:/temp/path"]"#,
                 NetAddress::Unix(PathBuf::from("/temp/path")),
             ]
         );
+        assert_eq!(
+            config.network.status_listen,
+            vec![NetAddress::Unix(PathBuf::from("/temp/status"))]
+        );
+        assert_eq!(
+            config.network.metrics_listen,
+            vec![NetAddress::TCP(std::net::SocketAddr::new(
+                std::net::IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 1)),
+                9103
+            ))]
+        );
+        assert_eq!(config.network.webhook_secret, "hunter2");
+        assert_eq!(config.network.socket_mode, "0600");
+        assert_eq!(config.network.socket_owner, "");
+        assert_eq!(config.network.socket_group, "modtorio");
+        assert_eq!(config.network.progress_interval, 100);
+        assert_eq!(config.network.max_batch_size, 20);
+        assert!(config.auth.disabled);
+        assert_eq!(config.ban.threshold, 3);
+        assert_eq!(config.ban.window, DEFAULT_BAN_WINDOW);
+        assert_eq!(config.ban.action, BanAction::Command);
+        assert_eq!(config.ban.command, "/usr/local/bin/block-address");
+        assert_eq!(config.hooks.pre_start, PathBuf::new());
+        assert_eq!(config.hooks.post_crash, PathBuf::from("/usr/local/bin/notify-crash"));
+        assert_eq!(config.hooks.timeout, 10);
+        assert_eq!(config.executable.channel_capacity, DEFAULT_CHANNEL_CAPACITY);
+        assert_eq!(config.executable.output_overflow, OverflowStrategy::DropOldest);
+        assert_eq!(config.executable.clean_exit_codes, vec![130, 143]);
+        assert_eq!(config.autostart.attempts, 10);
+        assert_eq!(config.autostart.initial_backoff, DEFAULT_AUTOSTART_INITIAL_BACKOFF);
+        assert_eq!(config.autostart.max_backoff, 600);
+        assert_eq!(config.watchdog.saving_timeout, DEFAULT_SAVING_TIMEOUT);
+        assert_eq!(config.watchdog.stuck_save_action, StuckSaveAction::Restart);
+        assert_eq!(config.log.file, PathBuf::from("/var/log/modtorio/modtorio.log"));
+        assert_eq!(config.log.max_size, 1_048_576);
+        assert_eq!(config.log.rotate_interval, DEFAULT_LOG_ROTATE_INTERVAL);
+        assert_eq!(config.log.retention, DEFAULT_LOG_RETENTION);
+        assert!(!config.log.compress);
+        assert_eq!(config.log.format, LogFormat::Json);
+        assert_eq!(config.backup.directory, PathBuf::from("/var/lib/modtorio/backups"));
+        assert_eq!(config.versions.directory, PathBuf::from("/var/lib/modtorio/versions"));
+        assert_eq!(config.mod_cache.directory, PathBuf::from("/var/lib/modtorio/mod-cache"));
+        assert_eq!(config.modpacks.directory, PathBuf::from("/var/lib/modtorio/modpacks"));
+        assert!(!config.general.portal_proxy);
+        assert_eq!(config.history.player_count_interval, 60);
+        assert_eq!(config.history.player_count_retention, DEFAULT_PLAYER_COUNT_RETENTION);
+        assert_eq!(config.history.event_retention, 0);
+        assert!(config.general.lazy_load);
         assert_eq!(config.debug.portal_page_size, Limit::Limited(5));
     }
 
+    #[test]
+    fn invalid_time_zone() {
+        let contents = String::from(
+            r#"[general]
+time_zone = "Nowhere/Special"
+[network]
+listen = ["0.0.0.0:1337"]"#,
+        );
+        let mut contents = Cursor::new(contents.into_bytes());
+
+        assert!(FileConfig::new(&mut contents).is_err());
+    }
+
+    #[test]
+    fn invalid_event_parser() {
+        let contents = String::from(
+            r#"[network]
+listen = ["0.0.0.0:1337"]
+[[executable.event_parsers]]
+name = "unclosed"
+pattern = "\\[TELEMETRY\\] (.+$""#,
+        );
+        let mut contents = Cursor::new(contents.into_bytes());
+
+        let error = FileConfig::new(&mut contents).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<GameEventError>(),
+            Some(GameEventError::InvalidParser { name, .. }) if name == "unclosed"
+        ));
+    }
+
     #[test]
     fn required() {
         let contents = String::new();
@@ -144,7 +909,48 @@ listen = ["0.0.0.0:1337"]"#,
         let mut contents = Cursor::new(contents.into_bytes());
         let config = FileConfig::new(&mut contents).expect("failed to create FileConfig");
 
+        assert_eq!(config.general.time_zone, TimeZone::default());
+        assert_eq!(config.general.shutdown_grace_period, DEFAULT_SHUTDOWN_GRACE_PERIOD);
+        assert!(!config.general.lazy_load);
+        assert!(!config.general.portal_proxy);
         assert_eq!(config.debug.log_level, LogLevel::default());
+        assert_eq!(config.debug.unparsed_line_level, LogLevel::Trace);
         assert_eq!(config.store.expiry, DEFAULT_STORE_EXPIRY);
+        assert_eq!(config.network.webhook_secret, "");
+        assert_eq!(config.network.socket_mode, DEFAULT_SOCKET_MODE);
+        assert_eq!(config.network.socket_owner, "");
+        assert_eq!(config.network.socket_group, "");
+        assert_eq!(config.network.progress_interval, DEFAULT_PROGRESS_INTERVAL);
+        assert_eq!(config.network.max_batch_size, DEFAULT_MAX_BATCH_SIZE);
+        assert_eq!(config.network.confirmation_ttl, DEFAULT_CONFIRMATION_TTL);
+        assert_eq!(config.network.download_concurrency, DEFAULT_DOWNLOAD_CONCURRENCY);
+        assert_eq!(config.network.portal_rate_limit, DEFAULT_PORTAL_RATE_LIMIT);
+        assert_eq!(config.network.portal_burst, DEFAULT_PORTAL_BURST);
+        assert!(!config.auth.disabled);
+        assert_eq!(config.ban.threshold, DEFAULT_BAN_THRESHOLD);
+        assert_eq!(config.ban.action, BanAction::Log);
+        assert_eq!(config.hooks.post_crash, PathBuf::new());
+        assert_eq!(config.hooks.timeout, DEFAULT_HOOK_TIMEOUT);
+        assert_eq!(config.executable.channel_capacity, DEFAULT_CHANNEL_CAPACITY);
+        assert_eq!(config.executable.output_overflow, OverflowStrategy::Backpressure);
+        assert!(config.executable.clean_exit_codes.is_empty());
+        assert_eq!(config.autostart.attempts, DEFAULT_AUTOSTART_ATTEMPTS);
+        assert_eq!(config.autostart.initial_backoff, DEFAULT_AUTOSTART_INITIAL_BACKOFF);
+        assert_eq!(config.autostart.max_backoff, DEFAULT_AUTOSTART_MAX_BACKOFF);
+        assert_eq!(config.watchdog.saving_timeout, DEFAULT_SAVING_TIMEOUT);
+        assert_eq!(config.watchdog.stuck_save_action, StuckSaveAction::Alert);
+        assert_eq!(config.log.file, PathBuf::new());
+        assert_eq!(config.log.max_size, DEFAULT_LOG_MAX_SIZE);
+        assert_eq!(config.log.rotate_interval, DEFAULT_LOG_ROTATE_INTERVAL);
+        assert_eq!(config.log.retention, DEFAULT_LOG_RETENTION);
+        assert!(config.log.compress);
+        assert_eq!(config.log.format, LogFormat::Text);
+        assert_eq!(config.backup.directory, PathBuf::from(DEFAULT_BACKUP_DIRECTORY));
+        assert_eq!(config.versions.directory, PathBuf::from(DEFAULT_VERSIONS_DIRECTORY));
+        assert_eq!(config.mod_cache.directory, PathBuf::from(DEFAULT_MOD_CACHE_DIRECTORY));
+        assert_eq!(config.modpacks.directory, PathBuf::from(DEFAULT_MODPACK_DIRECTORY));
+        assert_eq!(config.history.player_count_interval, DEFAULT_PLAYER_COUNT_INTERVAL);
+        assert_eq!(config.history.player_count_retention, DEFAULT_PLAYER_COUNT_RETENTION);
+        assert_eq!(config.history.event_retention, DEFAULT_EVENT_RETENTION);
     }
 }
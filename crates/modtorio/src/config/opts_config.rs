@@ -12,6 +12,8 @@ pub struct OptsConfig {
     log_level: Option<LogLevel>,
     /// Corresponds to the `--store-expiry` option.
     store_expiry: Option<u64>,
+    /// Corresponds to the `--offline` flag. `None` if the flag isn't given.
+    offline: Option<bool>,
 }
 
 impl ConfigSource for OptsConfig {
@@ -21,6 +23,7 @@ impl ConfigSource for OptsConfig {
         Config {
             log_level: self.log_level.unwrap_or(config.log_level),
             store_expiry: self.store_expiry.unwrap_or(config.store_expiry),
+            offline: self.offline.unwrap_or(config.offline),
             ..config
         }
     }
@@ -32,6 +35,21 @@ impl OptsConfig {
         Self {
             log_level: opts.log_level,
             store_expiry: opts.store_expiry,
+            offline: if opts.offline { Some(true) } else { None },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_flag() {
+        let config = OptsConfig::new(&Opts::custom_args(&["--offline"])).apply_to_config(Config::default());
+        assert!(config.offline());
+
+        let config = OptsConfig::new(&Opts::custom_args(&[])).apply_to_config(Config::default());
+        assert!(!config.offline());
+    }
+}
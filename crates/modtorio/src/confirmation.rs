@@ -0,0 +1,227 @@
+//! Provides the [`Confirmations`](Confirmations) object, which keeps track of the tokens destructive RPC requests are
+//! confirmed with.
+//!
+//! A destructive request without a confirmation token isn't executed. Instead, a short-lived token is issued for the
+//! exact operation the request asked for and returned with a summary of the operation's impact. Repeating the request
+//! with the token executes it. Each token confirms a single operation once, and only for the caller it was issued to.
+
+use crate::{error::RpcError, factorio::GameStoreId};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use std::{collections::HashMap, fmt, time::Duration};
+use tokio::sync::Mutex;
+
+/// The amount of random bytes in a generated confirmation token.
+const TOKEN_LENGTH: usize = 16;
+
+/// A destructive operation that has to be confirmed before it's executed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operation {
+    /// Removing a server, optionally deleting its mods directory.
+    RemoveServer {
+        /// The server's ID.
+        server_id: GameStoreId,
+        /// Whether the server's mods directory is deleted.
+        delete_files: bool,
+    },
+    /// Restoring a server's backup.
+    RestoreBackup {
+        /// The server's ID.
+        server_id: GameStoreId,
+        /// The backup's name.
+        name: String,
+    },
+    /// Reverting a server's mods to its last known good mods.
+    RevertToLastKnownGood {
+        /// The server's ID.
+        server_id: GameStoreId,
+    },
+}
+
+/// A confirmation token issued for an operation, and a summary of the operation's impact.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Confirmation {
+    /// The token.
+    token: String,
+    /// A summary of what the operation does.
+    summary: String,
+    /// Timestamp when the token expires.
+    expires_at: DateTime<Utc>,
+}
+
+/// Keeps track of the confirmation tokens that haven't been redeemed yet.
+#[derive(Debug)]
+pub struct Confirmations {
+    /// How long an issued token is valid for.
+    ttl: Duration,
+    /// The pending tokens' operations, keyed by the tokens.
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+/// An operation a pending token confirms.
+#[derive(Debug)]
+struct Pending {
+    /// The operation.
+    operation: Operation,
+    /// The name of the RPC token of the caller the token was issued to, or `None` if authorization is disabled.
+    caller: Option<String>,
+    /// Timestamp when the token expires.
+    expires_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Confirmation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.summary)
+    }
+}
+
+impl From<&Confirmation> for rpc::ConfirmationRequired {
+    fn from(confirmation: &Confirmation) -> Self {
+        Self {
+            token: confirmation.token.clone(),
+            summary: confirmation.summary.clone(),
+            expires_at: confirmation.expires_at.timestamp(),
+        }
+    }
+}
+
+impl Confirmations {
+    /// Returns a new `Confirmations` whose tokens are valid for a given duration.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Issues a new token that confirms a given operation for a given caller, identified by the name of the RPC token
+    /// it's authorized with, with a given summary of the operation's impact. Tokens that have expired unredeemed are
+    /// forgotten.
+    pub async fn issue(
+        &self,
+        operation: Operation,
+        caller: Option<String>,
+        summary: String,
+    ) -> anyhow::Result<Confirmation> {
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::from_std(self.ttl)?;
+
+        let mut bytes = [0; TOKEN_LENGTH];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = hex::encode(&bytes);
+
+        let mut pending = self.pending.lock().await;
+        pending.retain(|_, pending| pending.expires_at > now);
+        pending.insert(
+            token.clone(),
+            Pending {
+                operation,
+                caller,
+                expires_at,
+            },
+        );
+
+        Ok(Confirmation {
+            token,
+            summary,
+            expires_at,
+        })
+    }
+
+    /// Redeems a given token for a given operation by a given caller. Returns
+    /// [`RpcError::InvalidConfirmationToken`][InvalidConfirmationToken] if the token doesn't exist, has expired or
+    /// was issued for a different operation or caller. A token can't be redeemed again either way.
+    ///
+    /// [InvalidConfirmationToken]: crate::error::RpcError::InvalidConfirmationToken
+    pub async fn redeem(&self, token: &str, operation: &Operation, caller: Option<&str>) -> Result<(), RpcError> {
+        match self.pending.lock().await.remove(token) {
+            Some(pending)
+                if pending.operation == *operation
+                    && pending.caller.as_deref() == caller
+                    && pending.expires_at > Utc::now() =>
+            {
+                Ok(())
+            }
+            _ => Err(RpcError::InvalidConfirmationToken),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn issue_redeem() {
+        let confirmations = Confirmations::new(Duration::from_secs(60));
+        let operation = Operation::RemoveServer {
+            server_id: 1,
+            delete_files: true,
+        };
+
+        let caller = Some(String::from("operator"));
+
+        let confirmation = confirmations
+            .issue(operation.clone(), caller.clone(), String::from("summary"))
+            .await
+            .unwrap();
+        assert_eq!(confirmation.to_string(), "summary");
+        assert!(confirmations
+            .redeem("unknown", &operation, Some("operator"))
+            .await
+            .is_err());
+        assert!(confirmations
+            .redeem(&confirmation.token, &operation, Some("operator"))
+            .await
+            .is_ok());
+        assert!(confirmations
+            .redeem(&confirmation.token, &operation, Some("operator"))
+            .await
+            .is_err());
+
+        let confirmation = confirmations
+            .issue(operation.clone(), caller.clone(), String::from("summary"))
+            .await
+            .unwrap();
+        let other = Operation::RemoveServer {
+            server_id: 1,
+            delete_files: false,
+        };
+        assert!(confirmations
+            .redeem(&confirmation.token, &other, Some("operator"))
+            .await
+            .is_err());
+
+        // a token issued to one caller can't be redeemed by another
+        let confirmation = confirmations
+            .issue(operation.clone(), caller, String::from("summary"))
+            .await
+            .unwrap();
+        assert!(confirmations
+            .redeem(&confirmation.token, &operation, Some("monitoring"))
+            .await
+            .is_err());
+        let confirmation = confirmations
+            .issue(operation.clone(), None, String::from("summary"))
+            .await
+            .unwrap();
+        assert!(confirmations
+            .redeem(&confirmation.token, &operation, None)
+            .await
+            .is_ok());
+
+        let expired = Confirmations::new(Duration::from_secs(0));
+        let confirmation = expired
+            .issue(Operation::RevertToLastKnownGood { server_id: 1 }, None, String::new())
+            .await
+            .unwrap();
+        assert!(expired
+            .redeem(
+                &confirmation.token,
+                &Operation::RevertToLastKnownGood { server_id: 1 },
+                None
+            )
+            .await
+            .is_err());
+    }
+}
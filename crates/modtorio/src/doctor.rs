@@ -0,0 +1,392 @@
+//! Provides the `doctor` subcommand, which checks the program's runtime environment for common
+//! problems and prints actionable findings about them.
+
+use crate::{
+    config,
+    mod_portal::ModPortal,
+    opts::Opts,
+    store::{self, MAX_STORE_FILE_PERMISSIONS},
+    util::{file, HumanVersion},
+};
+use bytesize::ByteSize;
+use std::{
+    ffi::{CStr, CString},
+    fmt,
+    fs::File,
+    mem::MaybeUninit,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    sync::Arc,
+};
+
+/// The minimum glibc version the Factorio headless server runs on.
+const MIN_GLIBC_VERSION: HumanVersion = HumanVersion {
+    major: 2,
+    minor: 18,
+    patch: 0,
+};
+/// The recommended minimum for the open file descriptors limit.
+const MIN_OPEN_FILES: u64 = 4096;
+/// The recommended minimum amount of available disk space in bytes (1 GiB).
+const MIN_AVAILABLE_DISK_SPACE: u64 = 1024 * 1024 * 1024;
+
+/// The severity of a single finding.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Severity {
+    /// The check passed.
+    Ok,
+    /// The check found something that may cause problems.
+    Warning,
+    /// The check found something that will cause problems.
+    Error,
+}
+
+/// A single finding from a diagnostics check.
+#[derive(Debug)]
+pub struct Finding {
+    /// The finding's severity.
+    severity: Severity,
+    /// The name of the check that produced this finding.
+    check: &'static str,
+    /// A description of the finding.
+    message: String,
+    /// What the user can do to resolve the finding, if anything.
+    advice: Option<String>,
+}
+
+impl Finding {
+    /// Returns a new passed finding.
+    fn ok(check: &'static str, message: String) -> Self {
+        Self {
+            severity: Severity::Ok,
+            check,
+            message,
+            advice: None,
+        }
+    }
+
+    /// Returns a new warning finding with advice on how to resolve it.
+    fn warning(check: &'static str, message: String, advice: &str) -> Self {
+        Self {
+            severity: Severity::Warning,
+            check,
+            message,
+            advice: Some(advice.to_owned()),
+        }
+    }
+
+    /// Returns a new error finding with advice on how to resolve it.
+    fn error(check: &'static str, message: String, advice: &str) -> Self {
+        Self {
+            severity: Severity::Error,
+            check,
+            message,
+            advice: Some(advice.to_owned()),
+        }
+    }
+
+    /// Returns the finding's severity.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Ok => f.write_str("ok"),
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.check, self.message)?;
+        if let Some(advice) = &self.advice {
+            write!(f, "\n    {}", advice)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs all diagnostics checks, prints their findings to stdout and returns whether no check
+/// resulted in an error.
+pub async fn run(opts: &Opts) -> bool {
+    let findings = diagnose(opts).await;
+
+    for finding in &findings {
+        println!("{}", finding);
+    }
+
+    let errors = findings.iter().filter(|f| f.severity() == Severity::Error).count();
+    let warnings = findings.iter().filter(|f| f.severity() == Severity::Warning).count();
+    println!("{} checks, {} warnings, {} errors", findings.len(), warnings, errors);
+
+    errors == 0
+}
+
+/// Runs all diagnostics checks and returns their findings.
+pub async fn diagnose(opts: &Opts) -> Vec<Finding> {
+    let mut findings = vec![
+        check_glibc(),
+        check_open_files_limit(),
+        check_disk_space(Path::new(".")),
+    ];
+
+    findings.push(check_store(opts));
+    findings.push(check_store_schema(opts));
+
+    match build_config(opts) {
+        Ok(config) => {
+            findings.push(Finding::ok("config", String::from("configuration is valid")));
+            findings.push(check_event_parsers(&config));
+            findings.push(check_portal(config).await);
+        }
+        Err(e) => findings.push(Finding::error(
+            "config",
+            format!("failed to build configuration: {}", e),
+            "Fix the reported value in the config file or the environment variables, or remove the config file to \
+             have a default one created.",
+        )),
+    }
+
+    findings
+}
+
+/// Builds the program config from the config file, the command line and the environment without
+/// touching the program store.
+pub(crate) fn build_config(opts: &Opts) -> anyhow::Result<config::Config> {
+    let mut builder = config::Builder::new();
+
+    if !opts.no_conf && opts.config.exists() {
+        builder = builder.apply_config_file(&mut File::open(&opts.config)?)?;
+    }
+
+    builder = builder.apply_opts(opts);
+
+    if !opts.no_env {
+        builder = builder.apply_env()?;
+    }
+
+    Ok(builder.build())
+}
+
+/// Checks the system's glibc version against the minimum version Factorio requires.
+fn check_glibc() -> Finding {
+    // SAFETY: gnu_get_libc_version returns a pointer to a static, null-terminated string
+    let version = unsafe { CStr::from_ptr(libc::gnu_get_libc_version()) }.to_string_lossy();
+
+    match version.parse::<HumanVersion>() {
+        Ok(version) if version >= MIN_GLIBC_VERSION => Finding::ok("glibc", format!("glibc version {}", version)),
+        Ok(version) => Finding::error(
+            "glibc",
+            format!("glibc version {} is older than {}", version, MIN_GLIBC_VERSION),
+            "The Factorio headless server won't start on this system. Upgrade the system's glibc.",
+        ),
+        Err(e) => Finding::warning(
+            "glibc",
+            format!("failed to parse glibc version '{}': {}", version, e),
+            "Ensure the system's glibc is recent enough to run the Factorio headless server.",
+        ),
+    }
+}
+
+/// Checks the process' open file descriptors limit.
+fn check_open_files_limit() -> Finding {
+    let mut limit = MaybeUninit::<libc::rlimit>::uninit();
+
+    // SAFETY: getrlimit initialises the given struct if it returns 0
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, limit.as_mut_ptr()) } != 0 {
+        return Finding::warning(
+            "ulimit",
+            format!("failed to get open files limit: {}", std::io::Error::last_os_error()),
+            "Ensure the open files limit (ulimit -n) is high enough.",
+        );
+    }
+
+    // SAFETY: the call above succeeded
+    let limit = unsafe { limit.assume_init() };
+    let soft = limit.rlim_cur as u64;
+    if soft >= MIN_OPEN_FILES {
+        Finding::ok("ulimit", format!("open files limit {}", soft))
+    } else {
+        Finding::warning(
+            "ulimit",
+            format!("open files limit {} is lower than {}", soft, MIN_OPEN_FILES),
+            "Servers with many players or mods may run out of file descriptors. Raise the limit with ulimit -n or \
+             LimitNOFILE in the service file.",
+        )
+    }
+}
+
+/// Checks the available disk space in the filesystem a given path is in.
+fn check_disk_space(path: &Path) -> Finding {
+    let c_path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(c_path) => c_path,
+        Err(e) => {
+            return Finding::warning(
+                "disk space",
+                format!("invalid path {}: {}", path.display(), e),
+                "Ensure there is enough disk space available for the servers.",
+            )
+        }
+    };
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: statvfs initialises the given struct if it returns 0
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Finding::warning(
+            "disk space",
+            format!(
+                "failed to get disk space for {}: {}",
+                path.display(),
+                std::io::Error::last_os_error()
+            ),
+            "Ensure there is enough disk space available for the servers.",
+        );
+    }
+
+    // SAFETY: the call above succeeded
+    let stat = unsafe { stat.assume_init() };
+    let available = stat.f_bavail as u64 * stat.f_frsize as u64;
+    if available >= MIN_AVAILABLE_DISK_SPACE {
+        Finding::ok("disk space", format!("{} available", ByteSize::b(available)))
+    } else {
+        Finding::warning(
+            "disk space",
+            format!(
+                "only {} available, less than {}",
+                ByteSize::b(available),
+                ByteSize::b(MIN_AVAILABLE_DISK_SPACE)
+            ),
+            "Saves, mods and Factorio updates may fail to write. Free up disk space.",
+        )
+    }
+}
+
+/// Checks the program store file's permissions.
+fn check_store(opts: &Opts) -> Finding {
+    let path = match store::StoreLocation::from(&opts.store) {
+        store::StoreLocation::Memory => {
+            return Finding::ok("store", String::from("using an in-memory store"));
+        }
+        store::StoreLocation::File(path) => path,
+    };
+
+    if !path.exists() {
+        return Finding::ok(
+            "store",
+            format!("store file {} doesn't exist yet and will be created", path.display()),
+        );
+    }
+
+    match file::ensure_permission(path, MAX_STORE_FILE_PERMISSIONS) {
+        Ok(true) => Finding::ok("store", format!("store file {} permissions are valid", path.display())),
+        Ok(false) => Finding::error(
+            "store",
+            format!(
+                "store file {} permissions are too permissive ({:o}, maximum {:o})",
+                path.display(),
+                file::get_permissions(path).unwrap_or_default() & 0o777,
+                MAX_STORE_FILE_PERMISSIONS
+            ),
+            &format!("Run: chmod 600 {}", path.display()),
+        ),
+        Err(e) => Finding::error(
+            "store",
+            format!("failed to read store file {} permissions: {}", path.display(), e),
+            "Ensure the program has access to the store file.",
+        ),
+    }
+}
+
+/// Checks the program store's schema version against the one the program supports.
+fn check_store_schema(opts: &Opts) -> Finding {
+    let path = match store::StoreLocation::from(&opts.store) {
+        store::StoreLocation::File(path) if path.exists() => path,
+        _ => {
+            return Finding::ok(
+                "store schema",
+                format!(
+                    "a new store will be created with schema version {}",
+                    store::SCHEMA_VERSION
+                ),
+            )
+        }
+    };
+
+    match store::read_schema_version(path) {
+        Ok(Some(version)) if version > store::SCHEMA_VERSION => Finding::error(
+            "store schema",
+            format!(
+                "store schema version {} is newer than the supported version {}",
+                version,
+                store::SCHEMA_VERSION
+            ),
+            "The store was created by a newer version of the program. Upgrade the program or use a different store \
+             file.",
+        ),
+        Ok(Some(version)) if version < store::SCHEMA_VERSION => Finding::warning(
+            "store schema",
+            format!(
+                "store schema version {} is older than the supported version {}",
+                version,
+                store::SCHEMA_VERSION
+            ),
+            "The schema is reapplied when the program starts, which clears the stored data. Back up the store file \
+             first.",
+        ),
+        Ok(Some(version)) => Finding::ok("store schema", format!("store schema version {} is supported", version)),
+        Ok(None) => Finding::ok(
+            "store schema",
+            String::from("store has no schema version yet, it's recorded when the program starts"),
+        ),
+        Err(e) => Finding::error(
+            "store schema",
+            format!("failed to read store schema version: {}", e),
+            "Ensure the store file is a valid program store.",
+        ),
+    }
+}
+
+/// Reports the user-defined event parsers in a given config. The parsers are compiled when the config file is loaded,
+/// so an invalid one is reported as a config error instead.
+fn check_event_parsers(config: &config::Config) -> Finding {
+    Finding::ok(
+        "event parsers",
+        format!("{} event parsers compile", config.event_parsers().len()),
+    )
+}
+
+/// Checks whether the mod portal is reachable. The mod portal isn't contacted in offline mode.
+async fn check_portal(config: config::Config) -> Finding {
+    if config.offline() {
+        return Finding::ok("portal", String::from("offline mode, mod portal not checked"));
+    }
+
+    let portal = match ModPortal::new(Arc::new(config)) {
+        Ok(portal) => portal,
+        Err(e) => {
+            return Finding::error(
+                "portal",
+                format!("failed to create mod portal client: {}", e),
+                "Ensure the system's TLS libraries are installed.",
+            )
+        }
+    };
+
+    let connectivity = portal.check_connectivity().await;
+    if connectivity.passed() {
+        Finding::ok("portal", format!("mod portal reachable ({})", connectivity))
+    } else {
+        Finding::warning(
+            "portal",
+            format!("mod portal unreachable ({})", connectivity),
+            "Mods can't be installed or updated. Check the system's DNS and network configuration, and any firewalls \
+             or proxies between the program and mods.factorio.com.",
+        )
+    }
+}
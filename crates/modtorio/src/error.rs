@@ -1,12 +1,14 @@
 //! Provides all error types the program uses.
 
 use crate::{
-    factorio::{ExecutionStatus, GameStoreId},
-    mod_common::Dependency,
+    auth::Scope,
+    confirmation::Confirmation,
+    factorio::{mods::ResolutionFailure, settings::StartBehaviour, ExecutionStatus, GameStoreId, GracefulAction},
     util::HumanVersion,
 };
 use chrono::{DateTime, Utc};
-use std::path::PathBuf;
+use prost::Message;
+use std::{path::PathBuf, time::Duration};
 use thiserror::Error;
 
 /// Represents all types of errors that can occur when interacting with the mod portal.
@@ -21,6 +23,9 @@ pub enum ModPortalError {
     /// The mod portal responded with an unexpected HTTP error status code.
     #[error("Portal returned unexpected status {0}")]
     UnexpectedStatus(reqwest::StatusCode),
+    /// Returned instead of contacting the mod portal when the program runs in offline mode.
+    #[error("The mod portal isn't contacted in offline mode")]
+    OfflineMode,
 }
 
 /// Represents all types of errors that can occur when transforming paths.
@@ -42,6 +47,30 @@ pub enum ResponseError {
     /// file name.
     #[error("Response URL doesn't have a filename component")]
     NoFilename,
+    /// Returned when a response body ends before the length given in its `Content-Length` header.
+    #[error("Response body truncated: received {received} of {expected} bytes")]
+    Truncated {
+        /// The length given in the response's `Content-Length` header.
+        expected: u64,
+        /// The amount of bytes received.
+        received: u64,
+    },
+    /// Returned when a response body is longer than the length given in its `Content-Length` header.
+    #[error("Response body longer than expected: received at least {received} of {expected} bytes")]
+    Oversized {
+        /// The length given in the response's `Content-Length` header.
+        expected: u64,
+        /// The amount of bytes received before aborting.
+        received: u64,
+    },
+}
+
+impl ResponseError {
+    /// Returns whether this error means the response body was cut short or otherwise didn't match its expected
+    /// length, in which case retrying the request may succeed.
+    pub fn is_length_mismatch(&self) -> bool {
+        matches!(self, ResponseError::Truncated { .. } | ResponseError::Oversized { .. })
+    }
 }
 
 /// Represents all types of errors that can occur when working with zip files.
@@ -58,19 +87,22 @@ pub enum ModError {
     /// Returned when looking up a non-existent mod from a game's mods.
     #[error("No such mod: {0}")]
     NoSuchMod(String),
+    /// Returned when removing a version of a mod that has no archive of that version in the mods directory.
+    #[error("No archive of mod {name} ver. {version}")]
+    NoSuchArchive {
+        /// The mod's name.
+        name: String,
+        /// The version without an archive.
+        version: HumanVersion,
+    },
     /// Returned when there already exists a mod with the same name when loading mods from the
     /// filesystem (likely means there are multiple versions of the same mod).
     #[error("Duplicate mod: {0}")]
     DuplicateMod(String),
-    /// Returned when a mod's dependency cannot be ensured (the mod is incompatible with some other
-    /// installed mod).
-    #[error("Cannot ensure dependency {dependency} of {mod_display}")]
-    CannotEnsureDependency {
-        /// The dependency that failed.
-        dependency: Dependency,
-        /// The mod's friendly display.
-        mod_display: String,
-    },
+    /// Returned when the installed mods' dependencies cannot be resolved (a mod is incompatible with some other
+    /// installed mod, or a dependency is unmet even after installing the missing mods).
+    #[error("Cannot resolve mod dependencies: {0}")]
+    UnresolvableDependencies(ResolutionFailure),
     /// A game's mod doesn't have its archive zip path set (it likely isn't installed).
     #[error("No zip path set (is the mod installed?)")]
     MissingZipPath,
@@ -122,6 +154,34 @@ pub enum ModError {
     /// A mod doesn't have any releases when searching for a certain release.
     #[error("No releases")]
     NoReleases,
+    /// Returned when installing a mod that has no release for the server's Factorio version.
+    #[error("Mod {name} has no release for Factorio {game_version}")]
+    NoCompatibleRelease {
+        /// The mod's name.
+        name: String,
+        /// The server's Factorio version.
+        game_version: HumanVersion,
+    },
+    /// Returned when installing a mod release that is for a different Factorio version than the server's.
+    #[error("Mod {name} ver. {version} is for Factorio {factorio_version}, not {game_version}")]
+    IncompatibleRelease {
+        /// The mod's name.
+        name: String,
+        /// The release's version.
+        version: HumanVersion,
+        /// The Factorio version the release is for.
+        factorio_version: HumanVersion,
+        /// The server's Factorio version.
+        game_version: HumanVersion,
+    },
+    /// Returned when installing a mod the game's mod policy excludes.
+    #[error("Mod {name} excluded by the mod policy: {reason}")]
+    ExcludedByPolicy {
+        /// The mod's name.
+        name: String,
+        /// Why the mod is excluded.
+        reason: String,
+    },
     /// The mod portal's mod info response doesn't contain a critical field.
     #[error("Missing critical field in mod portal response: {0}")]
     MissingField(&'static str),
@@ -148,7 +208,7 @@ pub enum DependencyParsingError {
     #[error("Invalid requirement string: {0}")]
     InvalidRequirementString(String),
     /// A given dependency string has an invalid version requirement portion.
-    #[error("Invalid version requirement string")]
+    #[error("Invalid version requirement string: {0}")]
     InvalidVersionRequirementString(#[from] HumanVersionError),
 }
 
@@ -189,6 +249,31 @@ pub enum StoreError {
         /// The database file's actual permissions.
         actual: u32,
     },
+    /// Returned when opening a program store whose schema is newer than the one this program version supports.
+    #[error(
+        "Store schema version {stored} is newer than the supported version {supported}. The store was created by a \
+         newer version of the program"
+    )]
+    NewerSchema {
+        /// The store's schema version.
+        stored: u32,
+        /// The schema version this program version supports.
+        supported: u32,
+    },
+    /// Returned when trying to change the program store while its database is read-only.
+    #[error("The store is read-only, changes can't be stored")]
+    ReadOnly,
+    /// Returned when opening a program store that another program instance is already using.
+    #[error(
+        "The store is already in use by another Modtorio instance (process {pid}, lock file {path}). Only one \
+         instance can use a store at a time"
+    )]
+    Locked {
+        /// Path to the store's lock file.
+        path: PathBuf,
+        /// The process ID written in the lock file by the instance holding the lock.
+        pid: String,
+    },
 }
 
 /// Represesnts all types of errors that correspond to invalid configuration.
@@ -198,6 +283,21 @@ pub enum ConfigError {
     /// in its config.
     #[error("No listen addresses specified")]
     NoListenAddresses,
+    /// Returned when a Unix socket listen address' directory doesn't exist.
+    #[error("The directory of the socket '{0}' doesn't exist")]
+    MissingSocketDirectory(PathBuf),
+    /// Returned when the configured socket mode isn't a valid octal file mode.
+    #[error("Invalid socket mode '{0}', expected an octal file mode such as \"0660\"")]
+    InvalidSocketMode(String),
+    /// Returned when the configured socket owner doesn't exist.
+    #[error("No such socket owner user: {0}")]
+    NoSuchUser(String),
+    /// Returned when the configured socket group doesn't exist.
+    #[error("No such socket group: {0}")]
+    NoSuchGroup(String),
+    /// Returned when the mod release webhook has listen addresses but no shared secret configured.
+    #[error("The mod release webhook has listen addresses but no webhook_secret configured")]
+    MissingWebhookSecret,
 }
 
 /// Represents all types of errors that can occur in RPC calls.
@@ -206,6 +306,23 @@ pub enum RpcError {
     /// Returned when trying to interact with a non-existent game.
     #[error("No such game ID: {0}")]
     NoSuchGame(GameStoreId),
+    /// Returned when addressing a game by a name no game has.
+    #[error("No game is named {0}")]
+    NoSuchGameName(String),
+    /// Returned when a request addresses a game both by an ID and by a name that belongs to a different game.
+    #[error("Game ID {server_id} isn't named {name}")]
+    ConflictingGameAddress {
+        /// The addressed game ID.
+        server_id: GameStoreId,
+        /// The addressed name.
+        name: String,
+    },
+    /// Returned when naming a game with a name that isn't valid.
+    #[error("Invalid game name: {0}")]
+    InvalidGameName(String),
+    /// Returned when naming a game with a name another game already has.
+    #[error("Another game is already named {0}")]
+    GameNameTaken(String),
     /// Returned when trying to import a Factorio server instance which is already managed by the Modtorio instance.
     #[error("A game in the root directory '{0}' is already managed by this Modtorio instance")]
     GameAlreadyExists(PathBuf),
@@ -223,6 +340,170 @@ pub enum RpcError {
     /// Returned when trying to run an invalid command.
     #[error("No such command identifier: {0}")]
     NoSuchCommand(i32),
+    /// Returned when requesting an invalid kind of raw server file.
+    #[error("No such raw server file kind: {0}")]
+    NoSuchRawFileKind(i32),
+    /// Returned when filtering a server's events by an invalid event kind.
+    #[error("No such server event kind: {0}")]
+    NoSuchEventKind(i32),
+    /// Returned when setting a mod's update policy to an invalid policy.
+    #[error("No such mod update policy: {0}")]
+    NoSuchUpdatePolicy(i32),
+    /// Returned when resolving a settings conflict in favour of an invalid source.
+    #[error("No such settings source: {0}")]
+    NoSuchSettingsSource(i32),
+    /// Returned when trying to install a mod the game's mod policy excludes.
+    #[error("{0}")]
+    ExcludedByPolicy(String),
+    /// Returned when trying to install a mod release that isn't for the server's Factorio version.
+    #[error("{0}")]
+    IncompatibleRelease(String),
+    /// Returned when a required request argument is missing or empty.
+    #[error("Missing required argument: {0}")]
+    MissingArgument(&'static str),
+    /// Returned when trying to set a non-existent feature flag.
+    #[error("No such feature flag: {0}")]
+    NoSuchFeatureFlag(String),
+    /// Returned when using a mod RPC for a game whose mods Modtorio doesn't manage.
+    #[error("The mods of game ID {0} aren't managed by Modtorio")]
+    ModsUnmanaged(GameStoreId),
+    /// Returned when accessing or resolving a game's settings conflict when there is none.
+    #[error("The settings of game ID {0} don't conflict")]
+    NoSettingsConflict(GameStoreId),
+    /// Returned when addressing, importing or installing a game while the instance runs as a mod portal proxy.
+    #[error("The instance runs as a mod portal proxy and doesn't manage servers")]
+    PortalProxyMode,
+    /// Returned when setting a game's settings based on a version of them that has since been changed.
+    #[error("The settings of game ID {0} have been changed since they were read, read them again")]
+    StaleSettings(GameStoreId),
+    /// Returned when a game's mod dependencies cannot be resolved. The failure is included in the status details.
+    #[error("Cannot resolve mod dependencies: {0}")]
+    UnresolvableDependencies(ResolutionFailure),
+    /// Returned when trying to import an invalid map exchange string.
+    #[error("Invalid map exchange string: {0}")]
+    InvalidMapExchangeString(MapExchangeError),
+    /// Returned when parsing an invalid version requirement string.
+    #[error("Invalid version requirement: {0}")]
+    InvalidVersionRequirement(HumanVersionError),
+    /// Returned when parsing an invalid dependency string.
+    #[error("Invalid dependency: {0}")]
+    InvalidDependencyString(DependencyParsingError),
+    /// Returned when trying to set an invalid schedule.
+    #[error(transparent)]
+    InvalidSchedule(SettingsError),
+    /// Returned when exporting a game's map exchange string before one has been imported.
+    #[error("Game ID {0} has no imported map exchange string")]
+    NoMapExchangeString(GameStoreId),
+    /// Returned when trying to start or stop a game while a graceful action is waiting to be carried out on it.
+    #[error("Game ID {server_id} has a pending {action:?} action")]
+    OperationInProgress {
+        /// The game's ID.
+        server_id: GameStoreId,
+        /// The pending action.
+        action: GracefulAction,
+    },
+    /// Returned when a request isn't authorized.
+    #[error(transparent)]
+    Unauthorized(AuthError),
+    /// Returned when retrying a game's store update when its stored state isn't out of date.
+    #[error("The store of game ID {0} isn't dirty")]
+    StoreNotDirty(GameStoreId),
+    /// Returned when trying to install a server into a directory that isn't empty.
+    #[error("The directory '{0}' isn't empty")]
+    InstallRootNotEmpty(PathBuf),
+    /// Returned when trying to upgrade a server to a version that isn't newer than its installed one.
+    #[error("Version {target} isn't newer than the installed version {installed}")]
+    NotAnUpgrade {
+        /// The server's installed version.
+        installed: HumanVersion,
+        /// The version the server was tried to be upgraded to.
+        target: HumanVersion,
+    },
+    /// Returned when using a Factorio version that isn't installed in the version pool.
+    #[error("Factorio {0} isn't installed in the version pool")]
+    NoSuchPooledVersion(HumanVersion),
+    /// Returned when installing a Factorio version into the version pool that's already installed there.
+    #[error("Factorio {0} is already installed in the version pool")]
+    PooledVersionExists(HumanVersion),
+    /// Returned when removing a Factorio version from the version pool while a game uses it.
+    #[error("Factorio {version} in the version pool is used by game ID {server_id}")]
+    PooledVersionInUse {
+        /// The version.
+        version: HumanVersion,
+        /// The ID of a game using the version.
+        server_id: GameStoreId,
+    },
+    /// Returned when deleting a game's mods directory that isn't inside the game's root directory.
+    #[error("Refusing to delete the mods directory {0} outside the server's root directory")]
+    ModsDirectoryOutsideRoot(PathBuf),
+    /// Returned when deleting a game's mods directory that another managed game uses as well.
+    #[error("Refusing to delete the mods directory {path} also used by game ID {server_id}")]
+    SharedModsDirectory {
+        /// The mods directory.
+        path: PathBuf,
+        /// The ID of the other game using the directory.
+        server_id: GameStoreId,
+    },
+    /// Returned when a requested time range is empty or out of range.
+    #[error("Invalid time range from {start} to {end}")]
+    InvalidTimeRange {
+        /// The range's start as a Unix timestamp.
+        start: i64,
+        /// The range's end as a Unix timestamp.
+        end: i64,
+    },
+    /// Returned when a given page token wasn't returned with an earlier page.
+    #[error("Invalid page token: {0}")]
+    InvalidPageToken(String),
+    /// Returned when trying to restore a non-existent backup.
+    #[error("No such backup: {0}")]
+    NoSuchBackup(String),
+    /// Returned when trying to apply or remove a non-existent mod preset.
+    #[error("No such mod preset: {0}")]
+    NoSuchModPreset(String),
+    /// Returned when a modpack manifest name isn't a plain file name in the modpack directory.
+    #[error("Invalid modpack name: {0}. Expected a file name in the modpack directory")]
+    InvalidModpackName(String),
+    /// Returned when a modpack manifest to apply can't be read.
+    #[error("Invalid modpack: {0}")]
+    InvalidModpack(String),
+    /// Returned when trying to remove a version of a mod that has no archive of that version.
+    #[error("No archive of mod {name} ver. {version}")]
+    NoSuchModArchive {
+        /// The mod's name.
+        name: String,
+        /// The version without an archive.
+        version: HumanVersion,
+    },
+    /// Returned when reverting a game's mods before it has started successfully.
+    #[error("Game ID {0} has no last known good mods")]
+    NoLastKnownGoodMods(GameStoreId),
+    /// Returned when syncing mods onto the server they're synced from.
+    #[error("Cannot sync the mods of server ID {0} onto itself")]
+    SyncOntoSource(GameStoreId),
+    /// Returned when a requested save name isn't a plain file name.
+    #[error("Invalid save name: {0}")]
+    InvalidSaveName(String),
+    /// Returned when given new map settings aren't valid JSON in the game's formats.
+    #[error("Invalid map settings: {0}")]
+    InvalidMapSettings(String),
+    /// Returned when a request needs to change the program store while its database is read-only.
+    #[error("The store is read-only, changes can't be stored until the program is restarted with a writable store")]
+    StoreReadOnly,
+    /// Returned when a request needs network access while the program runs in offline mode.
+    #[error("Network access is disabled in offline mode")]
+    OfflineMode,
+    /// Returned when a long-running request is stopped early because the program is shutting down.
+    #[error("The program is shutting down, the operation was interrupted")]
+    ShuttingDown,
+    /// Returned when a destructive request is made without a confirmation token. A new token for the request's
+    /// operation is included in the status details.
+    #[error("The operation has to be confirmed: {0}")]
+    ConfirmationRequired(Confirmation),
+    /// Returned when a destructive request's confirmation token doesn't exist, has expired or was issued for a
+    /// different operation.
+    #[error("Invalid or expired confirmation token")]
+    InvalidConfirmationToken,
     /// Returned when an unknown or internal error occurred.
     #[error("An internal error occurred: {0}")]
     Internal(#[from] anyhow::Error),
@@ -238,11 +519,72 @@ impl From<&RpcError> for tonic::Status {
     fn from(e: &RpcError) -> Self {
         match e {
             RpcError::Internal(int) => tonic::Status::internal(int.to_string()),
-            RpcError::NoSuchMod(_) | RpcError::NoSuchGame(_) | RpcError::NoSuchCommand(_) => {
-                tonic::Status::invalid_argument(e.to_string())
+            RpcError::NoSuchMod(_)
+            | RpcError::NoSuchGame(_)
+            | RpcError::NoSuchGameName(_)
+            | RpcError::ConflictingGameAddress { .. }
+            | RpcError::InvalidGameName(_)
+            | RpcError::NoSuchCommand(_)
+            | RpcError::NoSuchRawFileKind(_)
+            | RpcError::NoSuchEventKind(_)
+            | RpcError::NoSuchUpdatePolicy(_)
+            | RpcError::NoSuchSettingsSource(_)
+            | RpcError::MissingArgument(_)
+            | RpcError::InvalidMapExchangeString(_)
+            | RpcError::InvalidVersionRequirement(_)
+            | RpcError::InvalidDependencyString(_)
+            | RpcError::InvalidSchedule(_)
+            | RpcError::NoSuchFeatureFlag(_)
+            | RpcError::NoSuchBackup(_)
+            | RpcError::NoSuchModPreset(_)
+            | RpcError::NoSuchPooledVersion(_)
+            | RpcError::InvalidTimeRange { .. }
+            | RpcError::InvalidPageToken(_)
+            | RpcError::NoSuchModArchive { .. }
+            | RpcError::InvalidSaveName(_)
+            | RpcError::InvalidMapSettings(_)
+            | RpcError::SyncOntoSource(_)
+            | RpcError::InvalidConfirmationToken
+            | RpcError::InvalidModpackName(_)
+            | RpcError::InvalidModpack(_) => tonic::Status::invalid_argument(e.to_string()),
+            RpcError::GameAlreadyExists(_) | RpcError::GameNameTaken(_) | RpcError::PooledVersionExists(_) => {
+                tonic::Status::already_exists(e.to_string())
+            }
+            RpcError::Unauthorized(AuthError::MissingScope { .. }) => tonic::Status::permission_denied(e.to_string()),
+            RpcError::Unauthorized(_) => tonic::Status::unauthenticated(e.to_string()),
+            RpcError::OperationInProgress { .. } | RpcError::StaleSettings(_) => tonic::Status::aborted(e.to_string()),
+            RpcError::StoreReadOnly | RpcError::ShuttingDown => tonic::Status::unavailable(e.to_string()),
+            RpcError::InvalidInstanceStatus { .. }
+            | RpcError::ExcludedByPolicy(_)
+            | RpcError::IncompatibleRelease(_)
+            | RpcError::ModsUnmanaged(_)
+            | RpcError::NoSettingsConflict(_)
+            | RpcError::StoreNotDirty(_)
+            | RpcError::InstallRootNotEmpty(_)
+            | RpcError::NotAnUpgrade { .. }
+            | RpcError::PooledVersionInUse { .. }
+            | RpcError::ModsDirectoryOutsideRoot(_)
+            | RpcError::SharedModsDirectory { .. }
+            | RpcError::NoMapExchangeString(_)
+            | RpcError::NoLastKnownGoodMods(_)
+            | RpcError::OfflineMode
+            | RpcError::PortalProxyMode => tonic::Status::failed_precondition(e.to_string()),
+            RpcError::UnresolvableDependencies(failure) => {
+                let mut details = Vec::new();
+                rpc::ResolutionFailure::from(failure)
+                    .encode(&mut details)
+                    .expect("failed to encode resolution failure");
+
+                tonic::Status::with_details(tonic::Code::FailedPrecondition, e.to_string(), details.into())
+            }
+            RpcError::ConfirmationRequired(confirmation) => {
+                let mut details = Vec::new();
+                rpc::ConfirmationRequired::from(confirmation)
+                    .encode(&mut details)
+                    .expect("failed to encode confirmation");
+
+                tonic::Status::with_details(tonic::Code::FailedPrecondition, e.to_string(), details.into())
             }
-            RpcError::GameAlreadyExists(_) => tonic::Status::already_exists(e.to_string()),
-            RpcError::InvalidInstanceStatus { .. } => tonic::Status::failed_precondition(e.to_string()),
         }
     }
 }
@@ -283,12 +625,177 @@ pub enum ExecutableError {
     NoStdioHandle,
 }
 
+/// Represents all types of errors that can occur when using a bounded channel.
+#[derive(Debug, Error)]
+pub enum ChannelError {
+    /// Returned when sending into a channel whose receiver has been dropped.
+    #[error("The channel's receiver has been dropped")]
+    Closed,
+}
+
+/// Represents all types of errors that can occur when calculating a checksum.
+#[derive(Debug, Error)]
+pub enum ChecksumError {
+    /// Returned when the calculation is cancelled before the whole input is hashed.
+    #[error("The checksum calculation was cancelled")]
+    Cancelled,
+}
+
 /// Represents all types of errors that can occur when loading or saving the server's settings.
 #[derive(Debug, Error)]
 pub enum SettingsError {
     /// A field has an unexpected value.
     #[error("Unexpected value in settings: {0}")]
     UnexpectedValue(String),
+    /// A custom location isn't an existing directory.
+    #[error("The {kind} location {path} isn't an existing directory")]
+    InvalidDirectory {
+        /// The kind of the location.
+        kind: &'static str,
+        /// The location.
+        path: PathBuf,
+    },
+    /// An included settings file can't be read or isn't a JSON object.
+    #[error("Invalid included settings file {path}: {reason}")]
+    InvalidInclude {
+        /// The file's path.
+        path: PathBuf,
+        /// Why the file is invalid.
+        reason: String,
+    },
+    /// An unknown setting passed through as-is has an invalid name or value.
+    #[error("Invalid extra setting '{name}': {reason}")]
+    InvalidExtraField {
+        /// The setting's name.
+        name: String,
+        /// Why the setting is invalid.
+        reason: String,
+    },
+    /// A start behaviour that loads a given save or scenario has no save or scenario name.
+    #[error("The start behaviour {0} needs a save or scenario name")]
+    MissingSaveName(StartBehaviour),
+    /// A pooled game version isn't a valid version.
+    #[error("Invalid game version: {0}")]
+    InvalidGameVersion(String),
+    /// An autosave policy's thresholds are invalid.
+    #[error("Invalid autosave policy: {0}")]
+    InvalidAutosavePolicy(String),
+    /// A schedule isn't a valid cron expression.
+    #[error("Invalid schedule '{expression}': {reason}")]
+    InvalidSchedule {
+        /// The schedule's expression.
+        expression: String,
+        /// Why the expression is invalid.
+        reason: String,
+    },
+}
+
+/// Represents all types of errors that can occur when parsing a map exchange string.
+#[derive(Debug, Error)]
+pub enum MapExchangeError {
+    /// Returned when the string isn't base64-encoded zlib-compressed data wrapped in `>>>` and `<<<`.
+    #[error("The string isn't a map exchange string")]
+    InvalidFormat,
+    /// Returned when the string's data ends unexpectedly.
+    #[error("The map exchange string's data is truncated")]
+    Truncated,
+    /// Returned when the string's data inflates to more than a given amount of bytes.
+    #[error("The map exchange string's data is larger than {0} bytes")]
+    TooLarge(u64),
+    /// Returned when the string's data has an invalid value of a given type.
+    #[error("The map exchange string's data has an invalid {0}")]
+    InvalidValue(&'static str),
+    /// Returned when the string's checksum doesn't match its data.
+    #[error("The map exchange string's checksum {expected:08x} doesn't match its data's checksum {actual:08x}")]
+    ChecksumMismatch {
+        /// The checksum in the string.
+        expected: u32,
+        /// The checksum of the string's data.
+        actual: u32,
+    },
+}
+
+/// Represents all types of errors that can occur with per-server mod portal credentials.
+#[derive(Debug, Error)]
+pub enum CredentialError {
+    /// Returned when per-server credentials are used without a credential key configured.
+    #[error("No credential key configured. Set one in the MODTORIO_CREDENTIAL_KEY environment variable")]
+    NoKey,
+    /// Returned when the configured credential key isn't 32 bytes as a hex string.
+    #[error("The credential key isn't 32 bytes as a hex string")]
+    InvalidKey,
+    /// Returned when encrypting credentials fails.
+    #[error("Failed to encrypt credentials")]
+    Encryption,
+    /// Returned when decrypting stored credentials fails, because the key is wrong or the stored credentials are
+    /// corrupt.
+    #[error("Failed to decrypt credentials")]
+    Decryption,
+}
+
+/// Represents all types of errors that can occur when authorizing RPC requests.
+#[derive(Debug, Error)]
+pub enum AuthError {
+    /// Returned when a request has no bearer token in its `authorization` metadata.
+    #[error("Missing bearer token in the authorization metadata")]
+    MissingToken,
+    /// Returned when a request's bearer token doesn't match any stored token.
+    #[error("Invalid bearer token")]
+    InvalidToken,
+    /// Returned when a request's token doesn't authorize the scope the request requires.
+    #[error("Token '{name}' lacks the '{scope}' scope")]
+    MissingScope {
+        /// The token's name.
+        name: String,
+        /// The scope the request requires.
+        scope: Scope,
+    },
+    /// Returned when parsing an unknown scope.
+    #[error("No such scope: {0}")]
+    NoSuchScope(String),
+    /// Returned when creating a token with a name that's already in use.
+    #[error("A token named '{0}' already exists")]
+    TokenExists(String),
+}
+
+/// Represents all types of errors that can occur when backing up or restoring a server.
+#[derive(Debug, Error)]
+pub enum BackupError {
+    /// Returned when a backup archive has an entry that isn't one of the backed up files.
+    #[error("Unexpected entry in backup archive: {0}")]
+    UnexpectedEntry(PathBuf),
+}
+
+/// Represents all types of errors that can occur when exporting or applying modpacks.
+#[derive(Debug, Error)]
+pub enum ModpackError {
+    /// Returned when a modpack manifest's path doesn't have a `.json` or `.toml` extension.
+    #[error("Unknown modpack format (expected a .json or .toml file): {0}")]
+    UnknownFormat(PathBuf),
+}
+
+/// Represents all types of errors that can occur when installing Factorio headless server releases.
+#[derive(Debug, Error)]
+pub enum InstallerError {
+    /// Returned when factorio.com doesn't list a checksum for a downloaded release archive.
+    #[error("No checksum found for {0}")]
+    NoChecksum(String),
+    /// Returned when a downloaded release archive's checksum doesn't match the one factorio.com lists.
+    #[error("Checksum mismatch for {file_name}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        /// The release archive's file name.
+        file_name: String,
+        /// The checksum factorio.com lists.
+        expected: String,
+        /// The downloaded archive's checksum.
+        actual: String,
+    },
+    /// Returned when a release archive has an entry outside its top-level directory.
+    #[error("Unexpected entry in release archive: {0}")]
+    UnexpectedEntry(PathBuf),
+    /// Returned instead of contacting factorio.com when the program runs in offline mode.
+    #[error("factorio.com isn't contacted in offline mode")]
+    OfflineMode,
 }
 
 /// Represents all types of errors that can occur when using the update batcher.
@@ -308,6 +815,34 @@ pub enum ServerError {
     /// Returned when trying to access a server's store ID when it hasn't been added to the store yet.
     #[error("The game hasn't been added to the program store yet")]
     GameNotInStore,
+    /// Returned when trying to resolve a settings conflict when the server's settings don't conflict.
+    #[error("The game's settings file doesn't conflict with its stored settings")]
+    NoSettingsConflict,
+    /// Returned when trying to write the game's settings to its settings file while they conflict with it.
+    #[error("The game's settings file conflicts with its stored settings, resolve the conflict first")]
+    SettingsConflict,
+    /// Returned when trying to load the latest save from a saves directory without any saves.
+    #[error("There are no saves in {0}")]
+    NoSaves(PathBuf),
+    /// Returned when the game's script output directory has to be replaced with a link to a custom script output
+    /// location but it has files in it.
+    #[error("The game's script output directory {0} isn't empty, so it can't be linked to the custom location")]
+    ScriptOutputInUse(PathBuf),
+    /// Returned when the game doesn't finish a requested save within the saving timeout.
+    #[error("The game didn't finish saving within {0:?}")]
+    SaveTimedOut(Duration),
+    /// Returned when the game doesn't shut down within a given timeout.
+    #[error("The game didn't shut down within {0:?}")]
+    ShutdownTimedOut(Duration),
+    /// Returned when the game exits before finishing a requested save.
+    #[error("The game exited before it finished saving")]
+    ExitedWhileSaving,
+    /// Returned when trying to create a save that already exists.
+    #[error("The save {0} already exists")]
+    SaveExists(PathBuf),
+    /// Returned when trying to prune the game's autosaves while it's saving its map.
+    #[error("The game is saving its map")]
+    Saving,
 }
 
 /// Represents the parser error for `GameEvent`.
@@ -316,4 +851,12 @@ pub enum GameEventError {
     /// Returned when a given string failed to be parsed into a `GameEvent`.
     #[error("The line '{0}' failed to be parsed into a GameEvent")]
     FailedToParse(String),
+    /// Returned when a user-defined event parser's definition is invalid.
+    #[error("Invalid event parser '{name}': {reason}")]
+    InvalidParser {
+        /// The parser's name.
+        name: String,
+        /// Why the definition is invalid.
+        reason: String,
+    },
 }
@@ -0,0 +1,282 @@
+//! Provides the [`ServerEventPage`](ServerEventPage) object, a page of a server's audit trail. The notable events of
+//! the running servers, players joining and leaving, ban actions, chat messages, crashes, hook script runs, the
+//! events of user-defined event parsers, autosave prunings and stuck map saves, are recorded into the program store
+//! as they happen so the trail survives restarts, and read back a page at a time, the newest first. A running
+//! server's events are recorded through a [`Recorder`](Recorder), which writes them in the background so the server's
+//! output keeps being processed meanwhile.
+
+use crate::{
+    error::RpcError,
+    factorio::GameStoreId,
+    store::{models::ServerEvent, Store},
+    util::{self, TimeZone},
+};
+use chrono::{DateTime, Utc};
+use log::*;
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, Value, ValueRef},
+    ToSql,
+};
+use std::{str::FromStr, sync::Arc};
+use strum_macros::{Display, EnumString};
+use tokio::{sync::mpsc, task};
+
+/// The amount of events in a page if the request doesn't specify it.
+const DEFAULT_PAGE_SIZE: u32 = 100;
+/// The largest amount of events in a page.
+const MAX_PAGE_SIZE: u32 = 1000;
+
+/// The kinds of a server's events kept in its audit trail.
+#[derive(Debug, PartialEq, Copy, Clone, EnumString, Display)]
+pub enum EventKind {
+    /// A player joined the game.
+    Join,
+    /// A player left the game.
+    Leave,
+    /// The ban action was taken for an address whose connections were repeatedly refused.
+    Ban,
+    /// A message was sent to the chat.
+    Chat,
+    /// The server's executable exited with an error.
+    Crash,
+    /// A hook script was run.
+    Hook,
+    /// An output line matched a user-defined event parser.
+    Custom,
+    /// Old autosaves were pruned.
+    Prune,
+    /// The server was stuck saving its map.
+    SaveStuck,
+}
+
+/// Records a game's events into its event history in a background task. The events are buffered in an unbounded
+/// channel, so recording one never waits on the program store. Every clone records into the same task, which stops
+/// once every clone has been dropped and the buffered events have been written.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    /// The game whose events are recorded.
+    game: GameStoreId,
+    /// Sends the events to the background task.
+    tx: mpsc::UnboundedSender<ServerEvent>,
+}
+
+/// A page of a server's events, the most recently recorded first.
+#[derive(Debug, PartialEq)]
+pub struct ServerEventPage {
+    /// The events in the page.
+    events: Vec<ServerEvent>,
+    /// The ID of the last event in the page if there are more events after it.
+    next: Option<i64>,
+}
+
+impl ServerEventPage {
+    /// Loads a page of a given game's events of given kinds, or of every kind if none are given, that happened at or
+    /// after a given start and before a given end from the program store. The page is at most a given size, where
+    /// zero means the default size, and continues from a given page token, where an empty token starts from the most
+    /// recently recorded event. Returns [`RpcError::InvalidPageToken`](RpcError::InvalidPageToken) if the token isn't
+    /// one returned with an earlier page.
+    pub async fn load(
+        store: &Store,
+        game: GameStoreId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        kinds: &[EventKind],
+        page_size: u32,
+        page_token: &str,
+    ) -> anyhow::Result<Self> {
+        let before = parse_page_token(page_token)?;
+        let page_size = effective_page_size(page_size);
+
+        // one more event than fits in the page tells whether there's a next page
+        let mut events = store
+            .get_server_events(game, start, end, kinds, before, page_size + 1)
+            .await?;
+        let next = if events.len() > page_size as usize {
+            events.truncate(page_size as usize);
+            events.last().map(|event| event.id)
+        } else {
+            None
+        };
+
+        Ok(Self { events, next })
+    }
+
+    /// Returns the page in its RPC format, with the events' times also formatted in a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::ServerEventPage {
+        rpc::ServerEventPage {
+            events: self
+                .events
+                .iter()
+                .map(|event| rpc::ServerEvent {
+                    kind: rpc::server_event::Kind::from(event.kind).into(),
+                    occurred_at: event.occurred_at.timestamp(),
+                    occurred_at_local: time_zone.format(event.occurred_at),
+                    username: event.username.clone().unwrap_or_default(),
+                    message: event.message.clone(),
+                })
+                .collect(),
+            next_page_token: self.next.map_or_else(String::new, |id| id.to_string()),
+        }
+    }
+}
+
+impl ToSql for EventKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Text(self.to_string())))
+    }
+}
+
+impl FromSql for EventKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match EventKind::from_str(value.as_str()?) {
+            Ok(v) => Ok(v),
+            Err(_) => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl From<rpc::server_event::Kind> for EventKind {
+    fn from(kind: rpc::server_event::Kind) -> Self {
+        match kind {
+            rpc::server_event::Kind::Join => Self::Join,
+            rpc::server_event::Kind::Leave => Self::Leave,
+            rpc::server_event::Kind::Ban => Self::Ban,
+            rpc::server_event::Kind::Chat => Self::Chat,
+            rpc::server_event::Kind::Crash => Self::Crash,
+            rpc::server_event::Kind::Hook => Self::Hook,
+            rpc::server_event::Kind::Custom => Self::Custom,
+            rpc::server_event::Kind::Prune => Self::Prune,
+            rpc::server_event::Kind::SaveStuck => Self::SaveStuck,
+        }
+    }
+}
+
+impl From<EventKind> for rpc::server_event::Kind {
+    fn from(kind: EventKind) -> Self {
+        match kind {
+            EventKind::Join => Self::Join,
+            EventKind::Leave => Self::Leave,
+            EventKind::Ban => Self::Ban,
+            EventKind::Chat => Self::Chat,
+            EventKind::Crash => Self::Crash,
+            EventKind::Hook => Self::Hook,
+            EventKind::Custom => Self::Custom,
+            EventKind::Prune => Self::Prune,
+            EventKind::SaveStuck => Self::SaveStuck,
+        }
+    }
+}
+
+impl Recorder {
+    /// Returns a new `Recorder` that records a given game's events into a given program store. Nothing is recorded if
+    /// the store is read-only.
+    pub fn spawn(store: Arc<Store>, game: GameStoreId) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<ServerEvent>();
+        task::spawn(util::correlation::for_server(game, async move {
+            while let Some(event) = rx.recv().await {
+                if store.is_read_only() {
+                    continue;
+                }
+
+                let kind = event.kind;
+                if let Err(e) = store.add_server_event(event).await {
+                    error!("Game ID {} failed to record {} event: {}", game, kind, e);
+                }
+            }
+        }));
+
+        Self { game, tx }
+    }
+
+    /// Records a given event of a given kind that happened at a given time in the background.
+    pub fn record(&self, kind: EventKind, occurred_at: DateTime<Utc>, username: Option<&str>, message: &str) {
+        let event = ServerEvent {
+            id: 0,
+            game: self.game,
+            kind,
+            occurred_at,
+            username: username.map(str::to_owned),
+            message: message.to_owned(),
+        };
+
+        // the task only stops once every sender is dropped
+        self.tx.send(event).ok();
+    }
+}
+
+/// Records a given event of a given kind that happened in a given game at a given time into the program store.
+pub async fn record(
+    store: &Store,
+    game: GameStoreId,
+    kind: EventKind,
+    occurred_at: DateTime<Utc>,
+    username: Option<&str>,
+    message: &str,
+) -> anyhow::Result<()> {
+    store
+        .add_server_event(ServerEvent {
+            id: 0,
+            game,
+            kind,
+            occurred_at,
+            username: username.map(str::to_owned),
+            message: message.to_owned(),
+        })
+        .await
+}
+
+/// Returns the page size to use for a given requested page size, where zero means the default size.
+fn effective_page_size(requested: u32) -> u32 {
+    if requested == 0 {
+        DEFAULT_PAGE_SIZE
+    } else {
+        requested.min(MAX_PAGE_SIZE)
+    }
+}
+
+/// Parses a given page token into the ID of the last event in the previous page, or `None` if the token is empty.
+fn parse_page_token(token: &str) -> Result<Option<i64>, RpcError> {
+    if token.is_empty() {
+        return Ok(None);
+    }
+
+    match token.parse::<i64>() {
+        Ok(id) if id > 0 => Ok(Some(id)),
+        _ => Err(RpcError::InvalidPageToken(token.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_size_and_token() {
+        assert_eq!(effective_page_size(0), DEFAULT_PAGE_SIZE);
+        assert_eq!(effective_page_size(10), 10);
+        assert_eq!(effective_page_size(u32::MAX), MAX_PAGE_SIZE);
+
+        assert_eq!(parse_page_token("").unwrap(), None);
+        assert_eq!(parse_page_token("42").unwrap(), Some(42));
+        assert!(parse_page_token("0").is_err());
+        assert!(parse_page_token("next").is_err());
+    }
+
+    #[test]
+    fn kind_names() {
+        for kind in &[
+            EventKind::Join,
+            EventKind::Leave,
+            EventKind::Ban,
+            EventKind::Chat,
+            EventKind::Crash,
+            EventKind::Hook,
+            EventKind::Custom,
+            EventKind::Prune,
+            EventKind::SaveStuck,
+        ] {
+            assert_eq!(kind.to_string().parse::<EventKind>().unwrap(), *kind);
+            assert_eq!(EventKind::from(rpc::server_event::Kind::from(*kind)), *kind);
+        }
+    }
+}
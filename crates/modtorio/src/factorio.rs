@@ -1,42 +1,84 @@
 //! The whole point. Provides the [`Factorio`](Factorio) struct used to interact with a single
 //! instance of a Factorio server.
 
+pub mod autosave_pruning;
+pub mod chat;
 pub mod executable;
+pub mod mapgen;
 pub mod mods;
+pub mod offenders;
+pub mod players;
+pub mod prototypes;
+pub mod raw_files;
 pub mod settings;
 mod status;
 
 use crate::{
-    error::ServerError,
+    error::{ExecutableError, ServerError},
+    event_history::{self, EventKind},
+    hooks::{self, HookPoint},
+    modpack::Modpack,
     store::{models, Store},
     util::{
+        self,
         async_status::{self, AsyncProgressChannel, AsyncProgressChannelExt},
-        ext::PathExt,
+        ext::{PathExt, SystemTimeExt},
+        file_transaction::{FileChange, FileTransaction},
+        BanAction, HumanVersion,
     },
+    version_pool::{self, VersionPool},
     Config, ModPortal,
 };
-use executable::{Executable, ExecutableEvent, GameEvent};
+use autosave_pruning::AutosavePruning;
+use chat::ChatMessage;
+use chrono::{DateTime, Utc};
+use executable::{Executable, ExecutableEvent, GameEvent, TimedGameEvent, Tool, ToolOutput};
 use log::*;
+use mapgen::NewMap;
 use models::GameSettings;
 use mods::{Mods, ModsBuilder};
+use offenders::{Offender, OffenderTracker};
+use players::{Player, Players};
+use prototypes::PrototypeInspection;
+use raw_files::{RawFile, RawFileKind};
 use rpc::send_command_request::Command;
-use settings::{ServerSettings, StartBehaviour};
+use settings::{AutosavePolicy, MapExchange, MapGenSettings, Paths, ServerSettings, StartBehaviour};
 use std::{
     fs,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 use tokio::{
-    sync::{mpsc, watch, Mutex, RwLock},
-    task,
+    sync::{broadcast, mpsc, watch, Mutex, RwLock},
+    task, time,
 };
 
-pub use status::{ExecutionStatus, InGameStatus, ServerStatus};
+pub use status::{
+    ExecutionStatus, GracefulAction, InGameStatus, PendingAction, Performance, Remediation, ServerStatus, StartupError,
+};
 
 /// The file name of the JSON file used to store a Factorio server's settings.
 const SERVER_SETTINGS_FILENAME: &str = "server-settings.json";
-/// The path relative to the Factorio server's root directory where the server's mods are stored.
-const MODS_PATH: &str = "mods/";
+/// The file name of the JSON file used to store a Factorio server's map generation settings for creating new maps.
+const MAP_GEN_SETTINGS_FILENAME: &str = "map-gen-settings.json";
+/// The path relative to the Factorio server's root directory of the example settings file bundled with the game.
+const EXAMPLE_SERVER_SETTINGS_PATH: &str = "data/server-settings.example.json";
+/// The glob pattern of save files in a saves directory.
+const SAVES_GLOB: &str = "*.zip";
+/// The prefix of the game's autosaves' names.
+const AUTOSAVE_PREFIX: &str = "_autosave";
+/// How many finished saves are buffered for each waiter of a requested save.
+const SAVED_CHANNEL_CAPACITY: usize = 8;
+/// How many chat messages are buffered for each chat subscriber.
+const CHAT_CHANNEL_CAPACITY: usize = 64;
+
+/// The next settings revision given to a server whose settings change. Shared by every server so a revision is never
+/// reused within the program's lifetime, even by a server that's removed and imported again.
+static NEXT_SETTINGS_REVISION: AtomicU64 = AtomicU64::new(1);
 
 /// The type used to identify games in the program store.
 pub type GameStoreId = i64;
@@ -51,20 +93,102 @@ pub struct Factorio {
     mods: Mods,
     /// The server's executable.
     executable: Executable,
+    /// The location of the server's own executable, run when the server doesn't use a pooled game version.
+    own_executable: PathBuf,
     /// The server's root directory.
     root: PathBuf,
     /// The program's store ID.
     store_id: Arc<Mutex<Option<GameStoreId>>>,
+    /// The server's optional unique name.
+    name: Arc<Mutex<Option<String>>>,
     /// Reference to the program store.
     store: Arc<Store>,
     /// The server's status.
     status: Arc<RwLock<ServerStatus>>,
+    /// The program config.
+    config: Arc<Config>,
+    /// The server's refused connection tracker.
+    offenders: Arc<Mutex<OffenderTracker>>,
+    /// The server's player tracker.
+    players: Arc<Mutex<Players>>,
     /// The running executable's stdin transmit channel.
     exec_stdin_tx: Mutex<Option<mpsc::Sender<String>>>,
+    /// The running executable's kill signal transmit channel.
+    exec_kill_tx: Mutex<Option<mpsc::Sender<()>>>,
     /// The running executable's stdout receiver channel.
     exec_stdout_rx: Mutex<Option<mpsc::Receiver<String>>>,
     /// The signal receiver for the executable's exit.
     exec_shutdown_rx: Mutex<Option<watch::Receiver<()>>>,
+    /// Sends the saves the game finishes writing.
+    saved_tx: broadcast::Sender<PathBuf>,
+    /// Sends the messages sent to the in-game chat.
+    chat_tx: broadcast::Sender<ChatMessage>,
+    /// The last mtime of the server's settings file the settings are in sync with, if known.
+    settings_last_mtime: Option<DateTime<Utc>>,
+    /// The settings file's version of the settings, if it conflicts with the stored settings.
+    settings_conflict: Option<SettingsConflict>,
+    /// The revision of the server's settings, which changes every time the settings may have been changed. Used to
+    /// detect concurrent settings edits.
+    settings_revision: u64,
+}
+
+/// A server's settings file's version of its settings, found when importing a stored server if the file was modified
+/// after the settings were stored and its settings differ from the stored ones. The stored settings stay in use until
+/// the conflict is resolved with [`resolve_settings_conflict`](Factorio::resolve_settings_conflict).
+#[derive(Debug)]
+pub struct SettingsConflict {
+    /// The settings read from the settings file.
+    settings: ServerSettings,
+    /// The settings file's last mtime when it was read.
+    file_last_mtime: DateTime<Utc>,
+}
+
+/// The source of a server's settings that wins when resolving a settings conflict.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SettingsSource {
+    /// The server's settings file.
+    File,
+    /// The program store.
+    Store,
+}
+
+/// A cheaply cloneable handle to a [`Factorio`](Factorio) server's status, which can be read without
+/// access to the server itself.
+#[derive(Debug, Clone)]
+pub struct StatusHandle {
+    /// The server's root directory.
+    root: PathBuf,
+    /// The program's store ID.
+    store_id: Arc<Mutex<Option<GameStoreId>>>,
+    /// The server's optional unique name.
+    name: Arc<Mutex<Option<String>>>,
+    /// The server's status.
+    status: Arc<RwLock<ServerStatus>>,
+    /// The server's player tracker.
+    players: Arc<Mutex<Players>>,
+}
+
+/// A save requested from a running server with [`save_map`](Factorio::save_map). Waiting for the game to finish the
+/// save doesn't need access to the server itself.
+#[derive(Debug)]
+pub struct PendingSave {
+    /// The requested save's name. Empty for the save the game loaded.
+    name: String,
+    /// Receives the saves the game finishes writing.
+    saved_rx: broadcast::Receiver<PathBuf>,
+    /// The signal receiver for the executable's exit.
+    shutdown_rx: watch::Receiver<()>,
+    /// How long the game may take to save before the save is given up on, if at all.
+    timeout: Option<Duration>,
+}
+
+/// The autosave settings a running server's autosave interval is adjusted with.
+#[derive(Debug, Clone)]
+struct AutosaveThrottle {
+    /// The server's autosave policy.
+    policy: AutosavePolicy,
+    /// The server's own autosave interval in minutes, used below every threshold of the policy.
+    default_interval: u64,
 }
 
 /// Builds a new instance of a [`Factorio`](Factorio) server by importing its information from the
@@ -78,6 +202,10 @@ pub struct Importer {
     executable: PathBuf,
     /// The program's store ID.
     game_store_id: Option<GameStoreId>,
+    /// The server's optional unique name.
+    name: Option<String>,
+    /// Whether to read a stored server's settings from its settings file instead of the program store.
+    settings_from_file: bool,
     /// A status update channel.
     prog_tx: Option<AsyncProgressChannel>,
 }
@@ -85,9 +213,52 @@ pub struct Importer {
 impl Factorio {
     /// Updates all information about the instance in the program store.
     pub async fn update_store(&self, prog_tx: Option<AsyncProgressChannel>) -> anyhow::Result<()> {
-        self.store.begin_transaction()?;
+        self.write_store(prog_tx, false).await
+    }
+
+    /// Updates all information about the instance in the program store like
+    /// [`update_store`](#method.update_store), and also removes any stored mods the instance no longer has. Used after
+    /// the instance has been re-imported from its root directory.
+    pub async fn replace_store(&self, prog_tx: Option<AsyncProgressChannel>) -> anyhow::Result<()> {
+        self.write_store(prog_tx, true).await
+    }
 
+    /// Writes all information about the instance to the program store, optionally removing the previously stored mods
+    /// first.
+    async fn write_store(&self, prog_tx: Option<AsyncProgressChannel>, replace_mods: bool) -> anyhow::Result<()> {
         let mut store_id = self.store_id.lock().await;
+        let previous_id = *store_id;
+
+        self.store.begin_transaction()?;
+        let result = self.write_store_transaction(&mut store_id, prog_tx, replace_mods).await;
+        match self.store.finish_transaction(result) {
+            Ok(id) => {
+                self.status.write().await.set_store_dirty(false);
+                info!("Game ID {} store updated", id);
+                Ok(())
+            }
+            Err(e) => {
+                // a new game's ID was rolled back with the rest of the transaction
+                *store_id = previous_id;
+                self.status.write().await.set_store_dirty(true);
+                error!(
+                    "Failed to write game in {} to the store, its stored state is now out of date: {}",
+                    self.root.display(),
+                    e
+                );
+                Err(e)
+            }
+        }
+    }
+
+    /// Writes all information about the instance to the program store inside an ongoing transaction, setting the
+    /// instance's store ID if it's new. Returns the store ID.
+    async fn write_store_transaction(
+        &self,
+        store_id: &mut Option<GameStoreId>,
+        prog_tx: Option<AsyncProgressChannel>,
+        replace_mods: bool,
+    ) -> anyhow::Result<GameStoreId> {
         let id = if let Some(c) = *store_id {
             info!("Updating existing game ID {} store", c);
             prog_tx
@@ -97,7 +268,8 @@ impl Factorio {
             self.store
                 .update_game(models::Game {
                     id: c,
-                    path: self.root.get_str()?.to_string(),
+                    path: self.root.to_bytes(),
+                    name: self.name().await,
                 })
                 .await?;
 
@@ -113,7 +285,8 @@ impl Factorio {
                 .insert_game(models::Game {
                     id: 0, /* this ID is irrelevant as the actual ID will be dictated by the
                             * database when inserting a new row */
-                    path: self.root.get_str()?.to_string(),
+                    path: self.root.to_bytes(),
+                    name: self.name().await,
                 })
                 .await?;
             *store_id = Some(new_id);
@@ -124,44 +297,102 @@ impl Factorio {
 
         let mut new_settings = GameSettings::default();
         new_settings.game = id;
+        new_settings.settings_last_mtime = self.settings_last_mtime;
         self.settings.to_store_format(&mut new_settings)?;
 
         debug!("Created new settings to store: {:?}", new_settings);
         self.store.set_settings(new_settings).await?;
 
-        self.mods.update_store(id, prog_tx).await?;
-        self.store.commit_transaction()?;
+        if replace_mods {
+            debug!("Removing game ID {}'s previously stored mods", id);
+            self.store.remove_mods_of_game(id).await?;
+        }
 
-        info!("Game ID {} store updated", id);
-        Ok(())
+        self.mods.update_store(id, prog_tx).await?;
+        Ok(id)
     }
 
-    /// Runs the server.
-    pub async fn run(&self) -> anyhow::Result<()> {
-        self.assert_status(ExecutionStatus::Shutdown).await?;
+    /// Runs the server. The server must be shut down or crashed. The server is run with the executable its game
+    /// version setting selects. The pre-start hook is run before the executable, the post-start hook once the server
+    /// is running, and the post-stop or post-crash hook once the executable has exited.
+    pub async fn run(&mut self) -> anyhow::Result<()> {
+        let status = self.status().await.game_status();
+        if status != ExecutionStatus::Shutdown && status != ExecutionStatus::Crashed {
+            return Err(ServerError::InvalidGameStatus(status).into());
+        }
         let store_id = self.store_id().await?;
+        self.select_executable().await?;
         debug!("Running game ID {} executable", store_id);
 
-        let (stdin_tx, stdin_rx) = mpsc::channel(64);
-        let (stdout_tx, stdout_rx) = mpsc::channel(64);
-        *self.exec_stdin_tx.lock().await = Some(stdin_tx);
+        // the save is created the first time the server is started and loaded on later starts
+        if self.settings.start.behaviour == StartBehaviour::Create {
+            let filename = self.settings.start.save_filename();
+            if !self.settings.paths.saves(&self.root).join(&filename).exists() {
+                info!("Creating save {} to start game ID {} with", filename, store_id);
+                self.create_save(&filename, &NewMap::default()).await?;
+            }
+        }
+
+        self.run_hook(HookPoint::PreStart).await;
+
+        let channel_capacity = self.config.channel_capacity();
+        let (stdin_tx, stdin_rx) = mpsc::channel(channel_capacity);
+        let (stdout_tx, stdout_rx) = mpsc::channel(channel_capacity);
+        let (kill_tx, kill_rx) = mpsc::channel(1);
+        *self.exec_stdin_tx.lock().await = Some(stdin_tx.clone());
+        *self.exec_kill_tx.lock().await = Some(kill_tx);
         *self.exec_stdout_rx.lock().await = Some(stdout_rx);
 
-        let exec_args = self.get_executable_args();
-        let mut state_rx = self.executable.run(stdout_tx, stdin_rx, &exec_args).await?;
+        self.link_script_output()?;
+        if self.settings.management.game_version.is_some() {
+            version_pool::write_pooled_config(&self.root).await?;
+        }
+        let exec_args = self.get_executable_args()?;
+        let mut state_rx = self
+            .executable
+            .run(
+                stdout_tx,
+                stdin_rx,
+                kill_rx,
+                &exec_args,
+                channel_capacity,
+                self.config.output_overflow(),
+                self.config.event_parsers().to_vec(),
+            )
+            .await?;
 
         let (shutdown_tx, mut shutdown_rx) = watch::channel(());
         shutdown_rx.recv().await;
         *self.exec_shutdown_rx.lock().await = Some(shutdown_rx);
 
         let status = Arc::clone(&self.status);
+        let offenders = Arc::clone(&self.offenders);
+        let players = Arc::clone(&self.players);
+        let config = Arc::clone(&self.config);
+        let saved_tx = self.saved_tx.clone();
+        let chat_tx = self.chat_tx.clone();
+        let autosave = AutosaveThrottle {
+            policy: self.settings.autosave_policy.clone(),
+            default_interval: self.settings.autosave.interval,
+        };
+        let store = Arc::clone(&self.store);
+        let events = event_history::Recorder::spawn(Arc::clone(&store), store_id);
+        let root = self.root.clone();
+        let mut post_start_pending = true;
+        // the mods can't change while the server runs, so the set it starts with is recorded once it's running
+        let mut started_mods = if self.settings.management.mods {
+            Some(Modpack::export(&self.mods, false).await?)
+        } else {
+            None
+        };
         {
             let mut status_w = status.write().await;
             status_w.reset_started_at();
             status_w.set_game_status(ExecutionStatus::Starting);
         }
 
-        task::spawn(async move {
+        // the server's events are logged with its ID as context even though no single request started the server
+        task::spawn(util::correlation::for_server(store_id, async move {
             debug!(
                 "Game ID {} executable running, beginning listening for state changes",
                 store_id
@@ -169,20 +400,313 @@ impl Factorio {
 
             while let Some(event) = state_rx.recv().await {
                 match event {
-                    ExecutableEvent::GameEvent(game_event) => process_game_event(store_id, game_event, &status).await,
+                    ExecutableEvent::GameEvent(game_event) => {
+                        process_game_event(
+                            store_id, game_event, &status, &offenders, &players, &config, &store, &events, &stdin_tx,
+                            &saved_tx, &chat_tx, &autosave,
+                        )
+                        .await;
+
+                        let running = status.read().await.game_status() == ExecutionStatus::Running;
+                        if started_mods.is_some() && running {
+                            if let Some(modpack) = started_mods.take() {
+                                record_last_known_good_mods(store_id, &modpack, &store).await;
+                            }
+                        }
+
+                        // the hook runs in its own task so the server's output keeps being processed meanwhile
+                        if post_start_pending && running {
+                            post_start_pending = false;
+                            let (config, store, root) = (Arc::clone(&config), Arc::clone(&store), root.clone());
+                            task::spawn(util::correlation::for_server(store_id, async move {
+                                hooks::run(
+                                    &config,
+                                    &store,
+                                    store_id,
+                                    HookPoint::PostStart,
+                                    &root,
+                                    ExecutionStatus::Running,
+                                )
+                                .await;
+                            }));
+                        }
+                    }
+                    ExecutableEvent::UnparsedLine(line) => {
+                        log!(
+                            config.unparsed_line_level().to_level(),
+                            "Game ID {} unparsed output line: {}",
+                            store_id,
+                            line
+                        );
+                        status.write().await.add_unparsed_line();
+                    }
+                    ExecutableEvent::DroppedLines(dropped) => {
+                        warn!(
+                            "Game ID {} output channel full, dropped {} output lines",
+                            store_id, dropped
+                        );
+                        status.write().await.add_dropped_lines(dropped);
+                    }
                     ExecutableEvent::Exited(exit_result) => {
-                        process_exited_event(store_id, exit_result, &status).await;
+                        process_exited_event(store_id, exit_result, &status, &config, &events).await;
+                        players.lock().await.end_sessions(Utc::now());
+
+                        // the hook runs in its own task so a script that doesn't exit can't hold up the shutdown
+                        let exit_status = status.read().await.game_status();
+                        let point = if exit_status == ExecutionStatus::Crashed {
+                            HookPoint::PostCrash
+                        } else {
+                            HookPoint::PostStop
+                        };
+                        let (config, store, root) = (Arc::clone(&config), Arc::clone(&store), root.clone());
+                        task::spawn(util::correlation::for_server(store_id, async move {
+                            hooks::run(&config, &store, store_id, point, &root, exit_status).await;
+                        }));
                         break;
                     }
                 }
             }
 
             shutdown_tx.broadcast(()).expect("failed to send shutdown signal");
-        });
+        }));
+
+        Ok(())
+    }
+
+    /// Writes the server's settings to its settings file, and its admin list and whitelist files if the settings
+    /// manage them, atomically. If `dry_run` is set, nothing is written. Returns the changes to each file. If the
+    /// settings include other settings files, they're composed from the files' current contents first, and the composed
+    /// settings are adopted once written. Fields in the settings file the settings don't have are kept.
+    pub async fn apply_settings(&mut self, dry_run: bool) -> anyhow::Result<Vec<FileChange>> {
+        let path = self.root.join(SERVER_SETTINGS_FILENAME);
+        let mut contents = self.settings.to_composed_game_json(&self.root).await?;
+        if let Ok(current) = tokio::fs::read_to_string(&path).await {
+            contents = settings::merge_game_json(&current, &contents)?;
+        }
+        let mut transaction = FileTransaction::new(&self.root).with_file(path, contents);
+        for (path, contents) in self.settings.access_lists.game_files(&self.root)? {
+            transaction = transaction.with_file(path, contents);
+        }
+
+        let changes = task::spawn_blocking(move || {
+            let changes = transaction.diff()?;
+            if !dry_run && changes.iter().any(|change| change.changed) {
+                transaction.commit()?;
+            }
+
+            Ok::<_, anyhow::Error>(changes)
+        })
+        .await??;
+
+        if !dry_run {
+            self.settings.compose(&self.root).await?;
+        }
+        Ok(changes)
+    }
+
+    /// Writes the server's settings to its settings file if they differ from the file's, and keeps the file's new
+    /// mtime as the one the settings are in sync with. Returns whether the file was written. Nothing is written while
+    /// the settings conflict with the file, so the file's version of the settings isn't lost before the conflict is
+    /// resolved.
+    pub async fn flush_settings_to_file(&mut self) -> anyhow::Result<bool> {
+        if self.settings_conflict.is_some() {
+            warn!("Not writing the settings to the settings file while they conflict with it");
+            return Ok(false);
+        }
+
+        let written = self.apply_settings(false).await?.iter().any(|change| change.changed);
+        if written {
+            debug!("Wrote the settings to the settings file");
+            self.settings_last_mtime = settings_file_last_mtime(&self.root.join(SERVER_SETTINGS_FILENAME)).await;
+        }
+
+        Ok(written)
+    }
+
+    /// Resolves the server's settings conflict by adopting a given source's version of the settings and writing it to
+    /// the other source. If writing fails, the conflict is left in place. Returns `ServerError::NoSettingsConflict` if
+    /// there is no conflict.
+    pub async fn resolve_settings_conflict(&mut self, prefer: SettingsSource) -> anyhow::Result<()> {
+        let conflict = self.settings_conflict.take().ok_or(ServerError::NoSettingsConflict)?;
+
+        match prefer {
+            SettingsSource::File => {
+                let SettingsConflict {
+                    mut settings,
+                    file_last_mtime,
+                } = conflict;
+                // the file's settings differ from the included files' settings in whatever way they now do
+                if let Err(e) = settings.update_overrides(&self.root).await {
+                    self.settings_conflict = Some(SettingsConflict {
+                        settings,
+                        file_last_mtime,
+                    });
+                    return Err(e);
+                }
+
+                let stored_settings = std::mem::replace(&mut self.settings, settings);
+                let stored_last_mtime = self.settings_last_mtime.replace(file_last_mtime);
+
+                if let Err(e) = self.update_store(None).await {
+                    let settings = std::mem::replace(&mut self.settings, stored_settings);
+                    self.settings_last_mtime = stored_last_mtime;
+                    self.settings_conflict = Some(SettingsConflict {
+                        settings,
+                        file_last_mtime,
+                    });
+                    return Err(e);
+                }
+                self.settings_revision = next_settings_revision();
+            }
+            SettingsSource::Store => {
+                if let Err(e) = self.apply_settings(false).await {
+                    self.settings_conflict = Some(conflict);
+                    return Err(e);
+                }
+
+                self.settings_last_mtime = settings_file_last_mtime(&self.root.join(SERVER_SETTINGS_FILENAME)).await;
+                // the file now has the stored settings, so even if storing its mtime fails there's no conflict left
+                self.update_store(None).await?;
+            }
+        }
 
+        self.status.write().await.set_settings_conflict(false);
         Ok(())
     }
 
+    /// Returns the settings file's version of the settings if it conflicts with the stored settings.
+    pub fn settings_conflict(&self) -> Option<&ServerSettings> {
+        self.settings_conflict.as_ref().map(|conflict| &conflict.settings)
+    }
+
+    /// Writes a given map exchange string's map generation settings to the server's map generation settings file, to
+    /// be used when creating new maps, and stores the string so its map settings can be exported again.
+    pub async fn import_map_exchange(&self, exchange: &MapExchange) -> anyhow::Result<()> {
+        let store_id = self.store_id().await?;
+        let transaction = FileTransaction::new(&self.root).with_file(
+            self.root.join(MAP_GEN_SETTINGS_FILENAME),
+            exchange.map_gen_settings().to_game_json()?,
+        );
+        task::spawn_blocking(move || transaction.commit()).await??;
+
+        self.store
+            .set_map_exchange_string(models::MapExchangeString {
+                game: store_id,
+                exchange_string: exchange.to_string(),
+            })
+            .await
+    }
+
+    /// Returns the server's current map settings as a map exchange string. The map generation settings are read from
+    /// the server's map generation settings file, and the rest of the map settings come from the imported map exchange
+    /// string. Returns `None` if a string hasn't been imported.
+    pub async fn export_map_exchange(&self) -> anyhow::Result<Option<MapExchange>> {
+        let store_id = self.store_id().await?;
+        let mut exchange: MapExchange = match self.store.get_map_exchange_string(store_id).await? {
+            Some(stored) => stored.exchange_string.parse()?,
+            None => return Ok(None),
+        };
+
+        let path = self.root.join(MAP_GEN_SETTINGS_FILENAME);
+        if tokio::fs::metadata(&path).await.is_ok() {
+            exchange.set_map_gen_settings(MapGenSettings::from_game_json(&tokio::fs::read_to_string(path).await?)?);
+        }
+
+        Ok(Some(exchange))
+    }
+
+    /// Runs a given one-shot tool with the server's executable and returns its captured output. The server must not be
+    /// running. A save file path in the tool is relative to the server's saves directory.
+    pub async fn run_tool(&self, tool: Tool) -> anyhow::Result<ToolOutput> {
+        let status = self.status().await.game_status();
+        if status != ExecutionStatus::Shutdown && status != ExecutionStatus::Crashed {
+            return Err(ServerError::InvalidGameStatus(status).into());
+        }
+
+        self.link_script_output()?;
+        let saves = self.settings.paths.saves(&self.root);
+        let tool = match tool {
+            Tool::Benchmark { save, ticks } => Tool::Benchmark {
+                save: saves.join(save),
+                ticks,
+            },
+            Tool::Map2Scenario { save } => Tool::Map2Scenario { save: saves.join(save) },
+            Tool::DumpData => Tool::DumpData,
+            Tool::Create { save } => Tool::Create { save: saves.join(save) },
+        };
+
+        let args = match tool {
+            Tool::Create { .. } => self.imported_map_gen_args().await,
+            _ => Vec::new(),
+        };
+        self.executable.run_tool_with_args(&tool, &args).await
+    }
+
+    /// Returns the executable arguments that pass the map generation settings imported from a map exchange string to
+    /// the game when creating a new map, or no arguments if none have been imported.
+    async fn imported_map_gen_args(&self) -> Vec<String> {
+        let path = self.root.join(MAP_GEN_SETTINGS_FILENAME);
+        if tokio::fs::metadata(&path).await.is_ok() {
+            vec![String::from("--map-gen-settings"), path.display().to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Creates a new map with given settings and the server's mods, saves it with a given file name in the server's
+    /// saves directory and returns the save's path. Without map generation settings in the given settings, the ones
+    /// imported from a map exchange string are used, if any. The server must not be running and the save must not
+    /// exist.
+    pub async fn create_save(&self, filename: &str, new_map: &NewMap) -> anyhow::Result<PathBuf> {
+        let status = self.status().await.game_status();
+        if status != ExecutionStatus::Shutdown && status != ExecutionStatus::Crashed {
+            return Err(ServerError::InvalidGameStatus(status).into());
+        }
+
+        let save = self.settings.paths.saves(&self.root).join(filename);
+        if save.exists() {
+            return Err(ServerError::SaveExists(save).into());
+        }
+
+        // the settings files are only needed while the map is created
+        let dir = tempfile::tempdir()?;
+        let mut args = new_map.write_args(dir.path()).await?;
+        if new_map.map_gen_settings.is_none() {
+            args.extend(self.imported_map_gen_args().await);
+        }
+        args.extend(vec![
+            String::from("--mod-directory"),
+            self.mods.directory().display().to_string(),
+        ]);
+
+        debug!("Creating save {}", save.display());
+        let output = self
+            .executable
+            .run_tool_with_args(&Tool::Create { save: save.clone() }, &args)
+            .await?;
+        if !output.success {
+            return Err(ExecutableError::Unsuccesfull {
+                exit_code: output.exit_code,
+                stdout: Some(output.stdout),
+                stderr: Some(output.stderr),
+            }
+            .into());
+        }
+
+        Ok(save)
+    }
+
+    /// Returns which item and recipe prototypes the base game and each of the server's mods add, by running the
+    /// executable's data dump for each of them. The server must not be running.
+    pub async fn inspect_prototypes(&self) -> anyhow::Result<PrototypeInspection> {
+        let status = self.status().await.game_status();
+        if status != ExecutionStatus::Shutdown && status != ExecutionStatus::Crashed {
+            return Err(ServerError::InvalidGameStatus(status).into());
+        }
+
+        prototypes::inspect(&self.executable, &self.mods).await
+    }
+
     /// Asynchronously waits for the game executable to shut down. Returns immediately if the executable isn't running.
     pub async fn wait_for_shutdown(&self) {
         if let Some(mut rx) = self.exec_shutdown_rx.lock().await.clone() {
@@ -190,7 +714,26 @@ impl Factorio {
         }
     }
 
-    /// Sends a command to the running executable.
+    /// Kills the running executable without letting the game save or shut down cleanly, after running the pre-stop
+    /// hook. Returns once the executable is told to exit; use [`wait_for_shutdown`](#method.wait_for_shutdown) to wait
+    /// for it to exit.
+    pub async fn kill(&self) -> anyhow::Result<()> {
+        let status = self.status().await.game_status();
+        if status == ExecutionStatus::Shutdown || status == ExecutionStatus::Crashed {
+            return Err(ServerError::InvalidGameStatus(status).into());
+        }
+
+        self.run_hook(HookPoint::PreStop).await;
+
+        if let Some(kill_tx) = self.exec_kill_tx.lock().await.as_mut() {
+            // the executable may have exited and closed the channel already, in which case there's nothing to kill
+            kill_tx.send(()).await.ok();
+        }
+
+        Ok(())
+    }
+
+    /// Sends a command to the running executable. The pre-stop hook is run before the quit command is sent.
     pub async fn send_command(&self, command: Command, arguments: Vec<String>) -> anyhow::Result<()> {
         self.assert_status(ExecutionStatus::Running).await?;
 
@@ -201,14 +744,16 @@ impl Factorio {
                 command_components.extend(arguments);
             }
             Command::Say => {
-                // TODO
-                todo!()
+                let message = chat_console_line(&arguments);
+                debug!("Final chat message: {}", message);
+                return self.write_to_exec_stdin(message).await;
             }
             Command::Save => {
                 command_components.push(String::from("save"));
                 command_components.extend(arguments);
             }
             Command::Quit => {
+                self.run_hook(HookPoint::PreStop).await;
                 command_components.push(String::from("quit"));
             }
         }
@@ -220,6 +765,44 @@ impl Factorio {
         Ok(())
     }
 
+    /// Tells the running game to save its map with a given name in its saves directory, or over the save it loaded if
+    /// the name is empty. Returns a `PendingSave` to wait for the game to finish the save with.
+    pub async fn save_map(&self, name: &str) -> anyhow::Result<PendingSave> {
+        self.assert_status(ExecutionStatus::Running).await?;
+        let shutdown_rx = self
+            .exec_shutdown_rx
+            .lock()
+            .await
+            .clone()
+            .ok_or(ServerError::InvalidGameStatus(ExecutionStatus::Shutdown))?;
+
+        // subscribe before the command is sent so the save can't finish before it's waited on
+        let saved_rx = self.saved_tx.subscribe();
+        let arguments = if name.is_empty() {
+            Vec::new()
+        } else {
+            vec![name.to_owned()]
+        };
+        self.send_command(Command::Save, arguments).await?;
+
+        Ok(PendingSave {
+            name: name.to_owned(),
+            saved_rx,
+            shutdown_rx,
+            timeout: self.config.saving_timeout(),
+        })
+    }
+
+    /// Returns the addresses that have been refused a connection too many times within the ban window.
+    pub async fn offenders(&self) -> Vec<Offender> {
+        self.offenders.lock().await.offenders(Utc::now())
+    }
+
+    /// Returns the players that have joined the server since the program started, the online players first.
+    pub async fn players(&self) -> Vec<Player> {
+        self.players.lock().await.players()
+    }
+
     /// Returns the instance's root directory.
     pub fn root(&self) -> &Path {
         &self.root
@@ -235,16 +818,126 @@ impl Factorio {
         &mut self.mods
     }
 
+    /// Rebuilds the server's mods with a given mod portal if its path settings point to a different mods directory than
+    /// the mods were built from. Like when importing the server, the mods are loaded from the program store, leaving
+    /// out the stored mods missing from the new directory and adding the directory's other mods.
+    pub async fn rebuild_moved_mods(&mut self, portal: Arc<ModPortal>) -> anyhow::Result<()> {
+        let directory = self.settings.paths.mods(&self.root);
+        if directory == self.mods.directory() {
+            return Ok(());
+        }
+
+        info!(
+            "Mods directory of {} moved to {}, rebuilding mods",
+            self.root.display(),
+            directory.display()
+        );
+        let mut mods_builder = ModsBuilder::root(directory)
+            .with_store_id_handle(Arc::clone(&self.store_id))
+            .with_game_version(self.executable.version());
+        if let Some(game_store_id) = *self.store_id.lock().await {
+            mods_builder = mods_builder.with_game_store_id(game_store_id);
+        }
+        if !self.settings.management.mods {
+            mods_builder = mods_builder.without_loading();
+        }
+
+        self.mods = mods_builder
+            .build(Arc::clone(&self.config), portal, Arc::clone(&self.store))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the mod set the server had the last time it started successfully, and when it was recorded. Returns
+    /// `None` if the server hasn't started successfully since its mods were managed.
+    pub async fn last_known_good_mods(&self) -> anyhow::Result<Option<(Modpack, DateTime<Utc>)>> {
+        let store_id = self.store_id().await?;
+        match self.store.get_last_known_good_mods(store_id).await? {
+            Some(mods) => Ok(Some((Modpack::from_store_format(&mods.modpack)?, mods.recorded_at))),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a given kind of the server's configuration files as it currently is on disk.
+    pub async fn raw_file(&self, kind: RawFileKind) -> anyhow::Result<RawFile> {
+        RawFile::read(kind, &self.root, self.mods.directory()).await
+    }
+
+    /// Prunes the server's autosaves its autosave retention doesn't keep, or only lists them in a dry run. Returns
+    /// `ServerError::Saving` if the server is saving its map, since it may be writing one of its autosaves.
+    pub async fn prune_autosaves(&self, dry_run: bool) -> anyhow::Result<AutosavePruning> {
+        if !dry_run && self.status.read().await.saving_since().is_some() {
+            return Err(ServerError::Saving.into());
+        }
+
+        let pruning = AutosavePruning::prune(
+            &self.settings.paths.saves(&self.root),
+            &self.settings.autosave_retention,
+            self.config.time_zone(),
+            dry_run,
+        )
+        .await?;
+
+        if !dry_run && !pruning.deleted().is_empty() {
+            let message = format!(
+                "Pruned {} autosaves, reclaiming {} bytes",
+                pruning.deleted().len(),
+                pruning.bytes_reclaimed()
+            );
+            self.record_event(EventKind::Prune, &message).await;
+        }
+
+        Ok(pruning)
+    }
+
+    /// Records an event of a given kind with a given message into the server's event history. Nothing is recorded if
+    /// the program store is read-only, and failing to record the event is only logged.
+    async fn record_event(&self, kind: EventKind, message: &str) {
+        if self.store.is_read_only() {
+            return;
+        }
+
+        let result = match self.store_id().await {
+            Ok(store_id) => event_history::record(&self.store, store_id, kind, Utc::now(), None, message).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("Failed to record {} event: {}", kind, e);
+        }
+    }
+
+    /// Returns the server executable's Factorio version.
+    pub fn game_version(&self) -> HumanVersion {
+        self.executable.version()
+    }
+
+    /// Updates the server's mods according to their update policies for the server executable's Factorio version, and
+    /// clears the updates the server was notified to have available.
+    pub async fn update_mods(&mut self, prog_tx: Option<AsyncProgressChannel>) -> anyhow::Result<()> {
+        let game_version = self.game_version();
+        debug!("Updating mods for Factorio {}", game_version);
+        self.mods.update(game_version, prog_tx).await?;
+        self.status.write().await.clear_available_updates();
+        Ok(())
+    }
+
     /// Immutably borrows the server's settings.
     pub fn settings(&self) -> &ServerSettings {
         &self.settings
     }
 
-    /// Mutably borrows the server's settings.
+    /// Mutably borrows the server's settings. Changes the settings' revision.
     pub fn settings_mut(&mut self) -> &mut ServerSettings {
+        self.settings_revision = next_settings_revision();
         &mut self.settings
     }
 
+    /// Returns the revision of the server's settings. The revision changes every time the settings are borrowed
+    /// mutably, and is never shared by two servers.
+    pub fn settings_revision(&self) -> u64 {
+        self.settings_revision
+    }
+
     /// Immutably borrows the server's executable.
     pub fn executable(&self) -> &Executable {
         &self.executable
@@ -265,9 +958,82 @@ impl Factorio {
             .ok_or_else(|| ServerError::GameNotInStore.into())
     }
 
+    /// Returns the server's unique name, or `None` if it doesn't have one.
+    pub async fn name(&self) -> Option<String> {
+        self.name.lock().await.clone()
+    }
+
+    /// Sets or clears the server's unique name and updates it in the program store. The name's uniqueness is enforced
+    /// by the store.
+    pub async fn set_name(&self, name: Option<String>) -> anyhow::Result<()> {
+        let store_id = self.store_id().await?;
+        let mut current = self.name.lock().await;
+        self.store.set_game_name(store_id, name.clone()).await?;
+        *current = name;
+        Ok(())
+    }
+
     /// Returns the server's status.
     pub async fn status(&self) -> ServerStatus {
-        *self.status.read().await
+        self.status.read().await.clone()
+    }
+
+    /// Records an automatic start attempt in the server's status.
+    pub async fn add_autostart_attempt(&self) {
+        self.status.write().await.add_autostart_attempt();
+    }
+
+    /// Flags the server as stuck saving its map in its status, and records it in the server's event history along with
+    /// how long the server has been saving for.
+    pub async fn mark_saving_stuck(&self, saving_for: chrono::Duration) {
+        self.status.write().await.set_saving_stuck();
+
+        let message = format!("Saving the map for {} seconds", saving_for.num_seconds());
+        self.record_event(EventKind::SaveStuck, &message).await;
+    }
+
+    /// Records in the server's status that a mod with a given name has a given newer release available.
+    pub async fn mark_update_available(&self, name: &str, version: HumanVersion) {
+        self.status.write().await.add_available_update(name, version);
+    }
+
+    /// Records in the server's status that automatically starting the server was given up.
+    pub async fn give_up_autostart(&self) {
+        self.status.write().await.set_autostart_gave_up();
+    }
+
+    /// Marks a given graceful action as waiting to be carried out on the server at a given time. The server must be
+    /// running.
+    pub async fn begin_pending_action(&self, action: GracefulAction, eta: DateTime<Utc>) -> anyhow::Result<()> {
+        let mut status = self.status.write().await;
+        if status.game_status() != ExecutionStatus::Running {
+            return Err(ServerError::InvalidGameStatus(status.game_status()).into());
+        }
+
+        status.set_pending_action(Some(PendingAction::new(action, eta)));
+        Ok(())
+    }
+
+    /// Clears the graceful action waiting to be carried out on the server.
+    pub async fn clear_pending_action(&self) {
+        self.status.write().await.set_pending_action(None);
+    }
+
+    /// Returns a new receiver for the messages sent to the server's in-game chat from now on. The receiver keeps
+    /// receiving across the server's restarts.
+    pub fn subscribe_chat(&self) -> broadcast::Receiver<ChatMessage> {
+        self.chat_tx.subscribe()
+    }
+
+    /// Returns a new handle to the server's status.
+    pub fn status_handle(&self) -> StatusHandle {
+        StatusHandle {
+            root: self.root.clone(),
+            store_id: Arc::clone(&self.store_id),
+            name: Arc::clone(&self.name),
+            status: Arc::clone(&self.status),
+            players: Arc::clone(&self.players),
+        }
     }
 
     /// Asserts that the server's status is `expected`, otherwise returns `ServerError::InvalidStatus`.
@@ -289,47 +1055,196 @@ impl Factorio {
         Ok(())
     }
 
-    /// Returns the proper server executable arguments to match the server's settings.
-    fn get_executable_args(&self) -> Vec<String> {
+    /// Returns the proper server executable arguments to match the server's settings. The game only looks for saves in
+    /// its default saves directory, so with a custom saves location the save to load is given to it as a full path.
+    fn get_executable_args(&self) -> anyhow::Result<Vec<String>> {
         let mut args = Vec::new();
+        let custom_saves = self.settings.paths.saves.is_some();
+        let saves = self.settings.paths.saves(&self.root);
 
         match self.settings.start.behaviour {
+            StartBehaviour::LoadLatest if custom_saves => args.extend(vec![
+                String::from("--start-server"),
+                latest_save(&saves)?.display().to_string(),
+            ]),
             StartBehaviour::LoadLatest => args.push(String::from("--start-server-load-latest")),
+            StartBehaviour::LoadFile if custom_saves => args.extend(vec![
+                String::from("--start-server"),
+                saves.join(&self.settings.start.save_name).display().to_string(),
+            ]),
             StartBehaviour::LoadFile => args.extend(vec![
                 String::from("--start-server"),
                 self.settings.start.save_name.clone(),
             ]),
-            _ => unimplemented!(), // TODO
+            StartBehaviour::LoadScenario => args.extend(vec![
+                String::from("--start-server-load-scenario"),
+                self.settings.start.save_name.clone(),
+            ]),
+            StartBehaviour::Create => args.extend(vec![
+                String::from("--start-server"),
+                saves.join(self.settings.start.save_filename()).display().to_string(),
+            ]),
         }
 
         args.extend(vec![
+            String::from("--mod-directory"),
+            self.mods.directory().display().to_string(),
             String::from("--bind"),
             self.settings.network.bind_address.to_string(),
         ]);
 
-        args
+        // a pooled version's own config would have every server using it write into the pool
+        if self.settings.management.game_version.is_some() {
+            args.extend(vec![
+                String::from("--config"),
+                version_pool::pooled_config_path(&self.root).display().to_string(),
+            ]);
+        }
+
+        Ok(args)
+    }
+
+    /// Switches the server's executable to the one its game version setting selects, if it isn't in use already: the
+    /// pooled version's executable if the setting is set, otherwise the server's own executable. The mods' releases
+    /// are checked against the new executable's version from then on.
+    async fn select_executable(&mut self) -> anyhow::Result<()> {
+        let path = executable_path(
+            &self.config,
+            self.settings.management.game_version,
+            &self.own_executable,
+        );
+        if path == self.executable.path() {
+            return Ok(());
+        }
+
+        let executable = Executable::new(&path).await?;
+        info!(
+            "Switching {} from Factorio {} to {} ({})",
+            self.root.display(),
+            self.executable.version(),
+            executable.version(),
+            path.display()
+        );
+        self.mods.set_game_version(executable.version());
+        self.executable = executable;
+        Ok(())
+    }
+
+    /// Runs the hook script configured for a given hook point with the server's current status. Nothing is run if the
+    /// server isn't in the program store.
+    async fn run_hook(&self, point: HookPoint) {
+        if let Some(store_id) = self.store_id_option().await {
+            let status = self.status().await.game_status();
+            hooks::run(&self.config, &self.store, store_id, point, &self.root, status).await;
+        }
+    }
+
+    /// Makes the game write its script output to the custom script output location, if one is set, by replacing the
+    /// game's script output directory in the server's root directory with a symlink to it. The game's directory is
+    /// only replaced if it's empty.
+    fn link_script_output(&self) -> anyhow::Result<()> {
+        let target = self.settings.paths.script_output(&self.root);
+        let link = Paths::game_script_output(&self.root);
+        if self.settings.paths.script_output.is_none() || target == link {
+            return Ok(());
+        }
+
+        match fs::symlink_metadata(&link) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                if fs::read_link(&link)? == target {
+                    return Ok(());
+                }
+                fs::remove_file(&link)?;
+            }
+            Ok(meta) if meta.is_dir() && fs::read_dir(&link)?.next().is_none() => fs::remove_dir(&link)?,
+            Ok(_) => return Err(ServerError::ScriptOutputInUse(link).into()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        debug!("Linking script output {} to {}", link.display(), target.display());
+        std::os::unix::fs::symlink(&target, &link)?;
+        Ok(())
+    }
+}
+
+impl StatusHandle {
+    /// Returns the server's root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the server's store ID. The value is `None` if the server has been newly created and hasn't yet been
+    /// added to the program store.
+    pub async fn store_id_option(&self) -> Option<GameStoreId> {
+        *self.store_id.lock().await
+    }
+
+    /// Returns the server's unique name, or `None` if it doesn't have one.
+    pub async fn name(&self) -> Option<String> {
+        self.name.lock().await.clone()
+    }
+
+    /// Returns the server's status.
+    pub async fn status(&self) -> ServerStatus {
+        self.status.read().await.clone()
+    }
+
+    /// Returns how many players are currently in the game.
+    pub async fn online_players(&self) -> usize {
+        self.players.lock().await.online()
+    }
+}
+
+impl PendingSave {
+    /// Waits for the game to finish the save and returns the written save file. Autosaves finishing in the meantime are
+    /// skipped. Returns `ServerError::SaveTimedOut` if the game doesn't finish the save within the configured saving
+    /// timeout, or `ServerError::ExitedWhileSaving` if the game exits before finishing it.
+    pub async fn wait(self) -> anyhow::Result<PathBuf> {
+        let PendingSave {
+            name,
+            mut saved_rx,
+            mut shutdown_rx,
+            timeout,
+        } = self;
+
+        let waited = async {
+            tokio::select! {
+                saved = wait_for_save(&mut saved_rx, &name) => saved,
+                _ = shutdown_rx.recv() => Err(ServerError::ExitedWhileSaving.into()),
+            }
+        };
+
+        match timeout {
+            Some(timeout) => time::timeout(timeout, waited)
+                .await
+                .map_err(|_| ServerError::SaveTimedOut(timeout))?,
+            None => waited.await,
+        }
     }
 }
 
 impl Importer {
     /// Returns a new `Importer` using a certain path as the new Factorio server instance's root
     /// directory.
-    pub fn from_root<P>(root: P) -> anyhow::Result<Self>
+    pub async fn from_root<P>(root: P) -> anyhow::Result<Self>
     where
         P: AsRef<Path>,
     {
         Ok(Self {
-            root: root.as_ref().canonicalize()?,
+            root: tokio::fs::canonicalize(&root).await?,
             settings: PathBuf::from(SERVER_SETTINGS_FILENAME),
             executable: root.as_ref().join(executable::DEFAULT_PATH),
             game_store_id: None,
+            name: None,
+            settings_from_file: false,
             prog_tx: None,
         })
     }
 
     /// Returns a new `Importer` with information from a stored `Game`.
     pub fn from_store(stored_game: &models::Game) -> Self {
-        let root = PathBuf::from(&stored_game.path);
+        let root = util::path_from_bytes(&stored_game.path);
         let executable = root.join(executable::DEFAULT_PATH);
 
         Self {
@@ -337,6 +1252,8 @@ impl Importer {
             settings: PathBuf::from(SERVER_SETTINGS_FILENAME),
             executable,
             game_store_id: Some(stored_game.id),
+            name: stored_game.name.clone(),
+            settings_from_file: false,
             prog_tx: None,
         }
     }
@@ -365,6 +1282,15 @@ impl Importer {
         }
     }
 
+    /// Read a stored server's settings from its settings file instead of the program store, picking up any changes
+    /// made to the file.
+    pub fn with_settings_from_file(self) -> Self {
+        Self {
+            settings_from_file: true,
+            ..self
+        }
+    }
+
     /// Specifies an `AsyncProgressChannel` to use for status updates when importing the game.
     pub fn with_status_updates(self, prog_tx: AsyncProgressChannel) -> Self {
         Self {
@@ -373,6 +1299,83 @@ impl Importer {
         }
     }
 
+    /// Reads the server's settings from its settings file. If the file doesn't exist, the settings are generated from
+    /// the game's bundled example settings file and written to the settings file.
+    async fn read_settings_file(&self) -> anyhow::Result<ServerSettings> {
+        let path = self.root.join(&self.settings);
+        if tokio::fs::metadata(&path).await.is_ok() {
+            let settings = ServerSettings::from_game_json(&tokio::fs::read_to_string(&path).await?)?;
+            debug!("Read settings from file: {:?}", settings);
+            return Ok(settings);
+        }
+
+        warn!(
+            "Server settings file {} doesn't exist, generating it from the bundled example settings",
+            path.display()
+        );
+        self.prog_tx
+            .send_status(async_status::indefinite(
+                "Server settings file missing, generating it from the example settings...",
+            ))
+            .await?;
+
+        let example = tokio::fs::read_to_string(self.root.join(EXAMPLE_SERVER_SETTINGS_PATH)).await?;
+        let name = self.root.get_file_name()?;
+        let settings = ServerSettings::from_example_game_json(&example, &name)?;
+        debug!("Generated settings from example: {:?}", settings);
+
+        let transaction = FileTransaction::new(&self.root).with_file(&path, settings.to_game_json()?);
+        task::spawn_blocking(move || transaction.commit()).await??;
+
+        Ok(settings)
+    }
+
+    /// Compares the settings file to given stored settings and the settings file's last mtime they're in sync with.
+    /// Returns the mtime the stored settings are in sync with afterwards, and the file's version of the settings if
+    /// the file was modified after the settings were stored and its settings differ from the stored ones.
+    async fn compare_settings_file(
+        &self,
+        stored: &ServerSettings,
+        stored_last_mtime: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<(Option<DateTime<Utc>>, Option<SettingsConflict>)> {
+        let path = self.root.join(&self.settings);
+        let file_last_mtime = match settings_file_last_mtime(&path).await {
+            Some(last_mtime) if Some(last_mtime) != stored_last_mtime => last_mtime,
+            _ => return Ok((stored_last_mtime, None)),
+        };
+
+        let mut settings = ServerSettings::from_game_json(&tokio::fs::read_to_string(&path).await?)?;
+        // the management, path, schedule, autosave policy, autosave retention and include settings exist only in the
+        // store
+        settings.management = stored.management.clone();
+        settings.paths = stored.paths.clone();
+        settings.schedule = stored.schedule.clone();
+        settings.autosave_policy = stored.autosave_policy.clone();
+        settings.autosave_retention = stored.autosave_retention.clone();
+        settings.includes = stored.includes.clone();
+
+        if settings.to_game_json()? == stored.to_game_json()? {
+            debug!(
+                "Settings file {} modified but its settings match the stored ones",
+                path.display()
+            );
+            return Ok((Some(file_last_mtime), None));
+        }
+
+        warn!(
+            "Settings file {} was modified after its settings were stored and they differ. Using the stored settings \
+             until the conflict is resolved",
+            path.display()
+        );
+        Ok((
+            stored_last_mtime,
+            Some(SettingsConflict {
+                settings,
+                file_last_mtime,
+            }),
+        ))
+    }
+
     /// Finalise the builder and return the imported Factorio server instance.
     pub async fn import<'a>(
         self,
@@ -380,26 +1383,58 @@ impl Importer {
         portal: Arc<ModPortal>,
         store: Arc<Store>,
     ) -> anyhow::Result<Factorio> {
-        let mut mods_builder = ModsBuilder::root(self.root.join(MODS_PATH));
-
         self.prog_tx
             .send_status(async_status::indefinite("Reading server settings..."))
             .await?;
-        let settings = if let Some(game_store_id) = self.game_store_id {
-            // TODO: ugly side effect
-            mods_builder = mods_builder.with_game_store_id(game_store_id);
 
-            let settings = ServerSettings::from_store_format(&store.get_settings(game_store_id).await?)?;
-            debug!("Read settings from store: {:?}", settings);
-            // TODO: if the settings are changed on disk, reload them from there. use the file's last mtime as an
-            // indicator
-            settings
-        } else {
-            let settings = ServerSettings::from_game_json(&fs::read_to_string(self.root.join(self.settings))?)?;
-            debug!("Read settings from file: {:?}", settings);
-            settings
+        let mut settings_conflict = None;
+        let (settings, settings_last_mtime) = match self.game_store_id {
+            Some(game_store_id) if !self.settings_from_file => {
+                let stored = store.get_settings(game_store_id).await?;
+                let settings = ServerSettings::from_store_format(&stored)?;
+                debug!("Read settings from store: {:?}", settings);
+
+                let (last_mtime, conflict) = self
+                    .compare_settings_file(&settings, stored.settings_last_mtime)
+                    .await?;
+                settings_conflict = conflict;
+                (settings, last_mtime)
+            }
+            Some(game_store_id) => {
+                // the management, path, schedule, autosave policy, autosave retention and include settings exist only
+                // in the store
+                let mut settings = self.read_settings_file().await?;
+                let stored = ServerSettings::from_store_format(&store.get_settings(game_store_id).await?)?;
+                settings.management = stored.management;
+                settings.paths = stored.paths;
+                settings.schedule = stored.schedule;
+                settings.autosave_policy = stored.autosave_policy;
+                settings.autosave_retention = stored.autosave_retention;
+                settings.includes = stored.includes;
+                (
+                    settings,
+                    settings_file_last_mtime(&self.root.join(&self.settings)).await,
+                )
+            }
+            None => (
+                self.read_settings_file().await?,
+                settings_file_last_mtime(&self.root.join(&self.settings)).await,
+            ),
         };
 
+        settings.paths.validate(&self.root).await?;
+        let store_id = Arc::new(Mutex::new(self.game_store_id));
+        let mut mods_builder =
+            ModsBuilder::root(settings.paths.mods(&self.root)).with_store_id_handle(Arc::clone(&store_id));
+        if let Some(game_store_id) = self.game_store_id {
+            mods_builder = mods_builder.with_game_store_id(game_store_id);
+        }
+
+        if !settings.management.mods {
+            info!("Mods of {} aren't managed, not loading them", self.root.display());
+            mods_builder = mods_builder.without_loading();
+        }
+
         if let Some(prog_tx) = &self.prog_tx {
             mods_builder = mods_builder.with_status_updates(prog_tx.clone());
         }
@@ -407,34 +1442,174 @@ impl Importer {
         self.prog_tx
             .send_status(async_status::indefinite("Verifying executable..."))
             .await?;
-        let executable = Executable::new(self.executable).await?;
+        let executable = Executable::new(executable_path(
+            &config,
+            settings.management.game_version,
+            &self.executable,
+        ))
+        .await?;
+        mods_builder = mods_builder.with_game_version(executable.version());
 
         self.prog_tx
             .send_status(async_status::indefinite("Loading mods..."))
             .await?;
-        let mods = mods_builder.build(config, portal, Arc::clone(&store)).await?;
+        let offenders = OffenderTracker::new(config.ban_threshold(), config.ban_window());
+        let mods = mods_builder
+            .build(Arc::clone(&config), portal, Arc::clone(&store))
+            .await?;
+
+        let mut status = ServerStatus::default();
+        status.set_settings_conflict(settings_conflict.is_some());
 
         Ok(Factorio {
             settings,
             mods,
             executable,
+            own_executable: self.executable,
             root: self.root,
-            store_id: Arc::new(Mutex::new(self.game_store_id)),
+            store_id,
+            name: Arc::new(Mutex::new(self.name)),
             store,
-            status: Arc::new(RwLock::new(ServerStatus::default())),
+            status: Arc::new(RwLock::new(status)),
+            offenders: Arc::new(Mutex::new(offenders)),
+            players: Arc::new(Mutex::new(Players::new())),
+            config,
             exec_stdin_tx: Mutex::new(None),
+            exec_kill_tx: Mutex::new(None),
             exec_stdout_rx: Mutex::new(None),
             exec_shutdown_rx: Mutex::new(None),
+            saved_tx: broadcast::channel(SAVED_CHANNEL_CAPACITY).0,
+            chat_tx: broadcast::channel(CHAT_CHANNEL_CAPACITY).0,
+            settings_last_mtime,
+            settings_conflict,
+            settings_revision: next_settings_revision(),
         })
     }
 }
 
-/// Processes a given `GameEvent` for a certain game (identified by `store_id`) and modifies a given `ServerStatus`
-/// accordingly.
-async fn process_game_event(store_id: GameStoreId, event: GameEvent, status: &RwLock<ServerStatus>) {
+/// Returns a new settings revision that hasn't been returned before.
+fn next_settings_revision() -> u64 {
+    NEXT_SETTINGS_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Returns the location of the executable a server runs: a given pooled game version's executable in the version pool
+/// of a given config, or the server's given own executable if it doesn't use a pooled version.
+fn executable_path(config: &Config, game_version: Option<HumanVersion>, own_executable: &Path) -> PathBuf {
+    match game_version {
+        Some(version) => VersionPool::new(config.versions_directory()).executable(version),
+        None => own_executable.to_path_buf(),
+    }
+}
+
+/// Waits for a save with a given name to be received from a given finished save receiver and returns it. An empty name
+/// waits for the first save that isn't an autosave.
+async fn wait_for_save(saved_rx: &mut broadcast::Receiver<PathBuf>, name: &str) -> anyhow::Result<PathBuf> {
+    let wanted = Path::new(name).file_stem();
+    loop {
+        let path = match saved_rx.recv().await {
+            Ok(path) => path,
+            Err(broadcast::RecvError::Lagged(skipped)) => {
+                warn!("Skipped {} finished saves while waiting for a save", skipped);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let stem = path.file_stem();
+        let is_wanted = match wanted {
+            Some(wanted) => stem == Some(wanted),
+            None => !stem.map_or(false, |stem| stem.to_string_lossy().starts_with(AUTOSAVE_PREFIX)),
+        };
+
+        if is_wanted {
+            return Ok(path);
+        }
+        debug!("Skipping finished save {} while waiting for a save", path.display());
+    }
+}
+
+/// Records a given mod set as the last known good mod set of a certain game (identified by `store_id`) in a given
+/// program store. Failing to record it is only logged.
+async fn record_last_known_good_mods(store_id: GameStoreId, modpack: &Modpack, store: &Store) {
+    let recorded = match modpack.to_store_format() {
+        Ok(modpack) => {
+            store
+                .set_last_known_good_mods(models::LastKnownGoodMods {
+                    game: store_id,
+                    modpack,
+                    recorded_at: Utc::now(),
+                })
+                .await
+        }
+        Err(e) => Err(e),
+    };
+
+    match recorded {
+        Ok(()) => debug!("Game ID {} recorded its mods as last known good", store_id),
+        Err(e) => error!("Game ID {} failed to record last known good mods: {}", store_id, e),
+    }
+}
+
+/// Returns the most recently modified save file in a given saves directory, or `ServerError::NoSaves` if there are no
+/// saves in it.
+fn latest_save(saves: &Path) -> anyhow::Result<PathBuf> {
+    let mut latest = None;
+    for save in util::glob(saves.join(SAVES_GLOB))? {
+        let last_mtime = util::file::get_last_mtime(&save)?;
+        if latest
+            .as_ref()
+            .map_or(true, |(latest_mtime, _)| last_mtime > *latest_mtime)
+        {
+            latest = Some((last_mtime, save));
+        }
+    }
+
+    latest
+        .map(|(_, save)| save)
+        .ok_or_else(|| ServerError::NoSaves(saves.to_path_buf()).into())
+}
+
+/// Returns the last mtime of a settings file at a given path, or `None` if it can't be read.
+async fn settings_file_last_mtime(path: &Path) -> Option<DateTime<Utc>> {
+    let meta = tokio::fs::metadata(path).await.ok()?;
+    Some(meta.modified().ok()?.to_chrono())
+}
+
+/// Processes a given `TimedGameEvent` for a certain game (identified by `store_id`) and modifies a given
+/// `ServerStatus` accordingly. Refused connections are recorded to a given `OffenderTracker` at the time they happened
+/// in the game, and the configured ban action is taken for new offenders. Peer connections and players joining and
+/// leaving are recorded to a given `Players`, and the saves the game finishes and the chat messages are sent to given
+/// transmit channels. Events from user-defined parsers are logged at their severity and counted in the status, and the
+/// mod loading errors are kept in the status with remediations suggested based on the game's available mod updates and
+/// last known good mods. Players joining and leaving, chat messages, the ban actions taken and the events from
+/// user-defined parsers are also recorded into the game's event history with a given `Recorder`.
+#[allow(clippy::too_many_arguments)]
+async fn process_game_event(
+    store_id: GameStoreId,
+    event: TimedGameEvent,
+    status: &RwLock<ServerStatus>,
+    offenders: &Mutex<OffenderTracker>,
+    players: &Mutex<Players>,
+    config: &Arc<Config>,
+    store: &Store,
+    events: &event_history::Recorder,
+    stdin_tx: &mpsc::Sender<String>,
+    saved_tx: &broadcast::Sender<PathBuf>,
+    chat_tx: &broadcast::Sender<ChatMessage>,
+    autosave: &AutosaveThrottle,
+) {
     debug!("Game ID {} got new game event: {:?}", store_id, event);
+    let time = event.time();
 
-    match event {
+    match event.event {
+        GameEvent::Version { version } => {
+            debug!("Game ID {} running version {}", store_id, version);
+            status.write().await.set_game_version(version);
+        }
+        GameEvent::LoadingSave { path } => {
+            info!("Game ID {} loading save {}", store_id, path.display());
+            status.write().await.set_current_save(path);
+        }
         GameEvent::GameStateChanged { from: _, to } => {
             let mut status_w = status.write().await;
             status_w.set_in_game_status(to);
@@ -462,27 +1637,446 @@ async fn process_game_event(store_id: GameStoreId, event: GameEvent, status: &Rw
                 "Game ID {} refusing connection for '{}' (addr {}): {}",
                 store_id, username, peer, reason
             );
+
+            let offender = offenders.lock().await.record(&peer, &username, &reason, time);
+            if let Some(offender) = offender {
+                let message = format!(
+                    "{} refused {} times: {}",
+                    offender.address(),
+                    offender.attempts(),
+                    reason
+                );
+                events.record(EventKind::Ban, time, Some(&offender.usernames().join(", ")), &message);
+                // the action may wait on a webhook or an external command, so the server's output keeps being
+                // processed meanwhile
+                let (config, stdin_tx) = (Arc::clone(config), stdin_tx.clone());
+                task::spawn(util::correlation::for_server(store_id, async move {
+                    if let Err(e) = take_ban_action(store_id, &offender, &config, stdin_tx).await {
+                        error!(
+                            "Game ID {} failed to take ban action for {}: {}",
+                            store_id,
+                            offender.address(),
+                            e
+                        );
+                    }
+                }));
+            }
+        }
+        GameEvent::PeerAdded { peer_id, address } => {
+            debug!("Game ID {} peer {} connected from {}", store_id, peer_id, address);
+            players.lock().await.peer_added(&peer_id, &address);
+        }
+        GameEvent::PeerInfo { peer_id, username } => {
+            debug!("Game ID {} peer {} is {}", store_id, peer_id, username);
+            players.lock().await.peer_info(&peer_id, &username);
         }
         GameEvent::PeerJoined { username } => {
             info!("Game ID {}: {} joined the game", store_id, username);
+            events.record(EventKind::Join, time, Some(&username), "");
+            let online = {
+                let mut players = players.lock().await;
+                players.joined(&username, time);
+                players.online()
+            };
+            throttle_autosaves(store_id, online, autosave, status, stdin_tx).await;
         }
         GameEvent::PeerLeft { username } => {
             info!("Game ID {}: {} left the game", store_id, username);
+            events.record(EventKind::Leave, time, Some(&username), "");
+            let online = {
+                let mut players = players.lock().await;
+                players.left(&username, time);
+                players.online()
+            };
+            throttle_autosaves(store_id, online, autosave, status, stdin_tx).await;
+        }
+        GameEvent::SavingStarted { path } => {
+            debug!("Game ID {} saving to {}", store_id, path.display());
+            status.write().await.set_saving_to(path);
+        }
+        GameEvent::SavingFinished => {
+            if let Some(path) = status.write().await.take_saving_to() {
+                info!("Game ID {} saved {}", store_id, path.display());
+                // there's nobody to receive the save if it wasn't requested
+                saved_tx.send(path).ok();
+            }
+        }
+        GameEvent::ModLoadFailed { mod_name, reason } => {
+            error!(
+                "Game ID {} failed to load mod {}: {}",
+                store_id,
+                mod_name.as_deref().unwrap_or("(unknown)"),
+                reason
+            );
+
+            let update_available = match &mod_name {
+                Some(name) => status.read().await.available_updates().contains_key(name),
+                None => false,
+            };
+            let can_revert = match store.get_last_known_good_mods(store_id).await {
+                Ok(mods) => mods.is_some(),
+                Err(e) => {
+                    warn!("Game ID {} failed to get last known good mods: {}", store_id, e);
+                    false
+                }
+            };
+            status.write().await.add_startup_error(StartupError::new(
+                mod_name,
+                reason,
+                time,
+                update_available,
+                can_revert,
+            ));
+        }
+        GameEvent::Performance { tick, ups } => {
+            trace!("Game ID {} at tick {}, {} UPS", store_id, tick, ups);
+            status.write().await.set_performance(Performance { tick, ups });
+        }
+        GameEvent::Chat { username, message } => {
+            debug!("Game ID {} chat: {}: {}", store_id, username, message);
+            events.record(EventKind::Chat, time, Some(&username), &message);
+            // there's nobody to receive the message if no chat bridge is open
+            chat_tx
+                .send(ChatMessage {
+                    username,
+                    message,
+                    time,
+                })
+                .ok();
+        }
+        GameEvent::Custom {
+            name,
+            severity,
+            message,
+            fields,
+        } => {
+            log!(
+                severity.to_level(),
+                "Game ID {} event '{}': {} {:?}",
+                store_id,
+                name,
+                message,
+                fields
+            );
+            status.write().await.add_custom_event(&name);
+            let message = format!("{}: {}", name, message);
+            events.record(EventKind::Custom, time, None, &message);
         }
         _ => {}
     }
 }
 
+/// Changes a given running server's autosave interval with the game's `/config` command if its autosave policy calls
+/// for a different interval for a given amount of players in the game than the current one.
+async fn throttle_autosaves(
+    store_id: GameStoreId,
+    online: usize,
+    autosave: &AutosaveThrottle,
+    status: &RwLock<ServerStatus>,
+    stdin_tx: &mpsc::Sender<String>,
+) {
+    if autosave.policy.is_empty() {
+        return;
+    }
+
+    let interval = autosave.policy.interval(online, autosave.default_interval);
+    let current = status
+        .read()
+        .await
+        .autosave_interval()
+        .unwrap_or(autosave.default_interval);
+    if interval == current {
+        return;
+    }
+
+    info!(
+        "Game ID {} has {} players in the game, changing autosave interval from {} to {} minutes",
+        store_id, online, current, interval
+    );
+    let command = format!("/config set autosave-interval {}\n", interval);
+    // the interval is recorded only once the command is sent, so a failed send is retried on the next player change
+    if let Err(e) = stdin_tx.clone().send(command).await {
+        error!("Game ID {} failed to change autosave interval: {}", store_id, e);
+        return;
+    }
+    status.write().await.set_autosave_interval(interval);
+}
+
+/// Takes the configured ban action for a given offender on a certain game (identified by `store_id`).
+async fn take_ban_action(
+    store_id: GameStoreId,
+    offender: &Offender,
+    config: &Config,
+    mut stdin_tx: mpsc::Sender<String>,
+) -> anyhow::Result<()> {
+    warn!(
+        "Game ID {}: {} refused {} times (usernames {:?}), taking action {:?}",
+        store_id,
+        offender.address(),
+        offender.attempts(),
+        offender.usernames(),
+        config.ban_action()
+    );
+
+    match config.ban_action() {
+        BanAction::Log => {}
+        BanAction::Webhook => {
+            reqwest::Client::new()
+                .post(config.ban_webhook())
+                .json(&serde_json::json!({ "server_id": store_id, "offender": offender }))
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        BanAction::Command => {
+            let output = tokio::process::Command::new(config.ban_command())
+                .arg(offender.address())
+                .args(offender.usernames())
+                .output()
+                .await?;
+
+            if !output.status.success() {
+                return Err(anyhow::anyhow!(
+                    "ban command exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+        }
+        BanAction::Ban => {
+            for username in offender.usernames() {
+                stdin_tx
+                    .send(format!("/ban {} Repeatedly refused connection\n", username))
+                    .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Processes a given executable exit event for a certain game (identified by `store_id`) and modifies a given
-/// `ServerStatus` accordingly.
-async fn process_exited_event(store_id: GameStoreId, exit_result: anyhow::Result<()>, status: &RwLock<ServerStatus>) {
+/// `ServerStatus` accordingly. Crashes are also recorded into the game's event history with a given `Recorder`.
+async fn process_exited_event(
+    store_id: GameStoreId,
+    exit_result: anyhow::Result<()>,
+    status: &RwLock<ServerStatus>,
+    config: &Config,
+    events: &event_history::Recorder,
+) {
     debug!("Game ID {} executable exited with {:?}", store_id, exit_result);
 
+    let exit_result = exit_result.or_else(|e| match e.downcast_ref::<ExecutableError>() {
+        Some(ExecutableError::Unsuccesfull {
+            exit_code: Some(exit_code),
+            ..
+        }) if config.clean_exit_codes().contains(exit_code) => {
+            debug!(
+                "Game ID {} exit code {} is configured as a clean exit",
+                store_id, exit_code
+            );
+            Ok(())
+        }
+        _ => Err(e),
+    });
+
     if let Err(e) = exit_result {
         error!("Game ID {} executable exited with error: {:?}", store_id, e);
-        status.write().await.set_game_status(ExecutionStatus::Crashed);
+        {
+            let mut status_w = status.write().await;
+            status_w.set_game_status(ExecutionStatus::Crashed);
+            status_w.add_crash();
+        }
+        events.record(EventKind::Crash, Utc::now(), None, &e.to_string());
     } else {
         info!("Game ID {} exited succesfully", store_id);
         status.write().await.set_game_status(ExecutionStatus::Shutdown);
     }
 }
+
+/// Returns the console line that sends given arguments as a single chat message. Anything written to the console that
+/// isn't a command is sent to the chat, so line breaks are replaced with spaces and leading slashes removed to keep the
+/// message from running commands.
+fn chat_console_line(arguments: &[String]) -> String {
+    format!("{}\n", chat::single_line(&arguments.join(" ")).trim_start_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::FixtureGame;
+    use settings::AutosaveThreshold;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn autosave_interval_throttled() {
+        let autosave = AutosaveThrottle {
+            policy: AutosavePolicy::new(vec![AutosaveThreshold {
+                players: 10,
+                interval: 30,
+            }])
+            .expect("failed to create autosave policy"),
+            default_interval: 10,
+        };
+        let status = RwLock::new(ServerStatus::default());
+        let (stdin_tx, mut stdin_rx) = mpsc::channel(1);
+
+        throttle_autosaves(1, 5, &autosave, &status, &stdin_tx).await;
+        assert_eq!(status.read().await.autosave_interval(), None);
+
+        throttle_autosaves(1, 12, &autosave, &status, &stdin_tx).await;
+        assert_eq!(
+            stdin_rx.recv().await.as_deref(),
+            Some("/config set autosave-interval 30\n")
+        );
+        assert_eq!(status.read().await.autosave_interval(), Some(30));
+
+        // the interval isn't recorded if the command can't be sent
+        drop(stdin_rx);
+        throttle_autosaves(1, 5, &autosave, &status, &stdin_tx).await;
+        assert_eq!(status.read().await.autosave_interval(), Some(30));
+    }
+
+    #[test]
+    fn chat_line_runs_no_commands() {
+        let arguments = vec![String::from("/hello"), String::from("there\n/quit\r\n/c game")];
+        assert_eq!(chat_console_line(&arguments), "hello there /quit  /c game\n");
+    }
+
+    #[tokio::test]
+    async fn settings_conflict() {
+        let server = FixtureGame::new().await.expect("failed to create fixture game");
+
+        let game = server.import().await.expect("failed to import server");
+        game.update_store(None).await.expect("failed to update store");
+        let stored_game = server.store().get_games().await.expect("failed to get games").remove(0);
+
+        // make sure the modification changes the file's mtime on coarse-grained filesystems
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+        let path = server.root().join(SERVER_SETTINGS_FILENAME);
+        let contents = fs::read_to_string(&path).expect("failed to read settings file");
+        fs::write(&path, contents.replace("sample server", "modified server")).expect("failed to write settings file");
+
+        let mut game = server
+            .import_stored(&stored_game)
+            .await
+            .expect("failed to import server");
+        assert_eq!(game.settings().information.name, "sample server");
+        assert_eq!(
+            game.settings_conflict().expect("no settings conflict").information.name,
+            "modified server"
+        );
+        assert!(game.status().await.settings_conflict());
+
+        game.resolve_settings_conflict(SettingsSource::File)
+            .await
+            .expect("failed to resolve settings conflict");
+        assert_eq!(game.settings().information.name, "modified server");
+        assert!(game.settings_conflict().is_none());
+        assert!(!game.status().await.settings_conflict());
+
+        let game = server
+            .import_stored(&stored_game)
+            .await
+            .expect("failed to import server");
+        assert_eq!(game.settings().information.name, "modified server");
+        assert!(game.settings_conflict().is_none());
+    }
+
+    #[tokio::test]
+    async fn create_with_imported_map_gen_settings() {
+        let server = FixtureGame::new().await.expect("failed to create fixture game");
+        let game = server.import().await.expect("failed to import server");
+        let create = || Tool::Create {
+            save: PathBuf::from("new-map.zip"),
+        };
+
+        let output = game.run_tool(create()).await.expect("failed to run create tool");
+        assert!(!output.stdout.contains("--map-gen-settings"));
+
+        let path = server.root().join(MAP_GEN_SETTINGS_FILENAME);
+        fs::write(&path, "{}").expect("failed to write map generation settings");
+        let output = game.run_tool(create()).await.expect("failed to run create tool");
+        assert!(output
+            .stdout
+            .contains(&format!("--map-gen-settings {}", path.display())));
+    }
+
+    #[tokio::test]
+    async fn executable_args() {
+        let server = FixtureGame::new().await.expect("failed to create fixture game");
+        let mut game = server.import().await.expect("failed to import server");
+        let mut start_args = |behaviour, save_name: &str| {
+            game.settings_mut().start.behaviour = behaviour;
+            game.settings_mut().start.save_name = save_name.to_owned();
+            let args = game.get_executable_args().expect("failed to get executable args");
+            args[..2].to_vec()
+        };
+
+        assert_eq!(
+            start_args(StartBehaviour::LoadScenario, "base/freeplay"),
+            vec!["--start-server-load-scenario", "base/freeplay"]
+        );
+        assert_eq!(
+            start_args(StartBehaviour::Create, "new-map"),
+            vec![
+                String::from("--start-server"),
+                server.root().join("saves/new-map.zip").display().to_string()
+            ]
+        );
+
+        game.settings_mut().management.game_version = Some("1.1.0".parse().unwrap());
+        let args = game.get_executable_args().expect("failed to get executable args");
+        assert_eq!(
+            args[args.len() - 2..].to_vec(),
+            vec![
+                String::from("--config"),
+                server.root().join("config/modtorio-pooled.ini").display().to_string()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn settings_revision() {
+        let server = FixtureGame::new().await.expect("failed to create fixture game");
+        let mut game = server.import().await.expect("failed to import server");
+        let other = server.import().await.expect("failed to import server");
+        assert_ne!(game.settings_revision(), other.settings_revision());
+
+        let revision = game.settings_revision();
+        game.settings_mut().information.name = String::from("renamed server");
+        assert_ne!(game.settings_revision(), revision);
+    }
+
+    #[tokio::test]
+    async fn apply_settings_rolls_back() {
+        let server = FixtureGame::new().await.expect("failed to create fixture game");
+        let mut game = server.import().await.expect("failed to import server");
+        let settings_path = server.root().join(SERVER_SETTINGS_FILENAME);
+        let admin_list_path = server.root().join("server-adminlist.json");
+        let original = fs::read_to_string(&settings_path).expect("failed to read settings file");
+
+        game.settings_mut().information.name = String::from("renamed server");
+        game.settings_mut().access_lists.admins = Some(vec![String::from("admin")]);
+        game.settings_mut().access_lists.whitelist = Some(vec![String::from("player")]);
+        // the whitelist can't be replaced while a directory is in its place
+        fs::create_dir(server.root().join("server-whitelist.json")).expect("failed to create directory");
+
+        assert!(game.apply_settings(false).await.is_err());
+        assert_eq!(
+            fs::read_to_string(&settings_path).expect("failed to read settings file"),
+            original
+        );
+        assert!(!admin_list_path.exists());
+
+        fs::remove_dir(server.root().join("server-whitelist.json")).expect("failed to remove directory");
+        game.apply_settings(false).await.expect("failed to apply settings");
+        assert!(fs::read_to_string(&settings_path)
+            .expect("failed to read settings file")
+            .contains("renamed server"));
+        assert_eq!(
+            fs::read_to_string(&admin_list_path).expect("failed to read admin list"),
+            "[\n  \"admin\"\n]"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Provides [`AutosavePruning`](AutosavePruning), the result of pruning a server's old autosaves according to its
+//! [`AutosaveRetention`](super::settings::AutosaveRetention).
+
+use super::{settings::AutosaveRetention, AUTOSAVE_PREFIX};
+use crate::util::{self, TimeZone};
+use chrono::Utc;
+use log::*;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// The autosaves pruned from a server's saves directory.
+#[derive(Debug, PartialEq, Default)]
+pub struct AutosavePruning {
+    /// The pruned autosaves.
+    deleted: Vec<PathBuf>,
+    /// The pruned autosaves' combined size in bytes.
+    bytes_reclaimed: u64,
+    /// Whether the autosaves were only planned to be pruned and weren't deleted.
+    dry_run: bool,
+}
+
+impl AutosavePruning {
+    /// Prunes the autosaves in a given saves directory that a given retention doesn't keep, counting days in a given
+    /// time zone. In a dry run the autosaves are only listed and not deleted.
+    pub async fn prune(
+        saves: &Path,
+        retention: &AutosaveRetention,
+        time_zone: TimeZone,
+        dry_run: bool,
+    ) -> anyhow::Result<Self> {
+        let mut autosaves = Vec::new();
+        for path in util::glob(saves.join(format!("{}*.zip", AUTOSAVE_PREFIX)))? {
+            let last_mtime = util::file::get_last_mtime(&path)?;
+            autosaves.push((path, last_mtime));
+        }
+
+        let mut pruning = Self {
+            dry_run,
+            ..Self::default()
+        };
+        for path in retention.pruned(autosaves, time_zone, Utc::now()) {
+            let size = fs::metadata(&path).await?.len();
+            if dry_run {
+                debug!("Would prune autosave {}", path.display());
+            } else {
+                debug!("Pruning autosave {}", path.display());
+                fs::remove_file(&path).await?;
+            }
+
+            pruning.bytes_reclaimed += size;
+            pruning.deleted.push(path);
+        }
+
+        Ok(pruning)
+    }
+
+    /// Returns the pruned autosaves.
+    pub fn deleted(&self) -> &[PathBuf] {
+        &self.deleted
+    }
+
+    /// Returns the pruned autosaves' combined size in bytes.
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.bytes_reclaimed
+    }
+}
+
+impl From<AutosavePruning> for rpc::AutosavePruning {
+    fn from(pruning: AutosavePruning) -> Self {
+        Self {
+            deleted: pruning.deleted.iter().map(|path| path.display().to_string()).collect(),
+            bytes_reclaimed: pruning.bytes_reclaimed,
+            dry_run: pruning.dry_run,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prune() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in &["_autosave1.zip", "_autosave2.zip", "_autosave3.zip", "manual.zip"] {
+            std::fs::write(dir.path().join(name), "save").unwrap();
+        }
+        let retention = AutosaveRetention {
+            keep_recent: 1,
+            keep_daily_days: 0,
+        };
+
+        let planned = AutosavePruning::prune(dir.path(), &retention, TimeZone::default(), true)
+            .await
+            .unwrap();
+        assert_eq!(planned.deleted().len(), 2);
+        assert_eq!(planned.bytes_reclaimed(), 8);
+        assert!(planned.deleted().iter().all(|path| path.exists()));
+
+        let pruned = AutosavePruning::prune(dir.path(), &retention, TimeZone::default(), false)
+            .await
+            .unwrap();
+        assert_eq!(pruned.deleted().len(), 2);
+        assert!(pruned.deleted().iter().all(|path| !path.exists()));
+        assert!(dir.path().join("manual.zip").exists());
+    }
+}
@@ -0,0 +1,56 @@
+//! Provides [`ChatMessage`](ChatMessage), a message sent to a server's in-game chat, used to bridge the chat to and
+//! from RPC clients.
+
+use crate::util::TimeZone;
+use chrono::{DateTime, Utc};
+
+/// The name the game shows for messages sent to the chat from the server console.
+pub const SERVER_USERNAME: &str = "<server>";
+
+/// A message sent to a server's in-game chat.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChatMessage {
+    /// The name of the player who sent the message, or [`SERVER_USERNAME`](SERVER_USERNAME) for messages sent from the
+    /// server console.
+    pub username: String,
+    /// The message.
+    pub message: String,
+    /// When the message was sent.
+    pub time: DateTime<Utc>,
+}
+
+impl ChatMessage {
+    /// Returns the message in RPC format, with its timestamp also localised to a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::ChatMessage {
+        rpc::ChatMessage {
+            username: self.username.clone(),
+            message: self.message.clone(),
+            sent_at: self.time.timestamp(),
+            sent_at_local: time_zone.format(self.time),
+            from_server: self.username == SERVER_USERNAME,
+        }
+    }
+}
+
+/// Returns a given message relayed into the chat as a single line. Anything written to the server console is sent to
+/// the chat one line at a time, so line breaks and other control characters are replaced with spaces to keep the
+/// message from being split into separate lines, which could be run as commands.
+pub fn single_line(message: &str) -> String {
+    message
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_line_message() {
+        assert_eq!(single_line("hello\n/quit"), "hello /quit");
+        assert_eq!(single_line(" tab\there\r\n"), "tab here");
+    }
+}
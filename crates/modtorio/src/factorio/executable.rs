@@ -1,10 +1,19 @@
 //! Provides utilities to work with a Factorio server's executable.
 
+mod event_parser;
 mod game_event;
 mod version_information;
 
-use crate::error::ExecutableError;
-pub use game_event::GameEvent;
+use crate::{
+    error::ExecutableError,
+    util::{
+        channel::{self, OverflowStrategy},
+        HumanVersion,
+    },
+};
+use chrono::{DateTime, Utc};
+pub use event_parser::{CustomEventParser, EventParserDefinition};
+pub use game_event::{GameEvent, TimedGameEvent};
 use log::*;
 use std::{
     path::{Path, PathBuf},
@@ -26,17 +35,89 @@ pub const DEFAULT_PATH: &str = "bin/x64/factorio";
 pub struct Executable {
     /// The path to the executable.
     path: PathBuf,
+    /// The executable's Factorio version, detected when the executable was verified.
+    version: HumanVersion,
 }
 
 /// Represesnts an event that happened with the executable.
 #[derive(Debug)]
 pub enum ExecutableEvent {
     /// Represents an event that happened in the server.
-    GameEvent(GameEvent),
+    GameEvent(TimedGameEvent),
+    /// An output line that wasn't recognised as a game event.
+    UnparsedLine(String),
+    /// A given amount of output lines were discarded because the output channel was full.
+    DroppedLines(u64),
     /// The executable exited with a given result.
     Exited(anyhow::Result<()>),
 }
 
+/// Represents a one-shot utility operation of the executable.
+#[derive(Debug, PartialEq)]
+pub enum Tool {
+    /// Benchmarks a given save file for a given amount of ticks.
+    Benchmark {
+        /// The path to the save file.
+        save: PathBuf,
+        /// How many ticks to run.
+        ticks: u32,
+    },
+    /// Converts a given save file into a scenario.
+    Map2Scenario {
+        /// The path to the save file.
+        save: PathBuf,
+    },
+    /// Dumps the game's prototype data (`data.raw`) to the `script-output` directory.
+    DumpData,
+    /// Creates a new map and saves it into a given save file.
+    Create {
+        /// The path to the new save file.
+        save: PathBuf,
+    },
+}
+
+/// The captured output of a one-shot run of the executable.
+#[derive(Debug)]
+pub struct ToolOutput {
+    /// The executable's exit code, if any.
+    pub exit_code: Option<i32>,
+    /// Whether the executable exited succesfully.
+    pub success: bool,
+    /// The executable's full standard output.
+    pub stdout: String,
+    /// The executable's full standard error.
+    pub stderr: String,
+}
+
+impl Tool {
+    /// Returns the executable arguments to run this tool with.
+    fn args(&self) -> Vec<String> {
+        match self {
+            Tool::Benchmark { save, ticks } => vec![
+                String::from("--benchmark"),
+                save.display().to_string(),
+                String::from("--benchmark-ticks"),
+                ticks.to_string(),
+                String::from("--disable-audio"),
+            ],
+            Tool::Map2Scenario { save } => vec![String::from("--map2scenario"), save.display().to_string()],
+            Tool::DumpData => vec![String::from("--dump-data")],
+            Tool::Create { save } => vec![String::from("--create"), save.display().to_string()],
+        }
+    }
+}
+
+impl From<ToolOutput> for rpc::ToolOutput {
+    fn from(output: ToolOutput) -> Self {
+        Self {
+            exit_code: output.exit_code.unwrap_or(-1),
+            success: output.success,
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
 impl Executable {
     /// Returns a new `Executable` from a given path to a server executable.
     pub async fn new<P>(path: P) -> anyhow::Result<Self>
@@ -44,31 +125,36 @@ impl Executable {
         P: AsRef<Path>,
     {
         let path = path.as_ref().to_path_buf();
-        let exec = Self { path };
-        match exec.detect_version().await {
-            Ok(ver) => debug!(
-                "{} is a valid Factorio executable. Version information: {:?}",
-                exec.path().display(),
-                ver
-            ),
-            Err(e) => {
-                return Err(ExecutableError::InvalidExecutable {
-                    path: exec.into_path(),
-                    source: e,
-                }
-                .into())
+        let version = match detect_version(&path).await {
+            Ok(ver) => {
+                debug!(
+                    "{} is a valid Factorio executable. Version information: {:?}",
+                    path.display(),
+                    ver
+                );
+                ver.version()
             }
+            Err(e) => return Err(ExecutableError::InvalidExecutable { path, source: e }.into()),
         };
+        let exec = Self { path, version };
 
         Ok(exec)
     }
 
-    /// Runs this executable.
+    /// Runs this executable. Each channel between the executable and the returned event receiver buffers at most
+    /// `channel_capacity` items, and the executable's output overflows with the given strategy. Output lines the
+    /// built-in parsers don't recognise are tried with the given user-defined parsers. Receiving a value from the kill
+    /// channel kills the executable.
+    #[allow(clippy::too_many_arguments)]
     pub async fn run(
         &self,
         stdout_tx: mpsc::Sender<String>,
         mut stdin_rx: mpsc::Receiver<String>,
+        mut kill_rx: mpsc::Receiver<()>,
         args: &[String],
+        channel_capacity: usize,
+        output_overflow: OverflowStrategy,
+        custom_parsers: Vec<CustomEventParser>,
     ) -> anyhow::Result<mpsc::Receiver<ExecutableEvent>> {
         let mut child = Command::new(&self.path)
             .args(args)
@@ -76,24 +162,30 @@ impl Executable {
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
             .spawn()?;
+        let started_at = Utc::now();
 
         let stdout = child.stdout.take().ok_or_else(|| ExecutableError::NoStdioHandle)?;
         let mut stdin = child.stdin.take().ok_or_else(|| ExecutableError::NoStdioHandle)?;
         let mut stdout_reader = BufReader::new(stdout).lines();
 
-        let (mut state_tx, state_rx) = mpsc::channel(64);
-        let (mut stdout_proc_tx, mut stdout_proc_rx) = mpsc::channel::<String>(64);
-        let (mut event_tx, mut event_rx) = mpsc::channel(64);
+        let (mut state_tx, state_rx) = mpsc::channel(channel_capacity);
+        let (stdout_proc_tx, mut stdout_proc_rx) =
+            channel::channel::<(String, DateTime<Utc>)>(channel_capacity, output_overflow);
+        let (mut event_tx, mut event_rx) = mpsc::channel(channel_capacity);
 
         task::spawn(async move {
-            while let Some(stdout_line) = stdout_proc_rx.recv().await {
-                trace!("Processing stdout line: {}", stdout_line);
-                let event = match stdout_line.parse::<GameEvent>() {
-                    Ok(event) => event,
-                    Err(e) => {
-                        trace!("Couldn't parse GameEvent: {}", e);
-                        continue;
+            while let Some((stdout_line, received_at)) = stdout_proc_rx.recv().await {
+                let dropped = stdout_proc_rx.take_dropped();
+                if dropped > 0 {
+                    if let Err(e) = event_tx.send(ExecutableEvent::DroppedLines(dropped)).await {
+                        error!("Writing to event tx failed: {}", e);
                     }
+                }
+
+                trace!("Processing stdout line: {}", stdout_line);
+                let event = match TimedGameEvent::parse(&stdout_line, &custom_parsers, started_at, received_at) {
+                    Ok(event) => ExecutableEvent::GameEvent(event),
+                    Err(_) => ExecutableEvent::UnparsedLine(stdout_line),
                 };
 
                 if let Err(e) = event_tx.send(event).await {
@@ -103,6 +195,7 @@ impl Executable {
         });
 
         task::spawn(async move {
+            let mut killed = false;
             loop {
                 tokio::select! {
                     child_result = wait_for_child(&mut child) => {
@@ -113,6 +206,16 @@ impl Executable {
                         break;
                     }
 
+                    kill = kill_rx.recv(), if !killed => {
+                        killed = true;
+                        if kill.is_some() {
+                            warn!("Killing child");
+                            if let Err(e) = child.kill() {
+                                error!("Killing child failed: {}", e);
+                            }
+                        }
+                    }
+
                     msg = stdin_rx.recv() => {
                         if let Some(msg) = msg {
                             trace!("Got input from stdin channel: {}", msg);
@@ -125,7 +228,7 @@ impl Executable {
                     stdout_line = stdout_reader.next_line() => {
                         if let Some(stdout_line) = stdout_line.expect("failed to read child stdout line") {
                             debug!("Child stdout: {}", stdout_line);
-                            if let Err(e) = stdout_proc_tx.send(stdout_line).await {
+                            if let Err(e) = stdout_proc_tx.send((stdout_line, Utc::now())).await {
                                 error!("Writing stdout line to stdout processor tx failed: {}", e);
                             }
                         }
@@ -133,9 +236,9 @@ impl Executable {
 
                     event = event_rx.recv() => {
                         if let Some(event) = event {
-                            trace!("Game event from executable: {:?}", event);
+                            trace!("Event from executable: {:?}", event);
 
-                            if let Err(e) = state_tx.send(ExecutableEvent::GameEvent(event)).await {
+                            if let Err(e) = state_tx.send(event).await {
                                 error!("Writing executable state to state tx failed: {}", e);
                             }
                         }
@@ -151,19 +254,53 @@ impl Executable {
 
     /// Returns the server's version information by running the executable with the `--version` parameter.
     pub async fn detect_version(&self) -> anyhow::Result<VersionInformation> {
-        let stdout = run_executable(&self.path, &["--version"]).await?;
-        Ok(stdout.parse()?)
+        detect_version(&self.path).await
+    }
+
+    /// Returns the Factorio version detected when the executable was verified.
+    pub fn version(&self) -> HumanVersion {
+        self.version
+    }
+
+    /// Runs a given tool and returns its captured output. An unsuccesful exit isn't an error, since the output is
+    /// what the caller is interested in.
+    pub async fn run_tool(&self, tool: &Tool) -> anyhow::Result<ToolOutput> {
+        self.run_tool_with_args(tool, &[]).await
+    }
+
+    /// Runs a given tool with given additional executable arguments and returns its captured output, like
+    /// [`run_tool`](#method.run_tool).
+    pub async fn run_tool_with_args(&self, tool: &Tool, extra_args: &[String]) -> anyhow::Result<ToolOutput> {
+        let mut args = tool.args();
+        args.extend_from_slice(extra_args);
+        debug!("Running {} with {:?}", self.path.display(), args);
+
+        let output = Command::new(&self.path)
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()
+            .await?;
+        Ok(ToolOutput {
+            exit_code: output.status.code(),
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
     }
 
     /// Immutably borrows the `Executable`'s path.
     pub fn path(&self) -> &Path {
         &self.path
     }
+}
 
-    /// Consumes the `Executable` and returns its path.
-    fn into_path(self) -> PathBuf {
-        self.path
-    }
+/// Returns a given executable's version information by running it with the `--version` parameter.
+async fn detect_version<P>(path: P) -> anyhow::Result<VersionInformation>
+where
+    P: AsRef<Path>,
+{
+    let stdout = run_executable(path, &["--version"]).await?;
+    Ok(stdout.parse()?)
 }
 
 /// Runs a given executable asynchronously and returns its standard output.
@@ -201,3 +338,28 @@ async fn wait_for_child(child: &mut Child) -> anyhow::Result<()> {
         .into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_args() {
+        let benchmark = Tool::Benchmark {
+            save: PathBuf::from("saves/test.zip"),
+            ticks: 1000,
+        };
+
+        assert_eq!(
+            benchmark.args(),
+            vec![
+                "--benchmark",
+                "saves/test.zip",
+                "--benchmark-ticks",
+                "1000",
+                "--disable-audio"
+            ]
+        );
+        assert_eq!(Tool::DumpData.args(), vec!["--dump-data"]);
+    }
+}
@@ -0,0 +1,147 @@
+//! Provides [`EventParserDefinition`](EventParserDefinition), a user-defined parser for server output lines given in
+//! the config, and [`CustomEventParser`](CustomEventParser), its compiled form which turns matching lines into
+//! `GameEvent::Custom` events. They let lines logged by mods and scenario scripts, such as `[TELEMETRY] ...`, be
+//! recognised without the built-in parsers knowing about them.
+
+use super::GameEvent;
+use crate::{error::GameEventError, util::LogLevel};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A user-defined parser for server output lines.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct EventParserDefinition {
+    /// The name of the events the parser produces.
+    pub name: String,
+    /// The regular expression output lines are matched against.
+    pub pattern: String,
+    /// The template of the events' messages. Capture groups are expanded with `$1` or `${1}` for numbered groups and
+    /// `$name` or `${name}` for named ones. If empty, the message is the whole matched text.
+    #[serde(default)]
+    pub template: String,
+    /// The level the events are logged at.
+    #[serde(default)]
+    pub severity: LogLevel,
+}
+
+/// A compiled user-defined parser for server output lines.
+#[derive(Debug, Clone)]
+pub struct CustomEventParser {
+    /// The name of the events the parser produces.
+    name: String,
+    /// The compiled pattern.
+    regex: Regex,
+    /// The template of the events' messages.
+    template: String,
+    /// The level the events are logged at.
+    severity: LogLevel,
+}
+
+impl CustomEventParser {
+    /// Returns a new `CustomEventParser` by compiling a given definition. Returns
+    /// [`GameEventError::InvalidParser`][InvalidParser] if the definition's name is empty or its pattern doesn't
+    /// compile.
+    ///
+    /// [InvalidParser]: crate::error::GameEventError::InvalidParser
+    pub fn new(definition: &EventParserDefinition) -> Result<Self, GameEventError> {
+        let invalid = |reason: String| GameEventError::InvalidParser {
+            name: definition.name.clone(),
+            reason,
+        };
+
+        if definition.name.trim().is_empty() {
+            return Err(invalid(String::from("the name is empty")));
+        }
+        let regex = Regex::new(&definition.pattern).map_err(|e| invalid(e.to_string()))?;
+
+        Ok(Self {
+            name: definition.name.clone(),
+            regex,
+            template: definition.template.clone(),
+            severity: definition.severity,
+        })
+    }
+
+    /// Returns new `CustomEventParser`s by compiling given definitions, or the first definition's error.
+    pub fn compile_all(definitions: &[EventParserDefinition]) -> Result<Vec<Self>, GameEventError> {
+        definitions.iter().map(Self::new).collect()
+    }
+
+    /// Parses a given output line into `GameEvent::Custom` if it matches the parser's pattern. The event's fields are
+    /// the pattern's named capture groups that took part in the match.
+    pub fn parse(&self, s: &str) -> Option<GameEvent> {
+        let captures = self.regex.captures(s)?;
+        let message = if self.template.is_empty() {
+            captures.get(0)?.as_str().to_owned()
+        } else {
+            let mut message = String::new();
+            captures.expand(&self.template, &mut message);
+            message
+        };
+        let fields = self
+            .regex
+            .capture_names()
+            .flatten()
+            .filter_map(|name| Some((name.to_owned(), captures.name(name)?.as_str().to_owned())))
+            .collect::<BTreeMap<_, _>>();
+
+        Some(GameEvent::Custom {
+            name: self.name.clone(),
+            severity: self.severity,
+            message,
+            fields,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(name: &str, pattern: &str, template: &str) -> EventParserDefinition {
+        EventParserDefinition {
+            name: name.to_owned(),
+            pattern: pattern.to_owned(),
+            template: template.to_owned(),
+            severity: LogLevel::Warn,
+        }
+    }
+
+    #[test]
+    fn custom_event() {
+        let parser = CustomEventParser::new(&definition(
+            "telemetry",
+            r#"\[TELEMETRY\] (?P<metric>\w+)=(?P<value>\d+)"#,
+            "$metric is ${value}",
+        ))
+        .unwrap();
+
+        let event = parser.parse("  42.000 Script @__telemetry__/control.lua:5: [TELEMETRY] rockets=3");
+        assert!(
+            matches!(event, Some(GameEvent::Custom { name, severity, message, fields })
+            if name == "telemetry"
+                && severity == LogLevel::Warn
+                && message == "rockets is 3"
+                && fields["metric"] == "rockets"
+                && fields["value"] == "3")
+        );
+        assert!(parser.parse("[CHAT] player: hello").is_none());
+
+        let parser = CustomEventParser::new(&definition("whole", r#"\[ALERT\] .+$"#, "")).unwrap();
+        assert!(
+            matches!(parser.parse("1.000 [ALERT] biters!"), Some(GameEvent::Custom { message, .. })
+            if message == "[ALERT] biters!")
+        );
+    }
+
+    #[test]
+    fn invalid_definition() {
+        assert!(CustomEventParser::new(&definition("unclosed", r#"\[TELEMETRY\] (.+$"#, "")).is_err());
+        assert!(CustomEventParser::new(&definition(" ", r#"\[TELEMETRY\]"#, "")).is_err());
+        assert!(
+            CustomEventParser::compile_all(&[definition("valid", "valid", ""), definition("invalid", "(", "")])
+                .is_err()
+        );
+    }
+}
@@ -1,13 +1,30 @@
-//! Provides the `GameEvent` enum which represents a single event that happened in-game in a server.
+//! Provides the `GameEvent` enum which represents a single event that happened in-game in a server, and the
+//! `TimedGameEvent` struct which pairs an event with the times it happened and was received.
 
-use crate::{error::GameEventError, factorio::status::InGameStatus};
+use super::CustomEventParser;
+use crate::{
+    error::GameEventError,
+    factorio::status::InGameStatus,
+    util::{HumanVersion, LogLevel},
+};
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::str::FromStr;
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
 
 /// Represents a single event that happened in-game in a server.
 #[derive(Debug)]
 pub enum GameEvent {
+    /// The game logged its version when starting.
+    Version {
+        /// The game's version.
+        version: HumanVersion,
+    },
+    /// The game started loading a save.
+    LoadingSave {
+        /// The path to the save file.
+        path: PathBuf,
+    },
     /// The game's state changed.
     GameStateChanged {
         /// The previous state.
@@ -24,6 +41,20 @@ pub enum GameEvent {
         /// Reason the connection was refused.
         reason: String,
     },
+    /// A peer connected from an address.
+    PeerAdded {
+        /// The peer's ID.
+        peer_id: String,
+        /// The peer's address-port-pair.
+        address: String,
+    },
+    /// A peer sent its username.
+    PeerInfo {
+        /// The peer's ID.
+        peer_id: String,
+        /// The peer's username.
+        username: String,
+    },
     /// A peer's state changed.
     PeerStateChanged {
         /// The peer's ID.
@@ -43,18 +74,78 @@ pub enum GameEvent {
         /// The peer's username
         username: String,
     },
+    /// The game started saving its map.
+    SavingStarted {
+        /// The save file being written. Autosaves are logged with only their name.
+        path: PathBuf,
+    },
+    /// The game finished saving its map.
+    SavingFinished,
+    /// The game reported its performance.
+    Performance {
+        /// The game's current tick.
+        tick: u64,
+        /// The game's updates per second.
+        ups: f64,
+    },
+    /// The game failed to load its mods when starting.
+    ModLoadFailed {
+        /// The name of the mod that failed to load, if the message identifies it.
+        mod_name: Option<String>,
+        /// Why loading the mods failed.
+        reason: String,
+    },
+    /// A message was sent to the chat.
+    Chat {
+        /// The sender's username.
+        username: String,
+        /// The message.
+        message: String,
+    },
+    /// A line matched a user-defined parser.
+    Custom {
+        /// The name of the parser that matched.
+        name: String,
+        /// The level the event is logged at.
+        severity: LogLevel,
+        /// The message built from the parser's template.
+        message: String,
+        /// The parser's named capture groups that took part in the match.
+        fields: BTreeMap<String, String>,
+    },
+}
+
+/// A `GameEvent` along with the time it happened in the game and the time its log line was received.
+#[derive(Debug)]
+pub struct TimedGameEvent {
+    /// The event.
+    pub event: GameEvent,
+    /// The time the event happened according to its log line, if the line had a timestamp.
+    pub game_time: Option<DateTime<Utc>>,
+    /// The time the event's log line was received from the server.
+    pub received_at: DateTime<Utc>,
 }
 
 /// Type of the string parser functions.
 type ParserFn = fn(&str) -> Option<GameEvent>;
 lazy_static! {
+    // chat is parsed first so players can't make their messages parse as other events
     static ref PARSERS: Vec<ParserFn> = vec![
+        chat,
         factorio_initialised,
+        version,
+        loading_save,
         game_state_changed,
         refusing_connection,
+        peer_added,
+        peer_info,
         peer_state_change,
         peer_joined,
-        peer_left
+        peer_left,
+        saving_started,
+        saving_finished,
+        mod_load_failed,
+        performance
     ];
 }
 
@@ -62,16 +153,88 @@ impl FromStr for GameEvent {
     type Err = GameEventError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        for parser in PARSERS.iter() {
-            if let Some(event) = parser(s) {
-                return Ok(event);
-            }
+        Self::parse_with(s, &[])
+    }
+}
+
+impl GameEvent {
+    /// Parses a given log line with the built-in parsers, and if none of them match, with given user-defined parsers
+    /// in order. The built-in parsers go first so user-defined ones can't hide the events the program relies on.
+    pub fn parse_with(s: &str, custom_parsers: &[CustomEventParser]) -> Result<Self, GameEventError> {
+        PARSERS
+            .iter()
+            .find_map(|parser| parser(s))
+            .or_else(|| custom_parsers.iter().find_map(|parser| parser.parse(s)))
+            .ok_or_else(|| GameEventError::FailedToParse(s.to_owned()))
+    }
+
+    /// Returns the name of the event's kind. Custom events are all of the same kind regardless of their parser.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GameEvent::Version { .. } => "Version",
+            GameEvent::LoadingSave { .. } => "LoadingSave",
+            GameEvent::GameStateChanged { .. } => "GameStateChanged",
+            GameEvent::RefusingConnection { .. } => "RefusingConnection",
+            GameEvent::PeerAdded { .. } => "PeerAdded",
+            GameEvent::PeerInfo { .. } => "PeerInfo",
+            GameEvent::PeerStateChanged { .. } => "PeerStateChanged",
+            GameEvent::PeerJoined { .. } => "PeerJoined",
+            GameEvent::PeerLeft { .. } => "PeerLeft",
+            GameEvent::SavingStarted { .. } => "SavingStarted",
+            GameEvent::SavingFinished => "SavingFinished",
+            GameEvent::ModLoadFailed { .. } => "ModLoadFailed",
+            GameEvent::Performance { .. } => "Performance",
+            GameEvent::Chat { .. } => "Chat",
+            GameEvent::Custom { .. } => "Custom",
         }
+    }
+}
 
-        Err(GameEventError::FailedToParse(s.to_owned()))
+impl TimedGameEvent {
+    /// Parses a given log line received at a given time from a server started at a given time, with given
+    /// user-defined parsers after the built-in ones.
+    pub fn parse(
+        line: &str,
+        custom_parsers: &[CustomEventParser],
+        started_at: DateTime<Utc>,
+        received_at: DateTime<Utc>,
+    ) -> Result<Self, GameEventError> {
+        Ok(Self {
+            event: GameEvent::parse_with(line, custom_parsers)?,
+            game_time: parse_timestamp(line, started_at),
+            received_at,
+        })
+    }
+
+    /// Returns the time the event happened in the game if known, otherwise the time it was received.
+    pub fn time(&self) -> DateTime<Utc> {
+        self.game_time.unwrap_or(self.received_at)
     }
 }
 
+/// Parses the timestamp at the start of a given log line from a server started at a given time. Most lines begin with
+/// the seconds elapsed since the server started, while chat lines begin with the server's local date and time.
+fn parse_timestamp(line: &str, started_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    lazy_static! {
+        static ref ELAPSED_RE: Regex =
+            Regex::new(r#"^\s*(\d+\.\d+) "#).expect("failed to create elapsed timestamp regex");
+        static ref DATETIME_RE: Regex =
+            Regex::new(r#"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}) "#).expect("failed to create date timestamp regex");
+    }
+
+    if let Some(captures) = ELAPSED_RE.captures(line) {
+        let elapsed = captures.get(1)?.as_str().parse::<f64>().ok()?;
+        return Some(started_at + Duration::milliseconds((elapsed * 1000.0) as i64));
+    }
+
+    let captures = DATETIME_RE.captures(line)?;
+    let naive = NaiveDateTime::parse_from_str(captures.get(1)?.as_str(), "%Y-%m-%d %H:%M:%S").ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|local| local.with_timezone(&Utc))
+}
+
 /// Parses the "Factorio initialised" message into `GameEvent::GameStateChanged`.
 fn factorio_initialised(s: &str) -> Option<GameEvent> {
     if s.ends_with("Factorio initialised") {
@@ -84,6 +247,32 @@ fn factorio_initialised(s: &str) -> Option<GameEvent> {
     }
 }
 
+/// Parses the game's version message logged at startup into `GameEvent::Version`.
+fn version(s: &str) -> Option<GameEvent> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r#"; Factorio (\d+\.\d+\.\d+) \(build"#).expect("failed to create game version regex");
+    }
+
+    let captures = RE.captures(s)?;
+    let version = captures.get(1)?.as_str().parse().ok()?;
+
+    Some(GameEvent::Version { version })
+}
+
+/// Parses the save loading message into `GameEvent::LoadingSave`.
+fn loading_save(s: &str) -> Option<GameEvent> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r#"Loading map (.+): \d+ bytes\.$"#).expect("failed to create loading save regex");
+    }
+
+    let captures = RE.captures(s)?;
+    let path = PathBuf::from(captures.get(1)?.as_str());
+
+    Some(GameEvent::LoadingSave { path })
+}
+
 /// Parses the game's state change message into `GameEvent::GameStateChanged`.
 fn game_state_changed(s: &str) -> Option<GameEvent> {
     lazy_static! {
@@ -114,6 +303,34 @@ fn refusing_connection(s: &str) -> Option<GameEvent> {
     Some(GameEvent::RefusingConnection { peer, username, reason })
 }
 
+/// Parses the peer addition message into `GameEvent::PeerAdded`.
+fn peer_added(s: &str) -> Option<GameEvent> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r#"adding peer\((\d+)\) address\((\S+)\)"#).expect("failed to create peer addition regex");
+    }
+
+    let captures = RE.captures(s)?;
+    let peer_id = captures.get(1)?.as_str().to_owned();
+    let address = captures.get(2)?.as_str().to_owned();
+
+    Some(GameEvent::PeerAdded { peer_id, address })
+}
+
+/// Parses the peer info message into `GameEvent::PeerInfo`.
+fn peer_info(s: &str) -> Option<GameEvent> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r#"Received peer info for peer\((\d+)\) username\((\S+)\)"#)
+            .expect("failed to create peer info regex");
+    }
+
+    let captures = RE.captures(s)?;
+    let peer_id = captures.get(1)?.as_str().to_owned();
+    let username = captures.get(2)?.as_str().to_owned();
+
+    Some(GameEvent::PeerInfo { peer_id, username })
+}
+
 /// Parses the peer state change message into `GameEvent::PeerStateChanged`.
 fn peer_state_change(s: &str) -> Option<GameEvent> {
     lazy_static! {
@@ -159,3 +376,253 @@ fn peer_left(s: &str) -> Option<GameEvent> {
 
     Some(GameEvent::PeerLeft { username })
 }
+
+/// Parses the map saving messages of both saves and autosaves into `GameEvent::SavingStarted`.
+fn saving_started(s: &str) -> Option<GameEvent> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r#"Saving game as (.+)$|Saving to (\S+) \((?:non-)?blocking\)\.$"#)
+            .expect("failed to create saving started regex");
+    }
+
+    let captures = RE.captures(s)?;
+    let path = PathBuf::from(captures.get(1).or_else(|| captures.get(2))?.as_str());
+
+    Some(GameEvent::SavingStarted { path })
+}
+
+/// Parses the map saving finished message into `GameEvent::SavingFinished`.
+fn saving_finished(s: &str) -> Option<GameEvent> {
+    if s.ends_with("Saving finished") {
+        Some(GameEvent::SavingFinished)
+    } else {
+        None
+    }
+}
+
+/// Parses the mod loading failure messages logged at startup into `GameEvent::ModLoadFailed`. The failing mod is
+/// either named in the message or identified by the first `__mod-name__/` path in the reason that isn't the game's own
+/// `core` or `base`.
+fn mod_load_failed(s: &str) -> Option<GameEvent> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r#"(?:Failed to load mods?|Error while loading mods?)(?: "([^"]+)")?: (.+)$"#)
+                .expect("failed to create mod load failure regex");
+        static ref MOD_PATH_RE: Regex = Regex::new(r#"__([\w-]+?)__/"#).expect("failed to create mod path regex");
+    }
+
+    let captures = RE.captures(s)?;
+    let reason = captures.get(2)?.as_str().to_owned();
+    let mod_name = captures.get(1).map(|name| name.as_str().to_owned()).or_else(|| {
+        MOD_PATH_RE
+            .captures_iter(&reason)
+            .filter_map(|path| path.get(1))
+            .map(|name| name.as_str())
+            .find(|name| *name != "core" && *name != "base")
+            .map(str::to_owned)
+    });
+
+    Some(GameEvent::ModLoadFailed { mod_name, reason })
+}
+
+/// Parses a performance report line into `GameEvent::Performance`. The game doesn't log its performance by itself, so
+/// the lines are expected to be logged by a mod or a scenario script, in the form `Performance: tick <tick>, UPS
+/// <ups>`.
+fn performance(s: &str) -> Option<GameEvent> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r#"Performance: tick (\d+), UPS (\d+(?:\.\d+)?)"#).expect("failed to create performance regex");
+    }
+
+    let captures = RE.captures(s)?;
+    let tick = captures.get(1)?.as_str().parse().ok()?;
+    let ups = captures.get(2)?.as_str().parse().ok()?;
+
+    Some(GameEvent::Performance { tick, ups })
+}
+
+/// Parses a chat message into `GameEvent::Chat`.
+fn chat(s: &str) -> Option<GameEvent> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r#"\[CHAT\] ([^:\s]+): (.*)$"#).expect("failed to create chat regex");
+    }
+
+    let captures = RE.captures(s)?;
+    let username = captures.get(1)?.as_str().to_owned();
+    let message = captures.get(2)?.as_str().to_owned();
+
+    Some(GameEvent::Chat { username, message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factorio::executable::EventParserDefinition;
+
+    #[test]
+    fn elapsed_timestamp() {
+        let started_at = Utc::now();
+        let received_at = started_at + Duration::seconds(60);
+        let event = TimedGameEvent::parse(
+            "  12.345 Info ServerMultiplayerManager.cpp:706: updateTick(0) changing state from(CreatingGame) \
+             to(InGame)",
+            &[],
+            started_at,
+            received_at,
+        )
+        .expect("failed to parse event");
+
+        assert_eq!(event.game_time, Some(started_at + Duration::milliseconds(12345)));
+        assert_eq!(event.time(), started_at + Duration::milliseconds(12345));
+        assert_eq!(event.received_at, received_at);
+    }
+
+    #[test]
+    fn datetime_timestamp() {
+        let started_at = Utc::now();
+        let event = TimedGameEvent::parse(
+            "2020-07-20 10:00:05 [JOIN] player joined the game",
+            &[],
+            started_at,
+            started_at,
+        )
+        .expect("failed to parse event");
+
+        let expected = Local
+            .from_local_datetime(&NaiveDateTime::parse_from_str("2020-07-20 10:00:05", "%Y-%m-%d %H:%M:%S").unwrap())
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(event.game_time, Some(expected));
+    }
+
+    #[test]
+    fn version_and_save() {
+        let event: GameEvent = "   0.000 2020-08-14 12:00:00; Factorio 1.0.0 (build 54889, linux64, headless)"
+            .parse()
+            .expect("failed to parse version event");
+        assert!(matches!(event, GameEvent::Version { version } if version == HumanVersion::new(1, 0, 0)));
+
+        let event: GameEvent = "   1.234 Loading map /srv/factorio/saves/my save.zip: 5523312 bytes."
+            .parse()
+            .expect("failed to parse loading save event");
+        assert!(
+            matches!(event, GameEvent::LoadingSave { path } if path == PathBuf::from("/srv/factorio/saves/my save.zip"))
+        );
+    }
+
+    #[test]
+    fn peer_address_and_info() {
+        let event: GameEvent = "  20.120 Info ServerMultiplayerManager.cpp:962: adding peer(1) \
+                                address([::ffff:192.0.2.1]:34197)"
+            .parse()
+            .expect("failed to parse peer addition event");
+        assert!(
+            matches!(event, GameEvent::PeerAdded { peer_id, address } if peer_id == "1" && address == "[::ffff:192.0.2.1]:34197")
+        );
+
+        let event: GameEvent = "  20.450 Info ServerMultiplayerManager.cpp:1173: Received peer info for peer(1) \
+                                username(player)."
+            .parse()
+            .expect("failed to parse peer info event");
+        assert!(matches!(event, GameEvent::PeerInfo { peer_id, username } if peer_id == "1" && username == "player"));
+    }
+
+    #[test]
+    fn saving() {
+        let event: GameEvent = "  60.000 Info AppManagerStates.cpp:1802: Saving game as /srv/factorio/saves/backup.zip"
+            .parse()
+            .expect("failed to parse saving started event");
+        assert!(
+            matches!(event, GameEvent::SavingStarted { path } if path == PathBuf::from("/srv/factorio/saves/backup.zip"))
+        );
+
+        let event: GameEvent = " 300.000 Info AppManager.cpp:287: Saving to _autosave1 (blocking)."
+            .parse()
+            .expect("failed to parse autosave started event");
+        assert!(matches!(event, GameEvent::SavingStarted { path } if path == PathBuf::from("_autosave1")));
+
+        let event: GameEvent = "  60.250 Info AppManagerStates.cpp:1812: Saving finished"
+            .parse()
+            .expect("failed to parse saving finished event");
+        assert!(matches!(event, GameEvent::SavingFinished));
+    }
+
+    #[test]
+    fn performance_report() {
+        let event: GameEvent = "  42.010 Script @__perf-report__/control.lua:12: Performance: tick 2520, UPS 59.8"
+            .parse()
+            .expect("failed to parse performance event");
+        assert!(matches!(event, GameEvent::Performance { tick, ups } if tick == 2520 && (ups - 59.8).abs() < 1e-9));
+    }
+
+    #[test]
+    fn mod_load_failure() {
+        let event: GameEvent = "   0.950 Error Util.cpp:83: Failed to load mod \"Krastorio2\": Mod Krastorio2 (1.1.0) \
+                                requires base >= 1.1.0"
+            .parse()
+            .expect("failed to parse named mod load failure event");
+        assert!(
+            matches!(event, GameEvent::ModLoadFailed { mod_name, reason } if mod_name.as_deref() == Some("Krastorio2") && reason == "Mod Krastorio2 (1.1.0) requires base >= 1.1.0")
+        );
+
+        let event: GameEvent = "   1.204 Error Util.cpp:83: Failed to load mods: __base__/data.lua:3: \
+                                __bobplates__/data-updates.lua:12: attempt to index field 'item' (a nil value)"
+            .parse()
+            .expect("failed to parse mod load failure event");
+        assert!(matches!(event, GameEvent::ModLoadFailed { mod_name, .. } if mod_name.as_deref() == Some("bobplates")));
+
+        let event: GameEvent = "   1.204 Error Util.cpp:83: Error while loading mods: out of memory"
+            .parse()
+            .expect("failed to parse unidentified mod load failure event");
+        assert!(
+            matches!(event, GameEvent::ModLoadFailed { mod_name, reason } if mod_name.is_none() && reason == "out of memory")
+        );
+    }
+
+    #[test]
+    fn chat_message() {
+        let event: GameEvent = "2020-07-20 10:00:05 [CHAT] player: [JOIN] other joined the game"
+            .parse()
+            .expect("failed to parse chat event");
+        assert!(
+            matches!(event, GameEvent::Chat { username, message } if username == "player" && message == "[JOIN] other joined the game")
+        );
+
+        let event: GameEvent = "2020-07-20 10:00:06 [CHAT] <server>: restarting soon"
+            .parse()
+            .expect("failed to parse server chat event");
+        assert!(matches!(event, GameEvent::Chat { username, .. } if username == "<server>"));
+    }
+
+    #[test]
+    fn custom_parsers() {
+        let parsers = CustomEventParser::compile_all(&[EventParserDefinition {
+            name: String::from("telemetry"),
+            pattern: String::from(r#"\[TELEMETRY\] (.+)$"#),
+            template: String::from("$1"),
+            severity: LogLevel::Info,
+        }])
+        .unwrap();
+
+        let event = GameEvent::parse_with("  42.000 [TELEMETRY] rockets=3", &parsers).expect("failed to parse event");
+        assert!(
+            matches!(event, GameEvent::Custom { name, message, .. } if name == "telemetry" && message == "rockets=3")
+        );
+
+        // players can't fake custom events in the chat
+        let event = GameEvent::parse_with("2020-07-20 10:00:05 [CHAT] player: [TELEMETRY] rockets=9000", &parsers)
+            .expect("failed to parse event");
+        assert!(matches!(event, GameEvent::Chat { .. }));
+        assert!("  42.000 [TELEMETRY] rockets=3".parse::<GameEvent>().is_err());
+    }
+
+    #[test]
+    fn no_timestamp() {
+        let received_at = Utc::now();
+        let event = TimedGameEvent::parse("[LEAVE] player left the game", &[], received_at, received_at)
+            .expect("failed to parse event");
+
+        assert_eq!(event.game_time, None);
+        assert_eq!(event.time(), received_at);
+    }
+}
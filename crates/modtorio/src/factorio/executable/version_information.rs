@@ -20,6 +20,13 @@ pub struct VersionInformation {
     map_output: String,
 }
 
+impl VersionInformation {
+    /// Returns the server's version.
+    pub fn version(&self) -> HumanVersion {
+        self.version
+    }
+}
+
 impl FromStr for VersionInformation {
     type Err = anyhow::Error;
 
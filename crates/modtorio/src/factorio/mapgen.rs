@@ -0,0 +1,216 @@
+//! Provides [`NewMap`](NewMap), the settings a new map is created with, and [`MapSettings`](MapSettings), which
+//! corresponds to the game's `map-settings.json` file. The map generation settings are the same
+//! [`MapGenSettings`](MapGenSettings) map exchange strings carry.
+//!
+//! The game's map settings have many tuning values for pollution, enemy expansion, unit groups and path finding that
+//! rarely need changing. The commonly changed ones are typed, and the rest are carried over as-is.
+
+pub use super::settings::MapGenSettings;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// The file name the map generation settings are written to for the executable.
+const MAP_GEN_SETTINGS_FILENAME: &str = "map-gen-settings.json";
+/// The file name the map settings are written to for the executable.
+const MAP_SETTINGS_FILENAME: &str = "map-settings.json";
+
+/// The settings a new map is created with. Settings that aren't given are left to the game's defaults.
+#[derive(Debug, PartialEq, Default)]
+pub struct NewMap {
+    /// The map generation settings.
+    pub map_gen_settings: Option<MapGenSettings>,
+    /// The map settings.
+    pub map_settings: Option<MapSettings>,
+}
+
+/// A map's settings, in the format of the game's `map-settings.json` file.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
+pub struct MapSettings {
+    /// The recipe and technology difficulty settings.
+    #[serde(default)]
+    pub difficulty_settings: DifficultySettings,
+    /// The pollution settings.
+    #[serde(default)]
+    pub pollution: Toggle,
+    /// The enemy evolution settings.
+    #[serde(default)]
+    pub enemy_evolution: EnemyEvolution,
+    /// The enemy expansion settings.
+    #[serde(default)]
+    pub enemy_expansion: Toggle,
+    /// The rest of the settings, carried over as-is.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A map's recipe and technology difficulty settings.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct DifficultySettings {
+    /// The recipe difficulty, 0 for normal and 1 for expensive.
+    pub recipe_difficulty: u8,
+    /// The technology difficulty, 0 for normal and 1 for expensive.
+    pub technology_difficulty: u8,
+    /// The multiplier for technology costs.
+    pub technology_price_multiplier: f64,
+    /// The rest of the settings, carried over as-is.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A group of map settings that can be turned off as a whole.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Toggle {
+    /// Whether the settings' feature is enabled.
+    pub enabled: bool,
+    /// The rest of the settings, carried over as-is.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A map's enemy evolution settings.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct EnemyEvolution {
+    /// Whether enemies evolve.
+    pub enabled: bool,
+    /// How much the evolution factor grows every tick.
+    pub time_factor: f64,
+    /// How much the evolution factor grows for every destroyed spawner.
+    pub destroy_factor: f64,
+    /// How much the evolution factor grows for every unit of produced pollution.
+    pub pollution_factor: f64,
+    /// The rest of the settings, carried over as-is.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Default for DifficultySettings {
+    fn default() -> Self {
+        Self {
+            recipe_difficulty: 0,
+            technology_difficulty: 0,
+            technology_price_multiplier: 1.0,
+            other: Map::new(),
+        }
+    }
+}
+
+impl Default for Toggle {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            other: Map::new(),
+        }
+    }
+}
+
+impl Default for EnemyEvolution {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            time_factor: 0.000_004,
+            destroy_factor: 0.002,
+            pollution_factor: 0.000_000_9,
+            other: Map::new(),
+        }
+    }
+}
+
+impl MapSettings {
+    /// Returns new `MapSettings` by deserializing a given `map-settings.json` file's contents.
+    pub fn from_game_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Returns a string by serializing the settings into the game's `map-settings.json` file format.
+    pub fn to_game_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl NewMap {
+    /// Returns a new `NewMap` from given map generation settings and map settings in the game's JSON formats. Empty
+    /// JSON leaves the respective settings to the game's defaults.
+    pub fn from_game_json(map_gen_settings: &str, map_settings: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            map_gen_settings: match map_gen_settings.trim() {
+                "" => None,
+                json => Some(MapGenSettings::from_game_json(json)?),
+            },
+            map_settings: match map_settings.trim() {
+                "" => None,
+                json => Some(MapSettings::from_game_json(json)?),
+            },
+        })
+    }
+
+    /// Writes the given settings into files in a given directory and returns the executable arguments that pass them
+    /// to the game.
+    pub async fn write_args(&self, dir: &Path) -> anyhow::Result<Vec<String>> {
+        let mut args = Vec::new();
+
+        if let Some(settings) = &self.map_gen_settings {
+            let path = write(dir, MAP_GEN_SETTINGS_FILENAME, settings.to_game_json()?).await?;
+            args.extend(vec![String::from("--map-gen-settings"), path.display().to_string()]);
+        }
+        if let Some(settings) = &self.map_settings {
+            let path = write(dir, MAP_SETTINGS_FILENAME, settings.to_game_json()?).await?;
+            args.extend(vec![String::from("--map-settings"), path.display().to_string()]);
+        }
+
+        Ok(args)
+    }
+}
+
+/// Writes given contents into a file with a given name in a given directory and returns the file's path.
+async fn write(dir: &Path, filename: &str, contents: String) -> anyhow::Result<PathBuf> {
+    let path = dir.join(filename);
+    fs::write(&path, contents).await?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_settings() {
+        let settings = MapSettings::from_game_json(
+            r#"{
+                "difficulty_settings": {
+                    "recipe_difficulty": 1,
+                    "technology_difficulty": 0,
+                    "technology_price_multiplier": 4,
+                    "research_queue_setting": "always"
+                },
+                "pollution": { "enabled": false, "diffusion_ratio": 0.02 },
+                "enemy_expansion": { "enabled": true, "max_expansion_distance": 7 },
+                "max_failed_behavior_count": 3
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.difficulty_settings.recipe_difficulty, 1);
+        assert!(!settings.pollution.enabled);
+        assert_eq!(settings.enemy_evolution, EnemyEvolution::default());
+        assert_eq!(settings.other["max_failed_behavior_count"], 3);
+
+        let json: Value = serde_json::from_str(&settings.to_game_json().unwrap()).unwrap();
+        assert_eq!(json["pollution"]["diffusion_ratio"], 0.02);
+        assert_eq!(json["difficulty_settings"]["technology_price_multiplier"], 4.0);
+        assert_eq!(json["difficulty_settings"]["research_queue_setting"], "always");
+    }
+
+    #[tokio::test]
+    async fn write_args() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(NewMap::default().write_args(dir.path()).await.unwrap().is_empty());
+
+        let new_map = NewMap::from_game_json("", r#"{"pollution": {"enabled": false}}"#).unwrap();
+        let args = new_map.write_args(dir.path()).await.unwrap();
+        assert_eq!(args[0], "--map-settings");
+        assert!(Path::new(&args[1]).exists());
+        assert!(NewMap::from_game_json("", "{").is_err());
+    }
+}
@@ -1,27 +1,43 @@
 //! Provides the [`Mods`](Mods) object used to interact with the mods installed in a Factorio
 //! server.
 
+mod mod_list;
 mod mods_builder;
+mod policy;
+mod removal;
+mod resolution;
 mod update_batcher;
+mod update_policy;
+mod verification;
 
 use super::GameStoreId;
 use crate::{
     error::ModError,
     mod_common::{DownloadResult, Mod, Requirement},
+    shutdown,
     store::{models, Store},
-    util::{async_status, ext::PathExt, HumanVersion},
+    util::{async_status, checksum, ext::PathExt, HumanVersion},
     Config, ModPortal,
 };
 use async_status::{AsyncProgressChannel, AsyncProgressChannelExt};
+use futures::stream::{self, StreamExt};
 use log::*;
+pub use mod_list::ModList;
 pub use mods_builder::ModsBuilder;
+pub use policy::ModPolicy;
+pub use removal::Removal;
+pub use resolution::ResolutionFailure;
+use resolution::{Conflict, ConflictKind};
 use std::{
-    collections::{hash_map::Entry, HashMap},
-    path::PathBuf,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
     sync::Arc,
 };
-use tokio::{fs, sync::Mutex};
+use tokio::{fs, sync::Mutex, task};
 use update_batcher::UpdateBatcher;
+pub use update_policy::UpdatePolicy;
+pub use verification::Verification;
+use verification::{ChecksumMismatch, ModContent};
 
 // TODO: function to scan and remove duplicate mods
 
@@ -29,8 +45,18 @@ use update_batcher::UpdateBatcher;
 pub struct Mods {
     /// The mod's root directory.
     directory: PathBuf,
+    /// The store ID of the game the mods belong to, or `None` if the game hasn't been stored yet.
+    store_id: Arc<Mutex<Option<GameStoreId>>>,
     /// The collection of installed mods.
     mods: HashMap<String, Arc<Mod>>,
+    /// The policy on which mods may be installed from the mod portal.
+    policy: ModPolicy,
+    /// The update policies of the mods that have one set, keyed by the mods' names.
+    update_policies: HashMap<String, UpdatePolicy>,
+    /// The names of the mods that were installed only as dependencies of other mods.
+    dependency_installs: HashSet<String>,
+    /// The game's Factorio version, or `None` if it's unknown and any release may be installed.
+    game_version: Option<HumanVersion>,
     /// Reference to the program config.
     config: Arc<Config>,
     /// Reference to the mod portal.
@@ -46,6 +72,61 @@ impl Mods {
         self.mods.len()
     }
 
+    /// Returns an iterator over the mods.
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Mod>> {
+        self.mods.values()
+    }
+
+    /// Returns the mods directory.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Sets the game's Factorio version the mods' releases are checked against.
+    pub fn set_game_version(&mut self, game_version: HumanVersion) {
+        self.game_version = Some(game_version);
+    }
+
+    /// Returns the policy on which mods may be installed from the mod portal.
+    pub fn policy(&self) -> &ModPolicy {
+        &self.policy
+    }
+
+    /// Sets the policy on which mods may be installed from the mod portal. Doesn't affect already installed mods.
+    pub fn set_policy(&mut self, policy: ModPolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns the update policy of a mod with a given name. Mods without one set use the default policy.
+    pub fn update_policy(&self, name: &str) -> UpdatePolicy {
+        self.update_policies.get(name).copied().unwrap_or_default()
+    }
+
+    /// Returns whether a given release of a mod with a given name would update the installed mod: the mod is
+    /// installed, isn't pinned by its update policy and its installed version is older than the release's.
+    pub async fn is_updated_by(&self, name: &str, version: HumanVersion) -> anyhow::Result<bool> {
+        let fact_mod = match self.mods.get(name) {
+            Some(fact_mod) => fact_mod,
+            None => return Ok(false),
+        };
+
+        if self.update_policy(name) == UpdatePolicy::Pinned {
+            return Ok(false);
+        }
+        Ok(fact_mod.own_version().await? < version)
+    }
+
+    /// Sets the update policy of an installed mod with a given name. Returns
+    /// [`ModError::NoSuchMod`](crate::error::ModError::NoSuchMod) if the mod isn't installed.
+    pub fn set_update_policy(&mut self, name: &str, policy: UpdatePolicy) -> anyhow::Result<()> {
+        if !self.mods.contains_key(name) {
+            return Err(ModError::NoSuchMod(name.to_owned()).into());
+        }
+
+        self.update_policies.insert(name.to_owned(), policy);
+        Ok(())
+    }
+
     /// Updates the store for all current mods. This includes updating both the mod information and
     /// the game-to-mod mapping.
     #[allow(dead_code)]
@@ -84,8 +165,10 @@ impl Mods {
 
             let factorio_mod = mod_name.clone();
             let mod_version = fact_mod.own_version().await?;
-            let mod_zip = fact_mod.zip_path().await?.get_str()?.to_owned();
+            let mod_zip = fact_mod.zip_path().await?.to_bytes();
             let zip_last_mtime = fact_mod.get_zip_last_mtime().await?;
+            let update_policy = self.update_policy(&mod_name);
+            let installed_as_dependency = self.dependency_installs.contains(&mod_name);
 
             let store_game_mod = models::GameMod {
                 game: game_id,
@@ -93,6 +176,8 @@ impl Mods {
                 mod_version,
                 mod_zip,
                 zip_last_mtime,
+                update_policy,
+                installed_as_dependency,
             };
             // trace!(
             //     "{}'s stored mod {}: {:?}",
@@ -106,17 +191,31 @@ impl Mods {
         }
 
         self.store.set_mods_of_game(new_game_mods.into_inner()).await?;
+        self.store.set_mod_policy(self.policy.to_store_format(game_id)).await?;
         info!("Updated game ID {}'s stored mods", game_id);
 
         Ok(())
     }
 
     /// Adds and installs a new mod with a given name from the portal. Optionally a wanted version
-    /// can be supplied. If no wanted version is supplied, the latest version is installed.
+    /// can be supplied. If no wanted version is supplied, the latest version for the game's Factorio version is
+    /// installed.
+    ///
+    /// A mod that isn't already installed has to pass the mod policy, unless `override_policy` is set. Otherwise
+    /// returns [`ModError::ExcludedByPolicy`][ExcludedByPolicy]. Unless `allow_incompatible` is set, the installed
+    /// release has to be for the game's Factorio version. Otherwise returns
+    /// [`ModError::NoCompatibleRelease`][NoCompatibleRelease] or
+    /// [`ModError::IncompatibleRelease`][IncompatibleRelease].
+    ///
+    /// [ExcludedByPolicy]: crate::error::ModError::ExcludedByPolicy
+    /// [NoCompatibleRelease]: crate::error::ModError::NoCompatibleRelease
+    /// [IncompatibleRelease]: crate::error::ModError::IncompatibleRelease
     pub async fn add_from_portal(
         &mut self,
         name: &str,
         version: Option<HumanVersion>,
+        override_policy: bool,
+        allow_incompatible: bool,
         prog_tx: Option<AsyncProgressChannel>,
     ) -> anyhow::Result<()> {
         if let Some(version) = version {
@@ -129,15 +228,90 @@ impl Mods {
             .send_status(async_status::indefinite(&format!("Installing {}...", name)))
             .await?;
 
-        let new_mod = self.add_or_update_in_place(name, version).await?;
+        let new_mod = self
+            .add_or_update_in_place(name, version, !override_policy, !allow_incompatible, prog_tx)
+            .await?;
         info!("Added {}", new_mod.display().await);
+
+        // a mod installed explicitly is kept even if it was first installed as a dependency
+        self.dependency_installs.remove(name);
         Ok(())
     }
 
-    /// Updates the portal info for all mods and downloads their most recent version if the
-    /// currently installed version is older.
+    /// Removes a mod with a given name by deleting its archives from the mods directory. If a version is given, only
+    /// that version's archive is deleted, otherwise every archive of the mod is. If the installed archive is deleted
+    /// while others remain, the newest remaining one is loaded in its place. Once no archive of the mod remains, it's
+    /// also removed from the mod list.
+    ///
+    /// If `cascade` is set and the mod is removed entirely, the mods that were installed only as its dependencies and
+    /// that no remaining mod depends on are removed along with it. If `dry_run` is set, nothing is deleted and the
+    /// returned `Removal` describes what would be.
+    ///
+    /// Returns [`ModError::NoSuchMod`][NoSuchMod] if the mod has no archives, or
+    /// [`ModError::NoSuchArchive`][NoSuchArchive] if it has none of the given version.
+    ///
+    /// [NoSuchMod]: crate::error::ModError::NoSuchMod
+    /// [NoSuchArchive]: crate::error::ModError::NoSuchArchive
+    pub async fn remove(
+        &mut self,
+        name: &str,
+        version: Option<HumanVersion>,
+        cascade: bool,
+        dry_run: bool,
+    ) -> anyhow::Result<Removal> {
+        // the dependencies are found before anything is removed, since removing the mod drops its dependency info
+        let orphans = if cascade {
+            let mut dependencies = HashMap::new();
+            for (mod_name, fact_mod) in &self.mods {
+                let mandatory = fact_mod
+                    .dependencies()
+                    .await?
+                    .into_iter()
+                    .filter(|dep| dep.requirement() == Requirement::Mandatory)
+                    .map(|dep| dep.name().to_owned())
+                    .collect();
+                dependencies.insert(mod_name.clone(), mandatory);
+            }
+
+            removal::orphaned_dependencies(name, &dependencies, &self.dependency_installs)
+        } else {
+            Vec::new()
+        };
+
+        let (mut deleted, removed) = self.remove_archives(name, version, dry_run).await?;
+        let mut removed_mods = Vec::new();
+        let mut cascaded = Vec::new();
+        if removed {
+            removed_mods.push(name.to_owned());
+
+            for orphan in orphans {
+                info!("Removing '{}' along with '{}' as its orphaned dependency", orphan, name);
+                deleted.extend(self.remove_archives(&orphan, None, dry_run).await?.0);
+                removed_mods.push(orphan.clone());
+                cascaded.push(orphan);
+            }
+        }
+
+        let mut mod_list = ModList::load(&self.directory).await?;
+        let mut mod_list_changed = false;
+        for removed_mod in &removed_mods {
+            mod_list_changed |= mod_list.remove(removed_mod);
+        }
+        if mod_list_changed && !dry_run {
+            mod_list.save().await?;
+        }
+
+        Ok(Removal::new(deleted, mod_list_changed, cascaded))
+    }
+
+    /// Updates the portal info for all mods and downloads the version each mod's update policy picks for a given
+    /// Factorio version if the currently installed version is older.
     #[allow(dead_code)]
-    pub async fn update(&mut self, prog_tx: Option<AsyncProgressChannel>) -> anyhow::Result<()> {
+    pub async fn update(
+        &mut self,
+        game_version: HumanVersion,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<()> {
         info!("Checking for mod updates...");
         prog_tx
             .send_status(async_status::indefinite("Checking for mod updates..."))
@@ -152,7 +326,13 @@ impl Mods {
         debug!("Update batcher built, applying...");
         update_batcher.apply().await?;
 
-        let updates = update_batcher.get_updates().await?;
+        let update_policies = &self.update_policies;
+        let updates = update_batcher
+            .get_updates(
+                |name| update_policies.get(name).copied().unwrap_or_default(),
+                game_version,
+            )
+            .await?;
         info!("Found {} updates", updates.len());
         prog_tx
             .send_status(async_status::indefinite(&format!("Found {} updates", updates.len())))
@@ -162,34 +342,29 @@ impl Mods {
             debug!("{:?}", updates)
         };
 
-        let max_updates = updates.len() as u32;
-        for (index, update) in updates.iter().enumerate() {
-            info!("Updating {}...", update);
-            prog_tx
-                .send_status(async_status::definite(
-                    &format!("Updating {}...", update),
-                    index as u32,
-                    max_updates,
-                ))
-                .await?;
-
-            self.add_or_update_in_place(update, None).await?;
-        }
+        let updates = updates
+            .into_iter()
+            .map(|(name, version)| (name, Some(version)))
+            .collect::<Vec<_>>();
+        self.add_or_update_concurrently(&updates, false, "Updated", &prog_tx)
+            .await?;
 
         Ok(())
     }
 
     /// Tries to ensure all mod dependencies are met by installing any missing mods or mods that
     /// don't meet a dependency's version requirement. If a mod is incompatible with another
-    /// installed mod, the ensuring will fail with
-    /// [`ModError::CannotEnsureDependency`][CannotEnsureDependency].
+    /// installed mod, or a dependency is still unmet after installing, the ensuring will fail with
+    /// [`ModError::UnresolvableDependencies`][UnresolvableDependencies], and every unmet dependency
+    /// is explained through the status updates.
     ///
-    /// [CannotEnsureDependency]: crate::error::ModError::CannotEnsureDependency
+    /// [UnresolvableDependencies]: crate::error::ModError::UnresolvableDependencies
     #[allow(dead_code)]
     pub async fn ensure_dependencies(&mut self, prog_tx: Option<AsyncProgressChannel>) -> anyhow::Result<()> {
         info!("Ensuring mod dependencies are met...");
 
         let mut missing: Vec<String> = Vec::new();
+        let mut incompatible = Vec::new();
 
         let mods = self.mods.values();
         let max_mods = mods.len() as u32;
@@ -203,33 +378,155 @@ impl Mods {
                 ))
                 .await?;
 
-            missing.extend(self.ensure_single_dependencies(fact_mod).await?.into_iter());
+            for conflict in self.unmet_dependencies(fact_mod).await? {
+                if conflict.kind() == ConflictKind::Incompatible {
+                    incompatible.push(conflict);
+                } else if !missing.iter().any(|name| name == conflict.dependency().name()) {
+                    missing.push(conflict.dependency().name().to_owned());
+                }
+            }
+        }
+
+        if !incompatible.is_empty() {
+            return Err(fail_resolution(ResolutionFailure::new(incompatible), &prog_tx).await);
         }
 
         if missing.is_empty() {
             info!("All mod dependencies met");
+            return Ok(());
+        }
+
+        info!("Found {} missing mod dependencies, installing", missing.len());
+
+        let missing = missing.into_iter().map(|name| (name, None)).collect::<Vec<_>>();
+        self.add_or_update_concurrently(&missing, true, "Installed missing mod", &prog_tx)
+            .await?;
+
+        // the latest release of a mod doesn't necessarily meet every version requirement on it, and a newly
+        // installed mod may be incompatible with an existing one, so check everything again
+        let mut unmet = Vec::new();
+        for fact_mod in self.mods.values() {
+            unmet.extend(self.unmet_dependencies(fact_mod).await?);
+        }
+
+        if unmet.is_empty() {
+            info!("All mod dependencies met");
+            Ok(())
         } else {
-            info!("Found {} missing mod dependencies, installing", missing.len());
+            Err(fail_resolution(ResolutionFailure::new(unmet), &prog_tx).await)
+        }
+    }
 
-            let max_missing = missing.len() as u32;
-            for (index, miss) in missing.iter().enumerate() {
-                prog_tx
-                    .send_status(async_status::definite(
-                        &format!("Installing missing mod '{}'...", miss),
-                        index as u32,
-                        max_missing,
-                    ))
-                    .await?;
+    /// Verifies the installed mods, finding every unmet declared dependency like
+    /// [`ensure_dependencies`](#method.ensure_dependencies) does but without installing anything. If `analyze_content`
+    /// is set, also scans every installed mod's zip archive for content that likely conflicts with another mod's. If
+    /// `verify_checksums` is set, also compares every installed mod's zip archive's checksum to the one the mod portal
+    /// lists for its release. The checksums are calculated in blocking threads, which stop if the returned future is
+    /// dropped, and their progress is reported to a given progress channel.
+    pub async fn verify(
+        &self,
+        analyze_content: bool,
+        verify_checksums: bool,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<Verification> {
+        info!("Verifying mods...");
 
-                self.add_from_portal(&miss, None, None).await?;
-            }
+        let mut unmet = Vec::new();
+        for fact_mod in self.mods.values() {
+            unmet.extend(self.unmet_dependencies(fact_mod).await?);
         }
 
-        Ok(())
+        let content_conflicts = if analyze_content {
+            Some(self.content_conflicts().await?)
+        } else {
+            None
+        };
+        let checksum_mismatches = if verify_checksums {
+            Some(self.checksum_mismatches(prog_tx).await?)
+        } else {
+            None
+        };
+
+        Ok(Verification::new(unmet, content_conflicts, checksum_mismatches))
     }
 }
 
 impl Mods {
+    /// Scans every installed mod's zip archive and returns the likely content conflicts between them.
+    async fn content_conflicts(&self) -> anyhow::Result<Vec<verification::ContentConflict>> {
+        let mut contents = Vec::new();
+        let mut dependencies = BTreeMap::new();
+        for fact_mod in self.mods.values() {
+            let name = fact_mod.name().await;
+            let zip_path = self.directory.join(fact_mod.zip_path().await?);
+            debug!("Scanning content of mod '{}' ({})", name, zip_path.display());
+
+            let scanned_name = name.clone();
+            contents.push(task::spawn_blocking(move || ModContent::from_zip(scanned_name, zip_path)).await??);
+            dependencies.insert(name, fact_mod.dependencies().await?);
+        }
+
+        let content_conflicts = verification::analyze(contents, &dependencies);
+        info!("Found {} likely mod content conflicts", content_conflicts.len());
+        Ok(content_conflicts)
+    }
+
+    /// Returns the installed mods whose zip archives' SHA1 checksums don't match the ones the mod portal lists for
+    /// their releases. Mods whose release isn't known, for example when the portal can't be reached, are skipped. The
+    /// progress of every checksum calculation is reported to a given progress channel.
+    async fn checksum_mismatches(
+        &self,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<Vec<ChecksumMismatch>> {
+        let mut mismatches = Vec::new();
+        for fact_mod in self.mods.values() {
+            let name = fact_mod.name().await;
+            let version = fact_mod.own_version().await?;
+
+            if fact_mod.get_release(version).await.is_err() {
+                if let Err(e) = fact_mod.ensure_portal_info().await {
+                    debug!("Failed to ensure portal info of mod '{}': {}", name, e);
+                }
+            }
+            let release = match fact_mod.get_release(version).await {
+                Ok(release) => release,
+                Err(e) => {
+                    warn!("Skipping checksum verification of mod '{}' {}: {}", name, version, e);
+                    continue;
+                }
+            };
+
+            let zip_path = self.directory.join(fact_mod.zip_path().await?);
+            debug!("Verifying checksum of mod '{}' ({})", name, zip_path.display());
+            let actual = checksum::file_with_progress(
+                checksum::Algorithm::Sha1,
+                &zip_path,
+                &format!("Verifying checksum of {}...", name),
+                prog_tx.clone(),
+            )
+            .await?;
+
+            if actual != release.sha1() {
+                warn!(
+                    "Mod '{}' {} checksum mismatch: expected {}, got {}",
+                    name,
+                    version,
+                    release.sha1(),
+                    actual
+                );
+                mismatches.push(ChecksumMismatch {
+                    name,
+                    version,
+                    expected: release.sha1().to_owned(),
+                    actual,
+                });
+            }
+        }
+
+        info!("Found {} mod checksum mismatches", mismatches.len());
+        Ok(mismatches)
+    }
+
     /// Retrieves a currently installed mod based on its name. Returns
     /// [`Err(ModError::NoSuchMod)`][NoSuchMod] if there is no mod with such name.
     ///
@@ -241,6 +538,86 @@ impl Mods {
             .ok_or_else(|| ModError::NoSuchMod(name.to_owned()))?)
     }
 
+    /// Deletes the archives of a mod with a given name, or only the archive of a given version, like
+    /// [`remove`](#method.remove) does, but leaves the mod list as is. If `dry_run` is set, nothing is deleted. Returns
+    /// the deleted archives and whether no archive of the mod remains.
+    async fn remove_archives(
+        &mut self,
+        name: &str,
+        version: Option<HumanVersion>,
+        dry_run: bool,
+    ) -> anyhow::Result<(Vec<PathBuf>, bool)> {
+        let mut archives = removal::archives(&self.directory, name).await?;
+        let installed = match self.mods.get(name) {
+            Some(installed) => Some((installed.own_version().await?, installed.zip_path().await?)),
+            None => None,
+        };
+
+        // the installed archive may have been renamed from what the portal names them
+        if let Some(installed) = &installed {
+            if !archives.iter().any(|(_, path)| *path == installed.1) {
+                archives.push(installed.clone());
+            }
+        }
+
+        if archives.is_empty() {
+            return Err(ModError::NoSuchMod(name.to_owned()).into());
+        }
+
+        let (deleted, remaining): (Vec<_>, Vec<_>) = archives
+            .into_iter()
+            .partition(|(archive_version, _)| version.map_or(true, |version| *archive_version == version));
+        if let (Some(version), true) = (version, deleted.is_empty()) {
+            return Err(ModError::NoSuchArchive {
+                name: name.to_owned(),
+                version,
+            }
+            .into());
+        }
+
+        if dry_run {
+            return Ok((
+                deleted.into_iter().map(|(_, path)| path).collect(),
+                remaining.is_empty(),
+            ));
+        }
+
+        for (_, path) in &deleted {
+            info!("Removing mod archive {}", path.display());
+            fs::remove_file(self.directory.join(path)).await?;
+        }
+
+        if installed.map_or(false, |installed| deleted.contains(&installed)) {
+            self.mods.remove(name);
+
+            let newest = remaining
+                .iter()
+                .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            if let Some((_, path)) = newest {
+                let replacement = Mod::from_zip(
+                    self.directory.join(path),
+                    Arc::clone(&self.config),
+                    Arc::clone(&self.portal),
+                    Arc::clone(&self.store),
+                )
+                .await?;
+
+                info!("Replaced removed mod with {}", replacement.display().await);
+                self.mods.insert(name.to_owned(), Arc::new(replacement));
+            }
+        }
+
+        if remaining.is_empty() {
+            self.update_policies.remove(name);
+            self.dependency_installs.remove(name);
+        }
+
+        Ok((
+            deleted.into_iter().map(|(_, path)| path).collect(),
+            remaining.is_empty(),
+        ))
+    }
+
     /// Given a mod name and an optional version, this function will redownload the mod if it's
     /// already installed or download it new if it doesn't.
     ///
@@ -249,58 +626,207 @@ impl Mods {
     ///
     /// If an already installed mod is redownloaded and its version is higher than earlier, the old
     /// mod archive will be removed.
-    async fn add_or_update_in_place(&mut self, name: &str, version: Option<HumanVersion>) -> anyhow::Result<&Mod> {
-        match self.mods.entry(name.to_owned()) {
-            Entry::Occupied(entry) => {
-                let existing_mod = entry.into_mut();
-                let existing_mod_display = existing_mod.display().await;
-
-                info!("Downloading {}...", existing_mod_display);
-
-                match existing_mod.download(version, &self.directory).await? {
-                    DownloadResult::New => info!("{} added", existing_mod_display),
-                    DownloadResult::Unchanged => info!("{} unchanged", existing_mod_display),
-                    DownloadResult::Replaced {
-                        old_version,
-                        old_archive,
-                    } => {
-                        let old_archive = self.directory.join(old_archive);
-                        debug!("Removing old mod archive {}", old_archive.display());
-                        fs::remove_file(old_archive).await?;
-
-                        // do .display() again since it has changed
-                        info!("{} replaced from ver. {}", existing_mod.display().await, old_version);
+    ///
+    /// If `enforce_policy` is set, a mod that isn't already installed has to pass the mod policy before it's
+    /// downloaded. If `enforce_compatibility` is set and the game's Factorio version is known, only a release for that
+    /// version is downloaded, and without a given version the latest such release is. Retried downloads are reported
+    /// to a given progress channel.
+    async fn add_or_update_in_place(
+        &mut self,
+        name: &str,
+        version: Option<HumanVersion>,
+        enforce_policy: bool,
+        enforce_compatibility: bool,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<&Mod> {
+        let server = *self.store_id.lock().await;
+        if let Some(new_mod) = self
+            .download_mod(name, version, enforce_policy, enforce_compatibility, server, prog_tx)
+            .await?
+        {
+            self.mods.insert(name.to_owned(), new_mod);
+        }
+
+        Ok(&self.mods[name])
+    }
+
+    /// Downloads given mods in given versions like [`add_or_update_in_place`](#method.add_or_update_in_place) does,
+    /// up to the configured download concurrency at a time. If `as_dependencies` is set, the mod policy and
+    /// compatibility are enforced and the downloaded mods are recorded as installed as dependencies. Every finished
+    /// download is reported to a given progress channel with a given action, such as "Updated". A failed download
+    /// doesn't stop the others; once every download has finished, the first failure is returned. If the program is
+    /// shutting down, the downloads that haven't started yet are skipped and the finished ones are added before
+    /// stopping.
+    async fn add_or_update_concurrently(
+        &mut self,
+        mods: &[(String, Option<HumanVersion>)],
+        as_dependencies: bool,
+        action: &str,
+        prog_tx: &Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<()> {
+        let server = *self.store_id.lock().await;
+        let max_mods = mods.len() as u32;
+        let mut downloaded = Vec::new();
+        let mut first_error = None;
+
+        prog_tx
+            .send_status(async_status::definite(
+                &format!("Downloading {} mods...", max_mods),
+                0,
+                max_mods,
+            ))
+            .await?;
+
+        {
+            // the downloads only borrow the mods immutably, the new mods are added once they're all done. the names are
+            // streamed owned, since futures built from borrowed stream items can't be proven Send when spawned
+            let this = &*self;
+            let mut downloads = stream::iter(mods.to_vec())
+                .map(|(name, version)| async move {
+                    if shutdown::is_cancelled() {
+                        return (name, version, None);
                     }
-                }
 
-                Ok(existing_mod)
+                    let result = this
+                        .download_mod(
+                            &name,
+                            version,
+                            as_dependencies,
+                            as_dependencies,
+                            server,
+                            prog_tx.clone(),
+                        )
+                        .await;
+                    (name, version, Some(result))
+                })
+                .buffer_unordered(this.config.download_concurrency());
+
+            let mut finished = 0;
+            while let Some((name, version, result)) = downloads.next().await {
+                let result = match result {
+                    Some(result) => result,
+                    None => {
+                        debug!("Skipping downloading {} since the program is shutting down", name);
+                        continue;
+                    }
+                };
+
+                finished += 1;
+                shutdown::checkpoint(format!("{} of {} mods downloaded", finished, max_mods));
+                let message = match result {
+                    Ok(new_mod) => {
+                        let message = match version {
+                            Some(version) => format!("{} {} to {}", action, name, version),
+                            None => format!("{} {}", action, name),
+                        };
+                        downloaded.push((name, new_mod));
+                        message
+                    }
+                    Err(e) => {
+                        let message = format!("Failed to download {}: {}", name, e);
+                        warn!("{}", message);
+                        first_error.get_or_insert(e);
+                        message
+                    }
+                };
+
+                prog_tx
+                    .send_status(async_status::definite(&message, finished, max_mods))
+                    .await?;
             }
-            Entry::Vacant(entry) => {
-                let new_mod = Arc::new(
-                    Mod::from_portal(
-                        name,
-                        Arc::clone(&self.config),
-                        Arc::clone(&self.portal),
-                        Arc::clone(&self.store),
-                    )
-                    .await?,
-                );
+        }
+
+        let finished = downloaded.len();
+        for (name, new_mod) in downloaded {
+            if let Some(new_mod) = new_mod {
+                self.mods.insert(name.clone(), new_mod);
+            }
+            if as_dependencies {
+                self.dependency_installs.insert(name);
+            }
+        }
+        shutdown::stop_point(format!("{} of {} mods downloaded and added", finished, max_mods))?;
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
 
-                info!("Downloading {}...", name);
+    /// Downloads a given mod like [`add_or_update_in_place`](#method.add_or_update_in_place) does, without adding a
+    /// newly downloaded mod to the mods. Returns the new mod, or `None` if the mod was already installed and was
+    /// redownloaded in place.
+    async fn download_mod(
+        &self,
+        name: &str,
+        version: Option<HumanVersion>,
+        enforce_policy: bool,
+        enforce_compatibility: bool,
+        server: Option<GameStoreId>,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<Option<Arc<Mod>>> {
+        let game_version = if enforce_compatibility { self.game_version } else { None };
+        if let Some(existing_mod) = self.mods.get(name) {
+            let existing_mod_display = existing_mod.display().await;
+
+            let version = if let Some(game_version) = game_version {
+                existing_mod.ensure_portal_info().await?;
+                Some(compatible_release(existing_mod, version, game_version).await?)
+            } else {
+                version
+            };
 
-                new_mod.download(version, &self.directory).await?;
-                Ok(entry.insert(new_mod))
+            info!("Downloading {}...", existing_mod_display);
+
+            match existing_mod.download(version, &self.directory, server, prog_tx).await? {
+                DownloadResult::New => info!("{} added", existing_mod_display),
+                DownloadResult::Unchanged => info!("{} unchanged", existing_mod_display),
+                DownloadResult::Replaced {
+                    old_version,
+                    old_archive,
+                } => {
+                    let old_archive = self.directory.join(old_archive);
+                    debug!("Removing old mod archive {}", old_archive.display());
+                    fs::remove_file(old_archive).await?;
+
+                    // do .display() again since it has changed
+                    info!("{} replaced from ver. {}", existing_mod.display().await, old_version);
+                }
             }
+
+            return Ok(None);
         }
+
+        let new_mod = Arc::new(
+            Mod::from_portal(
+                name,
+                Arc::clone(&self.config),
+                Arc::clone(&self.portal),
+                Arc::clone(&self.store),
+            )
+            .await?,
+        );
+
+        if enforce_policy {
+            let category = new_mod.category().await;
+            self.policy.check(name, category.as_deref(), &new_mod.tags().await)?;
+        }
+
+        let version = match game_version {
+            Some(game_version) => Some(compatible_release(&new_mod, version, game_version).await?),
+            None => version,
+        };
+
+        info!("Downloading {}...", name);
+
+        new_mod.download(version, &self.directory, server, prog_tx).await?;
+        Ok(Some(new_mod))
     }
 
-    /// Given a reference to an installed mod, tries to ensure its dependencies are met. Returns a
-    /// vector of mod names that are missing or don't meet a dependency's version requirement
-    /// and should be installed to meet the mod's dependencies. If the mod is incompatible with
-    /// another installed mod, the function will return an error
-    /// `ModError::CannotEnsureDependency`.
-    async fn ensure_single_dependencies(&self, target_mod: &Mod) -> anyhow::Result<Vec<String>> {
-        let mut missing = Vec::new();
+    /// Given a reference to an installed mod, returns every one of its mandatory dependencies that is missing or
+    /// doesn't meet the dependency's version requirement, and every installed mod it is incompatible with.
+    async fn unmet_dependencies(&self, target_mod: &Mod) -> anyhow::Result<Vec<Conflict>> {
+        let mut unmet = Vec::new();
         let target_name = target_mod.name().await;
 
         for dep in target_mod.dependencies().await? {
@@ -320,7 +846,12 @@ impl Mods {
                                     "Dependency {} of '{}' not met: version requirement mismatch (found {})",
                                     dep, target_name, required_version
                                 );
-                                missing.push(dep.name().to_string());
+                                unmet.push(Conflict::new(
+                                    target_mod.display().await,
+                                    dep,
+                                    Some(required_version),
+                                    ConflictKind::VersionMismatch,
+                                ));
                             }
                             _ => debug!(
                                 "Dependency {} of '{}' met (found {})",
@@ -334,16 +865,26 @@ impl Mods {
                         );
 
                         // TODO: resolve version
-                        missing.push(dep.name().to_string());
+                        unmet.push(Conflict::new(
+                            target_mod.display().await,
+                            dep,
+                            None,
+                            ConflictKind::Missing,
+                        ));
                     }
                 }
                 Requirement::Incompatible => {
-                    if self.get_mod(dep.name()).is_ok() {
-                        return Err(ModError::CannotEnsureDependency {
-                            dependency: dep,
-                            mod_display: target_mod.display().await,
-                        }
-                        .into());
+                    if let Ok(incompatible_mod) = self.get_mod(dep.name()) {
+                        debug!(
+                            "Dependency {} of '{}' not met: incompatible mod installed",
+                            dep, target_name
+                        );
+                        unmet.push(Conflict::new(
+                            target_mod.display().await,
+                            dep,
+                            incompatible_mod.own_version().await.ok(),
+                            ConflictKind::Incompatible,
+                        ));
                     } else {
                         debug!("Dependency {} of '{}' met", dep, target_name);
                     }
@@ -352,6 +893,61 @@ impl Mods {
             }
         }
 
-        Ok(missing)
+        Ok(unmet)
+    }
+}
+
+/// Explains every conflict in a given resolution failure through a given status update channel, and returns the
+/// failure as a `ModError::UnresolvableDependencies`.
+async fn fail_resolution(failure: ResolutionFailure, prog_tx: &Option<AsyncProgressChannel>) -> anyhow::Error {
+    for explanation in failure.explanations() {
+        warn!("Unmet mod dependency: {}", explanation);
+        if let Err(e) = prog_tx.send_status(async_status::indefinite(&explanation)).await {
+            error!("Failed to send dependency explanation: {}", e);
+        }
+    }
+
+    ModError::UnresolvableDependencies(failure).into()
+}
+
+/// Returns the version of a given mod to download for a given Factorio version: the given version if its release is for
+/// the Factorio version, or the mod's newest release for it if no version is given. The mod's portal info has to be
+/// populated.
+async fn compatible_release(
+    fact_mod: &Mod,
+    version: Option<HumanVersion>,
+    game_version: HumanVersion,
+) -> anyhow::Result<HumanVersion> {
+    let name = fact_mod.name().await;
+    match version {
+        Some(version) => {
+            let factorio_version = fact_mod.get_release(version).await?.factorio_version();
+            if update_policy::is_compatible(factorio_version, game_version) {
+                Ok(version)
+            } else {
+                Err(ModError::IncompatibleRelease {
+                    name,
+                    version,
+                    factorio_version,
+                    game_version,
+                }
+                .into())
+            }
+        }
+        None => {
+            let releases = fact_mod
+                .releases()
+                .await?
+                .into_iter()
+                .map(|release| (release.version(), release.factorio_version()));
+
+            match update_policy::newest_compatible(releases, game_version) {
+                Some(version) => {
+                    debug!("Newest release of {} for Factorio {}: {}", name, game_version, version);
+                    Ok(version)
+                }
+                None => Err(ModError::NoCompatibleRelease { name, game_version }.into()),
+            }
+        }
     }
 }
@@ -0,0 +1,149 @@
+//! Provides the [`ModList`](ModList) object, which reads and edits the `mod-list.json` in a mods directory that lists
+//! which mods the game enables.
+
+use log::*;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// The name of the file in a mods directory that lists which mods are enabled.
+const MOD_LIST_FILE: &str = "mod-list.json";
+
+/// The mod list in a mods directory. Only the mods' `name` and `enabled` fields are touched; everything else in the
+/// file is kept as is.
+#[derive(Debug)]
+pub struct ModList {
+    /// The path to the mod list file.
+    path: PathBuf,
+    /// The mod list's contents. Empty if the file doesn't exist.
+    contents: Value,
+}
+
+impl ModList {
+    /// Reads the mod list in a given mods directory. A missing mod list is read as an empty one.
+    pub async fn load<P>(directory: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = directory.as_ref().join(MOD_LIST_FILE);
+        let contents = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path).await?)?
+        } else {
+            json!({ "mods": [] })
+        };
+
+        Ok(Self { path, contents })
+    }
+
+    /// Writes the mod list back into its file.
+    pub async fn save(&self) -> anyhow::Result<()> {
+        debug!("Writing mod list {}", self.path.display());
+        fs::write(&self.path, serde_json::to_string_pretty(&self.contents)?).await?;
+        Ok(())
+    }
+
+    /// Returns whether a given mod is enabled, or `None` if it isn't listed.
+    pub fn enabled(&self, name: &str) -> Option<bool> {
+        self.listed()?
+            .iter()
+            .find(|listed| is_named(listed, name))
+            .map(|listed| listed.get("enabled").and_then(Value::as_bool).unwrap_or(true))
+    }
+
+    /// Returns every listed mod's name and whether it's enabled, in the order they're listed.
+    pub fn states(&self) -> Vec<(String, bool)> {
+        self.listed()
+            .into_iter()
+            .flatten()
+            .filter_map(|listed| {
+                let name = listed.get("name").and_then(Value::as_str)?;
+                let enabled = listed.get("enabled").and_then(Value::as_bool).unwrap_or(true);
+                Some((name.to_owned(), enabled))
+            })
+            .collect()
+    }
+
+    /// Enables or disables a given mod, listing it if it isn't listed yet. Returns whether the mod list changed.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let listed = match self.listed_mut() {
+            Some(listed) => listed,
+            None => return false,
+        };
+
+        match listed.iter_mut().find(|listed| is_named(listed, name)) {
+            Some(existing) if existing.get("enabled").and_then(Value::as_bool) == Some(enabled) => false,
+            Some(existing) => {
+                existing["enabled"] = Value::Bool(enabled);
+                true
+            }
+            None => {
+                listed.push(json!({ "name": name, "enabled": enabled }));
+                true
+            }
+        }
+    }
+
+    /// Removes a given mod from the list. Returns whether the mod list changed; it doesn't if the mod isn't listed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let listed = match self.listed_mut() {
+            Some(listed) => listed,
+            None => return false,
+        };
+
+        let before = listed.len();
+        listed.retain(|listed| !is_named(listed, name));
+        listed.len() != before
+    }
+
+    /// Returns the listed mods, or `None` if the mod list is malformed.
+    fn listed(&self) -> Option<&Vec<Value>> {
+        self.contents.get("mods").and_then(Value::as_array)
+    }
+
+    /// Returns the listed mods mutably, or `None` if the mod list is malformed.
+    fn listed_mut(&mut self) -> Option<&mut Vec<Value>> {
+        self.contents.get_mut("mods").and_then(Value::as_array_mut)
+    }
+}
+
+/// Returns whether a given listed mod has a given name.
+fn is_named(listed: &Value, name: &str) -> bool {
+    listed.get("name").and_then(Value::as_str) == Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn edit_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(MOD_LIST_FILE),
+            r#"{"mods": [{"name": "base", "enabled": true}, {"name": "gears", "enabled": false}]}"#,
+        )
+        .unwrap();
+
+        let mut mod_list = ModList::load(dir.path()).await.unwrap();
+        assert_eq!(mod_list.enabled("gears"), Some(false));
+        assert_eq!(mod_list.enabled("belts"), None);
+        assert_eq!(
+            mod_list.states(),
+            vec![(String::from("base"), true), (String::from("gears"), false)]
+        );
+
+        assert!(mod_list.set_enabled("gears", true));
+        assert!(!mod_list.set_enabled("gears", true));
+        assert!(mod_list.set_enabled("belts", false));
+        assert!(mod_list.remove("base"));
+        assert!(!mod_list.remove("base"));
+        mod_list.save().await.unwrap();
+
+        let saved: Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join(MOD_LIST_FILE)).unwrap()).unwrap();
+        assert_eq!(
+            saved,
+            json!({"mods": [{"name": "gears", "enabled": true}, {"name": "belts", "enabled": false}]})
+        );
+    }
+}
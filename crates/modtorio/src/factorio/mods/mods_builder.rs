@@ -1,7 +1,7 @@
 //! Provides the [ModsBuilder](ModsBuilder) which is used to build a [Mods](super::Mods) object from
 //! a game's mod root directory, optionally loading them from the program store.
 
-use super::Mods;
+use super::{ModPolicy, Mods, UpdatePolicy};
 use crate::{
     config::Config,
     error::ModError,
@@ -10,7 +10,7 @@ use crate::{
     mod_portal::ModPortal,
     store::Store,
     util,
-    util::{async_status, ext::PathExt},
+    util::{async_status, ext::PathExt, HumanVersion},
 };
 use async_status::{AsyncProgressChannel, AsyncProgressChannelExt};
 use log::*;
@@ -19,6 +19,7 @@ use std::{
     path::PathBuf,
     sync::Arc,
 };
+use tokio::sync::Mutex;
 
 /// A glob string used to find zip archives (.zip extension).
 const ZIP_GLOB: &str = "*.zip";
@@ -34,8 +35,14 @@ pub struct ModsBuilder {
     directory: PathBuf,
     /// The store ID of the game these mods belong to.
     game_store_id: Option<GameStoreId>,
+    /// The game's store ID handle, shared with the game so the built mods see the ID once the game is stored.
+    store_id_handle: Arc<Mutex<Option<GameStoreId>>>,
     /// A status update channel.
     prog_tx: Option<AsyncProgressChannel>,
+    /// Whether loading the mods is skipped.
+    skip_loading: bool,
+    /// The game's Factorio version.
+    game_version: Option<HumanVersion>,
 }
 
 impl<'a> ModsBuilder {
@@ -45,7 +52,18 @@ impl<'a> ModsBuilder {
         ModsBuilder {
             directory,
             game_store_id: None,
+            store_id_handle: Arc::new(Mutex::new(None)),
             prog_tx: None,
+            skip_loading: false,
+            game_version: None,
+        }
+    }
+
+    /// Sets the game's store ID handle the built mods use to authenticate mod portal downloads on behalf of the game.
+    pub fn with_store_id_handle(self, store_id_handle: Arc<Mutex<Option<GameStoreId>>>) -> Self {
+        Self {
+            store_id_handle,
+            ..self
         }
     }
 
@@ -65,6 +83,23 @@ impl<'a> ModsBuilder {
         }
     }
 
+    /// Sets the game's Factorio version, which the built mods install only compatible releases for.
+    pub fn with_game_version(self, game_version: HumanVersion) -> Self {
+        Self {
+            game_version: Some(game_version),
+            ..self
+        }
+    }
+
+    /// Skips loading and verifying the mods, so the built `Mods` is empty. Used for games whose mods Modtorio doesn't
+    /// manage.
+    pub fn without_loading(self) -> Self {
+        Self {
+            skip_loading: true,
+            ..self
+        }
+    }
+
     /// Builds mods from the program store with a given game store ID.
     async fn build_mods_from_store(
         &self,
@@ -107,10 +142,10 @@ impl<'a> ModsBuilder {
                 }
             };
 
-            let mod_zip = created_mod.zip_path().await?.get_str()?.to_string();
-            trace!("{} zip: {}", created_mod.name().await, mod_zip);
-            if !mod_zips.insert(mod_zip.clone()) {
-                return Err(ModError::DuplicateMod(mod_zip).into());
+            let mod_zip = created_mod.zip_path().await?;
+            trace!("{} zip: {}", created_mod.name().await, mod_zip.display());
+            if !mod_zips.insert(mod_zip.clone().into_os_string()) {
+                return Err(ModError::DuplicateMod(mod_zip.display().to_string()).into());
             }
 
             debug!("Loaded {} from store", created_mod.name().await);
@@ -129,8 +164,15 @@ impl<'a> ModsBuilder {
         let zips = util::glob(&self.directory.join(ZIP_GLOB))?;
         let max_zips = zips.len() as u32;
         for (index, entry) in zips.into_iter().enumerate() {
-            let entry_file_name = entry.get_file_name()?;
-            trace!("Checking if {} is loaded...", entry_file_name);
+            // the file name isn't required to be valid Unicode, so compare the raw OS strings instead
+            let entry_file_name = match entry.get_os_file_name() {
+                Ok(name) => name,
+                Err(e) => {
+                    warn!("Skipping mod zip archive {}: {}", entry.display(), e);
+                    continue;
+                }
+            };
+            trace!("Checking if {:?} is loaded...", entry_file_name);
             self.prog_tx
                 .send_status(async_status::definite(
                     &format!("Checking if {} is loaded...", entry.display()),
@@ -217,7 +259,34 @@ impl<'a> ModsBuilder {
 
     /// Finalises the builder and returns a new `Mods` object.
     pub async fn build(self, config: Arc<Config>, portal: Arc<ModPortal>, store: Arc<Store>) -> anyhow::Result<Mods> {
-        let built_mods = if let Some(game_store_id) = self.game_store_id {
+        let policy = if let Some(game_store_id) = self.game_store_id {
+            store
+                .get_mod_policy(game_store_id)
+                .await?
+                .map(|policy| ModPolicy::from_store_format(&policy))
+                .unwrap_or_default()
+        } else {
+            ModPolicy::default()
+        };
+        let stored_mods = if let Some(game_store_id) = self.game_store_id {
+            store.get_mods_of_game(game_store_id).await?
+        } else {
+            Vec::new()
+        };
+        let update_policies: HashMap<String, UpdatePolicy> = stored_mods
+            .iter()
+            .map(|game_mod| (game_mod.factorio_mod.clone(), game_mod.update_policy))
+            .collect();
+        let dependency_installs: HashSet<String> = stored_mods
+            .into_iter()
+            .filter(|game_mod| game_mod.installed_as_dependency)
+            .map(|game_mod| game_mod.factorio_mod)
+            .collect();
+
+        let built_mods = if self.skip_loading {
+            debug!("Mods not managed, skipping loading them");
+            Vec::new()
+        } else if let Some(game_store_id) = self.game_store_id {
             debug!("Got stored game ID {}, loading mods from store", game_store_id);
 
             self.build_mods_from_store(
@@ -264,7 +333,12 @@ impl<'a> ModsBuilder {
 
         Ok(Mods {
             directory: self.directory,
+            store_id: self.store_id_handle,
             mods,
+            policy,
+            update_policies,
+            dependency_installs,
+            game_version: self.game_version,
             config,
             portal,
             store,
@@ -0,0 +1,123 @@
+//! Provides the [`ModPolicy`](ModPolicy) object, which restricts which mods may be installed from the mod portal
+//! based on their portal category and tags.
+
+use super::GameStoreId;
+use crate::{error::ModError, store::models::GameModPolicy};
+
+/// The splitter sequence used to separate categories and tags in the store database columns.
+const SPLITTER: &str = ",";
+
+/// A game's policy on which mods may be installed from the mod portal.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ModPolicy {
+    /// The mod categories excluded from installation.
+    excluded_categories: Vec<String>,
+    /// The mod tags excluded from installation.
+    excluded_tags: Vec<String>,
+}
+
+impl ModPolicy {
+    /// Returns a new `ModPolicy` excluding given categories and tags.
+    pub fn new(excluded_categories: Vec<String>, excluded_tags: Vec<String>) -> Self {
+        Self {
+            excluded_categories,
+            excluded_tags,
+        }
+    }
+
+    /// Checks whether a mod with a given category and tags may be installed. Returns `ModError::ExcludedByPolicy` if
+    /// the mod's category or any of its tags are excluded.
+    pub fn check(&self, name: &str, category: Option<&str>, tags: &[String]) -> anyhow::Result<()> {
+        if let Some(category) = category {
+            if self.excluded_categories.iter().any(|excluded| excluded == category) {
+                return Err(ModError::ExcludedByPolicy {
+                    name: name.to_owned(),
+                    reason: format!("category '{}' is excluded", category),
+                }
+                .into());
+            }
+        }
+
+        if let Some(tag) = tags.iter().find(|tag| self.excluded_tags.contains(tag)) {
+            return Err(ModError::ExcludedByPolicy {
+                name: name.to_owned(),
+                reason: format!("tag '{}' is excluded", tag),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether a mod with a given category and tags passes the policy.
+    pub fn permits(&self, category: Option<&str>, tags: &[String]) -> bool {
+        self.check("", category, tags).is_ok()
+    }
+
+    /// Returns a new `ModPolicy` from a given `GameModPolicy`.
+    pub fn from_store_format(store_format: &GameModPolicy) -> Self {
+        Self {
+            excluded_categories: split(&store_format.excluded_categories),
+            excluded_tags: split(&store_format.excluded_tags),
+        }
+    }
+
+    /// Returns a new `GameModPolicy` for a given game from this policy.
+    pub fn to_store_format(&self, game: GameStoreId) -> GameModPolicy {
+        GameModPolicy {
+            game,
+            excluded_categories: self.excluded_categories.join(SPLITTER),
+            excluded_tags: self.excluded_tags.join(SPLITTER),
+        }
+    }
+
+    /// Returns a new `ModPolicy` from a given `ModPolicy` in RPC format.
+    pub fn from_rpc_format(rpc_format: &rpc::ModPolicy) -> Self {
+        Self {
+            excluded_categories: rpc_format.excluded_categories.clone(),
+            excluded_tags: rpc_format.excluded_tags.clone(),
+        }
+    }
+
+    /// Returns this policy in RPC format.
+    pub fn to_rpc_format(&self) -> rpc::ModPolicy {
+        rpc::ModPolicy {
+            excluded_categories: self.excluded_categories.clone(),
+            excluded_tags: self.excluded_tags.clone(),
+        }
+    }
+}
+
+/// Splits a given stored column into its individual values.
+fn split(column: &str) -> Vec<String> {
+    column
+        .split(SPLITTER)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check() {
+        let policy = ModPolicy::new(vec![String::from("cheats")], vec![String::from("mining")]);
+
+        assert!(policy.check("a", Some("content"), &[String::from("logistics")]).is_ok());
+        assert!(policy.check("b", Some("cheats"), &[]).is_err());
+        assert!(policy
+            .check("c", None, &[String::from("logistics"), String::from("mining")])
+            .is_err());
+    }
+
+    #[test]
+    fn store_format_roundtrip() {
+        let policy = ModPolicy::new(vec![String::from("cheats")], Vec::new());
+        let store_format = policy.to_store_format(1);
+
+        assert_eq!(store_format.excluded_tags, "");
+        assert_eq!(ModPolicy::from_store_format(&store_format), policy);
+    }
+}
@@ -0,0 +1,171 @@
+//! Provides the [`Removal`](Removal) object, which describes what removing a mod deleted,
+//! [`archives`](archives), which finds a mod's archives in a mods directory, and
+//! [`orphaned_dependencies`](orphaned_dependencies), which finds the dependencies left unneeded by removing a mod.
+
+use crate::util::HumanVersion;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+/// The extension of mod archives.
+const ARCHIVE_EXTENSION: &str = ".zip";
+
+/// What removing a mod deleted.
+#[derive(Debug, PartialEq, Default)]
+pub struct Removal {
+    /// The deleted archives, relative to the mods directory.
+    deleted: Vec<PathBuf>,
+    /// Whether the mod's entry was removed from the mod list.
+    mod_list_changed: bool,
+    /// The names of the orphaned dependencies removed along with the mod.
+    cascaded: Vec<String>,
+}
+
+impl Removal {
+    /// Returns a new `Removal` from given deleted archives, whether the mod list changed and the orphaned dependencies
+    /// removed along with the mod.
+    pub fn new(deleted: Vec<PathBuf>, mod_list_changed: bool, cascaded: Vec<String>) -> Self {
+        Self {
+            deleted,
+            mod_list_changed,
+            cascaded,
+        }
+    }
+}
+
+impl From<&Removal> for rpc::ModRemoval {
+    fn from(removal: &Removal) -> Self {
+        Self {
+            deleted: removal.deleted.iter().map(|path| path.display().to_string()).collect(),
+            mod_list_changed: removal.mod_list_changed,
+            cascaded: removal.cascaded.clone(),
+        }
+    }
+}
+
+/// Returns the archives of a given mod in a given mods directory with their versions, based on the archives' file
+/// names (`<name>_<version>.zip`). The paths are relative to the directory.
+pub async fn archives<P>(directory: P, name: &str) -> anyhow::Result<Vec<(HumanVersion, PathBuf)>>
+where
+    P: AsRef<Path>,
+{
+    let prefix = format!("{}_", name);
+    let mut archives = Vec::new();
+    let mut entries = fs::read_dir(directory).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name();
+        let version = file_name
+            .to_str()
+            .and_then(|file_name| file_name.strip_prefix(&prefix))
+            .and_then(|rest| rest.strip_suffix(ARCHIVE_EXTENSION))
+            .and_then(|version| version.parse::<HumanVersion>().ok());
+
+        if let Some(version) = version {
+            archives.push((version, PathBuf::from(file_name)));
+        }
+    }
+
+    Ok(archives)
+}
+
+/// Returns the mods that become orphaned when a mod with a given name is removed, given every installed mod's mandatory
+/// dependencies and the mods that were installed only as dependencies. A mod is orphaned if it was installed only as a
+/// dependency, a removed mod depended on it and no remaining mod does, which in turn may orphan its own dependencies.
+/// The orphans are sorted by name.
+pub fn orphaned_dependencies(
+    removed: &str,
+    dependencies: &HashMap<String, Vec<String>>,
+    dependency_installs: &HashSet<String>,
+) -> Vec<String> {
+    let mut removed: HashSet<&str> = vec![removed].into_iter().collect();
+    let depended_by = |name: &str, removed: &HashSet<&str>, by_removed: bool| {
+        dependencies.iter().any(|(dependent, deps)| {
+            removed.contains(dependent.as_str()) == by_removed && deps.iter().any(|dep| dep == name)
+        })
+    };
+
+    let mut orphans = Vec::new();
+    loop {
+        let orphan = dependency_installs.iter().find(|name| {
+            dependencies.contains_key(*name)
+                && !removed.contains(name.as_str())
+                && depended_by(name.as_str(), &removed, true)
+                && !depended_by(name.as_str(), &removed, false)
+        });
+
+        match orphan {
+            Some(orphan) => {
+                removed.insert(orphan.as_str());
+                orphans.push(orphan.clone());
+            }
+            None => break,
+        }
+    }
+
+    orphans.sort();
+    orphans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn find_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in &[
+            "gears_1.0.0.zip",
+            "gears_1.1.0.zip",
+            "gears_extra_1.0.0.zip",
+            "gears_1.0.0.txt",
+        ] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        let mut found = archives(dir.path(), "gears").await.unwrap();
+        found.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            found,
+            vec![
+                (HumanVersion::new(1, 0, 0), PathBuf::from("gears_1.0.0.zip")),
+                (HumanVersion::new(1, 1, 0), PathBuf::from("gears_1.1.0.zip")),
+            ]
+        );
+    }
+
+    #[test]
+    fn orphans() {
+        let dependencies: HashMap<String, Vec<String>> = vec![
+            ("trains", vec!["rails", "signals"]),
+            ("rails", vec!["steel"]),
+            ("signals", vec![]),
+            ("steel", vec![]),
+            ("belts", vec!["steel", "gears"]),
+            ("gears", vec![]),
+        ]
+        .into_iter()
+        .map(|(name, deps)| (name.to_owned(), deps.into_iter().map(String::from).collect()))
+        .collect();
+        let installs = |names: &[&str]| names.iter().map(|name| String::from(*name)).collect::<HashSet<_>>();
+
+        assert_eq!(
+            orphaned_dependencies("trains", &dependencies, &installs(&["rails", "steel", "gears"])),
+            vec![String::from("rails")]
+        );
+        assert_eq!(
+            orphaned_dependencies("belts", &dependencies, &installs(&["steel", "gears"])),
+            vec![String::from("gears")]
+        );
+        assert!(orphaned_dependencies("trains", &dependencies, &installs(&[])).is_empty());
+
+        let dependencies: HashMap<String, Vec<String>> =
+            dependencies.into_iter().filter(|(name, _)| name != "belts").collect();
+        assert_eq!(
+            orphaned_dependencies("trains", &dependencies, &installs(&["rails", "steel"])),
+            vec![String::from("rails"), String::from("steel")]
+        );
+    }
+}
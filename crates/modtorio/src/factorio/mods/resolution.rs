@@ -0,0 +1,157 @@
+//! Provides the [`ResolutionFailure`](ResolutionFailure) object, which explains why a server's mod dependencies
+//! couldn't be resolved.
+
+use crate::{mod_common::Dependency, util::HumanVersion};
+use std::fmt;
+
+/// The way an installed mod's dependency is unmet.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ConflictKind {
+    /// The dependency is a mod the requirer is incompatible with, and it is installed.
+    Incompatible,
+    /// The required mod isn't installed.
+    Missing,
+    /// The required mod is installed, but its version doesn't meet the dependency's version requirement.
+    VersionMismatch,
+}
+
+/// A single unmet dependency of an installed mod.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Conflict {
+    /// The friendly display of the mod that has the dependency.
+    requirer: String,
+    /// The unmet dependency.
+    dependency: Dependency,
+    /// The installed version of the dependency's mod, if it is installed.
+    installed: Option<HumanVersion>,
+    /// The way the dependency is unmet.
+    kind: ConflictKind,
+}
+
+/// The unmet dependencies that remained when resolving a server's mod dependencies.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ResolutionFailure {
+    /// The unmet dependencies.
+    conflicts: Vec<Conflict>,
+}
+
+impl Conflict {
+    /// Returns a new `Conflict`.
+    pub fn new(requirer: String, dependency: Dependency, installed: Option<HumanVersion>, kind: ConflictKind) -> Self {
+        Self {
+            requirer,
+            dependency,
+            installed,
+            kind,
+        }
+    }
+
+    /// Returns the unmet dependency.
+    pub fn dependency(&self) -> &Dependency {
+        &self.dependency
+    }
+
+    /// Returns the way the dependency is unmet.
+    pub fn kind(&self) -> ConflictKind {
+        self.kind
+    }
+
+    /// Returns a human-readable explanation of the conflict.
+    pub fn explanation(&self) -> String {
+        let name = self.dependency.name();
+        let installed = self
+            .installed
+            .map_or_else(|| String::from("unknown version"), |version| version.to_string());
+
+        match self.kind {
+            ConflictKind::Incompatible => format!(
+                "{} is incompatible with {}, which is installed ({})",
+                self.requirer, name, installed
+            ),
+            ConflictKind::Missing => format!("{} requires {}, but it isn't installed", self.requirer, self.dependency),
+            ConflictKind::VersionMismatch => format!(
+                "{} requires {}, but {} {} is installed",
+                self.requirer, self.dependency, name, installed
+            ),
+        }
+    }
+}
+
+impl ResolutionFailure {
+    /// Returns a new `ResolutionFailure` from given conflicts.
+    pub fn new(conflicts: Vec<Conflict>) -> Self {
+        Self { conflicts }
+    }
+
+    /// Returns the unmet dependencies.
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Returns the human-readable explanation of every conflict.
+    pub fn explanations(&self) -> Vec<String> {
+        self.conflicts.iter().map(Conflict::explanation).collect()
+    }
+}
+
+impl fmt::Display for ResolutionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.explanations().join("; "))
+    }
+}
+
+impl From<&Conflict> for rpc::resolution_failure::Conflict {
+    fn from(conflict: &Conflict) -> Self {
+        Self {
+            requirer: conflict.requirer.clone(),
+            dependency: conflict.dependency.to_string(),
+            installed: conflict.installed.map(Into::into),
+            kind: match conflict.kind {
+                ConflictKind::Incompatible => rpc::resolution_failure::conflict::Kind::Incompatible,
+                ConflictKind::Missing => rpc::resolution_failure::conflict::Kind::Missing,
+                ConflictKind::VersionMismatch => rpc::resolution_failure::conflict::Kind::VersionMismatch,
+            }
+            .into(),
+            explanation: conflict.explanation(),
+        }
+    }
+}
+
+impl From<&ResolutionFailure> for rpc::ResolutionFailure {
+    fn from(failure: &ResolutionFailure) -> Self {
+        Self {
+            conflicts: failure.conflicts.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explanations() {
+        let failure = ResolutionFailure::new(vec![
+            Conflict::new(
+                String::from("Mod A (a 1.0.0)"),
+                "!b".parse().unwrap(),
+                Some(HumanVersion::new(0, 2, 0)),
+                ConflictKind::Incompatible,
+            ),
+            Conflict::new(
+                String::from("Mod C (c 1.0.0)"),
+                "d >= 2.0.0".parse().unwrap(),
+                Some(HumanVersion::new(1, 5, 0)),
+                ConflictKind::VersionMismatch,
+            ),
+        ]);
+
+        assert_eq!(
+            failure.explanations(),
+            vec![
+                String::from("Mod A (a 1.0.0) is incompatible with b, which is installed (0.2.0)"),
+                String::from("Mod C (c 1.0.0) requires d >= 2.0.0, but d 1.5.0 is installed"),
+            ]
+        );
+    }
+}
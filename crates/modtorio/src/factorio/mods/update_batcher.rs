@@ -1,12 +1,20 @@
 //! Provides the `UpdateBatcher` which is used to update the portal info for multiple mods with a single request.
+//!
+//! The portal's mod list endpoint returns many mods' info at once, but it may leave out mods or their releases. Those
+//! mods are fetched one at a time instead.
 
+use super::UpdatePolicy;
 use crate::{
     error::UpdateBatcherError,
     mod_common::Mod,
     mod_portal::{ModPortal, PortalResult},
+    util::HumanVersion,
 };
 use log::*;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 /// Used to update the portal info for multiple mods with a single request.
 pub struct UpdateBatcher<'a> {
@@ -14,6 +22,8 @@ pub struct UpdateBatcher<'a> {
     portal: &'a ModPortal,
     /// The mods this batcher keeps track of.
     mods: HashMap<String, Arc<Mod>>,
+    /// The names of the tracked mods whose portal info couldn't be fetched. They aren't checked for updates.
+    unavailable: HashSet<String>,
 }
 
 impl<'a> UpdateBatcher<'a> {
@@ -22,6 +32,7 @@ impl<'a> UpdateBatcher<'a> {
         Self {
             portal,
             mods: HashMap::new(),
+            unavailable: HashSet::new(),
         }
     }
 
@@ -30,46 +41,100 @@ impl<'a> UpdateBatcher<'a> {
         self.mods.insert(fact_mod.name().await, fact_mod);
     }
 
-    /// Fetches and applies the portal info for all tracked mods.
+    /// Fetches and applies the portal info for all tracked mods. The mods the batched request leaves out, or returns
+    /// without their releases, are fetched separately. If the batched response can't be parsed at all, every mod is.
+    /// Mods whose info can't be fetched separately either are left out of the updates.
     pub async fn apply(&mut self) -> anyhow::Result<()> {
-        let mut names = Vec::new();
-
-        for name in self.mods.keys() {
-            names.push(name.as_str());
-        }
+        let names = self.mods.keys().map(String::as_str).collect::<Vec<_>>();
 
         trace!("Batching info update for mods: {:?}", names);
-        let mod_infos: Vec<PortalResult> = self.portal.fetch_multiple_mods(&names).await?;
+        let mod_infos: Vec<PortalResult> = match self.portal.fetch_multiple_mods(&names).await {
+            Err(e) if e.is::<serde_json::Error>() => {
+                warn!("Failed to parse batched mod info, fetching every mod separately: {}", e);
+                Vec::new()
+            }
+            result => result?,
+        };
 
+        let mut missing = self.mods.keys().cloned().collect::<HashSet<_>>();
         for info in mod_infos {
-            let name = info.name()?;
-            if let Some(fact_mod) = self.mods.get(name) {
-                fact_mod.apply_portal_info(info).await?;
-            } else {
-                return Err(UpdateBatcherError::UnknownModName(name.to_owned()).into());
+            let name = info.name()?.to_owned();
+            let fact_mod = match self.mods.get(&name) {
+                Some(fact_mod) => fact_mod,
+                None => return Err(UpdateBatcherError::UnknownModName(name).into()),
+            };
+
+            if info.releases().is_err() {
+                debug!("Batched info for '{}' is missing its releases", name);
+                continue;
+            }
+
+            fact_mod.apply_portal_info(info).await?;
+            missing.remove(&name);
+        }
+
+        if !missing.is_empty() {
+            debug!("Fetching info separately for {} mods: {:?}", missing.len(), missing);
+        }
+        for name in missing {
+            if let Err(e) = self.mods[&name].fetch_portal_info().await {
+                warn!(
+                    "Failed to fetch portal info for '{}', not checking it for updates: {}",
+                    name, e
+                );
+                self.unavailable.insert(name);
             }
         }
 
         Ok(())
     }
 
-    /// Consumes the batcher and returns all mods that can be updated.
-    pub async fn get_updates(self) -> anyhow::Result<Vec<String>> {
+    /// Consumes the batcher and returns the mods that can be updated along with the versions they're updated to, as
+    /// decided by each mod's update policy for a given Factorio version.
+    pub async fn get_updates<F>(
+        self,
+        update_policy: F,
+        game_version: HumanVersion,
+    ) -> anyhow::Result<Vec<(String, HumanVersion)>>
+    where
+        F: Fn(&str) -> UpdatePolicy,
+    {
         let mut updated_mods = Vec::new();
 
         for (name, fact_mod) in self.mods {
+            if self.unavailable.contains(&name) {
+                continue;
+            }
+
+            let policy = update_policy(&name);
             let latest = fact_mod.latest_release().await?;
             let version = fact_mod.own_version().await?;
+            let releases = fact_mod
+                .releases()
+                .await?
+                .into_iter()
+                .map(|release| (release.version(), release.factorio_version()));
 
-            if version < latest.version() {
-                info!(
-                    "Found newer version of {}: {} over {}, released on {}",
+            match policy.target(version, releases, game_version) {
+                Some(target) => {
+                    info!(
+                        "Found newer version of {}: {} over {} ({})",
+                        fact_mod.name().await,
+                        target,
+                        version,
+                        policy
+                    );
+                    updated_mods.push((name, target));
+                }
+                None if version < latest.version() => info!(
+                    "Not updating {} to its latest version {} (released on {}, for Factorio {}): update policy is {}",
                     fact_mod.name().await,
                     latest.version(),
-                    version,
-                    latest.released_on()
-                );
-                updated_mods.push(name);
+                    latest.released_on(),
+                    latest.factorio_version(),
+                    policy
+                ),
+                None => {}
             }
         }
 
@@ -0,0 +1,176 @@
+//! Provides the [`UpdatePolicy`](UpdatePolicy) enum, which decides which release an installed mod is updated to.
+
+use crate::util::HumanVersion;
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, Value, ValueRef},
+    ToSql,
+};
+use std::str::FromStr;
+use strum_macros::{Display, EnumString};
+
+/// Decides which release an installed mod is updated to when the server's mods are updated.
+///
+/// Defaults to `LatestCompatible`.
+#[derive(Debug, PartialEq, Copy, Clone, EnumString, Display)]
+pub enum UpdatePolicy {
+    /// The mod is updated to its newest release for the server's Factorio version.
+    LatestCompatible,
+    /// The mod is kept in its installed version and never updated.
+    Pinned,
+    /// The mod is updated to its newest release, even if it requires a newer Factorio version than the server's.
+    Latest,
+}
+
+impl Default for UpdatePolicy {
+    fn default() -> Self {
+        Self::LatestCompatible
+    }
+}
+
+impl ToSql for UpdatePolicy {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Text(self.to_string())))
+    }
+}
+
+impl FromSql for UpdatePolicy {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match UpdatePolicy::from_str(value.as_str()?) {
+            Ok(v) => Ok(v),
+            Err(_) => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl From<rpc::ModUpdatePolicy> for UpdatePolicy {
+    fn from(rpc_format: rpc::ModUpdatePolicy) -> Self {
+        match rpc_format {
+            rpc::ModUpdatePolicy::Pinned => Self::Pinned,
+            rpc::ModUpdatePolicy::LatestCompatible => Self::LatestCompatible,
+            rpc::ModUpdatePolicy::Latest => Self::Latest,
+        }
+    }
+}
+
+impl From<UpdatePolicy> for rpc::ModUpdatePolicy {
+    fn from(policy: UpdatePolicy) -> Self {
+        match policy {
+            UpdatePolicy::Pinned => Self::Pinned,
+            UpdatePolicy::LatestCompatible => Self::LatestCompatible,
+            UpdatePolicy::Latest => Self::Latest,
+        }
+    }
+}
+
+impl UpdatePolicy {
+    /// Returns the version a mod installed in a given version is updated to, given its releases' versions paired with
+    /// the Factorio versions they're for and the server's Factorio version. Returns `None` if the mod isn't updated.
+    pub fn target<I>(self, installed: HumanVersion, releases: I, game_version: HumanVersion) -> Option<HumanVersion>
+    where
+        I: IntoIterator<Item = (HumanVersion, HumanVersion)>,
+    {
+        if self == Self::Pinned {
+            return None;
+        }
+
+        releases
+            .into_iter()
+            .filter(|(_, factorio_version)| self == Self::Latest || is_compatible(*factorio_version, game_version))
+            .map(|(version, _)| version)
+            .filter(|version| *version > installed)
+            .fold(None, |newest, version| match newest {
+                Some(newest) if newest >= version => Some(newest),
+                _ => Some(version),
+            })
+    }
+}
+
+/// Returns the newest version out of a mod's releases' versions paired with the Factorio versions they're for that can
+/// be loaded by a given Factorio version, or `None` if no release can be.
+pub fn newest_compatible<I>(releases: I, game_version: HumanVersion) -> Option<HumanVersion>
+where
+    I: IntoIterator<Item = (HumanVersion, HumanVersion)>,
+{
+    releases
+        .into_iter()
+        .filter(|(_, factorio_version)| is_compatible(*factorio_version, game_version))
+        .map(|(version, _)| version)
+        .fold(None, |newest, version| match newest {
+            Some(newest) if newest >= version => Some(newest),
+            _ => Some(version),
+        })
+}
+
+/// Returns whether a mod release for a given Factorio version can be loaded by a given Factorio version. The game only
+/// loads mods for its own major and minor version, except that 1.0 also loads mods for 0.18.
+pub fn is_compatible(factorio_version: HumanVersion, game_version: HumanVersion) -> bool {
+    let same_minor = |a: HumanVersion, b: HumanVersion| a.major == b.major && a.minor == b.minor;
+    same_minor(factorio_version, game_version)
+        || (same_minor(game_version, HumanVersion::new(1, 0, 0))
+            && same_minor(factorio_version, HumanVersion::new(0, 18, 0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn releases() -> Vec<(HumanVersion, HumanVersion)> {
+        vec![
+            (HumanVersion::new(1, 0, 0), HumanVersion::new(0, 18, 0)),
+            (HumanVersion::new(1, 1, 0), HumanVersion::new(1, 0, 0)),
+            (HumanVersion::new(2, 0, 0), HumanVersion::new(1, 1, 0)),
+        ]
+    }
+
+    #[test]
+    fn target() {
+        let installed = HumanVersion::new(1, 0, 0);
+        let game = HumanVersion::new(1, 0, 0);
+
+        assert_eq!(UpdatePolicy::Pinned.target(installed, releases(), game), None);
+        assert_eq!(
+            UpdatePolicy::LatestCompatible.target(installed, releases(), game),
+            Some(HumanVersion::new(1, 1, 0))
+        );
+        assert_eq!(
+            UpdatePolicy::Latest.target(installed, releases(), game),
+            Some(HumanVersion::new(2, 0, 0))
+        );
+        assert_eq!(
+            UpdatePolicy::LatestCompatible.target(HumanVersion::new(1, 1, 0), releases(), game),
+            None
+        );
+    }
+
+    #[test]
+    fn compatibility() {
+        assert!(is_compatible(HumanVersion::new(1, 1, 0), HumanVersion::new(1, 1, 32)));
+        assert!(is_compatible(HumanVersion::new(0, 18, 0), HumanVersion::new(1, 0, 0)));
+        assert!(!is_compatible(HumanVersion::new(1, 1, 0), HumanVersion::new(1, 0, 0)));
+        assert!(!is_compatible(HumanVersion::new(0, 17, 0), HumanVersion::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn newest() {
+        assert_eq!(
+            newest_compatible(releases(), HumanVersion::new(1, 0, 0)),
+            Some(HumanVersion::new(1, 1, 0))
+        );
+        assert_eq!(
+            newest_compatible(releases(), HumanVersion::new(1, 1, 5)),
+            Some(HumanVersion::new(2, 0, 0))
+        );
+        assert_eq!(newest_compatible(releases(), HumanVersion::new(0, 17, 0)), None);
+    }
+
+    #[test]
+    fn store_format() {
+        for policy in &[
+            UpdatePolicy::Pinned,
+            UpdatePolicy::LatestCompatible,
+            UpdatePolicy::Latest,
+        ] {
+            assert_eq!(UpdatePolicy::from_str(&policy.to_string()).unwrap(), *policy);
+        }
+    }
+}
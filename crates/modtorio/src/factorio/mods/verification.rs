@@ -0,0 +1,530 @@
+//! Provides the [`Verification`](Verification) object, which reports the problems found when verifying a server's
+//! installed mods: their unmet declared dependencies and, optionally, content in their zip archives that likely
+//! conflicts with another mod's even though neither mod declares anything about the other, and zip archives whose
+//! checksums don't match the mod portal's.
+
+use super::resolution::Conflict;
+use crate::{mod_common::Dependency, util::HumanVersion};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Read,
+    path::Path,
+};
+
+/// The name of the data stage file that is run after every mod's other data stage files.
+const FINAL_FIXES_FILE: &str = "data-final-fixes.lua";
+/// The name of the control stage file, which can't add prototypes.
+const CONTROL_FILE: &str = "control.lua";
+/// How many of the same prototypes two mods' final fixes have to modify for the mods to be reported as colliding. A
+/// couple of shared tweaks is common and usually harmless.
+const MIN_FINAL_FIXES_COLLISIONS: usize = 3;
+
+lazy_static! {
+    /// Matches the start of a `data:extend` call up to and including its argument table's opening brace.
+    static ref EXTEND_CALL: Regex = Regex::new(r"data\s*:\s*extend\s*\(?\s*\{").unwrap();
+    /// Matches a `type` or `name` field with a literal string value.
+    static ref PROTOTYPE_FIELD: Regex = Regex::new(r#"\b(type|name)\s*=\s*["']([\w-]+)["']"#).unwrap();
+    /// Matches a reference to a single prototype in `data.raw` by a literal type and name.
+    static ref RAW_REFERENCE: Regex = Regex::new(
+        r#"data\.raw(?:\.([A-Za-z_]\w*)|\[\s*["']([\w-]+)["']\s*\])(?:\.([A-Za-z_]\w*)|\[\s*["']([\w-]+)["']\s*\])"#
+    )
+    .unwrap();
+}
+
+/// The way two mods' content likely conflicts.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ContentConflictKind {
+    /// Both mods add a prototype with the same type and name, so whichever loads last overrides the other's.
+    DuplicatePrototype,
+    /// Both mods' final fixes modify many of the same prototypes, so the changes of whichever loads last win.
+    FinalFixesCollision,
+}
+
+/// Content in two installed mods' zip archives that likely conflicts.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ContentConflict {
+    /// The names of the two mods, in alphabetical order.
+    mods: (String, String),
+    /// The colliding prototypes as `type/name`, sorted.
+    prototypes: Vec<String>,
+    /// The way the content conflicts.
+    kind: ContentConflictKind,
+}
+
+/// The result of verifying a server's installed mods.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Verification {
+    /// The installed mods' unmet declared dependencies.
+    unmet: Vec<Conflict>,
+    /// The likely content conflicts between the installed mods, or `None` if their content wasn't analysed.
+    content_conflicts: Option<Vec<ContentConflict>>,
+    /// The installed zip archives whose checksums don't match the mod portal's, or `None` if they weren't verified.
+    checksum_mismatches: Option<Vec<ChecksumMismatch>>,
+}
+
+/// An installed mod zip archive whose checksum doesn't match the one the mod portal lists for its release.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChecksumMismatch {
+    /// The name of the mod.
+    pub name: String,
+    /// The installed version of the mod.
+    pub version: HumanVersion,
+    /// The SHA1 checksum the mod portal lists for the release.
+    pub expected: String,
+    /// The SHA1 checksum of the installed zip archive.
+    pub actual: String,
+}
+
+/// The prototypes a single mod's zip archive adds and modifies in its final fixes, found by scanning its Lua files.
+///
+/// Only prototypes added with literal types and names in `data:extend` calls, and referenced in `data.raw` with
+/// literal types and names, are found, so the scan is a heuristic that can miss content but rarely misattributes it.
+#[derive(Debug, PartialEq, Default)]
+pub struct ModContent {
+    /// The name of the mod.
+    name: String,
+    /// The prototypes the mod adds, as `type/name`.
+    added: BTreeSet<String>,
+    /// The prototypes the mod's final fixes reference, as `type/name`.
+    final_fixes: BTreeSet<String>,
+}
+
+impl ContentConflict {
+    /// Returns a human-readable explanation of the conflict.
+    pub fn explanation(&self) -> String {
+        let (first, second) = &self.mods;
+        let prototypes = self.prototypes.join(", ");
+
+        match self.kind {
+            ContentConflictKind::DuplicatePrototype => format!(
+                "{} and {} both add {} of the same prototypes ({}); whichever loads last overrides the other's",
+                first,
+                second,
+                self.prototypes.len(),
+                prototypes
+            ),
+            ContentConflictKind::FinalFixesCollision => format!(
+                "{} and {} both modify {} of the same prototypes in their final fixes ({}); the changes of whichever \
+                 loads last win",
+                first,
+                second,
+                self.prototypes.len(),
+                prototypes
+            ),
+        }
+    }
+}
+
+impl Verification {
+    /// Returns a new `Verification` from given unmet dependencies, optional content conflicts and optional checksum
+    /// mismatches.
+    pub fn new(
+        unmet: Vec<Conflict>,
+        content_conflicts: Option<Vec<ContentConflict>>,
+        checksum_mismatches: Option<Vec<ChecksumMismatch>>,
+    ) -> Self {
+        Self {
+            unmet,
+            content_conflicts,
+            checksum_mismatches,
+        }
+    }
+}
+
+impl ModContent {
+    /// Scans the Lua files in a given mod's zip archive for the prototypes it adds and modifies in its final fixes.
+    /// Control stage and migration files are skipped. This function blocks while reading the archive.
+    pub fn from_zip<P>(name: String, path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+        let mut content = Self {
+            name,
+            ..Self::default()
+        };
+
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            // every file in a mod zip is inside the mod's top-level directory
+            let relative = match file.name().splitn(2, '/').nth(1) {
+                Some(relative) if relative.ends_with(".lua") => relative.to_owned(),
+                _ => continue,
+            };
+
+            if relative == CONTROL_FILE || relative.starts_with("migrations/") {
+                continue;
+            }
+
+            let mut source = Vec::new();
+            file.read_to_end(&mut source)?;
+            content.scan(&relative, &String::from_utf8_lossy(&source));
+        }
+
+        Ok(content)
+    }
+
+    /// Adds the prototypes added and modified by a given Lua file's source code, at a given path relative to the mod's
+    /// top-level directory.
+    fn scan(&mut self, relative: &str, source: &str) {
+        let source = strip_comments(source);
+        self.added.extend(extended_prototypes(&source));
+
+        if relative == FINAL_FIXES_FILE {
+            self.final_fixes.extend(raw_references(&source));
+        }
+    }
+}
+
+impl From<&ContentConflict> for rpc::mod_verification::ContentConflict {
+    fn from(conflict: &ContentConflict) -> Self {
+        let (first, second) = &conflict.mods;
+        Self {
+            mods: vec![first.clone(), second.clone()],
+            prototypes: conflict.prototypes.clone(),
+            kind: match conflict.kind {
+                ContentConflictKind::DuplicatePrototype => {
+                    rpc::mod_verification::content_conflict::Kind::DuplicatePrototype
+                }
+                ContentConflictKind::FinalFixesCollision => {
+                    rpc::mod_verification::content_conflict::Kind::FinalFixesCollision
+                }
+            }
+            .into(),
+            explanation: conflict.explanation(),
+        }
+    }
+}
+
+impl From<&ChecksumMismatch> for rpc::mod_verification::ChecksumMismatch {
+    fn from(mismatch: &ChecksumMismatch) -> Self {
+        Self {
+            mod_name: mismatch.name.clone(),
+            version: Some(mismatch.version.into()),
+            expected: mismatch.expected.clone(),
+            actual: mismatch.actual.clone(),
+        }
+    }
+}
+
+impl From<&Verification> for rpc::ModVerification {
+    fn from(verification: &Verification) -> Self {
+        Self {
+            unmet: verification.unmet.iter().map(Into::into).collect(),
+            content_analyzed: verification.content_conflicts.is_some(),
+            content_conflicts: verification
+                .content_conflicts
+                .iter()
+                .flatten()
+                .map(Into::into)
+                .collect(),
+            checksums_verified: verification.checksum_mismatches.is_some(),
+            checksum_mismatches: verification
+                .checksum_mismatches
+                .iter()
+                .flatten()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+/// Returns the likely content conflicts between every pair of given mods. Pairs where either mod declares any
+/// dependency on the other are skipped: a mod overriding what it depends on is deliberate, and declared
+/// incompatibilities are already reported as unmet dependencies.
+pub fn analyze(
+    mut contents: Vec<ModContent>,
+    dependencies: &BTreeMap<String, Vec<Dependency>>,
+) -> Vec<ContentConflict> {
+    contents.sort_by(|a, b| a.name.cmp(&b.name));
+    let declares = |requirer: &str, name: &str| {
+        dependencies
+            .get(requirer)
+            .map_or(false, |deps| deps.iter().any(|dep| dep.name() == name))
+    };
+
+    let mut conflicts = Vec::new();
+    for (index, first) in contents.iter().enumerate() {
+        for second in &contents[index + 1..] {
+            if declares(&first.name, &second.name) || declares(&second.name, &first.name) {
+                continue;
+            }
+
+            let mods = (first.name.clone(), second.name.clone());
+            let duplicates = first.added.intersection(&second.added).cloned().collect::<Vec<_>>();
+            if !duplicates.is_empty() {
+                conflicts.push(ContentConflict {
+                    mods: mods.clone(),
+                    prototypes: duplicates,
+                    kind: ContentConflictKind::DuplicatePrototype,
+                });
+            }
+
+            let collisions = first
+                .final_fixes
+                .intersection(&second.final_fixes)
+                .cloned()
+                .collect::<Vec<_>>();
+            if collisions.len() >= MIN_FINAL_FIXES_COLLISIONS {
+                conflicts.push(ContentConflict {
+                    mods,
+                    prototypes: collisions,
+                    kind: ContentConflictKind::FinalFixesCollision,
+                });
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// Returns given Lua source code with its comments removed. Long strings such as `[==[ ... ]==]` are kept as is, along
+/// with any comment markers inside them.
+fn strip_comments(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut stripped = String::with_capacity(source.len());
+    let mut quote = None;
+    let mut start = 0;
+    let mut index = 0;
+
+    // the source is walked byte by byte but only sliced at ASCII bytes, which are always character boundaries
+    while index < bytes.len() {
+        match quote {
+            Some(_) if bytes[index] == b'\\' => index += 1,
+            Some(open) if bytes[index] == open => quote = None,
+            Some(_) => {}
+            None if bytes[index] == b'"' || bytes[index] == b'\'' => quote = Some(bytes[index]),
+            None if bytes[index..].starts_with(b"--") => {
+                stripped.push_str(&source[start..index]);
+                index = match long_bracket_level(&bytes[index + 2..]) {
+                    Some(level) => long_bracket_end(bytes, index + 2, level),
+                    // the line break ending the comment is kept
+                    None => bytes[index..]
+                        .iter()
+                        .position(|b| *b == b'\n')
+                        .map_or(bytes.len(), |end| index + end),
+                };
+                start = index;
+                continue;
+            }
+            None => {
+                if let Some(level) = long_bracket_level(&bytes[index..]) {
+                    index = long_bracket_end(bytes, index, level);
+                    continue;
+                }
+            }
+        }
+
+        index += 1;
+    }
+
+    stripped.push_str(&source[start..]);
+    stripped
+}
+
+/// Returns the level of the Lua long bracket a given byte slice starts with, such as 2 for `[==[`, if it starts with
+/// one.
+fn long_bracket_level(bytes: &[u8]) -> Option<usize> {
+    let level = bytes.iter().skip(1).take_while(|b| **b == b'=').count();
+    if bytes.first() == Some(&b'[') && bytes.get(level + 1) == Some(&b'[') {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+/// Returns the index right after the long bracket of a given level that closes the long bracket opened at a given index
+/// in given bytes, or the length of the bytes if it's never closed.
+fn long_bracket_end(bytes: &[u8], open: usize, level: usize) -> usize {
+    let mut close = vec![b'='; level + 2];
+    close[0] = b']';
+    close[level + 1] = b']';
+
+    let contents = open + level + 2;
+    bytes[contents..]
+        .windows(close.len())
+        .position(|window| window == close.as_slice())
+        .map_or(bytes.len(), |end| contents + end + close.len())
+}
+
+/// Returns the prototypes added in given comment-free Lua source code's `data:extend` calls as `type/name`.
+fn extended_prototypes(source: &str) -> Vec<String> {
+    let mut prototypes = Vec::new();
+
+    for call in EXTEND_CALL.find_iter(source) {
+        // the prototypes are the tables directly inside the argument table, and only their own fields are wanted,
+        // not the fields of the tables nested in them
+        let mut depth = 0;
+        let mut quote = None;
+        let mut escaped = false;
+        let mut fields = String::new();
+
+        for c in source[call.end() - 1..].chars() {
+            if let Some(open) = quote {
+                if depth == 2 {
+                    fields.push(c);
+                }
+
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == open {
+                    quote = None;
+                }
+                continue;
+            }
+
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    if depth == 2 {
+                        prototypes.extend(prototype_key(&fields));
+                        fields.clear();
+                    }
+
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                '"' | '\'' => {
+                    quote = Some(c);
+                    if depth == 2 {
+                        fields.push(c);
+                    }
+                }
+                _ if depth == 2 => fields.push(c),
+                _ => {}
+            }
+        }
+    }
+
+    prototypes
+}
+
+/// Returns a prototype's `type/name` from its fields, if both are literal strings.
+fn prototype_key(fields: &str) -> Option<String> {
+    let mut prototype_type = None;
+    let mut name = None;
+
+    for captures in PROTOTYPE_FIELD.captures_iter(fields) {
+        match &captures[1] {
+            "type" => prototype_type = Some(captures[2].to_owned()),
+            _ => name = Some(captures[2].to_owned()),
+        }
+    }
+
+    Some(format!("{}/{}", prototype_type?, name?))
+}
+
+/// Returns the prototypes referenced in `data.raw` in given comment-free Lua source code as `type/name`.
+fn raw_references(source: &str) -> Vec<String> {
+    RAW_REFERENCE
+        .captures_iter(source)
+        .filter_map(|captures| {
+            let prototype_type = captures.get(1).or_else(|| captures.get(2))?;
+            let name = captures.get(3).or_else(|| captures.get(4))?;
+            Some(format!("{}/{}", prototype_type.as_str(), name.as_str()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_lua() {
+        let mut content = ModContent::default();
+        content.scan(
+            "prototypes/item.lua",
+            r#"
+            -- data:extend({{type = "item", name = "commented-out"}})
+            data:extend({
+                {
+                    type = "recipe",
+                    name = "steel-gear", --[[ a long
+                    comment ]]
+                    ingredients = {{type = "item", name = "steel-plate", amount = 2}},
+                },
+                {name = "steel-gear", type = "item", icon = "__gears__/graphics/gear.png"},
+            })
+            data:extend{{type = "fluid", name = 'molten-steel'}}
+            data.raw["recipe"]["iron-gear-wheel"].enabled = false
+            "#,
+        );
+        content.scan(
+            FINAL_FIXES_FILE,
+            r#"
+            data.raw["recipe"]["iron-gear-wheel"].energy_required = 2
+            data.raw.item["iron-plate"].stack_size = 200
+            for _, recipe in pairs(data.raw.recipe) do end
+            "#,
+        );
+
+        assert_eq!(
+            content.added.into_iter().collect::<Vec<_>>(),
+            vec!["fluid/molten-steel", "item/steel-gear", "recipe/steel-gear"]
+        );
+        assert_eq!(
+            content.final_fixes.into_iter().collect::<Vec<_>>(),
+            vec!["item/iron-plate", "recipe/iron-gear-wheel"]
+        );
+    }
+
+    #[test]
+    fn strip_lua_comments() {
+        assert_eq!(strip_comments("a = 1 -- one\nb = 2"), "a = 1 \nb = 2");
+        assert_eq!(strip_comments("a = 1 --[==[ one ]] still\n ]==] b = 2"), "a = 1  b = 2");
+        assert_eq!(strip_comments("a = [=[ -- kept ]=] --[[ ]]"), "a = [=[ -- kept ]=] ");
+        assert_eq!(strip_comments("a = t[t[1]] -- index"), "a = t[t[1]] ");
+        // non-ASCII text outside strings and comments doesn't split a character
+        assert_eq!(
+            strip_comments("local nämé = \"ö\" -- kommentti ä\nx = 1"),
+            "local nämé = \"ö\" \nx = 1"
+        );
+        assert_eq!(strip_comments("ä --[[ ö"), "ä ");
+    }
+
+    #[test]
+    fn conflicts_between_unrelated_mods() {
+        let content = |name: &str, added: &[&str], final_fixes: &[&str]| ModContent {
+            name: name.to_owned(),
+            added: added.iter().map(|p| (*p).to_owned()).collect(),
+            final_fixes: final_fixes.iter().map(|p| (*p).to_owned()).collect(),
+        };
+        let fixes = &["item/a", "item/b", "item/c"];
+        let contents = vec![
+            content("c", &["item/gear"], fixes),
+            content("a", &["item/gear", "item/plate"], fixes),
+            content("b", &["item/gear"], &["item/a"]),
+        ];
+
+        let mut dependencies = BTreeMap::new();
+        dependencies.insert(String::from("b"), vec!["?c".parse().unwrap()]);
+
+        assert_eq!(
+            analyze(contents, &dependencies),
+            vec![
+                ContentConflict {
+                    mods: (String::from("a"), String::from("b")),
+                    prototypes: vec![String::from("item/gear")],
+                    kind: ContentConflictKind::DuplicatePrototype,
+                },
+                ContentConflict {
+                    mods: (String::from("a"), String::from("c")),
+                    prototypes: vec![String::from("item/gear")],
+                    kind: ContentConflictKind::DuplicatePrototype,
+                },
+                ContentConflict {
+                    mods: (String::from("a"), String::from("c")),
+                    prototypes: fixes.iter().map(|p| (*p).to_owned()).collect(),
+                    kind: ContentConflictKind::FinalFixesCollision,
+                },
+            ]
+        );
+    }
+}
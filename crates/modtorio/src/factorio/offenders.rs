@@ -0,0 +1,189 @@
+//! Provides the [`OffenderTracker`](OffenderTracker) object, which detects addresses that are repeatedly refused a
+//! connection to a server within a time window.
+
+use crate::util::TimeZone;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::{collections::HashMap, net::SocketAddr};
+
+/// An address that has been refused a connection to a server.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Offender {
+    /// The offender's address, without a port.
+    address: String,
+    /// The usernames the offender has tried to connect with.
+    usernames: Vec<String>,
+    /// The reason the last connection was refused.
+    last_reason: String,
+    /// The timestamps of the refused connections within the window.
+    attempts: Vec<DateTime<Utc>>,
+    /// Whether the ban action has been taken for the offender.
+    actioned: bool,
+}
+
+/// Keeps track of the refused connections to a single server.
+#[derive(Debug)]
+pub struct OffenderTracker {
+    /// How many refused connections within the window make an address an offender. 0 disables the detection.
+    threshold: u32,
+    /// The window refused connections are counted in.
+    window: Duration,
+    /// The tracked addresses.
+    addresses: HashMap<String, Offender>,
+}
+
+impl Offender {
+    /// Returns the offender's address, without a port.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Returns the usernames the offender has tried to connect with.
+    pub fn usernames(&self) -> &[String] {
+        &self.usernames
+    }
+
+    /// Returns the reason the last connection was refused.
+    pub fn last_reason(&self) -> &str {
+        &self.last_reason
+    }
+
+    /// Returns how many connections were refused within the window.
+    pub fn attempts(&self) -> usize {
+        self.attempts.len()
+    }
+
+    /// Returns whether the ban action has been taken for the offender.
+    pub fn actioned(&self) -> bool {
+        self.actioned
+    }
+
+    /// Returns the offender in RPC format, with its timestamps also localised to a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::Offender {
+        let first_attempt = self.attempts.first();
+        let last_attempt = self.attempts.last();
+
+        rpc::Offender {
+            address: self.address.clone(),
+            usernames: self.usernames.clone(),
+            last_reason: self.last_reason.clone(),
+            attempts: self.attempts.len() as u32,
+            first_attempt: first_attempt.map_or(0, DateTime::timestamp),
+            last_attempt: last_attempt.map_or(0, DateTime::timestamp),
+            actioned: self.actioned,
+            first_attempt_local: first_attempt.map_or_else(String::new, |attempt| time_zone.format(*attempt)),
+            last_attempt_local: last_attempt.map_or_else(String::new, |attempt| time_zone.format(*attempt)),
+        }
+    }
+}
+
+impl OffenderTracker {
+    /// Returns a new `OffenderTracker` with a given threshold and window.
+    pub fn new(threshold: u32, window: std::time::Duration) -> Self {
+        Self {
+            threshold,
+            window: Duration::seconds(window.as_secs() as i64),
+            addresses: HashMap::new(),
+        }
+    }
+
+    /// Records a refused connection from a given peer at a given time. Returns the peer as an `Offender` if this
+    /// connection made it cross the threshold, after which it is marked as actioned.
+    pub fn record(&mut self, peer: &str, username: &str, reason: &str, now: DateTime<Utc>) -> Option<Offender> {
+        if self.threshold == 0 {
+            return None;
+        }
+
+        self.expire(now);
+        let address = peer_address(peer);
+        let offender = self.addresses.entry(address.clone()).or_insert_with(|| Offender {
+            address,
+            usernames: Vec::new(),
+            last_reason: String::new(),
+            attempts: Vec::new(),
+            actioned: false,
+        });
+
+        if !offender.usernames.iter().any(|existing| existing == username) {
+            offender.usernames.push(username.to_owned());
+        }
+        offender.last_reason = reason.to_owned();
+        offender.attempts.push(now);
+
+        if !offender.actioned && offender.attempts.len() >= self.threshold as usize {
+            offender.actioned = true;
+            Some(offender.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the addresses that have crossed the threshold within the window at a given time.
+    pub fn offenders(&mut self, now: DateTime<Utc>) -> Vec<Offender> {
+        self.expire(now);
+        self.addresses
+            .values()
+            .filter(|offender| offender.actioned)
+            .cloned()
+            .collect()
+    }
+
+    /// Forgets the refused connections that have fallen out of the window at a given time, and the addresses that no
+    /// longer have any.
+    fn expire(&mut self, now: DateTime<Utc>) {
+        let window = self.window;
+        for offender in self.addresses.values_mut() {
+            offender.attempts.retain(|attempt| now - *attempt < window);
+        }
+
+        self.addresses.retain(|_, offender| !offender.attempts.is_empty());
+    }
+}
+
+/// Returns the address part of a given peer address-port-pair, or the peer as-is if it can't be parsed.
+fn peer_address(peer: &str) -> String {
+    peer.parse::<SocketAddr>()
+        .map_or_else(|_| peer.to_owned(), |addr| addr.ip().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let mut tracker = OffenderTracker::new(3, std::time::Duration::from_secs(60));
+        let start = Utc::now();
+
+        assert!(tracker.record("1.2.3.4:1000", "a", "Banned", start).is_none());
+        assert!(tracker.record("1.2.3.4:1001", "b", "Banned", start).is_none());
+        assert!(tracker.record("5.6.7.8:1000", "c", "Banned", start).is_none());
+
+        let offender = tracker
+            .record("1.2.3.4:1002", "a", "Banned", start + Duration::seconds(10))
+            .expect("third refused connection didn't make an offender");
+        assert_eq!(offender.address(), "1.2.3.4");
+        assert_eq!(offender.usernames(), &[String::from("a"), String::from("b")]);
+        assert_eq!(offender.attempts(), 3);
+
+        // the offender is only returned once
+        assert!(tracker
+            .record("1.2.3.4:1003", "a", "Banned", start + Duration::seconds(20))
+            .is_none());
+        assert_eq!(tracker.offenders(start + Duration::seconds(20)).len(), 1);
+
+        // every attempt falls out of the window
+        assert!(tracker.offenders(start + Duration::seconds(90)).is_empty());
+    }
+
+    #[test]
+    fn window() {
+        let mut tracker = OffenderTracker::new(2, std::time::Duration::from_secs(60));
+        let start = Utc::now();
+
+        assert!(tracker.record("1.2.3.4:1000", "a", "Banned", start).is_none());
+        assert!(tracker
+            .record("1.2.3.4:1000", "a", "Banned", start + Duration::seconds(61))
+            .is_none());
+    }
+}
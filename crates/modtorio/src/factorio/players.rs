@@ -0,0 +1,171 @@
+//! Provides the [`Players`](Players) object, which keeps track of the players that have joined a server and their
+//! current sessions.
+
+use crate::util::TimeZone;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// A player that has joined a server since the program started.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Player {
+    /// The player's username.
+    username: String,
+    /// The peer ID of the player's current or latest connection, if known.
+    peer_id: Option<String>,
+    /// The address-port-pair of the player's current or latest connection, if known.
+    address: Option<String>,
+    /// Whether the player is currently in the game.
+    online: bool,
+    /// When the player last joined the game.
+    joined_at: Option<DateTime<Utc>>,
+    /// When the player last left the game, if they have left since joining.
+    left_at: Option<DateTime<Utc>>,
+}
+
+/// Keeps track of the players of a single server.
+#[derive(Debug, Default)]
+pub struct Players {
+    /// The players that have joined, keyed by their usernames.
+    players: HashMap<String, Player>,
+    /// The addresses of the connected peers that haven't yet sent their username, keyed by their peer IDs.
+    pending_addresses: HashMap<String, String>,
+}
+
+impl Player {
+    /// Returns a new `Player` with a given username that hasn't joined yet.
+    fn new(username: &str) -> Self {
+        Self {
+            username: username.to_owned(),
+            peer_id: None,
+            address: None,
+            online: false,
+            joined_at: None,
+            left_at: None,
+        }
+    }
+
+    /// Returns the player's username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Returns whether the player is currently in the game.
+    pub fn online(&self) -> bool {
+        self.online
+    }
+
+    /// Returns the player in RPC format, with its timestamps also localised to a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::Player {
+        rpc::Player {
+            username: self.username.clone(),
+            peer_id: self.peer_id.clone().unwrap_or_default(),
+            address: self.address.clone().unwrap_or_default(),
+            online: self.online,
+            joined_at: self.joined_at.map_or(0, |joined_at| joined_at.timestamp()),
+            left_at: self.left_at.map_or(0, |left_at| left_at.timestamp()),
+            joined_at_local: self
+                .joined_at
+                .map_or_else(String::new, |joined_at| time_zone.format(joined_at)),
+            left_at_local: self
+                .left_at
+                .map_or_else(String::new, |left_at| time_zone.format(left_at)),
+        }
+    }
+}
+
+impl Players {
+    /// Returns a new `Players` with no players.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a given peer connecting from a given address.
+    pub fn peer_added(&mut self, peer_id: &str, address: &str) {
+        self.pending_addresses.insert(peer_id.to_owned(), address.to_owned());
+    }
+
+    /// Records a given peer identifying itself with a given username.
+    pub fn peer_info(&mut self, peer_id: &str, username: &str) {
+        let address = self.pending_addresses.remove(peer_id);
+        let player = self.player_mut(username);
+        player.peer_id = Some(peer_id.to_owned());
+        if address.is_some() {
+            player.address = address;
+        }
+    }
+
+    /// Records a given player joining the game at a given time.
+    pub fn joined(&mut self, username: &str, time: DateTime<Utc>) {
+        let player = self.player_mut(username);
+        player.online = true;
+        player.joined_at = Some(time);
+        player.left_at = None;
+    }
+
+    /// Records a given player leaving the game at a given time.
+    pub fn left(&mut self, username: &str, time: DateTime<Utc>) {
+        let player = self.player_mut(username);
+        if player.online {
+            player.online = false;
+            player.left_at = Some(time);
+        }
+    }
+
+    /// Records every online player leaving the game at a given time, for example because the server stopped.
+    pub fn end_sessions(&mut self, time: DateTime<Utc>) {
+        self.pending_addresses.clear();
+        for player in self.players.values_mut().filter(|player| player.online) {
+            player.online = false;
+            player.left_at = Some(time);
+        }
+    }
+
+    /// Returns every player that has joined, the online players first and then by username.
+    pub fn players(&self) -> Vec<Player> {
+        let mut players: Vec<Player> = self.players.values().cloned().collect();
+        players.sort_by(|a, b| b.online.cmp(&a.online).then_with(|| a.username.cmp(&b.username)));
+        players
+    }
+
+    /// Returns how many players are currently in the game.
+    pub fn online(&self) -> usize {
+        self.players.values().filter(|player| player.online).count()
+    }
+
+    /// Returns a mutable reference to the player with a given username, adding the player if it isn't tracked yet.
+    fn player_mut(&mut self, username: &str) -> &mut Player {
+        self.players
+            .entry(username.to_owned())
+            .or_insert_with(|| Player::new(username))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn session_tracking() {
+        let now = Utc::now();
+        let mut players = Players::new();
+
+        players.peer_added("1", "192.0.2.1:34197");
+        players.peer_info("1", "alice");
+        players.joined("alice", now);
+        players.joined("bob", now);
+        players.left("bob", now + Duration::seconds(10));
+
+        let list = players.players();
+        assert_eq!(list.iter().map(Player::username).collect::<Vec<_>>(), ["alice", "bob"]);
+        assert!(list[0].online());
+        assert_eq!(list[0].address.as_deref(), Some("192.0.2.1:34197"));
+        assert_eq!(list[0].peer_id.as_deref(), Some("1"));
+        assert!(!list[1].online());
+        assert_eq!(players.online(), 1);
+        assert_eq!(list[1].left_at, Some(now + Duration::seconds(10)));
+
+        players.end_sessions(now + Duration::seconds(20));
+        assert!(players.players().iter().all(|player| !player.online()));
+    }
+}
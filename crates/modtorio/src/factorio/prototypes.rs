@@ -0,0 +1,267 @@
+//! Provides [`inspect`](inspect), which finds out which item and recipe prototypes the base game and each of a
+//! server's mods add by running the server executable's data dump once with only the base game enabled, and then once
+//! more for each mod, enabling the mods one at a time in load order.
+
+use super::{
+    executable::{Executable, Tool},
+    mods::Mods,
+};
+use crate::{
+    error::ExecutableError,
+    mod_common::{Dependency, Requirement},
+};
+use log::*;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+use tokio::fs;
+
+/// The name of the base game's mod.
+const BASE_MOD: &str = "base";
+/// The path relative to the executable's write data directory where the data dump is written to.
+const DATA_DUMP_PATH: &str = "script-output/data-raw-dump.json";
+/// The prototype types that are items.
+const ITEM_TYPES: &[&str] = &[
+    "item",
+    "ammo",
+    "armor",
+    "blueprint",
+    "blueprint-book",
+    "capsule",
+    "copy-paste-tool",
+    "deconstruction-item",
+    "gun",
+    "item-with-entity-data",
+    "item-with-inventory",
+    "item-with-label",
+    "item-with-tags",
+    "mining-tool",
+    "module",
+    "rail-planner",
+    "repair-tool",
+    "selection-tool",
+    "spidertron-remote",
+    "tool",
+    "upgrade-item",
+];
+/// The prototype type of recipes.
+const RECIPE_TYPE: &str = "recipe";
+
+/// The prototypes added by the base game or a single mod.
+#[derive(Debug, PartialEq)]
+pub struct ModPrototypes {
+    /// The name of the mod.
+    name: String,
+    /// The names of the added item prototypes, sorted.
+    items: Vec<String>,
+    /// The names of the added recipe prototypes, sorted.
+    recipes: Vec<String>,
+}
+
+/// The prototypes added by the base game and each of a server's mods.
+#[derive(Debug, PartialEq)]
+pub struct PrototypeInspection {
+    /// The base game's prototypes followed by each mod's in load order.
+    mods: Vec<ModPrototypes>,
+}
+
+/// The item and recipe prototypes in a single data dump.
+#[derive(Debug, Default)]
+struct Prototypes {
+    /// The names of the item prototypes.
+    items: HashSet<String>,
+    /// The names of the recipe prototypes.
+    recipes: HashSet<String>,
+}
+
+impl Prototypes {
+    /// Returns the item and recipe prototypes in a given data dump (`data.raw` as JSON).
+    fn from_dump(dump: &str) -> anyhow::Result<Self> {
+        let dump: serde_json::Value = serde_json::from_str(dump)?;
+        let names = |prototype_type: &str| {
+            dump.get(prototype_type)
+                .and_then(serde_json::Value::as_object)
+                .into_iter()
+                .flat_map(|prototypes| prototypes.keys().cloned())
+                .collect::<Vec<_>>()
+        };
+
+        Ok(Self {
+            items: ITEM_TYPES.iter().flat_map(|item_type| names(item_type)).collect(),
+            recipes: names(RECIPE_TYPE).into_iter().collect(),
+        })
+    }
+
+    /// Returns the prototypes that are in this dump but not in a given previous one, attributed to a given mod.
+    fn added_since(&self, previous: &Prototypes, name: &str) -> ModPrototypes {
+        let added = |current: &HashSet<String>, previous: &HashSet<String>| {
+            let mut added = current.difference(previous).cloned().collect::<Vec<_>>();
+            added.sort();
+            added
+        };
+
+        ModPrototypes {
+            name: name.to_owned(),
+            items: added(&self.items, &previous.items),
+            recipes: added(&self.recipes, &previous.recipes),
+        }
+    }
+}
+
+impl From<&PrototypeInspection> for rpc::PrototypeInspection {
+    fn from(inspection: &PrototypeInspection) -> Self {
+        Self {
+            mods: inspection
+                .mods
+                .iter()
+                .map(|prototypes| rpc::prototype_inspection::Mod {
+                    name: prototypes.name.clone(),
+                    items: prototypes.items.clone(),
+                    recipes: prototypes.recipes.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Runs a given executable's data dump with the base game and a given server's mods, and returns which prototypes the
+/// base game and each mod add. The dumps are written to a temporary directory, so the server's own files and mod list
+/// aren't touched.
+pub async fn inspect(executable: &Executable, mods: &Mods) -> anyhow::Result<PrototypeInspection> {
+    let dir = tempfile::tempdir()?;
+    let mod_directory = dir.path().join("mods");
+    fs::create_dir(&mod_directory).await?;
+
+    let config = dir.path().join("config.ini");
+    fs::write(
+        &config,
+        format!(
+            "[path]\nread-data=__PATH__executable__/../../data\nwrite-data={}\n",
+            dir.path().display()
+        ),
+    )
+    .await?;
+
+    let mut dependencies = BTreeMap::new();
+    for fact_mod in mods.iter() {
+        let zip_path = fact_mod.zip_path().await?;
+        fs::os::unix::symlink(mods.directory().join(&zip_path), mod_directory.join(&zip_path)).await?;
+        dependencies.insert(fact_mod.name().await, fact_mod.dependencies().await?);
+    }
+
+    let order = load_order(&dependencies);
+    debug!("Inspecting prototypes of mods in load order {:?}", order);
+
+    let mut previous = dump_prototypes(executable, dir.path(), &order, 0).await?;
+    let mut inspection = vec![previous.added_since(&Prototypes::default(), BASE_MOD)];
+    for (index, name) in order.iter().enumerate() {
+        debug!("Inspecting prototypes of mod '{}'", name);
+        let current = dump_prototypes(executable, dir.path(), &order, index + 1).await?;
+        inspection.push(current.added_since(&previous, name));
+        previous = current;
+    }
+
+    Ok(PrototypeInspection { mods: inspection })
+}
+
+/// Runs a given executable's data dump with the base game and the first `enabled` mods of a given load order enabled,
+/// using the config file and mod directory in a given directory, and returns the dumped prototypes.
+async fn dump_prototypes(
+    executable: &Executable,
+    dir: &Path,
+    order: &[String],
+    enabled: usize,
+) -> anyhow::Result<Prototypes> {
+    let mod_directory = dir.join("mods");
+    let mod_list = serde_json::json!({
+        "mods": std::iter::once((BASE_MOD, true))
+            .chain(order.iter().enumerate().map(|(index, name)| (name.as_str(), index < enabled)))
+            .map(|(name, enabled)| serde_json::json!({ "name": name, "enabled": enabled }))
+            .collect::<Vec<_>>()
+    });
+    fs::write(mod_directory.join("mod-list.json"), serde_json::to_string(&mod_list)?).await?;
+
+    let args = vec![
+        String::from("--config"),
+        dir.join("config.ini").display().to_string(),
+        String::from("--mod-directory"),
+        mod_directory.display().to_string(),
+    ];
+    let output = executable.run_tool_with_args(&Tool::DumpData, &args).await?;
+    if !output.success {
+        return Err(ExecutableError::Unsuccesfull {
+            exit_code: output.exit_code,
+            stdout: Some(output.stdout),
+            stderr: Some(output.stderr),
+        }
+        .into());
+    }
+
+    let dump_path = dir.join(DATA_DUMP_PATH);
+    let prototypes = Prototypes::from_dump(&fs::read_to_string(&dump_path).await?)?;
+    fs::remove_file(&dump_path).await?;
+    Ok(prototypes)
+}
+
+/// Returns the names of given mods in the order the game loads them: each mod after the mods it depends on, optionally
+/// or not, and otherwise in alphabetical order. Mods in a dependency cycle are loaded alphabetically.
+fn load_order(dependencies: &BTreeMap<String, Vec<Dependency>>) -> Vec<String> {
+    let mut order: Vec<String> = Vec::new();
+    let mut remaining = dependencies.keys().cloned().collect::<Vec<_>>();
+
+    while !remaining.is_empty() {
+        let next = remaining
+            .iter()
+            .position(|name| {
+                dependencies[name].iter().all(|dependency| {
+                    dependency.requirement() == Requirement::Incompatible
+                        || !dependencies.contains_key(dependency.name())
+                        || order.iter().any(|loaded| loaded == dependency.name())
+                })
+            })
+            .unwrap_or(0);
+
+        order.push(remaining.remove(next));
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn added_prototypes() {
+        let base = Prototypes::from_dump(r#"{"item": {"iron-plate": {}}, "recipe": {"iron-gear-wheel": {}}}"#).unwrap();
+        let modded = Prototypes::from_dump(
+            r#"{"item": {"iron-plate": {}, "steel-gear": {}}, "ammo": {"big-bullet": {}},
+            "recipe": {"iron-gear-wheel": {}, "steel-gear": {}}, "assembling-machine": {"fast-machine": {}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            modded.added_since(&base, "gears"),
+            ModPrototypes {
+                name: String::from("gears"),
+                items: vec![String::from("big-bullet"), String::from("steel-gear")],
+                recipes: vec![String::from("steel-gear")],
+            }
+        );
+    }
+
+    #[test]
+    fn dependencies_load_first() {
+        let mut dependencies = BTreeMap::new();
+        dependencies.insert(
+            String::from("a"),
+            vec!["base".parse().unwrap(), "c".parse().unwrap(), "!d".parse().unwrap()],
+        );
+        dependencies.insert(String::from("b"), vec!["?a".parse().unwrap()]);
+        dependencies.insert(String::from("c"), vec![]);
+        dependencies.insert(String::from("d"), vec!["a".parse().unwrap()]);
+
+        assert_eq!(load_order(&dependencies), vec!["c", "a", "b", "d"]);
+    }
+}
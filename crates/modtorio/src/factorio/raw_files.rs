@@ -0,0 +1,209 @@
+//! Provides [`RawFile`](RawFile), the current contents of one of a server's configuration files exactly as the game
+//! reads them, to compare against Modtorio's structured view of the server. The secrets in the server settings file
+//! are redacted from its contents.
+
+use super::settings::ServerSettings;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+/// The value secrets in the server settings file are replaced with.
+const REDACTED: &str = "<redacted>";
+
+/// One of a server's configuration files.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum RawFileKind {
+    /// The `server-settings.json` file in the server's root directory.
+    Settings,
+    /// The `mod-list.json` file in the server's mods directory.
+    ModList,
+    /// The `server-adminlist.json` file in the server's root directory.
+    AdminList,
+    /// The `server-whitelist.json` file in the server's root directory.
+    Whitelist,
+    /// The `server-banlist.json` file in the server's root directory.
+    BanList,
+}
+
+/// The current contents of one of a server's configuration files.
+#[derive(Debug, PartialEq)]
+pub struct RawFile {
+    /// Which file this is.
+    kind: RawFileKind,
+    /// The file's path.
+    path: PathBuf,
+    /// The file's contents, or `None` if the file doesn't exist.
+    contents: Option<String>,
+    /// Why the contents don't parse as the file's format, or `None` if they do or the file doesn't exist.
+    parse_error: Option<String>,
+}
+
+impl RawFileKind {
+    /// Every kind of file.
+    pub const ALL: &'static [RawFileKind] = &[
+        Self::Settings,
+        Self::ModList,
+        Self::AdminList,
+        Self::Whitelist,
+        Self::BanList,
+    ];
+
+    /// Returns the file's path given the server's root and mods directories.
+    pub fn path(self, root: &Path, mods: &Path) -> PathBuf {
+        match self {
+            Self::Settings => root.join("server-settings.json"),
+            Self::ModList => mods.join("mod-list.json"),
+            Self::AdminList => root.join("server-adminlist.json"),
+            Self::Whitelist => root.join("server-whitelist.json"),
+            Self::BanList => root.join("server-banlist.json"),
+        }
+    }
+
+    /// Checks whether given contents parse as the file's format. Returns the parse error if they don't.
+    fn parse(self, contents: &str) -> Result<(), String> {
+        match self {
+            Self::Settings => ServerSettings::from_game_json(contents)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Self::ModList => match serde_json::from_str::<Value>(contents).map_err(|e| e.to_string())? {
+                Value::Object(list) if list.get("mods").map_or(false, Value::is_array) => Ok(()),
+                _ => Err(String::from("expected an object with a mods array")),
+            },
+            Self::AdminList | Self::Whitelist | Self::BanList => {
+                match serde_json::from_str::<Value>(contents).map_err(|e| e.to_string())? {
+                    Value::Array(_) => Ok(()),
+                    _ => Err(String::from("expected an array")),
+                }
+            }
+        }
+    }
+
+    /// Returns given contents of the file with any secrets in them replaced with a placeholder.
+    fn redact(self, contents: String) -> String {
+        lazy_static! {
+            static ref SECRET: Regex = Regex::new(r#""(game_password|token|password)"(\s*:\s*)"(?:[^"\\]|\\.)+""#)
+                .expect("failed to create secret regex");
+        }
+
+        match self {
+            Self::Settings => SECRET
+                .replace_all(&contents, format!(r#""$1"$2"{}""#, REDACTED).as_str())
+                .into_owned(),
+            _ => contents,
+        }
+    }
+}
+
+impl From<rpc::raw_server_file::Kind> for RawFileKind {
+    fn from(rpc_format: rpc::raw_server_file::Kind) -> Self {
+        match rpc_format {
+            rpc::raw_server_file::Kind::Settings => Self::Settings,
+            rpc::raw_server_file::Kind::ModList => Self::ModList,
+            rpc::raw_server_file::Kind::AdminList => Self::AdminList,
+            rpc::raw_server_file::Kind::Whitelist => Self::Whitelist,
+            rpc::raw_server_file::Kind::BanList => Self::BanList,
+        }
+    }
+}
+
+impl From<RawFileKind> for rpc::raw_server_file::Kind {
+    fn from(kind: RawFileKind) -> Self {
+        match kind {
+            RawFileKind::Settings => Self::Settings,
+            RawFileKind::ModList => Self::ModList,
+            RawFileKind::AdminList => Self::AdminList,
+            RawFileKind::Whitelist => Self::Whitelist,
+            RawFileKind::BanList => Self::BanList,
+        }
+    }
+}
+
+impl RawFile {
+    /// Reads a given kind of file of a server with given root and mods directories, and checks whether it parses.
+    pub async fn read(kind: RawFileKind, root: &Path, mods: &Path) -> anyhow::Result<Self> {
+        let path = kind.path(root, mods);
+        let contents = match fs::metadata(&path).await {
+            Ok(_) => Some(fs::read_to_string(&path).await?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let parse_error = contents.as_deref().and_then(|contents| kind.parse(contents).err());
+        let contents = contents.map(|contents| kind.redact(contents));
+
+        Ok(Self {
+            kind,
+            path,
+            contents,
+            parse_error,
+        })
+    }
+}
+
+impl From<&RawFile> for rpc::RawServerFile {
+    fn from(file: &RawFile) -> Self {
+        Self {
+            kind: rpc::raw_server_file::Kind::from(file.kind).into(),
+            path: file.path.display().to_string(),
+            exists: file.contents.is_some(),
+            contents: file.contents.clone().unwrap_or_default(),
+            parse_error: file.parse_error.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn read() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("mods");
+        std::fs::create_dir(&mods).unwrap();
+        std::fs::write(
+            mods.join("mod-list.json"),
+            r#"{"mods":[{"name":"base","enabled":true}]}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("server-adminlist.json"), r#"{"admin":true}"#).unwrap();
+
+        let mod_list = RawFile::read(RawFileKind::ModList, dir.path(), &mods).await.unwrap();
+        assert!(mod_list.contents.is_some());
+        assert_eq!(mod_list.parse_error, None);
+
+        let admins = RawFile::read(RawFileKind::AdminList, dir.path(), &mods).await.unwrap();
+        assert!(admins.parse_error.is_some());
+
+        let settings = RawFile::read(RawFileKind::Settings, dir.path(), &mods).await.unwrap();
+        assert_eq!(settings.contents, None);
+        assert_eq!(settings.parse_error, None);
+    }
+
+    #[test]
+    fn redact() {
+        let contents = String::from(
+            r#"{
+  "name": "password",
+  "token": "abc\"def",
+  "game_password" : "hunter2",
+  "password": ""
+}"#,
+        );
+
+        assert_eq!(
+            RawFileKind::Settings.redact(contents.clone()),
+            r#"{
+  "name": "password",
+  "token": "<redacted>",
+  "game_password" : "<redacted>",
+  "password": ""
+}"#
+        );
+        assert_eq!(RawFileKind::ModList.redact(contents.clone()), contents);
+    }
+}
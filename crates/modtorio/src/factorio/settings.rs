@@ -1,26 +1,52 @@
 //! Provides the [`ServerSettings`](ServerSettings) object used to interact with a server's
 //! settings.
+//!
+//! The settings' RPC format is versioned with [`RPC_FORMAT_VERSION`](RPC_FORMAT_VERSION), so settings set by clients
+//! built against an older version keep the fields the clients don't know about.
 
+mod access_lists;
 mod allow_commands;
 mod autosave;
+mod autosave_policy;
+mod autosave_retention;
+mod extra;
 mod game_format;
+mod includes;
 mod information;
+mod management;
+mod map_exchange;
 mod network;
+mod paths;
 mod pause;
 mod publicity;
+pub mod schedule;
 mod start;
 
 use crate::store::models::GameSettings;
+pub use access_lists::AccessLists;
 use allow_commands::AllowCommands;
 use autosave::Autosave;
+pub use autosave_policy::{AutosavePolicy, AutosaveThreshold};
+pub use autosave_retention::AutosaveRetention;
+use extra::Extra;
 use game_format::ServerSettingsGameFormat;
+pub use includes::Includes;
 use information::Information;
+use management::Management;
+pub use map_exchange::{MapExchange, MapGenSettings};
 use network::Network;
+pub use paths::Paths;
 use pause::Pause;
 use publicity::Publicity;
+pub use schedule::Schedule;
 use serde::{Deserialize, Serialize};
 use start::Start;
 pub use start::StartBehaviour;
+use std::path::Path;
+
+/// The version of the settings' RPC format. Increased whenever fields are added to the RPC `ServerSettings`. Version 1
+/// added the extra fields, version 2 the pooled game version and version 3 the admin list and whitelist.
+pub const RPC_FORMAT_VERSION: u32 = 3;
 
 /// Stores a server's settings in a structured manner.
 #[derive(Deserialize, Serialize, Debug, Default)]
@@ -31,6 +57,10 @@ pub struct ServerSettings {
     pub publicity: Publicity,
     /// Contains settings related to the server's autosaving.
     pub autosave: Autosave,
+    /// Contains how the server's autosave interval is adjusted to its player count while it's running.
+    pub autosave_policy: AutosavePolicy,
+    /// Contains which of the server's autosaves are kept when old autosaves are pruned.
+    pub autosave_retention: AutosaveRetention,
     /// Contains settings related to the server's pausing.
     pub pause: Pause,
     /// Represents the `allow_commands` setting.
@@ -39,6 +69,18 @@ pub struct ServerSettings {
     pub network: Network,
     /// Contains settings related to starting the server.
     pub start: Start,
+    /// Contains which aspects of the server Modtorio manages.
+    pub management: Management,
+    /// Contains the locations of the server's saves, mods and script output.
+    pub paths: Paths,
+    /// Contains when the server's recurring tasks are run.
+    pub schedule: Schedule,
+    /// Contains the settings files the server's settings are composed from.
+    pub includes: Includes,
+    /// Contains the settings in the game's settings file that Modtorio doesn't know.
+    pub extra: Extra,
+    /// Contains the server's admin list and whitelist, if Modtorio manages them.
+    pub access_lists: AccessLists,
 }
 
 #[allow(dead_code)]
@@ -49,12 +91,78 @@ impl ServerSettings {
         Ok(ServerSettings::from_game_format(&game_format)?)
     }
 
+    /// Returns a new `ServerSettings` by deserializing the game's bundled `server-settings.example.json` from a given
+    /// JSON string. The example's placeholder values are replaced with ones a fresh server can start with: the server
+    /// isn't public since the example has no factorio.com credentials, and its name is set to a given name.
+    pub fn from_example_game_json(json: &str, name: &str) -> anyhow::Result<Self> {
+        let mut settings = Self::from_game_json(json)?;
+        settings.information.name = name.to_owned();
+        settings.publicity.public = None;
+        Ok(settings)
+    }
+
     /// Returns a string by serializing the `ServerSettings` object into the game's
     /// `server-settings.json` file format.
     pub fn to_game_json(&self) -> anyhow::Result<String> {
         let mut game_format = ServerSettingsGameFormat::default();
         self.to_game_format(&mut game_format)?;
-        Ok(serde_json::to_string(&game_format)?)
+        Ok(serde_json::to_string_pretty(&game_format)?)
+    }
+
+    /// Returns a string of the settings composed from the included settings files and the overrides in the game's
+    /// `server-settings.json` file format, given the server's root directory. If no files are included, the settings
+    /// are returned as-is.
+    pub async fn to_composed_game_json(&self, root: &Path) -> anyhow::Result<String> {
+        match self.composed(root).await? {
+            Some(composed) => composed.to_game_json(),
+            None => self.to_game_json(),
+        }
+    }
+
+    /// Composes the settings from the included settings files and the overrides, given the server's root directory.
+    /// Does nothing if no files are included.
+    pub async fn compose(&mut self, root: &Path) -> anyhow::Result<()> {
+        if let Some(composed) = self.composed(root).await? {
+            self.information = composed.information;
+            self.publicity = composed.publicity;
+            self.autosave = composed.autosave;
+            self.pause = composed.pause;
+            self.allow_commands = composed.allow_commands;
+            self.network = composed.network;
+            self.extra = composed.extra;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the settings from the game's settings file composed from the included settings files and the
+    /// overrides, or `None` if no files are included.
+    async fn composed(&self, root: &Path) -> anyhow::Result<Option<Self>> {
+        if self.includes.is_empty() {
+            return Ok(None);
+        }
+
+        let composed = self.includes.compose(root, self.to_game_value()?).await?;
+        Ok(Some(Self::from_game_format(&serde_json::from_value(composed)?)?))
+    }
+
+    /// Updates the overrides to the settings that differ from the included settings files, given the server's root
+    /// directory. Does nothing if no files are included.
+    pub async fn update_overrides(&mut self, root: &Path) -> anyhow::Result<()> {
+        if self.includes.is_empty() {
+            return Ok(());
+        }
+
+        let full = self.to_game_value()?;
+        self.includes.update_overrides(root, &full).await?;
+        Ok(())
+    }
+
+    /// Returns the settings in the game's `server-settings.json` format as a JSON value.
+    fn to_game_value(&self) -> anyhow::Result<serde_json::Value> {
+        let mut game_format = ServerSettingsGameFormat::default();
+        self.to_game_format(&mut game_format)?;
+        Ok(serde_json::to_value(&game_format)?)
     }
 
     /// Returns a new `ServerSettings` object by constructing it from a given `ServerSettingsGameFormat` object.
@@ -63,10 +171,18 @@ impl ServerSettings {
             information: Information::from_game_format(game_format),
             publicity: Publicity::from_game_format(game_format),
             autosave: Autosave::from_game_format(game_format),
+            autosave_policy: AutosavePolicy::default(),
+            autosave_retention: AutosaveRetention::default(),
             pause: Pause::from_game_format(game_format),
             allow_commands: AllowCommands::from_game_format(game_format)?,
             network: Network::from_game_format(game_format),
             start: Start::default(),
+            management: Management::default(),
+            paths: Paths::default(),
+            schedule: Schedule::default(),
+            includes: Includes::default(),
+            extra: Extra::from_game_format(game_format),
+            access_lists: AccessLists::default(),
         })
     }
 
@@ -78,6 +194,7 @@ impl ServerSettings {
         self.pause.to_game_format(game_format);
         self.allow_commands.to_game_format(game_format);
         self.network.to_game_format(game_format);
+        self.extra.to_game_format(game_format);
 
         Ok(())
     }
@@ -88,10 +205,18 @@ impl ServerSettings {
             information: Information::from_store_format(store_format),
             publicity: Publicity::from_store_format(store_format),
             autosave: Autosave::from_store_format(store_format),
+            autosave_policy: AutosavePolicy::from_store_format(store_format)?,
+            autosave_retention: AutosaveRetention::from_store_format(store_format),
             pause: Pause::from_store_format(store_format),
             allow_commands: AllowCommands::from_store_format(store_format)?,
             network: Network::from_store_format(store_format)?,
             start: Start::from_store_format(store_format),
+            management: Management::from_store_format(store_format),
+            paths: Paths::from_store_format(store_format),
+            schedule: Schedule::from_store_format(store_format),
+            includes: Includes::from_store_format(store_format)?,
+            extra: Extra::from_store_format(store_format)?,
+            access_lists: AccessLists::from_store_format(store_format)?,
         })
     }
 
@@ -100,10 +225,18 @@ impl ServerSettings {
         self.information.to_store_format(store_format);
         self.publicity.to_store_format(store_format);
         self.autosave.to_store_format(store_format);
+        self.autosave_policy.to_store_format(store_format);
+        self.autosave_retention.to_store_format(store_format);
         self.pause.to_store_format(store_format);
         self.allow_commands.to_store_format(store_format);
         self.network.to_store_format(store_format);
         self.start.to_store_format(store_format);
+        self.management.to_store_format(store_format);
+        self.paths.to_store_format(store_format);
+        self.schedule.to_store_format(store_format);
+        self.includes.to_store_format(store_format)?;
+        self.extra.to_store_format(store_format)?;
+        self.access_lists.to_store_format(store_format)?;
 
         Ok(())
     }
@@ -114,10 +247,18 @@ impl ServerSettings {
             information: Information::from_rpc_format(rpc_format),
             publicity: Publicity::from_rpc_format(rpc_format),
             autosave: Autosave::from_rpc_format(rpc_format),
+            autosave_policy: AutosavePolicy::from_rpc_format(rpc_format)?,
+            autosave_retention: AutosaveRetention::from_rpc_format(rpc_format),
             pause: Pause::from_rpc_format(rpc_format),
             allow_commands: AllowCommands::from_rpc_format(rpc_format)?,
             network: Network::from_rpc_format(rpc_format),
             start: Start::from_rpc_format(rpc_format)?,
+            management: Management::from_rpc_format(rpc_format)?,
+            paths: Paths::from_rpc_format(rpc_format),
+            schedule: Schedule::from_rpc_format(rpc_format)?,
+            includes: Includes::from_rpc_format(rpc_format),
+            extra: Extra::from_rpc_format(rpc_format)?,
+            access_lists: AccessLists::from_rpc_format(rpc_format),
         })
     }
 
@@ -126,13 +267,66 @@ impl ServerSettings {
         self.information.to_rpc_format(rpc_format);
         self.publicity.to_rpc_format(rpc_format);
         self.autosave.to_rpc_format(rpc_format);
+        self.autosave_policy.to_rpc_format(rpc_format);
+        self.autosave_retention.to_rpc_format(rpc_format);
         self.pause.to_rpc_format(rpc_format);
         self.allow_commands.to_rpc_format(rpc_format);
         self.network.to_rpc_format(rpc_format);
         self.start.to_rpc_format(rpc_format);
+        self.management.to_rpc_format(rpc_format);
+        self.paths.to_rpc_format(rpc_format);
+        self.schedule.to_rpc_format(rpc_format);
+        self.includes.to_rpc_format(rpc_format);
+        self.extra.to_rpc_format(rpc_format);
+        self.access_lists.to_rpc_format(rpc_format);
+        rpc_format.format_version = RPC_FORMAT_VERSION;
 
         Ok(())
     }
+
+    /// Keeps the given current settings' fields that were added to the RPC format after a given version, for settings
+    /// set by a client that only knows that version of the format and so can't have sent the fields.
+    pub fn keep_fields_newer_than(&mut self, format_version: u32, current: &ServerSettings) {
+        if format_version < 1 {
+            self.extra = current.extra.clone();
+        }
+        if format_version < 2 {
+            self.management.game_version = current.management.game_version;
+        }
+        if format_version < 3 {
+            self.access_lists = current.access_lists.clone();
+        }
+    }
+}
+
+/// Returns given settings in the game's `server-settings.json` file format merged on top of the settings file's
+/// current contents, so the fields in the file the settings don't have are kept as-is. Objects are merged recursively
+/// and other values are replaced. If the current contents aren't a JSON object, the settings are returned as-is.
+pub fn merge_game_json(current: &str, settings: &str) -> anyhow::Result<String> {
+    let mut merged = match serde_json::from_str(current) {
+        Ok(current @ serde_json::Value::Object(_)) => current,
+        _ => return Ok(settings.to_owned()),
+    };
+
+    merge_value(&mut merged, serde_json::from_str(settings)?);
+    Ok(serde_json::to_string_pretty(&merged)?)
+}
+
+/// Merges a given JSON value into another one. Objects are merged recursively and other values are replaced.
+fn merge_value(into: &mut serde_json::Value, value: serde_json::Value) {
+    match (into, value) {
+        (serde_json::Value::Object(into), serde_json::Value::Object(value)) => {
+            for (key, value) in value {
+                match into.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value),
+                    None => {
+                        into.insert(key, value);
+                    }
+                }
+            }
+        }
+        (into, value) => *into = value,
+    }
 }
 
 #[cfg(test)]
@@ -246,4 +440,97 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn unknown_settings() -> anyhow::Result<()> {
+        let mut json: serde_json::Value = serde_json::from_str(&ServerSettings::default().to_game_json()?)?;
+        json["new_setting"] = serde_json::json!({ "enabled": true });
+
+        let settings = ServerSettings::from_game_json(&json.to_string())?;
+        let written: serde_json::Value = serde_json::from_str(&settings.to_game_json()?)?;
+        assert_eq!(written, json);
+
+        let mut from_old_client = ServerSettings::default();
+        from_old_client.keep_fields_newer_than(0, &settings);
+        assert_eq!(from_old_client.extra, settings.extra);
+
+        let mut pooled = ServerSettings::default();
+        pooled.management.game_version = Some("1.1.0".parse()?);
+        let mut from_old_client = ServerSettings::default();
+        from_old_client.keep_fields_newer_than(1, &pooled);
+        assert_eq!(from_old_client.management.game_version, pooled.management.game_version);
+        assert_eq!(from_old_client.extra, ServerSettings::default().extra);
+
+        let mut managed = ServerSettings::default();
+        managed.access_lists.admins = Some(vec![String::from("admin")]);
+        let mut from_old_client = ServerSettings::default();
+        from_old_client.keep_fields_newer_than(2, &managed);
+        assert_eq!(from_old_client.access_lists, managed.access_lists);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merged_game_json() -> anyhow::Result<()> {
+        let current = r#"{"name": "old", "visibility": {"public": true, "steam": true}, "unknown": [1, 2]}"#;
+        let settings = r#"{"name": "new", "visibility": {"public": false, "lan": true}}"#;
+
+        let merged: serde_json::Value = serde_json::from_str(&merge_game_json(current, settings)?)?;
+        assert_eq!(
+            merged,
+            serde_json::json!({
+                "name": "new",
+                "visibility": { "public": false, "lan": true, "steam": true },
+                "unknown": [1, 2]
+            })
+        );
+        assert_eq!(merge_game_json("not json", settings)?, settings);
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_example_game_format() -> anyhow::Result<()> {
+        let obj = ServerSettings::from_example_game_json(
+            r#"{
+  "name": "Name of the game as it will appear in the game listing",
+  "description": "Description of the game that will appear in the listing",
+  "tags": ["game", "tags"],
+  "max_players": 0,
+  "visibility": {
+    "public": true,
+    "lan": true
+  },
+  "username": "",
+  "password": "",
+  "token": "",
+  "game_password": "",
+  "require_user_verification": true,
+  "max_upload_in_kilobytes_per_second": 0,
+  "max_upload_slots": 5,
+  "minimum_latency_in_ticks": 0,
+  "ignore_player_limit_for_returning_players": false,
+  "allow_commands": "admins-only",
+  "autosave_interval": 10,
+  "autosave_slots": 5,
+  "afk_autokick_interval": 0,
+  "auto_pause": true,
+  "only_admins_can_pause_the_game": true,
+  "autosave_only_on_server": true,
+  "non_blocking_saving": false,
+  "minimum_segment_size": 25,
+  "minimum_segment_size_peer_count": 20,
+  "maximum_segment_size": 100,
+  "maximum_segment_size_peer_count": 10
+}"#,
+            "factorio",
+        )?;
+
+        assert_eq!(obj.information.name, "factorio");
+        assert_eq!(obj.publicity.public, None);
+        assert!(obj.publicity.lan);
+        assert_eq!(obj.autosave.interval, 10);
+
+        Ok(())
+    }
 }
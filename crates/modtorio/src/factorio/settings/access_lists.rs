@@ -0,0 +1,99 @@
+//! Provides the [AccessLists](AccessLists) struct which manages a server's admin list and whitelist files.
+
+use crate::store::models::GameSettings;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The name of the server's admin list file in its root directory.
+const ADMIN_LIST_FILENAME: &str = "server-adminlist.json";
+/// The name of the server's whitelist file in its root directory.
+const WHITELIST_FILENAME: &str = "server-whitelist.json";
+
+/// Contains the usernames in a server's `server-adminlist.json` and `server-whitelist.json` files. A list that isn't
+/// set isn't managed by Modtorio, and its file is left as the game and its players leave it. A set list replaces its
+/// file's contents whenever the settings are applied, including changes made in-game such as with `/promote`.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
+pub struct AccessLists {
+    /// The usernames of the server's admins, if managed.
+    pub admins: Option<Vec<String>>,
+    /// The usernames allowed to join the server, if managed.
+    pub whitelist: Option<Vec<String>>,
+}
+
+impl AccessLists {
+    /// Returns the paths of the managed lists' files in a given root directory paired with the files' contents.
+    pub fn game_files(&self, root: &Path) -> anyhow::Result<Vec<(PathBuf, String)>> {
+        let mut files = Vec::new();
+        if let Some(admins) = &self.admins {
+            files.push((root.join(ADMIN_LIST_FILENAME), serde_json::to_string_pretty(admins)?));
+        }
+        if let Some(whitelist) = &self.whitelist {
+            files.push((root.join(WHITELIST_FILENAME), serde_json::to_string_pretty(whitelist)?));
+        }
+
+        Ok(files)
+    }
+
+    /// Returns a new `AccessLists` from a given `GameSettings`.
+    pub fn from_store_format(store_format: &GameSettings) -> anyhow::Result<Self> {
+        Ok(Self {
+            admins: store_format
+                .admin_list
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()?,
+            whitelist: store_format
+                .whitelist
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()?,
+        })
+    }
+
+    /// Modifies a given `GameSettings` with this object's settings.
+    pub fn to_store_format(&self, store_format: &mut GameSettings) -> anyhow::Result<()> {
+        store_format.admin_list = self.admins.as_ref().map(serde_json::to_string).transpose()?;
+        store_format.whitelist = self.whitelist.as_ref().map(serde_json::to_string).transpose()?;
+
+        Ok(())
+    }
+
+    /// Returns a new `AccessLists` from a given `ServerSettings`.
+    pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> Self {
+        Self {
+            admins: rpc_format.admin_list.as_ref().map(|list| list.usernames.clone()),
+            whitelist: rpc_format.whitelist.as_ref().map(|list| list.usernames.clone()),
+        }
+    }
+
+    /// Modifies a given `ServerSettings` with this object's settings.
+    pub fn to_rpc_format(&self, rpc_format: &mut rpc::ServerSettings) {
+        let to_rpc = |usernames: &Vec<String>| rpc::server_settings::UsernameList {
+            usernames: usernames.clone(),
+        };
+        rpc_format.admin_list = self.admins.as_ref().map(to_rpc);
+        rpc_format.whitelist = self.whitelist.as_ref().map(to_rpc);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_files() -> anyhow::Result<()> {
+        let root = Path::new("/server");
+        assert!(AccessLists::default().game_files(root)?.is_empty());
+
+        let lists = AccessLists {
+            admins: None,
+            whitelist: Some(vec![String::from("player")]),
+        };
+        assert_eq!(
+            lists.game_files(root)?,
+            vec![(root.join(WHITELIST_FILENAME), String::from("[\n  \"player\"\n]"))]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+//! Provides the [AutosavePolicy](AutosavePolicy) struct which adjusts a running server's autosave interval to how many
+//! players are in the game.
+
+use crate::{error::SettingsError, store::models::GameSettings};
+use serde::{Deserialize, Serialize};
+
+/// The string separating the thresholds in the program store.
+const THRESHOLD_SEPARATOR: &str = ",";
+/// The character separating a threshold's player count and interval in the program store.
+const FIELD_SEPARATOR: char = ':';
+
+/// An autosave interval used while at least a given amount of players are in the game.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Copy, Clone)]
+pub struct AutosaveThreshold {
+    /// The least amount of players in the game for the interval to be used.
+    pub players: u32,
+    /// The autosave interval in minutes.
+    pub interval: u64,
+}
+
+/// Adjusts a running server's autosave interval depending on how many players are in the game, for example to autosave
+/// less often during peak hours when every autosave pauses more players. The interval of the highest threshold the
+/// player count reaches is used, and below every threshold the server's own autosave interval is used. The interval is
+/// changed with the game's `/config set autosave-interval` command, so it doesn't persist over restarts. These settings
+/// only exist in Modtorio and have no counterpart in the game's settings files.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
+pub struct AutosavePolicy {
+    /// The thresholds, sorted by their player counts. An empty policy never adjusts the interval.
+    pub thresholds: Vec<AutosaveThreshold>,
+}
+
+impl AutosavePolicy {
+    /// Returns whether the policy has no thresholds.
+    pub fn is_empty(&self) -> bool {
+        self.thresholds.is_empty()
+    }
+
+    /// Returns the autosave interval in minutes for a given amount of players in the game, or a given default
+    /// interval if the amount doesn't reach any threshold.
+    pub fn interval(&self, players: usize, default: u64) -> u64 {
+        self.thresholds
+            .iter()
+            .rev()
+            .find(|threshold| players >= threshold.players as usize)
+            .map_or(default, |threshold| threshold.interval)
+    }
+
+    /// Returns a new `AutosavePolicy` with given thresholds sorted by their player counts. Returns
+    /// `SettingsError::InvalidAutosavePolicy` if any of the intervals is zero or two thresholds have the same player
+    /// count.
+    pub fn new(mut thresholds: Vec<AutosaveThreshold>) -> Result<Self, SettingsError> {
+        thresholds.sort_by_key(|threshold| threshold.players);
+
+        if let Some(threshold) = thresholds.iter().find(|threshold| threshold.interval == 0) {
+            return Err(SettingsError::InvalidAutosavePolicy(format!(
+                "the interval for {} players is zero",
+                threshold.players
+            )));
+        }
+        if let Some(pair) = thresholds.windows(2).find(|pair| pair[0].players == pair[1].players) {
+            return Err(SettingsError::InvalidAutosavePolicy(format!(
+                "more than one threshold for {} players",
+                pair[0].players
+            )));
+        }
+
+        Ok(Self { thresholds })
+    }
+
+    /// Returns a new `AutosavePolicy` from a given `GameSettings`.
+    pub fn from_store_format(store_format: &GameSettings) -> anyhow::Result<Self> {
+        let thresholds = match store_format.autosave_policy.as_deref() {
+            Some(thresholds) => thresholds
+                .split(THRESHOLD_SEPARATOR)
+                .map(parse_threshold)
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(Self::new(thresholds)?)
+    }
+
+    /// Modifies a given `GameSettings` with this object's settings.
+    pub fn to_store_format(&self, store_format: &mut GameSettings) {
+        store_format.autosave_policy = if self.is_empty() {
+            None
+        } else {
+            Some(
+                self.thresholds
+                    .iter()
+                    .map(|threshold| format!("{}{}{}", threshold.players, FIELD_SEPARATOR, threshold.interval))
+                    .collect::<Vec<_>>()
+                    .join(THRESHOLD_SEPARATOR),
+            )
+        };
+    }
+
+    /// Returns a new `AutosavePolicy` from a given `ServerSettings`. Returns `SettingsError::InvalidAutosavePolicy` if
+    /// the thresholds are invalid.
+    pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> Result<Self, SettingsError> {
+        Self::new(
+            rpc_format
+                .autosave_thresholds
+                .iter()
+                .map(|threshold| AutosaveThreshold {
+                    players: threshold.players,
+                    interval: threshold.interval,
+                })
+                .collect(),
+        )
+    }
+
+    /// Modifies a given `ServerSettings` with this object's settings.
+    pub fn to_rpc_format(&self, rpc_format: &mut rpc::ServerSettings) {
+        rpc_format.autosave_thresholds = self
+            .thresholds
+            .iter()
+            .map(|threshold| rpc::server_settings::AutosaveThreshold {
+                players: threshold.players,
+                interval: threshold.interval,
+            })
+            .collect();
+    }
+}
+
+/// Parses a threshold from its program store format, the player count and interval separated by a colon.
+fn parse_threshold(threshold: &str) -> Result<AutosaveThreshold, SettingsError> {
+    let invalid = || SettingsError::InvalidAutosavePolicy(format!("invalid stored threshold '{}'", threshold));
+    let mut fields = threshold.splitn(2, FIELD_SEPARATOR);
+
+    let players = fields
+        .next()
+        .and_then(|players| players.parse().ok())
+        .ok_or_else(invalid)?;
+    let interval = fields
+        .next()
+        .and_then(|interval| interval.parse().ok())
+        .ok_or_else(invalid)?;
+    Ok(AutosaveThreshold { players, interval })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> AutosavePolicy {
+        AutosavePolicy::new(vec![
+            AutosaveThreshold {
+                players: 20,
+                interval: 30,
+            },
+            AutosaveThreshold {
+                players: 10,
+                interval: 15,
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn interval() {
+        let policy = policy();
+        assert_eq!(policy.interval(0, 5), 5);
+        assert_eq!(policy.interval(9, 5), 5);
+        assert_eq!(policy.interval(10, 5), 15);
+        assert_eq!(policy.interval(19, 5), 15);
+        assert_eq!(policy.interval(50, 5), 30);
+        assert_eq!(AutosavePolicy::default().interval(50, 5), 5);
+    }
+
+    #[test]
+    fn invalid_thresholds() {
+        let threshold = |players, interval| AutosaveThreshold { players, interval };
+        assert!(AutosavePolicy::new(vec![threshold(10, 0)]).is_err());
+        assert!(AutosavePolicy::new(vec![threshold(10, 15), threshold(10, 30)]).is_err());
+    }
+
+    #[test]
+    fn store_format() {
+        let mut store_format = GameSettings::default();
+        policy().to_store_format(&mut store_format);
+        assert_eq!(store_format.autosave_policy.as_deref(), Some("10:15,20:30"));
+        assert_eq!(AutosavePolicy::from_store_format(&store_format).unwrap(), policy());
+
+        AutosavePolicy::default().to_store_format(&mut store_format);
+        assert_eq!(store_format.autosave_policy, None);
+
+        store_format.autosave_policy = Some(String::from("10"));
+        assert!(AutosavePolicy::from_store_format(&store_format).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Provides the [AutosaveRetention](AutosaveRetention) struct which decides which of a server's autosaves are kept when
+//! its saves directory is pruned.
+
+use crate::{store::models::GameSettings, util::TimeZone};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, path::PathBuf};
+
+/// Decides which of a server's autosaves are kept when old autosaves are pruned from its saves directory, for example
+/// ones left behind after the server's autosave slots were lowered. An autosave is kept if it's one of the most recent
+/// ones, or if it's the latest autosave of its day within the daily retention period. The days are counted in the
+/// instance's time zone. These settings only exist in Modtorio and have no counterpart in the game's settings files.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
+pub struct AutosaveRetention {
+    /// How many of the most recent autosaves are kept.
+    pub keep_recent: u32,
+    /// For how many days, including the current day, the latest autosave of each day is kept.
+    pub keep_daily_days: u32,
+}
+
+impl AutosaveRetention {
+    /// Returns whether the retention is disabled, in which case autosaves are never pruned.
+    pub fn is_disabled(&self) -> bool {
+        self.keep_recent == 0 && self.keep_daily_days == 0
+    }
+
+    /// Returns which of given autosaves paired with their last mtimes are pruned at a given time in a given time zone.
+    /// Nothing is pruned if the retention is disabled.
+    pub fn pruned(
+        &self,
+        mut autosaves: Vec<(PathBuf, DateTime<Utc>)>,
+        time_zone: TimeZone,
+        now: DateTime<Utc>,
+    ) -> Vec<PathBuf> {
+        if self.is_disabled() {
+            return Vec::new();
+        }
+
+        // newest first so each day's first autosave is its latest one
+        autosaves.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let today = time_zone.localize(now).date().naive_local();
+        let mut kept_days = HashSet::new();
+
+        autosaves
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, (path, last_mtime))| {
+                let day = time_zone.localize(last_mtime).date().naive_local();
+                let daily = (today - day).num_days() < i64::from(self.keep_daily_days) && kept_days.insert(day);
+
+                if index < self.keep_recent as usize || daily {
+                    None
+                } else {
+                    Some(path)
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a new `AutosaveRetention` from a given `GameSettings`.
+    pub fn from_store_format(store_format: &GameSettings) -> Self {
+        Self {
+            keep_recent: store_format.autosave_keep_recent as u32,
+            keep_daily_days: store_format.autosave_keep_daily_days as u32,
+        }
+    }
+
+    /// Modifies a given `GameSettings` with this object's settings.
+    pub fn to_store_format(&self, store_format: &mut GameSettings) {
+        store_format.autosave_keep_recent = i64::from(self.keep_recent);
+        store_format.autosave_keep_daily_days = i64::from(self.keep_daily_days);
+    }
+
+    /// Returns a new `AutosaveRetention` from a given `ServerSettings`.
+    pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> Self {
+        Self {
+            keep_recent: rpc_format.autosave_keep_recent,
+            keep_daily_days: rpc_format.autosave_keep_daily_days,
+        }
+    }
+
+    /// Modifies a given `ServerSettings` with this object's settings.
+    pub fn to_rpc_format(&self, rpc_format: &mut rpc::ServerSettings) {
+        rpc_format.autosave_keep_recent = self.keep_recent;
+        rpc_format.autosave_keep_daily_days = self.keep_daily_days;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone as _;
+
+    #[test]
+    fn pruned() {
+        let now = Utc.ymd(2020, 6, 10).and_hms(12, 0, 0);
+        let autosaves = vec![
+            (PathBuf::from("_autosave1.zip"), Utc.ymd(2020, 6, 10).and_hms(11, 0, 0)),
+            (PathBuf::from("_autosave2.zip"), Utc.ymd(2020, 6, 10).and_hms(10, 0, 0)),
+            (PathBuf::from("_autosave3.zip"), Utc.ymd(2020, 6, 10).and_hms(9, 0, 0)),
+            (PathBuf::from("_autosave4.zip"), Utc.ymd(2020, 6, 9).and_hms(23, 0, 0)),
+            (PathBuf::from("_autosave5.zip"), Utc.ymd(2020, 6, 9).and_hms(22, 0, 0)),
+            (PathBuf::from("_autosave6.zip"), Utc.ymd(2020, 6, 1).and_hms(22, 0, 0)),
+        ];
+        let retention = |keep_recent, keep_daily_days| AutosaveRetention {
+            keep_recent,
+            keep_daily_days,
+        };
+        let pruned = |retention: AutosaveRetention| {
+            retention
+                .pruned(autosaves.clone(), TimeZone::default(), now)
+                .into_iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(pruned(retention(0, 0)).is_empty());
+        assert_eq!(
+            pruned(retention(2, 0)),
+            vec!["_autosave3.zip", "_autosave4.zip", "_autosave5.zip", "_autosave6.zip"]
+        );
+        assert_eq!(
+            pruned(retention(2, 2)),
+            vec!["_autosave3.zip", "_autosave5.zip", "_autosave6.zip"]
+        );
+        assert_eq!(
+            pruned(retention(0, 30)),
+            vec!["_autosave2.zip", "_autosave3.zip", "_autosave5.zip"]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Provides the [Extra](Extra) struct which carries the settings in a server's `server-settings.json` file that
+//! Modtorio doesn't know, such as ones added in newer game versions, so they aren't lost when the file is rewritten.
+
+use super::ServerSettingsGameFormat;
+use crate::{error::SettingsError, store::models::GameSettings};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Contains the settings in a server's `server-settings.json` file that Modtorio doesn't know, keyed by their names.
+/// They're written back to the file as-is.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
+pub struct Extra {
+    /// The unknown settings.
+    fields: Map<String, Value>,
+}
+
+impl Extra {
+    /// Returns a new `Extra` from a given `ServerSettingsGameFormat`.
+    pub fn from_game_format(game_format: &ServerSettingsGameFormat) -> Self {
+        Self {
+            fields: game_format.extra.clone(),
+        }
+    }
+
+    /// Modifies a given `ServerSettingsGameFormat` with this object's settings.
+    pub fn to_game_format(&self, game_format: &mut ServerSettingsGameFormat) {
+        game_format.extra = self.fields.clone();
+    }
+
+    /// Returns a new `Extra` from a given `GameSettings`.
+    pub fn from_store_format(store_format: &GameSettings) -> anyhow::Result<Self> {
+        Ok(Self {
+            fields: match &store_format.settings_extra {
+                Some(fields) => serde_json::from_str(fields)?,
+                None => Map::new(),
+            },
+        })
+    }
+
+    /// Modifies a given `GameSettings` with this object's settings.
+    pub fn to_store_format(&self, store_format: &mut GameSettings) -> anyhow::Result<()> {
+        store_format.settings_extra = if self.fields.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&self.fields)?)
+        };
+
+        Ok(())
+    }
+
+    /// Returns a new `Extra` from a given `ServerSettings`. Returns `SettingsError::InvalidExtraField` if a field's
+    /// value isn't valid JSON or if its name is one of the known settings.
+    pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> Result<Self, SettingsError> {
+        let known = serde_json::to_value(ServerSettingsGameFormat::default()).expect("failed to serialize settings");
+        let mut fields = Map::new();
+
+        for (name, value) in &rpc_format.extra_fields {
+            let invalid = |reason: String| SettingsError::InvalidExtraField {
+                name: name.clone(),
+                reason,
+            };
+
+            if known.get(name).is_some() {
+                return Err(invalid(String::from("the setting is known and has its own field")));
+            }
+            let value = serde_json::from_str(value).map_err(|e| invalid(e.to_string()))?;
+            fields.insert(name.clone(), value);
+        }
+
+        Ok(Self { fields })
+    }
+
+    /// Modifies a given `ServerSettings` with this object's settings.
+    pub fn to_rpc_format(&self, rpc_format: &mut rpc::ServerSettings) {
+        rpc_format.extra_fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_string()))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpc_format() {
+        let mut rpc_format = rpc::ServerSettings::default();
+        rpc_format
+            .extra_fields
+            .insert(String::from("new_setting"), String::from(r#"{"enabled":true}"#));
+
+        let extra = Extra::from_rpc_format(&rpc_format).unwrap();
+        let mut round_tripped = rpc::ServerSettings::default();
+        extra.to_rpc_format(&mut round_tripped);
+        assert_eq!(round_tripped.extra_fields, rpc_format.extra_fields);
+
+        rpc_format
+            .extra_fields
+            .insert(String::from("new_setting"), String::from("not json"));
+        assert!(Extra::from_rpc_format(&rpc_format).is_err());
+
+        rpc_format.extra_fields.clear();
+        rpc_format
+            .extra_fields
+            .insert(String::from("max_players"), String::from("10"));
+        assert!(Extra::from_rpc_format(&rpc_format).is_err());
+    }
+}
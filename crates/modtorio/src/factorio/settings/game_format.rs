@@ -2,6 +2,7 @@
 //! `server-settings.json` into Modtorio's [`ServerSettings`](super::ServerSettings) and vice versa.
 
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 /// Stores a server's settings in the same structure as its `server-settings.json` file.
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -58,6 +59,9 @@ pub struct ServerSettingsGameFormat {
     pub maximum_segment_size: u64,
     /// Corresponds to the `maximum_segment_size_peer_count` setting.
     pub maximum_segment_size_peer_count: u64,
+    /// The settings that don't correspond to any of the other fields.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Corresponds to the `visibility` setting object.
@@ -0,0 +1,270 @@
+//! Provides the [Includes](Includes) struct which composes a server's settings from shared settings files and the
+//! server's own overrides.
+
+use crate::{error::SettingsError, store::models::GameSettings, util};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use util::ext::PathExt;
+
+/// The byte separating the included files' paths in the program store.
+const PATH_SEPARATOR: u8 = 0;
+
+/// Contains the settings files a server's settings are composed from. Each file is in the `server-settings.json`
+/// format but may contain only some of its settings, and later files override earlier ones. The server keeps only the
+/// settings that differ from the included ones as its overrides, so a change to a shared file takes effect on every
+/// server that includes it the next time their settings are written. These settings only exist in Modtorio and have
+/// no counterpart in the game's settings files.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
+pub struct Includes {
+    /// The included settings files, each either absolute or relative to the server's root directory.
+    pub files: Vec<PathBuf>,
+    /// The server's settings that differ from the included ones, in the `server-settings.json` format.
+    overrides: Map<String, Value>,
+}
+
+impl Includes {
+    /// Returns whether the server includes no settings files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns `SettingsError::InvalidInclude` if any of the included files, resolved against a given root directory,
+    /// can't be read or doesn't contain a JSON object.
+    pub async fn validate(&self, root: &Path) -> Result<(), SettingsError> {
+        self.read_included(root).await.map(|_| ())
+    }
+
+    /// Returns the given full settings in the `server-settings.json` format composed with the included files and the
+    /// overrides. The full settings are used for the settings neither set.
+    pub async fn compose(&self, root: &Path, full: Value) -> Result<Value, SettingsError> {
+        let mut composed = full;
+        merge(&mut composed, &self.read_included(root).await?);
+        merge(&mut composed, &Value::Object(self.overrides.clone()));
+        Ok(composed)
+    }
+
+    /// Replaces the overrides with the settings in given full settings in the `server-settings.json` format that
+    /// differ from the included files' settings.
+    pub async fn update_overrides(&mut self, root: &Path, full: &Value) -> Result<(), SettingsError> {
+        self.overrides = match diff(&self.read_included(root).await?, full) {
+            Some(Value::Object(overrides)) => overrides,
+            _ => Map::new(),
+        };
+        Ok(())
+    }
+
+    /// Returns the included files' settings merged in order.
+    async fn read_included(&self, root: &Path) -> Result<Value, SettingsError> {
+        let mut included = Value::Object(Map::new());
+        for file in &self.files {
+            let path = root.join(file);
+            let invalid = |reason: String| SettingsError::InvalidInclude {
+                path: path.clone(),
+                reason,
+            };
+
+            let contents = fs::read_to_string(&path).await.map_err(|e| invalid(e.to_string()))?;
+            let settings: Value = serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+            if !settings.is_object() {
+                return Err(invalid(String::from("not a JSON object")));
+            }
+
+            merge(&mut included, &settings);
+        }
+
+        Ok(included)
+    }
+
+    /// Returns a new `Includes` from a given `GameSettings`.
+    pub fn from_store_format(store_format: &GameSettings) -> anyhow::Result<Self> {
+        Ok(Self {
+            files: store_format
+                .settings_includes
+                .as_deref()
+                .map(|paths| {
+                    paths
+                        .split(|b| *b == PATH_SEPARATOR)
+                        .map(util::path_from_bytes)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            overrides: match &store_format.settings_overrides {
+                Some(overrides) => serde_json::from_str(overrides)?,
+                None => Map::new(),
+            },
+        })
+    }
+
+    /// Modifies a given `GameSettings` with this object's settings.
+    pub fn to_store_format(&self, store_format: &mut GameSettings) -> anyhow::Result<()> {
+        store_format.settings_includes = if self.files.is_empty() {
+            None
+        } else {
+            Some(
+                self.files
+                    .iter()
+                    .map(|path| path.to_bytes())
+                    .collect::<Vec<_>>()
+                    .join(&PATH_SEPARATOR),
+            )
+        };
+        store_format.settings_overrides = if self.overrides.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&self.overrides)?)
+        };
+
+        Ok(())
+    }
+
+    /// Returns a new `Includes` from a given `ServerSettings`. The overrides are empty until they're updated with
+    /// [`update_overrides`](#method.update_overrides).
+    pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> Self {
+        Self {
+            files: rpc_format.settings_includes.iter().map(PathBuf::from).collect(),
+            overrides: Map::new(),
+        }
+    }
+
+    /// Modifies a given `ServerSettings` with this object's settings.
+    pub fn to_rpc_format(&self, rpc_format: &mut rpc::ServerSettings) {
+        rpc_format.settings_includes = self.files.iter().map(|path| path.display().to_string()).collect();
+    }
+}
+
+/// Merges a given overlay value into a given base value. Objects are merged key by key recursively, and any other
+/// value replaces the base value.
+fn merge(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+/// Returns the parts of a given full value that differ from a given base value, or `None` if they're equal. Objects
+/// are compared key by key recursively.
+fn diff(base: &Value, full: &Value) -> Option<Value> {
+    match (base, full) {
+        (Value::Object(base), Value::Object(full)) => {
+            let differing: Map<String, Value> = full
+                .iter()
+                .filter_map(|(key, value)| match base.get(key) {
+                    Some(base_value) => diff(base_value, value).map(|value| (key.clone(), value)),
+                    None => Some((key.clone(), value.clone())),
+                })
+                .collect();
+
+            if differing.is_empty() {
+                None
+            } else {
+                Some(Value::Object(differing))
+            }
+        }
+        (base, full) if base == full => None,
+        (_, full) => Some(full.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn merge_and_diff() {
+        let mut base = json!({ "name": "common", "visibility": { "public": true, "lan": true }, "tags": ["a", "b"] });
+        merge(&mut base, &json!({ "visibility": { "lan": false }, "tags": ["c"] }));
+        assert_eq!(
+            base,
+            json!({ "name": "common", "visibility": { "public": true, "lan": false }, "tags": ["c"] })
+        );
+
+        let full = json!({ "name": "server 1", "visibility": { "public": true, "lan": false }, "tags": ["c"] });
+        assert_eq!(diff(&base, &full), Some(json!({ "name": "server 1" })));
+        assert_eq!(diff(&full, &full), None);
+    }
+
+    #[tokio::test]
+    async fn compose_with_shared_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("common-settings.json"),
+            r#"{ "max_players": 10, "visibility": { "public": false } }"#,
+        )
+        .unwrap();
+
+        let mut includes = Includes {
+            files: vec![PathBuf::from("common-settings.json")],
+            ..Includes::default()
+        };
+        let full = json!({ "name": "server 1", "max_players": 10, "visibility": { "public": false, "lan": true } });
+        includes.update_overrides(dir.path(), &full).await.unwrap();
+        assert_eq!(
+            Value::Object(includes.overrides.clone()),
+            json!({ "name": "server 1", "visibility": { "lan": true } })
+        );
+
+        // the shared file changing is reflected in the composed settings but the server's own overrides stay
+        fs::write(
+            dir.path().join("common-settings.json"),
+            r#"{ "max_players": 20, "name": "common" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            includes.compose(dir.path(), full).await.unwrap(),
+            json!({ "name": "server 1", "max_players": 20, "visibility": { "public": false, "lan": true } })
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_include() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("list.json"), "[1, 2]").unwrap();
+
+        let mut includes = Includes {
+            files: vec![PathBuf::from("missing.json")],
+            ..Includes::default()
+        };
+        assert!(includes.validate(dir.path()).await.is_err());
+
+        includes.files = vec![PathBuf::from("list.json")];
+        assert!(includes.validate(dir.path()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn store_format_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("common.json"), "{}").unwrap();
+
+        let mut includes = Includes {
+            files: vec![PathBuf::from("common.json")],
+            ..Includes::default()
+        };
+        includes
+            .update_overrides(dir.path(), &json!({ "name": "server 1" }))
+            .await
+            .unwrap();
+        includes.files.push(PathBuf::from("/srv/shared/eu.json"));
+
+        let mut store_format = GameSettings::default();
+        includes.to_store_format(&mut store_format).unwrap();
+        assert_eq!(Includes::from_store_format(&store_format).unwrap(), includes);
+
+        let mut store_format = GameSettings::default();
+        Includes::default().to_store_format(&mut store_format).unwrap();
+        assert_eq!(store_format.settings_includes, None);
+        assert_eq!(store_format.settings_overrides, None);
+    }
+}
@@ -0,0 +1,74 @@
+//! Provides the [Management](Management) struct which contains which aspects of a server Modtorio manages.
+
+use crate::{error::SettingsError, store::models::GameSettings, util::HumanVersion};
+use serde::{Deserialize, Serialize};
+
+/// Contains which aspects of a server Modtorio manages. These settings only exist in Modtorio and have no
+/// counterpart in the game's settings files.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Management {
+    /// Whether Modtorio manages the server's mods. If not, the mods aren't loaded or verified when the server is
+    /// imported, and the mod RPCs are rejected for the server. Defaults to `true`.
+    pub mods: bool,
+    /// Whether Modtorio starts the server when Modtorio itself starts. Defaults to `false`.
+    pub autostart: bool,
+    /// The Factorio version in the shared version pool the server runs instead of the executable in its root
+    /// directory, or `None` to run its own executable. Defaults to `None`.
+    pub game_version: Option<HumanVersion>,
+}
+
+impl Default for Management {
+    fn default() -> Self {
+        Self {
+            mods: true,
+            autostart: false,
+            game_version: None,
+        }
+    }
+}
+
+impl Management {
+    /// Returns a new `Management` from a given `GameSettings`.
+    pub fn from_store_format(store_format: &GameSettings) -> Self {
+        Self {
+            mods: store_format.mods_managed != 0,
+            autostart: store_format.autostart != 0,
+            game_version: store_format.game_version,
+        }
+    }
+
+    /// Modifies a given `GameSettings` with this object's settings.
+    pub fn to_store_format(&self, store_format: &mut GameSettings) {
+        store_format.mods_managed = self.mods as i64;
+        store_format.autostart = self.autostart as i64;
+        store_format.game_version = self.game_version;
+    }
+
+    /// Returns a new `Management` from a given `ServerSettings`. Returns `SettingsError::InvalidGameVersion` if the
+    /// game version isn't empty or a valid version.
+    pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> Result<Self, SettingsError> {
+        let game_version = if rpc_format.game_version.is_empty() {
+            None
+        } else {
+            Some(
+                rpc_format
+                    .game_version
+                    .parse()
+                    .map_err(|_| SettingsError::InvalidGameVersion(rpc_format.game_version.clone()))?,
+            )
+        };
+
+        Ok(Self {
+            mods: rpc_format.mods_managed,
+            autostart: rpc_format.autostart,
+            game_version,
+        })
+    }
+
+    /// Modifies a given `ServerSettings` with this object's settings.
+    pub fn to_rpc_format(&self, rpc_format: &mut rpc::ServerSettings) {
+        rpc_format.mods_managed = self.mods;
+        rpc_format.autostart = self.autostart;
+        rpc_format.game_version = self.game_version.map(|version| version.to_string()).unwrap_or_default();
+    }
+}
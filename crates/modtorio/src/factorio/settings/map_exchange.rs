@@ -0,0 +1,665 @@
+//! Provides the [`MapExchange`](MapExchange) struct which parses and generates the game's map exchange strings, and
+//! the [`MapGenSettings`](MapGenSettings) struct which corresponds to the game's `map-gen-settings.json` file.
+//!
+//! An exchange string is the game's binary map settings, compressed with zlib, encoded as base64 and wrapped in
+//! `>>>` and `<<<`. The binary data consists of the game version, the map generation settings, the map settings and a
+//! CRC32 checksum of everything before it. Only the map generation settings are parsed; the map settings (pollution,
+//! evolution, expansion and so on) are carried over as-is.
+
+use crate::error::MapExchangeError;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    fmt,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+/// The prefix of an exchange string.
+const PREFIX: &str = ">>>";
+/// The suffix of an exchange string.
+const SUFFIX: &str = "<<<";
+/// The size of the checksum at the end of the binary data in bytes.
+const CHECKSUM_LENGTH: usize = 4;
+/// The largest accepted size of an exchange string's inflated binary data in bytes. Real strings inflate to a few
+/// kilobytes, so anything near this is a decompression bomb rather than map settings.
+const MAX_DATA_SIZE: u64 = 1024 * 1024;
+/// The length of a count or string length that doesn't fit in a single byte is marked with this byte.
+const LONG_COUNT_MARKER: u8 = 0xFF;
+/// How many fixed-point units of a map position are in a tile.
+const POSITION_UNITS_PER_TILE: f64 = 256.0;
+
+/// A parsed map exchange string.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MapExchange {
+    /// The version of the game the string was generated with.
+    version: [u16; 4],
+    /// The byte following the version, carried over as-is.
+    version_suffix: u8,
+    /// The map generation settings.
+    map_gen_settings: MapGenSettings,
+    /// The map settings in the game's binary format.
+    map_settings: Vec<u8>,
+}
+
+/// A map's generation settings, in the format of the game's `map-gen-settings.json` file.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
+pub struct MapGenSettings {
+    /// The inverse of the size of water bodies.
+    pub terrain_segmentation: f32,
+    /// The multiplier for the amount of water.
+    pub water: f32,
+    /// The frequency, size and richness of each autoplace control, such as ores, trees and enemy bases.
+    pub autoplace_controls: BTreeMap<String, FrequencySizeRichness>,
+    /// The autoplace settings of each kind of prototype (`entity`, `tile` and `decorative`).
+    pub autoplace_settings: BTreeMap<String, AutoplaceSettings>,
+    /// Whether autoplace controls that aren't listed are enabled.
+    pub default_enable_all_autoplace_controls: bool,
+    /// The map generation seed.
+    pub seed: u32,
+    /// The width of the map in tiles. 0 means infinite.
+    pub width: u32,
+    /// The height of the map in tiles. 0 means infinite.
+    pub height: u32,
+    /// The area generated when the map is created. Not part of the settings file.
+    #[serde(skip)]
+    area_to_generate_at_start: BoundingBox,
+    /// The multiplier for the size of the starting area.
+    pub starting_area: f32,
+    /// Whether enemies only attack when attacked first.
+    pub peaceful_mode: bool,
+    /// The players' starting positions.
+    pub starting_points: Vec<Position>,
+    /// The names of the noise expressions used for map generation properties, such as `elevation`.
+    pub property_expression_names: BTreeMap<String, String>,
+    /// The cliff settings.
+    pub cliff_settings: CliffSettings,
+}
+
+/// The frequency, size and richness of an autoplaced feature.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+pub struct FrequencySizeRichness {
+    /// The frequency.
+    pub frequency: f32,
+    /// The size.
+    pub size: f32,
+    /// The richness.
+    pub richness: f32,
+}
+
+/// The autoplace settings of a kind of prototype.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
+pub struct AutoplaceSettings {
+    /// Whether prototypes that aren't listed use their default settings.
+    pub treat_missing_as_default: bool,
+    /// The frequency, size and richness of each prototype.
+    pub settings: BTreeMap<String, FrequencySizeRichness>,
+}
+
+/// A position on a map in tiles.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy, Default)]
+pub struct Position {
+    /// The X coordinate.
+    pub x: f64,
+    /// The Y coordinate.
+    pub y: f64,
+}
+
+/// An area on a map.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+struct BoundingBox {
+    /// The area's top left corner.
+    left_top: Position,
+    /// The area's bottom right corner.
+    right_bottom: Position,
+    /// The area's orientation.
+    orientation: f32,
+}
+
+/// The cliff settings of a map.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
+pub struct CliffSettings {
+    /// The name of the cliff prototype.
+    pub name: String,
+    /// The elevation of the first row of cliffs.
+    pub cliff_elevation_0: f32,
+    /// The elevation difference between successive rows of cliffs.
+    pub cliff_elevation_interval: f32,
+    /// The multiplier for how often cliffs appear.
+    pub richness: f32,
+}
+
+/// Reads values from the game's binary format.
+struct Reader<'a> {
+    /// The data being read.
+    data: &'a [u8],
+    /// The position of the next byte to read.
+    position: usize,
+}
+
+/// A value in the game's binary format.
+trait Binary: Sized {
+    /// Reads a value from a given reader.
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError>;
+    /// Writes the value to the end of a given buffer.
+    fn write(&self, buffer: &mut Vec<u8>);
+}
+
+impl MapExchange {
+    /// Returns the map generation settings.
+    pub fn map_gen_settings(&self) -> &MapGenSettings {
+        &self.map_gen_settings
+    }
+
+    /// Replaces the map generation settings.
+    pub fn set_map_gen_settings(&mut self, settings: MapGenSettings) {
+        self.map_gen_settings = settings;
+    }
+
+    /// Returns the version of the game the string was generated with, as `major.minor.patch`.
+    pub fn version(&self) -> String {
+        format!("{}.{}.{}", self.version[0], self.version[1], self.version[2])
+    }
+
+    /// Returns the string's binary data, without the checksum.
+    fn to_binary(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        for part in self.version.iter() {
+            part.write(&mut buffer);
+        }
+        self.version_suffix.write(&mut buffer);
+        self.map_gen_settings.write(&mut buffer);
+        buffer.extend_from_slice(&self.map_settings);
+        buffer
+    }
+}
+
+impl FromStr for MapExchange {
+    type Err = MapExchangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoded = s
+            .trim()
+            .strip_prefix(PREFIX)
+            .and_then(|s| s.strip_suffix(SUFFIX))
+            .ok_or(MapExchangeError::InvalidFormat)?
+            .split_whitespace()
+            .collect::<String>();
+        let compressed = base64::decode(encoded).map_err(|_| MapExchangeError::InvalidFormat)?;
+
+        let mut data = Vec::new();
+        ZlibDecoder::new(compressed.as_slice())
+            .take(MAX_DATA_SIZE + 1)
+            .read_to_end(&mut data)
+            .map_err(|_| MapExchangeError::InvalidFormat)?;
+        if data.len() as u64 > MAX_DATA_SIZE {
+            return Err(MapExchangeError::TooLarge(MAX_DATA_SIZE));
+        }
+
+        if data.len() < CHECKSUM_LENGTH {
+            return Err(MapExchangeError::Truncated);
+        }
+        let (data, checksum) = data.split_at(data.len() - CHECKSUM_LENGTH);
+        let expected = Reader::new(checksum).u32()?;
+        let actual = crc32fast::hash(data);
+        if expected != actual {
+            return Err(MapExchangeError::ChecksumMismatch { expected, actual });
+        }
+
+        let mut reader = Reader::new(data);
+        let version = [reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?];
+        let version_suffix = reader.u8()?;
+        let map_gen_settings = MapGenSettings::read(&mut reader)?;
+
+        Ok(Self {
+            version,
+            version_suffix,
+            map_gen_settings,
+            map_settings: reader.rest().to_vec(),
+        })
+    }
+}
+
+impl fmt::Display for MapExchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = self.to_binary();
+        let checksum = crc32fast::hash(&data);
+        checksum.write(&mut data);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&data).map_err(|_| fmt::Error)?;
+        let compressed = encoder.finish().map_err(|_| fmt::Error)?;
+
+        write!(f, "{}{}{}", PREFIX, base64::encode(compressed), SUFFIX)
+    }
+}
+
+impl MapGenSettings {
+    /// Returns new `MapGenSettings` by deserializing a given `map-gen-settings.json` file's contents. The area
+    /// generated when the map is created isn't in the file, so it's left empty.
+    pub fn from_game_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Returns a string by serializing the settings into the game's `map-gen-settings.json` file format.
+    pub fn to_game_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl<'a> Reader<'a> {
+    /// Returns a new `Reader` reading given data from the beginning.
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// Returns the next given amount of bytes.
+    fn bytes(&mut self, amount: usize) -> Result<&'a [u8], MapExchangeError> {
+        let end = self
+            .position
+            .checked_add(amount)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(MapExchangeError::Truncated)?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// Returns the rest of the data.
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+
+    /// Reads a byte.
+    fn u8(&mut self) -> Result<u8, MapExchangeError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Reads a little-endian 16-bit unsigned integer.
+    fn u16(&mut self) -> Result<u16, MapExchangeError> {
+        Ok(u16::from_le_bytes(
+            self.bytes(2)?.try_into().expect("slice has the wrong length"),
+        ))
+    }
+
+    /// Reads a little-endian 32-bit unsigned integer.
+    fn u32(&mut self) -> Result<u32, MapExchangeError> {
+        Ok(u32::from_le_bytes(
+            self.bytes(4)?.try_into().expect("slice has the wrong length"),
+        ))
+    }
+
+    /// Reads a count or a length, which is a single byte if it's small enough and a 32-bit integer otherwise.
+    fn count(&mut self) -> Result<usize, MapExchangeError> {
+        match self.u8()? {
+            LONG_COUNT_MARKER => Ok(self.u32()? as usize),
+            count => Ok(count as usize),
+        }
+    }
+}
+
+/// Writes a count or a length, which is a single byte if it's small enough and a 32-bit integer otherwise.
+fn write_count(count: usize, buffer: &mut Vec<u8>) {
+    if count < LONG_COUNT_MARKER as usize {
+        (count as u8).write(buffer);
+    } else {
+        LONG_COUNT_MARKER.write(buffer);
+        (count as u32).write(buffer);
+    }
+}
+
+impl Binary for u8 {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        reader.u8()
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.push(*self);
+    }
+}
+
+impl Binary for u16 {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        reader.u16()
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Binary for u32 {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        reader.u32()
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Binary for i32 {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        Ok(reader.u32()? as i32)
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Binary for f32 {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        Ok(f32::from_bits(reader.u32()?))
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Binary for bool {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        match reader.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(MapExchangeError::InvalidValue("bool")),
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        (*self as u8).write(buffer);
+    }
+}
+
+impl Binary for String {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        let length = reader.count()?;
+        String::from_utf8(reader.bytes(length)?.to_vec()).map_err(|_| MapExchangeError::InvalidValue("string"))
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        write_count(self.len(), buffer);
+        buffer.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl<T: Binary> Binary for Vec<T> {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        let count = reader.count()?;
+        (0..count).map(|_| T::read(reader)).collect()
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        write_count(self.len(), buffer);
+        for value in self {
+            value.write(buffer);
+        }
+    }
+}
+
+impl<T: Binary> Binary for BTreeMap<String, T> {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        let count = reader.count()?;
+        (0..count)
+            .map(|_| Ok((String::read(reader)?, T::read(reader)?)))
+            .collect()
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        write_count(self.len(), buffer);
+        for (key, value) in self {
+            key.write(buffer);
+            value.write(buffer);
+        }
+    }
+}
+
+impl Binary for MapGenSettings {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        Ok(Self {
+            terrain_segmentation: f32::read(reader)?,
+            water: f32::read(reader)?,
+            autoplace_controls: BTreeMap::read(reader)?,
+            autoplace_settings: BTreeMap::read(reader)?,
+            default_enable_all_autoplace_controls: bool::read(reader)?,
+            seed: u32::read(reader)?,
+            width: u32::read(reader)?,
+            height: u32::read(reader)?,
+            area_to_generate_at_start: BoundingBox::read(reader)?,
+            starting_area: f32::read(reader)?,
+            peaceful_mode: bool::read(reader)?,
+            starting_points: Vec::read(reader)?,
+            property_expression_names: BTreeMap::read(reader)?,
+            cliff_settings: CliffSettings::read(reader)?,
+        })
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        self.terrain_segmentation.write(buffer);
+        self.water.write(buffer);
+        self.autoplace_controls.write(buffer);
+        self.autoplace_settings.write(buffer);
+        self.default_enable_all_autoplace_controls.write(buffer);
+        self.seed.write(buffer);
+        self.width.write(buffer);
+        self.height.write(buffer);
+        self.area_to_generate_at_start.write(buffer);
+        self.starting_area.write(buffer);
+        self.peaceful_mode.write(buffer);
+        self.starting_points.write(buffer);
+        self.property_expression_names.write(buffer);
+        self.cliff_settings.write(buffer);
+    }
+}
+
+impl Binary for FrequencySizeRichness {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        Ok(Self {
+            frequency: f32::read(reader)?,
+            size: f32::read(reader)?,
+            richness: f32::read(reader)?,
+        })
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        self.frequency.write(buffer);
+        self.size.write(buffer);
+        self.richness.write(buffer);
+    }
+}
+
+impl Binary for AutoplaceSettings {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        Ok(Self {
+            treat_missing_as_default: bool::read(reader)?,
+            settings: BTreeMap::read(reader)?,
+        })
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        self.treat_missing_as_default.write(buffer);
+        self.settings.write(buffer);
+    }
+}
+
+impl Binary for Position {
+    // positions are stored as fixed-point numbers
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        Ok(Self {
+            x: f64::from(i32::read(reader)?) / POSITION_UNITS_PER_TILE,
+            y: f64::from(i32::read(reader)?) / POSITION_UNITS_PER_TILE,
+        })
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        ((self.x * POSITION_UNITS_PER_TILE).round() as i32).write(buffer);
+        ((self.y * POSITION_UNITS_PER_TILE).round() as i32).write(buffer);
+    }
+}
+
+impl Binary for BoundingBox {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        Ok(Self {
+            left_top: Position::read(reader)?,
+            right_bottom: Position::read(reader)?,
+            orientation: f32::read(reader)?,
+        })
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        self.left_top.write(buffer);
+        self.right_bottom.write(buffer);
+        self.orientation.write(buffer);
+    }
+}
+
+impl Binary for CliffSettings {
+    fn read(reader: &mut Reader<'_>) -> Result<Self, MapExchangeError> {
+        Ok(Self {
+            name: String::read(reader)?,
+            cliff_elevation_0: f32::read(reader)?,
+            cliff_elevation_interval: f32::read(reader)?,
+            richness: f32::read(reader)?,
+        })
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        self.name.write(buffer);
+        self.cliff_elevation_0.write(buffer);
+        self.cliff_elevation_interval.write(buffer);
+        self.richness.write(buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange() -> MapExchange {
+        let normal = FrequencySizeRichness {
+            frequency: 1.0,
+            size: 1.0,
+            richness: 1.0,
+        };
+
+        let mut autoplace_controls = BTreeMap::new();
+        autoplace_controls.insert(String::from("coal"), normal);
+        autoplace_controls.insert(
+            String::from("enemy-base"),
+            FrequencySizeRichness {
+                frequency: 0.5,
+                size: 2.0,
+                richness: 1.0,
+            },
+        );
+
+        let mut entity = AutoplaceSettings {
+            treat_missing_as_default: true,
+            settings: BTreeMap::new(),
+        };
+        entity.settings.insert(String::from("rock-big"), normal);
+        let mut autoplace_settings = BTreeMap::new();
+        autoplace_settings.insert(String::from("entity"), entity);
+
+        let mut property_expression_names = BTreeMap::new();
+        property_expression_names.insert(String::from("elevation"), String::from("0_17-island"));
+
+        MapExchange {
+            version: [1, 0, 0, 54889],
+            version_suffix: 0,
+            map_gen_settings: MapGenSettings {
+                terrain_segmentation: 1.0,
+                water: 0.5,
+                autoplace_controls,
+                autoplace_settings,
+                default_enable_all_autoplace_controls: true,
+                seed: 123_456_789,
+                width: 2000,
+                height: 0,
+                area_to_generate_at_start: BoundingBox {
+                    left_top: Position { x: -128.0, y: -128.0 },
+                    right_bottom: Position { x: 128.0, y: 128.0 },
+                    orientation: 0.0,
+                },
+                starting_area: 1.5,
+                peaceful_mode: false,
+                starting_points: vec![Position { x: 0.5, y: -10.25 }],
+                property_expression_names,
+                cliff_settings: CliffSettings {
+                    name: String::from("cliff"),
+                    cliff_elevation_0: 10.0,
+                    cliff_elevation_interval: 40.0,
+                    richness: 1.0,
+                },
+            },
+            map_settings: vec![1, 2, 3, 4, 5],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let exchange = exchange();
+        let string = exchange.to_string();
+        assert!(string.starts_with(PREFIX) && string.ends_with(SUFFIX));
+
+        let parsed: MapExchange = string.parse().unwrap();
+        assert_eq!(parsed, exchange);
+        assert_eq!(parsed.version(), "1.0.0");
+    }
+
+    #[test]
+    fn whitespace_ignored() {
+        let string = exchange().to_string();
+        let (first, second) = string.split_at(20);
+        let wrapped = format!("  {}\n{}\n", first, second);
+
+        assert_eq!(wrapped.parse::<MapExchange>().unwrap(), exchange());
+    }
+
+    #[test]
+    fn invalid_strings() {
+        assert!(matches!(
+            "not an exchange string".parse::<MapExchange>(),
+            Err(MapExchangeError::InvalidFormat)
+        ));
+
+        let string = exchange().to_string();
+        let encoded = &string[PREFIX.len()..string.len() - SUFFIX.len()];
+        let mut data = Vec::new();
+        ZlibDecoder::new(base64::decode(encoded).unwrap().as_slice())
+            .read_to_end(&mut data)
+            .unwrap();
+        data[10] ^= 0xFF;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let corrupted = format!("{}{}{}", PREFIX, base64::encode(encoder.finish().unwrap()), SUFFIX);
+        assert!(matches!(
+            corrupted.parse::<MapExchange>(),
+            Err(MapExchangeError::ChecksumMismatch { .. })
+        ));
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&vec![0; MAX_DATA_SIZE as usize + 1]).unwrap();
+        let bomb = format!("{}{}{}", PREFIX, base64::encode(encoder.finish().unwrap()), SUFFIX);
+        assert!(matches!(
+            bomb.parse::<MapExchange>(),
+            Err(MapExchangeError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn game_json() {
+        let settings = exchange().map_gen_settings;
+        let parsed = MapGenSettings::from_game_json(&settings.to_game_json().unwrap()).unwrap();
+
+        assert_eq!(parsed.autoplace_controls, settings.autoplace_controls);
+        assert_eq!(parsed.starting_points, settings.starting_points);
+        assert_eq!(parsed.area_to_generate_at_start, BoundingBox::default());
+    }
+}
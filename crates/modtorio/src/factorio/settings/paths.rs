@@ -0,0 +1,155 @@
+//! Provides the [Paths](Paths) struct which contains the locations of a server's saves, mods and script output.
+
+use crate::{error::SettingsError, store::models::GameSettings, util};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use util::ext::PathExt;
+
+/// The default path relative to the server's root directory where the server's saves are stored.
+const DEFAULT_SAVES_PATH: &str = "saves/";
+/// The default path relative to the server's root directory where the server's mods are stored.
+const DEFAULT_MODS_PATH: &str = "mods/";
+/// The path relative to the server's root directory where the game writes its script output.
+const DEFAULT_SCRIPT_OUTPUT_PATH: &str = "script-output";
+
+/// Contains the locations of a server's saves, mods and script output. Each location is either absolute or relative to
+/// the server's root directory, and `None` means the game's default location under the root directory. These
+/// settings only exist in Modtorio and have no counterpart in the game's settings files.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
+pub struct Paths {
+    /// The directory the server's saves are stored in.
+    pub saves: Option<PathBuf>,
+    /// The directory the server's mods are stored in.
+    pub mods: Option<PathBuf>,
+    /// The directory the game writes its script output to.
+    pub script_output: Option<PathBuf>,
+}
+
+impl Paths {
+    /// Returns the directory the server's saves are stored in, given the server's root directory.
+    pub fn saves(&self, root: &Path) -> PathBuf {
+        resolve(root, self.saves.as_deref(), DEFAULT_SAVES_PATH)
+    }
+
+    /// Returns the directory the server's mods are stored in, given the server's root directory.
+    pub fn mods(&self, root: &Path) -> PathBuf {
+        resolve(root, self.mods.as_deref(), DEFAULT_MODS_PATH)
+    }
+
+    /// Returns the directory the game writes its script output to, given the server's root directory.
+    pub fn script_output(&self, root: &Path) -> PathBuf {
+        resolve(root, self.script_output.as_deref(), DEFAULT_SCRIPT_OUTPUT_PATH)
+    }
+
+    /// Returns the path in the server's root directory the game always writes its script output to. If the script
+    /// output location is customised, this path is a symlink to it.
+    pub fn game_script_output(root: &Path) -> PathBuf {
+        root.join(DEFAULT_SCRIPT_OUTPUT_PATH)
+    }
+
+    /// Returns `SettingsError::InvalidDirectory` if any of the customised locations, resolved against a given root
+    /// directory, isn't an existing directory.
+    pub async fn validate(&self, root: &Path) -> Result<(), SettingsError> {
+        let custom = [
+            ("saves", &self.saves, self.saves(root)),
+            ("mods", &self.mods, self.mods(root)),
+            ("script output", &self.script_output, self.script_output(root)),
+        ];
+
+        for (kind, setting, path) in custom.iter() {
+            if setting.is_some() && !fs::metadata(path).await.map_or(false, |meta| meta.is_dir()) {
+                return Err(SettingsError::InvalidDirectory {
+                    kind,
+                    path: path.to_owned(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a new `Paths` from a given `GameSettings`.
+    pub fn from_store_format(store_format: &GameSettings) -> Self {
+        Self {
+            saves: store_format.saves_path.as_deref().map(util::path_from_bytes),
+            mods: store_format.mods_path.as_deref().map(util::path_from_bytes),
+            script_output: store_format.script_output_path.as_deref().map(util::path_from_bytes),
+        }
+    }
+
+    /// Modifies a given `GameSettings` with this object's settings.
+    pub fn to_store_format(&self, store_format: &mut GameSettings) {
+        store_format.saves_path = self.saves.as_ref().map(|path| path.to_bytes());
+        store_format.mods_path = self.mods.as_ref().map(|path| path.to_bytes());
+        store_format.script_output_path = self.script_output.as_ref().map(|path| path.to_bytes());
+    }
+
+    /// Returns a new `Paths` from a given `ServerSettings`.
+    pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> Self {
+        Self {
+            saves: path_from_rpc_format(&rpc_format.saves_path),
+            mods: path_from_rpc_format(&rpc_format.mods_path),
+            script_output: path_from_rpc_format(&rpc_format.script_output_path),
+        }
+    }
+
+    /// Modifies a given `ServerSettings` with this object's settings.
+    pub fn to_rpc_format(&self, rpc_format: &mut rpc::ServerSettings) {
+        rpc_format.saves_path = path_to_rpc_format(self.saves.as_deref());
+        rpc_format.mods_path = path_to_rpc_format(self.mods.as_deref());
+        rpc_format.script_output_path = path_to_rpc_format(self.script_output.as_deref());
+    }
+}
+
+/// Returns a given customised location resolved against a given root directory, or the root directory joined with a
+/// given default location if the location isn't customised.
+fn resolve(root: &Path, custom: Option<&Path>, default: &str) -> PathBuf {
+    root.join(custom.unwrap_or_else(|| Path::new(default)))
+}
+
+/// Returns a customised location from a given RPC path string, where an empty string means the default location.
+fn path_from_rpc_format(path: &str) -> Option<PathBuf> {
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Returns an RPC path string from a given customised location, where an empty string means the default location.
+fn path_to_rpc_format(path: Option<&Path>) -> String {
+    path.map(|path| path.display().to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_locations() {
+        let root = Path::new("/srv/factorio");
+        let paths = Paths {
+            saves: Some(PathBuf::from("/mnt/fast/saves")),
+            mods: Some(PathBuf::from("../shared/mods")),
+            script_output: None,
+        };
+
+        assert_eq!(paths.saves(root), Path::new("/mnt/fast/saves"));
+        assert_eq!(paths.mods(root), Path::new("/srv/factorio/../shared/mods"));
+        assert_eq!(paths.script_output(root), Path::new("/srv/factorio/script-output"));
+    }
+
+    #[tokio::test]
+    async fn validate_missing_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths = Paths {
+            saves: Some(dir.path().to_path_buf()),
+            ..Paths::default()
+        };
+        assert!(paths.validate(Path::new("/")).await.is_ok());
+
+        paths.mods = Some(dir.path().join("missing"));
+        assert!(paths.validate(Path::new("/")).await.is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Provides the [Schedule](Schedule) struct which contains when a server's recurring tasks are run.
+
+use crate::{error::SettingsError, store::models::GameSettings};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Contains when a server's recurring tasks are run, as cron expressions. An expression has either five fields
+/// (minute, hour, day of month, month, day of week) or additionally a leading seconds field, and is evaluated in the
+/// instance's time zone. These settings only exist in Modtorio and have no counterpart in the game's settings files.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
+pub struct Schedule {
+    /// When the server's mods are updated automatically, if they are. Updates are only run if Modtorio manages the
+    /// server's mods.
+    pub mod_updates: Option<String>,
+}
+
+impl Schedule {
+    /// Returns the parsed schedule of the server's automatic mod updates, if they're scheduled.
+    pub fn mod_updates(&self) -> Result<Option<cron::Schedule>, SettingsError> {
+        self.mod_updates.as_deref().map(parse).transpose()
+    }
+
+    /// Returns `SettingsError::InvalidSchedule` if any of the schedules isn't a valid cron expression.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        self.mod_updates().map(|_| ())
+    }
+
+    /// Returns a new `Schedule` from a given `GameSettings`.
+    pub fn from_store_format(store_format: &GameSettings) -> Self {
+        Self {
+            mod_updates: store_format.mod_update_schedule.clone(),
+        }
+    }
+
+    /// Modifies a given `GameSettings` with this object's settings.
+    pub fn to_store_format(&self, store_format: &mut GameSettings) {
+        store_format.mod_update_schedule = self.mod_updates.clone();
+    }
+
+    /// Returns a new `Schedule` from a given `ServerSettings`. Returns `SettingsError::InvalidSchedule` if any of the
+    /// schedules isn't a valid cron expression.
+    pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> Result<Self, SettingsError> {
+        let schedule = Self {
+            mod_updates: Some(rpc_format.mod_update_schedule.trim().to_owned()).filter(|expr| !expr.is_empty()),
+        };
+
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    /// Modifies a given `ServerSettings` with this object's settings.
+    pub fn to_rpc_format(&self, rpc_format: &mut rpc::ServerSettings) {
+        rpc_format.mod_update_schedule = self.mod_updates.clone().unwrap_or_default();
+    }
+}
+
+/// Parses a given cron expression of either five or six fields into a schedule. A five-field expression is run at the
+/// start of its minutes.
+pub fn parse(expression: &str) -> Result<cron::Schedule, SettingsError> {
+    let expression = expression.trim();
+    let full = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_owned()
+    };
+
+    cron::Schedule::from_str(&full).map_err(|e| SettingsError::InvalidSchedule {
+        expression: expression.to_owned(),
+        reason: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn parse_expressions() {
+        let after = Utc.ymd(2020, 9, 1).and_hms(12, 0, 30);
+
+        let five = parse("30 4 * * *").unwrap();
+        assert_eq!(five.after(&after).next(), Some(Utc.ymd(2020, 9, 2).and_hms(4, 30, 0)));
+
+        let six = parse("15 0 */6 * * *").unwrap();
+        assert_eq!(six.after(&after).next(), Some(Utc.ymd(2020, 9, 1).and_hms(18, 0, 15)));
+
+        assert!(parse("not a schedule").is_err());
+        assert!(parse("61 * * * *").is_err());
+    }
+
+    #[test]
+    fn from_rpc_format() {
+        let mut rpc_format = rpc::ServerSettings::default();
+        assert_eq!(Schedule::from_rpc_format(&rpc_format).unwrap(), Schedule::default());
+
+        rpc_format.mod_update_schedule = String::from("0 3 * * 1");
+        assert_eq!(
+            Schedule::from_rpc_format(&rpc_format).unwrap().mod_updates.as_deref(),
+            Some("0 3 * * 1")
+        );
+
+        rpc_format.mod_update_schedule = String::from("every night");
+        assert!(Schedule::from_rpc_format(&rpc_format).is_err());
+    }
+}
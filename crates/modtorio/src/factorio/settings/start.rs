@@ -29,9 +29,10 @@ pub enum StartBehaviour {
     LoadLatest,
     /// Corresponds to using the `--start-server` command line option.
     LoadFile,
-    /// Corresponds to using the `--start-server-load-scenario` command line option.
-    LoadScenario, // TODO: these two
-    /// Corresponds to using the `--create` command line option.
+    /// Corresponds to using the `--start-server-load-scenario` command line option with the scenario name.
+    LoadScenario,
+    /// Corresponds to using the `--create` command line option to create the save with the game's default map
+    /// settings if it doesn't exist, and then the `--start-server` command line option to load it.
     Create,
 }
 
@@ -56,7 +57,24 @@ impl FromSql for StartBehaviour {
     }
 }
 
+impl StartBehaviour {
+    /// Returns whether the behaviour needs a save or scenario name.
+    pub fn needs_save_name(self) -> bool {
+        self != Self::LoadLatest
+    }
+}
+
 impl Start {
+    /// Returns the file name of the save the `LoadFile` and `Create` behaviours load: the save name with the `.zip`
+    /// extension added if it's missing.
+    pub fn save_filename(&self) -> String {
+        if self.save_name.ends_with(".zip") {
+            self.save_name.clone()
+        } else {
+            format!("{}.zip", self.save_name)
+        }
+    }
+
     /// Returns a new `Start` from a given `GameSettings`.
     pub fn from_store_format(store_format: &GameSettings) -> Self {
         Self {
@@ -73,15 +91,20 @@ impl Start {
 
     /// Returns a new `Start` from a given `ServerSettings`.
     pub fn from_rpc_format(rpc_format: &rpc::ServerSettings) -> anyhow::Result<Self> {
+        let behaviour = match rpc_format.start_behaviour {
+            0 => StartBehaviour::LoadLatest,
+            1 => StartBehaviour::LoadFile,
+            2 => StartBehaviour::LoadScenario,
+            3 => StartBehaviour::Create,
+            v => return Err(SettingsError::UnexpectedValue(v.to_string()).into()),
+        };
+        if behaviour.needs_save_name() && rpc_format.save_name.trim().is_empty() {
+            return Err(SettingsError::MissingSaveName(behaviour).into());
+        }
+
         Ok(Self {
             save_name: rpc_format.save_name.to_owned(),
-            behaviour: match rpc_format.start_behaviour {
-                0 => StartBehaviour::LoadLatest,
-                1 => StartBehaviour::LoadFile,
-                2 => StartBehaviour::LoadScenario,
-                3 => StartBehaviour::Create,
-                v => return Err(SettingsError::UnexpectedValue(v.to_string()).into()),
-            },
+            behaviour,
         })
     }
 
@@ -1,11 +1,16 @@
 //! Provides the `ServerStatus` struct, used to represent a server's status in terms of the server as a whole and the
 //! in-game status.
 
+use crate::util::HumanVersion;
 use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 use strum_macros::EnumString; // TODO: don't use these RPC enums, instead make own and convert to/from
 
 /// Represent a server's status in terms of the server's execution and the in-game status.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct ServerStatus {
     /// The server executable's status.
     game_status: ExecutionStatus,
@@ -13,6 +18,98 @@ pub struct ServerStatus {
     in_game_status: InGameStatus,
     /// Timestamp when the server was started.
     started_at: DateTime<Utc>,
+    /// How many output lines since the server was started weren't recognised as game events.
+    unparsed_lines: u64,
+    /// How many output lines since the server was started were dropped because the output channel was full.
+    dropped_lines: u64,
+    /// Whether the server's settings file conflicts with its stored settings.
+    settings_conflict: bool,
+    /// How many times the server has been tried to be started automatically since the program started.
+    autostart_attempts: u32,
+    /// Whether automatically starting the server was given up after too many failed attempts.
+    autostart_gave_up: bool,
+    /// The graceful action waiting to be carried out on the server, if any.
+    pending_action: Option<PendingAction>,
+    /// The save the running game loaded, as logged by the game.
+    current_save: Option<PathBuf>,
+    /// The running game's version, as logged by the game.
+    game_version: Option<HumanVersion>,
+    /// Timestamp when the game started saving its map, if it's saving.
+    saving_since: Option<DateTime<Utc>>,
+    /// The save the game is writing, as logged by the game, if it's saving.
+    saving_to: Option<PathBuf>,
+    /// Whether the game has been saving its map for longer than the configured timeout.
+    saving_stuck: bool,
+    /// The autosave interval in minutes the server's autosave policy has set, if it has adjusted the interval since the
+    /// server was started.
+    autosave_interval: Option<u64>,
+    /// Whether writing the server to the program store failed, leaving the stored server out of date.
+    store_dirty: bool,
+    /// How many times the server has been started since the program started.
+    starts: u64,
+    /// How many times the server has crashed since the program started.
+    crashes: u64,
+    /// The game's latest reported performance, if it has reported it since the server was started.
+    performance: Option<Performance>,
+    /// How many events each user-defined event parser has produced since the program started, keyed by the parsers'
+    /// names.
+    custom_events: BTreeMap<String, u64>,
+    /// The releases the server's mods have been notified to be updated to since their last update, keyed by the mods'
+    /// names.
+    available_updates: BTreeMap<String, HumanVersion>,
+    /// The errors the game logged while loading its mods when it was last started.
+    last_startup_errors: Vec<StartupError>,
+}
+
+/// The game's tick and updates per second as reported in its log.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Performance {
+    /// The game's current tick.
+    pub tick: u64,
+    /// The game's updates per second.
+    pub ups: f64,
+}
+
+/// An error the game logged while loading its mods when starting, along with the suggested ways to resolve it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupError {
+    /// The name of the mod that failed to load, if the error identifies it.
+    mod_name: Option<String>,
+    /// Why loading the mods failed.
+    reason: String,
+    /// Timestamp when the error was logged.
+    logged_at: DateTime<Utc>,
+    /// The suggested ways to resolve the error, the most fitting first.
+    remediations: Vec<Remediation>,
+}
+
+/// A suggested way to resolve a mod's startup error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Remediation {
+    /// The mod is disabled.
+    DisableMod = 0,
+    /// The mod is updated to a release that fits the game and the other mods.
+    UpdateMod,
+    /// The server's mods are reverted to the last set the server ran with.
+    RevertMods,
+}
+
+/// A graceful action waiting for its grace period to end before it's carried out on a server.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PendingAction {
+    /// The action.
+    action: GracefulAction,
+    /// Timestamp when the action is carried out.
+    eta: DateTime<Utc>,
+}
+
+/// Represents an action that's carried out on a running server after giving its players a grace period.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GracefulAction {
+    /// The server is shut down.
+    Stop = 0,
+    /// The server is shut down and started again.
+    Restart,
 }
 
 /// Represents a server's execution status.
@@ -59,6 +156,25 @@ impl Default for ServerStatus {
             game_status: ExecutionStatus::Shutdown,
             in_game_status: InGameStatus::Initialising,
             started_at: Utc::now(),
+            unparsed_lines: 0,
+            dropped_lines: 0,
+            settings_conflict: false,
+            autostart_attempts: 0,
+            autostart_gave_up: false,
+            pending_action: None,
+            current_save: None,
+            game_version: None,
+            saving_since: None,
+            saving_to: None,
+            saving_stuck: false,
+            autosave_interval: None,
+            store_dirty: false,
+            starts: 0,
+            crashes: 0,
+            performance: None,
+            custom_events: BTreeMap::new(),
+            available_updates: BTreeMap::new(),
+            last_startup_errors: Vec::new(),
         }
     }
 }
@@ -79,8 +195,18 @@ impl ServerStatus {
         self.in_game_status
     }
 
-    /// Sets the server executable's status.
+    /// Sets the server's in-game status. Entering the map saving status records when the saving started, and leaving
+    /// it clears the record along with the stuck saving flag.
     pub fn set_in_game_status(&mut self, status: InGameStatus) {
+        if status == InGameStatus::InGameSavingMap {
+            if self.saving_since.is_none() {
+                self.saving_since = Some(Utc::now());
+            }
+        } else {
+            self.saving_since = None;
+            self.saving_stuck = false;
+        }
+
         self.in_game_status = status
     }
 
@@ -89,9 +215,304 @@ impl ServerStatus {
         Utc::now() - self.started_at
     }
 
-    /// Sets the server's started timestamp to the current time.
+    /// Sets the server's started timestamp to the current time, counts the start, resets the unparsed and dropped line
+    /// counts and clears the previous run's save, game version, saving state, autosave interval, performance and
+    /// startup errors.
     pub fn reset_started_at(&mut self) {
-        self.started_at = Utc::now()
+        self.started_at = Utc::now();
+        self.starts += 1;
+        self.performance = None;
+        self.unparsed_lines = 0;
+        self.dropped_lines = 0;
+        self.current_save = None;
+        self.game_version = None;
+        self.saving_since = None;
+        self.saving_to = None;
+        self.saving_stuck = false;
+        self.autosave_interval = None;
+        self.last_startup_errors.clear();
+    }
+
+    /// Returns how many output lines since the server was started weren't recognised as game events.
+    pub fn unparsed_lines(&self) -> u64 {
+        self.unparsed_lines
+    }
+
+    /// Increments the unparsed line count.
+    pub fn add_unparsed_line(&mut self) {
+        self.unparsed_lines += 1;
+    }
+
+    /// Returns how many output lines since the server was started were dropped because the output channel was full.
+    pub fn dropped_lines(&self) -> u64 {
+        self.dropped_lines
+    }
+
+    /// Increases the dropped line count by a given amount.
+    pub fn add_dropped_lines(&mut self, dropped: u64) {
+        self.dropped_lines += dropped;
+    }
+
+    /// Returns whether the server's settings file conflicts with its stored settings.
+    pub fn settings_conflict(&self) -> bool {
+        self.settings_conflict
+    }
+
+    /// Sets whether the server's settings file conflicts with its stored settings.
+    pub fn set_settings_conflict(&mut self, conflict: bool) {
+        self.settings_conflict = conflict;
+    }
+
+    /// Returns how many times the server has been tried to be started automatically since the program started.
+    pub fn autostart_attempts(&self) -> u32 {
+        self.autostart_attempts
+    }
+
+    /// Increments the automatic start attempt count.
+    pub fn add_autostart_attempt(&mut self) {
+        self.autostart_attempts += 1;
+    }
+
+    /// Returns whether automatically starting the server was given up after too many failed attempts.
+    pub fn autostart_gave_up(&self) -> bool {
+        self.autostart_gave_up
+    }
+
+    /// Marks automatically starting the server as given up.
+    pub fn set_autostart_gave_up(&mut self) {
+        self.autostart_gave_up = true;
+    }
+
+    /// Returns the graceful action waiting to be carried out on the server, if any.
+    pub fn pending_action(&self) -> Option<PendingAction> {
+        self.pending_action
+    }
+
+    /// Sets or clears the graceful action waiting to be carried out on the server.
+    pub fn set_pending_action(&mut self, action: Option<PendingAction>) {
+        self.pending_action = action;
+    }
+
+    /// Returns the save the running game loaded, if it has logged one.
+    pub fn current_save(&self) -> Option<&Path> {
+        self.current_save.as_deref()
+    }
+
+    /// Sets the save the running game loaded.
+    pub fn set_current_save(&mut self, save: PathBuf) {
+        self.current_save = Some(save);
+    }
+
+    /// Sets the save the game is writing.
+    pub fn set_saving_to(&mut self, save: PathBuf) {
+        self.saving_to = Some(save);
+    }
+
+    /// Returns and clears the save the game was writing, if it logged one.
+    pub fn take_saving_to(&mut self) -> Option<PathBuf> {
+        self.saving_to.take()
+    }
+
+    /// Returns the running game's version, if it has logged it.
+    pub fn game_version(&self) -> Option<HumanVersion> {
+        self.game_version
+    }
+
+    /// Sets the running game's version.
+    pub fn set_game_version(&mut self, version: HumanVersion) {
+        self.game_version = Some(version);
+    }
+
+    /// Returns the timestamp when the game started saving its map, if it's saving.
+    pub fn saving_since(&self) -> Option<DateTime<Utc>> {
+        self.saving_since
+    }
+
+    /// Returns whether the game has been saving its map for longer than the configured timeout.
+    pub fn saving_stuck(&self) -> bool {
+        self.saving_stuck
+    }
+
+    /// Flags the game as stuck saving its map. The flag is cleared when the game leaves the map saving status.
+    pub fn set_saving_stuck(&mut self) {
+        self.saving_stuck = true;
+    }
+
+    /// Returns the autosave interval in minutes the server's autosave policy has set, if it has adjusted the interval
+    /// since the server was started.
+    pub fn autosave_interval(&self) -> Option<u64> {
+        self.autosave_interval
+    }
+
+    /// Sets the autosave interval in minutes the server's autosave policy has set.
+    pub fn set_autosave_interval(&mut self, interval: u64) {
+        self.autosave_interval = Some(interval);
+    }
+
+    /// Returns whether writing the server to the program store failed, leaving the stored server out of date.
+    pub fn store_dirty(&self) -> bool {
+        self.store_dirty
+    }
+
+    /// Sets whether the stored server is out of date.
+    pub fn set_store_dirty(&mut self, dirty: bool) {
+        self.store_dirty = dirty;
+    }
+
+    /// Returns how many times the server has been started since the program started.
+    pub fn starts(&self) -> u64 {
+        self.starts
+    }
+
+    /// Returns how many times the server has crashed since the program started.
+    pub fn crashes(&self) -> u64 {
+        self.crashes
+    }
+
+    /// Increments the crash count.
+    pub fn add_crash(&mut self) {
+        self.crashes += 1;
+    }
+
+    /// Returns the game's latest reported performance, if it has reported it since the server was started.
+    pub fn performance(&self) -> Option<Performance> {
+        self.performance
+    }
+
+    /// Sets the game's latest reported performance.
+    pub fn set_performance(&mut self, performance: Performance) {
+        self.performance = Some(performance);
+    }
+
+    /// Returns how many events each user-defined event parser has produced since the program started, keyed by the
+    /// parsers' names.
+    pub fn custom_events(&self) -> &BTreeMap<String, u64> {
+        &self.custom_events
+    }
+
+    /// Counts an event produced by a user-defined event parser with a given name.
+    pub fn add_custom_event(&mut self, name: &str) {
+        *self.custom_events.entry(name.to_owned()).or_default() += 1;
+    }
+
+    /// Returns the releases the server's mods have been notified to be updated to since their last update, keyed by the
+    /// mods' names.
+    pub fn available_updates(&self) -> &BTreeMap<String, HumanVersion> {
+        &self.available_updates
+    }
+
+    /// Records that a mod with a given name has a given newer release available. A newer release replaces an older
+    /// one.
+    pub fn add_available_update(&mut self, name: &str, version: HumanVersion) {
+        let available = self.available_updates.entry(name.to_owned()).or_insert(version);
+        if *available < version {
+            *available = version;
+        }
+    }
+
+    /// Clears the available updates after the server's mods have been updated.
+    pub fn clear_available_updates(&mut self) {
+        self.available_updates.clear();
+    }
+
+    /// Returns the errors the game logged while loading its mods when it was last started.
+    pub fn last_startup_errors(&self) -> &[StartupError] {
+        &self.last_startup_errors
+    }
+
+    /// Records an error the game logged while loading its mods. The errors are cleared when the server is started
+    /// again.
+    pub fn add_startup_error(&mut self, error: StartupError) {
+        self.last_startup_errors.push(error);
+    }
+}
+
+impl StartupError {
+    /// Returns a new `StartupError` for a given mod, if known, that failed to load for a given reason at a given time.
+    ///
+    /// Updating the mod is suggested first if a newer release of it is known to be available or if the reason points
+    /// to an incompatible version or a missing dependency, followed by disabling the mod. Reverting the server's mods
+    /// is suggested last if the server has a last known good set of mods to revert to. Only reverting can be suggested
+    /// for errors that don't identify a mod.
+    pub fn new(
+        mod_name: Option<String>,
+        reason: String,
+        logged_at: DateTime<Utc>,
+        update_available: bool,
+        can_revert: bool,
+    ) -> Self {
+        let mut remediations = Vec::new();
+        if mod_name.is_some() {
+            let lowercase = reason.to_lowercase();
+            let incompatible = ["version", "dependency", "compatible", "requires"]
+                .iter()
+                .any(|hint| lowercase.contains(hint));
+            if update_available || incompatible {
+                remediations.push(Remediation::UpdateMod);
+            }
+            remediations.push(Remediation::DisableMod);
+        }
+        if can_revert {
+            remediations.push(Remediation::RevertMods);
+        }
+
+        Self {
+            mod_name,
+            reason,
+            logged_at,
+            remediations,
+        }
+    }
+
+    /// Returns the name of the mod that failed to load, if the error identifies it.
+    pub fn mod_name(&self) -> Option<&str> {
+        self.mod_name.as_deref()
+    }
+
+    /// Returns the suggested ways to resolve the error, the most fitting first.
+    pub fn remediations(&self) -> &[Remediation] {
+        &self.remediations
+    }
+}
+
+impl From<StartupError> for rpc::server_status::StartupError {
+    fn from(error: StartupError) -> Self {
+        Self {
+            mod_name: error.mod_name.unwrap_or_default(),
+            reason: error.reason,
+            logged_at: error.logged_at.timestamp(),
+            remediations: error
+                .remediations
+                .into_iter()
+                .map(|remediation| remediation as i32)
+                .collect(),
+        }
+    }
+}
+
+impl PendingAction {
+    /// Returns a new `PendingAction` for a given action carried out at a given time.
+    pub fn new(action: GracefulAction, eta: DateTime<Utc>) -> Self {
+        Self { action, eta }
+    }
+
+    /// Returns the action.
+    pub fn action(&self) -> GracefulAction {
+        self.action
+    }
+
+    /// Returns the timestamp when the action is carried out.
+    pub fn eta(&self) -> DateTime<Utc> {
+        self.eta
+    }
+}
+
+impl From<PendingAction> for rpc::server_status::PendingAction {
+    fn from(pending: PendingAction) -> Self {
+        Self {
+            action: pending.action as i32,
+            eta: pending.eta.timestamp(),
+        }
     }
 }
 
@@ -101,6 +522,89 @@ impl From<ServerStatus> for rpc::ServerStatus {
             uptime: status.get_uptime().num_seconds(),
             status: status.game_status as i32,
             in_game_status: status.in_game_status as i32,
+            unparsed_lines: status.unparsed_lines,
+            dropped_lines: status.dropped_lines,
+            settings_conflict: status.settings_conflict,
+            autostart_attempts: status.autostart_attempts,
+            autostart_gave_up: status.autostart_gave_up,
+            pending_action: status.pending_action.map(Into::into),
+            current_save: status
+                .current_save
+                .map(|save| save.display().to_string())
+                .unwrap_or_default(),
+            game_version: status.game_version.map(Into::into),
+            saving_since: status.saving_since.map_or(0, |since| since.timestamp()),
+            saving_stuck: status.saving_stuck,
+            store_dirty: status.store_dirty,
+            autosave_interval: status.autosave_interval.unwrap_or_default(),
+            available_updates: status
+                .available_updates
+                .into_iter()
+                .map(|(mod_name, version)| rpc::server_status::AvailableUpdate {
+                    mod_name,
+                    version: Some(version.into()),
+                })
+                .collect(),
+            last_startup_errors: status.last_startup_errors.into_iter().map(Into::into).collect(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saving_tracking() {
+        let mut status = ServerStatus::default();
+        status.set_in_game_status(InGameStatus::InGame);
+        assert_eq!(status.saving_since(), None);
+
+        status.set_in_game_status(InGameStatus::InGameSavingMap);
+        let since = status.saving_since().expect("saving not tracked");
+        status.set_saving_stuck();
+
+        // repeating the saving status doesn't restart the tracking
+        status.set_in_game_status(InGameStatus::InGameSavingMap);
+        assert_eq!(status.saving_since(), Some(since));
+        assert!(status.saving_stuck());
+
+        status.set_in_game_status(InGameStatus::InGame);
+        assert_eq!(status.saving_since(), None);
+        assert!(!status.saving_stuck());
+    }
+
+    #[test]
+    fn startup_errors() {
+        let now = Utc::now();
+        let incompatible = StartupError::new(
+            Some(String::from("Krastorio2")),
+            String::from("Mod Krastorio2 (1.1.0) requires base >= 1.1.0"),
+            now,
+            false,
+            true,
+        );
+        assert_eq!(
+            incompatible.remediations(),
+            &[Remediation::UpdateMod, Remediation::DisableMod, Remediation::RevertMods]
+        );
+
+        let script_error = StartupError::new(
+            Some(String::from("bobplates")),
+            String::from("__bobplates__/data-updates.lua:12: attempt to index field 'item' (a nil value)"),
+            now,
+            false,
+            false,
+        );
+        assert_eq!(script_error.remediations(), &[Remediation::DisableMod]);
+
+        let unidentified = StartupError::new(None, String::from("out of memory"), now, false, true);
+        assert_eq!(unidentified.remediations(), &[Remediation::RevertMods]);
+
+        let mut status = ServerStatus::default();
+        status.add_startup_error(script_error.clone());
+        assert_eq!(status.last_startup_errors(), &[script_error]);
+        status.reset_started_at();
+        assert!(status.last_startup_errors().is_empty());
+    }
+}
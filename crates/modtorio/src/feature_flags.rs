@@ -0,0 +1,120 @@
+//! Provides the [`FeatureFlags`](FeatureFlags) object, used to toggle experimental features of the Modtorio instance
+//! per deployment. The flags are kept in the program store's options.
+
+use crate::store::{option, Store};
+use std::collections::HashMap;
+use strum_macros::{Display, EnumString};
+
+/// An experimental feature that can be toggled.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, EnumString, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum FeatureFlag {
+    /// The REST gateway in front of the RPC services.
+    RestGateway,
+    /// Automatic mod and game updates.
+    AutoUpdates,
+    /// Running servers in containers.
+    ContainerBackend,
+}
+
+/// The state of every feature flag. Features are disabled unless enabled in the program store.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FeatureFlags {
+    /// The enabled state of every flag set in the program store.
+    flags: HashMap<FeatureFlag, bool>,
+}
+
+impl FeatureFlag {
+    /// Every feature flag.
+    pub const ALL: [FeatureFlag; 3] = [
+        FeatureFlag::RestGateway,
+        FeatureFlag::AutoUpdates,
+        FeatureFlag::ContainerBackend,
+    ];
+
+    /// Returns the program store option field this flag is kept in.
+    fn field(self) -> option::Field {
+        match self {
+            FeatureFlag::RestGateway => option::Field::FeatureRestGateway,
+            FeatureFlag::AutoUpdates => option::Field::FeatureAutoUpdates,
+            FeatureFlag::ContainerBackend => option::Field::FeatureContainerBackend,
+        }
+    }
+}
+
+impl FeatureFlags {
+    /// Loads every feature flag from the program store.
+    pub async fn load(store: &Store) -> anyhow::Result<Self> {
+        let mut flags = HashMap::new();
+
+        for flag in &FeatureFlag::ALL {
+            if let Some(value) = store
+                .get_option(flag.field())
+                .await?
+                .and_then(option::Value::take_value)
+            {
+                flags.insert(*flag, value.parse()?);
+            }
+        }
+
+        Ok(Self { flags })
+    }
+
+    /// Enables or disables a given feature and stores its state in the program store.
+    pub async fn set(&mut self, store: &Store, flag: FeatureFlag, enabled: bool) -> anyhow::Result<()> {
+        store.begin_transaction()?;
+        let result = store
+            .set_option(option::Value::new(flag.field(), Some(enabled.to_string())))
+            .await;
+        store.finish_transaction(result)?;
+
+        self.flags.insert(flag, enabled);
+        Ok(())
+    }
+
+    /// Returns whether a given feature is enabled.
+    pub fn is_enabled(&self, flag: FeatureFlag) -> bool {
+        self.flags.get(&flag).copied().unwrap_or(false)
+    }
+}
+
+impl From<&FeatureFlags> for rpc::FeatureFlags {
+    fn from(flags: &FeatureFlags) -> Self {
+        Self {
+            flags: FeatureFlag::ALL
+                .iter()
+                .map(|flag| rpc::feature_flags::Flag {
+                    name: flag.to_string(),
+                    enabled: flags.is_enabled(*flag),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{self, StoreLocation};
+
+    #[tokio::test]
+    async fn set_load() {
+        let store = store::Builder::<String>::from_location(StoreLocation::Memory)
+            .build()
+            .await
+            .expect("failed to build test store");
+
+        let mut flags = FeatureFlags::load(&store).await.expect("failed to load feature flags");
+        assert!(!flags.is_enabled(FeatureFlag::AutoUpdates));
+
+        flags
+            .set(&store, FeatureFlag::AutoUpdates, true)
+            .await
+            .expect("failed to set feature flag");
+
+        let loaded = FeatureFlags::load(&store).await.expect("failed to load feature flags");
+        assert!(loaded.is_enabled(FeatureFlag::AutoUpdates));
+        assert!(!loaded.is_enabled(FeatureFlag::RestGateway));
+        assert_eq!("auto_updates".parse::<FeatureFlag>(), Ok(FeatureFlag::AutoUpdates));
+    }
+}
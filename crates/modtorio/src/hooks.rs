@@ -0,0 +1,254 @@
+//! Provides [`run`](run), which runs the user-provided script configured for a point in a server's lifecycle. Hooks
+//! let custom integrations react to a server starting, stopping or crashing. A hook's script is told about the server
+//! in the `MODTORIO_HOOK`, `MODTORIO_SERVER_ID`, `MODTORIO_SERVER_PATH` and `MODTORIO_SERVER_STATUS` environment
+//! variables, and is killed if it runs longer than the configured timeout. Each run is recorded with the script's
+//! output, including whatever it output before timing out, into the server's event history.
+//!
+//! Hooks can't prevent what they're run for; a script failing or timing out is only logged and recorded.
+
+use crate::{
+    event_history::{self, EventKind},
+    factorio::{ExecutionStatus, GameStoreId},
+    store::Store,
+    Config,
+};
+use chrono::Utc;
+use log::*;
+use std::{path::Path, process::Stdio, time::Duration};
+use strum_macros::{Display, EnumString};
+use tokio::{io::AsyncReadExt, process::Command, time};
+
+/// The most bytes of a hook script's combined output kept in its event.
+const MAX_OUTPUT_LENGTH: usize = 4096;
+
+/// The points in a server's lifecycle a hook script can be run at.
+#[derive(Debug, PartialEq, Copy, Clone, EnumString, Display)]
+pub enum HookPoint {
+    /// Before the server's executable is started.
+    #[strum(serialize = "pre-start")]
+    PreStart,
+    /// Once the server is running and accepting players.
+    #[strum(serialize = "post-start")]
+    PostStart,
+    /// Before the server is told to shut down or is killed.
+    #[strum(serialize = "pre-stop")]
+    PreStop,
+    /// After the server's executable has shut down cleanly.
+    #[strum(serialize = "post-stop")]
+    PostStop,
+    /// After the server's executable has exited with an error.
+    #[strum(serialize = "post-crash")]
+    PostCrash,
+}
+
+/// The outcome of running a hook script.
+#[derive(Debug, PartialEq)]
+pub struct HookOutput {
+    /// Whether the script exited successfully.
+    pub success: bool,
+    /// Whether the script was killed for running longer than the timeout.
+    pub timed_out: bool,
+    /// The script's exit code, or `None` if it was terminated by a signal or timed out.
+    pub exit_code: Option<i32>,
+    /// The script's standard output followed by its standard error, lossily decoded and truncated.
+    pub output: String,
+}
+
+/// Runs the script configured for a given hook point, if any, for a certain game (identified by `store_id`) in a given
+/// root directory and with a given status. The run is logged and recorded into the game's event history in a given
+/// program `Store` unless the store is read-only.
+pub async fn run(
+    config: &Config,
+    store: &Store,
+    store_id: GameStoreId,
+    point: HookPoint,
+    root: &Path,
+    status: ExecutionStatus,
+) {
+    let script = match config.hook_script(point) {
+        Some(script) => script,
+        None => return,
+    };
+
+    debug!("Game ID {} running {} hook {}", store_id, point, script.display());
+    let timeout = config.hook_timeout();
+    let message = match execute(script, store_id, point, root, status, timeout).await {
+        Ok(output) if output.timed_out => {
+            error!(
+                "Game ID {} {} hook timed out after {:?}: {}",
+                store_id, point, timeout, output.output
+            );
+            format!("{} hook timed out after {:?}: {}", point, timeout, output.output)
+        }
+        Ok(output) if output.success => {
+            info!("Game ID {} {} hook finished: {}", store_id, point, output.output);
+            format!("{} hook finished: {}", point, output.output)
+        }
+        Ok(output) => {
+            warn!(
+                "Game ID {} {} hook exited with {:?}: {}",
+                store_id, point, output.exit_code, output.output
+            );
+            format!("{} hook exited with {:?}: {}", point, output.exit_code, output.output)
+        }
+        Err(e) => {
+            error!("Game ID {} {} hook failed: {}", store_id, point, e);
+            format!("{} hook failed: {}", point, e)
+        }
+    };
+
+    if store.is_read_only() {
+        return;
+    }
+
+    if let Err(e) = event_history::record(store, store_id, EventKind::Hook, Utc::now(), None, &message).await {
+        error!("Game ID {} failed to record {} hook event: {}", store_id, point, e);
+    }
+}
+
+/// Executes a given script for a given hook point of a certain game (identified by `store_id`) in a given root
+/// directory and with a given status, and waits for it to exit. The script is killed if it doesn't exit within a
+/// given timeout, if any, in which case the output it has written so far is returned.
+pub async fn execute(
+    script: &Path,
+    store_id: GameStoreId,
+    point: HookPoint,
+    root: &Path,
+    status: ExecutionStatus,
+    timeout: Option<Duration>,
+) -> anyhow::Result<HookOutput> {
+    let mut child = Command::new(script)
+        .current_dir(root)
+        .env("MODTORIO_HOOK", point.to_string())
+        .env("MODTORIO_SERVER_ID", store_id.to_string())
+        .env("MODTORIO_SERVER_PATH", root)
+        .env("MODTORIO_SERVER_STATUS", status_name(status))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut child_stdout = child.stdout.take().expect("hook script stdout not captured");
+    let mut child_stderr = child.stderr.take().expect("hook script stderr not captured");
+
+    // the output is read into buffers outside the waiting future so what was read is kept if it times out
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let wait = async {
+        let (exit_status, stdout_read, stderr_read) = tokio::join!(
+            &mut child,
+            child_stdout.read_to_end(&mut stdout),
+            child_stderr.read_to_end(&mut stderr)
+        );
+        stdout_read?;
+        stderr_read?;
+        exit_status
+    };
+
+    let exit_status = match timeout {
+        Some(timeout) => match time::timeout(timeout, wait).await {
+            Ok(exit_status) => Some(exit_status?),
+            Err(_) => {
+                child.kill()?;
+                None
+            }
+        },
+        None => Some(wait.await?),
+    };
+
+    stdout.extend(stderr);
+    stdout.truncate(MAX_OUTPUT_LENGTH);
+    let output = String::from_utf8_lossy(&stdout).trim().to_owned();
+
+    Ok(match exit_status {
+        Some(exit_status) => HookOutput {
+            success: exit_status.success(),
+            timed_out: false,
+            exit_code: exit_status.code(),
+            output,
+        },
+        None => HookOutput {
+            success: false,
+            timed_out: true,
+            exit_code: None,
+            output,
+        },
+    })
+}
+
+/// Returns the name of a given execution status as given to hook scripts.
+fn status_name(status: ExecutionStatus) -> &'static str {
+    match status {
+        ExecutionStatus::Shutdown => "shutdown",
+        ExecutionStatus::Starting => "starting",
+        ExecutionStatus::Running => "running",
+        ExecutionStatus::ShuttingDown => "shutting-down",
+        ExecutionStatus::Crashed => "crashed",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+    /// Writes a given shell script into a given directory as an executable file and returns its path.
+    fn write_script(dir: &Path, contents: &str) -> PathBuf {
+        let path = dir.join("hook.sh");
+        fs::write(&path, format!("#!/bin/sh\n{}\n", contents)).expect("failed to write script");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).expect("failed to set script permissions");
+        path
+    }
+
+    #[tokio::test]
+    async fn environment_and_output() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let script = write_script(
+            dir.path(),
+            r#"echo "$MODTORIO_HOOK $MODTORIO_SERVER_ID $MODTORIO_SERVER_STATUS $MODTORIO_SERVER_PATH"; exit 3"#,
+        );
+
+        let output = execute(
+            &script,
+            7,
+            HookPoint::PostCrash,
+            dir.path(),
+            ExecutionStatus::Crashed,
+            Some(Duration::from_secs(10)),
+        )
+        .await
+        .expect("failed to execute hook");
+
+        assert!(!output.success);
+        assert!(!output.timed_out);
+        assert_eq!(output.exit_code, Some(3));
+        assert_eq!(output.output, format!("post-crash 7 crashed {}", dir.path().display()));
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let script = write_script(dir.path(), "echo started; sleep 10");
+
+        let output = execute(
+            &script,
+            1,
+            HookPoint::PreStart,
+            dir.path(),
+            ExecutionStatus::Shutdown,
+            Some(Duration::from_millis(500)),
+        )
+        .await
+        .expect("failed to execute hook");
+
+        assert!(output.timed_out);
+        assert!(!output.success);
+        assert_eq!(output.output, "started");
+    }
+
+    #[test]
+    fn point_names() {
+        assert_eq!(HookPoint::PreStop.to_string(), "pre-stop");
+        assert_eq!("post-start".parse::<HookPoint>().unwrap(), HookPoint::PostStart);
+    }
+}
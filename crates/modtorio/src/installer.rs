@@ -0,0 +1,365 @@
+//! Provides the [`Installer`](Installer) object to download Factorio headless server releases from factorio.com and
+//! install them into a server's root directory, either as a new server or over an existing one to upgrade it.
+
+use crate::{
+    error::InstallerError,
+    mod_portal::USER_AGENT,
+    shutdown,
+    util::{
+        async_status::{self, AsyncProgressChannel, AsyncProgressChannelExt},
+        checksum,
+        ext::ResponseExt,
+        HumanVersion,
+    },
+};
+use log::*;
+use reqwest::Client;
+use serde::Deserialize;
+use std::{
+    fs::{self, File},
+    path::{Component, Path, PathBuf},
+};
+use strum_macros::{Display, EnumString};
+use tempfile::NamedTempFile;
+use tokio::{io::AsyncWriteExt, task};
+use xz2::read::XzDecoder;
+
+/// The Factorio website's root.
+const SITE_ROOT: &str = "https://factorio.com";
+/// The endpoint for the latest releases in each channel.
+const LATEST_RELEASES_ENDPOINT: &str = "/api/latest-releases";
+/// The endpoint for downloading a release. Followed by `/<version>/<build>/<platform>`.
+const DOWNLOAD_ENDPOINT: &str = "/get-download";
+/// The endpoint for the SHA-256 checksums of every release archive.
+const CHECKSUMS_ENDPOINT: &str = "/download/sha256sums/";
+/// The downloaded build.
+const BUILD: &str = "headless";
+/// The downloaded build's platform.
+const PLATFORM: &str = "linux64";
+/// The files in a server's root directory that are kept when installing over an existing server, since they may have
+/// been customised.
+const PRESERVED_FILES: &[&str] = &["config-path.cfg"];
+
+/// A Factorio release channel.
+#[derive(Debug, Copy, Clone, PartialEq, EnumString, Display)]
+pub enum Channel {
+    /// The stable releases.
+    #[strum(serialize = "stable")]
+    Stable,
+    /// The experimental releases.
+    #[strum(serialize = "experimental")]
+    Experimental,
+}
+
+/// A Factorio release to install.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Release {
+    /// The latest release in a channel.
+    Latest(Channel),
+    /// A specific version.
+    Version(HumanVersion),
+}
+
+/// Downloads and installs Factorio headless server releases.
+#[derive(Debug)]
+pub struct Installer {
+    /// The HTTP client.
+    client: Client,
+    /// Whether factorio.com is never contacted, because the program runs in offline mode.
+    offline: bool,
+}
+
+/// The latest releases in each channel, as returned by factorio.com.
+#[derive(Debug, Deserialize)]
+struct LatestReleases {
+    /// The latest stable releases.
+    stable: Builds,
+    /// The latest experimental releases.
+    experimental: Builds,
+}
+
+/// The latest version of each build in a release channel.
+#[derive(Debug, Deserialize)]
+struct Builds {
+    /// The latest headless server version.
+    headless: HumanVersion,
+}
+
+impl Release {
+    /// Returns a release from a given optional RPC version and RPC release channel. The latest release in the channel
+    /// is used if the version isn't given.
+    pub fn from_rpc_format(version: Option<rpc::Version>, channel: i32) -> Self {
+        match version {
+            Some(version) => Release::Version(version.into()),
+            None => match rpc::ReleaseChannel::from_i32(channel) {
+                Some(rpc::ReleaseChannel::Experimental) => Release::Latest(Channel::Experimental),
+                _ => Release::Latest(Channel::Stable),
+            },
+        }
+    }
+}
+
+impl Installer {
+    /// Returns a new `Installer`. If `offline` is set, every request to factorio.com fails with
+    /// [`InstallerError::OfflineMode`](crate::error::InstallerError::OfflineMode).
+    pub fn new(offline: bool) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder().user_agent(USER_AGENT).build()?,
+            offline,
+        })
+    }
+
+    /// Returns the version of a given release.
+    pub async fn resolve(&self, release: Release) -> anyhow::Result<HumanVersion> {
+        match release {
+            Release::Version(version) => Ok(version),
+            Release::Latest(channel) => {
+                let latest: LatestReleases = self.get(LATEST_RELEASES_ENDPOINT).await?.json().await?;
+                let version = match channel {
+                    Channel::Stable => latest.stable.headless,
+                    Channel::Experimental => latest.experimental.headless,
+                };
+
+                debug!("Latest {} headless server release: {}", channel, version);
+                Ok(version)
+            }
+        }
+    }
+
+    /// Downloads a given version's headless server, verifies its checksum and installs it into a given root directory.
+    /// The root directory is created if it doesn't exist. Installing over an existing server replaces its game files
+    /// but keeps its saves, mods and settings. Reports progress to a given progress channel.
+    pub async fn install(
+        &self,
+        version: HumanVersion,
+        root: &Path,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<()> {
+        info!("Installing Factorio {} headless server to {}", version, root.display());
+        prog_tx
+            .send_status(async_status::indefinite(&format!(
+                "Downloading Factorio {} headless server...",
+                version
+            )))
+            .await?;
+
+        let mut response = self
+            .get(&format!("{}/{}/{}/{}", DOWNLOAD_ENDPOINT, version, BUILD, PLATFORM))
+            .await?;
+        let file_name = response.url_file_name()?.to_owned();
+        let archive = NamedTempFile::new()?;
+        let mut archive_file = tokio::fs::File::from_std(archive.reopen()?);
+        let written = response.to_writer(&mut archive_file).await?;
+        // the file finishes writing in the background, so make sure it's done before reading it back
+        archive_file.flush().await?;
+        debug!("Downloaded {} ({} bytes)", file_name, written);
+
+        let checksums = self.get(CHECKSUMS_ENDPOINT).await?.text().await?;
+        let expected = find_checksum(&checksums, &file_name)
+            .ok_or_else(|| InstallerError::NoChecksum(file_name.clone()))?
+            .to_owned();
+        let actual = checksum::file_with_progress(
+            checksum::Algorithm::Sha256,
+            archive.path(),
+            "Verifying checksum...",
+            prog_tx.clone(),
+        )
+        .await?;
+        if actual != expected {
+            return Err(InstallerError::ChecksumMismatch {
+                file_name,
+                expected,
+                actual,
+            }
+            .into());
+        }
+
+        // stop before unpacking, so a shutdown doesn't leave a partially unpacked installation behind
+        shutdown::stop_point(format!("downloaded and verified {}", file_name))?;
+        prog_tx.send_status(async_status::indefinite("Unpacking...")).await?;
+        let root = root.to_path_buf();
+        let unpacked = task::spawn_blocking(move || unpack(archive.path(), &root)).await??;
+        info!("Installed Factorio {} headless server ({} files)", version, unpacked);
+
+        Ok(())
+    }
+
+    /// GETs a given endpoint on the Factorio website and returns the response, or an error if the response's status
+    /// isn't successful.
+    async fn get(&self, endpoint: &str) -> anyhow::Result<reqwest::Response> {
+        if self.offline {
+            return Err(InstallerError::OfflineMode.into());
+        }
+
+        let response = self.client.get(&format!("{}{}", SITE_ROOT, endpoint)).send().await?;
+        Ok(response.error_for_status()?)
+    }
+}
+
+/// Returns the checksum of a given file in a given list of checksums, with each line in the form of
+/// `<checksum>  <file name>`.
+fn find_checksum<'a>(checksums: &'a str, file_name: &str) -> Option<&'a str> {
+    checksums.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some(checksum), Some(name)) if name == file_name => Some(checksum),
+            _ => None,
+        }
+    })
+}
+
+/// Unpacks a given `tar.xz` release archive into a given root directory, and returns how many entries were unpacked.
+///
+/// The archive's contents are under a single top-level directory, which is stripped. The archive is first unpacked
+/// into a staging directory inside the root, after which each unpacked top-level entry replaces the root's existing
+/// one, except for the preserved files.
+fn unpack(archive: &Path, root: &Path) -> anyhow::Result<usize> {
+    fs::create_dir_all(root)?;
+    let staging = tempfile::tempdir_in(root)?;
+    let mut archive = tar::Archive::new(XzDecoder::new(File::open(archive)?));
+    let mut unpacked = 0;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let relative = match strip_archive_root(&entry.path()?)? {
+            Some(relative) => relative,
+            None => continue,
+        };
+
+        let destination = staging.path().join(relative);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&destination)?;
+        unpacked += 1;
+    }
+
+    for entry in fs::read_dir(staging.path())? {
+        let entry = entry?;
+        let destination = root.join(entry.file_name());
+
+        if let Ok(metadata) = fs::symlink_metadata(&destination) {
+            if PRESERVED_FILES.iter().any(|file| entry.file_name() == *file) {
+                debug!("Keeping existing {}", destination.display());
+                continue;
+            }
+
+            debug!("Replacing {}", destination.display());
+            if metadata.is_dir() {
+                fs::remove_dir_all(&destination)?;
+            } else {
+                fs::remove_file(&destination)?;
+            }
+        }
+
+        fs::rename(entry.path(), &destination)?;
+    }
+
+    Ok(unpacked)
+}
+
+/// Returns a given path in a release archive without its top-level directory, `None` if the path is the top-level
+/// directory itself, or an error if the path isn't a plain relative path.
+fn strip_archive_root(path: &Path) -> Result<Option<PathBuf>, InstallerError> {
+    let mut components = path.components();
+    match components.next() {
+        Some(Component::Normal(_)) => {}
+        _ => return Err(InstallerError::UnexpectedEntry(path.to_path_buf())),
+    }
+
+    let mut relative = PathBuf::new();
+    for component in components {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            _ => return Err(InstallerError::UnexpectedEntry(path.to_path_buf())),
+        }
+    }
+
+    if relative.as_os_str().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(relative))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use xz2::write::XzEncoder;
+
+    /// Writes a release archive with the given files under a `factorio` top-level directory into a given path.
+    fn write_archive(path: &Path, files: &[(&str, &str)]) {
+        let mut builder = tar::Builder::new(XzEncoder::new(File::create(path).expect("failed to create archive"), 6));
+
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("factorio/{}", name), contents.as_bytes())
+                .expect("failed to append to archive");
+        }
+
+        builder
+            .into_inner()
+            .expect("failed to finish archive")
+            .finish()
+            .expect("failed to finish compression")
+            .flush()
+            .expect("failed to flush archive");
+    }
+
+    #[test]
+    fn checksums() {
+        let checksums = "0123abcd  factorio_headless_x64_1.0.0.tar.xz\n4567ef01  factorio_headless_x64_1.1.0.tar.xz\n";
+        assert_eq!(
+            find_checksum(checksums, "factorio_headless_x64_1.1.0.tar.xz"),
+            Some("4567ef01")
+        );
+        assert_eq!(find_checksum(checksums, "factorio_headless_x64_0.18.0.tar.xz"), None);
+    }
+
+    #[test]
+    fn unpack_over_existing_server() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let root = dir.path().join("server");
+        fs::create_dir_all(root.join("bin/x64")).unwrap();
+        fs::create_dir_all(root.join("saves")).unwrap();
+        fs::write(root.join("bin/x64/factorio"), "old").unwrap();
+        fs::write(root.join("bin/x64/removed"), "old").unwrap();
+        fs::write(root.join("saves/world.zip"), "world").unwrap();
+        fs::write(root.join("config-path.cfg"), "customised").unwrap();
+
+        let archive = dir.path().join("factorio.tar.xz");
+        write_archive(
+            &archive,
+            &[
+                ("bin/x64/factorio", "new"),
+                ("data/base/info.json", "{}"),
+                ("config-path.cfg", "default"),
+            ],
+        );
+
+        assert_eq!(unpack(&archive, &root).expect("failed to unpack"), 3);
+        assert_eq!(fs::read_to_string(root.join("bin/x64/factorio")).unwrap(), "new");
+        assert!(!root.join("bin/x64/removed").exists());
+        assert!(root.join("data/base/info.json").is_file());
+        assert_eq!(fs::read_to_string(root.join("saves/world.zip")).unwrap(), "world");
+        assert_eq!(fs::read_to_string(root.join("config-path.cfg")).unwrap(), "customised");
+        // the staging directory is removed
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn unexpected_entries_rejected() {
+        assert_eq!(strip_archive_root(Path::new("factorio")).unwrap(), None);
+        assert_eq!(
+            strip_archive_root(Path::new("factorio/bin/x64/factorio")).unwrap(),
+            Some(PathBuf::from("bin/x64/factorio"))
+        );
+        assert!(strip_archive_root(Path::new("factorio/../../etc/passwd")).is_err());
+        assert!(strip_archive_root(Path::new("/etc/passwd")).is_err());
+    }
+}
@@ -10,49 +10,119 @@
 #![warn(clippy::missing_docs_in_private_items)]
 // #![warn(clippy::pedantic)]
 
+pub mod auth;
+pub mod backup;
+pub mod compatibility;
 pub mod config;
+pub mod confirmation;
+pub mod doctor;
 pub mod error;
+pub mod event_history;
 pub mod factorio;
+pub mod feature_flags;
+pub mod hooks;
+pub mod installer;
 pub mod log;
+pub mod metrics;
+pub mod mod_cache;
 pub mod mod_common;
 pub mod mod_portal;
+pub mod mod_preset;
+pub mod modpack;
+pub mod motd;
 pub mod opts;
+pub mod player_history;
+pub mod replay;
+pub mod scheduler;
+pub mod shutdown;
+pub mod startup;
 pub mod store;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 mod unix;
 pub mod util;
+pub mod version_pool;
+pub mod webhook;
 
 use ::log::*;
+use anyhow::Context;
+use auth::Scope;
 use chrono::{DateTime, Utc};
 use common::net::NetAddress;
 use config::Config;
-use error::{ModPortalError, RpcError};
-use factorio::{Factorio, GameStoreId, ServerStatus};
+use confirmation::{Confirmations, Operation};
+use error::{CredentialError, InstallerError, ModError, ModPortalError, RpcError, ServerError, StoreError};
+use event_history::{EventKind, ServerEventPage};
+use factorio::{
+    autosave_pruning::AutosavePruning,
+    chat,
+    executable::Tool,
+    mapgen::NewMap,
+    mods::{ModPolicy, UpdatePolicy},
+    raw_files::RawFileKind,
+    settings::{MapExchange, StartBehaviour},
+    ExecutionStatus, Factorio, GameStoreId, GracefulAction, ServerStatus, SettingsSource, StatusHandle,
+};
+use feature_flags::{FeatureFlag, FeatureFlags};
 use futures::{
     future::{join_all, try_join_all},
-    TryStreamExt,
+    Future, TryStreamExt,
 };
+use installer::{Installer, Release};
 use lazy_static::lazy_static;
-use mod_portal::ModPortal;
-use rpc::{instance_status, mod_rpc_server, send_command_request};
-use std::{path::Path, sync::Arc};
+use metrics::{prometheus::ServerMetrics, Instrumented, RpcMetrics};
+use mod_cache::ModCache;
+use mod_common::{Dependency, Info};
+use mod_portal::{CredentialKey, Credentials, ModPortal, SearchQuery};
+use mod_preset::ModPreset;
+use modpack::Modpack;
+use motd::Motd;
+use player_history::PlayerCountHistory;
+use rpc::{instance_status, mod_rpc_server, mod_rpc_status_server, send_command_request};
+use scheduler::{ScheduledRun, Scheduler};
+use startup::StartupSummary;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 use store::Store;
 use tokio::{
     fs,
     net::UnixListener,
-    sync::{mpsc, watch, Mutex},
-    task,
+    sync::{broadcast, mpsc, watch, Mutex, RwLock},
+    task, time,
 };
-use tonic::{transport::Server, Request, Response, Status};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
 use util::{
     async_status,
     async_status::{AsyncProgressChannel, AsyncProgressChannelExt, AsyncProgressResult},
-    HumanVersion,
+    correlation,
+    ext::PathExt,
+    Backoff, HumanVersion, HumanVersionReq, StuckSaveAction,
 };
+use version_pool::VersionPool;
+use webhook::ModRelease;
 
 /// The prefix used with every environment value related to the program configuration.
 pub const APP_PREFIX: &str = "MODTORIO_";
 /// The program's version at build-time.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// How many messages of a streamed RPC response may be waiting to be sent before building the next one is paused.
+const STREAM_CHANNEL_CAPACITY: usize = 2;
+/// How many chat messages may be waiting to be sent to a chat bridge client.
+const CHAT_BRIDGE_CHANNEL_CAPACITY: usize = 16;
+/// How often an autostarted game's status is polled while waiting for it to finish starting.
+const AUTOSTART_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// The length of generated game passwords.
+const GAME_PASSWORD_LENGTH: usize = 20;
+/// How often the managed games are checked for being stuck saving their map.
+const SAVING_WATCHDOG_INTERVAL: Duration = Duration::from_secs(10);
+/// How often the managed games' old autosaves are pruned.
+const AUTOSAVE_PRUNING_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// How often the servers' recorded events older than the configured retention are pruned.
+const EVENT_PRUNING_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 lazy_static! {
     static ref HVER_VERSION: HumanVersion = {
@@ -69,14 +139,95 @@ pub struct Modtorio {
     config: Arc<Config>,
     /// The mod portal.
     portal: Arc<ModPortal>,
+    /// The Factorio headless server installer.
+    installer: Arc<Installer>,
     /// The program store.
     store: Arc<Store>,
-    /// Collection of Factorio instances this Modtorio instance is managing.
-    games: Arc<Mutex<Vec<Factorio>>>,
+    /// Collection of Factorio instances this Modtorio instance is managing, keyed by their store IDs. Each instance
+    /// is behind its own lock so operations on different instances don't block each other.
+    games: Arc<RwLock<HashMap<GameStoreId, Arc<RwLock<Factorio>>>>>,
+    /// Handles to the managed Factorio instances' statuses, keyed by their store IDs. Used to read the statuses
+    /// without locking the instances themselves.
+    status_handles: Arc<RwLock<HashMap<GameStoreId, StatusHandle>>>,
+    /// The stored games that haven't been imported yet, keyed by their store IDs. Only used when stored games are
+    /// loaded lazily, in which case each game is imported and moved into the managed games when it's first accessed.
+    unloaded: Arc<RwLock<HashMap<GameStoreId, store::models::Game>>>,
+    /// Held while importing an unloaded game, so a game accessed by concurrent requests is only imported once.
+    loading: Arc<Mutex<()>>,
+    /// Held while changing the shared version pool or setting a game instance to use a pooled version, so a version
+    /// isn't installed twice at once or removed while a game instance is being set to use it. Taken before any game
+    /// instance's lock.
+    version_pool_lock: Arc<Mutex<()>>,
     /// Timestamp when this Modtorio instance was started.
     started_at: Arc<DateTime<Utc>>,
     /// The instance's status.
     status: Arc<Mutex<instance_status::Status>>,
+    /// The instance's message-of-the-day, if one has been set.
+    motd: Arc<RwLock<Option<Motd>>>,
+    /// The instance's experimental feature flags.
+    feature_flags: Arc<RwLock<FeatureFlags>>,
+    /// Keeps track of the managed games' scheduled tasks.
+    scheduler: Arc<Scheduler>,
+    /// A summary of how the stored games were imported and autostarted. `None` while the instance is starting.
+    startup_summary: Arc<RwLock<Option<StartupSummary>>>,
+    /// The metrics of the RPC requests served since the instance started.
+    rpc_metrics: Arc<RpcMetrics>,
+    /// The confirmation tokens issued for destructive RPC requests.
+    confirmations: Arc<Confirmations>,
+    /// Keeps track of the long-running tasks so they can be shut down gracefully.
+    tasks: shutdown::Coordinator,
+}
+
+/// Spawns a task that serves a given RPC service on a given listen address until a given shutdown signal receiver
+/// receives a value. Unix sockets are bound before the task is spawned, with the file mode and ownership from a given
+/// config. Returns the task's `JoinHandle`.
+macro_rules! spawn_rpc_listener {
+    ($service:expr, $listen:expr, $shutdown_rx:expr, $config:expr) => {{
+        let server = Server::builder().add_service(correlation::Correlated::new($service));
+        match $listen {
+            NetAddress::TCP(addr) => {
+                debug!("Starting RPC server on TCP {}", addr);
+
+                let addr = *addr;
+                let shutdown_signal = wait_for_signal($shutdown_rx.clone());
+                task::spawn(async move {
+                    server
+                        .serve_with_shutdown(addr, shutdown_signal)
+                        .await
+                        .expect("RPC TCP listener failed");
+                    debug!("RPC TCP listener on {} shut down", addr);
+                })
+            }
+            NetAddress::Unix(path) => {
+                debug!("Starting RPC server on Unix {}", path.display());
+
+                util::socket::check_parent_directory(path)?;
+                let mut unix = util::socket::bind(
+                    path,
+                    $config.socket_mode()?,
+                    $config.socket_owner(),
+                    $config.socket_group(),
+                    |path| UnixListener::bind(path),
+                )
+                .with_context(|| format!("Failed to bind to Unix socket {}", path.display()))?;
+
+                let path = path.to_owned();
+                let shutdown_signal = wait_for_signal($shutdown_rx.clone());
+                task::spawn(async move {
+                    server
+                        .serve_with_incoming_shutdown(unix.incoming().map_ok(unix::UnixStream), shutdown_signal)
+                        .await
+                        .expect("RPC Unix listener failed");
+
+                    // since the socket we had was created with bind(), we have to remove it with unlink after
+                    // we're done with it. right now Rust's remove_file corresponds to unlink, but it might not in
+                    // the future
+                    debug!("RPC Unix listener on {} shut down, removing socket", path.display());
+                    fs::remove_file(&path).await.expect("failed to remove socket");
+                })
+            }
+        }
+    }};
 }
 
 impl Modtorio {
@@ -86,18 +237,89 @@ impl Modtorio {
         let config = Arc::new(config);
         let store = Arc::new(store);
 
-        let portal = Arc::new(ModPortal::new(Arc::clone(&config))?);
+        let portal = Arc::new(ModPortal::new(Arc::clone(&config))?.with_store(Arc::clone(&store)));
+        let installer = Arc::new(Installer::new(config.offline())?);
+        let confirmations = Arc::new(Confirmations::new(config.confirmation_ttl()));
         let instance = Modtorio {
             config,
             portal,
+            installer,
             store,
-            games: Arc::new(Mutex::new(Vec::new())),
+            games: Arc::new(RwLock::new(HashMap::new())),
+            status_handles: Arc::new(RwLock::new(HashMap::new())),
+            unloaded: Arc::new(RwLock::new(HashMap::new())),
+            loading: Arc::new(Mutex::new(())),
+            version_pool_lock: Arc::new(Mutex::new(())),
             started_at: Arc::new(Utc::now()),
             status: Arc::new(Mutex::new(instance_status::Status::Starting)),
+            motd: Arc::new(RwLock::new(None)),
+            feature_flags: Arc::new(RwLock::new(FeatureFlags::default())),
+            scheduler: Arc::new(Scheduler::new()),
+            startup_summary: Arc::new(RwLock::new(None)),
+            rpc_metrics: Arc::new(RpcMetrics::new()),
+            confirmations,
+            tasks: shutdown::Coordinator::new(),
         };
 
+        if instance.config.auth_disabled() {
+            warn!("RPC authorization is disabled in the config, so every RPC request is authorized");
+        } else {
+            match auth::has_tokens(&instance.store).await {
+                Ok(true) => {}
+                Ok(false) => warn!(
+                    "No RPC tokens have been created, so every RPC request is denied. Create one with the token \
+                     subcommand"
+                ),
+                Err(e) => error!("Failed to check for RPC tokens: {}", e),
+            }
+        }
+
+        if instance.config.offline() {
+            info!("Running in offline mode, the mod portal and factorio.com won't be contacted");
+        } else {
+            let portal = Arc::clone(&instance.portal);
+            task::spawn(async move {
+                let connectivity = portal.check_connectivity().await;
+                if connectivity.passed() {
+                    info!("Mod portal reachable ({})", connectivity);
+                } else {
+                    warn!("Mod portal unreachable ({})", connectivity);
+                }
+            });
+        }
+
         let i = instance.clone();
         task::spawn(async move {
+            match Motd::load(&i.store).await {
+                Ok(motd) => *i.motd.write().await = motd,
+                Err(e) => error!("Failed to load stored message-of-the-day: {}", e),
+            }
+
+            match FeatureFlags::load(&i.store).await {
+                Ok(flags) => *i.feature_flags.write().await = flags,
+                Err(e) => error!("Failed to load stored feature flags: {}", e),
+            }
+
+            if let Err(e) = i.load_portal_credentials().await {
+                error!("Failed to load stored mod portal credentials: {}", e);
+            }
+
+            match shutdown::take_interrupted(&i.store).await {
+                Ok(interrupted) => {
+                    for task in interrupted {
+                        warn!("The previous shutdown interrupted a task: {}", task);
+                    }
+                }
+                Err(e) => error!("Failed to load the tasks the previous shutdown interrupted: {}", e),
+            }
+
+            if i.config.portal_proxy() {
+                info!("Running as a mod portal proxy, not loading any stored games");
+                *i.status.lock().await = instance_status::Status::Running;
+                *i.startup_summary.write().await = Some(StartupSummary::default());
+                return;
+            }
+
             info!("Loading previous games...");
             let stored_games = match i.store.get_games().await {
                 Ok(games) => games,
@@ -106,39 +328,60 @@ impl Modtorio {
                     return;
                 }
             };
-            let mut games = Vec::new();
+            let mut summary = StartupSummary::default();
             debug!("Got stored games: {:?}", stored_games);
 
-            for stored_game in &stored_games {
-                info!(
-                    "Importing stored game ID {} from path {}...",
-                    stored_game.id, stored_game.path
-                );
+            let import = i.tasks.register(String::from("Importing stored games"));
+            for (index, stored_game) in stored_games.iter().enumerate() {
+                if import.is_cancelled() {
+                    import.interrupt(format!("{} of {} stored games imported", index, stored_games.len()));
+                    return;
+                }
+                import.checkpoint(format!("{} of {} stored games imported", index, stored_games.len()));
 
-                let game = match factorio::Importer::from_store(stored_game)
-                    .import(Arc::clone(&i.config), Arc::clone(&i.portal), Arc::clone(&i.store))
-                    .await
-                {
-                    Ok(game) => game,
-                    Err(e) => {
-                        error!("Failed to import stored game ID {}: {}", stored_game.id, e);
-                        continue;
-                    }
-                };
+                if i.config.lazy_load() && i.can_defer_import(stored_game.id).await {
+                    debug!(
+                        "Deferring importing stored game ID {} until it's accessed",
+                        stored_game.id
+                    );
+                    i.unloaded.write().await.insert(stored_game.id, stored_game.clone());
+                    summary.deferred();
+                    continue;
+                }
 
-                info!(
-                    "Stored game ID {} imported from {}. {} mods",
-                    stored_game.id,
-                    stored_game.path,
-                    game.mods().count()
-                );
-                debug!("Stored game: {:?}", stored_game);
-                games.push(game);
+                if let Err(e) = i.import_stored_game(stored_game).await {
+                    error!("Failed to import stored game ID {}: {}", stored_game.id, e);
+                    summary.failed(stored_game.id, util::path_from_bytes(&stored_game.path), e.to_string());
+                    continue;
+                }
+                summary.imported();
             }
+            drop(import);
 
-            info!("{} previous games loaded.", games.len());
-            i.games.lock().await.extend(games);
             *i.status.lock().await = instance_status::Status::Running;
+
+            i.autostart_games(&mut summary).await;
+
+            info!("Startup finished: {}", summary);
+            for failure in summary.failures() {
+                error!(
+                    "Stored game ID {} from {} failed to import: {}",
+                    failure.server_id(),
+                    failure.path().display(),
+                    failure.reason()
+                );
+            }
+            *i.startup_summary.write().await = Some(summary);
+
+            let watchdog = i.clone();
+            task::spawn(async move { watchdog.run_saving_watchdog().await });
+            let pruner = i.clone();
+            task::spawn(async move { pruner.run_autosave_pruning().await });
+            let sampler = i.clone();
+            task::spawn(async move { sampler.run_player_count_sampling().await });
+            let event_pruner = i.clone();
+            task::spawn(async move { event_pruner.run_server_event_pruning().await });
+            i.run_scheduler().await;
         });
 
         Ok(instance)
@@ -163,11 +406,25 @@ impl Modtorio {
             Ok(())
         };
 
+        self.shutdown_tasks().await;
         self.wait_for_games_to_shutdown().await?;
         result
     }
 
-    /// Runs the RPC server.
+    /// Cancels the long-running tasks and waits up to the configured grace period for them to finish. The interrupted
+    /// tasks are logged, and stored so the next startup reports them again.
+    async fn shutdown_tasks(&self) {
+        let interrupted = self.tasks.shutdown(self.config.shutdown_grace_period()).await;
+        for task in &interrupted {
+            warn!("Shutdown interrupted a task: {}", task);
+        }
+
+        if let Err(e) = shutdown::store_interrupted(&self.store, &interrupted).await {
+            error!("Failed to store the interrupted tasks: {}", e);
+        }
+    }
+
+    /// Runs the RPC server, the Prometheus metrics endpoint and the mod release webhook.
     async fn run_rpc(&self, shutdown_rx: watch::Receiver<()>) -> anyhow::Result<()> {
         let listen_addresses = self.config.listen();
 
@@ -179,55 +436,78 @@ impl Modtorio {
 
         for listen in listen_addresses {
             // TODO: TLS
-            let server = Server::builder().add_service(mod_rpc_server::ModRpcServer::new(self.clone()));
-            rpc_listeners.push(match listen {
-                NetAddress::TCP(addr) => {
-                    debug!("Starting RPC server on TCP {}", addr);
-
-                    let addr = *addr;
-                    let shutdown_signal = wait_for_signal(shutdown_rx.clone());
-                    task::spawn(async move {
-                        server
-                            .serve_with_shutdown(addr, shutdown_signal)
-                            .await
-                            .expect("RPC TCP listener failed");
-                        debug!("RPC TCP listener on {} shut down", addr);
-                    })
-                }
-                NetAddress::Unix(path) => {
-                    debug!("Starting RPC server on Unix {}", path.display());
-
-                    let path = path.to_owned();
-                    let shutdown_signal = wait_for_signal(shutdown_rx.clone());
-                    task::spawn(async move {
-                        let mut unix = UnixListener::bind(&path).expect("failed to bind to unix socket");
-                        server
-                            .serve_with_incoming_shutdown(unix.incoming().map_ok(unix::UnixStream), shutdown_signal)
-                            .await
-                            .expect("RPC Unix listener failed");
-
-                        // since the socket we had was created with bind(), we have to remove it with unlink after
-                        // we're done with it. right now Rust's remove_file corresponds to unlink, but it might not in
-                        // the future
-                        debug!("RPC Unix listener on {} shut down, removing socket", path.display());
-                        fs::remove_file(&path).await.expect("failed to remove socket");
-                    })
-                }
-            });
+            rpc_listeners.push(spawn_rpc_listener!(
+                Instrumented::new(
+                    mod_rpc_server::ModRpcServer::new(self.clone()),
+                    Arc::clone(&self.rpc_metrics)
+                ),
+                listen,
+                shutdown_rx,
+                self.config
+            ));
+        }
+
+        for listen in self.config.metrics_listen() {
+            let instance = self.clone();
+            let collect = move || {
+                let instance = instance.clone();
+                async move { instance.get_prometheus_metrics().await }
+            };
+            rpc_listeners
+                .push(metrics::prometheus::spawn_listener(listen, &self.config, collect, shutdown_rx.clone()).await?);
+        }
+
+        for listen in self.config.webhook_listen() {
+            let instance = self.clone();
+            let handle = move |release| {
+                let instance = instance.clone();
+                async move { instance.receive_mod_release(release).await }
+            };
+            rpc_listeners.push(webhook::spawn_listener(listen, &self.config, handle, shutdown_rx.clone()).await?);
+        }
+
+        for listen in self.config.status_listen() {
+            rpc_listeners.push(spawn_rpc_listener!(
+                Instrumented::new(
+                    mod_rpc_status_server::ModRpcStatusServer::new(self.clone()),
+                    Arc::clone(&self.rpc_metrics)
+                ),
+                listen,
+                shutdown_rx,
+                self.config
+            ));
         }
 
         try_join_all(rpc_listeners).await?;
         Ok(())
     }
 
+    /// Returns every managed game's metrics, ordered by the games' IDs, and the RPC methods' metrics in the Prometheus
+    /// text exposition format.
+    async fn get_prometheus_metrics(&self) -> String {
+        let mut handles: Vec<_> = self.status_handles.read().await.clone().into_iter().collect();
+        handles.sort_by_key(|(server_id, _)| *server_id);
+
+        let mut servers = Vec::new();
+        for (server_id, handle) in handles {
+            servers.push(ServerMetrics::new(
+                server_id,
+                &handle.status().await,
+                handle.online_players().await,
+            ));
+        }
+
+        metrics::prometheus::render(&servers, &self.rpc_metrics.to_rpc_format())
+    }
+
     /// Waits for all the currently managed games to be shut down.
     async fn wait_for_games_to_shutdown(&self) -> anyhow::Result<()> {
-        let games = self.games.lock().await;
+        let games = self.games.read().await.clone();
         let mut waiters = Vec::new();
 
-        for game in games.iter() {
-            debug!("Waiting for game ID {} to shut down...", game.store_id().await?);
-            waiters.push(game.wait_for_shutdown());
+        for (id, game) in games {
+            debug!("Waiting for game ID {} to shut down...", id);
+            waiters.push(async move { game.read().await.wait_for_shutdown().await });
         }
 
         join_all(waiters).await;
@@ -253,205 +533,707 @@ impl Modtorio {
     where
         P: AsRef<Path>,
     {
-        self.games
-            .lock()
-            .await
-            .iter()
-            .any(|game| util::file::are_same(game.root(), path.as_ref()).expect("failed to compare file paths"))
+        let same = |root: &Path| util::file::are_same(root, path.as_ref()).expect("failed to compare file paths");
+        self.status_handles.read().await.values().any(|game| same(game.root()))
+            || self
+                .unloaded
+                .read()
+                .await
+                .values()
+                .any(|game| same(&util::path_from_bytes(&game.path)))
     }
 
-    /// Returns this instance's uptime.
-    async fn get_uptime(&self) -> chrono::Duration {
-        Utc::now() - *self.started_at
+    /// Starts every managed game that has autostart enabled, each in its own task. Records the autostarted and
+    /// skipped games in a given startup summary.
+    async fn autostart_games(&self, summary: &mut StartupSummary) {
+        let games = self.games.read().await.clone();
+        for (server_id, game) in games {
+            if game.read().await.settings().management.autostart {
+                summary.autostarted();
+                let instance = self.clone();
+                task::spawn(correlation::for_server(server_id, async move {
+                    instance.autostart_game(server_id, game).await
+                }));
+            } else {
+                summary.skipped();
+            }
+        }
     }
 
-    /// Returns this instance's managed games in RPC format.
-    async fn get_rpc_servers(&self) -> Vec<instance_status::Server> {
-        let mut rpc_games = Vec::new();
+    /// Runs the managed games' scheduled tasks as they come due, while the automatic updates feature flag is enabled.
+    /// Never returns.
+    async fn run_scheduler(&self) {
+        let time_zone = self.config.time_zone();
+        let mut since = Utc::now();
 
-        for game in self.games.lock().await.iter() {
-            let status = game.status().await.game_status() as i32;
-            let server_id = game.store_id_option().await.unwrap_or(0);
+        loop {
+            time::delay_for(scheduler::TICK_INTERVAL).await;
+            let now = Utc::now();
 
-            rpc_games.push(instance_status::Server {
-                path: format!("{}", game.root().display()),
-                status,
-                server_id,
-            });
-        }
+            let games = self.games.read().await.clone();
+            for (server_id, game) in games {
+                let schedule = {
+                    let game = game.read().await;
+                    if !game.settings().management.mods {
+                        continue;
+                    }
 
-        rpc_games
-    }
+                    match game.settings().schedule.mod_updates() {
+                        Ok(Some(schedule)) => schedule,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            error!("Game ID {} has an invalid mod update schedule: {}", server_id, e);
+                            continue;
+                        }
+                    }
+                };
 
-    /// Returns this instance's status.
-    async fn get_instance_status(&self) -> instance_status::Status {
-        *self.status.lock().await
-    }
+                if scheduler::is_due(&schedule, time_zone, since, now) {
+                    if !self.is_feature_enabled(FeatureFlag::AutoUpdates).await {
+                        debug!(
+                            "Skipping game ID {}'s scheduled mod update, automatic updates are disabled",
+                            server_id
+                        );
+                        continue;
+                    }
+                    if self.config.offline() {
+                        info!("Skipping game ID {}'s scheduled mod update in offline mode", server_id);
+                        continue;
+                    }
+                    if !self.scheduler.begin_mod_update(server_id).await {
+                        info!(
+                            "Skipping game ID {}'s scheduled mod update, one is already pending",
+                            server_id
+                        );
+                        continue;
+                    }
 
-    /// Imports a new Factorio instance from a given path to its root directory.
-    async fn import_game<P>(self, path: P, prog_tx: AsyncProgressChannel)
-    where
-        P: AsRef<Path>,
-    {
-        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
-            send_error_status(&prog_tx, e).await;
-            return;
-        }
+                    let instance = self.clone();
+                    self.tasks.spawn(
+                        format!("Scheduled mod update of game ID {}", server_id),
+                        correlation::for_server(server_id, async move {
+                            instance.run_scheduled_mod_update(server_id, game).await
+                        }),
+                    );
+                }
+            }
 
-        if self.game_exists_by_path(&path).await {
-            error!(
-                "RPC tried to import already existing game from path {}",
-                path.as_ref().display()
-            );
-            send_error_status(&prog_tx, RpcError::GameAlreadyExists(path.as_ref().to_path_buf())).await;
-            return;
+            since = now;
         }
+    }
 
-        let path = path.as_ref().to_path_buf();
-        task::spawn(async move {
-            let importer = match factorio::Importer::from_root(&path) {
-                Ok(i) => i,
-                Err(e) => {
-                    error!("Failed to create new Factorio importer: {}", e);
-                    send_error_status(&prog_tx, e).await;
-                    return;
+    /// Watches the managed games for being stuck saving their map for longer than the configured timeout. A stuck game
+    /// is alerted of once and flagged in its status, and restarted forcibly if so configured, unless a graceful action
+    /// is already pending on it. Never returns unless the watchdog is disabled.
+    async fn run_saving_watchdog(&self) {
+        let timeout = match self.config.saving_timeout().map(chrono::Duration::from_std) {
+            Some(Ok(timeout)) => timeout,
+            Some(Err(e)) => {
+                error!("Invalid saving watchdog timeout: {}", e);
+                return;
+            }
+            None => {
+                debug!("Saving watchdog disabled");
+                return;
+            }
+        };
+
+        loop {
+            time::delay_for(SAVING_WATCHDOG_INTERVAL).await;
+
+            let handles = self.status_handles.read().await.clone();
+            for (server_id, handle) in handles {
+                let status = handle.status().await;
+                let saving_since = match status.saving_since() {
+                    Some(since) if !status.saving_stuck() => since,
+                    _ => continue,
+                };
+
+                let saving_for = Utc::now() - saving_since;
+                if saving_for < timeout {
+                    continue;
                 }
-            };
 
-            let game = match importer
-                .with_status_updates(prog_tx.clone())
-                .import(
-                    Arc::clone(&self.config),
-                    Arc::clone(&self.portal),
-                    Arc::clone(&self.store),
-                )
-                .await
-            {
-                Ok(game) => {
-                    info!("Imported new Factorio server instance from {}", path.display());
-                    if !send_status(&prog_tx, async_status::indefinite("Game imported")).await {
-                        return;
+                error!(
+                    "Game ID {} has been saving its map for {} seconds and is likely stuck. Is its disk full?",
+                    server_id,
+                    saving_for.num_seconds()
+                );
+
+                let game = match self.get_game(server_id).await {
+                    Ok(game) => game,
+                    Err(e) => {
+                        error!("Failed to get stuck game ID {}: {}", server_id, e);
+                        continue;
+                    }
+                };
+                game.read().await.mark_saving_stuck(saving_for).await;
+
+                if self.config.stuck_save_action() == StuckSaveAction::Restart {
+                    if let Some(pending) = status.pending_action() {
+                        warn!(
+                            "Game ID {} has a pending {:?} action, not restarting it",
+                            server_id,
+                            pending.action()
+                        );
+                        continue;
                     }
-                    game
+
+                    task::spawn(force_restart(server_id, game));
                 }
-                Err(e) => {
-                    error!("Failed to import game: {}", e);
-                    send_error_status(&prog_tx, e).await;
-                    return;
+            }
+        }
+    }
+
+    /// Prunes the managed games' old autosaves according to their autosave retention settings periodically. A game
+    /// that is saving its map is skipped until the next round. Never returns.
+    async fn run_autosave_pruning(&self) {
+        loop {
+            time::delay_for(AUTOSAVE_PRUNING_INTERVAL).await;
+
+            let games = self.games.read().await.clone();
+            for (server_id, game) in games {
+                let game = game.read().await;
+                if game.settings().autosave_retention.is_disabled() {
+                    continue;
                 }
-            };
 
-            if let Err(e) = game.update_store(Some(prog_tx.clone())).await {
-                error!("Failed to update game store: {}", e);
-                send_error_status(&prog_tx, e).await;
-                return;
+                match game.prune_autosaves(false).await {
+                    Ok(pruning) => log_autosave_pruning(server_id, &pruning),
+                    Err(e) => warn!("Failed to prune game ID {}'s autosaves: {}", server_id, e),
+                }
             }
-
-            self.games.lock().await.push(game);
-            send_status(&prog_tx, async_status::done()).await;
-        });
+        }
     }
 
-    /// Updates a given game instance's store.
-    async fn update_store(self, server_id: GameStoreId, prog_tx: AsyncProgressChannel) {
-        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
-            send_error_status(&prog_tx, e).await;
-            return;
-        }
+    /// Samples the running games' player counts into the program store periodically, and prunes the samples older than
+    /// the configured retention. Returns right away if sampling is disabled, otherwise never returns.
+    async fn run_player_count_sampling(&self) {
+        let interval = match self.config.player_count_interval() {
+            Some(interval) => interval,
+            None => return,
+        };
 
-        task::spawn(async move {
-            let mut games = self.games.lock().await;
-            match find_game(server_id, &mut games).await {
-                Ok(game) => {
-                    if let Err(e) = game.update_store(Some(prog_tx.clone())).await {
-                        error!("Failed to update game store: {}", e);
-                        send_error_status(&prog_tx, e).await;
-                        return;
-                    }
+        loop {
+            time::delay_for(interval).await;
+            if self.store.is_read_only() {
+                continue;
+            }
 
-                    send_status(&prog_tx, async_status::done()).await
+            let handles = self.status_handles.read().await.clone();
+            for (server_id, handle) in handles {
+                if handle.status().await.game_status() != ExecutionStatus::Running {
+                    continue;
                 }
-                Err(e) => send_error_status(&prog_tx, e).await,
-            };
-        });
+
+                if let Err(e) = player_history::record(&self.store, server_id, handle.online_players().await).await {
+                    warn!("Failed to record game ID {}'s player count: {}", server_id, e);
+                }
+            }
+
+            if let Err(e) = self.prune_player_count_samples().await {
+                warn!("Failed to prune old player count samples: {}", e);
+            }
+        }
     }
 
-    /// Installs a mod to a given game instance.
-    async fn install_mod(
-        self,
-        server_id: GameStoreId,
-        mod_name: String,
-        version: Option<HumanVersion>,
-        prog_tx: AsyncProgressChannel,
-    ) {
-        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
-            send_error_status(&prog_tx, e).await;
-            return;
+    /// Removes the player count samples older than the configured retention, if they aren't kept forever.
+    async fn prune_player_count_samples(&self) -> anyhow::Result<()> {
+        if let Some(retention) = self.config.player_count_retention() {
+            let threshold = Utc::now() - chrono::Duration::from_std(retention)?;
+            let pruned = self.store.remove_player_count_samples_before(threshold).await?;
+            if pruned > 0 {
+                debug!("Pruned {} player count samples older than {}", pruned, threshold);
+            }
         }
 
-        task::spawn(async move {
-            let mut games = self.games.lock().await;
-            match find_game(server_id, &mut games).await {
-                Ok(game) => {
-                    if let Err(e) = game
-                        .mods_mut()
-                        .add_from_portal(&mod_name, version, Some(prog_tx.clone()))
-                        .await
-                    {
-                        if let Some(ModPortalError::ClientError(reqwest::StatusCode::NOT_FOUND)) = e.downcast_ref() {
-                            error!("Failed to install mod '{}': not found ({})", mod_name, e);
-                            send_error_status(&prog_tx, RpcError::NoSuchMod(mod_name)).await;
-                        } else {
-                            error!("Failed to install mod '{}': {}", mod_name, e);
-                            send_error_status(&prog_tx, e).await;
-                        }
-                        return;
-                    }
+        Ok(())
+    }
 
-                    send_status(&prog_tx, async_status::done()).await
+    /// Removes the servers' recorded events older than the configured retention periodically. Returns right away if
+    /// the events are kept forever, otherwise never returns.
+    async fn run_server_event_pruning(&self) {
+        let retention = match self.config.event_retention() {
+            Some(retention) => retention,
+            None => return,
+        };
+
+        loop {
+            if !self.store.is_read_only() {
+                if let Err(e) = self.prune_server_events(retention).await {
+                    warn!("Failed to prune old server events: {}", e);
                 }
-                Err(e) => send_error_status(&prog_tx, e).await,
-            };
-        });
-    }
+            }
 
-    /// Updates the installed mods of a given game instance.
-    async fn update_mods(self, server_id: GameStoreId, prog_tx: AsyncProgressChannel) {
-        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
-            send_error_status(&prog_tx, e).await;
-            return;
+            time::delay_for(EVENT_PRUNING_INTERVAL).await;
         }
+    }
 
-        task::spawn(async move {
-            let mut games = self.games.lock().await;
-            match find_game(server_id, &mut games).await {
-                Ok(game) => {
-                    if let Err(e) = game.mods_mut().update(Some(prog_tx.clone())).await {
-                        error!("Failed to update mods: {}", e);
-                        send_error_status(&prog_tx, e).await;
-                        return;
-                    }
+    /// Removes the servers' recorded events older than a given retention.
+    async fn prune_server_events(&self, retention: Duration) -> anyhow::Result<()> {
+        let threshold = Utc::now() - chrono::Duration::from_std(retention)?;
+        let pruned = self.store.remove_server_events_before(threshold).await?;
+        if pruned > 0 {
+            debug!("Pruned {} server events older than {}", pruned, threshold);
+        }
 
-                    send_status(&prog_tx, async_status::done()).await
-                }
-                Err(e) => send_error_status(&prog_tx, e).await,
-            };
-        });
+        Ok(())
     }
 
-    /// Updates the installed mods of a given game instance.
-    async fn ensure_mod_dependencies(self, server_id: GameStoreId, prog_tx: AsyncProgressChannel) {
+    /// Updates a given game's mods on schedule and records the run's result.
+    async fn run_scheduled_mod_update(&self, server_id: GameStoreId, game: Arc<RwLock<Factorio>>) {
+        info!("Running scheduled mod update for game ID {}", server_id);
+        let result = game.write().await.update_mods(None).await;
+
+        match &result {
+            Ok(()) => info!("Scheduled mod update for game ID {} finished", server_id),
+            Err(e) => error!("Scheduled mod update for game ID {} failed: {}", server_id, e),
+        }
+        self.scheduler
+            .record_mod_update(server_id, ScheduledRun::finished(&result))
+            .await;
+    }
+
+    /// Applies a mod release notification received through the webhook to the managed games in the background.
+    async fn receive_mod_release(self, release: ModRelease) -> anyhow::Result<()> {
+        info!(
+            "Received mod release notification: {} {}",
+            release.name, release.version
+        );
+        task::spawn(async move { self.apply_mod_release(release).await });
+        Ok(())
+    }
+
+    /// Marks the managed games a given mod release would update as having an update available, and updates the mods
+    /// of the games with a mod update schedule right away instead of waiting for the schedule to come due, if automatic
+    /// updates are enabled.
+    async fn apply_mod_release(&self, release: ModRelease) {
+        let games = self.games.read().await.clone();
+        for (server_id, game) in games {
+            let scheduled = {
+                let game = game.read().await;
+                if !game.settings().management.mods {
+                    continue;
+                }
+
+                match game.mods().is_updated_by(&release.name, release.version).await {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(e) => {
+                        warn!(
+                            "Failed to check whether game ID {} has {} {} available: {}",
+                            server_id, release.name, release.version, e
+                        );
+                        continue;
+                    }
+                }
+
+                game.mark_update_available(&release.name, release.version).await;
+                matches!(game.settings().schedule.mod_updates(), Ok(Some(_)))
+            };
+
+            info!(
+                "Game ID {} has {} {} available",
+                server_id, release.name, release.version
+            );
+            if scheduled && !self.config.offline() && self.is_feature_enabled(FeatureFlag::AutoUpdates).await {
+                if !self.scheduler.begin_mod_update(server_id).await {
+                    info!(
+                        "Skipping game ID {}'s mod update for {} {}, one is already pending",
+                        server_id, release.name, release.version
+                    );
+                    continue;
+                }
+
+                let instance = self.clone();
+                self.tasks.spawn(
+                    format!("Scheduled mod update of game ID {}", server_id),
+                    correlation::for_server(server_id, async move {
+                        instance.run_scheduled_mod_update(server_id, game).await
+                    }),
+                );
+            }
+        }
+    }
+
+    /// Starts a given game and waits for it to be running. A failed start is retried after a jittered exponential
+    /// backoff, up to the configured amount of attempts. An attempt fails if the executable can't be started or if it
+    /// exits before the game is running. Stops trying if the game is started or stopped by someone else in between.
+    async fn autostart_game(&self, server_id: GameStoreId, game: Arc<RwLock<Factorio>>) {
+        let attempts = self.config.autostart_attempts();
+        let mut backoff = Backoff::new(
+            self.config.autostart_initial_backoff(),
+            self.config.autostart_max_backoff(),
+        );
+        let handle = game.read().await.status_handle();
+
+        for attempt in 1..=attempts {
+            let status = handle.status().await.game_status();
+            if status != ExecutionStatus::Shutdown && status != ExecutionStatus::Crashed {
+                info!("Game ID {} is {:?}, not autostarting it any further", server_id, status);
+                return;
+            }
+
+            info!("Autostarting game ID {} (attempt {}/{})", server_id, attempt, attempts);
+            let result = {
+                let mut game = game.write().await;
+                game.add_autostart_attempt().await;
+                match game.flush_settings_to_file().await {
+                    Ok(_) => game.run().await,
+                    Err(e) => Err(e),
+                }
+            };
+
+            let failure = match result {
+                Ok(()) => match wait_for_start(&handle).await {
+                    ExecutionStatus::Shutdown | ExecutionStatus::Crashed => {
+                        String::from("the executable exited before the game was running")
+                    }
+                    _ => {
+                        info!("Game ID {} autostarted", server_id);
+                        return;
+                    }
+                },
+                Err(e) => e.to_string(),
+            };
+
+            if attempt == attempts {
+                error!(
+                    "Autostart attempt {}/{} of game ID {} failed: {}. Giving up",
+                    attempt, attempts, server_id, failure
+                );
+            } else {
+                let delay = backoff.next_delay();
+                warn!(
+                    "Autostart attempt {}/{} of game ID {} failed: {}. Retrying in {:?}",
+                    attempt, attempts, server_id, failure, delay
+                );
+                time::delay_for(delay).await;
+            }
+        }
+
+        game.read().await.give_up_autostart().await;
+    }
+
+    /// Loads every game's stored mod portal credentials into the mod portal.
+    async fn load_portal_credentials(&self) -> anyhow::Result<()> {
+        let stored = self.store.get_all_portal_credentials().await?;
+        if stored.is_empty() {
+            return Ok(());
+        }
+
+        let key = self.credential_key()?;
+        for stored_credentials in stored {
+            match Credentials::from_store_format(&stored_credentials, &key) {
+                Ok(credentials) => {
+                    self.portal
+                        .set_server_credentials(stored_credentials.game, Some(credentials))
+                        .await
+                }
+                Err(e) => error!(
+                    "Failed to load game ID {}'s mod portal credentials: {}",
+                    stored_credentials.game, e
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the configured key per-server mod portal credentials are encrypted with.
+    fn credential_key(&self) -> Result<CredentialKey, CredentialError> {
+        self.config
+            .credential_key()
+            .ok_or(CredentialError::NoKey)
+            .and_then(CredentialKey::from_hex)
+    }
+
+    /// Returns this instance's uptime.
+    async fn get_uptime(&self) -> chrono::Duration {
+        Utc::now() - *self.started_at
+    }
+
+    /// Returns a new progress channel throttled according to the program config, and its receiver.
+    fn progress_channel(&self) -> (AsyncProgressChannel, mpsc::Receiver<AsyncProgressResult>) {
+        let (tx, rx) = mpsc::channel(64);
+        (AsyncProgressChannel::new(tx, self.config.progress_interval()), rx)
+    }
+
+    /// Adds a given game to the games managed by this instance. The game must have been added to the program store.
+    async fn add_game(&self, game: Factorio) -> anyhow::Result<()> {
+        let id = game.store_id().await?;
+        let mut status_handles = self.status_handles.write().await;
+        let mut games = self.games.write().await;
+
+        status_handles.insert(id, game.status_handle());
+        games.insert(id, Arc::new(RwLock::new(game)));
+        Ok(())
+    }
+
+    /// Returns a given game based on its store ID, or returns `RpcError::NoSuchGame` if the game isn't found. An
+    /// unloaded game is imported first. The instance-wide games lock is released before returning, so only the returned
+    /// game's own lock is held by the caller.
+    async fn get_game(&self, server_id: GameStoreId) -> anyhow::Result<Arc<RwLock<Factorio>>> {
+        if let Some(game) = self.games.read().await.get(&server_id) {
+            return Ok(Arc::clone(game));
+        }
+
+        self.load_game(server_id).await
+    }
+
+    /// Imports a given unloaded game and adds it to the managed games, or returns `RpcError::NoSuchGame` if there's no
+    /// such unloaded game. A game that fails to be imported is left unloaded, so accessing it again retries the import.
+    async fn load_game(&self, server_id: GameStoreId) -> anyhow::Result<Arc<RwLock<Factorio>>> {
+        let _loading = self.loading.lock().await;
+        // another request may have imported the game while this one waited for its turn
+        if let Some(game) = self.games.read().await.get(&server_id) {
+            return Ok(Arc::clone(game));
+        }
+
+        let stored_game = self
+            .unloaded
+            .read()
+            .await
+            .get(&server_id)
+            .cloned()
+            .ok_or(RpcError::NoSuchGame(server_id))?;
+        info!("Loading game ID {} on its first access", server_id);
+        self.import_stored_game(&stored_game).await?;
+        self.unloaded.write().await.remove(&server_id);
+
+        self.games
+            .read()
+            .await
+            .get(&server_id)
+            .map(Arc::clone)
+            .ok_or_else(|| RpcError::NoSuchGame(server_id).into())
+    }
+
+    /// Imports a given stored game and adds it to the games managed by this instance.
+    async fn import_stored_game(&self, stored_game: &store::models::Game) -> anyhow::Result<()> {
+        let stored_path = util::path_from_bytes(&stored_game.path);
+        info!(
+            "Importing stored game ID {} from path {}...",
+            stored_game.id,
+            stored_path.display()
+        );
+
+        let game = factorio::Importer::from_store(stored_game)
+            .import(
+                Arc::clone(&self.config),
+                Arc::clone(&self.portal),
+                Arc::clone(&self.store),
+            )
+            .await?;
+
+        info!(
+            "Stored game ID {} imported from {}. {} mods",
+            stored_game.id,
+            stored_path.display(),
+            game.mods().count()
+        );
+        debug!("Stored game: {:?}", stored_game);
+
+        self.add_game(game).await
+    }
+
+    /// Returns whether importing a given stored game can be deferred until it's first accessed. Games that autostart or
+    /// have their mods updated on schedule are needed right away, as are games whose settings can't be read.
+    async fn can_defer_import(&self, server_id: GameStoreId) -> bool {
+        match self.store.get_settings(server_id).await {
+            Ok(settings) => {
+                settings.autostart == 0 && (settings.mods_managed == 0 || settings.mod_update_schedule.is_none())
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to get stored game ID {}'s settings, importing it right away: {}",
+                    server_id, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Requires a given destructive operation requested by a given caller to be confirmed with a given confirmation
+    /// token. Without a token, a new token is issued to the caller for the operation with the impact summary a given
+    /// future returns, and returned as `RpcError::ConfirmationRequired`. With one, the token is redeemed for the
+    /// operation by the caller.
+    async fn confirm<F>(&self, operation: Operation, caller: Option<&str>, token: &str, impact: F) -> anyhow::Result<()>
+    where
+        F: Future<Output = anyhow::Result<String>>,
+    {
+        if !token.is_empty() {
+            self.confirmations.redeem(token, &operation, caller).await?;
+            info!("Confirmed {:?}", operation);
+            return Ok(());
+        }
+
+        let confirmation = self
+            .confirmations
+            .issue(operation, caller.map(str::to_owned), impact.await?)
+            .await?;
+        Err(RpcError::ConfirmationRequired(confirmation).into())
+    }
+
+    /// Claims a given idempotency key of a request to a given RPC method. Returns whether the request should be
+    /// executed; `false` means a request with the same key has already been executed. Requests without a key are
+    /// always executed.
+    async fn claim_idempotency_key(&self, key: &str, method: &str) -> anyhow::Result<bool> {
+        if key.is_empty() {
+            return Ok(true);
+        }
+
+        let expire_before = Utc::now() - chrono::Duration::from_std(self.config.idempotency_ttl())?;
+        let claimed = match self
+            .store
+            .claim_idempotency_key(
+                store::models::IdempotencyKey {
+                    key: key.to_owned(),
+                    method: method.to_owned(),
+                    claimed_at: Utc::now(),
+                },
+                expire_before,
+            )
+            .await
+        {
+            // controlling the servers keeps working while the store is read-only, just without the deduplication
+            Err(e) if matches!(e.downcast_ref(), Some(StoreError::ReadOnly)) => {
+                warn!(
+                    "Can't claim idempotency key '{}' for {} request in a read-only store, executing the request",
+                    key, method
+                );
+                true
+            }
+            result => result?,
+        };
+
+        if !claimed {
+            info!(
+                "{} request with idempotency key '{}' already executed, skipping",
+                method, key
+            );
+        }
+
+        Ok(claimed)
+    }
+
+    /// Releases a given idempotency key after its request failed, so a retry of the request is executed.
+    async fn release_idempotency_key(&self, key: &str) {
+        if key.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.store.release_idempotency_key(key.to_owned()).await {
+            error!("Failed to release idempotency key '{}': {}", key, e);
+        }
+    }
+
+    /// Returns this instance's managed games in RPC format.
+    async fn get_rpc_servers(&self) -> Vec<instance_status::Server> {
+        let mut rpc_games = Vec::new();
+
+        // clone the handles so the lock isn't held while reading each game's status
+        let status_handles = self.status_handles.read().await.clone();
+        for (server_id, game) in status_handles {
+            let status = game.status().await;
+
+            rpc_games.push(instance_status::Server {
+                path: format!("{}", game.root().display()),
+                raw_path: game.root().to_bytes(),
+                status: status.game_status() as i32,
+                server_id,
+                settings_conflict: status.settings_conflict(),
+                name: game.name().await.unwrap_or_default(),
+            });
+        }
+
+        for (server_id, game) in self.unloaded.read().await.iter() {
+            rpc_games.push(instance_status::Server {
+                path: format!("{}", util::path_from_bytes(&game.path).display()),
+                raw_path: game.path.clone(),
+                status: rpc::ExecutionStatus::NotLoaded as i32,
+                server_id: *server_id,
+                settings_conflict: false,
+                name: game.name.clone().unwrap_or_default(),
+            });
+        }
+
+        rpc_games
+    }
+
+    /// Returns this instance's status.
+    async fn get_instance_status(&self) -> instance_status::Status {
+        *self.status.lock().await
+    }
+
+    /// Imports a new Factorio instance from a given path to its root directory.
+    async fn import_game<P>(self, path: P, prog_tx: AsyncProgressChannel)
+    where
+        P: AsRef<Path>,
+    {
         if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
             send_error_status(&prog_tx, e).await;
             return;
         }
+        if self.config.portal_proxy() {
+            send_error_status(&prog_tx, RpcError::PortalProxyMode).await;
+            return;
+        }
 
-        task::spawn(async move {
-            let mut games = self.games.lock().await;
-            match find_game(server_id, &mut games).await {
+        if self.game_exists_by_path(&path).await {
+            error!(
+                "RPC tried to import already existing game from path {}",
+                path.as_ref().display()
+            );
+            send_error_status(&prog_tx, RpcError::GameAlreadyExists(path.as_ref().to_path_buf())).await;
+            return;
+        }
+
+        let path = path.as_ref().to_path_buf();
+        let tasks = self.tasks.clone();
+        let description = format!("Importing a game from {}", path.display());
+        tasks.spawn(description, async move {
+            if let Err(e) = self.import_new_game(&path, &prog_tx).await {
+                error!("Failed to import game from {}: {}", path.display(), e);
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Imports a new game instance from a given root directory, stores it and starts managing it.
+    async fn import_new_game(&self, path: &Path, prog_tx: &AsyncProgressChannel) -> anyhow::Result<()> {
+        let game = factorio::Importer::from_root(path)
+            .await?
+            .with_status_updates(prog_tx.clone())
+            .import(
+                Arc::clone(&self.config),
+                Arc::clone(&self.portal),
+                Arc::clone(&self.store),
+            )
+            .await?;
+        info!("Imported new Factorio server instance from {}", path.display());
+        send_status(prog_tx, async_status::indefinite("Game imported")).await;
+
+        game.update_store(Some(prog_tx.clone())).await?;
+        self.add_game(game).await
+    }
+
+    /// Updates a given game instance's store.
+    async fn update_store(self, server_id: GameStoreId, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        correlation::spawn(async move {
+            match self.get_game(server_id).await {
                 Ok(game) => {
-                    if let Err(e) = game.mods_mut().ensure_dependencies(Some(prog_tx.clone())).await {
-                        error!("Failed to ensure mod dependencies: {}", e);
+                    let game = game.read().await;
+                    if let Err(e) = game.update_store(Some(prog_tx.clone())).await {
+                        error!("Failed to update game store: {}", e);
                         send_error_status(&prog_tx, e).await;
                         return;
                     }
@@ -463,229 +1245,3241 @@ impl Modtorio {
         });
     }
 
-    /// Retrieves a given game instance's server settings.
-    async fn get_server_settings(&self, server_id: GameStoreId) -> anyhow::Result<rpc::ServerSettings> {
-        self.assert_instance_status(instance_status::Status::Running).await?;
-
-        let mut games = self.games.lock().await;
-        let game = find_game(server_id, &mut games).await?;
-        let mut rpc_server_settings = rpc::ServerSettings::default();
-        game.settings().to_rpc_format(&mut rpc_server_settings)?;
+    /// Writes a given game instance to the program store again after an earlier write failed.
+    async fn retry_store_update(self, server_id: GameStoreId, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        correlation::spawn(async move {
+            let game = match self.get_game(server_id).await {
+                Ok(game) => game,
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            };
+
+            let game = game.read().await;
+            if !game.status().await.store_dirty() {
+                send_error_status(&prog_tx, RpcError::StoreNotDirty(server_id)).await;
+                return;
+            }
+
+            info!("Retrying game ID {} store update", server_id);
+            if let Err(e) = game.update_store(Some(prog_tx.clone())).await {
+                error!("Failed to update game store: {}", e);
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Re-imports a given game instance from its root directory, picking up any changes made to its mods, settings file
+    /// and executable outside of the program. The game keeps its store ID.
+    async fn refresh_server(self, server_id: GameStoreId, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        let tasks = self.tasks.clone();
+        let description = format!("Refreshing server ID {}", server_id);
+        tasks.spawn(description, async move {
+            let game = match self.get_game(server_id).await {
+                Ok(game) => game,
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            };
+
+            let mut game = game.write().await;
+            match game.status().await.game_status() {
+                ExecutionStatus::Shutdown | ExecutionStatus::Crashed => {}
+                status => {
+                    error!("RPC tried to refresh server ID {} while it is {:?}", server_id, status);
+                    send_error_status(&prog_tx, ServerError::InvalidGameStatus(status)).await;
+                    return;
+                }
+            }
+
+            if let Err(e) = self.reimport_game(server_id, &mut game, &prog_tx).await {
+                error!("Failed to refresh server ID {}: {}", server_id, e);
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+
+            info!("Refreshed server ID {} from {}", server_id, game.root().display());
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Re-imports a given game instance from its root directory in place and replaces its stored information. The game
+    /// must be shut down.
+    async fn reimport_game(
+        &self,
+        server_id: GameStoreId,
+        game: &mut Factorio,
+        prog_tx: &AsyncProgressChannel,
+    ) -> anyhow::Result<()> {
+        let stored_game = store::models::Game {
+            id: server_id,
+            path: game.root().to_bytes(),
+            name: game.name().await,
+        };
+        let refreshed = factorio::Importer::from_store(&stored_game)
+            .with_settings_from_file()
+            .with_status_updates(prog_tx.clone())
+            .import(
+                Arc::clone(&self.config),
+                Arc::clone(&self.portal),
+                Arc::clone(&self.store),
+            )
+            .await?;
+        refreshed.replace_store(Some(prog_tx.clone())).await?;
+
+        self.status_handles
+            .write()
+            .await
+            .insert(server_id, refreshed.status_handle());
+        *game = refreshed;
+        Ok(())
+    }
+
+    /// Stops managing a given game instance. The game is shut down if it's running and everything stored for it is
+    /// removed. Its mods directory is deleted if `delete_files` is set, otherwise its files are left in place. A mods
+    /// directory outside the game's root directory or shared with another managed game is never deleted. The removal
+    /// has to be confirmed with a given confirmation token issued to a given caller.
+    async fn remove_server(
+        &self,
+        server_id: GameStoreId,
+        delete_files: bool,
+        caller: Option<&str>,
+        confirmation_token: &str,
+    ) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        // the write lock keeps other requests off the game until it's no longer managed
+        let game = game.write().await;
+        if delete_files {
+            self.assert_mods_deletable(server_id, &game).await?;
+        }
+        let operation = Operation::RemoveServer {
+            server_id,
+            delete_files,
+        };
+        self.confirm(
+            operation,
+            caller,
+            confirmation_token,
+            removal_impact(server_id, &game, delete_files),
+        )
+        .await?;
+
+        // a game that doesn't exit is left managed instead of blocking the removal and the game's lock indefinitely
+        let timeout = self.config.shutdown_grace_period();
+        let shutdown = async {
+            time::timeout(timeout, game.wait_for_shutdown())
+                .await
+                .map_err(|_| ServerError::ShutdownTimedOut(timeout))
+        };
+        match game.status().await.game_status() {
+            ExecutionStatus::Shutdown | ExecutionStatus::Crashed => (),
+            ExecutionStatus::Running => {
+                info!("Shutting down game ID {} before removing it", server_id);
+                game.send_command(send_command_request::Command::Quit, Vec::new())
+                    .await?;
+                shutdown.await?;
+            }
+            ExecutionStatus::ShuttingDown => shutdown.await?,
+            ExecutionStatus::Starting => {
+                info!("Killing starting game ID {} before removing it", server_id);
+                game.kill().await?;
+                shutdown.await?;
+            }
+        }
+
+        self.store.begin_transaction()?;
+        let result = self.store.remove_game(server_id).await;
+        self.store.finish_transaction(result)?;
+
+        self.status_handles.write().await.remove(&server_id);
+        self.games.write().await.remove(&server_id);
+        self.portal.set_server_credentials(server_id, None).await;
+        info!("Game ID {} removed", server_id);
+
+        if delete_files {
+            let mods = game.mods().directory();
+            info!("Deleting game ID {}'s mods directory {}", server_id, mods.display());
+            fs::remove_dir_all(mods).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if a given game instance's mods directory isn't inside its root directory or if another managed
+    /// game uses the same directory, so removing the game with its files can't delete anything else's mods.
+    async fn assert_mods_deletable(&self, server_id: GameStoreId, game: &Factorio) -> anyhow::Result<()> {
+        let mods = fs::canonicalize(game.mods().directory()).await?;
+        let root = fs::canonicalize(game.root()).await?;
+        if mods == root || !mods.starts_with(&root) {
+            return Err(RpcError::ModsDirectoryOutsideRoot(mods).into());
+        }
+
+        let games = self.games.read().await.clone();
+        for (other_id, other) in games {
+            if other_id == server_id {
+                continue;
+            }
+
+            let other_mods = other.read().await.mods().directory().to_owned();
+            if fs::canonicalize(other_mods).await.ok().as_ref() == Some(&mods) {
+                return Err(RpcError::SharedModsDirectory {
+                    path: mods,
+                    server_id: other_id,
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets a given game instance's unique name, or clears it if the name is empty. A name consisting of only digits
+    /// isn't allowed, so it can't be confused with a store ID.
+    async fn set_server_name(&self, server_id: GameStoreId, name: String) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let name = if name.is_empty() {
+            info!("Clearing the name of server ID {}", server_id);
+            None
+        } else {
+            if name.trim() != name || name.chars().all(|c| c.is_ascii_digit()) {
+                return Err(RpcError::InvalidGameName(name).into());
+            }
+            match self.store.get_game_id_by_name(name.clone()).await? {
+                Some(named) if named != server_id => return Err(RpcError::GameNameTaken(name).into()),
+                _ => {}
+            }
+
+            info!("Naming server ID {} {}", server_id, name);
+            Some(name)
+        };
+
+        let result = game.read().await.set_name(name).await;
+        result
+    }
+
+    /// Installs a given Factorio headless server release into a given new root directory and imports it. The
+    /// directory must not exist or be empty.
+    async fn install_server(self, path: PathBuf, release: Release, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+        if self.config.portal_proxy() {
+            send_error_status(&prog_tx, RpcError::PortalProxyMode).await;
+            return;
+        }
+
+        if self.game_exists_by_path(&path).await {
+            send_error_status(&prog_tx, RpcError::GameAlreadyExists(path)).await;
+            return;
+        }
+
+        let tasks = self.tasks.clone();
+        let description = format!("Installing a server to {}", path.display());
+        tasks.spawn(description, async move {
+            if let Err(e) = self.install_new_game(&path, release, &prog_tx).await {
+                error!("Failed to install server to {}: {}", path.display(), e);
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Installs a given release into a given new root directory and imports it.
+    async fn install_new_game(
+        &self,
+        path: &Path,
+        release: Release,
+        prog_tx: &AsyncProgressChannel,
+    ) -> anyhow::Result<()> {
+        if path.exists() && fs::read_dir(path).await?.next_entry().await?.is_some() {
+            return Err(RpcError::InstallRootNotEmpty(path.to_path_buf()).into());
+        }
+
+        let version = self.installer.resolve(release).await?;
+        shutdown::stop_point(format!("resolved Factorio {}", version))?;
+        self.installer.install(version, path, Some(prog_tx.clone())).await?;
+        shutdown::stop_point(format!("installed Factorio {} to {}", version, path.display()))?;
+        self.import_new_game(path, prog_tx).await
+    }
+
+    /// Upgrades a given game instance in place to a given newer release, after backing it up. The game must be shut
+    /// down.
+    async fn upgrade_server(self, server_id: GameStoreId, release: Release, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        let tasks = self.tasks.clone();
+        let description = format!("Upgrading server ID {}", server_id);
+        tasks.spawn(description, async move {
+            let game = match self.get_game(server_id).await {
+                Ok(game) => game,
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            };
+
+            // hold the game's write lock so it can't be started while it's being upgraded
+            let mut game = game.write().await;
+            match game.status().await.game_status() {
+                ExecutionStatus::Shutdown | ExecutionStatus::Crashed => {}
+                status => {
+                    error!("RPC tried to upgrade server ID {} while it is {:?}", server_id, status);
+                    send_error_status(&prog_tx, ServerError::InvalidGameStatus(status)).await;
+                    return;
+                }
+            }
+
+            if let Err(e) = self.upgrade_game(server_id, &mut game, release, &prog_tx).await {
+                error!("Failed to upgrade server ID {}: {}", server_id, e);
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Backs up a given game instance, installs a given newer release over it and re-imports it.
+    async fn upgrade_game(
+        &self,
+        server_id: GameStoreId,
+        game: &mut Factorio,
+        release: Release,
+        prog_tx: &AsyncProgressChannel,
+    ) -> anyhow::Result<()> {
+        let installed = game.game_version();
+        let target = self.installer.resolve(release).await?;
+        if target <= installed {
+            return Err(RpcError::NotAnUpgrade { installed, target }.into());
+        }
+
+        info!("Upgrading server ID {} from {} to {}", server_id, installed, target);
+        let backup = backup::create(
+            self.config.backup_directory(),
+            server_id,
+            &backup::Sources::of(game),
+            Some(prog_tx.clone()),
+        )
+        .await?;
+        send_status(
+            prog_tx,
+            async_status::indefinite(&format!("Backed up to {} before upgrading", backup.name())),
+        )
+        .await;
+        shutdown::stop_point(format!("backed up to {}", backup.name()))?;
+
+        let root = game.root().to_path_buf();
+        self.installer.install(target, &root, Some(prog_tx.clone())).await?;
+        // the installed files no longer match the stored ones, so the game is reimported even if shutting down
+        shutdown::checkpoint(format!("installed Factorio {}", target));
+        self.reimport_game(server_id, game, prog_tx).await?;
+
+        info!("Upgraded server ID {} to {}", server_id, target);
+        Ok(())
+    }
+
+    /// Installs a given Factorio headless server release into the shared version pool.
+    async fn install_pooled_version(self, release: Release, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        let tasks = self.tasks.clone();
+        let description = String::from("Installing a Factorio version into the version pool");
+        tasks.spawn(description, async move {
+            let result = async {
+                let version = self.installer.resolve(release).await?;
+                let _pool = self.version_pool_lock.lock().await;
+                self.version_pool()
+                    .install(&self.installer, version, Some(prog_tx.clone()))
+                    .await
+            }
+            .await;
+
+            if let Err(e) = result {
+                error!("Failed to install a Factorio version into the version pool: {}", e);
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Returns the versions in the shared version pool along with the game instances set to use each.
+    async fn list_pooled_versions(&self) -> anyhow::Result<rpc::PooledVersionList> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let users = self.pooled_version_users().await;
+        let versions = self.version_pool().list().await?;
+        Ok(rpc::PooledVersionList {
+            versions: versions
+                .into_iter()
+                .map(|version| rpc::pooled_version_list::PooledVersion {
+                    version: Some(version.into()),
+                    server_ids: users
+                        .iter()
+                        .filter(|(_, used)| *used == version)
+                        .map(|(server_id, _)| *server_id)
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Removes a given version from the shared version pool. No game instance may be set to use it.
+    async fn remove_pooled_version(&self, version: Option<rpc::Version>) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let version = HumanVersion::from(version.ok_or(RpcError::MissingArgument("version"))?);
+        let _pool = self.version_pool_lock.lock().await;
+        if let Some((server_id, _)) = self
+            .pooled_version_users()
+            .await
+            .into_iter()
+            .find(|(_, used)| *used == version)
+        {
+            return Err(RpcError::PooledVersionInUse { version, server_id }.into());
+        }
+
+        self.version_pool().remove(version).await
+    }
+
+    /// Returns the IDs of the game instances set to use a pooled version along with the versions they use, sorted by
+    /// the IDs.
+    async fn pooled_version_users(&self) -> Vec<(GameStoreId, HumanVersion)> {
+        let games = self.games.read().await.clone();
+        let mut users = Vec::new();
+        for (server_id, game) in games {
+            if let Some(version) = game.read().await.settings().management.game_version {
+                users.push((server_id, version));
+            }
+        }
+
+        // the unloaded games' settings are only in the store
+        let unloaded: Vec<_> = self.unloaded.read().await.keys().copied().collect();
+        for server_id in unloaded {
+            match self.store.get_settings(server_id).await {
+                Ok(settings) => {
+                    if let Some(version) = settings.game_version {
+                        users.push((server_id, version));
+                    }
+                }
+                Err(e) => warn!("Failed to get unloaded game ID {}'s stored settings: {}", server_id, e),
+            }
+        }
+
+        users.sort_by_key(|(server_id, _)| *server_id);
+        users
+    }
+
+    /// Returns the shared version pool.
+    fn version_pool(&self) -> VersionPool {
+        VersionPool::new(self.config.versions_directory())
+    }
+
+    /// Backs up a given game's saves, mod list, settings and player lists.
+    async fn create_backup(self, server_id: GameStoreId, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        correlation::spawn(async move {
+            let game = match self.get_game(server_id).await {
+                Ok(game) => game,
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            };
+
+            let sources = backup::Sources::of(&*game.read().await);
+            match backup::create(
+                self.config.backup_directory(),
+                server_id,
+                &sources,
+                Some(prog_tx.clone()),
+            )
+            .await
+            {
+                Ok(created) => {
+                    send_status(
+                        &prog_tx,
+                        async_status::indefinite(&format!("Created backup {}", created.name())),
+                    )
+                    .await;
+                    send_status(&prog_tx, async_status::done()).await;
+                }
+                Err(e) => {
+                    error!("Failed to back up server ID {}: {}", server_id, e);
+                    send_error_status(&prog_tx, e).await;
+                }
+            }
+        });
+    }
+
+    /// Creates a new map with given settings for a given game and saves it with a given name, optionally setting the
+    /// game to load the save when it's started. The game mustn't be running.
+    async fn create_save(self, request: rpc::CreateSaveRequest, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        let name = request.name;
+        if name.is_empty() || name.contains('/') || name.trim() != name {
+            send_error_status(&prog_tx, RpcError::InvalidSaveName(name)).await;
+            return;
+        }
+        let filename = if name.ends_with(".zip") {
+            name
+        } else {
+            format!("{}.zip", name)
+        };
+
+        let new_map = match NewMap::from_game_json(&request.map_gen_settings, &request.map_settings) {
+            Ok(new_map) => new_map,
+            Err(e) => {
+                send_error_status(&prog_tx, RpcError::InvalidMapSettings(e.to_string())).await;
+                return;
+            }
+        };
+
+        let server_id = request.server_id;
+        let load_on_start = request.load_on_start;
+        correlation::spawn(async move {
+            let game = match self.get_game(server_id).await {
+                Ok(game) => game,
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            };
+
+            send_status(
+                &prog_tx,
+                async_status::indefinite(&format!("Creating save {}...", filename)),
+            )
+            .await;
+            // the write lock keeps the game from being started while the map is created
+            let mut game = game.write().await;
+            match game.create_save(&filename, &new_map).await {
+                Ok(save) => {
+                    info!("Created save {} for server ID {}", save.display(), server_id);
+                    if load_on_start {
+                        let start = &mut game.settings_mut().start;
+                        start.behaviour = StartBehaviour::LoadFile;
+                        start.save_name = filename.clone();
+                    }
+
+                    send_status(
+                        &prog_tx,
+                        async_status::indefinite(&format!("Created save {}", filename)),
+                    )
+                    .await;
+                    send_status(&prog_tx, async_status::done()).await;
+                }
+                Err(e) => {
+                    error!("Failed to create save for server ID {}: {}", server_id, e);
+                    send_error_status(&prog_tx, e).await;
+                }
+            }
+        });
+    }
+
+    /// Restores a given game's backup with a given name. The game mustn't be running. The restore has to be confirmed
+    /// with a given confirmation token issued to a given caller.
+    async fn restore_backup(
+        self,
+        server_id: GameStoreId,
+        name: String,
+        caller: Option<String>,
+        confirmation_token: String,
+        prog_tx: AsyncProgressChannel,
+    ) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        correlation::spawn(async move {
+            let game = match self.get_game(server_id).await {
+                Ok(game) => game,
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            };
+
+            // hold the game's write lock so it can't be started while its files are being overwritten
+            let game = game.write().await;
+            match game.status().await.game_status() {
+                ExecutionStatus::Shutdown | ExecutionStatus::Crashed => {}
+                status => {
+                    error!("RPC tried to restore server ID {} while it is {:?}", server_id, status);
+                    send_error_status(&prog_tx, ServerError::InvalidGameStatus(status)).await;
+                    return;
+                }
+            }
+
+            let sources = backup::Sources::of(&game);
+            let operation = Operation::RestoreBackup {
+                server_id,
+                name: name.clone(),
+            };
+            let impact = restore_impact(self.config.backup_directory(), server_id, &name, &sources);
+            if let Err(e) = self
+                .confirm(operation, caller.as_deref(), &confirmation_token, impact)
+                .await
+            {
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+
+            if let Err(e) = backup::restore(
+                self.config.backup_directory(),
+                server_id,
+                &name,
+                &sources,
+                Some(prog_tx.clone()),
+            )
+            .await
+            {
+                error!("Failed to restore server ID {} from backup {}: {}", server_id, name, e);
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Installs a mod to a given game instance.
+    #[allow(clippy::too_many_arguments)]
+    async fn install_mod(
+        self,
+        server_id: GameStoreId,
+        mod_name: String,
+        version: Option<HumanVersion>,
+        override_policy: bool,
+        allow_incompatible: bool,
+        idempotency_key: String,
+        prog_tx: AsyncProgressChannel,
+    ) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        let tasks = self.tasks.clone();
+        let description = format!("Installing mod {} to server ID {}", mod_name, server_id);
+        tasks.spawn(description, async move {
+            match self.claim_idempotency_key(&idempotency_key, "InstallMod").await {
+                Ok(true) => {}
+                Ok(false) => {
+                    send_status(&prog_tx, async_status::done()).await;
+                    return;
+                }
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            }
+
+            match self.get_game(server_id).await {
+                Ok(game) => {
+                    let mut game = game.write().await;
+                    if let Err(e) = assert_mods_managed(server_id, &game) {
+                        self.release_idempotency_key(&idempotency_key).await;
+                        send_error_status(&prog_tx, e).await;
+                        return;
+                    }
+
+                    if let Err(e) = game
+                        .mods_mut()
+                        .add_from_portal(
+                            &mod_name,
+                            version,
+                            override_policy,
+                            allow_incompatible,
+                            Some(prog_tx.clone()),
+                        )
+                        .await
+                    {
+                        if let Some(ModPortalError::ClientError(reqwest::StatusCode::NOT_FOUND)) = e.downcast_ref() {
+                            error!("Failed to install mod '{}': not found ({})", mod_name, e);
+                            send_error_status(&prog_tx, RpcError::NoSuchMod(mod_name)).await;
+                        } else if let Some(ModError::ExcludedByPolicy { .. }) = e.downcast_ref() {
+                            error!("Failed to install mod '{}': {}", mod_name, e);
+                            send_error_status(&prog_tx, RpcError::ExcludedByPolicy(e.to_string())).await;
+                        } else if let Some(ModError::NoCompatibleRelease { .. })
+                        | Some(ModError::IncompatibleRelease { .. }) = e.downcast_ref()
+                        {
+                            error!("Failed to install mod '{}': {}", mod_name, e);
+                            send_error_status(&prog_tx, RpcError::IncompatibleRelease(e.to_string())).await;
+                        } else {
+                            error!("Failed to install mod '{}': {}", mod_name, e);
+                            send_error_status(&prog_tx, e).await;
+                        }
+                        self.release_idempotency_key(&idempotency_key).await;
+                        return;
+                    }
+
+                    send_status(&prog_tx, async_status::done()).await
+                }
+                Err(e) => {
+                    self.release_idempotency_key(&idempotency_key).await;
+                    send_error_status(&prog_tx, e).await
+                }
+            };
+        });
+    }
+
+    /// Updates the installed mods of a given game instance.
+    async fn update_mods(self, server_id: GameStoreId, idempotency_key: String, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        let tasks = self.tasks.clone();
+        let description = format!("Updating the mods of server ID {}", server_id);
+        tasks.spawn(description, async move {
+            match self.claim_idempotency_key(&idempotency_key, "UpdateMods").await {
+                Ok(true) => {}
+                Ok(false) => {
+                    send_status(&prog_tx, async_status::done()).await;
+                    return;
+                }
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            }
+
+            match self.get_game(server_id).await {
+                Ok(game) => {
+                    let mut game = game.write().await;
+                    if let Err(e) = assert_mods_managed(server_id, &game) {
+                        self.release_idempotency_key(&idempotency_key).await;
+                        send_error_status(&prog_tx, e).await;
+                        return;
+                    }
+
+                    if let Err(e) = game.update_mods(Some(prog_tx.clone())).await {
+                        error!("Failed to update mods: {}", e);
+                        self.release_idempotency_key(&idempotency_key).await;
+                        send_error_status(&prog_tx, e).await;
+                        return;
+                    }
+
+                    send_status(&prog_tx, async_status::done()).await
+                }
+                Err(e) => {
+                    self.release_idempotency_key(&idempotency_key).await;
+                    send_error_status(&prog_tx, e).await
+                }
+            };
+        });
+    }
+
+    /// Ensures the dependencies of the installed mods of a given game instance are met.
+    async fn ensure_mod_dependencies(self, server_id: GameStoreId, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        let tasks = self.tasks.clone();
+        let description = format!("Ensuring the mod dependencies of server ID {}", server_id);
+        tasks.spawn(description, async move {
+            match self.get_game(server_id).await {
+                Ok(game) => {
+                    let mut game = game.write().await;
+                    if let Err(e) = assert_mods_managed(server_id, &game) {
+                        send_error_status(&prog_tx, e).await;
+                        return;
+                    }
+
+                    if let Err(e) = game.mods_mut().ensure_dependencies(Some(prog_tx.clone())).await {
+                        error!("Failed to ensure mod dependencies: {}", e);
+                        if let Some(ModError::UnresolvableDependencies(failure)) = e.downcast_ref() {
+                            send_error_status(&prog_tx, RpcError::UnresolvableDependencies(failure.clone())).await;
+                        } else {
+                            send_error_status(&prog_tx, e).await;
+                        }
+                        return;
+                    }
+
+                    send_status(&prog_tx, async_status::done()).await
+                }
+                Err(e) => send_error_status(&prog_tx, e).await,
+            };
+        });
+    }
+
+    /// Exports a given game instance's mods as a modpack manifest with a given file name into the modpack directory.
+    async fn export_modpack(
+        self,
+        server_id: GameStoreId,
+        name: String,
+        include_settings: bool,
+        prog_tx: AsyncProgressChannel,
+    ) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+        let path = match self.modpack_path(name) {
+            Ok(path) => path,
+            Err(e) => {
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+        };
+
+        correlation::spawn(async move {
+            match self.get_game(server_id).await {
+                Ok(game) => {
+                    let game = game.read().await;
+                    if let Err(e) = assert_mods_managed(server_id, &game) {
+                        send_error_status(&prog_tx, e).await;
+                        return;
+                    }
+
+                    send_status(&prog_tx, async_status::indefinite("Exporting modpack...")).await;
+                    let result = async {
+                        let modpack = Modpack::export(game.mods(), include_settings).await?;
+                        fs::create_dir_all(self.config.modpack_directory()).await?;
+                        modpack.write(&path).await
+                    }
+                    .await;
+
+                    if let Err(e) = result {
+                        error!("Failed to export modpack of server ID {}: {}", server_id, e);
+                        send_error_status(&prog_tx, e).await;
+                        return;
+                    }
+
+                    info!("Exported modpack of server ID {} to {}", server_id, path.display());
+                    send_status(&prog_tx, async_status::done()).await
+                }
+                Err(e) => send_error_status(&prog_tx, e).await,
+            };
+        });
+    }
+
+    /// Applies a modpack manifest with a given file name in the modpack directory to a given game instance.
+    async fn apply_modpack(
+        self,
+        server_id: GameStoreId,
+        name: String,
+        override_policy: bool,
+        prog_tx: AsyncProgressChannel,
+    ) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+        let path = match self.modpack_path(name) {
+            Ok(path) => path,
+            Err(e) => {
+                send_error_status(&prog_tx, e).await;
+                return;
+            }
+        };
+
+        let tasks = self.tasks.clone();
+        let description = format!("Applying modpack {} to server ID {}", path.display(), server_id);
+        tasks.spawn(description, async move {
+            let modpack = match Modpack::read(&path).await {
+                Ok(modpack) => modpack,
+                Err(e) => {
+                    error!("Failed to read modpack {}: {}", path.display(), e);
+                    send_error_status(&prog_tx, RpcError::InvalidModpack(e.to_string())).await;
+                    return;
+                }
+            };
+
+            match self.get_game(server_id).await {
+                Ok(game) => {
+                    let mut game = game.write().await;
+                    if let Err(e) = assert_mods_managed(server_id, &game) {
+                        send_error_status(&prog_tx, e).await;
+                        return;
+                    }
+
+                    if let Err(e) = modpack.apply(game.mods_mut(), override_policy, &prog_tx).await {
+                        error!(
+                            "Failed to apply modpack {} to server ID {}: {}",
+                            path.display(),
+                            server_id,
+                            e
+                        );
+                        if let Some(ModError::ExcludedByPolicy { .. }) = e.downcast_ref() {
+                            send_error_status(&prog_tx, RpcError::ExcludedByPolicy(e.to_string())).await;
+                        } else {
+                            send_error_status(&prog_tx, e).await;
+                        }
+                        return;
+                    }
+
+                    send_status(&prog_tx, async_status::done()).await
+                }
+                Err(e) => send_error_status(&prog_tx, e).await,
+            };
+        });
+    }
+
+    /// Makes the mods of given target game instances identical to a given source instance's, streaming every planned
+    /// change before applying it. With `dry_run`, the changes are only streamed.
+    async fn sync_mods(
+        self,
+        source_id: GameStoreId,
+        target_ids: Vec<GameStoreId>,
+        dry_run: bool,
+        override_policy: bool,
+        prog_tx: AsyncProgressChannel,
+    ) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        if target_ids.contains(&source_id) {
+            send_error_status(&prog_tx, RpcError::SyncOntoSource(source_id)).await;
+            return;
+        }
+
+        let tasks = self.tasks.clone();
+        let description = format!("Syncing the mods of server ID {} to {:?}", source_id, target_ids);
+        tasks.spawn(description, async move {
+            // the source is exported up front so it isn't kept locked while the targets are changed
+            let modpack = match self.export_source_mods(source_id).await {
+                Ok(modpack) => modpack,
+                Err(e) => {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+            };
+
+            let target_count = target_ids.len();
+            for (index, target_id) in target_ids.into_iter().enumerate() {
+                if let Err(e) = shutdown::stop_point(format!("{} of {} target servers synced", index, target_count)) {
+                    send_error_status(&prog_tx, e).await;
+                    return;
+                }
+
+                if let Err(e) = self
+                    .sync_target_mods(&modpack, target_id, dry_run, override_policy, &prog_tx)
+                    .await
+                {
+                    error!(
+                        "Failed to sync mods of server ID {} to server ID {}: {}",
+                        source_id, target_id, e
+                    );
+                    if let Some(ModError::ExcludedByPolicy { .. }) = e.downcast_ref() {
+                        send_error_status(&prog_tx, RpcError::ExcludedByPolicy(e.to_string())).await;
+                    } else {
+                        send_error_status(&prog_tx, e).await;
+                    }
+                    return;
+                }
+            }
+
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Restores the mod set a given game instance had the last time it started successfully. The revert has to be
+    /// confirmed with a given confirmation token issued to a given caller.
+    async fn revert_to_last_known_good(
+        self,
+        server_id: GameStoreId,
+        caller: Option<String>,
+        confirmation_token: String,
+        prog_tx: AsyncProgressChannel,
+    ) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+
+        correlation::spawn(async move {
+            if let Err(e) = self
+                .revert_mods(server_id, caller.as_deref(), &confirmation_token, &prog_tx)
+                .await
+            {
+                log_rpc_error(&format!("Failed to revert mods of server ID {}", server_id), &e);
+                if let Some(ModError::ExcludedByPolicy { .. }) = e.downcast_ref() {
+                    send_error_status(&prog_tx, RpcError::ExcludedByPolicy(e.to_string())).await;
+                } else {
+                    send_error_status(&prog_tx, e).await;
+                }
+                return;
+            }
+
+            send_status(&prog_tx, async_status::done()).await;
+        });
+    }
+
+    /// Makes a given game instance's mods match its last known good mod set, streaming every change. The revert has to
+    /// be confirmed with a given confirmation token issued to a given caller.
+    async fn revert_mods(
+        &self,
+        server_id: GameStoreId,
+        caller: Option<&str>,
+        confirmation_token: &str,
+        prog_tx: &AsyncProgressChannel,
+    ) -> anyhow::Result<()> {
+        let game = self.get_game(server_id).await?;
+        let mut game = game.write().await;
+        assert_mods_managed(server_id, &game)?;
+
+        let (modpack, recorded_at) = game
+            .last_known_good_mods()
+            .await?
+            .ok_or(RpcError::NoLastKnownGoodMods(server_id))?;
+        let impact = revert_impact(server_id, &modpack, recorded_at, &game);
+        self.confirm(
+            Operation::RevertToLastKnownGood { server_id },
+            caller,
+            confirmation_token,
+            impact,
+        )
+        .await?;
+
+        info!(
+            "Reverting mods of server ID {} to the last known good mods from {}",
+            server_id, recorded_at
+        );
+
+        // the mods were installed before, so the mod policy doesn't apply to them
+        let changes = modpack.sync(game.mods_mut(), true, prog_tx).await?;
+        for change in &changes {
+            prog_tx
+                .send_status(async_status::indefinite(&format!("Reverted: {}", change)))
+                .await?;
+        }
+
+        info!("Reverted {} mod changes on server ID {}", changes.len(), server_id);
+        Ok(())
+    }
+
+    /// Returns the path of a modpack manifest with a given file name in the modpack directory. Returns
+    /// `RpcError::InvalidModpackName` if the name isn't a plain file name, so the modpack RPCs can't read or write
+    /// files outside the directory.
+    fn modpack_path(&self, name: String) -> Result<PathBuf, RpcError> {
+        if is_plain_file_name(&name) {
+            Ok(self.config.modpack_directory().join(name))
+        } else {
+            Err(RpcError::InvalidModpackName(name))
+        }
+    }
+
+    /// Returns the mods of a given game instance as a modpack without mod settings, to sync them to other instances.
+    async fn export_source_mods(&self, source_id: GameStoreId) -> anyhow::Result<Modpack> {
+        let game = self.get_game(source_id).await?;
+        let game = game.read().await;
+        assert_mods_managed(source_id, &game)?;
+        Modpack::export(game.mods(), false).await
+    }
+
+    /// Streams the changes that make a given game instance's mods match a given modpack, and applies them unless
+    /// `dry_run` is set.
+    async fn sync_target_mods(
+        &self,
+        modpack: &Modpack,
+        target_id: GameStoreId,
+        dry_run: bool,
+        override_policy: bool,
+        prog_tx: &AsyncProgressChannel,
+    ) -> anyhow::Result<()> {
+        let game = self.get_game(target_id).await?;
+        let mut game = game.write().await;
+        assert_mods_managed(target_id, &game)?;
+
+        let changes = modpack.plan(game.mods()).await?;
+        info!("Syncing {} mod changes to server ID {}", changes.len(), target_id);
+        for change in &changes {
+            prog_tx
+                .send_status(async_status::indefinite(&format!(
+                    "Server ID {}: {}",
+                    target_id, change
+                )))
+                .await?;
+        }
+
+        if !dry_run && !changes.is_empty() {
+            modpack.sync(game.mods_mut(), override_policy, prog_tx).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves a given game instance's current mod enabled states as a preset with a given name, replacing its earlier
+    /// preset with the same name.
+    async fn save_mod_preset(&self, server_id: GameStoreId, name: String) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        if name.is_empty() {
+            return Err(RpcError::MissingArgument("name").into());
+        }
+
+        let game = self.get_game(server_id).await?;
+        let game = game.read().await;
+        assert_mods_managed(server_id, &game)?;
+
+        info!("Saving mod preset {} of server ID {}", name, server_id);
+        ModPreset::capture(name, game.mods())
+            .await?
+            .save(&self.store, server_id)
+            .await
+    }
+
+    /// Returns a given game instance's mod presets.
+    async fn list_mod_presets(&self, server_id: GameStoreId) -> anyhow::Result<rpc::ModPresetList> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        // make sure the game exists, since a non-existent game has no presets either
+        self.get_game(server_id).await?;
+
+        let presets = ModPreset::load_all(&self.store, server_id).await?;
+        let time_zone = self.config.time_zone();
+        Ok(rpc::ModPresetList {
+            presets: presets.iter().map(|preset| preset.to_rpc_format(time_zone)).collect(),
+        })
+    }
+
+    /// Applies a given game instance's mod preset with a given name, and optionally restarts the instance afterwards
+    /// with a given grace period if it's running.
+    async fn apply_mod_preset(
+        &self,
+        server_id: GameStoreId,
+        name: &str,
+        restart: bool,
+        grace_period: u64,
+    ) -> anyhow::Result<rpc::ModPresetApplication> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let preset = ModPreset::load(&self.store, server_id, name)
+            .await?
+            .ok_or_else(|| RpcError::NoSuchModPreset(name.to_owned()))?;
+        let game = self.get_game(server_id).await?;
+        let (missing_mods, running) = {
+            let game = game.write().await;
+            assert_mods_managed(server_id, &game)?;
+
+            info!("Applying mod preset {} to server ID {}", name, server_id);
+            let missing_mods = preset.apply(game.mods()).await?;
+            let running = game.status().await.game_status() == ExecutionStatus::Running;
+            (missing_mods, running)
+        };
+
+        let restarting = restart && running;
+        if restarting {
+            // the game isn't kept locked, since the restart checks for a pending action under its own lock
+            self.stop_server(server_id, grace_period, true, "").await?;
+        }
+
+        Ok(rpc::ModPresetApplication {
+            missing_mods,
+            restarting,
+        })
+    }
+
+    /// Removes a given game instance's mod preset with a given name.
+    async fn remove_mod_preset(&self, server_id: GameStoreId, name: String) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        info!("Removing mod preset {} of server ID {}", name, server_id);
+        if self.store.remove_mod_preset(server_id, name.clone()).await? {
+            Ok(())
+        } else {
+            Err(RpcError::NoSuchModPreset(name).into())
+        }
+    }
+
+    /// Removes a mod's archives, or only a given version's archive, from a given game instance, optionally along with
+    /// its orphaned dependencies. In a dry run, returns what would be removed.
+    async fn remove_mod(
+        &self,
+        server_id: GameStoreId,
+        mod_name: &str,
+        version: Option<HumanVersion>,
+        cascade: bool,
+        dry_run: bool,
+    ) -> anyhow::Result<rpc::ModRemoval> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let mut game = game.write().await;
+        assert_mods_managed(server_id, &game)?;
+
+        match game.mods_mut().remove(mod_name, version, cascade, dry_run).await {
+            Ok(removal) => Ok((&removal).into()),
+            Err(e) => match e.downcast::<ModError>() {
+                Ok(ModError::NoSuchMod(name)) => Err(RpcError::NoSuchMod(name).into()),
+                Ok(ModError::NoSuchArchive { name, version }) => {
+                    Err(RpcError::NoSuchModArchive { name, version }.into())
+                }
+                Ok(e) => Err(e.into()),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    /// Verifies a given game instance's installed mods, optionally analysing their content for likely conflicts and
+    /// verifying their zip archives' checksums. The progress of the checksum calculations is streamed before the
+    /// verification. If the client stops receiving the stream, such as when the request is cancelled, the checksum
+    /// calculations are stopped.
+    async fn verify_mods(
+        &self,
+        server_id: GameStoreId,
+        analyze_content: bool,
+        verify_checksums: bool,
+    ) -> anyhow::Result<mpsc::Receiver<Result<rpc::ModVerificationProgress, Status>>> {
+        use rpc::mod_verification_progress::Update;
+
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        assert_mods_managed(server_id, &*game.read().await)?;
+        let (prog_tx, mut prog_rx) = self.progress_channel();
+        let (mut tx, rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+
+        correlation::spawn(async move {
+            info!("Verifying mods of server ID {}", server_id);
+            let game = game.read().await;
+            let verifying = game.mods().verify(analyze_content, verify_checksums, Some(prog_tx));
+            tokio::pin!(verifying);
+
+            // the progress channel closes once the verification finishes, so every status is forwarded before the
+            // result
+            let mut result = None;
+            loop {
+                let status = tokio::select! {
+                    verified = &mut verifying, if result.is_none() => {
+                        result = Some(verified);
+                        continue;
+                    }
+                    status = prog_rx.recv() => match status {
+                        Some(status) => status,
+                        None => break,
+                    },
+                };
+
+                let update = status.map(|progress| rpc::ModVerificationProgress {
+                    update: Some(Update::Progress(progress)),
+                });
+                if tx.send(update).await.is_err() {
+                    debug!("Mod verification receiver dropped, stopping");
+                    return;
+                }
+            }
+
+            let update = match result {
+                Some(Ok(verification)) => Ok(rpc::ModVerificationProgress {
+                    update: Some(Update::Verification((&verification).into())),
+                }),
+                Some(Err(e)) => {
+                    error!("Failed to verify mods of server ID {}: {}", server_id, e);
+                    Err(error_status(e))
+                }
+                None => return,
+            };
+            let _ = tx.send(update).await;
+        });
+
+        Ok(rx)
+    }
+
+    /// Streams a given game instance's mods, optionally filtered by their portal tags and categories, in batches of at
+    /// most the configured maximum batch size. The next batch is built only once the client has received the previous
+    /// one.
+    async fn list_mods(
+        &self,
+        request: rpc::ListModsRequest,
+    ) -> anyhow::Result<mpsc::Receiver<Result<rpc::ModList, Status>>> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(request.server_id).await?;
+        // the game isn't kept locked while streaming, so a slow client can't block changes to its mods
+        let mods = {
+            let game = game.read().await;
+            assert_mods_managed(request.server_id, &game)?;
+
+            let mut mods = Vec::new();
+            for fact_mod in game.mods().iter() {
+                let update_policy = game.mods().update_policy(&fact_mod.name().await);
+                mods.push((Arc::clone(fact_mod), update_policy));
+            }
+            mods
+        };
+        let batch_size = self.config.max_batch_size();
+        let (mut tx, rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+
+        correlation::spawn(async move {
+            let include_tags = request.include_tags;
+            let filter = ModPolicy::new(request.exclude_categories, request.exclude_tags);
+            let mut batch = Vec::new();
+
+            for (fact_mod, update_policy) in mods {
+                let category = fact_mod.category().await;
+                let tags = fact_mod.tags().await;
+
+                if !include_tags.is_empty() && !tags.iter().any(|tag| include_tags.contains(tag)) {
+                    continue;
+                }
+
+                if !filter.permits(category.as_deref(), &tags) {
+                    continue;
+                }
+
+                batch.push(rpc::mod_list::Mod {
+                    name: fact_mod.name().await,
+                    title: fact_mod.title().await,
+                    version: fact_mod.own_version().await.ok().map(Into::into),
+                    category: category.unwrap_or_default(),
+                    tags,
+                    update_policy: rpc::ModUpdatePolicy::from(update_policy) as i32,
+                });
+
+                if batch.len() >= batch_size {
+                    let mods = std::mem::take(&mut batch);
+                    if tx.send(Ok(rpc::ModList { mods })).await.is_err() {
+                        debug!("Mod list receiver dropped, stopping");
+                        return;
+                    }
+                }
+            }
+
+            if !batch.is_empty() {
+                let _ = tx.send(Ok(rpc::ModList { mods: batch })).await;
+            }
+        });
+
+        Ok(rx)
+    }
+
+    /// Searches the mod portal for mods matching a given query.
+    async fn search_mods(&self, request: rpc::SearchModsRequest) -> anyhow::Result<rpc::ModSearchResults> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let query = SearchQuery::from_rpc_format(request);
+        let results = self.portal.search(&query).await?;
+
+        Ok(rpc::ModSearchResults {
+            results: results.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// Fetches a given mod's information and releases from the mod portal. Doesn't need any game instance.
+    async fn get_portal_mod(&self, name: String) -> anyhow::Result<rpc::PortalMod> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        if name.is_empty() {
+            return Err(RpcError::MissingArgument("name").into());
+        }
+
+        let info = Info::from_portal(&name, &self.portal).await?;
+        Ok(rpc::PortalMod {
+            name: info.name().to_owned(),
+            title: info.title().to_owned(),
+            author: info.author().to_owned(),
+            summary: info.summary().unwrap_or_default().to_owned(),
+            category: info.category().unwrap_or_default().to_owned(),
+            tags: info.tags().to_vec(),
+            releases: info
+                .releases()?
+                .iter()
+                .map(|release| rpc::portal_mod::Release {
+                    version: Some(release.version().into()),
+                    factorio_version: Some(release.factorio_version().into()),
+                    released_at: release.released_on().timestamp(),
+                    sha1: release.sha1().to_owned(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Downloads a given version of a given mod, or its latest version if no version is given, from the mod portal into
+    /// the shared mod cache unless it's cached already. Doesn't need any game instance. The archive's path in the cache
+    /// is sent as the done status' message.
+    async fn download_portal_mod(self, name: String, version: Option<rpc::Version>, prog_tx: AsyncProgressChannel) {
+        if let Err(e) = self.assert_instance_status(instance_status::Status::Running).await {
+            send_error_status(&prog_tx, e).await;
+            return;
+        }
+        if name.is_empty() {
+            send_error_status(&prog_tx, RpcError::MissingArgument("name")).await;
+            return;
+        }
+
+        let version = version.map(HumanVersion::from);
+        let tasks = self.tasks.clone();
+        let description = format!("Downloading mod {} into the mod cache", name);
+        tasks.spawn(description, async move {
+            let cache = ModCache::new(self.config.mod_cache_directory());
+            match cache.fetch(&self.portal, &name, version, Some(prog_tx.clone())).await {
+                Ok(cached) => {
+                    send_status(&prog_tx, async_status::done_with(&cached.path.display().to_string())).await;
+                }
+                Err(e) => {
+                    error!("Failed to download mod {} into the mod cache: {}", name, e);
+                    send_error_status(&prog_tx, e).await;
+                }
+            }
+        });
+    }
+
+    /// Parses and normalizes a given version requirement string. Doesn't depend on the instance's state, so it works
+    /// even while the instance is starting.
+    fn parse_version_requirement(&self, requirement: &str) -> anyhow::Result<rpc::VersionRequirement> {
+        let requirement = requirement
+            .parse::<HumanVersionReq>()
+            .map_err(RpcError::InvalidVersionRequirement)?;
+        Ok(requirement.into())
+    }
+
+    /// Parses and normalizes a given dependency string. Doesn't depend on the instance's state, so it works even while
+    /// the instance is starting.
+    fn parse_dependency_string(&self, dependency: &str) -> anyhow::Result<rpc::Dependency> {
+        let dependency = dependency
+            .parse::<Dependency>()
+            .map_err(RpcError::InvalidDependencyString)?;
+        Ok((&dependency).into())
+    }
+
+    /// Retrieves a given game instance's mod policy.
+    async fn get_mod_policy(&self, server_id: GameStoreId) -> anyhow::Result<rpc::ModPolicy> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let game = game.read().await;
+        assert_mods_managed(server_id, &game)?;
+
+        Ok(game.mods().policy().to_rpc_format())
+    }
+
+    /// Sets a given game instance's mod policy.
+    async fn set_mod_policy(&self, server_id: GameStoreId, policy: Option<rpc::ModPolicy>) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let policy = if let Some(policy) = policy {
+            info!("Updating server ID {}'s mod policy", server_id);
+            ModPolicy::from_rpc_format(&policy)
+        } else {
+            info!("Clearing server ID {}'s mod policy", server_id);
+            ModPolicy::default()
+        };
+
+        debug!("{:?}", policy);
+        let mut game = game.write().await;
+        assert_mods_managed(server_id, &game)?;
+        game.mods_mut().set_policy(policy);
+
+        Ok(())
+    }
+
+    /// Sets the update policy of a given mod in a given game instance.
+    async fn set_mod_update_policy(&self, server_id: GameStoreId, mod_name: &str, policy: i32) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let policy = match rpc::ModUpdatePolicy::from_i32(policy) {
+            Some(policy) => UpdatePolicy::from(policy),
+            None => return Err(RpcError::NoSuchUpdatePolicy(policy).into()),
+        };
+
+        let game = self.get_game(server_id).await?;
+        let mut game = game.write().await;
+        assert_mods_managed(server_id, &game)?;
+
+        info!(
+            "Setting server ID {}'s mod {} update policy to {}",
+            server_id, mod_name, policy
+        );
+        match game.mods_mut().set_update_policy(mod_name, policy) {
+            Ok(()) => Ok(()),
+            Err(e) => match e.downcast::<ModError>() {
+                Ok(ModError::NoSuchMod(name)) => Err(RpcError::NoSuchMod(name).into()),
+                Ok(e) => Err(e.into()),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    /// Returns the mod portal credentials a given game instance's mods are downloaded with, without the token.
+    async fn get_portal_credentials(&self, server_id: GameStoreId) -> anyhow::Result<rpc::PortalCredentials> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        self.get_game(server_id).await?;
+
+        Ok(match self.portal.server_credentials(server_id).await {
+            Some(credentials) => rpc::PortalCredentials {
+                overridden: true,
+                username: credentials.username().to_owned(),
+            },
+            None => rpc::PortalCredentials {
+                overridden: false,
+                username: self.config.portal_username().to_owned(),
+            },
+        })
+    }
+
+    /// Sets the mod portal credentials a given game instance's mods are downloaded with, or clears them so the game
+    /// uses the configured credentials.
+    async fn set_portal_credentials(
+        &self,
+        server_id: GameStoreId,
+        credentials: Option<rpc::set_portal_credentials_request::Credentials>,
+    ) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        self.get_game(server_id).await?;
+
+        if let Some(credentials) = credentials {
+            if credentials.username.is_empty() {
+                return Err(RpcError::MissingArgument("username").into());
+            }
+
+            info!(
+                "Setting server ID {}'s mod portal credentials for user '{}'",
+                server_id, credentials.username
+            );
+            let credentials = Credentials::new(credentials.username, credentials.token);
+            self.store
+                .set_portal_credentials(credentials.to_store_format(server_id, &self.credential_key()?)?)
+                .await?;
+            self.portal.set_server_credentials(server_id, Some(credentials)).await;
+        } else {
+            info!("Clearing server ID {}'s mod portal credentials", server_id);
+            self.store.remove_portal_credentials(server_id).await?;
+            self.portal.set_server_credentials(server_id, None).await;
+        }
+
+        Ok(())
+    }
+
+    /// Reads given kinds of a given game instance's configuration files as they currently are on disk, or every kind if
+    /// none are given.
+    async fn get_raw_server_files(
+        &self,
+        server_id: GameStoreId,
+        kinds: Vec<i32>,
+    ) -> anyhow::Result<rpc::RawServerFiles> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let kinds = if kinds.is_empty() {
+            RawFileKind::ALL.to_vec()
+        } else {
+            kinds
+                .into_iter()
+                .map(|kind| {
+                    rpc::raw_server_file::Kind::from_i32(kind)
+                        .map(RawFileKind::from)
+                        .ok_or(RpcError::NoSuchRawFileKind(kind))
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        let game = self.get_game(server_id).await?;
+        let game = game.read().await;
+        let mut files = Vec::new();
+        for kind in kinds {
+            files.push(rpc::RawServerFile::from(&game.raw_file(kind).await?));
+        }
+
+        Ok(rpc::RawServerFiles { files })
+    }
+
+    /// Retrieves a given game instance's server settings.
+    async fn get_server_settings(
+        &self,
+        server_id: GameStoreId,
+        conflicting_file: bool,
+    ) -> anyhow::Result<rpc::ServerSettings> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let game = game.read().await;
+        let settings = if conflicting_file {
+            game.settings_conflict()
+                .ok_or(RpcError::NoSettingsConflict(server_id))?
+        } else {
+            game.settings()
+        };
+
+        let mut rpc_server_settings = rpc::ServerSettings::default();
+        settings.to_rpc_format(&mut rpc_server_settings)?;
+        // the etag is of the stored settings even when returning the conflicting file's, since those are the ones set
+        rpc_server_settings.etag = self.settings_etag(game.settings_revision());
+
+        Ok(rpc_server_settings)
+    }
+
+    /// Returns the etag of a given revision of a game's settings. The etags of an earlier run of the program never
+    /// match the current ones.
+    fn settings_etag(&self, revision: u64) -> String {
+        format!("{:x}-{:x}", self.started_at.timestamp_nanos(), revision)
+    }
+
+    /// Sets a given game instance's server settings, if the settings' current etag matches a given one. If `persist` is
+    /// set, the settings are written to the game's settings file right away instead of before the game is run next.
+    async fn set_server_settings(
+        &self,
+        server_id: GameStoreId,
+        settings: Option<rpc::ServerSettings>,
+        persist: bool,
+        etag: &str,
+    ) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        if etag.is_empty() {
+            return Err(RpcError::MissingArgument("etag").into());
+        }
+
+        // the pool lock is taken before the game's lock, since removing a pooled version reads every game's settings
+        let _pool = self.version_pool_lock.lock().await;
+        let game = self.get_game(server_id).await?;
+        let mut game = game.write().await;
+        if etag != self.settings_etag(game.settings_revision()) {
+            return Err(RpcError::StaleSettings(server_id).into());
+        }
+        if persist && game.settings_conflict().is_some() {
+            return Err(ServerError::SettingsConflict.into());
+        }
+        let mut server_settings = if let Some(settings) = settings {
+            info!("Updating server ID {}'s settings", server_id);
+            let mut server_settings = factorio::settings::ServerSettings::from_rpc_format(&settings)?;
+            server_settings.keep_fields_newer_than(settings.format_version, game.settings());
+            server_settings
+        } else {
+            info!("Resetting server ID {}'s settings to default", server_id);
+            factorio::settings::ServerSettings::default()
+        };
+
+        debug!("{:?}", server_settings);
+        server_settings.paths.validate(game.root()).await?;
+        server_settings.includes.validate(game.root()).await?;
+        if let Some(version) = server_settings.management.game_version {
+            if !self.version_pool().contains(version).await {
+                return Err(RpcError::NoSuchPooledVersion(version).into());
+            }
+        }
+        server_settings.update_overrides(game.root()).await?;
+        *game.settings_mut() = server_settings;
+        game.rebuild_moved_mods(Arc::clone(&self.portal)).await?;
+
+        if persist && game.flush_settings_to_file().await? {
+            info!("Wrote server ID {}'s settings to its settings file", server_id);
+        }
+        Ok(())
+    }
+
+    /// Bridges a game instance's in-game chat to a given stream of chat bridge requests. The first request selects the
+    /// game instance. The messages sent to the chat are sent to the returned receiver, and the messages in the requests
+    /// are sent to the chat. The bridge stays open until either the requests or the receiver end.
+    fn chat_bridge(
+        self,
+        requests: Streaming<rpc::ChatBridgeRequest>,
+    ) -> mpsc::Receiver<Result<rpc::ChatMessage, Status>> {
+        let (mut tx, rx) = mpsc::channel(CHAT_BRIDGE_CHANNEL_CAPACITY);
+
+        correlation::spawn(async move {
+            if let Err(e) = self.run_chat_bridge(requests, &mut tx).await {
+                error!("Chat bridge failed: {}", e);
+                let _ = tx.send(Err(error_status(e))).await;
+            }
+        });
+        rx
+    }
+
+    /// Runs a chat bridge between a given stream of chat bridge requests and a given transmit channel until either
+    /// ends.
+    async fn run_chat_bridge(
+        &self,
+        mut requests: Streaming<rpc::ChatBridgeRequest>,
+        tx: &mut mpsc::Sender<Result<rpc::ChatMessage, Status>>,
+    ) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let first = match requests.message().await? {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+        let server_id = self.resolve_server(first.server_id, &first.server_name).await?;
+        let game = self.get_game(server_id).await?;
+        let mut chat_rx = game.read().await.subscribe_chat();
+        let time_zone = self.config.time_zone();
+        info!("Chat bridge to server ID {} opened", server_id);
+
+        let mut incoming = Some(first);
+        loop {
+            if let Some(request) = incoming.take() {
+                let message = chat::single_line(&request.message);
+                if !message.is_empty() {
+                    match game
+                        .read()
+                        .await
+                        .send_command(send_command_request::Command::Say, vec![message])
+                        .await
+                    {
+                        Ok(()) => {}
+                        Err(e) if matches!(e.downcast_ref(), Some(ServerError::InvalidGameStatus(_))) => {
+                            warn!("Server ID {} isn't running, dropping bridged chat message", server_id)
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+
+            tokio::select! {
+                message = chat_rx.recv() => match message {
+                    Ok(message) => {
+                        if tx.send(Ok(message.to_rpc_format(time_zone))).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::RecvError::Lagged(skipped)) => {
+                        warn!("Chat bridge to server ID {} skipped {} chat messages", server_id, skipped)
+                    }
+                    Err(broadcast::RecvError::Closed) => break,
+                },
+                request = requests.message() => match request? {
+                    Some(request) => incoming = Some(request),
+                    None => break,
+                },
+            }
+        }
+
+        info!("Chat bridge to server ID {} closed", server_id);
+        Ok(())
+    }
+
+    /// Sets a given game instance's game password to a new random one and returns it. Like other settings changes, the
+    /// password is written to the settings overrides if the game includes settings files.
+    async fn generate_game_password(&self, server_id: GameStoreId) -> anyhow::Result<rpc::GeneratedPassword> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let mut game = game.write().await;
+        let password = util::password::generate(GAME_PASSWORD_LENGTH);
+        let root = game.root().to_path_buf();
+        let settings = game.settings_mut();
+        let previous = std::mem::replace(&mut settings.publicity.password, password.clone());
+        if let Err(e) = settings.update_overrides(&root).await {
+            settings.publicity.password = previous;
+            return Err(e);
+        }
+
+        info!("Generated a new game password for server ID {}", server_id);
+        Ok(rpc::GeneratedPassword { password })
+    }
+
+    /// Returns a given game instance's automatic mod update schedule and its latest run.
+    async fn get_mod_update_schedule(&self, server_id: GameStoreId) -> anyhow::Result<rpc::ModUpdateSchedule> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let settings = game.read().await.settings().schedule.clone();
+        let time_zone = self.config.time_zone();
+
+        let mut response = rpc::ModUpdateSchedule {
+            expression: settings.mod_updates.clone().unwrap_or_default(),
+            ..Default::default()
+        };
+
+        if let Some(next_run) = settings
+            .mod_updates()?
+            .and_then(|schedule| scheduler::next_run(&schedule, time_zone, Utc::now()))
+        {
+            response.next_run = next_run.timestamp();
+            response.next_run_local = time_zone.format(next_run);
+        }
+
+        if let Some(last_run) = self.scheduler.last_mod_update(server_id).await {
+            response.last_run = last_run.finished_at().timestamp();
+            response.last_run_local = time_zone.format(last_run.finished_at());
+            response.last_error = last_run.error().unwrap_or_default().to_owned();
+        }
+
+        Ok(response)
+    }
+
+    /// Sets a given game instance's automatic mod update schedule, or disables the updates if the given expression is
+    /// empty.
+    async fn set_mod_update_schedule(&self, server_id: GameStoreId, expression: String) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let expression = expression.trim();
+        let mod_updates = if expression.is_empty() {
+            info!("Disabling server ID {}'s scheduled mod updates", server_id);
+            None
+        } else {
+            factorio::settings::schedule::parse(expression).map_err(RpcError::InvalidSchedule)?;
+            info!("Scheduling server ID {}'s mod updates at '{}'", server_id, expression);
+            Some(expression.to_owned())
+        };
+
+        let game = self.get_game(server_id).await?;
+        game.write().await.settings_mut().schedule.mod_updates = mod_updates;
+
+        Ok(())
+    }
+
+    /// Resolves a given game instance's settings conflict by adopting a given source's version of the settings.
+    async fn resolve_settings_conflict(&self, server_id: GameStoreId, prefer: i32) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let prefer = match rpc::resolve_settings_conflict_request::Source::from_i32(prefer) {
+            Some(rpc::resolve_settings_conflict_request::Source::PreferFile) => SettingsSource::File,
+            Some(rpc::resolve_settings_conflict_request::Source::PreferStore) => SettingsSource::Store,
+            None => return Err(RpcError::NoSuchSettingsSource(prefer).into()),
+        };
+
+        let game = self.get_game(server_id).await?;
+        let mut game = game.write().await;
+        if game.settings_conflict().is_none() {
+            return Err(RpcError::NoSettingsConflict(server_id).into());
+        }
+
+        info!(
+            "Resolving server ID {}'s settings conflict in favour of {:?}",
+            server_id, prefer
+        );
+        game.resolve_settings_conflict(prefer).await
+    }
+
+    /// Writes a given game instance's settings to its settings files, or only returns the changes if `dry_run` is set.
+    async fn apply_server_settings(
+        &self,
+        server_id: GameStoreId,
+        dry_run: bool,
+    ) -> anyhow::Result<rpc::ApplyServerSettingsResponse> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let changes = game.write().await.apply_settings(dry_run).await?;
+        if dry_run {
+            debug!("Server ID {}'s settings dry-run: {:?}", server_id, changes);
+        } else {
+            info!("Applied server ID {}'s settings", server_id);
+        }
+
+        Ok(rpc::ApplyServerSettingsResponse {
+            changes: changes
+                .into_iter()
+                .map(|change| rpc::apply_server_settings_response::FileChange {
+                    path: format!("{}", change.path.display()),
+                    changed: change.changed,
+                    diff: change.diff,
+                })
+                .collect(),
+        })
+    }
+
+    /// Runs a given game instance.
+    async fn run_server(&self, server_id: GameStoreId, idempotency_key: &str) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        if !self.claim_idempotency_key(idempotency_key, "RunServer").await? {
+            return Ok(());
+        }
+
+        let result = {
+            let mut game = game.write().await;
+            match game.status().await.pending_action() {
+                Some(pending) => Err(RpcError::OperationInProgress {
+                    server_id,
+                    action: pending.action(),
+                }
+                .into()),
+                None => match game.flush_settings_to_file().await {
+                    Ok(_) => game.run().await,
+                    Err(e) => Err(e),
+                },
+            }
+        };
+
+        if let Err(e) = result {
+            error!("Server ID {} failed to run: {}", server_id, e);
+            self.release_idempotency_key(idempotency_key).await;
+            Err(e)
+        } else {
+            info!("Server ID {} starting", server_id);
+            Ok(())
+        }
+    }
+
+    /// Stops a given game instance, optionally starting it again afterwards. If a grace period is given, the players
+    /// are warned and the game is stopped once the period ends. Starting or stopping the game is rejected while it's
+    /// waiting to be stopped.
+    async fn stop_server(
+        &self,
+        server_id: GameStoreId,
+        grace_period: u64,
+        restart: bool,
+        idempotency_key: &str,
+    ) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        if !self.claim_idempotency_key(idempotency_key, "StopServer").await? {
+            return Ok(());
+        }
+
+        let action = if restart {
+            GracefulAction::Restart
+        } else {
+            GracefulAction::Stop
+        };
+        let grace_period = Duration::from_secs(grace_period);
+        let result = {
+            // the pending action is checked and set under a write lock so concurrent requests can't both pass
+            let game = game.write().await;
+            match game.status().await.pending_action() {
+                Some(pending) => Err(RpcError::OperationInProgress {
+                    server_id,
+                    action: pending.action(),
+                }
+                .into()),
+                None => {
+                    let eta = Utc::now() + chrono::Duration::from_std(grace_period)?;
+                    game.begin_pending_action(action, eta).await
+                }
+            }
+        };
+
+        if let Err(e) = result {
+            self.release_idempotency_key(idempotency_key).await;
+            return Err(e);
+        }
+
+        info!("Server ID {} {:?} in {:?}", server_id, action, grace_period);
+        correlation::spawn(async move { carry_out_graceful_action(server_id, game, action, grace_period).await });
+        Ok(())
+    }
+
+    /// Sends a command to a given game instance.
+    async fn send_server_command(
+        &self,
+        server_id: GameStoreId,
+        command: i32,
+        arguments: Vec<String>,
+        idempotency_key: &str,
+    ) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let command = match command {
+            0 => send_command_request::Command::Raw,
+            i => return Err(RpcError::NoSuchCommand(i).into()),
+        };
+
+        if !self.claim_idempotency_key(idempotency_key, "SendServerCommand").await? {
+            return Ok(());
+        }
+
+        if let Err(e) = game.read().await.send_command(command, arguments).await {
+            self.release_idempotency_key(idempotency_key).await;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Saves a given game instance's map with a given name and returns the written save file's name once the game has
+    /// finished writing it.
+    async fn save_map(&self, server_id: GameStoreId, name: String) -> anyhow::Result<rpc::SavedMap> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        if name.contains('/') || name.trim() != name {
+            return Err(RpcError::InvalidSaveName(name).into());
+        }
+
+        let game = self.get_game(server_id).await?;
+        info!("Saving server ID {}'s map", server_id);
+        // the server's lock isn't held while waiting so the server can be stopped if the save never finishes
+        let pending = game.read().await.save_map(&name).await?;
+        let path = pending.wait().await?;
+
+        Ok(rpc::SavedMap {
+            filename: path
+                .file_name()
+                .map(|filename| filename.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Prunes a given game instance's autosaves its autosave retention doesn't keep, or only lists them in a dry run.
+    async fn prune_autosaves(&self, server_id: GameStoreId, dry_run: bool) -> anyhow::Result<rpc::AutosavePruning> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let pruning = game.read().await.prune_autosaves(dry_run).await?;
+        if !dry_run {
+            log_autosave_pruning(server_id, &pruning);
+        }
+
+        Ok(pruning.into())
+    }
+
+    /// Returns the latest mod portal connectivity report. Runs a new connectivity check if one
+    /// hasn't been run yet or if `refresh` is set. Fails in offline mode.
+    async fn get_portal_connectivity(&self, refresh: bool) -> anyhow::Result<rpc::PortalConnectivity> {
+        if self.config.offline() {
+            return Err(RpcError::OfflineMode.into());
+        }
+
+        let connectivity = match self.portal.connectivity().await {
+            Some(connectivity) if !refresh => connectivity,
+            _ => self.portal.check_connectivity().await,
+        };
+
+        Ok(connectivity.to_rpc_format(self.config.time_zone()))
+    }
+
+    /// Returns a given game instance's status.
+    async fn get_server_status(&self, server_id: GameStoreId) -> anyhow::Result<ServerStatus> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let handle = self
+            .status_handles
+            .read()
+            .await
+            .get(&server_id)
+            .cloned()
+            .ok_or(RpcError::NoSuchGame(server_id))?;
+
+        Ok(handle.status().await)
+    }
+
+    /// Runs a one-shot tool with a given game instance's executable.
+    async fn run_server_tool(&self, request: rpc::RunToolRequest) -> anyhow::Result<rpc::ToolOutput> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(request.server_id).await?;
+        let tool = match rpc::run_tool_request::Tool::from_i32(request.tool) {
+            Some(rpc::run_tool_request::Tool::DumpData) => Tool::DumpData,
+            Some(_) if request.save.is_empty() => return Err(RpcError::MissingArgument("save").into()),
+            Some(_) if !is_plain_file_name(&request.save) => return Err(RpcError::InvalidSaveName(request.save).into()),
+            Some(rpc::run_tool_request::Tool::Benchmark) => Tool::Benchmark {
+                save: PathBuf::from(request.save),
+                ticks: request.ticks,
+            },
+            Some(rpc::run_tool_request::Tool::Map2scenario) => Tool::Map2Scenario {
+                save: PathBuf::from(request.save),
+            },
+            None => return Err(RpcError::NoSuchCommand(request.tool).into()),
+        };
+
+        info!("Running {:?} for server ID {}", tool, request.server_id);
+        // the tool runs under a write lock so the server can't be started while it's running
+        let output = game.write().await.run_tool(tool).await?;
+        Ok(output.into())
+    }
+
+    /// Returns which item and recipe prototypes the base game and each of a given game instance's mods add.
+    async fn inspect_prototypes(&self, server_id: GameStoreId) -> anyhow::Result<rpc::PrototypeInspection> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        // the dumps run under a write lock so the server can't be started while they're running
+        let game = game.write().await;
+        assert_mods_managed(server_id, &game)?;
+
+        info!("Inspecting prototypes of server ID {}", server_id);
+        let inspection = game.inspect_prototypes().await?;
+        Ok((&inspection).into())
+    }
+
+    /// Imports a given map exchange string's map generation settings into a given game instance.
+    async fn import_map_exchange_string(&self, server_id: GameStoreId, exchange_string: &str) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let exchange: MapExchange = exchange_string.parse().map_err(RpcError::InvalidMapExchangeString)?;
+        let game = self.get_game(server_id).await?;
+
+        info!(
+            "Importing map exchange string from version {} into server ID {}",
+            exchange.version(),
+            server_id
+        );
+        let game = game.read().await;
+        game.import_map_exchange(&exchange).await
+    }
+
+    /// Returns a given game instance's current map settings as a map exchange string.
+    async fn export_map_exchange_string(&self, server_id: GameStoreId) -> anyhow::Result<rpc::MapExchangeString> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let exchange = game
+            .read()
+            .await
+            .export_map_exchange()
+            .await?
+            .ok_or(RpcError::NoMapExchangeString(server_id))?;
+
+        Ok(rpc::MapExchangeString {
+            exchange_string: exchange.to_string(),
+            version: exchange.version(),
+        })
+    }
+
+    /// Returns the addresses that have been refused a connection to a given game instance too many times.
+    async fn get_offenders(&self, server_id: GameStoreId) -> anyhow::Result<rpc::OffenderList> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let offenders = game.read().await.offenders().await;
+
+        let time_zone = self.config.time_zone();
+        Ok(rpc::OffenderList {
+            offenders: offenders
+                .iter()
+                .map(|offender| offender.to_rpc_format(time_zone))
+                .collect(),
+        })
+    }
+
+    /// Returns the players that have joined a given game instance since the program started.
+    async fn get_players(&self, server_id: GameStoreId) -> anyhow::Result<rpc::PlayerList> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let game = self.get_game(server_id).await?;
+        let players = game.read().await.players().await;
+
+        let time_zone = self.config.time_zone();
+        Ok(rpc::PlayerList {
+            players: players.iter().map(|player| player.to_rpc_format(time_zone)).collect(),
+        })
+    }
+
+    /// Returns a given game's player count history in a given time range between Unix timestamps, with the samples
+    /// grouped into buckets of a given resolution in seconds.
+    async fn get_player_count_history(
+        &self,
+        server_id: GameStoreId,
+        range_start: i64,
+        range_end: i64,
+        resolution: u64,
+    ) -> anyhow::Result<rpc::PlayerCountHistory> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        // make sure the game exists, since a non-existent game has no samples either
+        self.get_game(server_id).await?;
+
+        let (start, end) =
+            player_history::time_range(range_start, range_end, Utc::now()).ok_or(RpcError::InvalidTimeRange {
+                start: range_start,
+                end: range_end,
+            })?;
+        let time_zone = self.config.time_zone();
+        let history = PlayerCountHistory::load(
+            &self.store,
+            server_id,
+            start,
+            end,
+            Duration::from_secs(resolution),
+            time_zone,
+        )
+        .await?;
+        Ok(history.to_rpc_format(time_zone))
+    }
+
+    /// Returns a page of a given game's recorded events of given kinds, or of every kind if none are given, in a given
+    /// time range between Unix timestamps.
+    async fn get_server_events(
+        &self,
+        server_id: GameStoreId,
+        range_start: i64,
+        range_end: i64,
+        kinds: Vec<i32>,
+        page_size: u32,
+        page_token: &str,
+    ) -> anyhow::Result<rpc::ServerEventPage> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        // make sure the game exists, since a non-existent game has no events either
+        self.get_game(server_id).await?;
+
+        let (start, end) =
+            player_history::time_range(range_start, range_end, Utc::now()).ok_or(RpcError::InvalidTimeRange {
+                start: range_start,
+                end: range_end,
+            })?;
+        let kinds = kinds
+            .into_iter()
+            .map(|kind| {
+                rpc::server_event::Kind::from_i32(kind)
+                    .map(EventKind::from)
+                    .ok_or(RpcError::NoSuchEventKind(kind))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let page = ServerEventPage::load(&self.store, server_id, start, end, &kinds, page_size, page_token).await?;
+        Ok(page.to_rpc_format(self.config.time_zone()))
+    }
+
+    /// Returns a given game's backups.
+    async fn list_backups(&self, server_id: GameStoreId) -> anyhow::Result<rpc::BackupList> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        // make sure the game exists, since a non-existent game has no backups either
+        self.get_game(server_id).await?;
+
+        let backups = backup::list(self.config.backup_directory(), server_id).await?;
+        let time_zone = self.config.time_zone();
+        Ok(rpc::BackupList {
+            backups: backups.iter().map(|backup| backup.to_rpc_format(time_zone)).collect(),
+        })
+    }
+
+    /// Sets the instance's message-of-the-day, or clears it if the given message is empty.
+    async fn set_motd(&self, message: String, author: String) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let mut current = self.motd.write().await;
+        if message.is_empty() {
+            info!("Clearing message-of-the-day");
+            Motd::clear(&self.store).await?;
+            *current = None;
+        } else {
+            if author.is_empty() {
+                return Err(RpcError::MissingArgument("author").into());
+            }
+
+            info!("Setting message-of-the-day by {}", author);
+            let motd = Motd::new(message, author);
+            motd.save(&self.store).await?;
+            *current = Some(motd);
+        }
+
+        Ok(())
+    }
+
+    /// Acknowledges the stored games' import failures from when the instance started, which removes them from the
+    /// instance status.
+    async fn acknowledge_startup_failures(&self) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        if let Some(summary) = self.startup_summary.write().await.as_mut() {
+            let acknowledged = summary.acknowledge();
+            info!("Acknowledged {} startup import failures", acknowledged);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the metrics of the RPC requests served since the instance started.
+    fn get_rpc_metrics(&self) -> rpc::RpcMetrics {
+        rpc::RpcMetrics {
+            version: Some((*HVER_VERSION).into()),
+            methods: self.rpc_metrics.to_rpc_format(),
+        }
+    }
+
+    /// Returns whether a given experimental feature is enabled.
+    pub async fn is_feature_enabled(&self, flag: FeatureFlag) -> bool {
+        self.feature_flags.read().await.is_enabled(flag)
+    }
+
+    /// Returns the state of every feature flag.
+    async fn get_feature_flags(&self) -> anyhow::Result<rpc::FeatureFlags> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+        Ok((&*self.feature_flags.read().await).into())
+    }
+
+    /// Enables or disables a given experimental feature.
+    async fn set_feature_flag(&self, name: String, enabled: bool) -> anyhow::Result<()> {
+        self.assert_instance_status(instance_status::Status::Running).await?;
+
+        let flag = name
+            .parse::<FeatureFlag>()
+            .map_err(|_| RpcError::NoSuchFeatureFlag(name))?;
+        info!("Setting feature flag {} to {}", flag, enabled);
+        self.feature_flags.write().await.set(&self.store, flag, enabled).await
+    }
+
+    /// Authorizes a given RPC request for a given scope with the bearer token in its metadata. Returns the token's name,
+    /// or `None` if authorization is disabled.
+    async fn authorize<T>(&self, request: &Request<T>, scope: Scope) -> Result<Option<String>, Status> {
+        auth::authorize(&self.config, &self.store, request.metadata(), scope)
+            .await
+            .map_err(|e| {
+                warn!("RPC request denied: {}", e);
+                error_status(e)
+            })
+    }
+
+    /// Returns the message of a given request addressed to a single server. If the request addresses the server by its
+    /// name, the server's store ID is resolved from the name and set in the message.
+    async fn address<T>(&self, request: Request<T>) -> Result<T, Status>
+    where
+        T: rpc::ServerRequest,
+    {
+        if self.config.portal_proxy() {
+            warn!("RPC request addressed a server while running as a mod portal proxy");
+            return Err(RpcError::PortalProxyMode.into());
+        }
+
+        let mut msg = request.into_inner();
+        let server_id = self
+            .resolve_server(msg.server_id(), msg.server_name())
+            .await
+            .map_err(|e| {
+                warn!("RPC request addressed no server: {}", e);
+                error_status(e)
+            })?;
+
+        msg.set_server_id(server_id);
+        correlation::set_server(server_id);
+        Ok(msg)
+    }
+
+    /// Returns the store ID of the game addressed by a given store ID and name. An empty name addresses the game by
+    /// the store ID alone. If both are given, they have to refer to the same game.
+    async fn resolve_server(&self, server_id: GameStoreId, name: &str) -> anyhow::Result<GameStoreId> {
+        if name.is_empty() {
+            return Ok(server_id);
+        }
+
+        let named = self
+            .store
+            .get_game_id_by_name(name.to_owned())
+            .await?
+            .ok_or_else(|| RpcError::NoSuchGameName(name.to_owned()))?;
+        if server_id != 0 && server_id != named {
+            return Err(RpcError::ConflictingGameAddress {
+                server_id,
+                name: name.to_owned(),
+            }
+            .into());
+        }
+
+        Ok(named)
+    }
+
+    /// Returns this instance's version information in RPC format.
+    fn get_version_information(&self) -> rpc::VersionInformation {
+        rpc::VersionInformation {
+            version: Some((*HVER_VERSION).into()),
+            protocol_version: Some(
+                rpc::VERSION
+                    .parse::<HumanVersion>()
+                    .expect("failed to parse RPC protocol buffer specification version as HumanVersion")
+                    .into(),
+            ),
+            compatibility: compatibility::releases().iter().cloned().map(Into::into).collect(),
+            store_schema_version: store::SCHEMA_VERSION,
+        }
+    }
+
+    /// Returns this instance's status in RPC format.
+    async fn get_rpc_instance_status(&self) -> rpc::InstanceStatus {
+        let uptime = self.get_uptime().await;
+        let servers = self.get_rpc_servers().await;
+        let instance_status = self.get_instance_status().await;
+        let time_zone = self.config.time_zone();
+        let motd = self
+            .motd
+            .read()
+            .await
+            .as_ref()
+            .map(|motd| motd.to_rpc_format(time_zone));
+        let startup_summary = self
+            .startup_summary
+            .read()
+            .await
+            .as_ref()
+            .map(StartupSummary::to_rpc_format);
+
+        rpc::InstanceStatus {
+            uptime: uptime.num_seconds(),
+            servers,
+            instance_status: instance_status.into(),
+            motd,
+            time_zone: time_zone.name().to_owned(),
+            startup_summary,
+            offline: self.config.offline(),
+            store_read_only: self.store.is_read_only(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl mod_rpc_server::ModRpc for Modtorio {
+    type ImportGameStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type UpdateStoreStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type RetryStoreUpdateStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type ExportModpackStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type ApplyModpackStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type SyncModsStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type RevertToLastKnownGoodStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type RefreshServerStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type ChatBridgeStream = mpsc::Receiver<Result<rpc::ChatMessage, Status>>;
+    type InstallServerStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type UpgradeServerStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type InstallPooledVersionStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type CreateBackupStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type RestoreBackupStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type CreateSaveStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type InstallModStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type UpdateModsStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type EnsureModDependenciesStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    type VerifyModsStream = mpsc::Receiver<Result<rpc::ModVerificationProgress, Status>>;
+    type ListModsStream = mpsc::Receiver<Result<rpc::ModList, Status>>;
+    type DownloadPortalModStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+
+    async fn get_version_information(
+        &self,
+        req: Request<rpc::Empty>,
+    ) -> Result<Response<rpc::VersionInformation>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        respond(self.get_version_information())
+    }
+
+    async fn get_instance_status(&self, req: Request<rpc::Empty>) -> Result<Response<rpc::InstanceStatus>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        respond(self.get_rpc_instance_status().await)
+    }
+
+    // I tried to macro these repetitive functions into DRYness but the tonic::async_trait macro messes with them in
+    // some funky way that a macro_rules! didn't work as I'd hoped and I just couldn't bother to figure it out
+    async fn acknowledge_startup_failures(&self, req: Request<rpc::Empty>) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+        map_to_response(self.acknowledge_startup_failures().await)
+    }
+
+    async fn get_rpc_metrics(&self, req: Request<rpc::Empty>) -> Result<Response<rpc::RpcMetrics>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        respond(self.get_rpc_metrics())
+    }
+
+    async fn import_game(&self, req: Request<rpc::ImportRequest>) -> Result<Response<Self::ImportGameStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = req.into_inner();
+        self.clone().import_game(msg.path, tx).await;
+
+        respond(rx)
+    }
+
+    async fn update_store(
+        &self,
+        req: Request<rpc::UpdateStoreRequest>,
+    ) -> Result<Response<Self::UpdateStoreStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageSettings).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone().update_store(msg.server_id, tx).await;
+
+        respond(rx)
+    }
+
+    async fn retry_store_update(
+        &self,
+        req: Request<rpc::RetryStoreUpdateRequest>,
+    ) -> Result<Response<Self::RetryStoreUpdateStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageSettings).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone().retry_store_update(msg.server_id, tx).await;
+
+        respond(rx)
+    }
+
+    async fn refresh_server(
+        &self,
+        req: Request<rpc::RefreshServerRequest>,
+    ) -> Result<Response<Self::RefreshServerStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageSettings).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone().refresh_server(msg.server_id, tx).await;
+
+        respond(rx)
+    }
+
+    async fn remove_server(&self, req: Request<rpc::RemoveServerRequest>) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        let caller = self.authorize(&req, Scope::Admin).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.remove_server(
+                msg.server_id,
+                msg.delete_files,
+                caller.as_deref(),
+                &msg.confirmation_token,
+            )
+            .await,
+        )
+    }
+
+    async fn set_server_name(&self, req: Request<rpc::SetServerNameRequest>) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.set_server_name(msg.server_id, msg.name).await)
+    }
+
+    async fn install_server(
+        &self,
+        req: Request<rpc::InstallServerRequest>,
+    ) -> Result<Response<Self::InstallServerStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = req.into_inner();
+        let release = Release::from_rpc_format(msg.version, msg.channel);
+        self.clone().install_server(PathBuf::from(msg.path), release, tx).await;
+
+        respond(rx)
+    }
+
+    async fn upgrade_server(
+        &self,
+        req: Request<rpc::UpgradeServerRequest>,
+    ) -> Result<Response<Self::UpgradeServerStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        let release = Release::from_rpc_format(msg.version, msg.channel);
+        self.clone().upgrade_server(msg.server_id, release, tx).await;
+
+        respond(rx)
+    }
+
+    async fn install_pooled_version(
+        &self,
+        req: Request<rpc::InstallPooledVersionRequest>,
+    ) -> Result<Response<Self::InstallPooledVersionStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = req.into_inner();
+        let release = Release::from_rpc_format(msg.version, msg.channel);
+        self.clone().install_pooled_version(release, tx).await;
+
+        respond(rx)
+    }
+
+    async fn list_pooled_versions(&self, req: Request<rpc::Empty>) -> Result<Response<rpc::PooledVersionList>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        map_to_response(self.list_pooled_versions().await)
+    }
+
+    async fn remove_pooled_version(
+        &self,
+        req: Request<rpc::RemovePooledVersionRequest>,
+    ) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+
+        let msg = req.into_inner();
+        map_to_response(self.remove_pooled_version(msg.version).await)
+    }
+
+    async fn create_backup(
+        &self,
+        req: Request<rpc::CreateBackupRequest>,
+    ) -> Result<Response<Self::CreateBackupStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ControlServer).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone().create_backup(msg.server_id, tx).await;
+
+        respond(rx)
+    }
+
+    async fn create_save(
+        &self,
+        req: Request<rpc::CreateSaveRequest>,
+    ) -> Result<Response<Self::CreateSaveStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ControlServer).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone().create_save(msg, tx).await;
+
+        respond(rx)
+    }
+
+    async fn list_backups(&self, req: Request<rpc::ListBackupsRequest>) -> Result<Response<rpc::BackupList>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        let msg = self.address(req).await?;
+        map_to_response(self.list_backups(msg.server_id).await)
+    }
+
+    async fn restore_backup(
+        &self,
+        req: Request<rpc::RestoreBackupRequest>,
+    ) -> Result<Response<Self::RestoreBackupStream>, Status> {
+        log_rpc_request(&req);
+        let caller = self.authorize(&req, Scope::Admin).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone()
+            .restore_backup(msg.server_id, msg.name, caller, msg.confirmation_token, tx)
+            .await;
+
+        respond(rx)
+    }
+
+    async fn install_mod(
+        &self,
+        req: Request<rpc::InstallModRequest>,
+    ) -> Result<Response<Self::InstallModStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        let version = msg.mod_version.map(HumanVersion::from);
+        self.clone()
+            .install_mod(
+                msg.server_id,
+                msg.mod_name,
+                version,
+                msg.override_policy,
+                msg.allow_incompatible,
+                msg.idempotency_key,
+                tx,
+            )
+            .await;
+
+        respond(rx)
+    }
+
+    async fn update_mods(
+        &self,
+        req: Request<rpc::UpdateModsRequest>,
+    ) -> Result<Response<Self::UpdateModsStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone().update_mods(msg.server_id, msg.idempotency_key, tx).await;
+
+        respond(rx)
+    }
+
+    async fn ensure_mod_dependencies(
+        &self,
+        req: Request<rpc::EnsureModDependenciesRequest>,
+    ) -> Result<Response<Self::EnsureModDependenciesStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone().ensure_mod_dependencies(msg.server_id, tx).await;
+
+        respond(rx)
+    }
+
+    async fn export_modpack(
+        &self,
+        req: Request<rpc::ExportModpackRequest>,
+    ) -> Result<Response<Self::ExportModpackStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone()
+            .export_modpack(msg.server_id, msg.path, msg.include_settings, tx)
+            .await;
+
+        respond(rx)
+    }
+
+    async fn apply_modpack(
+        &self,
+        req: Request<rpc::ApplyModpackRequest>,
+    ) -> Result<Response<Self::ApplyModpackStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone()
+            .apply_modpack(msg.server_id, msg.path, msg.override_policy, tx)
+            .await;
+
+        respond(rx)
+    }
+
+    async fn sync_mods(&self, req: Request<rpc::SyncModsRequest>) -> Result<Response<Self::SyncModsStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = req.into_inner();
+        self.clone()
+            .sync_mods(
+                msg.source_server_id,
+                msg.target_server_ids,
+                msg.dry_run,
+                msg.override_policy,
+                tx,
+            )
+            .await;
+
+        respond(rx)
+    }
+
+    async fn revert_to_last_known_good(
+        &self,
+        req: Request<rpc::RevertToLastKnownGoodRequest>,
+    ) -> Result<Response<Self::RevertToLastKnownGoodStream>, Status> {
+        log_rpc_request(&req);
+        let caller = self.authorize(&req, Scope::ManageMods).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = self.address(req).await?;
+        self.clone()
+            .revert_to_last_known_good(msg.server_id, caller, msg.confirmation_token, tx)
+            .await;
+
+        respond(rx)
+    }
+
+    async fn remove_mod(&self, req: Request<rpc::RemoveModRequest>) -> Result<Response<rpc::ModRemoval>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+
+        let msg = self.address(req).await?;
+        let version = msg.mod_version.map(HumanVersion::from);
+        map_to_response(
+            self.remove_mod(msg.server_id, &msg.mod_name, version, msg.cascade, msg.dry_run)
+                .await,
+        )
+    }
+
+    async fn save_mod_preset(&self, req: Request<rpc::SaveModPresetRequest>) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.save_mod_preset(msg.server_id, msg.name).await)
+    }
+
+    async fn list_mod_presets(
+        &self,
+        req: Request<rpc::ListModPresetsRequest>,
+    ) -> Result<Response<rpc::ModPresetList>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        let msg = self.address(req).await?;
+        map_to_response(self.list_mod_presets(msg.server_id).await)
+    }
+
+    async fn apply_mod_preset(
+        &self,
+        req: Request<rpc::ApplyModPresetRequest>,
+    ) -> Result<Response<rpc::ModPresetApplication>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+        // restarting stops the server, so it needs the same scope as stopping it directly
+        if req.get_ref().restart {
+            self.authorize(&req, Scope::ControlServer).await?;
+        }
+
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.apply_mod_preset(msg.server_id, &msg.name, msg.restart, msg.grace_period)
+                .await,
+        )
+    }
+
+    async fn remove_mod_preset(
+        &self,
+        req: Request<rpc::RemoveModPresetRequest>,
+    ) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.remove_mod_preset(msg.server_id, msg.name).await)
+    }
+
+    async fn verify_mods(
+        &self,
+        req: Request<rpc::VerifyModsRequest>,
+    ) -> Result<Response<Self::VerifyModsStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.verify_mods(msg.server_id, msg.analyze_content, msg.verify_checksums)
+                .await,
+        )
+    }
+
+    async fn list_mods(&self, req: Request<rpc::ListModsRequest>) -> Result<Response<Self::ListModsStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        let msg = self.address(req).await?;
+        map_to_response(self.list_mods(msg).await)
+    }
+
+    async fn search_mods(
+        &self,
+        req: Request<rpc::SearchModsRequest>,
+    ) -> Result<Response<rpc::ModSearchResults>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        map_to_response(self.search_mods(req.into_inner()).await)
+    }
+
+    async fn get_portal_mod(&self, req: Request<rpc::GetPortalModRequest>) -> Result<Response<rpc::PortalMod>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        map_to_response(self.get_portal_mod(req.into_inner().name).await)
+    }
+
+    async fn download_portal_mod(
+        &self,
+        req: Request<rpc::DownloadPortalModRequest>,
+    ) -> Result<Response<Self::DownloadPortalModStream>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+        let (tx, rx) = self.progress_channel();
+
+        let msg = req.into_inner();
+        self.clone().download_portal_mod(msg.name, msg.version, tx).await;
+
+        respond(rx)
+    }
+
+    async fn parse_version_requirement(
+        &self,
+        req: Request<rpc::ParseVersionRequirementRequest>,
+    ) -> Result<Response<rpc::VersionRequirement>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        map_to_response(self.parse_version_requirement(&req.into_inner().requirement))
+    }
+
+    async fn parse_dependency_string(
+        &self,
+        req: Request<rpc::ParseDependencyStringRequest>,
+    ) -> Result<Response<rpc::Dependency>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        map_to_response(self.parse_dependency_string(&req.into_inner().dependency))
+    }
+
+    async fn get_mod_policy(&self, req: Request<rpc::GetModPolicyRequest>) -> Result<Response<rpc::ModPolicy>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.get_mod_policy(msg.server_id).await)
+    }
+
+    async fn set_mod_policy(&self, req: Request<rpc::SetModPolicyRequest>) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.set_mod_policy(msg.server_id, msg.policy).await)
+    }
+
+    async fn set_mod_update_policy(
+        &self,
+        req: Request<rpc::SetModUpdatePolicyRequest>,
+    ) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.set_mod_update_policy(msg.server_id, &msg.mod_name, msg.policy)
+                .await,
+        )
+    }
+
+    async fn get_portal_credentials(
+        &self,
+        req: Request<rpc::GetPortalCredentialsRequest>,
+    ) -> Result<Response<rpc::PortalCredentials>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.get_portal_credentials(msg.server_id).await)
+    }
+
+    async fn set_portal_credentials(
+        &self,
+        req: Request<rpc::SetPortalCredentialsRequest>,
+    ) -> Result<Response<rpc::Empty>, Status> {
+        // the request isn't logged as usual since it contains the token
+        debug!(
+            "RPC request: set server ID {}'s portal credentials",
+            req.get_ref().server_id
+        );
+        self.authorize(&req, Scope::ManageMods).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.set_portal_credentials(msg.server_id, msg.credentials).await)
+    }
+
+    async fn get_server_settings(
+        &self,
+        req: Request<rpc::GetServerSettingsRequest>,
+    ) -> Result<Response<rpc::ServerSettings>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.get_server_settings(msg.server_id, msg.conflicting_file).await)
+    }
+
+    async fn get_raw_server_files(
+        &self,
+        req: Request<rpc::GetRawServerFilesRequest>,
+    ) -> Result<Response<rpc::RawServerFiles>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.get_raw_server_files(msg.server_id, msg.kinds).await)
+    }
+
+    async fn resolve_settings_conflict(
+        &self,
+        req: Request<rpc::ResolveSettingsConflictRequest>,
+    ) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageSettings).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.resolve_settings_conflict(msg.server_id, msg.prefer).await)
+    }
+
+    async fn get_mod_update_schedule(
+        &self,
+        req: Request<rpc::GetModUpdateScheduleRequest>,
+    ) -> Result<Response<rpc::ModUpdateSchedule>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        let msg = self.address(req).await?;
+        map_to_response(self.get_mod_update_schedule(msg.server_id).await)
+    }
+
+    async fn set_mod_update_schedule(
+        &self,
+        req: Request<rpc::SetModUpdateScheduleRequest>,
+    ) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageMods).await?;
 
-        Ok(rpc_server_settings)
+        let msg = self.address(req).await?;
+        map_to_response(self.set_mod_update_schedule(msg.server_id, msg.expression).await)
     }
 
-    /// Sets a given game instance's server settings.
     async fn set_server_settings(
         &self,
-        server_id: GameStoreId,
-        settings: Option<rpc::ServerSettings>,
-    ) -> anyhow::Result<()> {
-        self.assert_instance_status(instance_status::Status::Running).await?;
-
-        let mut games = self.games.lock().await;
-        let game = find_game(server_id, &mut games).await?;
-        let server_settings = if let Some(settings) = settings {
-            info!("Updating server ID {}'s settings", server_id);
-            factorio::settings::ServerSettings::from_rpc_format(&settings)?
-        } else {
-            info!("Resetting server ID {}'s settings to default", server_id);
-            factorio::settings::ServerSettings::default()
-        };
-
-        debug!("{:?}", server_settings);
-        *game.settings_mut() = server_settings;
+        req: Request<rpc::SetServerSettingsRequest>,
+    ) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageSettings).await?;
 
-        Ok(())
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.set_server_settings(msg.server_id, msg.settings, msg.persist, &msg.etag)
+                .await,
+        )
     }
 
-    /// Runs a given game instance.
-    async fn run_server(&self, server_id: GameStoreId) -> anyhow::Result<()> {
-        self.assert_instance_status(instance_status::Status::Running).await?;
-
-        let mut games = self.games.lock().await;
-        let game = find_game(server_id, &mut games).await?;
+    async fn generate_game_password(
+        &self,
+        req: Request<rpc::GenerateGamePasswordRequest>,
+    ) -> Result<Response<rpc::GeneratedPassword>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageSettings).await?;
 
-        if let Err(e) = game.run().await {
-            error!("Server ID {} failed to run: {}", server_id, e);
-            Err(e)
-        } else {
-            info!("Server ID {} starting", server_id);
-            Ok(())
-        }
+        let msg = self.address(req).await?;
+        map_to_response(self.generate_game_password(msg.server_id).await)
     }
 
-    /// Sends a command to a given game instance.
-    async fn send_server_command(
+    async fn apply_server_settings(
         &self,
-        server_id: GameStoreId,
-        command: i32,
-        arguments: Vec<String>,
-    ) -> anyhow::Result<()> {
-        self.assert_instance_status(instance_status::Status::Running).await?;
+        req: Request<rpc::ApplyServerSettingsRequest>,
+    ) -> Result<Response<rpc::ApplyServerSettingsResponse>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ManageSettings).await?;
 
-        let mut games = self.games.lock().await;
-        let game = find_game(server_id, &mut games).await?;
-        let command = match command {
-            0 => send_command_request::Command::Raw,
-            i => return Err(RpcError::NoSuchCommand(i).into()),
-        };
+        let msg = self.address(req).await?;
+        map_to_response(self.apply_server_settings(msg.server_id, msg.dry_run).await)
+    }
 
-        game.send_command(command, arguments).await?;
+    async fn run_server(&self, req: Request<rpc::RunServerRequest>) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ControlServer).await?;
 
-        Ok(())
+        let msg = self.address(req).await?;
+        map_to_response(self.run_server(msg.server_id, &msg.idempotency_key).await)
     }
 
-    /// Sends a command to a given game instance.
-    async fn get_server_status(&self, server_id: GameStoreId) -> anyhow::Result<ServerStatus> {
-        self.assert_instance_status(instance_status::Status::Running).await?;
-
-        let mut games = self.games.lock().await;
-        let game = find_game(server_id, &mut games).await?;
+    async fn stop_server(&self, req: Request<rpc::StopServerRequest>) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ControlServer).await?;
 
-        Ok(game.status().await)
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.stop_server(msg.server_id, msg.grace_period, msg.restart, &msg.idempotency_key)
+                .await,
+        )
     }
-}
 
-#[tonic::async_trait]
-impl mod_rpc_server::ModRpc for Modtorio {
-    type ImportGameStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
-    type UpdateStoreStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
-    type InstallModStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
-    type UpdateModsStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
-    type EnsureModDependenciesStream = mpsc::Receiver<Result<rpc::Progress, Status>>;
+    async fn send_server_command(&self, req: Request<rpc::SendCommandRequest>) -> Result<Response<rpc::Empty>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ControlServer).await?;
 
-    async fn get_version_information(
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.send_server_command(msg.server_id, msg.command, msg.arguments, &msg.idempotency_key)
+                .await,
+        )
+    }
+
+    async fn chat_bridge(
         &self,
-        req: Request<rpc::Empty>,
-    ) -> Result<Response<rpc::VersionInformation>, Status> {
+        req: Request<Streaming<rpc::ChatBridgeRequest>>,
+    ) -> Result<Response<Self::ChatBridgeStream>, Status> {
         log_rpc_request(&req);
+        self.authorize(&req, Scope::ControlServer).await?;
 
-        respond(rpc::VersionInformation {
-            version: Some((*HVER_VERSION).into()),
-            protocol_version: Some(
-                rpc::VERSION
-                    .parse::<HumanVersion>()
-                    .expect("failed to parse RPC protocol buffer specification version as HumanVersion")
-                    .into(),
-            ),
-        })
+        respond(self.clone().chat_bridge(req.into_inner()))
     }
 
-    async fn get_instance_status(&self, req: Request<rpc::Empty>) -> Result<Response<rpc::InstanceStatus>, Status> {
+    async fn get_server_status(
+        &self,
+        req: Request<rpc::ServerStatusRequest>,
+    ) -> Result<Response<rpc::ServerStatus>, Status> {
         log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
 
-        let uptime = self.get_uptime().await;
-        let servers = self.get_rpc_servers().await;
-        let instance_status = self.get_instance_status().await;
-
-        respond(rpc::InstanceStatus {
-            uptime: uptime.num_seconds(),
-            servers,
-            instance_status: instance_status.into(),
-        })
+        let msg = self.address(req).await?;
+        map_to_response(self.get_server_status(msg.server_id).await)
     }
 
-    // I tried to macro these repetitive functions into DRYness but the tonic::async_trait macro messes with them in
-    // some funky way that a macro_rules! didn't work as I'd hoped and I just couldn't bother to figure it out
-    async fn import_game(&self, req: Request<rpc::ImportRequest>) -> Result<Response<Self::ImportGameStream>, Status> {
+    async fn save_map(&self, req: Request<rpc::SaveMapRequest>) -> Result<Response<rpc::SavedMap>, Status> {
         log_rpc_request(&req);
-        let (tx, rx) = channel();
-
-        let msg = req.into_inner();
-        self.clone().import_game(msg.path, tx).await;
+        self.authorize(&req, Scope::ControlServer).await?;
 
-        respond(rx)
+        let msg = self.address(req).await?;
+        map_to_response(self.save_map(msg.server_id, msg.name).await)
     }
 
-    async fn update_store(
+    async fn prune_autosaves(
         &self,
-        req: Request<rpc::UpdateStoreRequest>,
-    ) -> Result<Response<Self::UpdateStoreStream>, Status> {
+        req: Request<rpc::PruneAutosavesRequest>,
+    ) -> Result<Response<rpc::AutosavePruning>, Status> {
         log_rpc_request(&req);
-        let (tx, rx) = channel();
+        self.authorize(&req, Scope::ControlServer).await?;
 
-        let msg = req.into_inner();
-        self.clone().update_store(msg.server_id, tx).await;
+        let msg = self.address(req).await?;
+        map_to_response(self.prune_autosaves(msg.server_id, msg.dry_run).await)
+    }
 
-        respond(rx)
+    async fn run_server_tool(&self, req: Request<rpc::RunToolRequest>) -> Result<Response<rpc::ToolOutput>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::ControlServer).await?;
+        let msg = self.address(req).await?;
+        map_to_response(self.run_server_tool(msg).await)
     }
 
-    async fn install_mod(
+    async fn inspect_prototypes(
         &self,
-        req: Request<rpc::InstallModRequest>,
-    ) -> Result<Response<Self::InstallModStream>, Status> {
+        req: Request<rpc::InspectPrototypesRequest>,
+    ) -> Result<Response<rpc::PrototypeInspection>, Status> {
         log_rpc_request(&req);
-        let (tx, rx) = channel();
-
-        let msg = req.into_inner();
-        let version = msg.mod_version.map(HumanVersion::from);
-        self.clone().install_mod(msg.server_id, msg.mod_name, version, tx).await;
+        self.authorize(&req, Scope::Read).await?;
 
-        respond(rx)
+        let msg = self.address(req).await?;
+        map_to_response(self.inspect_prototypes(msg.server_id).await)
     }
 
-    async fn update_mods(
+    async fn import_map_exchange_string(
         &self,
-        req: Request<rpc::UpdateModsRequest>,
-    ) -> Result<Response<Self::UpdateModsStream>, Status> {
+        req: Request<rpc::ImportMapExchangeStringRequest>,
+    ) -> Result<Response<rpc::Empty>, Status> {
         log_rpc_request(&req);
-        let (tx, rx) = channel();
+        self.authorize(&req, Scope::ManageSettings).await?;
 
-        let msg = req.into_inner();
-        self.clone().update_mods(msg.server_id, tx).await;
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.import_map_exchange_string(msg.server_id, &msg.exchange_string)
+                .await,
+        )
+    }
 
-        respond(rx)
+    async fn export_map_exchange_string(
+        &self,
+        req: Request<rpc::ExportMapExchangeStringRequest>,
+    ) -> Result<Response<rpc::MapExchangeString>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+
+        let msg = self.address(req).await?;
+        map_to_response(self.export_map_exchange_string(msg.server_id).await)
     }
 
-    async fn ensure_mod_dependencies(
+    async fn get_offenders(
         &self,
-        req: Request<rpc::EnsureModDependenciesRequest>,
-    ) -> Result<Response<Self::EnsureModDependenciesStream>, Status> {
+        req: Request<rpc::GetOffendersRequest>,
+    ) -> Result<Response<rpc::OffenderList>, Status> {
         log_rpc_request(&req);
-        let (tx, rx) = channel();
+        self.authorize(&req, Scope::Read).await?;
 
-        let msg = req.into_inner();
-        self.clone().ensure_mod_dependencies(msg.server_id, tx).await;
+        let msg = self.address(req).await?;
+        map_to_response(self.get_offenders(msg.server_id).await)
+    }
 
-        respond(rx)
+    async fn get_players(&self, req: Request<rpc::GetPlayersRequest>) -> Result<Response<rpc::PlayerList>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        let msg = self.address(req).await?;
+        map_to_response(self.get_players(msg.server_id).await)
     }
 
-    async fn get_server_settings(
+    async fn get_player_count_history(
         &self,
-        req: Request<rpc::GetServerSettingsRequest>,
-    ) -> Result<Response<rpc::ServerSettings>, Status> {
+        req: Request<rpc::PlayerCountHistoryRequest>,
+    ) -> Result<Response<rpc::PlayerCountHistory>, Status> {
         log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.get_player_count_history(msg.server_id, msg.range_start, msg.range_end, msg.resolution)
+                .await,
+        )
+    }
 
-        let msg = req.into_inner();
-        map_to_response(self.get_server_settings(msg.server_id).await)
+    async fn get_server_events(
+        &self,
+        req: Request<rpc::GetServerEventsRequest>,
+    ) -> Result<Response<rpc::ServerEventPage>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        let msg = self.address(req).await?;
+        map_to_response(
+            self.get_server_events(
+                msg.server_id,
+                msg.range_start,
+                msg.range_end,
+                msg.kinds,
+                msg.page_size,
+                &msg.page_token,
+            )
+            .await,
+        )
     }
 
-    async fn set_server_settings(
+    async fn get_portal_connectivity(
         &self,
-        req: Request<rpc::SetServerSettingsRequest>,
-    ) -> Result<Response<rpc::Empty>, Status> {
+        req: Request<rpc::PortalConnectivityRequest>,
+    ) -> Result<Response<rpc::PortalConnectivity>, Status> {
         log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
 
         let msg = req.into_inner();
-        map_to_response(self.set_server_settings(msg.server_id, msg.settings).await)
+        map_to_response(self.get_portal_connectivity(msg.refresh).await)
     }
 
-    async fn run_server(&self, req: Request<rpc::RunServerRequest>) -> Result<Response<rpc::Empty>, Status> {
+    async fn set_motd(&self, req: Request<rpc::SetMotdRequest>) -> Result<Response<rpc::Empty>, Status> {
         log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
 
         let msg = req.into_inner();
-        map_to_response(self.run_server(msg.server_id).await)
+        map_to_response(self.set_motd(msg.message, msg.author).await)
     }
 
-    async fn send_server_command(&self, req: Request<rpc::SendCommandRequest>) -> Result<Response<rpc::Empty>, Status> {
+    async fn get_feature_flags(&self, req: Request<rpc::Empty>) -> Result<Response<rpc::FeatureFlags>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        map_to_response(self.get_feature_flags().await)
+    }
+
+    async fn set_feature_flag(&self, req: Request<rpc::SetFeatureFlagRequest>) -> Result<Response<rpc::Empty>, Status> {
         log_rpc_request(&req);
+        self.authorize(&req, Scope::Admin).await?;
 
         let msg = req.into_inner();
-        map_to_response(
-            self.send_server_command(msg.server_id, msg.command, msg.arguments)
-                .await,
-        )
+        map_to_response(self.set_feature_flag(msg.name, msg.enabled).await)
+    }
+}
+
+/// The read-only status service. Every method reads snapshots of the instance's state, so they never wait on the
+/// control operations in [`ModRpc`](mod_rpc_server::ModRpc).
+#[tonic::async_trait]
+impl mod_rpc_status_server::ModRpcStatus for Modtorio {
+    async fn get_version_information(
+        &self,
+        req: Request<rpc::Empty>,
+    ) -> Result<Response<rpc::VersionInformation>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        respond(self.get_version_information())
+    }
+
+    async fn get_instance_status(&self, req: Request<rpc::Empty>) -> Result<Response<rpc::InstanceStatus>, Status> {
+        log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
+        respond(self.get_rpc_instance_status().await)
     }
 
     async fn get_server_status(
@@ -693,19 +4487,13 @@ impl mod_rpc_server::ModRpc for Modtorio {
         req: Request<rpc::ServerStatusRequest>,
     ) -> Result<Response<rpc::ServerStatus>, Status> {
         log_rpc_request(&req);
+        self.authorize(&req, Scope::Read).await?;
 
-        let msg = req.into_inner();
+        let msg = self.address(req).await?;
         map_to_response(self.get_server_status(msg.server_id).await)
     }
 }
 
-/// Creates a new bounded channel and returns the receiver and sender, the sender wrapped in an
-/// Arc<Mutex>.
-fn channel<T>() -> (mpsc::Sender<T>, mpsc::Receiver<T>) {
-    let (tx, rx) = mpsc::channel(64);
-    (tx, rx)
-}
-
 /// Logs a given RPC request.
 fn log_rpc_request<T: std::fmt::Debug>(request: &Request<T>) {
     debug!(
@@ -731,17 +4519,193 @@ async fn send_status(prog_tx: &AsyncProgressChannel, status: AsyncProgressResult
     }
 }
 
+/// Returns `RpcError::ModsUnmanaged` if Modtorio doesn't manage the mods of a given game (identified by `server_id`).
+fn assert_mods_managed(server_id: GameStoreId, game: &Factorio) -> Result<(), RpcError> {
+    if game.settings().management.mods {
+        Ok(())
+    } else {
+        Err(RpcError::ModsUnmanaged(server_id))
+    }
+}
+
+/// Returns whether a given name is a plain file name that stays within the directory it's joined to, i.e. it has no
+/// path separators and isn't `.` or `..`. Absolute paths have separators so they're never plain names.
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains('/') && !name.contains('\\') && name != "." && name != ".."
+}
+
+/// Returns a summary of what removing a given game instance does, for confirming the removal.
+async fn removal_impact(server_id: GameStoreId, game: &Factorio, delete_files: bool) -> anyhow::Result<String> {
+    let mut impact = format!(
+        "Server ID {} in {} will no longer be managed and everything stored for it will be removed",
+        server_id,
+        game.root().display()
+    );
+    match game.status().await.game_status() {
+        ExecutionStatus::Shutdown | ExecutionStatus::Crashed => {}
+        status => impact.push_str(&format!(". It is {:?} and will be shut down", status)),
+    }
+    if delete_files {
+        impact.push_str(&format!(
+            ". Its mods directory {} will be deleted with its {} mods",
+            game.mods().directory().display(),
+            game.mods().count()
+        ));
+    }
+
+    Ok(impact)
+}
+
+/// Returns a summary of what restoring a given game instance's backup with a given name from a given backup directory
+/// does, for confirming the restore.
+async fn restore_impact(
+    directory: &Path,
+    server_id: GameStoreId,
+    name: &str,
+    sources: &backup::Sources,
+) -> anyhow::Result<String> {
+    let overwritten = backup::overwritten_files(directory, server_id, name, sources).await?;
+    Ok(format!(
+        "Restoring backup {} overwrites {} files of server ID {}: {}",
+        name,
+        overwritten.len(),
+        server_id,
+        overwritten
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+/// Returns a summary of what reverting a given game instance's mods to a given last known good modpack recorded at a
+/// given time does, for confirming the revert.
+async fn revert_impact(
+    server_id: GameStoreId,
+    modpack: &Modpack,
+    recorded_at: DateTime<Utc>,
+    game: &Factorio,
+) -> anyhow::Result<String> {
+    let changes = modpack.plan(game.mods()).await?;
+    Ok(format!(
+        "Reverting server ID {} to the last known good mods from {} makes {} mod changes: {}",
+        server_id,
+        recorded_at,
+        changes.len(),
+        changes.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    ))
+}
+
+/// Waits until a given game is no longer starting, and returns its execution status after that.
+async fn wait_for_start(handle: &StatusHandle) -> ExecutionStatus {
+    loop {
+        let status = handle.status().await.game_status();
+        if status != ExecutionStatus::Starting {
+            return status;
+        }
+
+        time::delay_for(AUTOSTART_POLL_INTERVAL).await;
+    }
+}
+
+/// Carries out a given graceful action on a given game after warning its players and waiting for a given grace
+/// period. The game's pending action is cleared once the action is done, or has failed.
+async fn carry_out_graceful_action(
+    server_id: GameStoreId,
+    game: Arc<RwLock<Factorio>>,
+    action: GracefulAction,
+    grace_period: Duration,
+) {
+    if grace_period > Duration::from_secs(0) {
+        let verb = match action {
+            GracefulAction::Stop => "stopping",
+            GracefulAction::Restart => "restarting",
+        };
+        let warning = format!("Server {} in {} seconds", verb, grace_period.as_secs());
+        if let Err(e) = game
+            .read()
+            .await
+            .send_command(send_command_request::Command::Say, vec![warning])
+            .await
+        {
+            warn!(
+                "Failed to warn game ID {}'s players of the {:?}: {}",
+                server_id, action, e
+            );
+        }
+
+        time::delay_for(grace_period).await;
+    }
+
+    let result = async {
+        {
+            let game = game.read().await;
+            if game.status().await.game_status() == ExecutionStatus::Running {
+                game.send_command(send_command_request::Command::Quit, Vec::new())
+                    .await?;
+                game.wait_for_shutdown().await;
+            }
+        }
+
+        if action == GracefulAction::Restart {
+            let mut game = game.write().await;
+            game.flush_settings_to_file().await?;
+            game.run().await?;
+        }
+
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => info!("Game ID {} {:?} done", server_id, action),
+        Err(e) => error!("Game ID {} {:?} failed: {}", server_id, action, e),
+    }
+
+    game.read().await.clear_pending_action().await;
+}
+
+/// Kills a given game without letting it save or shut down cleanly, and starts it again.
+async fn force_restart(server_id: GameStoreId, game: Arc<RwLock<Factorio>>) {
+    warn!("Forcibly restarting game ID {}", server_id);
+
+    let result = async {
+        {
+            let game = game.read().await;
+            game.kill().await?;
+            game.wait_for_shutdown().await;
+        }
+
+        let mut game = game.write().await;
+        game.flush_settings_to_file().await?;
+        game.run().await
+    }
+    .await;
+
+    match result {
+        Ok(()) => info!("Game ID {} forcibly restarted", server_id),
+        Err(e) => error!("Forcibly restarting game ID {} failed: {}", server_id, e),
+    }
+}
+
+/// Logs a summary of a given game's pruned autosaves, if any were pruned.
+fn log_autosave_pruning(server_id: GameStoreId, pruning: &AutosavePruning) {
+    if !pruning.deleted().is_empty() {
+        info!(
+            "Pruned {} autosaves of game ID {}, reclaiming {} bytes",
+            pruning.deleted().len(),
+            server_id,
+            pruning.bytes_reclaimed()
+        );
+    }
+}
+
 /// Sends an error status update to a given channel, returning a boolean whether the sending succeeded or not.
 async fn send_error_status<T>(prog_tx: &AsyncProgressChannel, error: T) -> bool
 where
     T: Into<anyhow::Error>,
 {
-    let error = error.into();
-    if let Some(rpc_error) = error.downcast_ref::<RpcError>() {
-        send_status(&prog_tx, Err(rpc_error.into())).await
-    } else {
-        send_status(&prog_tx, Err(RpcError::from(error).into())).await
-    }
+    send_status(&prog_tx, Err(error_status(error.into()))).await
 }
 
 /// Asynchronously returns the unit type after the current process receives a SIGINT signal (Ctrl-C).
@@ -763,15 +4727,39 @@ fn respond<T: std::fmt::Debug>(message: T) -> Result<Response<T>, Status> {
 
 /// Creates a new RPC error respose, logs it and returns it.
 fn respond_err<T>(error: anyhow::Error) -> Result<Response<T>, Status> {
-    error!("RPC request failed: {}", error);
+    log_rpc_error("RPC request failed", &error);
+    Err(error_status(error))
+}
+
+/// Logs a given error an RPC request failed with, after a given context. The confirmation a destructive request
+/// requires before it's executed is expected and only logged at debug level.
+fn log_rpc_error(context: &str, error: &anyhow::Error) {
+    if let Some(RpcError::ConfirmationRequired(confirmation)) = error.downcast_ref() {
+        debug!("RPC request requires confirmation: {}", confirmation);
+    } else {
+        error!("{}: {}", context, error);
+    }
+}
 
+/// Returns the RPC status corresponding to a given error.
+fn error_status(error: anyhow::Error) -> Status {
     if let Some(rpc_error) = error.downcast_ref::<RpcError>() {
-        Err(rpc_error.into())
+        rpc_error.into()
+    } else if is_offline_mode(&error) {
+        RpcError::OfflineMode.into()
+    } else if matches!(error.downcast_ref(), Some(StoreError::ReadOnly)) {
+        RpcError::StoreReadOnly.into()
     } else {
-        Err(RpcError::Internal(error).into())
+        RpcError::Internal(error).into()
     }
 }
 
+/// Returns whether a given error was caused by a network call being refused in offline mode.
+fn is_offline_mode(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref(), Some(ModPortalError::OfflineMode))
+        || matches!(error.downcast_ref(), Some(InstallerError::OfflineMode))
+}
+
 /// Maps a given `anyhow::Result` into an RPC response.
 fn map_to_response<TResult, TResponse>(result: anyhow::Result<TResult>) -> Result<Response<TResponse>, Status>
 where
@@ -783,17 +4771,3 @@ where
         Err(e) => respond_err(e),
     }
 }
-
-/// Finds and returns a mutable reference to a game based on its store ID, or returns `RpcError::NoSuchGame` if the game
-/// isn't found.
-async fn find_game(server_id: GameStoreId, games: &mut Vec<Factorio>) -> anyhow::Result<&mut Factorio> {
-    for g in games.iter_mut() {
-        if let Some(id) = g.store_id_option().await {
-            if id == server_id {
-                return Ok(g);
-            }
-        }
-    }
-
-    Err(RpcError::NoSuchGame(server_id).into())
-}
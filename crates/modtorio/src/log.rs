@@ -1,25 +1,50 @@
 //! Provides functionality to set up a logging facade and print logging information for the program.
 
-use crate::config::Config;
+mod rotation;
+
+use crate::{
+    config::Config,
+    util::{
+        correlation::{self, Context},
+        LogFormat,
+    },
+};
+use anyhow::Context as _;
+use chrono::{DateTime, SecondsFormat, Utc};
 use fern::Dispatch;
+use log::Record;
 pub use log::{debug, error, info, trace, warn};
-use std::{thread, time::Instant};
+pub use rotation::RotatingFile;
+use serde_json::{Map, Value};
+use std::{io::Write, thread, time::Instant};
 
-/// Sets up the logging facade.
+/// Sets up the logging facade. The log is written to stdout and, if configured, to a rotated log file, in the
+/// configured format.
 pub fn setup_logging(config: &Config) -> anyhow::Result<()> {
     let start = Instant::now();
-    Dispatch::new()
-        .format(move |out, msg, record| {
-            out.finish(format_args!(
-                "{: >11.3} {: >5} [{:0>3?}] [{}] {}",
-                // "[{} UTC] [{}] {}",
-                // chrono::Utc::now().format(time_format),
-                start.elapsed().as_secs_f32(),
-                record.level(),
-                thread::current().id().as_u64(),
-                record.target(),
-                msg
-            ))
+    let format = config.log_format();
+    let mut dispatch = Dispatch::new()
+        .format(move |out, msg, record| match format {
+            LogFormat::Text => {
+                let correlation_id = correlation::current()
+                    .map(|id| format!(" [{}]", id))
+                    .unwrap_or_default();
+                out.finish(format_args!(
+                    "{: >11.3} {: >5} [{:0>3?}] [{}]{} {}",
+                    // "[{} UTC] [{}] {}",
+                    // chrono::Utc::now().format(time_format),
+                    start.elapsed().as_secs_f32(),
+                    record.level(),
+                    thread::current().id().as_u64(),
+                    record.target(),
+                    correlation_id,
+                    msg
+                ))
+            }
+            LogFormat::Json => out.finish(format_args!(
+                "{}",
+                json_line(record, Utc::now(), correlation::context())
+            )),
         })
         .level(config.log_level().to_level_filter())
         .level_for("hyper", log::LevelFilter::Info)
@@ -29,7 +54,94 @@ pub fn setup_logging(config: &Config) -> anyhow::Result<()> {
         .level_for("h2", log::LevelFilter::Info)
         .level_for("tokio_util", log::LevelFilter::Info)
         .level_for("want", log::LevelFilter::Info)
-        .chain(std::io::stdout())
-        .apply()?;
+        .chain(std::io::stdout());
+
+    if let Some(path) = config.log_file() {
+        let file = RotatingFile::open(
+            path,
+            config.log_max_size(),
+            config.log_rotate_interval(),
+            config.log_retention(),
+            config.log_compress(),
+        )
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+        dispatch = dispatch.chain(Box::new(file) as Box<dyn Write + Send>);
+    }
+
+    dispatch.apply()?;
     Ok(())
 }
+
+/// Returns a given log record logged at a given time as a single-line JSON object. The correlation ID, RPC method and
+/// server ID of a given context are included as their own fields when they're known.
+fn json_line(record: &Record, logged_at: DateTime<Utc>, context: Option<Context>) -> String {
+    let mut line = Map::new();
+    line.insert(
+        String::from("timestamp"),
+        logged_at.to_rfc3339_opts(SecondsFormat::Millis, true).into(),
+    );
+    line.insert(String::from("level"), record.level().to_string().into());
+    line.insert(String::from("target"), record.target().into());
+    line.insert(String::from("thread"), thread::current().id().as_u64().get().into());
+    line.insert(String::from("message"), record.args().to_string().into());
+
+    if let Some(context) = context {
+        if let Some(id) = context.id {
+            line.insert(String::from("request_id"), id.into());
+        }
+        if let Some(method) = context.method {
+            line.insert(String::from("rpc_method"), method.into());
+        }
+        if let Some(server_id) = context.server_id {
+            line.insert(String::from("server_id"), server_id.into());
+        }
+    }
+
+    Value::Object(line).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use log::Level;
+
+    #[test]
+    fn json() {
+        let logged_at = Utc.ymd(2020, 9, 6).and_hms_milli(19, 30, 0, 250);
+        let context = Context {
+            id: Some(String::from("0123456789abcdef")),
+            method: Some(String::from("/mod_rpc.ModRpc/RunServer")),
+            server_id: Some(3),
+        };
+
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("Game ID {} \"started\"", 3))
+                .level(Level::Info)
+                .target("modtorio")
+                .build(),
+            logged_at,
+            Some(context),
+        );
+        assert!(!line.contains('\n'));
+
+        let line: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(line["timestamp"], "2020-09-06T19:30:00.250Z");
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["target"], "modtorio");
+        assert_eq!(line["message"], "Game ID 3 \"started\"");
+        assert_eq!(line["request_id"], "0123456789abcdef");
+        assert_eq!(line["rpc_method"], "/mod_rpc.ModRpc/RunServer");
+        assert_eq!(line["server_id"], 3);
+
+        let line = json_line(
+            &Record::builder().args(format_args!("Idle")).level(Level::Debug).build(),
+            logged_at,
+            None,
+        );
+        let line: Value = serde_json::from_str(&line).unwrap();
+        assert!(line.get("request_id").is_none());
+        assert!(line.get("server_id").is_none());
+    }
+}
@@ -0,0 +1,242 @@
+//! Provides the [`RotatingFile`](RotatingFile) object, a log file writer that rotates the file based on its size and
+//! age and keeps a bounded amount of the rotated files.
+
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+/// The extension appended to compressed rotated log files.
+const COMPRESSED_EXTENSION: &str = "gz";
+
+/// A log file writer that rotates the file once it grows too large or too old.
+///
+/// Rotated files are numbered from the newest (`<file>.1`) to the oldest (`<file>.<retention>`), optionally
+/// compressed with gzip (`<file>.1.gz`). Files past the retention count are removed. Rotation only happens between
+/// lines so a single line is never split between two files. Rotated files are compressed on a background thread so
+/// writing isn't held up meanwhile; the next rotation waits for the previous compression to finish.
+#[derive(Debug)]
+pub struct RotatingFile {
+    /// The path of the current log file.
+    path: PathBuf,
+    /// The current log file.
+    file: File,
+    /// The current log file's size in bytes.
+    size: u64,
+    /// When the current log file was started.
+    started_at: SystemTime,
+    /// Whether the last byte written ended a line.
+    at_line_start: bool,
+    /// The size in bytes the file may grow to before it's rotated.
+    max_size: Option<u64>,
+    /// The time after which the file is rotated.
+    rotate_interval: Option<Duration>,
+    /// The amount of rotated files kept.
+    retention: u32,
+    /// Whether rotated files are compressed.
+    compress: bool,
+    /// The background thread compressing the most recently rotated file, if one has been started.
+    compressing: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl RotatingFile {
+    /// Opens a given log file for appending, creating it if it doesn't exist. The file is rotated once it grows
+    /// larger than a given maximum size or older than a given interval, keeping a given amount of the rotated files.
+    pub fn open<P>(
+        path: P,
+        max_size: Option<u64>,
+        rotate_interval: Option<Duration>,
+        retention: u32,
+        compress: bool,
+    ) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let (file, size, started_at) = open_file(&path)?;
+        Ok(Self {
+            path,
+            file,
+            size,
+            started_at,
+            at_line_start: true,
+            max_size,
+            rotate_interval,
+            retention,
+            compress,
+            compressing: None,
+        })
+    }
+
+    /// Returns whether the current file should be rotated before writing a given amount of bytes to it.
+    fn should_rotate(&self, incoming: usize) -> bool {
+        if !self.at_line_start || self.size == 0 {
+            return false;
+        }
+
+        let too_large = self
+            .max_size
+            .map_or(false, |max_size| self.size + incoming as u64 > max_size);
+        let too_old = self.rotate_interval.map_or(false, |interval| {
+            self.started_at.elapsed().map_or(false, |elapsed| elapsed >= interval)
+        });
+        too_large || too_old
+    }
+
+    /// Rotates the current file: shifts the previously rotated files by one, removing the ones past the retention
+    /// count, moves the current file in their place and starts a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.wait_for_compression()?;
+
+        remove_if_exists(&self.rotated_path(self.retention.max(1), false))?;
+        remove_if_exists(&self.rotated_path(self.retention.max(1), true))?;
+        for index in (1..self.retention).rev() {
+            for compressed in &[false, true] {
+                let from = self.rotated_path(index, *compressed);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1, *compressed))?;
+                }
+            }
+        }
+
+        if self.retention == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let rotated = self.rotated_path(1, false);
+            fs::rename(&self.path, &rotated)?;
+            if self.compress {
+                let compressed = self.rotated_path(1, true);
+                self.compressing = Some(thread::spawn(move || compress_file(&rotated, &compressed)));
+            }
+        }
+
+        let (file, size, started_at) = open_file(&self.path)?;
+        self.file = file;
+        self.size = size;
+        self.started_at = started_at;
+        Ok(())
+    }
+
+    /// Waits for the background thread compressing the most recently rotated file to finish, if there is one. Returns
+    /// the error the compression failed with, if any.
+    fn wait_for_compression(&mut self) -> io::Result<()> {
+        match self.compressing.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::new(io::ErrorKind::Other, "log file compression panicked")),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the path of the rotated file with a given number.
+    fn rotated_path(&self, index: u32, compressed: bool) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        if compressed {
+            name.push(format!(".{}", COMPRESSED_EXTENSION));
+        }
+        PathBuf::from(name)
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written - 1] == b'\n';
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Opens a given file for appending. Returns the file, its current size and when it was started.
+fn open_file(path: &Path) -> io::Result<(File, u64, SystemTime)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    // the creation time isn't available on every platform and filesystem, in which case the file is considered to be
+    // started now
+    let started_at = metadata.created().unwrap_or_else(|_| SystemTime::now());
+    Ok((file, metadata.len(), started_at))
+}
+
+/// Compresses a given file into another with gzip and removes the original.
+fn compress_file(from: &Path, to: &Path) -> io::Result<()> {
+    let mut input = File::open(from)?;
+    let mut encoder = GzEncoder::new(File::create(to)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(from)
+}
+
+/// Removes a given file if it exists.
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn rotates_by_size_and_keeps_retention() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let path = dir.path().join("modtorio.log");
+        let mut file = RotatingFile::open(&path, Some(6), None, 2, false).expect("failed to open log file");
+
+        for line in &["first\n", "second\n", "third\n", "fourth\n"] {
+            // write each line in two parts like a formatted log record would be written, which mustn't rotate in
+            // between
+            let (start, end) = line.split_at(3);
+            file.write_all(start.as_bytes()).expect("failed to write");
+            file.write_all(end.as_bytes()).expect("failed to write");
+        }
+        file.flush().expect("failed to flush");
+
+        let read = |path: PathBuf| fs::read_to_string(path).expect("failed to read log file");
+        assert_eq!(read(path.clone()), "fourth\n");
+        assert_eq!(read(dir.path().join("modtorio.log.1")), "third\n");
+        assert_eq!(read(dir.path().join("modtorio.log.2")), "second\n");
+        assert!(!dir.path().join("modtorio.log.3").exists());
+    }
+
+    #[test]
+    fn compresses_rotated_files() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let path = dir.path().join("modtorio.log");
+        let mut file = RotatingFile::open(&path, Some(10), None, 1, true).expect("failed to open log file");
+
+        file.write_all(b"compressed\n").expect("failed to write");
+        file.write_all(b"current\n").expect("failed to write");
+        file.wait_for_compression().expect("failed to compress rotated file");
+
+        let compressed = File::open(dir.path().join("modtorio.log.1.gz")).expect("failed to open rotated file");
+        let mut contents = String::new();
+        GzDecoder::new(compressed)
+            .read_to_string(&mut contents)
+            .expect("failed to decompress rotated file");
+        assert_eq!(contents, "compressed\n");
+        assert!(!dir.path().join("modtorio.log.1").exists());
+    }
+}
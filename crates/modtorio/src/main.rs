@@ -10,7 +10,30 @@ const PORTAL_TOKEN_ENV_VARIABLE: &str = "MODTORIO_PORTAL_TOKEN";
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let opts = opts::Opts::get();
-    let store = store::Builder::from_location((&opts.store).into()).build().await?;
+
+    if let Some(opts::Subcommand::Doctor) = opts.subcommand {
+        if !doctor::run(&opts).await {
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(opts::Subcommand::Replay { log, unparsed_only }) = &opts.subcommand {
+        return replay::run(&opts, log, *unparsed_only);
+    }
+
+    if let Some(opts::Subcommand::Token(command)) = &opts.subcommand {
+        // token commands are meant to be run alongside the running daemon
+        let store = store::Builder::from_location((&opts.store).into()).build().await?;
+        return run_token_command(&store, command).await;
+    }
+
+    let store = store::Builder::from_location((&opts.store).into())
+        .exclusive(true)
+        .build()
+        .await?;
+
     let config = build_config(&opts, &store).await?;
 
     log::setup_logging(&config)?;
@@ -31,6 +54,33 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Runs a given action of the `token` subcommand with a given program `Store`.
+async fn run_token_command(store: &store::Store, command: &opts::TokenCommand) -> anyhow::Result<()> {
+    match command {
+        opts::TokenCommand::Add { name, scopes } => {
+            let token = auth::create_token(store, name.clone(), scopes).await?;
+            println!("Created token '{}'. Store it now, it can't be shown again:", name);
+            println!("{}", token);
+        }
+        opts::TokenCommand::List => {
+            let tokens = auth::list_tokens(store).await?;
+            for token in &tokens {
+                println!("{}", token);
+            }
+            println!("{} tokens", tokens.len());
+        }
+        opts::TokenCommand::Revoke { name } => {
+            if auth::revoke_token(store, name.clone()).await? {
+                println!("Revoked token '{}'", name);
+            } else {
+                println!("No token named '{}'", name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Builds a complete configuration object with given command-line Opts and a program Store.
 async fn build_config(opts: &opts::Opts, store: &store::Store) -> anyhow::Result<config::Config> {
     let mut builder = config::Builder::new();
@@ -68,33 +118,36 @@ where
 /// * `Field::PortalToken` from the variable whose name is in the constant `PORTAL_TOKEN_ENV_VARIABLE`
 async fn update_store_from_env(store: &store::Store) -> anyhow::Result<()> {
     store.begin_transaction()?;
-
-    for (key, value) in util::env::dump_map(APP_PREFIX) {
-        match key.as_str() {
-            PORTAL_USERNAME_ENV_VARIABLE => {
-                debug!("Got portal username env variable, updating store");
-                store
-                    .set_option(store::option::Value::new(
-                        store::option::Field::PortalUsername,
-                        Some(value),
-                    ))
-                    .await?
+    let result = async {
+        for (key, value) in util::env::dump_map(APP_PREFIX) {
+            match key.as_str() {
+                PORTAL_USERNAME_ENV_VARIABLE => {
+                    debug!("Got portal username env variable, updating store");
+                    store
+                        .set_option(store::option::Value::new(
+                            store::option::Field::PortalUsername,
+                            Some(value),
+                        ))
+                        .await?
+                }
+                PORTAL_TOKEN_ENV_VARIABLE => {
+                    debug!("Got portal token env variable, updating store");
+                    store
+                        .set_option(store::option::Value::new(
+                            store::option::Field::PortalToken,
+                            Some(value),
+                        ))
+                        .await?
+                }
+                _ => {}
             }
-            PORTAL_TOKEN_ENV_VARIABLE => {
-                debug!("Got portal token env variable, updating store");
-                store
-                    .set_option(store::option::Value::new(
-                        store::option::Field::PortalToken,
-                        Some(value),
-                    ))
-                    .await?
-            }
-            _ => {}
         }
+
+        Ok(())
     }
+    .await;
 
-    store.commit_transaction()?;
-    Ok(())
+    store.finish_transaction(result)
 }
 
 /// Logs the program's information.
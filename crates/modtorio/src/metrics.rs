@@ -0,0 +1,291 @@
+//! Provides [`RpcMetrics`](RpcMetrics), which records every RPC method's request count, error count, in-flight request
+//! count and latency histogram in memory, and [`Instrumented`](Instrumented), which wraps an RPC service to record
+//! them for every request to it.
+//!
+//! A request's latency is measured until its response begins. For streamed responses that's when the stream starts,
+//! and only errors returned before the stream starts are counted as errors. The requests are recorded by the RPC method
+//! they matched, and the requests to paths that match no method are all recorded together, so clients can't grow the
+//! recorded metrics by requesting arbitrary paths.
+//!
+//! The managed servers' metrics are exported for Prometheus by the [`prometheus`](prometheus) module.
+
+pub mod prometheus;
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tonic::{
+    body::BoxBody,
+    codegen::{http, BoxFuture, Service},
+    transport::{Body, NamedService},
+};
+
+/// The upper bounds of the latency histogram's buckets. Latencies above the last bound go to an extra bucket.
+const LATENCY_BUCKETS: [Duration; 8] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+];
+/// The gRPC response header that carries the status code of a response that failed before any message was sent.
+const GRPC_STATUS_HEADER: &str = "grpc-status";
+/// The gRPC status code for a successful response.
+const GRPC_STATUS_OK: &str = "0";
+/// The method the requests to paths that match no RPC method are recorded as.
+const UNMATCHED_METHOD: &str = "unmatched";
+
+/// The recorded metrics of a single RPC method.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct MethodMetrics {
+    /// How many requests have finished.
+    requests: u64,
+    /// How many of the finished requests failed.
+    errors: u64,
+    /// How many requests are currently being handled.
+    in_flight: u64,
+    /// The finished requests' combined latency.
+    total_latency: Duration,
+    /// The longest latency of a finished request.
+    max_latency: Duration,
+    /// How many finished requests fall into each latency bucket, with the extra unbounded bucket last.
+    latency_buckets: [u64; LATENCY_BUCKETS.len() + 1],
+}
+
+/// The recorded metrics of every RPC method that has been requested, keyed by the methods' paths.
+#[derive(Debug, Default)]
+pub struct RpcMetrics {
+    /// The metrics of each method.
+    methods: Mutex<BTreeMap<&'static str, MethodMetrics>>,
+}
+
+/// A request that is being handled. Records the request as finished when dropped, as failed unless it was
+/// [finished](#method.finish) successfully.
+pub struct InFlight {
+    /// The metrics the request is recorded in.
+    metrics: Arc<RpcMetrics>,
+    /// The path of the requested method.
+    method: &'static str,
+    /// When the request started.
+    started: Instant,
+    /// Whether the request succeeded.
+    success: bool,
+}
+
+/// An RPC service that records the metrics of every request to it.
+#[derive(Clone)]
+pub struct Instrumented<S> {
+    /// The wrapped service.
+    inner: S,
+    /// The metrics to record the requests in.
+    metrics: Arc<RpcMetrics>,
+}
+
+impl MethodMetrics {
+    /// Records a single finished request.
+    fn record(&mut self, latency: Duration, success: bool) {
+        self.requests += 1;
+        if !success {
+            self.errors += 1;
+        }
+
+        self.total_latency += latency;
+        self.max_latency = self.max_latency.max(latency);
+
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_buckets[bucket] += 1;
+    }
+
+    /// Returns the method's metrics in RPC format.
+    fn to_rpc_format(&self, method: &str) -> rpc::rpc_metrics::Method {
+        rpc::rpc_metrics::Method {
+            method: method.to_owned(),
+            requests: self.requests,
+            errors: self.errors,
+            in_flight: self.in_flight,
+            total_latency_micros: self.total_latency.as_micros() as u64,
+            max_latency_micros: self.max_latency.as_micros() as u64,
+            latency_buckets: self
+                .latency_buckets
+                .iter()
+                .enumerate()
+                .map(|(index, count)| rpc::rpc_metrics::Bucket {
+                    upper_bound_micros: LATENCY_BUCKETS.get(index).map_or(0, |bound| bound.as_micros() as u64),
+                    count: *count,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl RpcMetrics {
+    /// Returns a new `RpcMetrics` with nothing recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the start of a request to a given path. The request is recorded as finished when the returned
+    /// `InFlight` is dropped.
+    pub fn start(self: &Arc<Self>, path: &str) -> InFlight {
+        let method = matched_method(path);
+        self.methods.lock().unwrap().entry(method).or_default().in_flight += 1;
+
+        InFlight {
+            metrics: Arc::clone(self),
+            method,
+            started: Instant::now(),
+            success: false,
+        }
+    }
+
+    /// Returns the metrics of every requested method in RPC format, sorted by the methods' paths.
+    pub fn to_rpc_format(&self) -> Vec<rpc::rpc_metrics::Method> {
+        self.methods
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(method, metrics)| metrics.to_rpc_format(method))
+            .collect()
+    }
+}
+
+impl InFlight {
+    /// Records the request as finished, successfully or not.
+    pub fn finish(mut self, success: bool) {
+        self.success = success;
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut methods = self.metrics.methods.lock().unwrap();
+        let metrics = methods.entry(self.method).or_default();
+        metrics.in_flight = metrics.in_flight.saturating_sub(1);
+        metrics.record(self.started.elapsed(), self.success);
+    }
+}
+
+impl<S> Instrumented<S> {
+    /// Wraps a given service to record the metrics of every request to it in given metrics.
+    pub fn new(inner: S, metrics: Arc<RpcMetrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+impl<S> NamedService for Instrumented<S>
+where
+    S: NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S> Service<http::Request<Body>> for Instrumented<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        let request = self.metrics.start(req.uri().path());
+        let response = self.inner.call(req);
+
+        Box::pin(async move {
+            let response = response.await;
+            request.finish(response.as_ref().map_or(false, |response| {
+                response
+                    .headers()
+                    .get(GRPC_STATUS_HEADER)
+                    .map_or(true, |status| status == GRPC_STATUS_OK)
+            }));
+            response
+        })
+    }
+}
+
+/// Returns the RPC method a given request path matches, or [`UNMATCHED_METHOD`](UNMATCHED_METHOD) if it matches none.
+fn matched_method(path: &str) -> &'static str {
+    rpc::METHODS
+        .iter()
+        .find(|method| **method == path)
+        .copied()
+        .unwrap_or(UNMATCHED_METHOD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_requests() {
+        let metrics = Arc::new(RpcMetrics::new());
+        let first = metrics.start("/mod_rpc.ModRpc/InstallMod");
+        let second = metrics.start("/mod_rpc.ModRpc/InstallMod");
+        assert_eq!(metrics.to_rpc_format()[0].in_flight, 2);
+
+        first.finish(true);
+        drop(second);
+
+        let recorded = metrics.to_rpc_format();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].method, "/mod_rpc.ModRpc/InstallMod");
+        assert_eq!(recorded[0].requests, 2);
+        assert_eq!(recorded[0].errors, 1);
+        assert_eq!(recorded[0].in_flight, 0);
+        assert_eq!(
+            recorded[0]
+                .latency_buckets
+                .iter()
+                .map(|bucket| bucket.count)
+                .sum::<u64>(),
+            2
+        );
+    }
+
+    #[test]
+    fn unmatched_paths() {
+        let metrics = Arc::new(RpcMetrics::new());
+        metrics.start("/mod_rpc.ModRpc/NoSuchMethod").finish(false);
+        metrics.start("/garbage").finish(false);
+        metrics.start("/mod_rpc.ModRpcStatus/GetServerStatus").finish(true);
+
+        let recorded = metrics.to_rpc_format();
+        assert_eq!(
+            recorded.iter().map(|method| method.method.as_str()).collect::<Vec<_>>(),
+            vec!["/mod_rpc.ModRpcStatus/GetServerStatus", UNMATCHED_METHOD]
+        );
+        assert_eq!(recorded[1].requests, 2);
+    }
+
+    #[test]
+    fn latency_buckets() {
+        let mut metrics = MethodMetrics::default();
+        metrics.record(Duration::from_millis(1), true);
+        metrics.record(Duration::from_millis(7), true);
+        metrics.record(Duration::from_secs(10), false);
+
+        let recorded = metrics.to_rpc_format("/mod_rpc.ModRpc/ListMods");
+        assert_eq!(recorded.max_latency_micros, 10_000_000);
+        assert_eq!(recorded.latency_buckets[0].upper_bound_micros, 1000);
+        assert_eq!(recorded.latency_buckets[0].count, 1);
+        assert_eq!(recorded.latency_buckets[2].count, 1);
+        assert_eq!(recorded.latency_buckets[LATENCY_BUCKETS.len()].upper_bound_micros, 0);
+        assert_eq!(recorded.latency_buckets[LATENCY_BUCKETS.len()].count, 1);
+    }
+}
@@ -0,0 +1,448 @@
+//! Provides [`ServerMetrics`](ServerMetrics), a snapshot of a single managed server's metrics, [`render`](render),
+//! which renders the snapshots and the RPC methods' metrics in the Prometheus text exposition format, and
+//! [`spawn_listener`](spawn_listener), which serves them over HTTP.
+
+use crate::{
+    factorio::{ExecutionStatus, GameStoreId, ServerStatus},
+    util, Config,
+};
+use anyhow::Context;
+use common::net::NetAddress;
+use futures::{Future, Stream};
+use hyper::{
+    header,
+    server::accept,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use log::*;
+use std::{collections::BTreeMap, convert::Infallible, fmt::Write, io};
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, UnixListener},
+    sync::watch,
+    task::{self, JoinHandle},
+};
+
+/// The HTTP path the metrics are served at.
+const METRICS_PATH: &str = "/metrics";
+/// The content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+/// The name of the metric family that counts the events user-defined event parsers have produced, labeled by the
+/// parsers' names.
+const CUSTOM_EVENTS_FAMILY: &str = "modtorio_server_custom_events_total";
+/// The name of the histogram of the finished RPC requests' latencies, labeled by the requested methods.
+const RPC_LATENCY_FAMILY: &str = "modtorio_rpc_latency_seconds";
+
+/// A snapshot of a single managed server's metrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerMetrics {
+    /// The server's store ID.
+    server_id: GameStoreId,
+    /// Whether the server is running.
+    up: bool,
+    /// How many players are in the game.
+    players: usize,
+    /// The server's uptime in seconds, or 0 if it isn't running.
+    uptime: i64,
+    /// How many times the server has been started again since its first start after the program started.
+    restarts: u64,
+    /// How many times the server has crashed since the program started.
+    crashes: u64,
+    /// The game's latest reported tick, if any.
+    tick: Option<u64>,
+    /// The game's latest reported updates per second, if any.
+    ups: Option<f64>,
+    /// How many events each user-defined event parser has produced, keyed by the parsers' names.
+    custom_events: BTreeMap<String, u64>,
+}
+
+/// A single metric family: its name, type, help text and how to get its value from a server's metrics. Servers without
+/// a value are left out.
+struct Family {
+    /// The metric's name.
+    name: &'static str,
+    /// The metric's type.
+    kind: &'static str,
+    /// The metric's help text.
+    help: &'static str,
+    /// Returns the metric's value for a given server, if it has one.
+    value: fn(&ServerMetrics) -> Option<f64>,
+}
+
+/// A single RPC method metric family: its name, type, help text and how to get its value from a method's metrics.
+struct MethodFamily {
+    /// The metric's name.
+    name: &'static str,
+    /// The metric's type.
+    kind: &'static str,
+    /// The metric's help text.
+    help: &'static str,
+    /// Returns the metric's value for a given method.
+    value: fn(&rpc::rpc_metrics::Method) -> u64,
+}
+
+/// Every exported metric family.
+const FAMILIES: [Family; 7] = [
+    Family {
+        name: "modtorio_server_up",
+        kind: "gauge",
+        help: "Whether the server is running.",
+        value: |server| Some(if server.up { 1.0 } else { 0.0 }),
+    },
+    Family {
+        name: "modtorio_server_players",
+        kind: "gauge",
+        help: "How many players are in the game.",
+        value: |server| Some(server.players as f64),
+    },
+    Family {
+        name: "modtorio_server_uptime_seconds",
+        kind: "gauge",
+        help: "How long the server has been running, or 0 if it isn't running.",
+        value: |server| Some(server.uptime as f64),
+    },
+    Family {
+        name: "modtorio_server_restarts_total",
+        kind: "counter",
+        help: "How many times the server has been started again since the program started.",
+        value: |server| Some(server.restarts as f64),
+    },
+    Family {
+        name: "modtorio_server_crashes_total",
+        kind: "counter",
+        help: "How many times the server has crashed since the program started.",
+        value: |server| Some(server.crashes as f64),
+    },
+    Family {
+        name: "modtorio_server_tick",
+        kind: "gauge",
+        help: "The game's latest reported tick.",
+        value: |server| server.tick.map(|tick| tick as f64),
+    },
+    Family {
+        name: "modtorio_server_ups",
+        kind: "gauge",
+        help: "The game's latest reported updates per second.",
+        value: |server| server.ups,
+    },
+];
+
+/// Every exported RPC method metric family, except for the latency histogram.
+const RPC_FAMILIES: [MethodFamily; 3] = [
+    MethodFamily {
+        name: "modtorio_rpc_requests_total",
+        kind: "counter",
+        help: "How many requests to each RPC method have finished since the program started.",
+        value: |method| method.requests,
+    },
+    MethodFamily {
+        name: "modtorio_rpc_errors_total",
+        kind: "counter",
+        help: "How many requests to each RPC method have failed since the program started.",
+        value: |method| method.errors,
+    },
+    MethodFamily {
+        name: "modtorio_rpc_in_flight",
+        kind: "gauge",
+        help: "How many requests to each RPC method are currently being handled.",
+        value: |method| method.in_flight,
+    },
+];
+
+impl ServerMetrics {
+    /// Returns a snapshot of a given server's metrics from its status and its amount of online players.
+    pub fn new(server_id: GameStoreId, status: &ServerStatus, players: usize) -> Self {
+        let running = matches!(
+            status.game_status(),
+            ExecutionStatus::Starting | ExecutionStatus::Running | ExecutionStatus::ShuttingDown
+        );
+
+        Self {
+            server_id,
+            up: status.game_status() == ExecutionStatus::Running,
+            players,
+            uptime: if running { status.get_uptime().num_seconds() } else { 0 },
+            restarts: status.starts().saturating_sub(1),
+            crashes: status.crashes(),
+            tick: status.performance().map(|performance| performance.tick),
+            ups: status.performance().map(|performance| performance.ups),
+            custom_events: status.custom_events().clone(),
+        }
+    }
+}
+
+/// Renders given servers' metrics and RPC methods' metrics in the Prometheus text exposition format, with each server
+/// labeled by its ID and each method by its path.
+pub fn render(servers: &[ServerMetrics], methods: &[rpc::rpc_metrics::Method]) -> String {
+    let mut rendered = String::new();
+
+    for family in &FAMILIES {
+        writeln!(rendered, "# HELP {} {}", family.name, family.help).expect("failed to write metrics");
+        writeln!(rendered, "# TYPE {} {}", family.name, family.kind).expect("failed to write metrics");
+
+        for server in servers {
+            if let Some(value) = (family.value)(server) {
+                writeln!(
+                    rendered,
+                    "{}{{server_id=\"{}\"}} {}",
+                    family.name, server.server_id, value
+                )
+                .expect("failed to write metrics");
+            }
+        }
+    }
+
+    writeln!(
+        rendered,
+        "# HELP {} How many events each user-defined event parser has produced since the program started.",
+        CUSTOM_EVENTS_FAMILY
+    )
+    .expect("failed to write metrics");
+    writeln!(rendered, "# TYPE {} counter", CUSTOM_EVENTS_FAMILY).expect("failed to write metrics");
+    for server in servers {
+        for (event, count) in &server.custom_events {
+            writeln!(
+                rendered,
+                "{}{{server_id=\"{}\",event=\"{}\"}} {}",
+                CUSTOM_EVENTS_FAMILY,
+                server.server_id,
+                escape_label_value(event),
+                count
+            )
+            .expect("failed to write metrics");
+        }
+    }
+
+    render_rpc_methods(&mut rendered, methods);
+    rendered
+}
+
+/// Renders given RPC methods' metrics in the Prometheus text exposition format into a given string, with each method
+/// labeled by its path. The latencies are rendered as a histogram with cumulative buckets, bounded in seconds.
+fn render_rpc_methods(rendered: &mut String, methods: &[rpc::rpc_metrics::Method]) {
+    for family in &RPC_FAMILIES {
+        writeln!(rendered, "# HELP {} {}", family.name, family.help).expect("failed to write metrics");
+        writeln!(rendered, "# TYPE {} {}", family.name, family.kind).expect("failed to write metrics");
+        for method in methods {
+            writeln!(
+                rendered,
+                "{}{{method=\"{}\"}} {}",
+                family.name,
+                escape_label_value(&method.method),
+                (family.value)(method)
+            )
+            .expect("failed to write metrics");
+        }
+    }
+
+    writeln!(
+        rendered,
+        "# HELP {} How long the finished requests to each RPC method took until their response began.",
+        RPC_LATENCY_FAMILY
+    )
+    .expect("failed to write metrics");
+    writeln!(rendered, "# TYPE {} histogram", RPC_LATENCY_FAMILY).expect("failed to write metrics");
+    for method in methods {
+        let label = escape_label_value(&method.method);
+        let mut cumulative = 0;
+
+        for bucket in &method.latency_buckets {
+            cumulative += bucket.count;
+            // the last bucket has no upper bound
+            let bound = if bucket.upper_bound_micros == 0 {
+                String::from("+Inf")
+            } else {
+                (bucket.upper_bound_micros as f64 / 1_000_000.0).to_string()
+            };
+
+            writeln!(
+                rendered,
+                "{}_bucket{{method=\"{}\",le=\"{}\"}} {}",
+                RPC_LATENCY_FAMILY, label, bound, cumulative
+            )
+            .expect("failed to write metrics");
+        }
+
+        writeln!(
+            rendered,
+            "{}_sum{{method=\"{}\"}} {}",
+            RPC_LATENCY_FAMILY,
+            label,
+            method.total_latency_micros as f64 / 1_000_000.0
+        )
+        .expect("failed to write metrics");
+        writeln!(
+            rendered,
+            "{}_count{{method=\"{}\"}} {}",
+            RPC_LATENCY_FAMILY, label, method.requests
+        )
+        .expect("failed to write metrics");
+    }
+}
+
+/// Escapes a given label value for the Prometheus text exposition format.
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Spawns a task that serves the metrics returned by a given function over HTTP on a given listen address until a
+/// given shutdown signal receiver receives a value. Unix sockets are bound before the task is spawned, with the file
+/// mode and ownership from a given config. Returns the task's `JoinHandle`.
+pub async fn spawn_listener<F, Fut>(
+    listen: &NetAddress,
+    config: &Config,
+    collect: F,
+    mut shutdown_rx: watch::Receiver<()>,
+) -> anyhow::Result<JoinHandle<()>>
+where
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = String> + Send + 'static,
+{
+    let shutdown_signal = async move {
+        shutdown_rx.recv().await;
+    };
+
+    match listen {
+        NetAddress::TCP(addr) => {
+            debug!("Starting metrics endpoint on TCP {}", addr);
+
+            let mut tcp = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind metrics endpoint to {}", addr))?;
+            let addr = *addr;
+            Ok(task::spawn(async move {
+                serve(tcp.incoming(), collect, shutdown_signal)
+                    .await
+                    .expect("metrics TCP listener failed");
+                debug!("Metrics TCP listener on {} shut down", addr);
+            }))
+        }
+        NetAddress::Unix(path) => {
+            debug!("Starting metrics endpoint on Unix {}", path.display());
+
+            util::socket::check_parent_directory(path)?;
+            let mut unix = util::socket::bind(
+                path,
+                config.socket_mode()?,
+                config.socket_owner(),
+                config.socket_group(),
+                |path| UnixListener::bind(path),
+            )
+            .with_context(|| format!("Failed to bind to Unix socket {}", path.display()))?;
+
+            let path = path.to_owned();
+            Ok(task::spawn(async move {
+                serve(unix.incoming(), collect, shutdown_signal)
+                    .await
+                    .expect("metrics Unix listener failed");
+
+                debug!("Metrics Unix listener on {} shut down, removing socket", path.display());
+                fs::remove_file(&path).await.expect("failed to remove socket");
+            }))
+        }
+    }
+}
+
+/// Serves the metrics returned by a given function over HTTP on the connections from a given stream until a given
+/// shutdown signal completes.
+async fn serve<I, S, F, Fut>(incoming: I, collect: F, shutdown_signal: impl Future<Output = ()>) -> hyper::Result<()>
+where
+    I: Stream<Item = io::Result<S>>,
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = String> + Send + 'static,
+{
+    let make_service = make_service_fn(move |_: &S| {
+        let collect = collect.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| respond(req, collect.clone()))) }
+    });
+
+    Server::builder(accept::from_stream(incoming))
+        .serve(make_service)
+        .with_graceful_shutdown(shutdown_signal)
+        .await
+}
+
+/// Responds to a given HTTP request with the metrics returned by a given function, or with 404 if the request isn't
+/// for the metrics.
+async fn respond<F, Fut>(req: Request<Body>, collect: F) -> Result<Response<Body>, Infallible>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = String>,
+{
+    let response = if req.method() == Method::GET && req.uri().path() == METRICS_PATH {
+        Response::builder()
+            .header(header::CONTENT_TYPE, CONTENT_TYPE)
+            .body(Body::from(collect().await))
+    } else {
+        Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty())
+    };
+
+    Ok(response.expect("failed to build metrics response"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{factorio::Performance, metrics::RpcMetrics};
+    use std::sync::Arc;
+
+    #[test]
+    fn render_servers() {
+        let mut running = ServerStatus::default();
+        running.reset_started_at();
+        running.reset_started_at();
+        running.set_game_status(ExecutionStatus::Running);
+        running.set_performance(Performance { tick: 3600, ups: 60.0 });
+        running.add_custom_event("telemetry");
+        running.add_custom_event("telemetry");
+        running.add_custom_event("say \"hi\"");
+
+        let mut crashed = ServerStatus::default();
+        crashed.reset_started_at();
+        crashed.set_game_status(ExecutionStatus::Crashed);
+        crashed.add_crash();
+
+        let rendered = render(
+            &[ServerMetrics::new(1, &running, 2), ServerMetrics::new(2, &crashed, 0)],
+            &[],
+        );
+
+        assert!(rendered.contains("# TYPE modtorio_server_up gauge\n"));
+        assert!(rendered.contains("modtorio_server_up{server_id=\"1\"} 1\n"));
+        assert!(rendered.contains("modtorio_server_up{server_id=\"2\"} 0\n"));
+        assert!(rendered.contains("modtorio_server_players{server_id=\"1\"} 2\n"));
+        assert!(rendered.contains("modtorio_server_restarts_total{server_id=\"1\"} 1\n"));
+        assert!(rendered.contains("modtorio_server_crashes_total{server_id=\"2\"} 1\n"));
+        assert!(rendered.contains("modtorio_server_uptime_seconds{server_id=\"2\"} 0\n"));
+        assert!(rendered.contains("modtorio_server_tick{server_id=\"1\"} 3600\n"));
+        assert!(rendered.contains("modtorio_server_ups{server_id=\"1\"} 60\n"));
+        assert!(!rendered.contains("modtorio_server_tick{server_id=\"2\"}"));
+        assert!(rendered.contains("modtorio_server_custom_events_total{server_id=\"1\",event=\"telemetry\"} 2\n"));
+        assert!(rendered.contains("modtorio_server_custom_events_total{server_id=\"1\",event=\"say \\\"hi\\\"\"} 1\n"));
+    }
+
+    #[test]
+    fn render_rpc_metrics() {
+        let metrics = Arc::new(RpcMetrics::new());
+        metrics.start("/mod_rpc.ModRpc/GetServerStatus").finish(true);
+        metrics.start("/mod_rpc.ModRpc/GetServerStatus");
+        let _in_flight = metrics.start("/mod_rpc.ModRpc/InstallMod");
+
+        let rendered = render(&[], &metrics.to_rpc_format());
+
+        assert!(rendered.contains("# TYPE modtorio_rpc_requests_total counter\n"));
+        assert!(rendered.contains("modtorio_rpc_requests_total{method=\"/mod_rpc.ModRpc/GetServerStatus\"} 2\n"));
+        assert!(rendered.contains("modtorio_rpc_errors_total{method=\"/mod_rpc.ModRpc/GetServerStatus\"} 1\n"));
+        assert!(rendered.contains("modtorio_rpc_in_flight{method=\"/mod_rpc.ModRpc/InstallMod\"} 1\n"));
+        assert!(rendered.contains("# TYPE modtorio_rpc_latency_seconds histogram\n"));
+        assert!(rendered.contains(
+            "modtorio_rpc_latency_seconds_bucket{method=\"/mod_rpc.ModRpc/GetServerStatus\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(rendered
+            .contains("modtorio_rpc_latency_seconds_bucket{method=\"/mod_rpc.ModRpc/GetServerStatus\",le=\"0.001\"}"));
+        assert!(rendered.contains("modtorio_rpc_latency_seconds_count{method=\"/mod_rpc.ModRpc/InstallMod\"} 0\n"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Provides the [`ModCache`](ModCache) object, a shared cache of mod zip archives downloaded from the mod portal
+//! outside any server, so other tooling can use the instance's authenticated and rate-limited mod portal client without
+//! importing a server.
+//!
+//! The archives are kept in the configured cache directory named `<name>_<version>.zip` like the game names them. A
+//! cached archive is reused for as long as its checksum matches the one the mod portal reports for the release.
+
+use crate::{
+    error::ModError,
+    mod_common::Info,
+    mod_portal::ModPortal,
+    util::{async_status::AsyncProgressChannel, checksum, HumanVersion},
+};
+use log::*;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// The algorithm the mod portal's release checksums are calculated with.
+const CHECKSUM_ALGORITHM: checksum::Algorithm = checksum::Algorithm::Sha1;
+
+/// A shared cache of mod zip archives downloaded from the mod portal.
+#[derive(Debug, Clone)]
+pub struct ModCache {
+    /// The directory the archives are kept in.
+    directory: PathBuf,
+}
+
+/// A mod release's archive in the cache.
+#[derive(Debug)]
+pub struct CachedMod {
+    /// The archive's location.
+    pub path: PathBuf,
+    /// The cached release's version.
+    pub version: HumanVersion,
+    /// Whether the archive was downloaded instead of reused from the cache.
+    pub downloaded: bool,
+}
+
+impl ModCache {
+    /// Returns a new `ModCache` in a given directory.
+    pub fn new<P>(directory: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the location of a given mod release's archive in the cache.
+    pub fn path(&self, name: &str, version: HumanVersion) -> PathBuf {
+        self.directory.join(format!("{}_{}.zip", name, version))
+    }
+
+    /// Makes sure a given version of a given mod, or its latest version if no version is given, is in the cache.
+    /// Downloads the release with a given mod portal, reporting progress to a given progress channel, unless a cached
+    /// archive matches its checksum. Returns [`ModError::ZipChecksumMismatch`](ModError::ZipChecksumMismatch) and
+    /// leaves nothing in the cache if the downloaded archive doesn't match.
+    pub async fn fetch(
+        &self,
+        portal: &ModPortal,
+        name: &str,
+        version: Option<HumanVersion>,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<CachedMod> {
+        let info = Info::from_portal(name, portal).await?;
+        let release = info.get_release(version)?;
+        let path = self.path(name, release.version());
+
+        if is_cached(&path, release.sha1()).await? {
+            debug!("Reusing cached {} {} from {}", name, release.version(), path.display());
+            return Ok(CachedMod {
+                path,
+                version: release.version(),
+                downloaded: false,
+            });
+        }
+
+        fs::create_dir_all(&self.directory).await?;
+        let (downloaded, _) = portal
+            .download_mod(name, release.url()?, &self.directory, None, prog_tx.clone())
+            .await?;
+        if downloaded != path {
+            fs::rename(&downloaded, &path).await?;
+        }
+
+        let zip_checksum =
+            checksum::file_with_progress(CHECKSUM_ALGORITHM, &path, "Verifying checksum...", prog_tx).await?;
+        if zip_checksum != release.sha1() {
+            fs::remove_file(&path).await?;
+            return Err(ModError::ZipChecksumMismatch {
+                zip_checksum,
+                expected: release.sha1().to_owned(),
+            }
+            .into());
+        }
+
+        info!("Cached {} {} into {}", name, release.version(), path.display());
+        Ok(CachedMod {
+            path,
+            version: release.version(),
+            downloaded: true,
+        })
+    }
+}
+
+/// Returns whether a mod archive with a given checksum is at a given location.
+async fn is_cached(path: &Path, sha1: &str) -> anyhow::Result<bool> {
+    match fs::metadata(path).await {
+        Ok(meta) if meta.is_file() => {}
+        Ok(_) => return Ok(false),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    }
+
+    let cached_checksum = checksum::file_with_progress(CHECKSUM_ALGORITHM, path, "", None).await?;
+    Ok(cached_checksum == sha1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA1 checksum of an empty file.
+    const EMPTY_SHA1: &str = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+
+    #[tokio::test]
+    async fn cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ModCache::new(dir.path());
+        let path = cache.path("cool-mod", "1.0.0".parse().unwrap());
+        assert_eq!(path, dir.path().join("cool-mod_1.0.0.zip"));
+
+        assert!(!is_cached(&path, EMPTY_SHA1).await.unwrap());
+        std::fs::write(&path, "").unwrap();
+        assert!(is_cached(&path, EMPTY_SHA1).await.unwrap());
+        assert!(!is_cached(&path, "0000000000000000000000000000000000000000")
+            .await
+            .unwrap());
+    }
+}
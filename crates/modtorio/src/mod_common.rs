@@ -5,25 +5,25 @@ mod info;
 
 use crate::{
     error::ModError,
+    factorio::GameStoreId,
     mod_portal::PortalResult,
-    store::{models, Store},
-    util::{self, file, HumanVersion},
+    store::{models, CompressedText, Store},
+    util::{self, async_status::AsyncProgressChannel, checksum, file, HumanVersion},
     Config, ModPortal,
 };
 use bytesize::ByteSize;
 use chrono::{DateTime, Utc};
-use info::Info;
 use log::*;
 use std::{
     path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
 };
-use tokio::{sync::RwLock, task};
+use tokio::sync::RwLock;
 use util::ext::PathExt;
 
 pub use dependency::{Dependency, Requirement};
-pub use info::Release;
+pub use info::{Info, Release};
 
 /// A Factorio mod.
 ///
@@ -61,43 +61,40 @@ pub enum DownloadResult {
     },
 }
 
-/// The available checksum algorithms.
-enum ChecksumAlgorithm {
-    /// The `BLAKE2b`-algorith.
-    BLAKE2b,
-    /// The `SHA1`-algorithm.
-    SHA1,
-}
-
 /// The default checksum algorithm to use to verifying stored mods.
-const STORE_ZIP_CHECKSUM_ALGO: ChecksumAlgorithm = ChecksumAlgorithm::BLAKE2b;
+const STORE_ZIP_CHECKSUM_ALGO: checksum::Algorithm = checksum::Algorithm::Blake2b;
 /// The algorithm used to verify downloaded mods from the mod portal. This is dictated by what the
 /// mod portal returns as a checksum.
-const DOWNLOADED_ZIP_CHECKSUM_ALGO: ChecksumAlgorithm = ChecksumAlgorithm::SHA1;
+const DOWNLOADED_ZIP_CHECKSUM_ALGO: checksum::Algorithm = checksum::Algorithm::Sha1;
+/// The splitter sequence used to separate mod tags in the store database column.
+const TAGS_SPLITTER: &str = ",";
+
+/// Splits a given stored tags column into the individual tags.
+fn split_stored_tags(tags: &str) -> Vec<String> {
+    tags.split(TAGS_SPLITTER)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
 
 /// Calculates a mod zip archive's checksum using the given checksum algorithm and returns it as a
 /// `Result<String>`.
 ///
-/// The checksum is calculated in a blocking thread with `task::spawn_blocking`. The function will
-/// return an error if:
+/// The checksum is calculated in a blocking thread with
+/// [`checksum::file_with_progress`](crate::util::checksum::file_with_progress), so the calculation is cancelled if the
+/// returned future is dropped, and its progress is reported to a given progress channel. The function will return an
+/// error if:
 /// * The task spawning fails
 /// * The checksum function fails
-async fn calculate_zip_checksum<P>(algorithm: ChecksumAlgorithm, zip: P) -> anyhow::Result<String>
+async fn calculate_zip_checksum<P>(
+    algorithm: checksum::Algorithm,
+    zip: P,
+    prog_tx: Option<AsyncProgressChannel>,
+) -> anyhow::Result<String>
 where
     P: AsRef<Path>,
 {
-    let path = zip.as_ref().to_owned();
-    let result = match algorithm {
-        ChecksumAlgorithm::BLAKE2b => {
-            task::spawn_blocking(move || -> anyhow::Result<String> { util::checksum::blake2b_file(path) })
-        }
-        ChecksumAlgorithm::SHA1 => {
-            task::spawn_blocking(move || -> anyhow::Result<String> { util::checksum::sha1_file(path) })
-        }
-    }
-    .await?;
-
-    let result = result?;
+    let result = checksum::file_with_progress(algorithm, zip.as_ref(), "Calculating checksum...", prog_tx).await?;
     trace!("Calculated zip checksum ({}): {}", zip.as_ref().display(), result);
     Ok(result)
 }
@@ -113,7 +110,7 @@ async fn verify_zip<P>(game_mod: &models::GameMod, mods_root_path: P) -> anyhow:
 where
     P: AsRef<Path>,
 {
-    let zip_path = mods_root_path.as_ref().join(&game_mod.mod_zip);
+    let zip_path = mods_root_path.as_ref().join(util::path_from_bytes(&game_mod.mod_zip));
     if !zip_path.exists() {
         return Err(ModError::MissingZip(zip_path).into());
     }
@@ -154,12 +151,13 @@ impl Mod {
             .ok_or(ModError::ModNotInStore)?;
         let info = RwLock::new(Info::from_store(factorio_mod, game_mod.mod_version, store.as_ref()).await?);
 
+        let zip_path = util::path_from_bytes(&game_mod.mod_zip);
         debug!(
             "Verifying mod '{}' zip ({}) against store...",
-            game_mod.factorio_mod, game_mod.mod_zip
+            game_mod.factorio_mod,
+            zip_path.display()
         );
 
-        let zip_path = PathBuf::from(&game_mod.mod_zip);
         if let Err(e) = verify_zip(&game_mod, &mods_root_path).await {
             if let Some(ModError::ZipLastMtimeMismatch { last_mtime, expected }) = e.downcast_ref() {
                 warn!(
@@ -195,7 +193,7 @@ impl Mod {
     {
         debug!("Creating mod from zip: '{}'", path.as_ref().display());
         let info = RwLock::new(Info::from_zip(path.as_ref().to_owned()).await?);
-        let zip_path = PathBuf::from(path.as_ref().get_file_name()?);
+        let zip_path = PathBuf::from(path.as_ref().get_os_file_name()?);
         let zip_last_mtime = file::get_last_mtime(&path)?;
 
         Ok(Self {
@@ -230,19 +228,29 @@ impl Mod {
 }
 
 impl Mod {
-    /// Updates the mod's store
+    /// Updates the mod's store. In offline mode a mod without portal info is stored with only the info from its zip
+    /// archive and no releases, and marked as expired so it's refreshed from the portal once back online.
     pub async fn update_store(&self) -> anyhow::Result<()> {
         trace!("Updating store for '{}'", self.name().await);
 
         if !self.is_portal_populated().await {
-            debug!(
-                "Info not populated from portal before updating store for '{}', populating...",
-                self.name().await
-            );
+            if self.config.offline() {
+                debug!(
+                    "Info not populated from portal before updating store for '{}', storing zip info in offline mode",
+                    self.name().await
+                );
+            } else {
+                debug!(
+                    "Info not populated from portal before updating store for '{}', populating...",
+                    self.name().await
+                );
 
-            self.fetch_portal_info().await?;
+                self.fetch_portal_info().await?;
+            }
         }
 
+        let portal_populated = self.is_portal_populated().await;
+
         let name = self.name().await;
         let author = self.author().await;
         let contact = self.contact().await;
@@ -251,6 +259,8 @@ impl Mod {
         let summary = self.summary().await;
         let description = self.description().await;
         let changelog = self.changelog().await;
+        let category = self.category().await;
+        let tags = self.tags().await.join(TAGS_SPLITTER);
         let new_factorio_mod = models::FactorioMod {
             name,
             author,
@@ -258,14 +268,24 @@ impl Mod {
             homepage,
             title,
             summary,
-            description,
-            changelog,
-            last_updated: Utc::now(),
+            description: description.into(),
+            changelog: changelog.map(CompressedText::from),
+            last_updated: if portal_populated {
+                Utc::now()
+            } else {
+                DateTime::from(std::time::UNIX_EPOCH)
+            },
+            category,
+            tags,
         };
 
         // trace!("'{}' stored mod: {:?}", self.name().await, new_factorio_mod);
         self.store.set_factorio_mod(new_factorio_mod).await?;
 
+        if !portal_populated {
+            return Ok(());
+        }
+
         for release in self.releases().await? {
             let new_mod_release = models::ModRelease {
                 factorio_mod: self.name().await,
@@ -365,8 +385,16 @@ impl Mod {
         self.fetch_portal_info().await
     }
 
-    /// Download a certain version of the mod. If no version is given, downloads the latest version.
-    pub async fn download<P>(&self, version: Option<HumanVersion>, destination: P) -> anyhow::Result<DownloadResult>
+    /// Download a certain version of the mod. If no version is given, downloads the latest version. The download is
+    /// authenticated with the mod portal credentials of a given server, if any. Retried downloads are reported to a
+    /// given progress channel.
+    pub async fn download<P>(
+        &self,
+        version: Option<HumanVersion>,
+        destination: P,
+        server: Option<GameStoreId>,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<DownloadResult>
     where
         P: AsRef<Path>,
     {
@@ -385,7 +413,7 @@ impl Mod {
 
         let (path, download_size) = self
             .portal
-            .download_mod(&self.name().await, release.url()?, destination)
+            .download_mod(&self.name().await, release.url()?, destination, server, prog_tx.clone())
             .await?;
 
         debug!(
@@ -394,7 +422,7 @@ impl Mod {
             download_size,
         );
 
-        let download_checksum = calculate_zip_checksum(DOWNLOADED_ZIP_CHECKSUM_ALGO, &path).await?;
+        let download_checksum = calculate_zip_checksum(DOWNLOADED_ZIP_CHECKSUM_ALGO, &path, prog_tx).await?;
         let checksums_match = download_checksum == release.sha1();
         trace!(
             "Got downloaded zip checksum: {} (matches: {})",
@@ -462,7 +490,7 @@ impl Mod {
     /// Returns the mod zip archive's checksum if set. If not set, will calculate the checksum, set
     /// it and return it. Returns `ModError::MissingZipPath` if the path isn't set.
     pub async fn get_zip_checksum(&self) -> anyhow::Result<String> {
-        let checksum = calculate_zip_checksum(STORE_ZIP_CHECKSUM_ALGO, self.zip_path().await?).await?;
+        let checksum = calculate_zip_checksum(STORE_ZIP_CHECKSUM_ALGO, self.zip_path().await?, None).await?;
 
         trace!(
             "Calculated zip checksum for mod '{}' ({}): {}",
@@ -519,6 +547,18 @@ impl Mod {
         info.summary().map(std::string::ToString::to_string)
     }
 
+    /// Returns the mod's category on the mod portal, if any.
+    pub async fn category(&self) -> Option<String> {
+        let info = self.info.read().await;
+        info.category().map(std::string::ToString::to_string)
+    }
+
+    /// Returns the mod's tags on the mod portal.
+    pub async fn tags(&self) -> Vec<String> {
+        let info = self.info.read().await;
+        info.tags().to_vec()
+    }
+
     /// Returns the mod's description.
     pub async fn description(&self) -> String {
         let info = self.info.read().await;
@@ -94,6 +94,9 @@ impl FromStr for Dependency {
             .as_str()
             .trim()
             .to_string();
+        if name.is_empty() {
+            return Err(DependencyParsingError::NameNotCaptured(s.to_owned()));
+        }
 
         let version = captures
             .get(3)
@@ -134,15 +137,40 @@ impl fmt::Display for Requirement {
 
 impl fmt::Display for Dependency {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!("{} {} ", self.requirement, &self.name))?;
+        if self.requirement != Requirement::Mandatory {
+            f.write_fmt(format_args!("{} ", self.requirement))?;
+        }
+        f.write_str(&self.name)?;
         if let Some(version) = self.version {
-            f.write_fmt(format_args!("{}", version))?;
+            f.write_fmt(format_args!(" {}", version))?;
         }
 
         Ok(())
     }
 }
 
+impl From<Requirement> for rpc::dependency::Requirement {
+    fn from(requirement: Requirement) -> Self {
+        match requirement {
+            Requirement::Mandatory => Self::Mandatory,
+            Requirement::Optional => Self::Optional,
+            Requirement::OptionalHidden => Self::OptionalHidden,
+            Requirement::Incompatible => Self::Incompatible,
+        }
+    }
+}
+
+impl From<&Dependency> for rpc::Dependency {
+    fn from(dependency: &Dependency) -> Self {
+        Self {
+            requirement: rpc::dependency::Requirement::from(dependency.requirement).into(),
+            name: dependency.name.clone(),
+            version: dependency.version.map(Into::into),
+            normalized: dependency.to_string(),
+        }
+    }
+}
+
 impl ToSql for Requirement {
     fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
         Ok(ToSqlOutput::Owned(Value::Text(self.to_string())))
@@ -258,4 +286,25 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn normalize() -> anyhow::Result<()> {
+        assert_eq!("base".parse::<Dependency>()?.to_string(), "base");
+        assert_eq!("base>=0.18".parse::<Dependency>()?.to_string(), "base >= 0.18.0");
+        assert_eq!("?  cool-mod".parse::<Dependency>()?.to_string(), "? cool-mod");
+        assert_eq!(
+            "(?)cool-mod = 1.0".parse::<Dependency>()?.to_string(),
+            "(?) cool-mod == 1.0.0"
+        );
+        assert_eq!("! evil-mod".parse::<Dependency>()?.to_string(), "! evil-mod");
+
+        for dependency in &["base >= 0.18.0", "? cool-mod < 2.0.0", "(?) cool-mod == 1.0.0"] {
+            let parsed = dependency.parse::<Dependency>()?;
+            assert_eq!(parsed.to_string().parse::<Dependency>()?, parsed);
+        }
+
+        assert!("".parse::<Dependency>().is_err());
+        assert!(" ".parse::<Dependency>().is_err());
+        Ok(())
+    }
 }
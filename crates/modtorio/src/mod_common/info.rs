@@ -1,11 +1,11 @@
 //! Provides structured objects of a mod's metadata information, both from the mod zip
 //! archive and the mod portal.
 
-use super::Dependency;
+use super::{split_stored_tags, Dependency};
 use crate::{
     error::ModError,
     mod_portal::{ModPortal, PortalResult},
-    store::{models, Store},
+    store::{models, CompressedText, Store},
     util::{
         ext::{PathExt, ZipExt},
         HumanVersion,
@@ -35,8 +35,10 @@ pub struct Info {
     dependencies: Option<Vec<Dependency>>,
     /// The mod's releases. Will only exist once the info has been populated from the mod portal.
     releases: Option<Vec<Release>>,
+    /// The mod's category and tags on the mod portal.
+    classification: Classification,
     /* fields the portal API has but not represented here:
-     * github_path, created_at, tag */
+     * github_path, created_at */
 }
 
 /// A mod author's information.
@@ -63,6 +65,15 @@ pub struct Display {
     changelog: Option<String>,
 }
 
+/// A mod's category and tags on the mod portal. Both are empty until the info has been populated from the mod portal.
+#[derive(Debug, Default)]
+pub struct Classification {
+    /// The mod's category, if any.
+    category: Option<String>,
+    /// The mod's tags.
+    tags: Vec<String>,
+}
+
 /// A mod's version information.
 #[derive(Debug, Copy, Clone)]
 pub struct Versions {
@@ -233,11 +244,15 @@ impl Info {
             display: Display {
                 title: factorio_mod.title,
                 summary: factorio_mod.summary,
-                description: factorio_mod.description,
-                changelog: factorio_mod.changelog,
+                description: factorio_mod.description.into_inner(),
+                changelog: factorio_mod.changelog.map(CompressedText::into_inner),
             },
             dependencies: Some(this_release.info_object.dependencies.clone()),
             releases: Some(releases),
+            classification: Classification {
+                category: factorio_mod.category,
+                tags: split_stored_tags(&factorio_mod.tags),
+            },
         })
     }
 
@@ -263,6 +278,7 @@ impl Info {
             },
             dependencies: Some(info.dependencies),
             releases: None,
+            classification: Classification::default(),
         }
     }
 
@@ -286,6 +302,10 @@ impl Info {
             },
             dependencies: None,
             releases: Some(info.releases()?.to_owned()),
+            classification: Classification {
+                category: info.category().map(str::to_owned),
+                tags: info.tags().to_vec(),
+            },
         })
     }
 
@@ -326,6 +346,10 @@ impl Info {
         info.compress()?;
 
         self.display.summary = info.summary().map(str::to_owned);
+        self.classification = Classification {
+            category: info.category().map(str::to_owned),
+            tags: info.tags().to_vec(),
+        };
         self.releases = Some(info.into_releases()?);
 
         Ok(())
@@ -344,8 +368,12 @@ impl Info {
         self.author.homepage = store_mod.homepage;
         self.display.title = store_mod.title;
         self.display.summary = store_mod.summary;
-        self.display.description = store_mod.description;
-        self.display.changelog = store_mod.changelog;
+        self.display.description = store_mod.description.into_inner();
+        self.display.changelog = store_mod.changelog.map(CompressedText::into_inner);
+        self.classification = Classification {
+            category: store_mod.category,
+            tags: split_stored_tags(&store_mod.tags),
+        };
 
         let mut releases = Vec::new();
         for release in store.get_mod_releases(self.name.clone()).await? {
@@ -454,6 +482,16 @@ impl Info {
         self.display.changelog.as_deref()
     }
 
+    /// Returns the mod's category on the mod portal, if any.
+    pub fn category(&self) -> Option<&str> {
+        self.classification.category.as_deref()
+    }
+
+    /// Returns the mod's tags on the mod portal.
+    pub fn tags(&self) -> &[String] {
+        &self.classification.tags
+    }
+
     /// Returns the mod's installed version, or an error if mod isn't installed
     /// (`ModError::MissingInfo`).
     pub fn own_version(&self) -> anyhow::Result<HumanVersion> {
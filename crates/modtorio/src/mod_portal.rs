@@ -1,24 +1,45 @@
 //! Provides the [`ModPortal`](ModPortal) object to interact with the Factorio mod portal via HTTP.
 
+mod connectivity;
+mod credentials;
+mod search;
+
 use crate::{
     config::Config,
-    error::{ModError, ModPortalError},
+    error::{ModError, ModPortalError, ResponseError},
+    factorio::GameStoreId,
     mod_common::Release,
-    util::{self, ext::ResponseExt},
+    store::{models::PortalResponse, CompressedText, Store},
+    util::{
+        self,
+        async_status::{self, AsyncProgressChannel, AsyncProgressChannelExt},
+        ext::ResponseExt,
+        Backoff, RateLimiter,
+    },
 };
+use chrono::Utc;
+pub use connectivity::{Connectivity, Stage};
+pub use credentials::{CredentialKey, Credentials};
 use log::*;
-use reqwest::Client;
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Client, StatusCode,
+};
+pub use search::{SearchQuery, SearchResult};
 use serde::Deserialize;
 use std::{
+    collections::HashMap,
+    io::SeekFrom,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 use tempfile::tempfile;
-use tokio::{fs, io};
+use tokio::{fs, io, sync::RwLock, time};
 use url::Url;
 
 /// The user-agent used in all HTTP requests.
-const USER_AGENT: &str = "modtorio";
+pub const USER_AGENT: &str = "modtorio";
 /// The mod portal's site root.
 const SITE_ROOT: &str = "https://mods.factorio.com";
 /// The mod portal's download root.
@@ -27,23 +48,31 @@ const DOWNLOAD_ROOT: &str = "/download/";
 const API_ROOT: &str = "/api/mods";
 /// The endpoint for requesting full mod information.
 const FULL_ENDPOINT: &str = "full";
-
-/// A username-token pair used to authenticate with the mod portal.
-#[derive(Debug)]
-struct Credentials {
-    /// The username.
-    username: String,
-    /// The token used to authenticate.
-    token: String,
-}
+/// The largest amount of mod names requested in a single mod list request, to keep the request URL reasonably short.
+const NAMELIST_MAX_NAMES: usize = 100;
+/// How many times downloading a mod is attempted before giving up.
+const DOWNLOAD_ATTEMPTS: u32 = 5;
+/// The delay before retrying a failed mod download for the first time.
+const DOWNLOAD_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// The maximum delay between mod download attempts.
+const DOWNLOAD_MAX_BACKOFF: Duration = Duration::from_secs(30);
 
 /// The mod portal interface object.
 #[derive(Debug)]
 pub struct ModPortal {
     /// The HTTP client.
     client: Client,
+    /// Limits the rate of requests made to the mod portal on behalf of every server.
+    limiter: RateLimiter,
     /// The program config.
     config: Arc<Config>,
+    /// The latest connectivity check's report, if a check has been run.
+    connectivity: RwLock<Option<Connectivity>>,
+    /// Credentials overriding the configured ones for requests made on behalf of certain servers, keyed by the
+    /// servers' store IDs.
+    server_credentials: RwLock<HashMap<GameStoreId, Credentials>>,
+    /// The program store mod info responses are cached in, if any.
+    store: Option<Arc<Store>>,
 }
 
 /// Represents a single mods full information from the mod portal.
@@ -67,15 +96,19 @@ pub struct PortalResult {
     description: Option<String>,
     /// The mod author's homepage.
     homepage: Option<String>,
+    /// The mod's category.
+    category: Option<String>,
+    /// The mod's tags.
+    tags: Option<Vec<String>>,
 }
 
 /// Represents the result to querying for multiple mods.
 #[derive(Debug, Deserialize)]
-struct ModList {
+struct ModList<T> {
     /// The pagination, if any.
     pagination: Option<Pagination>,
     /// The individual mod results.
-    results: Vec<PortalResult>,
+    results: Vec<T>,
 }
 
 /// Represents the pagination from querying for multiple mods.
@@ -111,7 +144,54 @@ impl ModPortal {
     pub fn new(config: Arc<Config>) -> anyhow::Result<Self> {
         let client = Client::builder().user_agent(USER_AGENT).build()?;
 
-        Ok(Self { client, config })
+        let limiter = RateLimiter::new(config.portal_rate_limit(), config.portal_burst());
+
+        Ok(Self {
+            client,
+            limiter,
+            config,
+            connectivity: RwLock::new(None),
+            server_credentials: RwLock::new(HashMap::new()),
+            store: None,
+        })
+    }
+
+    /// Caches the responses to mod info requests in a given program store. Each cached response is revalidated with
+    /// a conditional request instead of being fetched again, and reused if the portal reports it unchanged or refuses
+    /// the request because of rate limiting.
+    pub fn with_store(self, store: Arc<Store>) -> Self {
+        Self {
+            store: Some(store),
+            ..self
+        }
+    }
+
+    /// Sets the credentials used for requests made on behalf of a given server. `None` makes the server use the
+    /// configured credentials.
+    pub async fn set_server_credentials(&self, server: GameStoreId, credentials: Option<Credentials>) {
+        let mut server_credentials = self.server_credentials.write().await;
+        match credentials {
+            Some(credentials) => server_credentials.insert(server, credentials),
+            None => server_credentials.remove(&server),
+        };
+    }
+
+    /// Returns the credentials overriding the configured ones for a given server, if any.
+    pub async fn server_credentials(&self, server: GameStoreId) -> Option<Credentials> {
+        self.server_credentials.read().await.get(&server).cloned()
+    }
+
+    /// Checks the connectivity to the mod portal, stores the report as the latest one and returns
+    /// it.
+    pub async fn check_connectivity(&self) -> Connectivity {
+        let report = Connectivity::check(&self.client).await;
+        *self.connectivity.write().await = Some(report.clone());
+        report
+    }
+
+    /// Returns the latest connectivity check's report, or `None` if a check hasn't been run yet.
+    pub async fn connectivity(&self) -> Option<Connectivity> {
+        self.connectivity.read().await.clone()
     }
 
     /// Fetches information for a given mod based on its name.
@@ -122,12 +202,63 @@ impl ModPortal {
             .join(FULL_ENDPOINT)?;
         debug!("Fetching mod info from {}", url);
 
-        Ok(self.get_json(url).await?)
+        Ok(self.get_json_cached(url).await?)
     }
 
-    /// Fetches information for multiple mods based on their names.
+    /// Fetches information for multiple mods based on their names. The names are requested in batches, each with as
+    /// few requests as the configured page size allows. Mods the portal doesn't know are omitted from the results.
     pub async fn fetch_multiple_mods(&self, names: &[&str]) -> anyhow::Result<Vec<PortalResult>> {
         let mut mods = Vec::new();
+        let page_size = match self.config.portal_page_size() {
+            util::Limit::Unlimited => String::from("max"),
+            util::Limit::Limited(limit) => limit.to_string(),
+        };
+
+        for batch in names.chunks(NAMELIST_MAX_NAMES) {
+            let mut batch_mods = Vec::new();
+            let mut current_page = 1;
+
+            loop {
+                let mut url = Url::parse(SITE_ROOT)?.join(API_ROOT)?;
+                url.query_pairs_mut()
+                    .append_pair("full", "True")
+                    .append_pair("page_size", &page_size)
+                    .append_pair("namelist", &batch.join(","))
+                    .append_pair("page", &current_page.to_string());
+                debug!("Fetching mod list from {} for {} mods", url, batch.len());
+
+                let mut mod_list: ModList<PortalResult> = self.get_json_cached(url).await?;
+                debug!(
+                    "Got mod list response. Mod count in this response: {}. Pagination: {:?}",
+                    mod_list.results.len(),
+                    mod_list.pagination
+                );
+                trace!("Got mod list: {:?}", mod_list);
+
+                let received = mod_list.results.len();
+                batch_mods.append(&mut mod_list.results);
+
+                // the results run out early if some of the names aren't on the portal
+                let has_next = mod_list
+                    .pagination
+                    .map_or(false, |pagination| pagination.links.next.is_some());
+                if batch_mods.len() >= batch.len() || received == 0 || !has_next {
+                    break;
+                }
+
+                current_page += 1;
+            }
+
+            mods.append(&mut batch_mods);
+        }
+
+        Ok(mods)
+    }
+
+    /// Searches the mod portal for mods matching a given query. The portal's mod list is requested one page at a time
+    /// and filtered until enough matching mods are found or there are no more pages.
+    pub async fn search(&self, query: &SearchQuery) -> anyhow::Result<Vec<SearchResult>> {
+        let mut results = Vec::new();
         let mut current_page = 1;
         let page_size = match self.config.portal_page_size() {
             util::Limit::Unlimited => String::from("max"),
@@ -137,37 +268,47 @@ impl ModPortal {
         loop {
             let mut url = Url::parse(SITE_ROOT)?.join(API_ROOT)?;
             url.query_pairs_mut()
-                .append_pair("full", "True")
                 .append_pair("page_size", &page_size)
-                .append_pair("namelist", &names.join(","))
                 .append_pair("page", &current_page.to_string());
-            debug!("Fetching mod list from {} for {} mods", url, names.len());
-
-            let mut mod_list: ModList = self.get_json(url).await?;
-            debug!(
-                "Got mod list response. Mod count in this response: {}. Pagination: {:?}",
-                mod_list.results.len(),
-                mod_list.pagination
-            );
-            trace!("Got mod list: {:?}", mod_list);
+            if !query.names.is_empty() {
+                url.query_pairs_mut().append_pair("namelist", &query.names.join(","));
+            }
+            debug!("Searching mod list page {} from {}", current_page, url);
 
-            mods.append(&mut mod_list.results);
+            let mod_list: ModList<SearchResult> = self.get_json(url, None).await?;
+            results.extend(mod_list.results.into_iter().filter(|result| query.matches(result)));
 
-            if mods.len() < names.len() {
-                current_page += 1;
-                continue;
+            let has_next = mod_list
+                .pagination
+                .map_or(false, |pagination| pagination.links.next.is_some());
+            if results.len() >= query.max_results || !has_next {
+                break;
             }
 
-            break;
+            current_page += 1;
         }
 
-        Ok(mods)
+        results.truncate(query.max_results);
+        debug!("Found {} mods matching {:?}", results.len(), query);
+        Ok(results)
     }
 
     /// Downloads a given mod its zip archive URL to a temporary location and copies it to the final
     /// given location. Returns the final location's path and the zip archive's size in the
-    /// filesystem.
-    pub async fn download_mod<P>(&self, name: &str, url_path: &str, directory: P) -> anyhow::Result<(PathBuf, usize)>
+    /// filesystem. The download is authenticated with the credentials of a given server, if any, or with the
+    /// configured credentials.
+    ///
+    /// A download that fails with a transient error, such as its connection being cut, is retried after a jittered
+    /// exponential backoff up to a fixed amount of attempts. Each retry is reported to a given progress channel, and
+    /// resumes from where the previous attempt was cut off if the portal honors range requests.
+    pub async fn download_mod<P>(
+        &self,
+        name: &str,
+        url_path: &str,
+        directory: P,
+        server: Option<GameStoreId>,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<(PathBuf, usize)>
     where
         P: AsRef<Path>,
     {
@@ -177,15 +318,41 @@ impl ModPortal {
             .join(url_path)?;
         debug!("Downloading mod from {}", download_url);
 
-        let mut response = self.get(download_url).await?;
-
         let mut temp = fs::File::from_std(tempfile()?);
-        let written = response.to_writer(&mut temp).await?;
+        let mut backoff = Backoff::new(DOWNLOAD_INITIAL_BACKOFF, DOWNLOAD_MAX_BACKOFF);
+        let mut attempt = 1;
+        let filename = loop {
+            match self.download_to_file(download_url.clone(), server, &mut temp).await {
+                Ok(filename) => break filename,
+                Err(e) if attempt < DOWNLOAD_ATTEMPTS && is_transient(&e) => {
+                    if !is_resumable(&e) {
+                        temp.set_len(0).await?;
+                    }
+
+                    let delay = backoff.next_delay();
+                    warn!(
+                        "Downloading mod {} failed (attempt {}/{}): {}. Retrying in {:?}",
+                        name, attempt, DOWNLOAD_ATTEMPTS, e, delay
+                    );
+                    prog_tx
+                        .send_status(async_status::indefinite(&format!(
+                            "Downloading {} failed: {}. Retrying ({}/{})...",
+                            name,
+                            e,
+                            attempt + 1,
+                            DOWNLOAD_ATTEMPTS
+                        )))
+                        .await?;
+
+                    time::delay_for(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        let written = temp.seek(SeekFrom::End(0)).await? as usize;
 
-        // TODO: if the URL's file name contains spaces, they'll be URL-encoded (%20). turn them back into proper
-        // characters
-        let filename = response.url_file_name()?;
-        let dest_path = directory.as_ref().join(filename);
+        let dest_path = directory.as_ref().join(&filename);
         debug!(
             "'{}' downloaded to tempfile, copying to destination ({})...",
             filename,
@@ -193,7 +360,7 @@ impl ModPortal {
         );
 
         let mut dest = fs::File::create(&dest_path).await?;
-        temp.seek(std::io::SeekFrom::Start(0)).await?;
+        temp.seek(SeekFrom::Start(0)).await?;
         io::copy(&mut temp, &mut dest).await?;
 
         Ok((dest_path, written))
@@ -201,21 +368,97 @@ impl ModPortal {
 }
 
 impl ModPortal {
-    /// GETs a given URL and returns the response. Will include the current mod portal credentials
-    /// in the request query.
-    async fn get(&self, url: Url) -> anyhow::Result<reqwest::Response> {
+    /// Downloads a given URL into a given file. If the file already has content, the download resumes after it with
+    /// a range request, or overwrites it if the response isn't partial content. Returns the file name in the response's
+    /// URL.
+    async fn download_to_file(
+        &self,
+        url: Url,
+        server: Option<GameStoreId>,
+        file: &mut fs::File,
+    ) -> anyhow::Result<String> {
+        let offset = file.seek(SeekFrom::End(0)).await?;
+        let mut response = self.get_from(url, server, offset).await?;
+
+        if offset > 0 {
+            if response.status() == StatusCode::PARTIAL_CONTENT {
+                debug!("Resuming download from {} bytes", offset);
+            } else {
+                debug!("Portal didn't honor the range request, restarting download");
+                file.set_len(0).await?;
+                file.seek(SeekFrom::Start(0)).await?;
+            }
+        }
+        response.to_writer(file).await?;
+
+        // TODO: if the URL's file name contains spaces, they'll be URL-encoded (%20). turn them back into proper
+        // characters
+        Ok(response.url_file_name()?.to_owned())
+    }
+
+    /// Returns the credentials used for requests made on behalf of a given server, or the configured credentials if
+    /// the server doesn't override them or no server is given.
+    async fn credentials(&self, server: Option<GameStoreId>) -> Credentials {
+        if let Some(server) = server {
+            if let Some(credentials) = self.server_credentials(server).await {
+                return credentials;
+            }
+        }
+
+        Credentials::new(
+            self.config.portal_username().to_owned(),
+            self.config.portal_token().to_owned(),
+        )
+    }
+
+    /// GETs a given URL and returns the response. Will include the mod portal credentials of a given server, or the
+    /// configured credentials, in the request query.
+    async fn get(&self, url: Url, server: Option<GameStoreId>) -> anyhow::Result<reqwest::Response> {
+        self.get_from(url, server, 0).await
+    }
+
+    /// GETs a given URL starting from a given byte offset and returns the response. A nonzero offset is requested with
+    /// a `Range` header, which the server may ignore and respond with the whole body. Will include the mod portal
+    /// credentials of a given server, or the configured credentials, in the request query.
+    async fn get_from(&self, url: Url, server: Option<GameStoreId>, offset: u64) -> anyhow::Result<reqwest::Response> {
+        let mut headers = HeaderMap::new();
+        if offset > 0 {
+            headers.insert(header::RANGE, HeaderValue::from_str(&format!("bytes={}-", offset))?);
+        }
+
+        self.get_with_headers(url, server, headers).await
+    }
+
+    /// GETs a given URL with given headers and returns the response. A `304 Not Modified` response is returned like a
+    /// successful one, since it only answers a conditional request the caller made. The request waits its turn if the
+    /// configured rate limit has been reached. Will include the mod portal credentials of a given server, or the
+    /// configured credentials, in the request query.
+    async fn get_with_headers(
+        &self,
+        url: Url,
+        server: Option<GameStoreId>,
+        headers: HeaderMap,
+    ) -> anyhow::Result<reqwest::Response> {
+        if self.config.offline() {
+            return Err(ModPortalError::OfflineMode.into());
+        }
+
+        let waited = self.limiter.acquire().await;
+        if waited > Duration::from_secs(0) {
+            debug!("Mod portal request to {} was rate limited for {:?}", url.path(), waited);
+        }
+
+        let credentials = self.credentials(server).await;
         let response = self
             .client
             .get(url.as_str())
-            .query(&[
-                ("username", self.config.portal_username()),
-                ("token", self.config.portal_token()),
-            ])
+            .query(&[("username", credentials.username()), ("token", credentials.token())])
+            .headers(headers)
             .send()
             .await?;
 
         let status = response.status();
-        if status.is_success() {
+        if status.is_success() || status == StatusCode::NOT_MODIFIED {
             Ok(response)
         } else if status.is_client_error() {
             debug!("Caught erroneus response. Body: {:?}", response.text().await);
@@ -229,24 +472,100 @@ impl ModPortal {
         }
     }
 
-    /// GETs a given URL and returns the response as a string. Will include the current mod portal
-    /// credentials in the request query.
-    async fn get_string(&self, url: Url) -> anyhow::Result<String> {
-        let response = self.get(url).await?;
+    /// GETs a given URL and returns the response as a string. Will include the mod portal credentials of a given
+    /// server, or the configured credentials, in the request query.
+    async fn get_string(&self, url: Url, server: Option<GameStoreId>) -> anyhow::Result<String> {
+        let response = self.get(url, server).await?;
         trace!("{:?}", response);
         Ok(response.text().await?)
     }
 
-    /// GETs a given URL and returns the response as a object deserialized from JSON. Will include
-    /// the current mod portal credentials in the request query.
-    async fn get_json<T>(&self, url: Url) -> anyhow::Result<T>
+    /// GETs a given URL and returns the response as a object deserialized from JSON. Will include the mod portal
+    /// credentials of a given server, or the configured credentials, in the request query.
+    async fn get_json<T>(&self, url: Url, server: Option<GameStoreId>) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let response = self.get_string(url, server).await?;
+        trace!("{}", response);
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// GETs a given URL like [`get_json`](#method.get_json) does with the configured credentials, but through the
+    /// response cache in the program store if there is one.
+    async fn get_json_cached<T>(&self, url: Url) -> anyhow::Result<T>
     where
         T: serde::de::DeserializeOwned,
     {
-        let response = self.get_string(url).await?;
+        let response = self.get_string_cached(url).await?;
         trace!("{}", response);
         Ok(serde_json::from_str(&response)?)
     }
+
+    /// GETs a given URL and returns the response as a string. If a response to the URL is cached in the program store,
+    /// the request is made conditional on the cached response's `ETag` and `Last-Modified` headers, and the cached
+    /// response is returned if the portal responds with `304 Not Modified` or `429 Too Many Requests`. Responses with
+    /// either header are cached, and caching one forgets the responses older than the program store expiry. Failing to
+    /// read or write the cache only logs a warning.
+    async fn get_string_cached(&self, url: Url) -> anyhow::Result<String> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return self.get_string(url, None).await,
+        };
+
+        let cached = store.get_portal_response(url.to_string()).await.unwrap_or_else(|e| {
+            warn!("Failed to read the cached mod portal response to {}: {}", url, e);
+            None
+        });
+
+        let mut headers = HeaderMap::new();
+        if let Some(cached) = &cached {
+            insert_header(&mut headers, header::IF_NONE_MATCH, cached.etag.as_deref());
+            insert_header(&mut headers, header::IF_MODIFIED_SINCE, cached.last_modified.as_deref());
+        }
+
+        let response = match (self.get_with_headers(url.clone(), None, headers).await, cached) {
+            (Ok(response), Some(cached)) if response.status() == StatusCode::NOT_MODIFIED => {
+                debug!(
+                    "{} not modified since {}, using cached response",
+                    url, cached.fetched_at
+                );
+                return Ok(cached.body.into_inner());
+            }
+            (Err(e), Some(cached)) if is_rate_limited(&e) => {
+                warn!(
+                    "Mod portal rate limited the request to {}, using cached response from {}",
+                    url, cached.fetched_at
+                );
+                return Ok(cached.body.into_inner());
+            }
+            (Ok(response), _) if response.status() == StatusCode::NOT_MODIFIED => {
+                return Err(ModPortalError::UnexpectedStatus(StatusCode::NOT_MODIFIED).into());
+            }
+            (result, _) => result?,
+        };
+
+        let etag = header_string(response.headers(), header::ETAG);
+        let last_modified = header_string(response.headers(), header::LAST_MODIFIED);
+        let body = response.text().await?;
+
+        if etag.is_some() || last_modified.is_some() {
+            let cached = PortalResponse {
+                url: url.to_string(),
+                etag,
+                last_modified,
+                body: CompressedText::from(body.clone()),
+                fetched_at: Utc::now(),
+            };
+
+            let expire_before = Utc::now() - chrono::Duration::seconds(self.config.store_expiry() as i64);
+            if let Err(e) = store.set_portal_response(cached, expire_before).await {
+                warn!("Failed to cache the mod portal response to {}: {}", url, e);
+            }
+        }
+
+        Ok(body)
+    }
 }
 
 impl PortalResult {
@@ -315,6 +634,16 @@ impl PortalResult {
         self.changelog.as_deref()
     }
 
+    /// Returns the result's category if it was present in the portal response.
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    /// Returns the result's tags or an empty slice if they were missing from the portal response.
+    pub fn tags(&self) -> &[String] {
+        self.tags.as_deref().unwrap_or_default()
+    }
+
     /// Removes redundant information from an info object returned by the mod portal.
     ///
     /// The function will:
@@ -329,3 +658,110 @@ impl PortalResult {
         Ok(())
     }
 }
+
+/// Inserts a given header with a given value into a given header map, if there's a value and it's a valid header value.
+fn insert_header(headers: &mut HeaderMap, name: HeaderName, value: Option<&str>) {
+    if let Some(value) = value.and_then(|value| HeaderValue::from_str(value).ok()) {
+        headers.insert(name, value);
+    }
+}
+
+/// Returns the value of a given header in a given header map as a string, if the header exists and is valid UTF-8.
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// Returns whether a given request error means the portal refused the request because of rate limiting.
+fn is_rate_limited(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<ModPortalError>(),
+        Some(ModPortalError::ClientError(StatusCode::TOO_MANY_REQUESTS))
+    )
+}
+
+/// Returns whether a given download error is likely transient, so that retrying the download may succeed.
+fn is_transient(error: &anyhow::Error) -> bool {
+    if let Some(e) = error.downcast_ref::<ResponseError>() {
+        e.is_length_mismatch()
+    } else if let Some(e) = error.downcast_ref::<ModPortalError>() {
+        matches!(
+            e,
+            ModPortalError::ServerError(_) | ModPortalError::ClientError(StatusCode::RANGE_NOT_SATISFIABLE)
+        )
+    } else if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+        e.is_timeout() || e.is_connect() || e.is_request() || e.is_body()
+    } else {
+        false
+    }
+}
+
+/// Returns whether a download that failed with a given error can be resumed from the content received before the
+/// failure. Otherwise the content is likely invalid and the download has to start over.
+fn is_resumable(error: &anyhow::Error) -> bool {
+    if let Some(e) = error.downcast_ref::<ResponseError>() {
+        matches!(e, ResponseError::Truncated { .. })
+    } else if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+        e.is_timeout() || e.is_body()
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retried_errors() {
+        let truncated = anyhow::Error::from(ResponseError::Truncated {
+            expected: 10,
+            received: 5,
+        });
+        assert!(is_transient(&truncated));
+        assert!(is_resumable(&truncated));
+
+        let oversized = anyhow::Error::from(ResponseError::Oversized {
+            expected: 10,
+            received: 11,
+        });
+        assert!(is_transient(&oversized));
+        assert!(!is_resumable(&oversized));
+
+        let unsatisfiable = anyhow::Error::from(ModPortalError::ClientError(StatusCode::RANGE_NOT_SATISFIABLE));
+        assert!(is_transient(&unsatisfiable));
+        assert!(!is_resumable(&unsatisfiable));
+
+        assert!(is_transient(
+            &ModPortalError::ServerError(StatusCode::BAD_GATEWAY).into()
+        ));
+        assert!(!is_transient(
+            &ModPortalError::ClientError(StatusCode::FORBIDDEN).into()
+        ));
+        assert!(!is_transient(&ModPortalError::OfflineMode.into()));
+        assert!(!is_transient(&ResponseError::NoFilename.into()));
+    }
+
+    #[test]
+    fn cache_headers() {
+        let mut headers = HeaderMap::new();
+        insert_header(&mut headers, header::IF_NONE_MATCH, Some("\"abc\""));
+        insert_header(&mut headers, header::IF_MODIFIED_SINCE, None);
+        insert_header(&mut headers, header::IF_MODIFIED_SINCE, Some("invalid\nvalue"));
+        assert_eq!(headers.len(), 1);
+        assert_eq!(
+            header_string(&headers, header::IF_NONE_MATCH).as_deref(),
+            Some("\"abc\"")
+        );
+        assert_eq!(header_string(&headers, header::ETAG), None);
+
+        assert!(is_rate_limited(
+            &ModPortalError::ClientError(StatusCode::TOO_MANY_REQUESTS).into()
+        ));
+        assert!(!is_rate_limited(
+            &ModPortalError::ServerError(StatusCode::SERVICE_UNAVAILABLE).into()
+        ));
+    }
+}
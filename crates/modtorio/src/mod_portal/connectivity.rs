@@ -0,0 +1,203 @@
+//! Provides the [`Connectivity`](Connectivity) report, which diagnoses the program's connectivity
+//! to the mod portal one stage at a time, so network problems can be told apart from the program's
+//! own.
+
+use super::SITE_ROOT;
+use crate::{error::ModPortalError, util::TimeZone};
+use chrono::{DateTime, Utc};
+use log::*;
+use reqwest::Client;
+use std::{
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpStream, time};
+use url::Url;
+
+/// The port used to connect to the mod portal.
+const HTTPS_PORT: u16 = 443;
+/// How long to wait for a single stage to complete before considering it failed.
+const STAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The result of a single connectivity check stage.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stage {
+    /// The stage passed in the given duration.
+    Passed(Duration),
+    /// The stage failed with the given error.
+    Failed(String),
+    /// The stage was skipped because an earlier stage failed.
+    Skipped,
+}
+
+/// A report of the program's connectivity to the mod portal.
+#[derive(Debug, Clone)]
+pub struct Connectivity {
+    /// Timestamp when the check was started.
+    checked_at: DateTime<Utc>,
+    /// Resolving the portal's host name.
+    dns: Stage,
+    /// Opening a TCP connection to the portal.
+    tcp: Stage,
+    /// Completing a TLS handshake with the portal.
+    tls: Stage,
+    /// Sending a HEAD request to the portal's site root.
+    head: Stage,
+}
+
+impl Stage {
+    /// Returns whether the stage passed.
+    pub fn passed(&self) -> bool {
+        matches!(self, Stage::Passed(_))
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Passed(latency) => write!(f, "{} ms", latency.as_millis()),
+            Stage::Failed(error) => write!(f, "failed: {}", error),
+            Stage::Skipped => f.write_str("skipped"),
+        }
+    }
+}
+
+impl fmt::Display for Connectivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "DNS {}, TCP {}, TLS {}, HEAD {}",
+            self.dns, self.tcp, self.tls, self.head
+        )
+    }
+}
+
+impl Connectivity {
+    /// Checks the connectivity to the mod portal, using a given client for the HEAD request. Each
+    /// stage is run only if the previous one passed.
+    pub async fn check(client: &Client) -> Self {
+        let checked_at = Utc::now();
+        let mut report = Self {
+            checked_at,
+            dns: Stage::Skipped,
+            tcp: Stage::Skipped,
+            tls: Stage::Skipped,
+            head: Stage::Skipped,
+        };
+
+        let host = match Url::parse(SITE_ROOT).map(|url| url.host_str().map(str::to_owned)) {
+            Ok(Some(host)) => host,
+            _ => {
+                report.dns = Stage::Failed(format!("invalid site root {}", SITE_ROOT));
+                return report;
+            }
+        };
+
+        let (dns, addr) = timed(async {
+            let mut addrs = tokio::net::lookup_host((host.as_str(), HTTPS_PORT)).await?;
+            addrs
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("{} resolved to no addresses", host))
+        })
+        .await;
+        report.dns = dns;
+        let addr: SocketAddr = match addr {
+            Some(addr) => addr,
+            None => return report,
+        };
+
+        let (tcp, stream) = timed(async { Ok(TcpStream::connect(addr).await?) }).await;
+        report.tcp = tcp;
+        let stream = match stream {
+            Some(stream) => stream,
+            None => return report,
+        };
+
+        let (tls, _) = timed(async {
+            let connector = tokio_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+            Ok(connector.connect(&host, stream).await?)
+        })
+        .await;
+        report.tls = tls;
+        if !report.tls.passed() {
+            return report;
+        }
+
+        let (head, _) = timed(async {
+            let status = client.head(SITE_ROOT).send().await?.status();
+            if status.is_success() {
+                Ok(())
+            } else {
+                Err(ModPortalError::UnexpectedStatus(status).into())
+            }
+        })
+        .await;
+        report.head = head;
+
+        debug!("Mod portal connectivity: {}", report);
+        report
+    }
+
+    /// Returns whether all stages of the check passed.
+    pub fn passed(&self) -> bool {
+        self.dns.passed() && self.tcp.passed() && self.tls.passed() && self.head.passed()
+    }
+
+    /// Returns the timestamp when the check was started.
+    pub fn checked_at(&self) -> DateTime<Utc> {
+        self.checked_at
+    }
+
+    /// Returns the report in RPC format, with its timestamp also localised to a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::PortalConnectivity {
+        rpc::PortalConnectivity {
+            checked_at: self.checked_at.timestamp(),
+            checked_at_local: time_zone.format(self.checked_at),
+            dns: Some((&self.dns).into()),
+            tcp: Some((&self.tcp).into()),
+            tls: Some((&self.tls).into()),
+            head: Some((&self.head).into()),
+        }
+    }
+}
+
+/// Runs a given future with a timeout, returning the stage's result and the future's output if it
+/// succeeded.
+async fn timed<F, T>(future: F) -> (Stage, Option<T>)
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    let start = Instant::now();
+    match time::timeout(STAGE_TIMEOUT, future).await {
+        Ok(Ok(output)) => (Stage::Passed(start.elapsed()), Some(output)),
+        Ok(Err(e)) => (Stage::Failed(e.to_string()), None),
+        Err(_) => (
+            Stage::Failed(format!("timed out after {} s", STAGE_TIMEOUT.as_secs())),
+            None,
+        ),
+    }
+}
+
+impl From<&Stage> for rpc::ConnectivityStage {
+    fn from(stage: &Stage) -> Self {
+        match stage {
+            Stage::Passed(latency) => Self {
+                result: rpc::connectivity_stage::Result::Passed.into(),
+                latency_millis: latency.as_millis() as u64,
+                error: String::new(),
+            },
+            Stage::Failed(error) => Self {
+                result: rpc::connectivity_stage::Result::Failed.into(),
+                latency_millis: 0,
+                error: error.clone(),
+            },
+            Stage::Skipped => Self {
+                result: rpc::connectivity_stage::Result::Skipped.into(),
+                latency_millis: 0,
+                error: String::new(),
+            },
+        }
+    }
+}
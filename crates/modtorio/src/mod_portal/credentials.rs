@@ -0,0 +1,149 @@
+//! Provides the [`Credentials`](Credentials) used to authenticate with the mod portal, and the
+//! [`CredentialKey`](CredentialKey) used to encrypt per-server credentials in the program store.
+
+use crate::{error::CredentialError, factorio::GameStoreId, store::models::PortalCredentials};
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+use std::{convert::TryInto, fmt};
+
+/// The length of a credential key in bytes.
+const KEY_LENGTH: usize = 32;
+/// The length of an encryption nonce in bytes.
+const NONCE_LENGTH: usize = 12;
+
+/// A username-token pair used to authenticate with the mod portal.
+#[derive(Clone, PartialEq)]
+pub struct Credentials {
+    /// The username.
+    username: String,
+    /// The token used to authenticate.
+    token: String,
+}
+
+/// The key per-server credentials' tokens are encrypted with in the program store.
+pub struct CredentialKey(ChaCha20Poly1305);
+
+impl Credentials {
+    /// Returns new `Credentials` with a given username and token.
+    pub fn new(username: String, token: String) -> Self {
+        Self { username, token }
+    }
+
+    /// Returns the username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Returns the token.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Returns the store format of these credentials for a given game, with the token encrypted with a given key.
+    pub fn to_store_format(
+        &self,
+        game: GameStoreId,
+        key: &CredentialKey,
+    ) -> Result<PortalCredentials, CredentialError> {
+        let mut nonce = [0; NONCE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let token = key
+            .0
+            .encrypt(&Nonce::from(nonce), self.token.as_bytes())
+            .map_err(|_| CredentialError::Encryption)?;
+
+        Ok(PortalCredentials {
+            game,
+            username: self.username.clone(),
+            token_nonce: nonce.to_vec(),
+            token,
+        })
+    }
+
+    /// Returns new `Credentials` from a given store format, decrypting the token with a given key.
+    pub fn from_store_format(store_format: &PortalCredentials, key: &CredentialKey) -> Result<Self, CredentialError> {
+        let nonce: [u8; NONCE_LENGTH] = store_format
+            .token_nonce
+            .as_slice()
+            .try_into()
+            .map_err(|_| CredentialError::Decryption)?;
+
+        let token = key
+            .0
+            .decrypt(&Nonce::from(nonce), store_format.token.as_slice())
+            .map_err(|_| CredentialError::Decryption)?;
+
+        Ok(Self {
+            username: store_format.username.clone(),
+            token: String::from_utf8(token).map_err(|_| CredentialError::Decryption)?,
+        })
+    }
+}
+
+// the token is never written to logs
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("token", &"<redacted>")
+            .finish()
+    }
+}
+
+impl CredentialKey {
+    /// Returns a new `CredentialKey` from a given hex string of 32 bytes.
+    pub fn from_hex(hex: &str) -> Result<Self, CredentialError> {
+        let bytes: [u8; KEY_LENGTH] = hex::decode(hex)
+            .map_err(|_| CredentialError::InvalidKey)?
+            .as_slice()
+            .try_into()
+            .map_err(|_| CredentialError::InvalidKey)?;
+
+        Ok(Self(ChaCha20Poly1305::new(&Key::from(bytes))))
+    }
+}
+
+impl fmt::Debug for CredentialKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CredentialKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn round_trip() {
+        let key = CredentialKey::from_hex(KEY).unwrap();
+        let credentials = Credentials::new(String::from("user"), String::from("secret-token"));
+
+        let stored = credentials.to_store_format(1, &key).unwrap();
+        assert_eq!(stored.username, "user");
+        assert_ne!(stored.token, b"secret-token");
+        assert_eq!(Credentials::from_store_format(&stored, &key).unwrap(), credentials);
+    }
+
+    #[test]
+    fn wrong_key() {
+        let key = CredentialKey::from_hex(KEY).unwrap();
+        let other = CredentialKey::from_hex(&KEY.replace("00", "ff")).unwrap();
+        let stored = Credentials::new(String::from("user"), String::from("secret-token"))
+            .to_store_format(1, &key)
+            .unwrap();
+
+        assert!(Credentials::from_store_format(&stored, &other).is_err());
+    }
+
+    #[test]
+    fn invalid_key() {
+        assert!(CredentialKey::from_hex("abcd").is_err());
+        assert!(CredentialKey::from_hex("not hex").is_err());
+    }
+}
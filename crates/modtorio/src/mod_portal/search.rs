@@ -0,0 +1,176 @@
+//! Provides the [`SearchQuery`](SearchQuery) and [`SearchResult`](SearchResult) objects used to search the mod portal
+//! for mods.
+
+use crate::util::HumanVersion;
+use serde::Deserialize;
+
+/// The default maximum amount of search results.
+const DEFAULT_MAX_RESULTS: usize = 25;
+
+/// A query for searching the mod portal for mods.
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    /// Text searched for in the mods' names and titles, case-insensitively. Empty matches every mod.
+    pub text: String,
+    /// The category the mods must be in, if any.
+    pub category: Option<String>,
+    /// The exact names of the mods to search, if only certain mods are searched.
+    pub names: Vec<String>,
+    /// The maximum amount of results.
+    pub max_results: usize,
+}
+
+/// A single mod found by searching the mod portal.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SearchResult {
+    /// The mod's name.
+    name: String,
+    /// The mod's title.
+    title: String,
+    /// The mod's author.
+    owner: String,
+    /// The mod's summary.
+    #[serde(default)]
+    summary: String,
+    /// How many times the mod has been downloaded.
+    #[serde(default)]
+    downloads_count: u64,
+    /// The mod's category, if any.
+    category: Option<String>,
+    /// The mod's latest release, if it has any.
+    latest_release: Option<LatestRelease>,
+}
+
+/// The latest release of a mod found by searching the mod portal.
+#[derive(Debug, Deserialize, PartialEq)]
+struct LatestRelease {
+    /// The release's version.
+    version: HumanVersion,
+    /// The release's `info.json`.
+    info_json: LatestReleaseInfo,
+}
+
+/// The `info.json` of the latest release of a mod found by searching the mod portal.
+#[derive(Debug, Deserialize, PartialEq)]
+struct LatestReleaseInfo {
+    /// The release's required Factorio version.
+    factorio_version: HumanVersion,
+}
+
+impl SearchQuery {
+    /// Returns a new `SearchQuery` from a given RPC `SearchModsRequest`.
+    pub fn from_rpc_format(rpc_format: rpc::SearchModsRequest) -> Self {
+        Self {
+            text: rpc_format.query,
+            category: Some(rpc_format.category).filter(|category| !category.is_empty()),
+            names: rpc_format.names,
+            max_results: match rpc_format.max_results {
+                0 => DEFAULT_MAX_RESULTS,
+                max => max as usize,
+            },
+        }
+    }
+
+    /// Returns whether a given search result matches this query.
+    pub fn matches(&self, result: &SearchResult) -> bool {
+        let text = self.text.to_lowercase();
+        let text_matches = result.name.to_lowercase().contains(&text) || result.title.to_lowercase().contains(&text);
+        let category_matches = self
+            .category
+            .as_ref()
+            .map_or(true, |category| result.category.as_ref() == Some(category));
+
+        text_matches && category_matches
+    }
+}
+
+impl From<SearchResult> for rpc::mod_search_results::Result {
+    fn from(result: SearchResult) -> Self {
+        let (latest_version, factorio_version) = match result.latest_release {
+            Some(release) => (
+                Some(release.version.into()),
+                Some(release.info_json.factorio_version.into()),
+            ),
+            None => (None, None),
+        };
+
+        Self {
+            name: result.name,
+            title: result.title,
+            owner: result.owner,
+            summary: result.summary,
+            downloads_count: result.downloads_count,
+            category: result.category.unwrap_or_default(),
+            latest_version,
+            factorio_version,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, title: &str, category: Option<&str>) -> SearchResult {
+        SearchResult {
+            name: name.to_owned(),
+            title: title.to_owned(),
+            owner: String::from("owner"),
+            summary: String::new(),
+            downloads_count: 0,
+            category: category.map(str::to_owned),
+            latest_release: None,
+        }
+    }
+
+    #[test]
+    fn matches() {
+        let query = SearchQuery {
+            text: String::from("belt"),
+            category: Some(String::from("tweaks")),
+            ..SearchQuery::default()
+        };
+
+        assert!(query.matches(&result("miniloader", "Belt Loaders", Some("tweaks"))));
+        assert!(query.matches(&result("bobbelts", "Bob's Logistics", Some("tweaks"))));
+        assert!(!query.matches(&result("bobbelts", "Bob's Logistics", Some("content"))));
+        assert!(!query.matches(&result("bobbelts", "Bob's Logistics", None)));
+        assert!(!query.matches(&result("helmod", "Helmod", Some("tweaks"))));
+        assert!(SearchQuery::default().matches(&result("helmod", "Helmod", None)));
+    }
+
+    #[test]
+    fn deserialize_list_result() {
+        let result: SearchResult = serde_json::from_str(
+            r#"{
+  "name": "helmod",
+  "title": "Helmod",
+  "owner": "Helfima",
+  "summary": "Production planner",
+  "downloads_count": 1000,
+  "category": "tweaks",
+  "score": 12.5,
+  "latest_release": {
+    "download_url": "/download/helmod/5f0",
+    "file_name": "helmod_0.12.5.zip",
+    "info_json": { "factorio_version": "1.0" },
+    "released_at": "2020-09-01T12:00:00.000000Z",
+    "version": "0.12.5",
+    "sha1": "abc"
+  }
+}"#,
+        )
+        .unwrap();
+
+        let rpc_result = rpc::mod_search_results::Result::from(result);
+        assert_eq!(rpc_result.downloads_count, 1000);
+        assert_eq!(
+            rpc_result.latest_version,
+            Some(rpc::Version {
+                major: 0,
+                minor: 12,
+                patch: 5
+            })
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Provides the [`ModPreset`](ModPreset) object, a named preset of which of a server's mods are enabled, such as
+//! "vanilla-friendly" or "full pack". Presets are saved from the server's `mod-list.json` into the program store, and
+//! applying one enables and disables the server's mods all at once instead of toggling them one by one.
+
+use crate::{
+    factorio::{
+        mods::{ModList, Mods},
+        GameStoreId,
+    },
+    store::{models::GameModPreset, Store},
+    util::TimeZone,
+};
+use chrono::{DateTime, Utc};
+use log::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A named preset of which of a server's mods are enabled.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ModPreset {
+    /// The preset's name.
+    name: String,
+    /// Whether each mod is enabled, keyed by the mods' names.
+    mods: BTreeMap<String, bool>,
+    /// Timestamp when the preset was saved.
+    created_at: DateTime<Utc>,
+}
+
+impl ModPreset {
+    /// Returns a new `ModPreset` with a given name of given mods' current enabled states. The states are read from
+    /// the mod list in the mods' directory; installed mods that aren't listed are enabled, like the game does.
+    pub async fn capture(name: String, mods: &Mods) -> anyhow::Result<Self> {
+        let mod_list = ModList::load(mods.directory()).await?;
+        Ok(Self::from_mod_list(name, &mod_list, &installed_names(mods).await))
+    }
+
+    /// Loads a given game's presets from the program store, sorted by their names.
+    pub async fn load_all(store: &Store, game: GameStoreId) -> anyhow::Result<Vec<Self>> {
+        let mut presets = store
+            .get_mod_presets(game)
+            .await?
+            .into_iter()
+            .map(|stored| {
+                Ok(Self {
+                    name: stored.name,
+                    mods: serde_json::from_str(&stored.mods)?,
+                    created_at: stored.created_at,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(presets)
+    }
+
+    /// Loads a given game's preset with a given name from the program store. Returns `None` if it doesn't exist.
+    pub async fn load(store: &Store, game: GameStoreId, name: &str) -> anyhow::Result<Option<Self>> {
+        Ok(Self::load_all(store, game)
+            .await?
+            .into_iter()
+            .find(|preset| preset.name == name))
+    }
+
+    /// Stores the preset as a given game's preset to the program store, replacing its earlier preset with the same
+    /// name.
+    pub async fn save(&self, store: &Store, game: GameStoreId) -> anyhow::Result<()> {
+        store
+            .set_mod_preset(GameModPreset {
+                game,
+                name: self.name.clone(),
+                mods: serde_json::to_string(&self.mods)?,
+                created_at: self.created_at,
+            })
+            .await
+    }
+
+    /// Enables the preset's enabled mods among given mods and disables every other one, and writes the mod list back
+    /// into its file. Returns the names of the preset's mods that are neither installed nor listed, which are skipped.
+    pub async fn apply(&self, mods: &Mods) -> anyhow::Result<Vec<String>> {
+        let mut mod_list = ModList::load(mods.directory()).await?;
+        let missing = self.apply_to(&mut mod_list, &installed_names(mods).await);
+        mod_list.save().await?;
+
+        if !missing.is_empty() {
+            warn!(
+                "Mod preset {} has mods that aren't installed: {}",
+                self.name,
+                missing.join(", ")
+            );
+        }
+        info!("Applied mod preset {}", self.name);
+        Ok(missing)
+    }
+
+    /// Returns the preset's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the preset in RPC format, with its timestamp also localised to a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::mod_preset_list::ModPreset {
+        let names = |enabled: bool| {
+            self.mods
+                .iter()
+                .filter(|(_, state)| **state == enabled)
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+
+        rpc::mod_preset_list::ModPreset {
+            name: self.name.clone(),
+            enabled_mods: names(true),
+            disabled_mods: names(false),
+            created_at: self.created_at.timestamp(),
+            created_at_local: time_zone.format(self.created_at),
+        }
+    }
+
+    /// Returns a new `ModPreset` with a given name of the enabled states in a given mod list, with given installed
+    /// mods that aren't listed enabled.
+    fn from_mod_list(name: String, mod_list: &ModList, installed: &[String]) -> Self {
+        let mut mods = mod_list.states().into_iter().collect::<BTreeMap<_, _>>();
+        for name in installed {
+            mods.entry(name.clone()).or_insert(true);
+        }
+
+        Self {
+            name,
+            mods,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Enables the preset's enabled mods among the mods listed in a given mod list and given installed mods, and
+    /// disables every other one. Returns the names of the preset's mods that are neither listed nor installed.
+    fn apply_to(&self, mod_list: &mut ModList, installed: &[String]) -> Vec<String> {
+        let known = mod_list
+            .states()
+            .into_iter()
+            .map(|(name, _)| name)
+            .chain(installed.iter().cloned())
+            .collect::<BTreeSet<_>>();
+
+        for name in &known {
+            mod_list.set_enabled(name, self.mods.get(name).copied().unwrap_or(false));
+        }
+
+        self.mods
+            .keys()
+            .filter(|name| !known.contains(*name))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Returns the names of given installed mods.
+async fn installed_names(mods: &Mods) -> Vec<String> {
+    let mut names = Vec::new();
+    for fact_mod in mods.iter() {
+        names.push(fact_mod.name().await);
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn capture_and_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("mod-list.json"),
+            r#"{"mods": [{"name": "base", "enabled": true}, {"name": "gears", "enabled": false}]}"#,
+        )
+        .unwrap();
+        let installed = vec![String::from("gears"), String::from("belts")];
+
+        let mod_list = ModList::load(dir.path()).await.unwrap();
+        let preset = ModPreset::from_mod_list(String::from("vanilla"), &mod_list, &installed);
+        let states = preset
+            .mods
+            .iter()
+            .map(|(name, enabled)| (name.as_str(), *enabled))
+            .collect::<Vec<_>>();
+        assert_eq!(states, vec![("base", true), ("belts", true), ("gears", false)]);
+
+        let mut full = preset.clone();
+        full.mods.insert(String::from("gears"), true);
+        full.mods.remove("belts");
+        full.mods.insert(String::from("rails"), true);
+
+        let mut mod_list = ModList::load(dir.path()).await.unwrap();
+        assert_eq!(full.apply_to(&mut mod_list, &installed), vec![String::from("rails")]);
+        assert_eq!(mod_list.enabled("base"), Some(true));
+        assert_eq!(mod_list.enabled("gears"), Some(true));
+        assert_eq!(mod_list.enabled("belts"), Some(false));
+        assert_eq!(mod_list.enabled("rails"), None);
+    }
+}
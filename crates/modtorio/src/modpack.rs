@@ -0,0 +1,392 @@
+//! Provides [`Modpack`](Modpack), a portable manifest of a server's mod set: every mod's name, version and whether
+//! it's enabled, and optionally the mod settings. A modpack exported from one server can be applied to another, which
+//! downloads the mods it's missing from the mod portal.
+//!
+//! Modpack manifests are written as JSON or TOML, chosen by the manifest file's extension.
+
+use crate::{
+    error::ModpackError,
+    factorio::mods::{ModList, Mods},
+    util::{
+        async_status::{self, AsyncProgressChannel, AsyncProgressChannelExt},
+        HumanVersion,
+    },
+};
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::Path};
+use tokio::fs;
+
+/// The name of the file in a mods directory that holds the mod settings.
+const MOD_SETTINGS_FILE: &str = "mod-settings.dat";
+
+/// The format of a modpack manifest.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Format {
+    /// JSON, with the `.json` extension.
+    Json,
+    /// TOML, with the `.toml` extension.
+    Toml,
+}
+
+/// A portable manifest of a server's mod set.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Modpack {
+    /// The contents of the mod settings file, base64-encoded, if they were exported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    settings: Option<String>,
+    /// The mods, sorted by name.
+    #[serde(default)]
+    mods: Vec<Entry>,
+}
+
+/// A single mod in a modpack.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The mod's name.
+    name: String,
+    /// The mod's version.
+    version: HumanVersion,
+    /// Whether the mod is enabled.
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+/// A single change that makes a server's mods match a modpack.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Change {
+    /// A mod is installed in a version, replacing any other installed version of it.
+    Install { name: String, version: HumanVersion },
+    /// A mod that isn't in the modpack is removed.
+    Remove { name: String },
+    /// A mod is enabled or disabled.
+    SetEnabled { name: String, enabled: bool },
+}
+
+/// Returns that mods are enabled unless a modpack says otherwise.
+#[doc(hidden)]
+fn default_enabled() -> bool {
+    true
+}
+
+impl Format {
+    /// Returns the format of a modpack manifest at a given path based on its extension. Returns
+    /// [`ModpackError::UnknownFormat`](crate::error::ModpackError::UnknownFormat) for other extensions.
+    pub fn from_path<P>(path: P) -> Result<Self, ModpackError>
+    where
+        P: AsRef<Path>,
+    {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("toml") => Ok(Self::Toml),
+            _ => Err(ModpackError::UnknownFormat(path.as_ref().to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Install { name, version } => write!(f, "Install {} {}", name, version),
+            Self::Remove { name } => write!(f, "Remove {}", name),
+            Self::SetEnabled { name, enabled: true } => write!(f, "Enable {}", name),
+            Self::SetEnabled { name, enabled: false } => write!(f, "Disable {}", name),
+        }
+    }
+}
+
+impl Modpack {
+    /// Returns a modpack of given mods, with their enabled states from the mod list in their directory. If
+    /// `include_settings` is set, the mod settings file is included as well, if it exists.
+    pub async fn export(mods: &Mods, include_settings: bool) -> anyhow::Result<Self> {
+        let mod_list = ModList::load(mods.directory()).await?;
+        let mut entries = Vec::new();
+
+        for fact_mod in mods.iter() {
+            let name = fact_mod.name().await;
+            entries.push(Entry {
+                enabled: mod_list.enabled(&name).unwrap_or(true),
+                version: fact_mod.own_version().await?,
+                name,
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let settings_path = mods.directory().join(MOD_SETTINGS_FILE);
+        let settings = if include_settings && settings_path.exists() {
+            Some(base64::encode(fs::read(&settings_path).await?))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            settings,
+            mods: entries,
+        })
+    }
+
+    /// Reads a modpack manifest from a given path, in the format of its extension.
+    pub async fn read<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let format = Format::from_path(&path)?;
+        let manifest = fs::read_to_string(path).await?;
+        Self::parse(&manifest, format)
+    }
+
+    /// Writes the modpack as a manifest into a given path, in the format of its extension.
+    pub async fn write<P>(&self, path: P) -> anyhow::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let format = Format::from_path(&path)?;
+        fs::write(path, self.to_manifest(format)?).await?;
+        Ok(())
+    }
+
+    /// Installs the modpack's mods into given mods, downloading the ones that aren't installed in the same version,
+    /// and enables exactly the modpack's enabled mods in the mod list. The mod settings are replaced with the
+    /// modpack's, if it has any. A mod that isn't already installed has to pass the mod policy, unless
+    /// `override_policy` is set.
+    pub async fn apply(
+        &self,
+        mods: &mut Mods,
+        override_policy: bool,
+        prog_tx: &AsyncProgressChannel,
+    ) -> anyhow::Result<()> {
+        let settings = self.settings.as_deref().map(base64::decode).transpose()?;
+        let max_mods = self.mods.len() as u32;
+
+        for (index, entry) in self.mods.iter().enumerate() {
+            let mut installed = None;
+            for fact_mod in mods.iter() {
+                if fact_mod.name().await == entry.name {
+                    installed = Some(fact_mod.own_version().await?);
+                    break;
+                }
+            }
+
+            if installed == Some(entry.version) {
+                debug!("Modpack mod {} {} already installed", entry.name, entry.version);
+                continue;
+            }
+
+            prog_tx
+                .send_status(async_status::definite(
+                    &format!("Installing {} {}...", entry.name, entry.version),
+                    index as u32,
+                    max_mods,
+                ))
+                .await?;
+            mods.add_from_portal(&entry.name, Some(entry.version), override_policy, false, None)
+                .await?;
+        }
+
+        let mut mod_list = ModList::load(mods.directory()).await?;
+        for fact_mod in mods.iter() {
+            let name = fact_mod.name().await;
+            let enabled = self
+                .mods
+                .iter()
+                .find(|entry| entry.name == name)
+                .map_or(false, |entry| entry.enabled);
+            mod_list.set_enabled(&name, enabled);
+        }
+        mod_list.save().await?;
+
+        if let Some(settings) = settings {
+            debug!("Replacing mod settings from modpack");
+            fs::write(mods.directory().join(MOD_SETTINGS_FILE), settings).await?;
+        }
+
+        info!("Applied modpack of {} mods", self.mods.len());
+        Ok(())
+    }
+
+    /// Returns the changes that make given mods match the modpack's mods, their versions and their enabled states. The
+    /// mod settings aren't compared.
+    pub async fn plan(&self, mods: &Mods) -> anyhow::Result<Vec<Change>> {
+        let installed = Self::export(mods, false).await?;
+        Ok(self.diff(&installed))
+    }
+
+    /// Makes given mods match the modpack: removes the mods that aren't in it, installs the ones that aren't installed
+    /// in the same version and enables exactly the modpack's enabled mods. Unlike [`apply`](Modpack::apply), the mods
+    /// the modpack doesn't have are removed instead of disabled. Returns the changes that were made.
+    pub async fn sync(
+        &self,
+        mods: &mut Mods,
+        override_policy: bool,
+        prog_tx: &AsyncProgressChannel,
+    ) -> anyhow::Result<Vec<Change>> {
+        let changes = self.plan(mods).await?;
+
+        for change in &changes {
+            if let Change::Remove { name } = change {
+                prog_tx
+                    .send_status(async_status::indefinite(&format!("Removing {}...", name)))
+                    .await?;
+                mods.remove(name, None, false, false).await?;
+            }
+        }
+
+        self.apply(mods, override_policy, prog_tx).await?;
+        Ok(changes)
+    }
+
+    /// Returns the modpack in its program store format, a JSON manifest.
+    pub fn to_store_format(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Returns a modpack from its program store format.
+    pub fn from_store_format(store_format: &str) -> anyhow::Result<Self> {
+        Self::parse(store_format, Format::Json)
+    }
+
+    /// Returns the changes that make the mods of a given installed modpack match this modpack. Newly installed mods are
+    /// assumed to be enabled.
+    fn diff(&self, installed: &Modpack) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        for entry in &self.mods {
+            let existing = installed.mods.iter().find(|existing| existing.name == entry.name);
+            if existing.map(|existing| existing.version) != Some(entry.version) {
+                changes.push(Change::Install {
+                    name: entry.name.clone(),
+                    version: entry.version,
+                });
+            }
+
+            if existing.map_or(true, |existing| existing.enabled) != entry.enabled {
+                changes.push(Change::SetEnabled {
+                    name: entry.name.clone(),
+                    enabled: entry.enabled,
+                });
+            }
+        }
+
+        for existing in &installed.mods {
+            if !self.mods.iter().any(|entry| entry.name == existing.name) {
+                changes.push(Change::Remove {
+                    name: existing.name.clone(),
+                });
+            }
+        }
+
+        changes
+    }
+
+    /// Parses a modpack from a given manifest in a given format.
+    fn parse(manifest: &str, format: Format) -> anyhow::Result<Self> {
+        Ok(match format {
+            Format::Json => serde_json::from_str(manifest)?,
+            Format::Toml => toml::from_str(manifest)?,
+        })
+    }
+
+    /// Returns the modpack as a manifest in a given format.
+    fn to_manifest(&self, format: Format) -> anyhow::Result<String> {
+        Ok(match format {
+            Format::Json => serde_json::to_string_pretty(self)?,
+            Format::Toml => toml::to_string(self)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modpack() -> Modpack {
+        Modpack {
+            settings: Some(base64::encode(b"settings")),
+            mods: vec![
+                Entry {
+                    name: String::from("belts"),
+                    version: HumanVersion::new(0, 18, 2),
+                    enabled: false,
+                },
+                Entry {
+                    name: String::from("gears"),
+                    version: HumanVersion::new(1, 0, 0),
+                    enabled: true,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(Format::from_path("pack.json").unwrap(), Format::Json);
+        assert_eq!(Format::from_path("dir/pack.toml").unwrap(), Format::Toml);
+        assert!(Format::from_path("pack.yaml").is_err());
+        assert!(Format::from_path("pack").is_err());
+    }
+
+    #[test]
+    fn manifest_roundtrip() {
+        for format in &[Format::Json, Format::Toml] {
+            let manifest = modpack().to_manifest(*format).unwrap();
+            assert_eq!(Modpack::parse(&manifest, *format).unwrap(), modpack());
+        }
+    }
+
+    #[test]
+    fn store_format() {
+        let store_format = modpack().to_store_format().unwrap();
+        assert_eq!(Modpack::from_store_format(&store_format).unwrap(), modpack());
+    }
+
+    #[test]
+    fn enabled_by_default() {
+        let parsed = Modpack::parse(
+            r#"[[mods]]
+            name = "gears"
+            version = "1.0.0""#,
+            Format::Toml,
+        )
+        .unwrap();
+
+        assert_eq!(parsed.settings, None);
+        assert!(parsed.mods[0].enabled);
+    }
+
+    #[test]
+    fn diff() {
+        let installed = Modpack {
+            settings: None,
+            mods: vec![
+                Entry {
+                    name: String::from("belts"),
+                    version: HumanVersion::new(0, 18, 2),
+                    enabled: true,
+                },
+                Entry {
+                    name: String::from("trains"),
+                    version: HumanVersion::new(2, 1, 0),
+                    enabled: true,
+                },
+            ],
+        };
+
+        assert_eq!(
+            modpack().diff(&installed),
+            vec![
+                Change::SetEnabled {
+                    name: String::from("belts"),
+                    enabled: false,
+                },
+                Change::Install {
+                    name: String::from("gears"),
+                    version: HumanVersion::new(1, 0, 0),
+                },
+                Change::Remove {
+                    name: String::from("trains"),
+                },
+            ]
+        );
+        assert!(modpack().diff(&modpack()).is_empty());
+    }
+}
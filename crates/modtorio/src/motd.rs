@@ -0,0 +1,145 @@
+//! Provides the [`Motd`](Motd) object, an instance-wide message-of-the-day shown to every operator of the
+//! Modtorio instance.
+
+use crate::{
+    store::{option, Store},
+    util::TimeZone,
+};
+use chrono::{DateTime, Utc};
+
+/// An instance-wide message-of-the-day.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Motd {
+    /// The message.
+    message: String,
+    /// The name of who set the message.
+    author: String,
+    /// Timestamp when the message was set.
+    set_at: DateTime<Utc>,
+}
+
+impl Motd {
+    /// Returns a new `Motd` with a given message and author, set at the current time.
+    pub fn new(message: String, author: String) -> Self {
+        Self {
+            message,
+            author,
+            set_at: Utc::now(),
+        }
+    }
+
+    /// Loads the message-of-the-day from the program store. Returns `None` if one hasn't been set.
+    pub async fn load(store: &Store) -> anyhow::Result<Option<Self>> {
+        let message = match get_option_value(store, option::Field::Motd).await? {
+            Some(message) => message,
+            None => return Ok(None),
+        };
+        let author = get_option_value(store, option::Field::MotdAuthor)
+            .await?
+            .unwrap_or_default();
+        let set_at = match get_option_value(store, option::Field::MotdSetAt).await? {
+            Some(set_at) => DateTime::parse_from_rfc3339(&set_at)?.with_timezone(&Utc),
+            None => Utc::now(),
+        };
+
+        Ok(Some(Self {
+            message,
+            author,
+            set_at,
+        }))
+    }
+
+    /// Stores the message-of-the-day to the program store, replacing any previous one.
+    pub async fn save(&self, store: &Store) -> anyhow::Result<()> {
+        store.begin_transaction()?;
+        let result = async {
+            store
+                .set_option(option::Value::new(option::Field::Motd, Some(self.message.clone())))
+                .await?;
+            store
+                .set_option(option::Value::new(option::Field::MotdAuthor, Some(self.author.clone())))
+                .await?;
+            store
+                .set_option(option::Value::new(
+                    option::Field::MotdSetAt,
+                    Some(self.set_at.to_rfc3339()),
+                ))
+                .await
+        }
+        .await;
+
+        store.finish_transaction(result)
+    }
+
+    /// Removes the message-of-the-day from the program store.
+    pub async fn clear(store: &Store) -> anyhow::Result<()> {
+        store.begin_transaction()?;
+        let result = async {
+            for field in &[option::Field::Motd, option::Field::MotdAuthor, option::Field::MotdSetAt] {
+                store.set_option(option::Value::new(*field, None)).await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        store.finish_transaction(result)
+    }
+
+    /// Returns the message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the name of who set the message.
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// Returns the timestamp when the message was set.
+    pub fn set_at(&self) -> DateTime<Utc> {
+        self.set_at
+    }
+
+    /// Returns the message in RPC format, with its timestamp also localised to a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::Motd {
+        rpc::Motd {
+            message: self.message.clone(),
+            author: self.author.clone(),
+            set_at: self.set_at.timestamp(),
+            set_at_local: time_zone.format(self.set_at),
+        }
+    }
+}
+
+/// Returns a given option's string value from the program store, or `None` if the option or its value is missing.
+async fn get_option_value(store: &Store, field: option::Field) -> anyhow::Result<Option<String>> {
+    Ok(store.get_option(field).await?.and_then(option::Value::take_value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{self, StoreLocation};
+
+    #[tokio::test]
+    async fn save_load_clear() {
+        let store = store::Builder::<String>::from_location(StoreLocation::Memory)
+            .build()
+            .await
+            .expect("failed to build test store");
+        assert_eq!(Motd::load(&store).await.expect("failed to load motd"), None);
+
+        let motd = Motd::new(String::from("maintenance window Sunday"), String::from("admin"));
+        motd.save(&store).await.expect("failed to save motd");
+        let loaded = Motd::load(&store)
+            .await
+            .expect("failed to load motd")
+            .expect("store returned no motd");
+        assert_eq!(loaded.message(), "maintenance window Sunday");
+        assert_eq!(loaded.author(), "admin");
+        assert_eq!(loaded.set_at().timestamp(), motd.set_at().timestamp());
+
+        Motd::clear(&store).await.expect("failed to clear motd");
+        assert_eq!(Motd::load(&store).await.expect("failed to load motd"), None);
+    }
+}
@@ -1,8 +1,8 @@
 //! Provides the [`Opts`](Opts) struct, used to read and access the program's command line
 //! arguments.
 
-use crate::{config, util::LogLevel};
-use clap::{App, Arg, ArgMatches};
+use crate::{auth::Scope, config, util::LogLevel};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use std::path::PathBuf;
 use strum::VariantNames;
 
@@ -21,6 +21,45 @@ pub struct Opts {
     pub log_level: Option<LogLevel>,
     /// The program store expiry in seconds.
     pub store_expiry: Option<u64>,
+    /// Whether to run in offline mode, without any network calls.
+    pub offline: bool,
+    /// The subcommand to run instead of the program itself, if any.
+    pub subcommand: Option<Subcommand>,
+}
+
+/// The subcommands the program supports.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Subcommand {
+    /// Checks the runtime environment for common problems and prints the findings.
+    Doctor,
+    /// Manages the tokens that authorize RPC requests.
+    Token(TokenCommand),
+    /// Feeds a saved server log through the game event parsers and prints each line's result.
+    Replay {
+        /// The log file to replay.
+        log: PathBuf,
+        /// Whether to print only the lines that didn't parse.
+        unparsed_only: bool,
+    },
+}
+
+/// The actions of the `token` subcommand.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenCommand {
+    /// Creates a new token and prints it.
+    Add {
+        /// The new token's name.
+        name: String,
+        /// The scopes the new token authorizes.
+        scopes: Vec<Scope>,
+    },
+    /// Lists every token.
+    List,
+    /// Revokes a token.
+    Revoke {
+        /// The revoked token's name.
+        name: String,
+    },
 }
 
 impl Opts {
@@ -75,6 +114,62 @@ impl Opts {
                     .takes_value(true)
                     .help("Specify the store expiry time."),
             )
+            .arg(Arg::with_name("offline").long("offline").help(
+                "Run in offline mode: the mod portal and factorio.com aren't contacted and scheduled mod updates are \
+                 skipped. Imports, statuses and server control keep working with local data.",
+            ))
+            .subcommand(
+                SubCommand::with_name("doctor")
+                    .about("Checks the runtime environment for common problems and prints actionable findings."),
+            )
+            .subcommand(
+                SubCommand::with_name("token")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .about(
+                        "Manages the tokens that authorize RPC requests. Requests are authorized only once at least \
+                         one token exists.",
+                    )
+                    .subcommand(
+                        SubCommand::with_name("add")
+                            .about("Creates a new token and prints it. The token can't be shown again later.")
+                            .arg(Arg::with_name("name").required(true).help("The token's name."))
+                            .arg(
+                                Arg::with_name("scope")
+                                    .long("scope")
+                                    .value_name("SCOPE")
+                                    .possible_values(Scope::VARIANTS)
+                                    .multiple(true)
+                                    .number_of_values(1)
+                                    .required(true)
+                                    .help("A scope the token authorizes. May be given multiple times."),
+                            ),
+                    )
+                    .subcommand(SubCommand::with_name("list").about("Lists every token and their scopes."))
+                    .subcommand(
+                        SubCommand::with_name("revoke")
+                            .about("Revokes a token.")
+                            .arg(Arg::with_name("name").required(true).help("The token's name.")),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("replay")
+                    .about(
+                        "Feeds a saved server log through the game event parsers, including the ones in the config \
+                         file, and prints each line's parse result and timing followed by a summary. Primarily used \
+                         for validating parser changes against real-world logs.",
+                    )
+                    .arg(
+                        Arg::with_name("log")
+                            .value_name("FILE")
+                            .required(true)
+                            .help("The server log file to replay."),
+                    )
+                    .arg(
+                        Arg::with_name("unparsed-only")
+                            .long("unparsed-only")
+                            .help("Print only the lines that didn't parse into any event."),
+                    ),
+            )
     }
 
     /// Returns a new `Opts` object from a given set of matched command line parameters.
@@ -93,6 +188,36 @@ impl Opts {
             store_expiry: matches
                 .value_of("store-expiry")
                 .map(|s| s.parse().expect("failed to parse value as u64")),
+            offline: matches.is_present("offline"),
+            subcommand: match matches.subcommand() {
+                ("doctor", _) => Some(Subcommand::Doctor),
+                ("token", Some(token)) => Opts::token_command_from_matches(token).map(Subcommand::Token),
+                ("replay", Some(replay)) => Some(Subcommand::Replay {
+                    log: replay.value_of_os("log").expect("replay log has no value").into(),
+                    unparsed_only: replay.is_present("unparsed-only"),
+                }),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns the `token` subcommand's action from its matched command line parameters, or `None` if no action was
+    /// given.
+    fn token_command_from_matches(matches: &ArgMatches) -> Option<TokenCommand> {
+        match matches.subcommand() {
+            ("add", Some(add)) => Some(TokenCommand::Add {
+                name: add.value_of("name").expect("token name has no value").to_owned(),
+                scopes: add
+                    .values_of("scope")
+                    .expect("token scope has no value")
+                    .map(|s| s.parse().expect("failed to parse value as scope"))
+                    .collect(),
+            }),
+            ("list", _) => Some(TokenCommand::List),
+            ("revoke", Some(revoke)) => Some(TokenCommand::Revoke {
+                name: revoke.value_of("name").expect("token name has no value").to_owned(),
+            }),
+            _ => None,
         }
     }
 
@@ -0,0 +1,262 @@
+//! Provides the [`PlayerCountHistory`](PlayerCountHistory) object, a summary of how many players have been in a server
+//! over time. The running servers' player counts are sampled periodically into the program store, and the samples in a
+//! given time range are summarised into buckets of a given resolution and into the peaks of each day and week.
+
+use crate::{
+    factorio::GameStoreId,
+    store::{models::PlayerCountSample, Store},
+    util::TimeZone,
+};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone as _, Utc};
+use std::{collections::BTreeMap, time::Duration};
+
+/// A summary of a server's player count samples in a time range.
+#[derive(Debug, PartialEq)]
+pub struct PlayerCountHistory {
+    /// The samples grouped into buckets, the oldest first. Buckets without samples are left out.
+    buckets: Vec<Bucket>,
+    /// The highest player count of each day with samples, the oldest first.
+    daily_peaks: Vec<Peak>,
+    /// The highest player count of each week with samples, the oldest first.
+    weekly_peaks: Vec<Peak>,
+}
+
+/// The player count samples taken in a time span.
+#[derive(Debug, PartialEq)]
+struct Bucket {
+    /// When the bucket's time span starts.
+    start: DateTime<Utc>,
+    /// The lowest sampled player count.
+    min: u32,
+    /// The highest sampled player count.
+    max: u32,
+    /// The sum of the sampled player counts.
+    total: u64,
+    /// How many samples there are.
+    samples: u32,
+}
+
+/// The highest player count of a day or a week.
+#[derive(Debug, PartialEq)]
+struct Peak {
+    /// The day, or the first day of the week, in the instance's time zone.
+    period: NaiveDate,
+    /// The highest sampled player count.
+    players: u32,
+    /// When the highest player count was first sampled.
+    reached_at: DateTime<Utc>,
+}
+
+impl PlayerCountHistory {
+    /// Loads a given game's player count samples taken at or after a given start and before a given end from the
+    /// program store, and summarises them with a given bucket resolution in a given time zone.
+    pub async fn load(
+        store: &Store,
+        game: GameStoreId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        resolution: Duration,
+        time_zone: TimeZone,
+    ) -> anyhow::Result<Self> {
+        let samples = store.get_player_count_samples(game, start, end).await?;
+        Ok(Self::summarise(&samples, resolution, time_zone))
+    }
+
+    /// Returns a summary of given player count samples, sorted from the oldest to the newest. The buckets are each a
+    /// given resolution long and aligned to the Unix epoch; a zero resolution puts each sample in its own bucket. The
+    /// days and weeks, starting on Monday, are counted in a given time zone.
+    fn summarise(samples: &[PlayerCountSample], resolution: Duration, time_zone: TimeZone) -> Self {
+        let resolution = resolution.as_secs() as i64;
+        let mut buckets = BTreeMap::new();
+        let mut daily_peaks = BTreeMap::new();
+        let mut weekly_peaks = BTreeMap::new();
+
+        for sample in samples {
+            let players = sample.players.max(0) as u32;
+            let start = if resolution > 0 {
+                let timestamp = sample.sampled_at.timestamp();
+                Utc.timestamp(timestamp - timestamp.rem_euclid(resolution), 0)
+            } else {
+                sample.sampled_at
+            };
+
+            buckets
+                .entry(start)
+                .and_modify(|bucket: &mut Bucket| bucket.add(players))
+                .or_insert(Bucket {
+                    start,
+                    min: players,
+                    max: players,
+                    total: u64::from(players),
+                    samples: 1,
+                });
+
+            let day = time_zone.localize(sample.sampled_at).date().naive_local();
+            let week = day - chrono::Duration::days(i64::from(day.weekday().num_days_from_monday()));
+            record_peak(&mut daily_peaks, day, players, sample.sampled_at);
+            record_peak(&mut weekly_peaks, week, players, sample.sampled_at);
+        }
+
+        Self {
+            buckets: buckets.into_iter().map(|(_, bucket)| bucket).collect(),
+            daily_peaks: daily_peaks.into_iter().map(|(_, peak)| peak).collect(),
+            weekly_peaks: weekly_peaks.into_iter().map(|(_, peak)| peak).collect(),
+        }
+    }
+
+    /// Returns the history in RPC format, with its timestamps also localised to a given time zone.
+    pub fn to_rpc_format(&self, time_zone: TimeZone) -> rpc::PlayerCountHistory {
+        let peak = |peak: &Peak| rpc::player_count_history::Peak {
+            period: peak.period.to_string(),
+            players: peak.players,
+            reached_at: peak.reached_at.timestamp(),
+            reached_at_local: time_zone.format(peak.reached_at),
+        };
+
+        rpc::PlayerCountHistory {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|bucket| rpc::player_count_history::Bucket {
+                    start: bucket.start.timestamp(),
+                    start_local: time_zone.format(bucket.start),
+                    min: bucket.min,
+                    max: bucket.max,
+                    average: bucket.average(),
+                    samples: bucket.samples,
+                })
+                .collect(),
+            daily_peaks: self.daily_peaks.iter().map(peak).collect(),
+            weekly_peaks: self.weekly_peaks.iter().map(peak).collect(),
+        }
+    }
+}
+
+impl Bucket {
+    /// Adds a given sampled player count to the bucket.
+    fn add(&mut self, players: u32) {
+        self.min = self.min.min(players);
+        self.max = self.max.max(players);
+        self.total += u64::from(players);
+        self.samples += 1;
+    }
+
+    /// Returns the average sampled player count.
+    fn average(&self) -> f64 {
+        self.total as f64 / f64::from(self.samples)
+    }
+}
+
+/// Records a given player count sampled at a given time as the peak of a given period in given peaks, if it's higher
+/// than the period's earlier peak.
+fn record_peak(peaks: &mut BTreeMap<NaiveDate, Peak>, period: NaiveDate, players: u32, sampled_at: DateTime<Utc>) {
+    let peak = peaks.entry(period).or_insert(Peak {
+        period,
+        players,
+        reached_at: sampled_at,
+    });
+    if players > peak.players {
+        peak.players = players;
+        peak.reached_at = sampled_at;
+    }
+}
+
+/// Returns the time range between given Unix timestamps, where a zero end means a given current time. Returns `None`
+/// if either timestamp is out of range or the range is empty.
+pub fn time_range(start: i64, end: i64, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = Utc.timestamp_opt(start, 0).single()?;
+    let end = if end == 0 {
+        now
+    } else {
+        Utc.timestamp_opt(end, 0).single()?
+    };
+
+    if start < end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+/// Records a given game's current player count as a sample taken now into the program store.
+pub async fn record(store: &Store, game: GameStoreId, players: usize) -> anyhow::Result<()> {
+    store
+        .add_player_count_sample(PlayerCountSample {
+            game,
+            sampled_at: Utc::now(),
+            players: players as i64,
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarise() {
+        let time_zone: TimeZone = "Europe/Helsinki".parse().unwrap();
+        // Sunday 2020-09-06 22:30 local time
+        let start = Utc.ymd(2020, 9, 6).and_hms(19, 30, 0);
+        let samples = [(0, 2), (10, 6), (20, 4), (40, 1), (100, 3)]
+            .iter()
+            .map(|(minutes, players)| PlayerCountSample {
+                game: 1,
+                sampled_at: start + chrono::Duration::minutes(*minutes),
+                players: *players,
+            })
+            .collect::<Vec<_>>();
+
+        let history = PlayerCountHistory::summarise(&samples, Duration::from_secs(30 * 60), time_zone);
+        let buckets = history
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.start, bucket.min, bucket.max, bucket.average()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            buckets,
+            vec![
+                (start, 2, 6, 4.0),
+                (start + chrono::Duration::minutes(30), 1, 1, 1.0),
+                (start + chrono::Duration::minutes(90), 3, 3, 3.0),
+            ]
+        );
+
+        // the samples after midnight local time fall on Monday, which starts a new week
+        let peaks = |peaks: &[Peak]| {
+            peaks
+                .iter()
+                .map(|peak| (peak.period.to_string(), peak.players, peak.reached_at))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            peaks(&history.daily_peaks),
+            vec![
+                (String::from("2020-09-06"), 6, start + chrono::Duration::minutes(10)),
+                (String::from("2020-09-07"), 3, start + chrono::Duration::minutes(100)),
+            ]
+        );
+        assert_eq!(
+            peaks(&history.weekly_peaks),
+            vec![
+                (String::from("2020-08-31"), 6, start + chrono::Duration::minutes(10)),
+                (String::from("2020-09-07"), 3, start + chrono::Duration::minutes(100)),
+            ]
+        );
+
+        let raw = PlayerCountHistory::summarise(&samples, Duration::from_secs(0), time_zone);
+        assert_eq!(raw.buckets.len(), samples.len());
+    }
+
+    #[test]
+    fn parse_time_range() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        assert_eq!(time_range(0, 0, now), Some((Utc.timestamp(0, 0), now)));
+        assert_eq!(
+            time_range(1_500_000_000, 1_550_000_000, now),
+            Some((Utc.timestamp(1_500_000_000, 0), Utc.timestamp(1_550_000_000, 0)))
+        );
+        assert_eq!(time_range(1_600_000_000, 0, now), None);
+        assert_eq!(time_range(0, i64::MAX, now), None);
+    }
+}
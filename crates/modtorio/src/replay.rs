@@ -0,0 +1,170 @@
+//! Provides the `replay` subcommand, which feeds a saved server log file through the game event parsers line by line
+//! and reports each line's parse result and timing. It's meant for validating parser changes against real-world logs
+//! from different Factorio versions without running a server.
+
+use crate::{
+    doctor,
+    factorio::executable::{CustomEventParser, GameEvent},
+    opts::Opts,
+};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// The parse result of a single replayed log line.
+#[derive(Debug)]
+pub struct LineResult {
+    /// The line's number in the log, starting from 1.
+    pub number: usize,
+    /// The line without its line ending.
+    pub line: String,
+    /// The event the line parsed into, if any.
+    pub event: Option<GameEvent>,
+    /// How long parsing the line took.
+    pub elapsed: Duration,
+}
+
+/// The parse results of every line in a replayed log.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The results in the order the lines are in the log.
+    pub lines: Vec<LineResult>,
+}
+
+impl Report {
+    /// Returns the amount of lines that parsed into an event.
+    pub fn parsed(&self) -> usize {
+        self.lines.iter().filter(|result| result.event.is_some()).count()
+    }
+
+    /// Returns the amount of lines that didn't parse into any event.
+    pub fn unparsed(&self) -> usize {
+        self.lines.len() - self.parsed()
+    }
+
+    /// Returns how long parsing every line took in total.
+    pub fn total_elapsed(&self) -> Duration {
+        self.lines.iter().map(|result| result.elapsed).sum()
+    }
+
+    /// Returns the line that took the longest to parse, if there are any lines.
+    pub fn slowest(&self) -> Option<&LineResult> {
+        self.lines.iter().max_by_key(|result| result.elapsed)
+    }
+
+    /// Returns the amount of parsed events of each kind.
+    pub fn kinds(&self) -> BTreeMap<&'static str, usize> {
+        let mut kinds = BTreeMap::new();
+        for event in self.lines.iter().filter_map(|result| result.event.as_ref()) {
+            *kinds.entry(event.kind()).or_insert(0) += 1;
+        }
+
+        kinds
+    }
+}
+
+/// Parses every line read from a given reader with the built-in parsers and given user-defined parsers like a running
+/// server's output is parsed. Lines that aren't valid UTF-8 are parsed lossily.
+pub fn replay<R>(mut reader: R, custom_parsers: &[CustomEventParser]) -> anyhow::Result<Report>
+where
+    R: BufRead,
+{
+    let mut report = Report::default();
+    let mut buf = Vec::new();
+
+    while reader.read_until(b'\n', &mut buf)? > 0 {
+        let line = String::from_utf8_lossy(&buf)
+            .trim_end_matches(|c| c == '\n' || c == '\r')
+            .to_owned();
+        buf.clear();
+
+        let start = Instant::now();
+        let event = GameEvent::parse_with(&line, custom_parsers).ok();
+        let elapsed = start.elapsed();
+
+        report.lines.push(LineResult {
+            number: report.lines.len() + 1,
+            line,
+            event,
+            elapsed,
+        });
+    }
+
+    Ok(report)
+}
+
+/// Replays a given log file with the user-defined parsers in the config and prints each line's result followed by a
+/// summary to stdout. If `unparsed_only` is set, only the lines that didn't parse are printed before the summary.
+pub fn run(opts: &Opts, log: &Path, unparsed_only: bool) -> anyhow::Result<()> {
+    let config = doctor::build_config(opts)?;
+    let report = replay(BufReader::new(File::open(log)?), config.event_parsers())?;
+
+    for result in &report.lines {
+        match &result.event {
+            Some(_) if unparsed_only => {}
+            Some(event) => println!("{:>6} {:>10?} {:?}", result.number, result.elapsed, event),
+            None => println!("{:>6} {:>10?} unparsed: {}", result.number, result.elapsed, result.line),
+        }
+    }
+
+    println!(
+        "{} lines, {} parsed, {} unparsed in {:?}",
+        report.lines.len(),
+        report.parsed(),
+        report.unparsed(),
+        report.total_elapsed()
+    );
+    if let Some(slowest) = report.slowest() {
+        println!("slowest line {} took {:?}", slowest.number, slowest.elapsed);
+    }
+    for (kind, count) in report.kinds() {
+        println!("{:>6} {}", count, kind);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{factorio::executable::EventParserDefinition, util::LogLevel};
+    use std::io::Cursor;
+
+    #[test]
+    fn replay_log() {
+        let log = "   0.000 2020-08-14 12:00:00; Factorio 1.0.0 (build 54889, linux64, headless)\r\n\
+                   \x20  0.500 Info RemoteCommandProcessor.cpp:131: Starting RCON interface\n\
+                   2020-07-20 10:00:05 [JOIN] player joined the game\n\
+                   \x20 42.000 [TELEMETRY] rockets=3\n\
+                   2020-07-20 10:00:06 [CHAT] player: hello";
+        let parsers = CustomEventParser::compile_all(&[EventParserDefinition {
+            name: String::from("telemetry"),
+            pattern: String::from(r#"\[TELEMETRY\] (.+)$"#),
+            template: String::new(),
+            severity: LogLevel::Info,
+        }])
+        .unwrap();
+
+        let report = replay(Cursor::new(log), &parsers).unwrap();
+        assert_eq!(report.lines.len(), 5);
+        assert_eq!(report.parsed(), 4);
+        assert_eq!(report.unparsed(), 1);
+
+        let unparsed = report.lines.iter().find(|result| result.event.is_none()).unwrap();
+        assert_eq!(unparsed.number, 2);
+        assert_eq!(
+            unparsed.line,
+            "   0.500 Info RemoteCommandProcessor.cpp:131: Starting RCON interface"
+        );
+
+        let kinds = report.kinds();
+        assert_eq!(kinds["Version"], 1);
+        assert_eq!(kinds["PeerJoined"], 1);
+        assert_eq!(kinds["Custom"], 1);
+        assert_eq!(kinds["Chat"], 1);
+    }
+}
@@ -0,0 +1,164 @@
+//! Provides the [`Scheduler`](Scheduler) object which keeps track of the managed servers' scheduled tasks, and
+//! functions to evaluate the tasks' cron schedules.
+
+use crate::{factorio::GameStoreId, util::TimeZone};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+use tokio::sync::RwLock;
+
+/// How often the managed servers' schedules are checked for due tasks. Tasks are run at most this late.
+pub const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Keeps track of the latest runs of the managed servers' scheduled tasks.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    /// The latest scheduled mod update of each server, keyed by their store IDs.
+    mod_updates: RwLock<HashMap<GameStoreId, ScheduledRun>>,
+    /// The store IDs of the servers whose mod update is pending or running.
+    pending_mod_updates: RwLock<HashSet<GameStoreId>>,
+}
+
+/// A finished run of a scheduled task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledRun {
+    /// Timestamp when the run finished.
+    finished_at: DateTime<Utc>,
+    /// The error the run failed with, if it failed.
+    error: Option<String>,
+}
+
+impl Scheduler {
+    /// Returns a new `Scheduler` with no recorded runs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the latest scheduled mod update of a given server, if its mods have been updated on schedule since
+    /// Modtorio started.
+    pub async fn last_mod_update(&self, server_id: GameStoreId) -> Option<ScheduledRun> {
+        self.mod_updates.read().await.get(&server_id).cloned()
+    }
+
+    /// Marks a mod update of a given server as pending until its run is recorded. Returns false without marking it if
+    /// the server already has a mod update pending or running.
+    pub async fn begin_mod_update(&self, server_id: GameStoreId) -> bool {
+        self.pending_mod_updates.write().await.insert(server_id)
+    }
+
+    /// Records a given run as the latest scheduled mod update of a given server, and marks the server as no longer
+    /// having a mod update pending.
+    pub async fn record_mod_update(&self, server_id: GameStoreId, run: ScheduledRun) {
+        self.mod_updates.write().await.insert(server_id, run);
+        self.pending_mod_updates.write().await.remove(&server_id);
+    }
+}
+
+impl ScheduledRun {
+    /// Returns a new `ScheduledRun` that finished at the current time, with a given result.
+    pub fn finished<T>(result: &anyhow::Result<T>) -> Self {
+        Self {
+            finished_at: Utc::now(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        }
+    }
+
+    /// Returns the timestamp when the run finished.
+    pub fn finished_at(&self) -> DateTime<Utc> {
+        self.finished_at
+    }
+
+    /// Returns the error the run failed with, if it failed.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Returns the next time a given schedule evaluated in a given time zone is due after a given timestamp, if it's ever
+/// due again.
+pub fn next_run(schedule: &cron::Schedule, time_zone: TimeZone, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    schedule
+        .after(&time_zone.localize(after))
+        .next()
+        .map(|next| next.with_timezone(&Utc))
+}
+
+/// Returns whether a given schedule evaluated in a given time zone is due at least once after a given timestamp up to
+/// and including another timestamp.
+pub fn is_due(schedule: &cron::Schedule, time_zone: TimeZone, since: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    next_run(schedule, time_zone, since).map_or(false, |next| next <= now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factorio::settings::Schedule;
+    use chrono::TimeZone as _;
+
+    fn schedule(expression: &str) -> cron::Schedule {
+        Schedule {
+            mod_updates: Some(expression.to_owned()),
+        }
+        .mod_updates()
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn due_within_tick() {
+        let nightly = schedule("0 4 * * *");
+        let utc = TimeZone::default();
+        let since = Utc.ymd(2020, 9, 1).and_hms(3, 59, 45);
+
+        assert!(is_due(&nightly, utc, since, since + chrono::Duration::seconds(30)));
+        assert!(!is_due(&nightly, utc, since, since + chrono::Duration::seconds(10)));
+        // a run exactly at the previous tick isn't due again
+        assert!(!is_due(
+            &nightly,
+            utc,
+            Utc.ymd(2020, 9, 1).and_hms(4, 0, 0),
+            Utc.ymd(2020, 9, 1).and_hms(4, 0, 30)
+        ));
+    }
+
+    #[test]
+    fn next_run_in_time_zone() {
+        let nightly = schedule("0 4 * * *");
+        let helsinki: TimeZone = "Europe/Helsinki".parse().unwrap();
+
+        // 04:00 in Helsinki is 01:00 UTC during summer time
+        assert_eq!(
+            next_run(&nightly, helsinki, Utc.ymd(2020, 9, 1).and_hms(12, 0, 0)),
+            Some(Utc.ymd(2020, 9, 2).and_hms(1, 0, 0))
+        );
+    }
+
+    #[tokio::test]
+    async fn records_latest_run() {
+        let scheduler = Scheduler::new();
+        assert_eq!(scheduler.last_mod_update(1).await, None);
+
+        scheduler
+            .record_mod_update(1, ScheduledRun::finished::<()>(&Err(anyhow::anyhow!("portal down"))))
+            .await;
+        scheduler.record_mod_update(1, ScheduledRun::finished(&Ok(()))).await;
+
+        let run = scheduler.last_mod_update(1).await.unwrap();
+        assert_eq!(run.error(), None);
+        assert_eq!(scheduler.last_mod_update(2).await, None);
+    }
+
+    #[tokio::test]
+    async fn skips_pending_update() {
+        let scheduler = Scheduler::new();
+
+        assert!(scheduler.begin_mod_update(1).await);
+        assert!(!scheduler.begin_mod_update(1).await);
+        assert!(scheduler.begin_mod_update(2).await);
+
+        scheduler.record_mod_update(1, ScheduledRun::finished(&Ok(()))).await;
+        assert!(scheduler.begin_mod_update(1).await);
+    }
+}
@@ -0,0 +1,402 @@
+//! Provides the [`Coordinator`](Coordinator) object, which keeps track of the long-running background tasks such as
+//! imports, mod updates and downloads, so they can be shut down gracefully when the program exits.
+//!
+//! On shutdown, every registered task is cancelled and given a grace period to finish or to stop at a safe point.
+//! Tasks record checkpoints of how far they've got along the way. The tasks that stopped early because of the
+//! cancellation or were still running once the grace period ran out are reported as interrupted with their latest
+//! checkpoint, and kept in the program store until the next startup reports them again.
+//!
+//! Tasks [spawned](Coordinator::spawn) through the coordinator reach their handle through the free functions in this
+//! module, such as [`checkpoint`](checkpoint) and [`stop_point`](stop_point), so the code they call into doesn't have
+//! to pass the handle around. Outside such tasks the functions do nothing.
+
+use crate::{
+    error::RpcError,
+    store::{option, Store},
+    util::correlation,
+};
+use chrono::{DateTime, Utc};
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{sync::watch, task::JoinHandle, time};
+
+/// A record of a registered long-running task.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TaskRecord {
+    /// What the task does.
+    pub description: String,
+    /// The task's latest checkpoint, if it has recorded one.
+    pub checkpoint: Option<String>,
+    /// Timestamp when the task was started.
+    pub started_at: DateTime<Utc>,
+}
+
+/// Keeps track of the running long-running tasks and cancels them on shutdown. Every clone refers to the same tasks.
+#[derive(Debug, Clone)]
+pub struct Coordinator {
+    /// The state shared with the tasks' handles.
+    shared: Arc<Shared>,
+}
+
+/// A handle a registered task uses to learn it has been cancelled and to record checkpoints. The task is unregistered
+/// once its handle is dropped.
+#[derive(Debug)]
+pub struct Task {
+    /// The task's ID.
+    id: u64,
+    /// The coordinator's shared state.
+    shared: Arc<Shared>,
+    /// Receives whether the tasks have been cancelled.
+    cancel_rx: watch::Receiver<bool>,
+    /// Whether the task stopped early because it was cancelled.
+    interrupted: AtomicBool,
+}
+
+tokio::task_local! {
+    /// The handle of the task spawned through a coordinator the current task is running.
+    static CURRENT: Arc<Task>;
+}
+
+/// The state shared between a coordinator and its tasks' handles.
+#[derive(Debug)]
+struct Shared {
+    /// The ID given to the next registered task.
+    next_id: AtomicU64,
+    /// The running and interrupted tasks.
+    tasks: Mutex<Tasks>,
+    /// Sends whether the tasks have been cancelled.
+    cancel_tx: watch::Sender<bool>,
+    /// Receives whether the tasks have been cancelled. Cloned into every task's handle.
+    cancel_rx: watch::Receiver<bool>,
+    /// Sends the amount of running tasks whenever it changes.
+    running_tx: watch::Sender<usize>,
+    /// Receives the amount of running tasks.
+    running_rx: watch::Receiver<usize>,
+}
+
+/// The running and interrupted tasks.
+#[derive(Debug, Default)]
+struct Tasks {
+    /// The running tasks, keyed by their IDs.
+    running: HashMap<u64, TaskRecord>,
+    /// The tasks that stopped early because they were cancelled.
+    interrupted: Vec<TaskRecord>,
+}
+
+impl fmt::Display for TaskRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (started at {}", self.description, self.started_at)?;
+        match &self.checkpoint {
+            Some(checkpoint) => write!(f, ", last checkpoint: {})", checkpoint),
+            None => write!(f, ", no checkpoint)"),
+        }
+    }
+}
+
+impl Coordinator {
+    /// Returns a new `Coordinator` without any tasks.
+    pub fn new() -> Self {
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+        let (running_tx, running_rx) = watch::channel(0);
+
+        Self {
+            shared: Arc::new(Shared {
+                next_id: AtomicU64::new(0),
+                tasks: Mutex::new(Tasks::default()),
+                cancel_tx,
+                cancel_rx,
+                running_tx,
+                running_rx,
+            }),
+        }
+    }
+
+    /// Spawns a given long-running task with a given description like
+    /// [`correlation::spawn`](crate::util::correlation::spawn) does. The task stays registered until it finishes. While
+    /// it runs, the task's handle is reachable through the free functions in this module, so the task learns it has
+    /// been cancelled and records its checkpoints with them.
+    pub fn spawn<Fut>(&self, description: String, task: Fut) -> JoinHandle<Fut::Output>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let handle = Arc::new(self.register(description));
+        correlation::spawn(CURRENT.scope(handle, task))
+    }
+
+    /// Cancels every registered task and waits up to a given grace period for them to finish. Returns the tasks that
+    /// stopped early because they were cancelled, followed by the tasks still running after the grace period.
+    pub async fn shutdown(&self, grace_period: Duration) -> Vec<TaskRecord> {
+        self.shared.cancel_tx.broadcast(true).ok();
+
+        let mut running_rx = self.shared.running_rx.clone();
+        let all_finished = async {
+            while *running_rx.borrow() > 0 {
+                if running_rx.recv().await.is_none() {
+                    break;
+                }
+            }
+        };
+
+        if time::timeout(grace_period, all_finished).await.is_err() {
+            warn!(
+                "Long-running tasks didn't finish in the {:?} shutdown grace period",
+                grace_period
+            );
+        }
+
+        let mut tasks = self.shared.tasks.lock().unwrap();
+        let mut interrupted = std::mem::take(&mut tasks.interrupted);
+        interrupted.extend(tasks.running.values().cloned());
+        interrupted
+    }
+
+    /// Registers a new task with a given description and returns its handle. The task stays registered until the
+    /// handle is dropped.
+    pub fn register(&self, description: String) -> Task {
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        debug!("Starting task {}: {}", id, description);
+
+        let mut tasks = self.shared.tasks.lock().unwrap();
+        tasks.running.insert(
+            id,
+            TaskRecord {
+                description,
+                checkpoint: None,
+                started_at: Utc::now(),
+            },
+        );
+        self.shared.running_tx.broadcast(tasks.running.len()).ok();
+
+        Task {
+            id,
+            shared: Arc::clone(&self.shared),
+            cancel_rx: self.shared.cancel_rx.clone(),
+            interrupted: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Default for Coordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Task {
+    /// Returns whether the task has been cancelled because the program is shutting down.
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancel_rx.borrow()
+    }
+
+    /// Waits until the task is cancelled.
+    pub async fn cancelled(&mut self) {
+        while !*self.cancel_rx.borrow() {
+            if self.cancel_rx.recv().await.is_none() {
+                return;
+            }
+        }
+    }
+
+    /// Records a given checkpoint of how far the task has got, replacing the earlier one.
+    pub fn checkpoint<S>(&self, checkpoint: S)
+    where
+        S: Into<String>,
+    {
+        if let Some(task) = self.shared.tasks.lock().unwrap().running.get_mut(&self.id) {
+            task.checkpoint = Some(checkpoint.into());
+        }
+    }
+
+    /// Unregisters the task as having stopped early because it was cancelled, with a given checkpoint of how far it
+    /// got.
+    pub fn interrupt<S>(self, checkpoint: S)
+    where
+        S: Into<String>,
+    {
+        self.mark_interrupted(checkpoint);
+    }
+
+    /// Marks the task as having stopped early because it was cancelled, with a given checkpoint of how far it got. The
+    /// task is unregistered once its handle is dropped.
+    fn mark_interrupted<S>(&self, checkpoint: S)
+    where
+        S: Into<String>,
+    {
+        self.checkpoint(checkpoint);
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        let mut tasks = match self.shared.tasks.lock() {
+            Ok(tasks) => tasks,
+            Err(_) => return,
+        };
+
+        if let Some(task) = tasks.running.remove(&self.id) {
+            debug!("Task {} finished: {}", self.id, task.description);
+            if self.interrupted.load(Ordering::Relaxed) {
+                tasks.interrupted.push(task);
+            }
+        }
+        self.shared.running_tx.broadcast(tasks.running.len()).ok();
+    }
+}
+
+/// Returns whether the task spawned through a coordinator the current task is running has been cancelled. Returns
+/// `false` outside such tasks.
+pub fn is_cancelled() -> bool {
+    CURRENT.try_with(|task| task.is_cancelled()).unwrap_or(false)
+}
+
+/// Waits until the task spawned through a coordinator the current task is running is cancelled. Never finishes outside
+/// such tasks.
+pub async fn cancelled() {
+    match CURRENT.try_with(|task| task.cancel_rx.clone()) {
+        Ok(mut cancel_rx) => {
+            while !*cancel_rx.borrow() {
+                if cancel_rx.recv().await.is_none() {
+                    break;
+                }
+            }
+        }
+        Err(_) => futures::future::pending().await,
+    }
+}
+
+/// Records a given checkpoint of how far the task spawned through a coordinator the current task is running has got.
+/// Does nothing outside such tasks.
+pub fn checkpoint<S>(checkpoint: S)
+where
+    S: Into<String>,
+{
+    CURRENT.try_with(|task| task.checkpoint(checkpoint)).ok();
+}
+
+/// Records a given checkpoint like [`checkpoint`](checkpoint) does, and if the task has been cancelled, marks it as
+/// having stopped early at the checkpoint and returns [`RpcError::ShuttingDown`](RpcError::ShuttingDown), so the task
+/// stops at a safe point by returning the error.
+pub fn stop_point<S>(checkpoint: S) -> Result<(), RpcError>
+where
+    S: Into<String>,
+{
+    let stopped = CURRENT
+        .try_with(|task| {
+            if task.is_cancelled() {
+                task.mark_interrupted(checkpoint);
+                true
+            } else {
+                task.checkpoint(checkpoint);
+                false
+            }
+        })
+        .unwrap_or(false);
+
+    if stopped {
+        Err(RpcError::ShuttingDown)
+    } else {
+        Ok(())
+    }
+}
+
+/// Stores given interrupted tasks in a given program store, replacing the earlier ones.
+pub async fn store_interrupted(store: &Store, interrupted: &[TaskRecord]) -> anyhow::Result<()> {
+    let value = if interrupted.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(interrupted)?)
+    };
+
+    store
+        .set_option(option::Value::new(option::Field::InterruptedTasks, value))
+        .await
+}
+
+/// Returns the interrupted tasks stored in a given program store and removes them from the store.
+pub async fn take_interrupted(store: &Store) -> anyhow::Result<Vec<TaskRecord>> {
+    let stored = store
+        .get_option(option::Field::InterruptedTasks)
+        .await?
+        .and_then(option::Value::take_value);
+
+    match stored {
+        Some(stored) => {
+            let interrupted = serde_json::from_str(&stored)?;
+            store_interrupted(store, &[]).await?;
+            Ok(interrupted)
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn shutdown() {
+        let coordinator = Coordinator::new();
+
+        coordinator.spawn(String::from("finished"), async {}).await.unwrap();
+        coordinator.spawn(String::from("unfinished"), async {
+            time::delay_for(Duration::from_secs(3600)).await;
+        });
+
+        let mut task = coordinator.register(String::from("cooperative"));
+        correlation::spawn(async move {
+            task.checkpoint("started");
+            task.cancelled().await;
+            task.interrupt("halfway");
+        });
+        coordinator.spawn(String::from("spawned"), async {
+            checkpoint("started");
+            cancelled().await;
+            assert!(is_cancelled());
+            stop_point("stopped").unwrap_err();
+        });
+        let task = coordinator.register(String::from("stuck"));
+        correlation::spawn(async move {
+            task.checkpoint("stuck");
+            time::delay_for(Duration::from_secs(3600)).await;
+            drop(task);
+        });
+
+        let interrupted = coordinator.shutdown(Duration::from_millis(100)).await;
+        let (cancelled, running) = interrupted.split_at(2);
+        let mut cancelled = cancelled
+            .iter()
+            .map(|task| (task.description.as_str(), task.checkpoint.as_deref()))
+            .collect::<Vec<_>>();
+        let mut running = running
+            .iter()
+            .map(|task| (task.description.as_str(), task.checkpoint.as_deref()))
+            .collect::<Vec<_>>();
+        // the tasks are in no particular order
+        cancelled.sort();
+        running.sort();
+        assert_eq!(
+            cancelled,
+            vec![("cooperative", Some("halfway")), ("spawned", Some("stopped"))]
+        );
+        assert_eq!(running, vec![("stuck", Some("stuck")), ("unfinished", None)]);
+    }
+
+    #[tokio::test]
+    async fn outside_tasks() {
+        assert!(!is_cancelled());
+        checkpoint("ignored");
+        stop_point("ignored").unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+//! Provides the [`StartupSummary`](StartupSummary) object, which summarises how importing and autostarting the
+//! stored games went when the Modtorio instance started.
+
+use crate::factorio::GameStoreId;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A summary of the stored games' import and autostart when the instance started.
+#[derive(Debug, Default, Clone)]
+pub struct StartupSummary {
+    /// How many stored games were imported successfully.
+    imported: u32,
+    /// How many imported games were autostarted.
+    autostarted: u32,
+    /// How many imported games were not autostarted because they have autostart disabled.
+    skipped: u32,
+    /// How many stored games were left unloaded until they're first accessed.
+    deferred: u32,
+    /// The stored games that failed to be imported.
+    failures: Vec<ImportFailure>,
+    /// Whether an operator has acknowledged the import failures.
+    acknowledged: bool,
+}
+
+/// A stored game that failed to be imported when the instance started.
+#[derive(Debug, Clone)]
+pub struct ImportFailure {
+    /// The game's store ID.
+    server_id: GameStoreId,
+    /// The game's root path.
+    path: PathBuf,
+    /// Why the game failed to be imported.
+    reason: String,
+}
+
+impl StartupSummary {
+    /// Records a stored game as imported successfully.
+    pub fn imported(&mut self) {
+        self.imported += 1;
+    }
+
+    /// Records a stored game as failed to be imported for a given reason.
+    pub fn failed<P>(&mut self, server_id: GameStoreId, path: P, reason: String)
+    where
+        P: AsRef<Path>,
+    {
+        self.failures.push(ImportFailure {
+            server_id,
+            path: path.as_ref().to_path_buf(),
+            reason,
+        });
+    }
+
+    /// Records an imported game as autostarted.
+    pub fn autostarted(&mut self) {
+        self.autostarted += 1;
+    }
+
+    /// Records an imported game as skipped from autostarting.
+    pub fn skipped(&mut self) {
+        self.skipped += 1;
+    }
+
+    /// Records a stored game as left unloaded until it's first accessed.
+    pub fn deferred(&mut self) {
+        self.deferred += 1;
+    }
+
+    /// Returns the stored games that failed to be imported.
+    pub fn failures(&self) -> &[ImportFailure] {
+        &self.failures
+    }
+
+    /// Marks the import failures as acknowledged, which hides them from the instance status. Returns how many
+    /// failures were acknowledged.
+    pub fn acknowledge(&mut self) -> usize {
+        if self.acknowledged {
+            0
+        } else {
+            self.acknowledged = true;
+            self.failures.len()
+        }
+    }
+
+    /// Returns the summary in RPC format. The import failures are left out once they've been acknowledged.
+    pub fn to_rpc_format(&self) -> rpc::StartupSummary {
+        let failures = if self.acknowledged {
+            Vec::new()
+        } else {
+            self.failures.iter().map(ImportFailure::to_rpc_format).collect()
+        };
+
+        rpc::StartupSummary {
+            imported: self.imported,
+            failed: self.failures.len() as u32,
+            autostarted: self.autostarted,
+            skipped: self.skipped,
+            failures,
+            deferred: self.deferred,
+        }
+    }
+}
+
+impl fmt::Display for StartupSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} imported, {} failed, {} autostarted, {} skipped, {} deferred",
+            self.imported,
+            self.failures.len(),
+            self.autostarted,
+            self.skipped,
+            self.deferred
+        )
+    }
+}
+
+impl ImportFailure {
+    /// Returns the failed game's store ID.
+    pub fn server_id(&self) -> GameStoreId {
+        self.server_id
+    }
+
+    /// Returns the failed game's root path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns why the game failed to be imported.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Returns the failure in RPC format.
+    fn to_rpc_format(&self) -> rpc::startup_summary::Failure {
+        rpc::startup_summary::Failure {
+            server_id: self.server_id,
+            path: self.path.display().to_string(),
+            reason: self.reason.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acknowledge_hides_failures() {
+        let mut summary = StartupSummary::default();
+        summary.imported();
+        summary.imported();
+        summary.autostarted();
+        summary.skipped();
+        summary.deferred();
+        summary.failed(3, "/srv/factorio/broken", String::from("server executable not found"));
+        assert_eq!(
+            summary.to_string(),
+            "2 imported, 1 failed, 1 autostarted, 1 skipped, 1 deferred"
+        );
+
+        let rpc = summary.to_rpc_format();
+        assert_eq!(rpc.failed, 1);
+        assert_eq!(rpc.deferred, 1);
+        assert_eq!(rpc.failures.len(), 1);
+        assert_eq!(rpc.failures[0].server_id, 3);
+        assert_eq!(rpc.failures[0].path, "/srv/factorio/broken");
+
+        assert_eq!(summary.acknowledge(), 1);
+        assert_eq!(summary.acknowledge(), 0);
+        let rpc = summary.to_rpc_format();
+        assert_eq!(rpc.failed, 1);
+        assert!(rpc.failures.is_empty());
+        assert_eq!(summary.failures().len(), 1);
+    }
+}
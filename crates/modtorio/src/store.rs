@@ -1,15 +1,29 @@
 //! The program store, used to store persistent data about the program in an SQLite database.
 
+mod compressed_text;
 pub mod models;
 pub mod option;
 
-use crate::{error::StoreError, factorio::GameStoreId, util, util::ext::PathExt};
+use crate::{error::StoreError, event_history::EventKind, factorio::GameStoreId, util, util::ext::PathExt};
+use chrono::{DateTime, Utc};
+pub use compressed_text::{CompressedText, COMPRESSION_THRESHOLD};
 use log::*;
-use models::{FactorioMod, Game, GameMod, GameSettings, ModRelease, ReleaseDependency};
-use rusqlite::{Connection, OptionalExtension, NO_PARAMS};
+use models::{
+    FactorioMod, Game, GameMod, GameModPolicy, GameModPreset, GameSettings, IdempotencyKey, LastKnownGoodMods,
+    MapExchangeString, ModRelease, PlayerCountSample, PortalCredentials, PortalResponse, ReleaseDependency, RpcToken,
+    ServerEvent,
+};
+use rusqlite::{named_params, Connection, OpenFlags, OptionalExtension, NO_PARAMS};
 use std::{
-    path::Path,
-    sync::{Arc, Mutex},
+    fmt,
+    fs::{File, OpenOptions},
+    io::Write,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 use tokio::task;
 use util::HumanVersion;
@@ -19,13 +33,29 @@ include!(concat!(env!("OUT_DIR"), "/store_consts.rs"));
 /// The special value interpreted as using an in-memory SQLite database.
 pub(crate) const MEMORY_STORE: &str = "_memory";
 /// The maximum permissions the store database file can have (600: `r--------`)
-const MAX_STORE_FILE_PERMISSIONS: u32 = 0o600;
+pub const MAX_STORE_FILE_PERMISSIONS: u32 = 0o600;
+/// The default schema's version. Increased whenever the schema changes. Stores with a newer schema version than this
+/// are refused instead of having the schema reapplied.
+pub const SCHEMA_VERSION: u32 = 17;
+/// The extension of the lock file created next to the store database file.
+const LOCK_FILE_EXTENSION: &str = "lock";
 
 /// Provides access to the program store and store. New instances are created with a
 /// [`Builder`](Builder).
 pub struct Store {
     /// The connection to the SQLite database file.
-    conn: Arc<Mutex<Connection>>,
+    conn: Arc<SharedConnection>,
+    /// The store's locked lock file, if the store was opened exclusively. The lock is held as long as the file is open.
+    _lock: Option<File>,
+}
+
+/// The connection to the store's SQLite database, shared with the blocking tasks the database is used in.
+struct SharedConnection {
+    /// The connection to the SQLite database.
+    connection: Mutex<Connection>,
+    /// Whether the database has refused a write because it's read-only. Once set, the store stays degraded until the
+    /// program is restarted.
+    read_only: AtomicBool,
 }
 
 /// Builds new [`Store`](Store) instances.
@@ -39,8 +69,10 @@ where
     schema_checksum: Option<String>,
     /// Location for the store database. Either a filesystem path, or in-memory.
     store_location: StoreLocation<P>,
-    /// Should the schema checksum not be stored as an option in the program store.
+    /// Should the schema checksum and version not be stored as options in the program store.
     skip_storing_checksum: bool,
+    /// Should a file store be locked so no other program instance can open it exclusively at the same time.
+    exclusive: bool,
 }
 
 /// Specifies the location for the store database.
@@ -63,6 +95,7 @@ where
             schema_checksum: Some(String::from(SCHEMA_CHECKSUM)),
             store_location,
             skip_storing_checksum: false,
+            exclusive: false,
         }
     }
 
@@ -77,7 +110,7 @@ where
         }
     }
 
-    /// Specify whether to skip storing the schema checksum in the store options.
+    /// Specify whether to skip storing the schema checksum and version in the store options.
     #[allow(dead_code)]
     pub fn skip_storing_checksum(self, skip: bool) -> Self {
         Self {
@@ -86,7 +119,16 @@ where
         }
     }
 
-    /// Finalise the builder and return a new `Store`.
+    /// Specify whether to lock a file store for as long as the `Store` exists, so that another program instance
+    /// opening the same store exclusively is refused. In-memory stores are never locked.
+    pub fn exclusive(self, exclusive: bool) -> Self {
+        Self { exclusive, ..self }
+    }
+
+    /// Finalise the builder and return a new `Store`. Returns
+    /// [`StoreError::NewerSchema`](crate::error::StoreError::NewerSchema) if an existing store's schema version is
+    /// newer than [`SCHEMA_VERSION`](SCHEMA_VERSION), and [`StoreError::Locked`](crate::error::StoreError::Locked) if
+    /// the store is opened exclusively and another program instance already has it open exclusively.
     pub async fn build(self) -> anyhow::Result<Store> {
         let schema_checksum = if let Some(checksum) = self.schema_checksum {
             checksum
@@ -96,19 +138,47 @@ where
         };
         trace!("Store database schema checksum: {}", schema_checksum);
 
-        let (store_file_exists, conn) = match self.store_location {
+        let (store_file_exists, conn, lock) = match self.store_location {
             StoreLocation::Memory => {
                 // when opening an in-memory database, it will initially be empty, i.e. it didn't
                 // exist beforehand
-                (false, Connection::open_in_memory()?)
+                (false, Connection::open_in_memory()?, None)
+            }
+            StoreLocation::File(path) => {
+                // lock before touching the database so a running instance's store never gets its schema reapplied
+                let lock = if self.exclusive {
+                    Some(lock_store_file(path.as_ref())?)
+                } else {
+                    None
+                };
+                (path.as_ref().exists(), open_file_connection(path)?, lock)
             }
-            StoreLocation::File(path) => (path.as_ref().exists(), open_file_connection(path)?),
         };
-        let conn = Arc::new(Mutex::new(conn));
+        let conn = Arc::new(SharedConnection {
+            connection: Mutex::new(conn),
+            read_only: AtomicBool::new(false),
+        });
 
-        let store = Store { conn };
+        let store = Store { conn, _lock: lock };
         debug!("Store database exists: {}", store_file_exists);
 
+        let stored_version = if store_file_exists {
+            store.schema_version().await?
+        } else {
+            None
+        };
+        debug!("Store database schema version: {:?}", stored_version);
+
+        if let Some(stored) = stored_version {
+            if stored > SCHEMA_VERSION {
+                return Err(StoreError::NewerSchema {
+                    stored,
+                    supported: SCHEMA_VERSION,
+                }
+                .into());
+            }
+        }
+
         let checksums_match = store_file_exists && checksum_matches_meta(&store, &schema_checksum).await?;
         debug!("Schema checksums match: {}", checksums_match);
 
@@ -122,6 +192,19 @@ where
             }
         }
 
+        // stores created before schema versions were tracked get their version once they're opened
+        if !self.skip_storing_checksum && (!checksums_match || stored_version != Some(SCHEMA_VERSION)) {
+            store_schema_version(&store).await?;
+        }
+
+        if checksums_match {
+            // a freshly applied schema has no existing rows to compress
+            let compressed = store.compress_stored_metadata().await?;
+            if compressed > 0 {
+                info!("Compressed the metadata of {} stored mods", compressed);
+            }
+        }
+
         Ok(store)
     }
 }
@@ -155,6 +238,43 @@ where
     }
 }
 
+/// Returns the path of the lock file of a store database file in a given path.
+fn lock_file_path(path: &Path) -> PathBuf {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".");
+    lock_path.push(LOCK_FILE_EXTENSION);
+    PathBuf::from(lock_path)
+}
+
+/// Creates and locks the lock file of a store database file in a given path, and writes the program's process ID in
+/// it. The lock is released when the returned file is closed, including when the program exits abnormally.
+///
+/// # Errors
+/// Returns `StoreError::Locked` if the lock file is already locked.
+fn lock_store_file(path: &Path) -> anyhow::Result<File> {
+    let lock_path = lock_file_path(path);
+    let mut file = OpenOptions::new().create(true).write(true).open(&lock_path)?;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let err = std::io::Error::last_os_error();
+        return if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+            let owner = std::fs::read_to_string(&lock_path).unwrap_or_default();
+            Err(StoreError::Locked {
+                path: lock_path,
+                pid: owner.trim().to_owned(),
+            }
+            .into())
+        } else {
+            Err(err.into())
+        };
+    }
+
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?;
+    debug!("Locked store lock file {}", lock_path.display());
+    Ok(file)
+}
+
 /// Applies a given SQL schema to a given `Store`.
 async fn apply_store_schema(store: &Store, schema: &str) -> anyhow::Result<()> {
     trace!("Applying database schema...");
@@ -177,6 +297,44 @@ async fn store_schema_checksum(store: &Store, checksum: &str) -> anyhow::Result<
     Ok(())
 }
 
+/// Stores [`SCHEMA_VERSION`](SCHEMA_VERSION) to the program store's `SchemaVersion` option.
+async fn store_schema_version(store: &Store) -> anyhow::Result<()> {
+    trace!("Storing schema version {}...", SCHEMA_VERSION);
+
+    store
+        .set_option(option::Value::new(
+            option::Field::SchemaVersion,
+            Some(SCHEMA_VERSION.to_string()),
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Returns the schema version of the store database file at a given path without modifying it, or `None` if the
+/// store doesn't have a schema version.
+pub fn read_schema_version<P>(path: P) -> anyhow::Result<Option<u32>>
+where
+    P: AsRef<Path>,
+{
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    schema_version_from_connection(&conn)
+}
+
+/// Returns the schema version stored in a given database connection, or `None` if it doesn't have one.
+fn schema_version_from_connection(conn: &Connection) -> anyhow::Result<Option<u32>> {
+    let mut stmt = conn.prepare(option::Value::select())?;
+    let value = stmt
+        .query_row_named(&option::Value::select_params(&option::Field::SchemaVersion), |row| {
+            option::Value::try_from_row(row)
+        })
+        .optional()?;
+
+    Ok(value
+        .and_then(option::Value::take_value)
+        .map(|version| version.parse())
+        .transpose()?)
+}
+
 impl<P> From<P> for StoreLocation<P>
 where
     P: AsRef<Path>,
@@ -214,16 +372,52 @@ async fn checksum_matches_meta(store: &Store, wanted_checksum: &str) -> anyhow::
 /// `task::spawn_blocking`. Returns what the given block returns.
 #[macro_export]
 macro_rules! sql {
-    ($conn:ident => $b:block) => {
-        Ok({
-            let _c = Arc::clone(&$conn);
-            task::spawn_blocking(move || -> anyhow::Result<_> {
-                let $conn = _c.lock().unwrap();
-                $b
-            })
-            .await??
+    ($conn:ident => $b:block) => {{
+        let _c = Arc::clone(&$conn);
+        let result = task::spawn_blocking(move || -> anyhow::Result<_> {
+            let $conn = _c.connection.lock().unwrap();
+            $b
         })
-    };
+        .await?;
+        $conn.check_read_only(result)
+    }};
+}
+
+impl SharedConnection {
+    /// Checks whether a given result of using the database failed because the database refused a write for being
+    /// read-only. If so, the store is marked read-only and the error is replaced with `StoreError::ReadOnly`.
+    fn check_read_only<T>(&self, result: anyhow::Result<T>) -> anyhow::Result<T> {
+        match result {
+            Err(e) if is_read_only_error(&e) => {
+                if !self.read_only.swap(true, Ordering::SeqCst) {
+                    error!(
+                        "The store database is read-only, continuing in degraded mode where changes can't be stored: \
+                         {}",
+                        e
+                    );
+                }
+
+                Err(StoreError::ReadOnly.into())
+            }
+            result => result,
+        }
+    }
+}
+
+/// Returns whether a given error is SQLite refusing a write because the database is read-only.
+fn is_read_only_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref(),
+        Some(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ffi::ErrorCode::ReadOnly
+    )
+}
+
+impl fmt::Debug for Store {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Store")
+            .field("read_only", &self.is_read_only())
+            .finish()
+    }
 }
 
 impl Store {
@@ -232,7 +426,8 @@ impl Store {
         let conn = Arc::clone(&self.conn);
         let schema = String::from(schema);
         let result = task::spawn_blocking(move || -> anyhow::Result<()> {
-            conn.lock()
+            conn.connection
+                .lock()
                 .unwrap()
                 .execute_batch(&format!("BEGIN TRANSACTION; {} COMMIT;", schema))?;
             Ok(())
@@ -245,13 +440,50 @@ impl Store {
     /// Begins a new transaction in the database with `BEGIN TRANSACTION;`.
     pub fn begin_transaction(&self) -> anyhow::Result<()> {
         trace!("Beginning new store transaction");
-        Ok(self.conn.lock().unwrap().execute_batch("BEGIN TRANSACTION")?)
+        self.execute_batch("BEGIN TRANSACTION")
     }
 
     /// Commits an ongoing transaction in the database with `COMMIT`;
     pub fn commit_transaction(&self) -> anyhow::Result<()> {
         trace!("Committing store transaction");
-        Ok(self.conn.lock().unwrap().execute_batch("COMMIT")?)
+        self.execute_batch("COMMIT")
+    }
+
+    /// Rolls back an ongoing transaction in the database with `ROLLBACK`;
+    pub fn rollback_transaction(&self) -> anyhow::Result<()> {
+        trace!("Rolling back store transaction");
+        self.execute_batch("ROLLBACK")
+    }
+
+    /// Executes a given SQL statement batch in the database, blocking the current thread.
+    fn execute_batch(&self, sql: &str) -> anyhow::Result<()> {
+        let result = self.conn.connection.lock().unwrap().execute_batch(sql);
+        self.conn.check_read_only(result.map_err(Into::into))
+    }
+
+    /// Returns whether the store's database has refused a write because it's read-only. While it is, changes can't be
+    /// stored but everything stored can still be read.
+    pub fn is_read_only(&self) -> bool {
+        self.conn.read_only.load(Ordering::SeqCst)
+    }
+
+    /// Finishes an ongoing transaction based on a given result of the operations done in it: commits the transaction
+    /// if the result is `Ok`, or rolls it back if the result or committing is an error. Returns the result.
+    pub fn finish_transaction<T>(&self, result: anyhow::Result<T>) -> anyhow::Result<T> {
+        let result = result.and_then(|value| {
+            self.commit_transaction()?;
+            Ok(value)
+        });
+
+        if let Err(e) = &result {
+            warn!("Store transaction failed, rolling back: {}", e);
+            // SQLite may have rolled the transaction back already by itself, for example when the disk is full
+            if let Err(rollback_e) = self.rollback_transaction() {
+                debug!("Failed to roll back store transaction: {}", rollback_e);
+            }
+        }
+
+        result
     }
 
     /// Retrieves an option value from the option table with a given option field.
@@ -262,12 +494,18 @@ impl Store {
 
             Ok(stmt
                 .query_row_named(&option::Value::select_params(&field), |row| {
-                    Ok(row.into())
+                    option::Value::try_from_row(row)
                 })
                 .optional()?)
         })
     }
 
+    /// Returns the store's schema version, or `None` if the store doesn't have one.
+    pub async fn schema_version(&self) -> anyhow::Result<Option<u32>> {
+        let conn = &self.conn;
+        sql!(conn => { schema_version_from_connection(&conn) })
+    }
+
     /// Stores an option value to the options table.
     pub async fn set_option(&self, value: option::Value) -> anyhow::Result<()> {
         let conn = &self.conn;
@@ -285,7 +523,7 @@ impl Store {
 
             Ok(stmt
                 .query_row_named(&GameSettings::select_params(&game), |row| {
-                    Ok(row.into())
+                    GameSettings::try_from_row(row)
                 })?)
         })
     }
@@ -299,218 +537,1421 @@ impl Store {
         })
     }
 
-    /// Retrieves all stored `Game`s.
-    pub async fn get_games(&self) -> anyhow::Result<Vec<Game>> {
+    /// Retrieves a given game's mod policy, or `None` if one hasn't been stored.
+    pub async fn get_mod_policy(&self, game: GameStoreId) -> anyhow::Result<Option<GameModPolicy>> {
         let conn = &self.conn;
         sql!(conn => {
-            let mut stmt = conn.prepare(Game::select_all())?;
-            let mut games = Vec::new();
+            let mut stmt = conn.prepare(GameModPolicy::select())?;
 
-            for game in stmt.query_map(NO_PARAMS, |row| {
-                Ok(row.into())
-            })? {
-                games.push(game?);
-            }
+            Ok(stmt
+                .query_row_named(&GameModPolicy::select_params(&game), |row| {
+                    GameModPolicy::try_from_row(row)
+                })
+                .optional()?)
+        })
+    }
 
-            Ok(games)
+    /// Stores a given game's mod policy.
+    pub async fn set_mod_policy(&self, policy: GameModPolicy) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(GameModPolicy::replace_into(), &policy.all_params())?;
+            Ok(())
         })
     }
 
-    /// Retrieves all mods of a given `Game`, identified by its store ID.
-    pub async fn get_mods_of_game(&self, game_store_id: GameStoreId) -> anyhow::Result<Vec<GameMod>> {
+    /// Returns every game's stored mod portal credentials.
+    pub async fn get_all_portal_credentials(&self) -> anyhow::Result<Vec<PortalCredentials>> {
         let conn = &self.conn;
         sql!(conn => {
-            let mut stmt = conn.prepare(GameMod::select())?;
-            let mut mods = Vec::new();
+            let mut stmt = conn.prepare(PortalCredentials::select_all())?;
+            let mut credentials = Vec::new();
 
-            for row in stmt.query_map_named(&GameMod::select_params(&game_store_id), |row| {
-                Ok(row.into())
+            for game_credentials in stmt.query_map(NO_PARAMS, |row| {
+                PortalCredentials::try_from_row(row)
             })? {
-                mods.push(row?);
+                credentials.push(game_credentials?);
             }
 
-            Ok(mods)
+            Ok(credentials)
         })
     }
 
-    /// Stores all the mods of a `Game`. Will replace existing stored mods in the database.
-    pub async fn set_mods_of_game(&self, mods: Vec<GameMod>) -> anyhow::Result<()> {
+    /// Stores a given game's mod portal credentials.
+    pub async fn set_portal_credentials(&self, credentials: PortalCredentials) -> anyhow::Result<()> {
         let conn = &self.conn;
         sql!(conn => {
-            let mut stmt = conn.prepare(GameMod::replace_into())?;
+            conn.execute_named(PortalCredentials::replace_into(), &credentials.all_params())?;
+            Ok(())
+        })
+    }
 
-            for m in &mods {
-                stmt.execute_named(&m.all_params())?;
-            }
+    /// Removes a given game's stored mod portal credentials, identified by the game's store ID.
+    pub async fn remove_portal_credentials(&self, game: GameStoreId) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(
+                "DELETE FROM portal_credentials WHERE game = :game",
+                named_params! { ":game": game },
+            )?;
 
             Ok(())
         })
     }
 
-    /// Stores a new `Game`.
-    pub async fn insert_game(&self, new_game: Game) -> anyhow::Result<GameStoreId> {
+    /// Retrieves a given game's map exchange string, or `None` if one hasn't been stored.
+    pub async fn get_map_exchange_string(&self, game: GameStoreId) -> anyhow::Result<Option<MapExchangeString>> {
         let conn = &self.conn;
         sql!(conn => {
-            conn.execute_named(Game::insert_into(), &new_game.all_params())?;
-            let id = conn.last_insert_rowid();
+            let mut stmt = conn.prepare(MapExchangeString::select())?;
 
-            Ok(id as GameStoreId)
+            Ok(stmt
+                .query_row_named(&MapExchangeString::select_params(&game), |row| {
+                    MapExchangeString::try_from_row(row)
+                })
+                .optional()?)
         })
     }
 
-    /// Updates an existing stored `Game`.
-    pub async fn update_game(&self, game: Game) -> anyhow::Result<()> {
+    /// Stores a given game's map exchange string.
+    pub async fn set_map_exchange_string(&self, exchange_string: MapExchangeString) -> anyhow::Result<()> {
         let conn = &self.conn;
         sql!(conn => {
-            conn.execute_named(
-                Game::update(),
-                &game.all_params(),
-            )?;
-
+            conn.execute_named(MapExchangeString::replace_into(), &exchange_string.all_params())?;
             Ok(())
         })
     }
 
-    /// Retrieves an optional `FactorioMod`.
-    pub async fn get_factorio_mod(&self, factorio_mod: String) -> anyhow::Result<Option<FactorioMod>> {
+    /// Retrieves a given game's last known good mod set, or `None` if one hasn't been recorded.
+    pub async fn get_last_known_good_mods(&self, game: GameStoreId) -> anyhow::Result<Option<LastKnownGoodMods>> {
         let conn = &self.conn;
         sql!(conn => {
-            let mut stmt = conn.prepare(FactorioMod::select())?;
+            let mut stmt = conn.prepare(LastKnownGoodMods::select())?;
 
             Ok(stmt
-                .query_row_named(&FactorioMod::select_params(&factorio_mod), |row| {
-                    Ok(row.into())
+                .query_row_named(&LastKnownGoodMods::select_params(&game), |row| {
+                    LastKnownGoodMods::try_from_row(row)
                 })
                 .optional()?)
         })
     }
 
-    /// Stores a single `FactorioMod`.
-    pub async fn set_factorio_mod(&self, factorio_mod: models::FactorioMod) -> anyhow::Result<()> {
+    /// Stores a given game's last known good mod set, replacing the earlier one.
+    pub async fn set_last_known_good_mods(&self, mods: LastKnownGoodMods) -> anyhow::Result<()> {
         let conn = &self.conn;
         sql!(conn => {
-            conn.execute_named(FactorioMod::replace_into(), &factorio_mod.all_params())?;
+            conn.execute_named(LastKnownGoodMods::replace_into(), &mods.all_params())?;
             Ok(())
         })
     }
 
-    /// Retrieves all releases of a `FactorioMod`.
-    pub async fn get_mod_releases(&self, factorio_mod: String) -> anyhow::Result<Vec<ModRelease>> {
+    /// Retrieves a given game's mod presets.
+    pub async fn get_mod_presets(&self, game: GameStoreId) -> anyhow::Result<Vec<GameModPreset>> {
         let conn = &self.conn;
         sql!(conn => {
-            let mut stmt = conn.prepare(ModRelease::select())?;
-            let mut mods = Vec::new();
+            let mut stmt = conn.prepare(GameModPreset::select())?;
+            let mut presets = Vec::new();
 
-            for m in
-                stmt.query_map_named(&ModRelease::select_params(&factorio_mod), |row| {
-                    Ok(row.into())
-                })?
-            {
-                mods.push(m?);
+            for row in stmt.query_map_named(&GameModPreset::select_params(&game), |row| {
+                GameModPreset::try_from_row(row)
+            })? {
+                presets.push(row?);
             }
 
-            Ok(mods)
+            Ok(presets)
         })
     }
 
-    /// Stores a single `ModRelease`.
-    pub async fn set_mod_release(&self, release: ModRelease) -> anyhow::Result<()> {
+    /// Stores a given mod preset, replacing the game's earlier preset with the same name.
+    pub async fn set_mod_preset(&self, preset: GameModPreset) -> anyhow::Result<()> {
         let conn = &self.conn;
         sql!(conn => {
-            conn.execute_named(ModRelease::replace_into(), &release.all_params())?;
+            conn.execute_named(GameModPreset::replace_into(), &preset.all_params())?;
             Ok(())
         })
     }
 
-    /// Retrieves all `ReleaseDependencies` of a given `ModRelease` based on its mod's name and its
-    /// version.
-    pub async fn get_release_dependencies(
-        &self,
-        release_mod_name: String,
-        release_version: HumanVersion,
-    ) -> anyhow::Result<Vec<ReleaseDependency>> {
+    /// Removes a given game's mod preset with a given name. Returns whether the preset was stored.
+    pub async fn remove_mod_preset(&self, game: GameStoreId, name: String) -> anyhow::Result<bool> {
         let conn = &self.conn;
         sql!(conn => {
-            let mut stmt = conn.prepare(ReleaseDependency::select())?;
-            let mut dependencies = Vec::new();
+            let removed = conn.execute_named(
+                "DELETE FROM game_mod_preset WHERE game = :game AND name = :name",
+                named_params! { ":game": game, ":name": name },
+            )?;
 
-            for dep in
-                stmt.query_map_named(&ReleaseDependency::select_params(&release_mod_name, &release_version), |row| {
-                    Ok(row.into())
-                })?
-            {
-                dependencies.push(dep?);
+            Ok(removed > 0)
+        })
+    }
+
+    /// Retrieves a given game's player count samples taken at or after a given start and before a given end, the
+    /// oldest first.
+    pub async fn get_player_count_samples(
+        &self,
+        game: GameStoreId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<PlayerCountSample>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(&format!(
+                "{} AND sampled_at >= :start AND sampled_at < :end ORDER BY sampled_at",
+                PlayerCountSample::select()
+            ))?;
+            let mut samples = Vec::new();
+
+            for row in stmt.query_map_named(
+                named_params! { ":game": game, ":start": start, ":end": end },
+                |row| PlayerCountSample::try_from_row(row),
+            )? {
+                samples.push(row?);
             }
 
-            Ok(dependencies)
+            Ok(samples)
         })
     }
 
-    /// Stores all given `ReleaseDependencies`.
-    pub async fn set_release_dependencies(&self, dependencies: Vec<ReleaseDependency>) -> anyhow::Result<()> {
+    /// Stores a given player count sample.
+    pub async fn add_player_count_sample(&self, sample: PlayerCountSample) -> anyhow::Result<()> {
         let conn = &self.conn;
         sql!(conn => {
-            let mut stmt = conn.prepare(ReleaseDependency::replace_into())?;
+            conn.execute_named(PlayerCountSample::replace_into(), &sample.all_params())?;
+            Ok(())
+        })
+    }
 
-            for rel in dependencies {
-                stmt.execute_named(&rel.all_params())?;
+    /// Removes every game's player count samples taken before a given threshold. Returns how many were removed.
+    pub async fn remove_player_count_samples_before(&self, threshold: DateTime<Utc>) -> anyhow::Result<usize> {
+        let conn = &self.conn;
+        sql!(conn => {
+            Ok(conn.execute_named(
+                "DELETE FROM player_count_sample WHERE sampled_at < :threshold",
+                named_params! { ":threshold": threshold },
+            )?)
+        })
+    }
+
+    /// Retrieves at most a given amount of a given game's events of given kinds, or of every kind if none are given,
+    /// that happened at or after a given start and before a given end. If a given event ID is given, only the events
+    /// recorded before it are retrieved. The most recently recorded events come first.
+    pub async fn get_server_events(
+        &self,
+        game: GameStoreId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        kinds: &[EventKind],
+        before: Option<i64>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<ServerEvent>> {
+        // the kinds' names are fixed, so they're safe to put in the query as they are
+        let kinds = if kinds.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " AND kind IN ({})",
+                kinds
+                    .iter()
+                    .map(|kind| format!("'{}'", kind))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT * FROM server_event WHERE game = :game AND occurred_at >= :start AND occurred_at < :end AND id < \
+                 :before{} ORDER BY id DESC LIMIT :limit",
+                kinds
+            ))?;
+            let mut events = Vec::new();
+
+            for row in stmt.query_map_named(
+                named_params! {
+                    ":game": game,
+                    ":start": start,
+                    ":end": end,
+                    ":before": before.unwrap_or(i64::MAX),
+                    ":limit": limit,
+                },
+                |row| ServerEvent::try_from_row(row),
+            )? {
+                events.push(row?);
             }
 
-            Ok(())
+            Ok(events)
         })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::store;
+    /// Stores a given new game event. The event's ID is ignored and a new one is assigned.
+    pub async fn add_server_event(&self, event: ServerEvent) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(ServerEvent::insert_into(), &event.all_params())?;
+            Ok(())
+        })
+    }
 
-    async fn get_test_store(schema: &str) -> Store {
-        store::Builder::<String>::from_location(StoreLocation::Memory)
-            .with_schema(schema)
-            .skip_storing_checksum(true)
-            .build()
-            .await
-            .expect("failed to build test store")
+    /// Removes every game's events that happened before a given threshold. Returns how many were removed.
+    pub async fn remove_server_events_before(&self, threshold: DateTime<Utc>) -> anyhow::Result<usize> {
+        let conn = &self.conn;
+        sql!(conn => {
+            Ok(conn.execute_named(
+                "DELETE FROM server_event WHERE occurred_at < :threshold",
+                named_params! { ":threshold": threshold },
+            )?)
+        })
     }
 
-    #[tokio::test]
-    async fn set_option() {
-        const SCHEMA: &str = r#"CREATE TABLE "options" (
-"field"	TEXT NOT NULL,
-"value"	TEXT,
-PRIMARY KEY("field")
-);"#;
-        let store = get_test_store(SCHEMA).await;
+    /// Claims a given idempotency key, after forgetting every key claimed before a given expiry threshold. Returns
+    /// whether the key was claimed; `false` means an earlier request already claimed it.
+    pub async fn claim_idempotency_key(
+        &self,
+        key: IdempotencyKey,
+        expire_before: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(
+                "DELETE FROM idempotency_key WHERE claimed_at < :threshold",
+                named_params! { ":threshold": expire_before },
+            )?;
 
-        store.begin_transaction().expect("failed to begin transaction");
-        store
-            .set_option(option::Value::new(
-                option::Field::PortalUsername,
-                Some(String::from("value")),
-            ))
-            .await
-            .expect("failed to set meta value");
-        store.commit_transaction().expect("failed to commit transaction");
+            let existing = conn
+                .query_row_named(IdempotencyKey::select(), &IdempotencyKey::select_params(&key.key), |row| {
+                    IdempotencyKey::try_from_row(row)
+                })
+                .optional()?;
+            if existing.is_some() {
+                return Ok(false);
+            }
+
+            conn.execute_named(IdempotencyKey::replace_into(), &key.all_params())?;
+            Ok(true)
+        })
     }
 
-    #[tokio::test]
-    async fn get_option() {
-        const SCHEMA: &str = r#"CREATE TABLE "options" (
-"field"	TEXT NOT NULL,
-"value"	TEXT,
-PRIMARY KEY("field")
-);
-INSERT INTO options("field", "value") VALUES("PortalUsername", "value");"#;
-        let store = get_test_store(SCHEMA).await;
+    /// Forgets a given claimed idempotency key, so a request with the key can be executed again.
+    pub async fn release_idempotency_key(&self, key: String) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(
+                "DELETE FROM idempotency_key WHERE key = :key",
+                named_params! { ":key": key },
+            )?;
 
-        let got_value = store
-            .get_option(option::Field::PortalUsername)
-            .await
-            .expect("failed to get option value")
-            .expect("store returned no value");
+            Ok(())
+        })
+    }
 
-        assert_eq!(got_value.value(), Some("value"));
+    /// Retrieves the stored mod portal response to a given URL, or `None` if one hasn't been stored.
+    pub async fn get_portal_response(&self, url: String) -> anyhow::Result<Option<PortalResponse>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(PortalResponse::select())?;
+
+            Ok(stmt
+                .query_row_named(&PortalResponse::select_params(&url), |row| {
+                    PortalResponse::try_from_row(row)
+                })
+                .optional()?)
+        })
+    }
+
+    /// Stores a given mod portal response, replacing the earlier one to the same URL, after forgetting every response
+    /// fetched before a given expiry threshold.
+    pub async fn set_portal_response(
+        &self,
+        response: PortalResponse,
+        expire_before: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(
+                "DELETE FROM portal_response WHERE fetched_at < :threshold",
+                named_params! { ":threshold": expire_before },
+            )?;
+
+            conn.execute_named(PortalResponse::replace_into(), &response.all_params())?;
+            Ok(())
+        })
+    }
+
+    /// Returns the RPC token with a given hash, if one exists.
+    pub async fn get_rpc_token_by_hash(&self, token_hash: String) -> anyhow::Result<Option<RpcToken>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            Ok(conn
+                .query_row_named(
+                    "SELECT * FROM rpc_token WHERE token_hash = :token_hash",
+                    named_params! { ":token_hash": token_hash },
+                    |row| RpcToken::try_from_row(row),
+                )
+                .optional()?)
+        })
+    }
+
+    /// Returns every stored RPC token.
+    pub async fn get_rpc_tokens(&self) -> anyhow::Result<Vec<RpcToken>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(RpcToken::select_all())?;
+            let mut tokens = Vec::new();
+
+            for token in stmt.query_map(NO_PARAMS, |row| RpcToken::try_from_row(row))? {
+                tokens.push(token?);
+            }
+
+            Ok(tokens)
+        })
+    }
+
+    /// Returns whether any RPC tokens are stored.
+    pub async fn has_rpc_tokens(&self) -> anyhow::Result<bool> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let count: i64 = conn.query_row("SELECT COUNT(*) FROM rpc_token", NO_PARAMS, |row| row.get(0))?;
+            Ok(count > 0)
+        })
+    }
+
+    /// Stores a given RPC token, replacing any existing one with the same name.
+    pub async fn set_rpc_token(&self, token: RpcToken) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(RpcToken::replace_into(), &token.all_params())?;
+            Ok(())
+        })
+    }
+
+    /// Removes the RPC token with a given name. Returns whether a token was removed.
+    pub async fn remove_rpc_token(&self, name: String) -> anyhow::Result<bool> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let removed = conn.execute_named(
+                "DELETE FROM rpc_token WHERE name = :name",
+                named_params! { ":name": name },
+            )?;
+
+            Ok(removed > 0)
+        })
+    }
+
+    /// Retrieves all stored `Game`s.
+    pub async fn get_games(&self) -> anyhow::Result<Vec<Game>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(Game::select_all())?;
+            let mut games = Vec::new();
+
+            for game in stmt.query_map(NO_PARAMS, |row| {
+                Game::try_from_row(row)
+            })? {
+                games.push(game?);
+            }
+
+            Ok(games)
+        })
+    }
+
+    /// Returns the store ID of the game with a given name, or `None` if no game has the name.
+    pub async fn get_game_id_by_name(&self, name: String) -> anyhow::Result<Option<GameStoreId>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            Ok(conn
+                .query_row_named(
+                    "SELECT id FROM game WHERE name = :name",
+                    named_params! { ":name": name },
+                    |row| row.get(0),
+                )
+                .optional()?)
+        })
+    }
+
+    /// Sets or clears the name of a given `Game`, identified by its store ID.
+    pub async fn set_game_name(&self, game_store_id: GameStoreId, name: Option<String>) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(
+                "UPDATE game SET name = :name WHERE id = :id",
+                named_params! { ":name": name, ":id": game_store_id },
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Retrieves all mods of a given `Game`, identified by its store ID.
+    pub async fn get_mods_of_game(&self, game_store_id: GameStoreId) -> anyhow::Result<Vec<GameMod>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(GameMod::select())?;
+            let mut mods = Vec::new();
+
+            for row in stmt.query_map_named(&GameMod::select_params(&game_store_id), |row| {
+                GameMod::try_from_row(row)
+            })? {
+                mods.push(row?);
+            }
+
+            Ok(mods)
+        })
+    }
+
+    /// Stores all the mods of a `Game`. Will replace existing stored mods in the database.
+    pub async fn set_mods_of_game(&self, mods: Vec<GameMod>) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(GameMod::replace_into())?;
+
+            for m in &mods {
+                stmt.execute_named(&m.all_params())?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Removes all stored mods of a given `Game`, identified by its store ID.
+    pub async fn remove_mods_of_game(&self, game_store_id: GameStoreId) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(
+                "DELETE FROM game_mod WHERE game = :game",
+                named_params! { ":game": game_store_id },
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Stores a new `Game`.
+    pub async fn insert_game(&self, new_game: Game) -> anyhow::Result<GameStoreId> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(Game::insert_into(), &new_game.all_params())?;
+            let id = conn.last_insert_rowid();
+
+            Ok(id as GameStoreId)
+        })
+    }
+
+    /// Updates an existing stored `Game`.
+    pub async fn update_game(&self, game: Game) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(
+                Game::update(),
+                &game.all_params(),
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Removes a given `Game` and everything stored for it, identified by its store ID. Returns whether the game was
+    /// stored.
+    pub async fn remove_game(&self, game_store_id: GameStoreId) -> anyhow::Result<bool> {
+        let conn = &self.conn;
+        sql!(conn => {
+            for table in &[
+                "game_settings",
+                "game_mod",
+                "game_mod_policy",
+                "portal_credentials",
+                "map_exchange_string",
+                "last_known_good_mods",
+                "game_mod_preset",
+                "player_count_sample",
+                "server_event",
+            ] {
+                conn.execute_named(
+                    &format!("DELETE FROM {} WHERE game = :game", table),
+                    named_params! { ":game": game_store_id },
+                )?;
+            }
+
+            let removed = conn.execute_named(
+                "DELETE FROM game WHERE id = :id",
+                named_params! { ":id": game_store_id },
+            )?;
+            Ok(removed > 0)
+        })
+    }
+
+    /// Retrieves an optional `FactorioMod`.
+    pub async fn get_factorio_mod(&self, factorio_mod: String) -> anyhow::Result<Option<FactorioMod>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(FactorioMod::select())?;
+
+            Ok(stmt
+                .query_row_named(&FactorioMod::select_params(&factorio_mod), |row| {
+                    FactorioMod::try_from_row(row)
+                })
+                .optional()?)
+        })
+    }
+
+    /// Stores a single `FactorioMod`.
+    pub async fn set_factorio_mod(&self, factorio_mod: models::FactorioMod) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(FactorioMod::replace_into(), &factorio_mod.all_params())?;
+            Ok(())
+        })
+    }
+
+    /// Compresses the large text columns of stored `FactorioMod`s that were stored before they
+    /// were compressed. Returns how many mods were compressed.
+    async fn compress_stored_metadata(&self) -> anyhow::Result<usize> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(
+                "SELECT name, description, changelog FROM factorio_mod WHERE \
+                 (typeof(description) = 'text' AND length(CAST(description AS BLOB)) >= :threshold) OR \
+                 (typeof(changelog) = 'text' AND length(CAST(changelog AS BLOB)) >= :threshold)",
+            )?;
+            let mut uncompressed = Vec::new();
+
+            for row in stmt.query_map_named(named_params! { ":threshold": COMPRESSION_THRESHOLD as i64 }, |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, CompressedText>(1)?,
+                    row.get::<_, Option<CompressedText>>(2)?,
+                ))
+            })? {
+                uncompressed.push(row?);
+            }
+
+            let mut stmt = conn.prepare(
+                "UPDATE factorio_mod SET description = :description, changelog = :changelog WHERE name = :name",
+            )?;
+            for (name, description, changelog) in &uncompressed {
+                stmt.execute_named(named_params! {
+                    ":name": name,
+                    ":description": description,
+                    ":changelog": changelog,
+                })?;
+            }
+
+            Ok(uncompressed.len())
+        })
+    }
+
+    /// Retrieves all releases of a `FactorioMod`.
+    pub async fn get_mod_releases(&self, factorio_mod: String) -> anyhow::Result<Vec<ModRelease>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(ModRelease::select())?;
+            let mut mods = Vec::new();
+
+            for m in
+                stmt.query_map_named(&ModRelease::select_params(&factorio_mod), |row| {
+                    ModRelease::try_from_row(row)
+                })?
+            {
+                mods.push(m?);
+            }
+
+            Ok(mods)
+        })
+    }
+
+    /// Stores a single `ModRelease`.
+    pub async fn set_mod_release(&self, release: ModRelease) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            conn.execute_named(ModRelease::replace_into(), &release.all_params())?;
+            Ok(())
+        })
+    }
+
+    /// Retrieves all `ReleaseDependencies` of a given `ModRelease` based on its mod's name and its
+    /// version.
+    pub async fn get_release_dependencies(
+        &self,
+        release_mod_name: String,
+        release_version: HumanVersion,
+    ) -> anyhow::Result<Vec<ReleaseDependency>> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(ReleaseDependency::select())?;
+            let mut dependencies = Vec::new();
+
+            for dep in
+                stmt.query_map_named(&ReleaseDependency::select_params(&release_mod_name, &release_version), |row| {
+                    ReleaseDependency::try_from_row(row)
+                })?
+            {
+                dependencies.push(dep?);
+            }
+
+            Ok(dependencies)
+        })
+    }
+
+    /// Stores all given `ReleaseDependencies`.
+    pub async fn set_release_dependencies(&self, dependencies: Vec<ReleaseDependency>) -> anyhow::Result<()> {
+        let conn = &self.conn;
+        sql!(conn => {
+            let mut stmt = conn.prepare(ReleaseDependency::replace_into())?;
+
+            for rel in dependencies {
+                stmt.execute_named(&rel.all_params())?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store;
+
+    async fn get_test_store(schema: &str) -> Store {
+        store::Builder::<String>::from_location(StoreLocation::Memory)
+            .with_schema(schema)
+            .skip_storing_checksum(true)
+            .build()
+            .await
+            .expect("failed to build test store")
+    }
+
+    #[tokio::test]
+    async fn exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("modtorio.db");
+        let open = |exclusive| {
+            store::Builder::from_location(StoreLocation::File(path.clone()))
+                .exclusive(exclusive)
+                .build()
+        };
+
+        let store = open(true).await.unwrap();
+        let err = open(true).await.err().expect("store opened exclusively twice");
+        assert!(matches!(err.downcast_ref(), Some(StoreError::Locked { .. })));
+        assert!(open(false).await.is_ok());
+
+        drop(store);
+        assert!(open(true).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn set_option() {
+        const SCHEMA: &str = r#"CREATE TABLE "options" (
+"field"	TEXT NOT NULL,
+"value"	TEXT,
+PRIMARY KEY("field")
+);"#;
+        let store = get_test_store(SCHEMA).await;
+
+        store.begin_transaction().expect("failed to begin transaction");
+        store
+            .set_option(option::Value::new(
+                option::Field::PortalUsername,
+                Some(String::from("value")),
+            ))
+            .await
+            .expect("failed to set meta value");
+        store.commit_transaction().expect("failed to commit transaction");
+    }
+
+    #[tokio::test]
+    async fn read_only() {
+        const SCHEMA: &str = r#"CREATE TABLE "options" (
+"field"	TEXT NOT NULL,
+"value"	TEXT,
+PRIMARY KEY("field")
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        store
+            .execute_batch("PRAGMA query_only = ON")
+            .expect("failed to make store read-only");
+        assert!(!store.is_read_only());
+
+        let err = store
+            .set_option(option::Value::new(
+                option::Field::PortalUsername,
+                Some(String::from("value")),
+            ))
+            .await
+            .expect_err("read-only store was written to");
+        assert!(matches!(err.downcast_ref(), Some(StoreError::ReadOnly)));
+        assert!(store.is_read_only());
+
+        assert!(store
+            .get_option(option::Field::PortalUsername)
+            .await
+            .expect("failed to read read-only store")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_rolled_back() {
+        const SCHEMA: &str = r#"CREATE TABLE "options" (
+"field"	TEXT NOT NULL,
+"value"	TEXT,
+PRIMARY KEY("field")
+);"#;
+        let store = get_test_store(SCHEMA).await;
+
+        store.begin_transaction().expect("failed to begin transaction");
+        let result = async {
+            store
+                .set_option(option::Value::new(
+                    option::Field::PortalUsername,
+                    Some(String::from("value")),
+                ))
+                .await?;
+            Err::<(), _>(anyhow::anyhow!("disk full"))
+        }
+        .await;
+        assert!(store.finish_transaction(result).is_err());
+
+        assert!(store
+            .get_option(option::Field::PortalUsername)
+            .await
+            .expect("failed to get option value")
+            .is_none());
+
+        // the failed transaction isn't left open
+        store.begin_transaction().expect("failed to begin transaction");
+        store.finish_transaction(Ok(())).expect("failed to commit transaction");
+    }
+
+    #[tokio::test]
+    async fn schema_version() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("store.db");
+
+        let store = store::Builder::from_location(StoreLocation::File(&path))
+            .build()
+            .await
+            .expect("failed to build store");
+        assert_eq!(
+            store.schema_version().await.expect("failed to get schema version"),
+            Some(SCHEMA_VERSION)
+        );
+
+        store
+            .set_option(option::Value::new(
+                option::Field::SchemaVersion,
+                Some((SCHEMA_VERSION + 1).to_string()),
+            ))
+            .await
+            .expect("failed to set schema version");
+        drop(store);
+        assert_eq!(
+            read_schema_version(&path).expect("failed to read schema version"),
+            Some(SCHEMA_VERSION + 1)
+        );
+
+        let error = store::Builder::from_location(StoreLocation::File(&path))
+            .build()
+            .await
+            .err()
+            .expect("store with a newer schema opened");
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(StoreError::NewerSchema { stored, supported }) if *stored == SCHEMA_VERSION + 1 && *supported == SCHEMA_VERSION
+        ));
+    }
+
+    #[tokio::test]
+    async fn history_survives_schema_reapply() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("store.db");
+
+        let store = store::Builder::from_location(StoreLocation::File(&path))
+            .build()
+            .await
+            .expect("failed to build store");
+        store
+            .set_rpc_token(RpcToken {
+                name: String::from("operator"),
+                token_hash: String::from("hash"),
+                scopes: String::from("admin"),
+                created_at: Utc::now(),
+            })
+            .await
+            .expect("failed to set token");
+        store
+            .add_player_count_sample(PlayerCountSample {
+                game: 1,
+                sampled_at: Utc::now(),
+                players: 3,
+            })
+            .await
+            .expect("failed to add player count sample");
+        store
+            .add_server_event(ServerEvent {
+                id: 0,
+                game: 1,
+                kind: EventKind::Join,
+                occurred_at: Utc::now(),
+                username: Some(String::from("player")),
+                message: String::new(),
+            })
+            .await
+            .expect("failed to add server event");
+        // a stale checksum makes the next build reapply the schema like an upgrade does
+        store
+            .set_option(option::Value::new(
+                option::Field::SchemaChecksum,
+                Some(String::from("stale")),
+            ))
+            .await
+            .expect("failed to set schema checksum");
+        drop(store);
+
+        let store = store::Builder::from_location(StoreLocation::File(&path))
+            .build()
+            .await
+            .expect("failed to build store");
+        assert_eq!(store.get_rpc_tokens().await.expect("failed to get tokens").len(), 1);
+        let samples = store
+            .get_player_count_samples(1, Utc::now() - chrono::Duration::days(1), Utc::now())
+            .await
+            .expect("failed to get player count samples");
+        assert_eq!(samples.len(), 1);
+        let events = store
+            .get_server_events(1, Utc::now() - chrono::Duration::days(1), Utc::now(), &[], None, 10)
+            .await
+            .expect("failed to get server events");
+        assert_eq!(events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn get_option() {
+        const SCHEMA: &str = r#"CREATE TABLE "options" (
+"field"	TEXT NOT NULL,
+"value"	TEXT,
+PRIMARY KEY("field")
+);
+INSERT INTO options("field", "value") VALUES("PortalUsername", "value");"#;
+        let store = get_test_store(SCHEMA).await;
+
+        let got_value = store
+            .get_option(option::Field::PortalUsername)
+            .await
+            .expect("failed to get option value")
+            .expect("store returned no value");
+
+        assert_eq!(got_value.value(), Some("value"));
+    }
+
+    #[tokio::test]
+    async fn compress_stored_metadata() {
+        let schema = format!(
+            r#"CREATE TABLE "factorio_mod" (
+"name"	TEXT NOT NULL,
+"author"	TEXT NOT NULL,
+"contact"	TEXT,
+"homepage"	TEXT,
+"title"	TEXT NOT NULL,
+"summary"	TEXT,
+"description"	TEXT NOT NULL,
+"changelog"	TEXT,
+"last_updated"	TEXT NOT NULL,
+"category"	TEXT,
+"tags"	TEXT NOT NULL,
+PRIMARY KEY("name")
+);
+INSERT INTO factorio_mod VALUES("big", "author", NULL, NULL, "title", NULL, "{0}", "{0}", "2020-01-01T00:00:00Z", NULL, "");
+INSERT INTO factorio_mod VALUES("small", "author", NULL, NULL, "title", NULL, "small", NULL, "2020-01-01T00:00:00Z", NULL, "");"#,
+            "a".repeat(COMPRESSION_THRESHOLD)
+        );
+        let store = get_test_store(&schema).await;
+
+        assert_eq!(
+            store
+                .compress_stored_metadata()
+                .await
+                .expect("failed to compress stored metadata"),
+            1
+        );
+        // compressed rows aren't compressed again
+        assert_eq!(
+            store
+                .compress_stored_metadata()
+                .await
+                .expect("failed to compress stored metadata"),
+            0
+        );
+
+        let big = store
+            .get_factorio_mod(String::from("big"))
+            .await
+            .expect("failed to get mod")
+            .expect("store returned no mod");
+        assert_eq!(&*big.description, "a".repeat(COMPRESSION_THRESHOLD));
+    }
+
+    #[tokio::test]
+    async fn remove_mods_of_game() {
+        const SCHEMA: &str = r#"CREATE TABLE "game_mod" (
+"game"	INTEGER NOT NULL,
+"factorio_mod"	TEXT NOT NULL,
+"mod_version"	TEXT NOT NULL,
+"mod_zip"	BLOB NOT NULL,
+"zip_last_mtime"	TEXT NOT NULL,
+"update_policy"	TEXT NOT NULL,
+"installed_as_dependency"	INTEGER NOT NULL,
+PRIMARY KEY("game","factorio_mod")
+);
+INSERT INTO game_mod VALUES(1, "a", "1.0.0", X'612E7A6970', "2020-01-01T00:00:00Z", "LatestCompatible", 0);
+INSERT INTO game_mod VALUES(2, "a", "1.0.0", X'612E7A6970', "2020-01-01T00:00:00Z", "Pinned", 1);"#;
+        let store = get_test_store(SCHEMA).await;
+
+        store.remove_mods_of_game(1).await.expect("failed to remove mods");
+
+        assert!(store.get_mods_of_game(1).await.expect("failed to get mods").is_empty());
+        assert_eq!(store.get_mods_of_game(2).await.expect("failed to get mods").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn game_names() {
+        const SCHEMA: &str = r#"CREATE TABLE "game" (
+"id"	INTEGER PRIMARY KEY,
+"path"	BLOB NOT NULL,
+"name"	TEXT UNIQUE
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        let mut ids = Vec::new();
+        for path in &["first", "second"] {
+            let id = store
+                .insert_game(Game {
+                    id: 0,
+                    path: path.as_bytes().to_vec(),
+                    name: None,
+                })
+                .await
+                .expect("failed to insert game");
+            ids.push(id);
+        }
+
+        store
+            .set_game_name(ids[0], Some(String::from("vanilla")))
+            .await
+            .expect("failed to set game name");
+        assert_eq!(
+            store
+                .get_game_id_by_name(String::from("vanilla"))
+                .await
+                .expect("failed to get game by name"),
+            Some(ids[0])
+        );
+        assert!(store
+            .set_game_name(ids[1], Some(String::from("vanilla")))
+            .await
+            .is_err());
+
+        store
+            .set_game_name(ids[0], None)
+            .await
+            .expect("failed to clear game name");
+        assert!(store
+            .get_game_id_by_name(String::from("vanilla"))
+            .await
+            .expect("failed to get game by name")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn remove_game() {
+        let store = store::Builder::<String>::from_location(StoreLocation::Memory)
+            .skip_storing_checksum(true)
+            .build()
+            .await
+            .expect("failed to build test store");
+        let mut ids = Vec::new();
+        for path in &["first", "second"] {
+            let id = store
+                .insert_game(Game {
+                    id: 0,
+                    path: path.as_bytes().to_vec(),
+                    name: None,
+                })
+                .await
+                .expect("failed to insert game");
+            store
+                .set_map_exchange_string(MapExchangeString {
+                    game: id,
+                    exchange_string: String::from("string"),
+                })
+                .await
+                .expect("failed to set map exchange string");
+            ids.push(id);
+        }
+
+        assert!(store.remove_game(ids[0]).await.expect("failed to remove game"));
+        assert!(!store.remove_game(ids[0]).await.expect("failed to remove game"));
+
+        let games = store.get_games().await.expect("failed to get games");
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id, ids[1]);
+        assert!(store
+            .get_map_exchange_string(ids[0])
+            .await
+            .expect("failed to get map exchange string")
+            .is_none());
+        assert!(store
+            .get_map_exchange_string(ids[1])
+            .await
+            .expect("failed to get map exchange string")
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn last_known_good_mods() {
+        const SCHEMA: &str = r#"CREATE TABLE "last_known_good_mods" (
+"game"	INTEGER PRIMARY KEY,
+"modpack"	TEXT NOT NULL,
+"recorded_at"	TEXT NOT NULL
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        let mods = |modpack: &str| LastKnownGoodMods {
+            game: 1,
+            modpack: modpack.to_owned(),
+            recorded_at: Utc::now(),
+        };
+
+        assert!(store
+            .get_last_known_good_mods(1)
+            .await
+            .expect("failed to get mods")
+            .is_none());
+
+        for modpack in &["first", "second"] {
+            store
+                .set_last_known_good_mods(mods(modpack))
+                .await
+                .expect("failed to set mods");
+        }
+
+        let stored = store
+            .get_last_known_good_mods(1)
+            .await
+            .expect("failed to get mods")
+            .expect("store returned no mods");
+        assert_eq!(stored.modpack, "second");
+    }
+
+    #[tokio::test]
+    async fn mod_presets() {
+        const SCHEMA: &str = r#"CREATE TABLE "game_mod_preset" (
+"game"	INTEGER NOT NULL,
+"name"	TEXT NOT NULL,
+"mods"	TEXT NOT NULL,
+"created_at"	TEXT NOT NULL,
+PRIMARY KEY("game","name")
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        let preset = |name: &str, mods: &str| GameModPreset {
+            game: 1,
+            name: name.to_owned(),
+            mods: mods.to_owned(),
+            created_at: Utc::now(),
+        };
+
+        for (name, mods) in &[("vanilla", "first"), ("full", "full"), ("vanilla", "second")] {
+            store
+                .set_mod_preset(preset(name, mods))
+                .await
+                .expect("failed to set mod preset");
+        }
+
+        let mut presets = store.get_mod_presets(1).await.expect("failed to get mod presets");
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+        let presets = presets
+            .iter()
+            .map(|preset| (preset.name.as_str(), preset.mods.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(presets, vec![("full", "full"), ("vanilla", "second")]);
+        assert!(store
+            .get_mod_presets(2)
+            .await
+            .expect("failed to get mod presets")
+            .is_empty());
+
+        assert!(store
+            .remove_mod_preset(1, String::from("full"))
+            .await
+            .expect("failed to remove mod preset"));
+        assert!(!store
+            .remove_mod_preset(1, String::from("full"))
+            .await
+            .expect("failed to remove mod preset"));
+        assert_eq!(
+            store.get_mod_presets(1).await.expect("failed to get mod presets").len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn player_count_samples() {
+        const SCHEMA: &str = r#"CREATE TABLE "player_count_sample" (
+"game"	INTEGER NOT NULL,
+"sampled_at"	TEXT NOT NULL,
+"players"	INTEGER NOT NULL,
+PRIMARY KEY("game","sampled_at")
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        let start = Utc::now();
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+
+        for (game, minutes, players) in &[(1, 10, 3), (1, 0, 1), (1, 20, 5), (2, 10, 7)] {
+            store
+                .add_player_count_sample(PlayerCountSample {
+                    game: *game,
+                    sampled_at: at(*minutes),
+                    players: *players,
+                })
+                .await
+                .expect("failed to add player count sample");
+        }
+
+        let players = |samples: Vec<PlayerCountSample>| samples.iter().map(|s| s.players).collect::<Vec<_>>();
+        let samples = store
+            .get_player_count_samples(1, at(0), at(20))
+            .await
+            .expect("failed to get player count samples");
+        assert_eq!(players(samples), vec![1, 3]);
+
+        assert_eq!(
+            store
+                .remove_player_count_samples_before(at(10))
+                .await
+                .expect("failed to remove player count samples"),
+            1
+        );
+        let samples = store
+            .get_player_count_samples(1, at(0), at(30))
+            .await
+            .expect("failed to get player count samples");
+        assert_eq!(players(samples), vec![3, 5]);
+    }
+
+    #[tokio::test]
+    async fn server_events() {
+        const SCHEMA: &str = r#"CREATE TABLE "server_event" (
+"id"	INTEGER PRIMARY KEY,
+"game"	INTEGER NOT NULL,
+"kind"	TEXT NOT NULL,
+"occurred_at"	TEXT NOT NULL,
+"username"	TEXT,
+"message"	TEXT NOT NULL
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        let start = Utc::now();
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+
+        for (game, minutes, kind, message) in &[
+            (1, 0, EventKind::Join, ""),
+            (1, 10, EventKind::Chat, "hello"),
+            (2, 10, EventKind::Chat, "other"),
+            (1, 20, EventKind::Leave, ""),
+            (1, 30, EventKind::Crash, "exit code 1"),
+        ] {
+            store
+                .add_server_event(ServerEvent {
+                    id: 0,
+                    game: *game,
+                    kind: *kind,
+                    occurred_at: at(*minutes),
+                    username: Some(String::from("player")),
+                    message: String::from(*message),
+                })
+                .await
+                .expect("failed to add server event");
+        }
+
+        let kinds = |events: Vec<ServerEvent>| events.iter().map(|e| e.kind).collect::<Vec<_>>();
+        let events = store
+            .get_server_events(1, at(0), at(30), &[], None, 10)
+            .await
+            .expect("failed to get server events");
+        assert_eq!(kinds(events), vec![EventKind::Leave, EventKind::Chat, EventKind::Join]);
+
+        let page = store
+            .get_server_events(1, at(0), at(40), &[], None, 2)
+            .await
+            .expect("failed to get server events");
+        assert_eq!(kinds(page), vec![EventKind::Crash, EventKind::Leave]);
+        let events = store
+            .get_server_events(1, at(0), at(40), &[], Some(4), 2)
+            .await
+            .expect("failed to get server events");
+        assert_eq!(kinds(events), vec![EventKind::Chat, EventKind::Join]);
+
+        let events = store
+            .get_server_events(1, at(0), at(40), &[EventKind::Chat, EventKind::Crash], None, 10)
+            .await
+            .expect("failed to get server events");
+        assert_eq!(kinds(events), vec![EventKind::Crash, EventKind::Chat]);
+
+        assert_eq!(
+            store
+                .remove_server_events_before(at(10))
+                .await
+                .expect("failed to remove server events"),
+            1
+        );
+        let events = store
+            .get_server_events(1, at(0), at(40), &[EventKind::Join], None, 10)
+            .await
+            .expect("failed to get server events");
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn claim_idempotency_key() {
+        const SCHEMA: &str = r#"CREATE TABLE "idempotency_key" (
+"key"	TEXT PRIMARY KEY,
+"method"	TEXT NOT NULL,
+"claimed_at"	TEXT NOT NULL
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        let now = Utc::now();
+        let key = || IdempotencyKey {
+            key: String::from("key"),
+            method: String::from("RunServer"),
+            claimed_at: now,
+        };
+
+        assert!(store
+            .claim_idempotency_key(key(), now - chrono::Duration::hours(1))
+            .await
+            .expect("failed to claim key"));
+        assert!(!store
+            .claim_idempotency_key(key(), now - chrono::Duration::hours(1))
+            .await
+            .expect("failed to claim key"));
+
+        // the key expires
+        assert!(store
+            .claim_idempotency_key(key(), now + chrono::Duration::seconds(1))
+            .await
+            .expect("failed to claim key"));
+
+        store
+            .release_idempotency_key(String::from("key"))
+            .await
+            .expect("failed to release key");
+        assert!(store
+            .claim_idempotency_key(key(), now - chrono::Duration::hours(1))
+            .await
+            .expect("failed to claim key"));
+    }
+
+    #[tokio::test]
+    async fn portal_responses() {
+        const SCHEMA: &str = r#"CREATE TABLE "portal_response" (
+"url"	TEXT PRIMARY KEY,
+"etag"	TEXT,
+"last_modified"	TEXT,
+"body"	TEXT NOT NULL,
+"fetched_at"	TEXT NOT NULL
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        let url = String::from("https://mods.factorio.com/api/mods/Krastorio2/full");
+        let response = |url: &str, etag: &str, body: String| PortalResponse {
+            url: url.to_owned(),
+            etag: Some(etag.to_owned()),
+            last_modified: None,
+            body: CompressedText::from(body),
+            fetched_at: Utc::now(),
+        };
+        let day_ago = Utc::now() - chrono::Duration::days(1);
+
+        assert!(store
+            .get_portal_response(url.clone())
+            .await
+            .expect("failed to get response")
+            .is_none());
+
+        let body = "{}".repeat(COMPRESSION_THRESHOLD);
+        store
+            .set_portal_response(response(&url, "\"first\"", String::from("{}")), day_ago)
+            .await
+            .expect("failed to set response");
+        store
+            .set_portal_response(response(&url, "\"second\"", body.clone()), day_ago)
+            .await
+            .expect("failed to set response");
+
+        let stored = store
+            .get_portal_response(url.clone())
+            .await
+            .expect("failed to get response")
+            .expect("store returned no response");
+        assert_eq!(stored.etag.as_deref(), Some("\"second\""));
+        assert_eq!(&*stored.body, body);
+
+        // storing another response forgets the expired ones
+        let other = String::from("https://mods.factorio.com/api/mods?page=2");
+        store
+            .set_portal_response(response(&other, "\"other\"", String::from("{}")), Utc::now())
+            .await
+            .expect("failed to set response");
+        assert!(store
+            .get_portal_response(url.clone())
+            .await
+            .expect("failed to get response")
+            .is_none());
+        assert!(store
+            .get_portal_response(other)
+            .await
+            .expect("failed to get response")
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn rpc_tokens() {
+        const SCHEMA: &str = r#"CREATE TABLE "rpc_token" (
+"name"	TEXT PRIMARY KEY,
+"token_hash"	TEXT NOT NULL UNIQUE,
+"scopes"	TEXT NOT NULL,
+"created_at"	TEXT NOT NULL
+);"#;
+        let store = get_test_store(SCHEMA).await;
+        assert!(!store.has_rpc_tokens().await.expect("failed to check for tokens"));
+
+        let token = RpcToken {
+            name: String::from("monitoring"),
+            token_hash: String::from("abcd"),
+            scopes: String::from("read"),
+            created_at: Utc::now(),
+        };
+        store.set_rpc_token(token).await.expect("failed to insert token");
+        assert!(store.has_rpc_tokens().await.expect("failed to check for tokens"));
+        assert_eq!(store.get_rpc_tokens().await.expect("failed to get tokens").len(), 1);
+
+        let found = store
+            .get_rpc_token_by_hash(String::from("abcd"))
+            .await
+            .expect("failed to get token")
+            .expect("store returned no token");
+        assert_eq!(found.name, "monitoring");
+        assert_eq!(
+            store
+                .get_rpc_token_by_hash(String::from("dcba"))
+                .await
+                .expect("failed to get token"),
+            None
+        );
+
+        assert!(store
+            .remove_rpc_token(String::from("monitoring"))
+            .await
+            .expect("failed to remove token"));
+        assert!(!store
+            .remove_rpc_token(String::from("monitoring"))
+            .await
+            .expect("failed to remove token"));
+        assert!(!store.has_rpc_tokens().await.expect("failed to check for tokens"));
     }
 }
@@ -0,0 +1,101 @@
+//! Provides the [`CompressedText`](CompressedText) object, used to transparently compress large text
+//! columns in the store database.
+
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, Value, ValueRef},
+    ToSql,
+};
+use std::ops::Deref;
+
+/// The size in bytes at and above which text values are compressed when stored.
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+/// The zstd compression level used when compressing text values.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// A text value that is stored compressed with zstd if it's at least
+/// [`COMPRESSION_THRESHOLD`](COMPRESSION_THRESHOLD) bytes long.
+///
+/// Values shorter than the threshold are stored as plain text. Compressed values are stored as
+/// blobs, which is how they're told apart when reading them back.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CompressedText(String);
+
+impl CompressedText {
+    /// Consumes the object and returns the contained text.
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl From<String> for CompressedText {
+    fn from(text: String) -> Self {
+        Self(text)
+    }
+}
+
+impl From<CompressedText> for String {
+    fn from(text: CompressedText) -> Self {
+        text.0
+    }
+}
+
+impl Deref for CompressedText {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl ToSql for CompressedText {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        if self.0.len() < COMPRESSION_THRESHOLD {
+            return Ok(ToSqlOutput::Owned(Value::Text(self.0.clone())));
+        }
+
+        let compressed = zstd::encode_all(self.0.as_bytes(), COMPRESSION_LEVEL)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        Ok(ToSqlOutput::Owned(Value::Blob(compressed)))
+    }
+}
+
+impl FromSql for CompressedText {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Text(_) => Ok(Self(value.as_str()?.to_owned())),
+            ValueRef::Blob(compressed) => {
+                let decompressed = zstd::decode_all(compressed).map_err(|e| FromSqlError::Other(Box::new(e)))?;
+                Ok(Self(
+                    String::from_utf8(decompressed).map_err(|e| FromSqlError::Other(Box::new(e)))?,
+                ))
+            }
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() -> anyhow::Result<()> {
+        let short = CompressedText::from(String::from("short"));
+        let long = CompressedText::from("long ".repeat(COMPRESSION_THRESHOLD));
+
+        match short.to_sql()? {
+            ToSqlOutput::Owned(Value::Text(text)) => assert_eq!(text, "short"),
+            other => panic!("short text not stored as text: {:?}", other),
+        }
+
+        match long.to_sql()? {
+            ToSqlOutput::Owned(Value::Blob(blob)) => {
+                assert!(blob.len() < long.len());
+                assert_eq!(CompressedText::column_result(ValueRef::Blob(&blob))?, long);
+            }
+            other => panic!("long text not stored as blob: {:?}", other),
+        }
+
+        Ok(())
+    }
+}
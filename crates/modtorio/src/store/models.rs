@@ -5,8 +5,10 @@
 //!
 //! [Model]: derive::Model
 
+use super::CompressedText;
 use crate::{
-    factorio::{settings::StartBehaviour, GameStoreId},
+    event_history::EventKind,
+    factorio::{mods::UpdatePolicy, settings::StartBehaviour, GameStoreId},
     mod_common::Requirement,
     util::{HumanVersion, HumanVersionReq},
 };
@@ -16,14 +18,16 @@ use derive::Model;
 /// An instance of a Factorio game.
 ///
 /// Uses the [`id`](#structfield.factorio_mod) field as index when querying the store database.
-#[derive(Debug, PartialEq, Model)]
+#[derive(Debug, Clone, PartialEq, Model)]
 pub struct Game {
     /// The game's store ID.
     #[index]
     #[ignore_in_all_params]
     pub id: GameStoreId,
-    /// The game's root directory's path.
-    pub path: String,
+    /// The game's root directory's path as raw bytes, since the path isn't necessarily valid Unicode.
+    pub path: Vec<u8>,
+    /// The game's optional unique name, which it can be addressed by instead of its store ID.
+    pub name: Option<String>,
 }
 
 /// An instance of a Factorio mod.
@@ -44,12 +48,16 @@ pub struct FactorioMod {
     pub title: String,
     /// The mod's optional summary.
     pub summary: Option<String>,
-    /// The mod's description.
-    pub description: String,
-    /// The mod's optional changelog.
-    pub changelog: Option<String>,
+    /// The mod's description. Compressed in the store if it's large.
+    pub description: CompressedText,
+    /// The mod's optional changelog. Compressed in the store if it's large.
+    pub changelog: Option<CompressedText>,
     /// The timestamp when this mod was last updated in the store.
     pub last_updated: DateTime<Utc>,
+    /// The mod's optional category on the mod portal.
+    pub category: Option<String>,
+    /// The mod's tags on the mod portal, combined into a single string.
+    pub tags: String,
 }
 
 /// A mapping of an instance of a Factorio game to an instance of a Factorio mod. Represents a
@@ -76,10 +84,185 @@ pub struct GameMod {
     ///
     /// [ModRelease]: super::ModRelease
     pub mod_version: HumanVersion,
-    /// The filesystem path of the mod's zip archive.
-    pub mod_zip: String,
+    /// The filesystem path of the mod's zip archive as raw bytes, since the path isn't necessarily
+    /// valid Unicode.
+    pub mod_zip: Vec<u8>,
     /// The the mod's zip archive last modified time.
     pub zip_last_mtime: DateTime<Utc>,
+    /// Which release the mod is updated to when the game's mods are updated.
+    pub update_policy: UpdatePolicy,
+    /// Whether the mod was installed only as a dependency of another mod.
+    pub installed_as_dependency: bool,
+}
+
+/// A game's mod portal credentials, overriding the configured credentials for the game. The token is encrypted.
+///
+/// Uses the [`game`](#structfield.game) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model, Default)]
+pub struct PortalCredentials {
+    /// The game's store ID. Corresponds to the [id][Game#structfield.id] field of a [Game].
+    ///
+    /// [Game]: super::Game
+    #[index]
+    pub game: GameStoreId,
+    /// The mod portal username.
+    pub username: String,
+    /// The nonce the token was encrypted with.
+    pub token_nonce: Vec<u8>,
+    /// The encrypted mod portal token.
+    pub token: Vec<u8>,
+}
+
+/// A game's map exchange string, which its map generation settings were imported from.
+///
+/// Uses the [`game`](#structfield.game) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model, Default)]
+pub struct MapExchangeString {
+    /// The game's store ID. Corresponds to the [id][Game#structfield.id] field of a [Game].
+    ///
+    /// [Game]: super::Game
+    #[index]
+    pub game: GameStoreId,
+    /// The map exchange string.
+    pub exchange_string: String,
+}
+
+/// A game's mod set recorded the last time the game started successfully.
+///
+/// Uses the [`game`](#structfield.game) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model)]
+pub struct LastKnownGoodMods {
+    /// The game's store ID. Corresponds to the [id][Game#structfield.id] field of a [Game].
+    ///
+    /// [Game]: super::Game
+    #[index]
+    pub game: GameStoreId,
+    /// The mod set as a JSON modpack manifest.
+    pub modpack: String,
+    /// The timestamp when the mod set was recorded.
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// A named preset of which of a game's mods are enabled.
+///
+/// Uses the [`game`](#structfield.game) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model)]
+pub struct GameModPreset {
+    /// The game's store ID. Corresponds to the [id][Game#structfield.id] field of a [Game].
+    ///
+    /// [Game]: super::Game
+    #[index]
+    pub game: GameStoreId,
+    /// The preset's name.
+    pub name: String,
+    /// The mods' enabled states as a JSON object keyed by the mods' names.
+    pub mods: String,
+    /// The timestamp when the preset was saved.
+    pub created_at: DateTime<Utc>,
+}
+
+/// A sample of how many players were in a game at a given time.
+///
+/// Uses the [`game`](#structfield.game) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model)]
+pub struct PlayerCountSample {
+    /// The game's store ID. Corresponds to the [id][Game#structfield.id] field of a [Game].
+    ///
+    /// [Game]: super::Game
+    #[index]
+    pub game: GameStoreId,
+    /// The timestamp when the sample was taken.
+    pub sampled_at: DateTime<Utc>,
+    /// How many players were in the game.
+    pub players: i64,
+}
+
+/// A notable event that happened in a game, kept as the game's audit trail.
+///
+/// Uses the [`id`](#structfield.id) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model)]
+pub struct ServerEvent {
+    /// The event's store ID. Increases as events are recorded.
+    #[index]
+    #[ignore_in_all_params]
+    pub id: i64,
+    /// The game's store ID. Corresponds to the [id][Game#structfield.id] field of a [Game].
+    ///
+    /// [Game]: super::Game
+    pub game: GameStoreId,
+    /// What kind of an event this is.
+    pub kind: EventKind,
+    /// The timestamp when the event happened.
+    pub occurred_at: DateTime<Utc>,
+    /// The player the event is about, if any.
+    pub username: Option<String>,
+    /// The chat message, the ban's reason or the crash's error. Empty for joins and leaves.
+    pub message: String,
+}
+
+/// A game's policy on which mods may be installed from the mod portal.
+///
+/// Uses the [`game`](#structfield.game) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model, Default)]
+pub struct GameModPolicy {
+    /// The game's store ID. Corresponds to the [id][Game#structfield.id] field of a [Game].
+    ///
+    /// [Game]: super::Game
+    #[index]
+    pub game: GameStoreId,
+    /// The mod categories excluded from installation, combined into a single string.
+    pub excluded_categories: String,
+    /// The mod tags excluded from installation, combined into a single string.
+    pub excluded_tags: String,
+}
+
+/// An idempotency key claimed by a mutating RPC request, used to detect retries of already executed requests.
+///
+/// Uses the [`key`](#structfield.key) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model)]
+pub struct IdempotencyKey {
+    /// The key.
+    #[index]
+    pub key: String,
+    /// The name of the RPC method the key was claimed for.
+    pub method: String,
+    /// The timestamp when the key was claimed.
+    pub claimed_at: DateTime<Utc>,
+}
+
+/// A mod portal response stored with its cache validators, so it can be requested conditionally and reused if it
+/// hasn't changed.
+///
+/// Uses the [`url`](#structfield.url) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model)]
+pub struct PortalResponse {
+    /// The request URL without the mod portal credentials.
+    #[index]
+    pub url: String,
+    /// The response's `ETag` header, if it had one.
+    pub etag: Option<String>,
+    /// The response's `Last-Modified` header, if it had one.
+    pub last_modified: Option<String>,
+    /// The response's body. Compressed in the store if it's large.
+    pub body: CompressedText,
+    /// The timestamp when the response was received.
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// A token that authorizes RPC requests for a set of scopes.
+///
+/// Uses the [`name`](#structfield.name) field as an index when querying the store database.
+#[derive(Debug, PartialEq, Model)]
+pub struct RpcToken {
+    /// The token's name.
+    #[index]
+    pub name: String,
+    /// The token's BLAKE2b hash as a hex string.
+    pub token_hash: String,
+    /// The scopes the token authorizes, combined into a single string.
+    pub scopes: String,
+    /// The timestamp when the token was created.
+    pub created_at: DateTime<Utc>,
 }
 
 /// An instance of a [`FactorioMod`'s](super::FactorioMod) release.
@@ -205,4 +388,37 @@ pub struct GameSettings {
     /// The start behaviour, a combination of the `--create`, `--start-server`, `--start-server-load-latest` and
     /// `--start-server-load-scenario` command line parameters.
     pub start_behaviour: StartBehaviour,
+    /// Whether Modtorio manages the game's mods, as a boolean.
+    pub mods_managed: i64,
+    /// Whether Modtorio starts the game when Modtorio itself starts, as a boolean.
+    pub autostart: i64,
+    /// The Factorio version in the shared version pool the game runs, if it runs a pooled version.
+    pub game_version: Option<HumanVersion>,
+    /// The custom location of the game's saves as raw bytes, if set.
+    pub saves_path: Option<Vec<u8>>,
+    /// The custom location of the game's mods as raw bytes, if set.
+    pub mods_path: Option<Vec<u8>>,
+    /// The custom location of the game's script output as raw bytes, if set.
+    pub script_output_path: Option<Vec<u8>>,
+    /// The cron expression of when the game's mods are updated automatically, if they are.
+    pub mod_update_schedule: Option<String>,
+    /// The paths of the settings files the game's settings are composed from as raw bytes, separated by null bytes, if
+    /// any.
+    pub settings_includes: Option<Vec<u8>>,
+    /// The game's settings that differ from its included settings files as a JSON object, if any.
+    pub settings_overrides: Option<String>,
+    /// The game's autosave interval thresholds as comma-separated `players:interval` pairs, if any.
+    pub autosave_policy: Option<String>,
+    /// How many of the game's most recent autosaves are kept when its autosaves are pruned.
+    pub autosave_keep_recent: i64,
+    /// For how many days the latest autosave of each day is kept when the game's autosaves are pruned.
+    pub autosave_keep_daily_days: i64,
+    /// The settings in the game's settings file that Modtorio doesn't know as a JSON object, if any.
+    pub settings_extra: Option<String>,
+    /// The last mtime of the game's settings file the stored settings are in sync with, if known.
+    pub settings_last_mtime: Option<DateTime<Utc>>,
+    /// The usernames in the game's admin list as a JSON array, if Modtorio manages the list.
+    pub admin_list: Option<String>,
+    /// The usernames in the game's whitelist as a JSON array, if Modtorio manages the list.
+    pub whitelist: Option<String>,
 }
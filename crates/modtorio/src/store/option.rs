@@ -19,6 +19,22 @@ pub enum Field {
     PortalToken,
     /// The current store database SQL schema's checksum. Used to detect changes in the SQL schema.
     SchemaChecksum,
+    /// The current store database SQL schema's version. Used to refuse stores created by newer program versions.
+    SchemaVersion,
+    /// The instance's message-of-the-day.
+    Motd,
+    /// The name of who set the instance's message-of-the-day.
+    MotdAuthor,
+    /// The RFC 3339 timestamp when the instance's message-of-the-day was set.
+    MotdSetAt,
+    /// Whether the REST gateway feature is enabled.
+    FeatureRestGateway,
+    /// Whether the automatic updates feature is enabled.
+    FeatureAutoUpdates,
+    /// Whether the container backend feature is enabled.
+    FeatureContainerBackend,
+    /// The long-running tasks the previous shutdown interrupted, as JSON.
+    InterruptedTasks,
 }
 
 /// A store option value.
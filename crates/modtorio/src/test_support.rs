@@ -0,0 +1,921 @@
+//! Provides support for end-to-end tests: [`FixtureServer`](FixtureServer), a server directory with a scripted fake
+//! Factorio executable, [`FixtureGame`](FixtureGame), a fixture server imported directly as a game instance, and
+//! [`TestInstance`](TestInstance), a full Modtorio instance with an in-memory store driven over its real RPC API.
+//!
+//! Available in the crate's own tests and to other crates with the `test-support` feature.
+
+use crate::{
+    auth::{self, Scope},
+    config,
+    factorio::{Factorio, GameStoreId, Importer},
+    store::{self, models, Store, StoreLocation},
+    Config, ModPortal, Modtorio,
+};
+use rpc::{mod_rpc_client::ModRpcClient, progress::ProgressType};
+use std::{
+    fmt, fs,
+    io::Cursor,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+use tempfile::TempDir;
+use tokio::{
+    sync::watch,
+    task::{self, JoinHandle},
+    time,
+};
+use tonic::{
+    metadata::MetadataValue,
+    transport::{Channel, Endpoint},
+};
+
+/// The fake Factorio executable's script.
+const FAKE_EXECUTABLE: &str = include_str!("test_support/fake_factorio.sh");
+/// The server settings every fixture server starts with.
+const FIXTURE_SERVER_SETTINGS: &str = include_str!("../../../sample/server-settings.json");
+/// The metadata key RPC requests carry their bearer token in.
+const AUTHORIZATION_KEY: &str = "authorization";
+/// How often a condition is polled while waiting for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A temporary server directory with a fake Factorio executable, server settings and empty mods and saves
+/// directories. The directory is removed when the fixture is dropped.
+#[derive(Debug)]
+pub struct FixtureServer {
+    /// The server's root directory.
+    dir: TempDir,
+}
+
+/// A fixture server imported directly as a game instance, without a Modtorio instance, with the default config and an
+/// in-memory store.
+#[derive(Debug)]
+pub struct FixtureGame {
+    /// The fixture server.
+    server: FixtureServer,
+    /// The config the server is imported with.
+    config: Arc<Config>,
+    /// The store the server is imported with.
+    store: Arc<Store>,
+}
+
+/// A Modtorio instance with an in-memory store, serving its RPC API on a local TCP port until shut down.
+pub struct TestInstance {
+    /// The instance.
+    instance: Modtorio,
+    /// A client connected to the instance's RPC API.
+    client: ModRpcClient<Channel>,
+    /// Sends the shutdown signal to the RPC server.
+    shutdown_tx: watch::Sender<()>,
+    /// The RPC server's task.
+    rpc_task: JoinHandle<anyhow::Result<()>>,
+}
+
+impl FixtureServer {
+    /// Creates a new fixture server in a temporary directory.
+    pub fn new() -> anyhow::Result<Self> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path();
+
+        let executable = root.join(crate::factorio::executable::DEFAULT_PATH);
+        fs::create_dir_all(executable.parent().expect("executable path has no parent"))?;
+        fs::write(&executable, FAKE_EXECUTABLE)?;
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755))?;
+
+        fs::write(root.join("server-settings.json"), FIXTURE_SERVER_SETTINGS)?;
+        fs::create_dir(root.join("mods"))?;
+        fs::create_dir(root.join("saves"))?;
+
+        Ok(Self { dir })
+    }
+
+    /// Returns the server's root directory.
+    pub fn root(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl FixtureGame {
+    /// Creates a new fixture server with the default config and an empty in-memory store.
+    pub async fn new() -> anyhow::Result<Self> {
+        let store = store::Builder::<String>::from_location(StoreLocation::Memory)
+            .build()
+            .await?;
+
+        Ok(Self {
+            server: FixtureServer::new()?,
+            config: Arc::new(Config::default()),
+            store: Arc::new(store),
+        })
+    }
+
+    /// Returns the server's root directory.
+    pub fn root(&self) -> &Path {
+        self.server.root()
+    }
+
+    /// Returns the store the server is imported with.
+    pub fn store(&self) -> &Arc<Store> {
+        &self.store
+    }
+
+    /// Imports the server from its root directory as a new game instance.
+    pub async fn import(&self) -> anyhow::Result<Factorio> {
+        self.import_with(Importer::from_root(self.root()).await?).await
+    }
+
+    /// Imports the server as a given stored game.
+    pub async fn import_stored(&self, stored_game: &models::Game) -> anyhow::Result<Factorio> {
+        self.import_with(Importer::from_store(stored_game)).await
+    }
+
+    /// Imports the server with a given importer.
+    async fn import_with(&self, importer: Importer) -> anyhow::Result<Factorio> {
+        let portal = Arc::new(ModPortal::new(Arc::clone(&self.config))?);
+        importer
+            .import(Arc::clone(&self.config), portal, Arc::clone(&self.store))
+            .await
+    }
+}
+
+impl fmt::Debug for TestInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TestInstance")
+            .field("client", &self.client)
+            .field("rpc_task", &self.rpc_task)
+            .finish()
+    }
+}
+
+impl TestInstance {
+    /// Starts a new instance with RPC authorization disabled and waits for it to finish starting up.
+    pub async fn start() -> anyhow::Result<Self> {
+        Self::start_with(false).await
+    }
+
+    /// Starts a new instance that requires a token with every RPC request and waits for it to finish starting up. The
+    /// instance's client carries an admin token with the requests that don't carry a token of their own.
+    pub async fn start_with_auth() -> anyhow::Result<Self> {
+        Self::start_with(true).await
+    }
+
+    /// Starts a new instance, with RPC authorization enabled if `auth` is set, and waits for it to finish starting up.
+    async fn start_with(auth: bool) -> anyhow::Result<Self> {
+        let addr = free_local_address()?;
+        let config_file = format!("[network]\nlisten = [\"{}\"]\n[auth]\ndisabled = {}\n", addr, !auth);
+        let config = config::Builder::new()
+            .apply_config_file(&mut Cursor::new(config_file.into_bytes()))?
+            .build();
+        let store = store::Builder::<String>::from_location(StoreLocation::Memory)
+            .build()
+            .await?;
+
+        let modtorio = Modtorio::new(config, store).await?;
+        let admin_token = if auth {
+            let token = auth::create_token(&modtorio.store, String::from("test-support"), &[Scope::Admin]).await?;
+            Some(MetadataValue::from_str(&format!("Bearer {}", token))?)
+        } else {
+            None
+        };
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(());
+        shutdown_rx.recv().await;
+
+        let instance = modtorio.clone();
+        let rpc_task = task::spawn(async move { instance.run_rpc(shutdown_rx).await });
+
+        let endpoint = Endpoint::from_shared(format!("http://{}", addr))?;
+        let channel = loop {
+            match endpoint.connect().await {
+                Ok(channel) => break channel,
+                Err(_) => time::delay_for(POLL_INTERVAL).await,
+            }
+        };
+        let client = ModRpcClient::with_interceptor(channel, move |mut request: tonic::Request<()>| {
+            if let Some(token) = &admin_token {
+                if !request.metadata().contains_key(AUTHORIZATION_KEY) {
+                    request.metadata_mut().insert(AUTHORIZATION_KEY, token.clone());
+                }
+            }
+            Ok(request)
+        });
+
+        let instance = Self {
+            instance: modtorio,
+            client,
+            shutdown_tx,
+            rpc_task,
+        };
+        instance
+            .wait_until(|status| status.instance_status == rpc::instance_status::Status::Running as i32)
+            .await?;
+
+        Ok(instance)
+    }
+
+    /// Returns a client connected to the instance's RPC API.
+    pub fn client(&self) -> ModRpcClient<Channel> {
+        self.client.clone()
+    }
+
+    /// Creates an RPC token with a given name and given scopes in the instance's store and returns the token.
+    pub async fn create_token(&self, name: &str, scopes: &[Scope]) -> anyhow::Result<String> {
+        auth::create_token(&self.instance.store, name.to_owned(), scopes).await
+    }
+
+    /// Imports the server in a given root directory over the RPC API and returns its store ID.
+    pub async fn import<P>(&self, root: P) -> anyhow::Result<GameStoreId>
+    where
+        P: AsRef<Path>,
+    {
+        let path = root.as_ref().display().to_string();
+        let mut stream = self
+            .client()
+            .import_game(rpc::ImportRequest { path: path.clone() })
+            .await?
+            .into_inner();
+
+        while let Some(progress) = stream.message().await? {
+            if progress.prog_type == ProgressType::Done as i32 {
+                break;
+            }
+        }
+
+        let status = self.client().get_instance_status(rpc::Empty {}).await?.into_inner();
+        status
+            .servers
+            .iter()
+            .find(|server| server.path == path)
+            .map(|server| server.server_id)
+            .ok_or_else(|| anyhow::anyhow!("imported server {} not found", path))
+    }
+
+    /// Waits until a given server's execution status is a given status.
+    pub async fn wait_for_server_status(
+        &self,
+        server_id: GameStoreId,
+        wanted: rpc::ExecutionStatus,
+    ) -> anyhow::Result<()> {
+        self.wait_until(|status| {
+            status
+                .servers
+                .iter()
+                .any(|server| server.server_id == server_id && server.status == wanted as i32)
+        })
+        .await
+    }
+
+    /// Polls the instance's status until a given condition holds for it.
+    async fn wait_until<F>(&self, condition: F) -> anyhow::Result<()>
+    where
+        F: Fn(&rpc::InstanceStatus) -> bool,
+    {
+        loop {
+            let status = self.client().get_instance_status(rpc::Empty {}).await?.into_inner();
+            if condition(&status) {
+                return Ok(());
+            }
+
+            time::delay_for(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Shuts down the instance's RPC server and waits for it to stop.
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        self.shutdown_tx.broadcast(())?;
+        self.rpc_task.await?
+    }
+}
+
+/// Returns a local TCP address that's currently free to listen on.
+fn free_local_address() -> anyhow::Result<SocketAddr> {
+    let listener = TcpListener::bind(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0))?;
+    Ok(listener.local_addr()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn import_run_quit() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        client
+            .run_server(rpc::RunServerRequest {
+                server_id,
+                server_name: String::new(),
+                idempotency_key: String::new(),
+            })
+            .await
+            .expect("failed to run server");
+        time::timeout(
+            Duration::from_secs(10),
+            instance.wait_for_server_status(server_id, rpc::ExecutionStatus::Running),
+        )
+        .await
+        .expect("server didn't start in time")
+        .expect("failed to get server status");
+
+        client
+            .send_server_command(rpc::SendCommandRequest {
+                server_id,
+                server_name: String::new(),
+                command: rpc::send_command_request::Command::Raw as i32,
+                arguments: vec![String::from("quit")],
+                idempotency_key: String::new(),
+            })
+            .await
+            .expect("failed to send command");
+        time::timeout(
+            Duration::from_secs(10),
+            instance.wait_for_server_status(server_id, rpc::ExecutionStatus::Shutdown),
+        )
+        .await
+        .expect("server didn't shut down in time")
+        .expect("failed to get server status");
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn save_map() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        client
+            .run_server(rpc::RunServerRequest {
+                server_id,
+                server_name: String::new(),
+                idempotency_key: String::new(),
+            })
+            .await
+            .expect("failed to run server");
+        time::timeout(
+            Duration::from_secs(10),
+            instance.wait_for_server_status(server_id, rpc::ExecutionStatus::Running),
+        )
+        .await
+        .expect("server didn't start in time")
+        .expect("failed to get server status");
+
+        let saved = time::timeout(
+            Duration::from_secs(10),
+            client.save_map(rpc::SaveMapRequest {
+                server_id,
+                server_name: String::new(),
+                name: String::from("before-backup"),
+            }),
+        )
+        .await
+        .expect("server didn't save in time")
+        .expect("failed to save map")
+        .into_inner();
+        assert_eq!(saved.filename, "before-backup.zip");
+
+        let invalid = client
+            .save_map(rpc::SaveMapRequest {
+                server_id,
+                server_name: String::new(),
+                name: String::from("../outside"),
+            })
+            .await
+            .expect_err("saved with an invalid name");
+        assert_eq!(invalid.code(), tonic::Code::InvalidArgument);
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn tool_save_outside_saves_rejected() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        for save in &["../../outside.zip", "/tmp/outside.zip", ".."] {
+            let status = client
+                .run_server_tool(rpc::RunToolRequest {
+                    server_id,
+                    tool: rpc::run_tool_request::Tool::Map2scenario as i32,
+                    save: (*save).to_owned(),
+                    ticks: 0,
+                    server_name: String::new(),
+                })
+                .await
+                .expect_err("ran a tool with a save outside the saves directory");
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn graceful_restart_blocks_conflicts() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        client
+            .run_server(rpc::RunServerRequest {
+                server_id,
+                server_name: String::new(),
+                idempotency_key: String::new(),
+            })
+            .await
+            .expect("failed to run server");
+        time::timeout(
+            Duration::from_secs(10),
+            instance.wait_for_server_status(server_id, rpc::ExecutionStatus::Running),
+        )
+        .await
+        .expect("server didn't start in time")
+        .expect("failed to get server status");
+
+        let restart = rpc::StopServerRequest {
+            server_id,
+            server_name: String::new(),
+            grace_period: 2,
+            restart: true,
+            idempotency_key: String::new(),
+        };
+        client
+            .stop_server(restart.clone())
+            .await
+            .expect("failed to restart server");
+
+        let status = client
+            .get_server_status(rpc::ServerStatusRequest {
+                server_id,
+                server_name: String::new(),
+            })
+            .await
+            .expect("failed to get server status")
+            .into_inner();
+        let pending = status.pending_action.expect("no pending action");
+        assert_eq!(
+            pending.action,
+            rpc::server_status::pending_action::Action::Restart as i32
+        );
+
+        let status = client
+            .stop_server(restart)
+            .await
+            .expect_err("conflicting stop accepted");
+        assert_eq!(status.code(), tonic::Code::Aborted);
+        let status = client
+            .run_server(rpc::RunServerRequest {
+                server_id,
+                server_name: String::new(),
+                idempotency_key: String::new(),
+            })
+            .await
+            .expect_err("conflicting start accepted");
+        assert_eq!(status.code(), tonic::Code::Aborted);
+
+        time::timeout(Duration::from_secs(10), async {
+            loop {
+                let status = client
+                    .get_server_status(rpc::ServerStatusRequest {
+                        server_id,
+                        server_name: String::new(),
+                    })
+                    .await
+                    .expect("failed to get server status")
+                    .into_inner();
+                if status.pending_action.is_none() && status.status == rpc::ExecutionStatus::Running as i32 {
+                    break;
+                }
+
+                time::delay_for(POLL_INTERVAL).await;
+            }
+        })
+        .await
+        .expect("server didn't restart in time");
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn address_by_name() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        client
+            .set_server_name(rpc::SetServerNameRequest {
+                server_id,
+                name: String::from("vanilla"),
+                server_name: String::new(),
+            })
+            .await
+            .expect("failed to set server name");
+
+        let status = client
+            .get_instance_status(rpc::Empty {})
+            .await
+            .expect("failed to get instance status")
+            .into_inner();
+        assert_eq!(status.servers[0].name, "vanilla");
+
+        client
+            .get_server_status(rpc::ServerStatusRequest {
+                server_id: 0,
+                server_name: String::from("vanilla"),
+            })
+            .await
+            .expect("failed to get server status by name");
+
+        let unknown = client
+            .get_server_status(rpc::ServerStatusRequest {
+                server_id: 0,
+                server_name: String::from("modded"),
+            })
+            .await
+            .expect_err("got status of an unknown name");
+        assert_eq!(unknown.code(), tonic::Code::InvalidArgument);
+
+        let numeric = client
+            .set_server_name(rpc::SetServerNameRequest {
+                server_id,
+                name: String::from("123"),
+                server_name: String::new(),
+            })
+            .await
+            .expect_err("set a numeric name");
+        assert_eq!(numeric.code(), tonic::Code::InvalidArgument);
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn unmanaged_mods_rejected() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        let mut settings = client
+            .get_server_settings(rpc::GetServerSettingsRequest {
+                server_id,
+                server_name: String::new(),
+                conflicting_file: false,
+            })
+            .await
+            .expect("failed to get server settings")
+            .into_inner();
+        assert!(settings.mods_managed);
+
+        settings.mods_managed = false;
+        client
+            .set_server_settings(rpc::SetServerSettingsRequest {
+                server_id,
+                server_name: String::new(),
+                etag: settings.etag.clone(),
+                settings: Some(settings),
+                persist: false,
+            })
+            .await
+            .expect("failed to set server settings");
+
+        let status = client
+            .get_mod_policy(rpc::GetModPolicyRequest {
+                server_id,
+                server_name: String::new(),
+            })
+            .await
+            .expect_err("mod policy returned for unmanaged mods");
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn portal_credentials_need_scope() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start_with_auth()
+            .await
+            .expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let read = instance
+            .create_token("monitoring", &[Scope::Read])
+            .await
+            .expect("failed to create token");
+        let manage = instance
+            .create_token("mods", &[Scope::ManageMods])
+            .await
+            .expect("failed to create token");
+        let set = |token: &str, credentials| {
+            let mut request = tonic::Request::new(rpc::SetPortalCredentialsRequest {
+                server_id,
+                server_name: String::new(),
+                credentials,
+            });
+            request.metadata_mut().insert(
+                "authorization",
+                format!("Bearer {}", token)
+                    .parse()
+                    .expect("failed to parse metadata value"),
+            );
+            request
+        };
+
+        let mut client = instance.client();
+        let credentials = rpc::set_portal_credentials_request::Credentials {
+            username: String::from("player"),
+            token: String::from("secret"),
+        };
+        let denied = client
+            .set_portal_credentials(set(&read, Some(credentials)))
+            .await
+            .expect_err("set portal credentials without the manage-mods scope");
+        assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+        // clearing the credentials doesn't need a credential key to encrypt them with
+        client
+            .set_portal_credentials(set(&manage, None))
+            .await
+            .expect("failed to clear portal credentials");
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn preset_restart_needs_scope() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start_with_auth()
+            .await
+            .expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let manage = instance
+            .create_token("mods", &[Scope::ManageMods])
+            .await
+            .expect("failed to create token");
+        let apply = |restart| {
+            let mut request = tonic::Request::new(rpc::ApplyModPresetRequest {
+                server_id,
+                name: String::from("vanilla"),
+                restart,
+                grace_period: 0,
+                server_name: String::new(),
+            });
+            request.metadata_mut().insert(
+                "authorization",
+                format!("Bearer {}", manage)
+                    .parse()
+                    .expect("failed to parse metadata value"),
+            );
+            request
+        };
+
+        let mut client = instance.client();
+        let denied = client
+            .apply_mod_preset(apply(true))
+            .await
+            .expect_err("apply mod preset with a restart without the control-server scope");
+        assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+        // without a restart, the request is authorized and fails only because the preset doesn't exist
+        let missing = client
+            .apply_mod_preset(apply(false))
+            .await
+            .expect_err("apply nonexistent mod preset");
+        assert_eq!(missing.code(), tonic::Code::InvalidArgument);
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn shared_mods_not_deleted() {
+        let first = FixtureServer::new().expect("failed to create fixture server");
+        let second = FixtureServer::new().expect("failed to create fixture server");
+        let mods = second.root().join("mods");
+        std::fs::remove_dir_all(&mods).expect("failed to remove mods directory");
+        std::os::unix::fs::symlink(first.root().join("mods"), &mods).expect("failed to link mods directory");
+
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let first_id = instance.import(first.root()).await.expect("failed to import server");
+        let second_id = instance.import(second.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        for server_id in [first_id, second_id].iter().copied() {
+            let refused = client
+                .remove_server(rpc::RemoveServerRequest {
+                    server_id,
+                    delete_files: true,
+                    confirmation_token: String::new(),
+                    server_name: String::new(),
+                })
+                .await
+                .expect_err("remove server with a shared mods directory");
+            assert_eq!(refused.code(), tonic::Code::FailedPrecondition);
+        }
+        assert!(first.root().join("mods").exists());
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn modpack_paths_rejected() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        for path in &["../modpack.json", "/tmp/modpack.json", ".."] {
+            let mut stream = client
+                .export_modpack(rpc::ExportModpackRequest {
+                    server_id,
+                    path: path.to_string(),
+                    include_settings: false,
+                    server_name: String::new(),
+                })
+                .await
+                .expect("failed to export modpack")
+                .into_inner();
+            let status = stream
+                .message()
+                .await
+                .expect_err("export modpack outside the modpack directory");
+            assert_eq!(status.code(), tonic::Code::InvalidArgument, "{}", path);
+
+            let mut stream = client
+                .apply_modpack(rpc::ApplyModpackRequest {
+                    server_id,
+                    path: path.to_string(),
+                    override_policy: false,
+                    server_name: String::new(),
+                })
+                .await
+                .expect("failed to apply modpack")
+                .into_inner();
+            let status = stream
+                .message()
+                .await
+                .expect_err("apply modpack outside the modpack directory");
+            assert_eq!(status.code(), tonic::Code::InvalidArgument, "{}", path);
+        }
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn verify_mods_streamed() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut stream = instance
+            .client()
+            .verify_mods(rpc::VerifyModsRequest {
+                server_id,
+                analyze_content: false,
+                verify_checksums: true,
+                server_name: String::new(),
+            })
+            .await
+            .expect("failed to verify mods")
+            .into_inner();
+
+        let mut last = None;
+        while let Some(update) = stream.message().await.expect("failed to receive verification") {
+            last = update.update;
+        }
+        match last {
+            Some(rpc::mod_verification_progress::Update::Verification(verification)) => {
+                assert!(verification.checksums_verified);
+                assert!(!verification.content_analyzed);
+            }
+            other => panic!("stream didn't end with the verification: {:?}", other),
+        }
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn invalid_enums_rejected() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        let status = client
+            .set_mod_update_policy(rpc::SetModUpdatePolicyRequest {
+                server_id,
+                mod_name: String::from("base"),
+                policy: 42,
+                server_name: String::new(),
+            })
+            .await
+            .expect_err("set an invalid update policy");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let status = client
+            .resolve_settings_conflict(rpc::ResolveSettingsConflictRequest {
+                server_id,
+                prefer: 42,
+                server_name: String::new(),
+            })
+            .await
+            .expect_err("resolved a conflict with an invalid source");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn stale_settings_rejected() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        let settings = client
+            .get_server_settings(rpc::GetServerSettingsRequest {
+                server_id,
+                server_name: String::new(),
+                conflicting_file: false,
+            })
+            .await
+            .expect("failed to get server settings")
+            .into_inner();
+        let set = |etag: &str| rpc::SetServerSettingsRequest {
+            server_id,
+            server_name: String::new(),
+            settings: Some(settings.clone()),
+            persist: false,
+            etag: etag.to_owned(),
+        };
+
+        let missing = client
+            .set_server_settings(set(""))
+            .await
+            .expect_err("set settings without an etag");
+        assert_eq!(missing.code(), tonic::Code::InvalidArgument);
+
+        client
+            .set_server_settings(set(&settings.etag))
+            .await
+            .expect("failed to set server settings");
+        // the first edit changed the settings, so another edit based on the same read is stale
+        let stale = client
+            .set_server_settings(set(&settings.etag))
+            .await
+            .expect_err("set settings with a stale etag");
+        assert_eq!(stale.code(), tonic::Code::Aborted);
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+
+    #[tokio::test]
+    async fn moved_mods_rebuilt() {
+        let server = FixtureServer::new().expect("failed to create fixture server");
+        let moved = server.root().join("moved-mods");
+        fs::create_dir(&moved).expect("failed to create mods directory");
+        let instance = TestInstance::start().await.expect("failed to start test instance");
+        let server_id = instance.import(server.root()).await.expect("failed to import server");
+
+        let mut client = instance.client();
+        let mut settings = client
+            .get_server_settings(rpc::GetServerSettingsRequest {
+                server_id,
+                server_name: String::new(),
+                conflicting_file: false,
+            })
+            .await
+            .expect("failed to get server settings")
+            .into_inner();
+        settings.mods_path = moved.display().to_string();
+        client
+            .set_server_settings(rpc::SetServerSettingsRequest {
+                server_id,
+                server_name: String::new(),
+                etag: settings.etag.clone(),
+                settings: Some(settings),
+                persist: false,
+            })
+            .await
+            .expect("failed to set server settings");
+
+        let files = client
+            .get_raw_server_files(rpc::GetRawServerFilesRequest {
+                server_id,
+                kinds: vec![rpc::raw_server_file::Kind::ModList.into()],
+                server_name: String::new(),
+            })
+            .await
+            .expect("failed to get raw server files")
+            .into_inner();
+        assert_eq!(files.files[0].path, moved.join("mod-list.json").display().to_string());
+
+        instance.shutdown().await.expect("failed to shut down test instance");
+    }
+}
@@ -0,0 +1,50 @@
+#!/bin/sh
+# A scripted stand-in for a headless Factorio server, used by Modtorio's test harness. It answers --version like the real
+# executable, echoes its arguments when creating a map, and when started as a server prints the log lines of a server
+# starting up, saving its map after /save and shutting down after /quit.
+
+state_change() {
+    echo "$1 Info ServerMultiplayerManager.cpp:706: updateTick(0) changing state from($2) to($3)"
+}
+
+case "$1" in
+    --version)
+        printf 'Version: 1.0.0 (build 54889, linux64, headless)\n'
+        printf 'Binary version: 64\n'
+        printf 'Map input version: 0.18.0-0\n'
+        printf 'Map output version: 1.0.0-0\n'
+        ;;
+    --create)
+        # echo the arguments so the map generation settings a new map is created with can be checked
+        echo "$@"
+        ;;
+    --start-server | --start-server-load-latest)
+        echo "   0.000 2020-08-14 12:00:00; Factorio 1.0.0 (build 54889, linux64, headless)"
+        echo "   0.100 Factorio initialised"
+        state_change "   0.200" Ready PreparedToHostGame
+        state_change "   0.300" PreparedToHostGame CreatingGame
+        state_change "   0.400" CreatingGame InGame
+
+        while read -r line; do
+            case "$line" in
+                /save*)
+                    name="${line#/save}"
+                    name="${name# }"
+                    state_change "   0.600" InGame InGameSavingMap
+                    echo "   0.600 Info AppManagerStates.cpp:1802: Saving game as saves/${name:-fixture}.zip"
+                    echo "   0.700 Info AppManagerStates.cpp:1812: Saving finished"
+                    state_change "   0.700" InGameSavingMap InGame
+                    ;;
+                /quit)
+                    state_change "   1.000" InGame DisconnectingScheduled
+                    state_change "   1.100" DisconnectingScheduled Disconnecting
+                    state_change "   1.200" Disconnecting Closed
+                    exit 0
+                    ;;
+                *)
+                    echo "   0.500 Info Command: $line"
+                    ;;
+            esac
+        done
+        ;;
+esac
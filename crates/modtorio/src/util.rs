@@ -1,20 +1,41 @@
 //! Provides several utilities and helper functions.
 
 pub mod async_status;
+mod backoff;
+mod ban_action;
+pub mod channel;
 pub mod checksum;
+pub mod correlation;
 pub mod env;
 pub mod ext;
 pub mod file;
+pub mod file_transaction;
 mod human_version;
 mod limit;
+mod log_format;
 mod log_level;
+pub mod password;
+mod rate_limiter;
+pub mod socket;
+mod stuck_save_action;
+mod time_zone;
 
+pub use backoff::Backoff;
+pub use ban_action::BanAction;
 use ext::PathExt;
 pub use human_version::{Comparator, HumanVersion, HumanVersionReq};
 pub use limit::Limit;
+pub use log_format::LogFormat;
 pub use log_level::LogLevel;
+pub use rate_limiter::RateLimiter;
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+pub use stuck_save_action::StuckSaveAction;
+pub use time_zone::TimeZone;
 
 /// Retrieves the last segment of a given path as a `PathBuf`. Panics if there is no last component
 /// in the path.
@@ -27,6 +48,12 @@ where
     last.to_path_buf()
 }
 
+/// Builds a `PathBuf` from a given path's raw bytes, such as ones returned by
+/// [`PathExt::to_bytes`](ext::PathExt::to_bytes). The bytes don't have to be valid Unicode.
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
 /// Returns all entries matched by a given glob pattern (for example `*.txt`).
 ///
 /// Returns an error if:
@@ -54,3 +81,19 @@ pub struct Range {
     /// The range's upper bound.
     pub max: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_bytes_roundtrip() {
+        let path = path_from_bytes(b"/tmp/mods/invalid-\xff-unicode_1.0.0.zip");
+        assert!(path.get_str().is_err());
+        assert_eq!(
+            path.get_os_file_name().unwrap().as_bytes(),
+            b"invalid-\xff-unicode_1.0.0.zip"
+        );
+        assert_eq!(path_from_bytes(&path.to_bytes()), path);
+    }
+}
@@ -3,12 +3,83 @@
 use async_trait::async_trait;
 use log::*;
 use rpc::{progress::ProgressType, Progress};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tokio::sync::mpsc;
 
 /// The Result type used to reprsent the status of an async task.
 pub type AsyncProgressResult = Result<Progress, tonic::Status>;
+
 /// The channel used to send async task results into.
-pub type AsyncProgressChannel = mpsc::Sender<AsyncProgressResult>;
+///
+/// Identical successive progress statuses are sent only once, and definite progress statuses sent faster than the
+/// channel's interval are dropped, since each one supersedes the previous. Errors, done statuses and the final step
+/// of a definite progress are always sent.
+#[derive(Debug, Clone)]
+pub struct AsyncProgressChannel {
+    /// The underlying channel.
+    tx: mpsc::Sender<AsyncProgressResult>,
+    /// The throttling state shared between the channel's clones.
+    throttle: Arc<Mutex<Throttle>>,
+}
+
+/// The throttling state of an `AsyncProgressChannel`.
+#[derive(Debug)]
+struct Throttle {
+    /// The minimum interval between successive definite progress statuses.
+    interval: Duration,
+    /// The last sent progress status.
+    last: Option<Progress>,
+    /// When the last definite progress status was sent.
+    last_definite_at: Option<Instant>,
+}
+
+impl AsyncProgressChannel {
+    /// Returns a new `AsyncProgressChannel` sending into a given channel, which sends definite progress statuses at
+    /// most once per a given interval. A zero interval disables the rate limiting.
+    pub fn new(tx: mpsc::Sender<AsyncProgressResult>, interval: Duration) -> Self {
+        Self {
+            tx,
+            throttle: Arc::new(Mutex::new(Throttle {
+                interval,
+                last: None,
+                last_definite_at: None,
+            })),
+        }
+    }
+
+    /// Returns whether a given status should be sent, and records it as sent if so.
+    fn should_send(&self, status: &AsyncProgressResult) -> bool {
+        let progress = match status {
+            Ok(progress) => progress,
+            Err(_) => return true,
+        };
+
+        let mut throttle = self.throttle.lock().expect("progress throttle mutex poisoned");
+        if throttle.last.as_ref() == Some(progress) {
+            return false;
+        }
+
+        if progress.prog_type == ProgressType::Definite as i32 {
+            let now = Instant::now();
+            let is_final = progress.value >= progress.max;
+            let too_soon = throttle
+                .last_definite_at
+                .map_or(false, |at| now.duration_since(at) < throttle.interval);
+
+            if too_soon && !is_final {
+                return false;
+            }
+
+            throttle.last_definite_at = Some(now);
+        }
+
+        throttle.last = Some(progress.clone());
+        true
+    }
+}
 
 /// Defines functions used with `AsyncProgressChannel`.
 #[async_trait]
@@ -31,11 +102,16 @@ impl AsyncProgressChannelExt for Option<AsyncProgressChannel> {
     }
 }
 
-/// Sends a given status update to an optional progress channel.
+/// Sends a given status update to an optional progress channel, unless the channel throttles it.
 pub async fn send_status(channel: Option<AsyncProgressChannel>, status: AsyncProgressResult) -> anyhow::Result<()> {
     if let Some(mut channel) = channel {
+        if !channel.should_send(&status) {
+            trace!("Throttled status update: {:?}", status);
+            return Ok(());
+        }
+
         trace!("Sending status update: {:?}", status);
-        if let Err(e) = channel.try_send(status) {
+        if let Err(e) = channel.tx.try_send(status) {
             error!("Caught error while sendig RPC status update: {}", e);
             return Err(anyhow::anyhow!(""));
         }
@@ -72,3 +148,47 @@ pub fn done() -> AsyncProgressResult {
         max: 1,
     })
 }
+
+/// Returns a new done status with a given message describing the result.
+pub fn done_with(message: &str) -> AsyncProgressResult {
+    Ok(Progress {
+        message: String::from(message),
+        prog_type: ProgressType::Done.into(),
+        value: 1,
+        max: 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn throttle() {
+        let (tx, mut rx) = mpsc::channel(64);
+        let channel = AsyncProgressChannel::new(tx, Duration::from_secs(60));
+
+        channel.send_status(indefinite("a")).await.unwrap();
+        channel.send_status(indefinite("a")).await.unwrap();
+        channel.send_status(definite("b", 0, 3)).await.unwrap();
+        channel.send_status(definite("b", 1, 3)).await.unwrap();
+        channel.send_status(definite("b", 3, 3)).await.unwrap();
+        channel.send_status(done()).await.unwrap();
+        drop(channel);
+
+        let mut received = Vec::new();
+        while let Some(status) = rx.recv().await {
+            received.push(status.unwrap());
+        }
+
+        assert_eq!(
+            received,
+            vec![
+                indefinite("a").unwrap(),
+                definite("b", 0, 3).unwrap(),
+                definite("b", 3, 3).unwrap(),
+                done().unwrap(),
+            ]
+        );
+    }
+}
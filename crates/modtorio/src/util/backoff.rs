@@ -0,0 +1,73 @@
+//! Provides the [`Backoff`](Backoff) struct.
+
+use rand::Rng;
+use std::time::Duration;
+
+/// Exponentially growing delays between retries of a failing operation. Each delay is double the previous one, up to
+/// a maximum, and is jittered randomly between half and all of its nominal value so that several operations failing
+/// at once don't all retry at the same instant.
+#[derive(Debug)]
+pub struct Backoff {
+    /// The nominal delay before the first retry.
+    initial: Duration,
+    /// The largest nominal delay.
+    max: Duration,
+    /// How many delays have been returned so far.
+    retries: u32,
+}
+
+impl Backoff {
+    /// Returns a new `Backoff` starting from a given initial delay and growing up to a given maximum delay.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            retries: 0,
+        }
+    }
+
+    /// Returns the delay to wait before the next retry.
+    pub fn next_delay(&mut self) -> Duration {
+        let nominal = self.nominal_delay();
+        self.retries = self.retries.saturating_add(1);
+
+        let half = nominal / 2;
+        half + rand::thread_rng().gen_range(Duration::from_secs(0), nominal - half + Duration::from_nanos(1))
+    }
+
+    /// Returns the current retry's delay without jitter.
+    fn nominal_delay(&self) -> Duration {
+        2u32.checked_pow(self.retries)
+            .and_then(|factor| self.initial.checked_mul(factor))
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_to_max() {
+        let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(10));
+        let nominal = [2, 4, 8, 10, 10];
+
+        for nominal in nominal.iter().map(|secs| Duration::from_secs(*secs)) {
+            let delay = backoff.next_delay();
+            assert!(
+                delay >= nominal / 2 && delay <= nominal,
+                "{:?} not within {:?}",
+                delay,
+                nominal
+            );
+        }
+    }
+
+    #[test]
+    fn many_retries() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(60));
+        for _ in 0..100 {
+            assert!(backoff.next_delay() <= Duration::from_secs(60));
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Provides the [`BanAction`](BanAction) enum.
+
+use serde::{Deserialize, Serialize};
+
+/// Represents the action taken when a peer has been refused a connection too many times.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum BanAction {
+    /// Only log the offender.
+    Log,
+    /// POST the offender as JSON to the configured webhook URL.
+    Webhook,
+    /// Run the configured command with the offender's address and username as arguments.
+    Command,
+    /// Ban the offender's username in-game.
+    Ban,
+}
+
+impl Default for BanAction {
+    fn default() -> Self {
+        BanAction::Log
+    }
+}
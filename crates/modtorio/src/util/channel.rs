@@ -0,0 +1,234 @@
+//! Provides a bounded multi-producer, single-consumer channel with a configurable
+//! [`OverflowStrategy`](OverflowStrategy), used where a fast producer may outpace its consumer.
+
+use crate::error::ChannelError;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::sync::Notify;
+
+/// Represents what a channel does when a value is sent into it while it's full.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowStrategy {
+    /// Wait until the receiver has made room for the value.
+    Backpressure,
+    /// Discard the oldest value in the channel to make room for the value and count the discarded value.
+    DropOldest,
+}
+
+/// The sending half of a bounded channel. Cloning it creates another sender into the same channel.
+#[derive(Debug)]
+pub struct Sender<T> {
+    /// The state shared with the receiver.
+    shared: Arc<Shared<T>>,
+}
+
+/// The receiving half of a bounded channel.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    /// The state shared with the senders.
+    shared: Arc<Shared<T>>,
+}
+
+/// The state shared between a channel's senders and receiver.
+#[derive(Debug)]
+struct Shared<T> {
+    /// The most values the channel holds at once.
+    capacity: usize,
+    /// What's done when the channel is full.
+    strategy: OverflowStrategy,
+    /// The values in the channel and whether its ends are still alive.
+    state: Mutex<State<T>>,
+    /// How many values have been discarded and not yet taken with
+    /// [`Receiver::take_dropped`](Receiver::take_dropped).
+    dropped: AtomicU64,
+    /// Notified when a value is sent or the last sender is dropped.
+    value_sent: Notify,
+    /// Notified when a value is received or the receiver is dropped.
+    value_received: Notify,
+}
+
+/// The mutable state of a channel.
+#[derive(Debug)]
+struct State<T> {
+    /// The values in the channel, oldest first.
+    queue: VecDeque<T>,
+    /// How many senders are alive.
+    senders: usize,
+    /// Whether the receiver is alive.
+    receiver_alive: bool,
+}
+
+/// Returns a new bounded channel that holds at most `capacity` values (at least 1) and overflows with a given
+/// strategy.
+pub fn channel<T>(capacity: usize, strategy: OverflowStrategy) -> (Sender<T>, Receiver<T>) {
+    let capacity = capacity.max(1);
+    let shared = Arc::new(Shared {
+        capacity,
+        strategy,
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(capacity),
+            senders: 1,
+            receiver_alive: true,
+        }),
+        dropped: AtomicU64::new(0),
+        value_sent: Notify::new(),
+        value_received: Notify::new(),
+    });
+
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Receiver { shared },
+    )
+}
+
+impl Default for OverflowStrategy {
+    fn default() -> Self {
+        OverflowStrategy::Backpressure
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends a value into the channel. If the channel is full, either waits for room or discards the oldest value,
+    /// depending on the channel's overflow strategy. Returns an error if the receiver has been dropped.
+    pub async fn send(&self, value: T) -> Result<(), ChannelError> {
+        let mut value = Some(value);
+        loop {
+            {
+                let mut state = self.shared.state.lock().expect("channel state mutex poisoned");
+                if !state.receiver_alive {
+                    drop(state);
+                    // pass the wakeup on to any other sender waiting for room
+                    self.shared.value_received.notify();
+                    return Err(ChannelError::Closed);
+                }
+
+                let full = state.queue.len() >= self.shared.capacity;
+                if full && self.shared.strategy == OverflowStrategy::DropOldest {
+                    state.queue.pop_front();
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+
+                if !full || self.shared.strategy == OverflowStrategy::DropOldest {
+                    state.queue.push_back(value.take().expect("value already sent"));
+                    drop(state);
+                    self.shared.value_sent.notify();
+                    return Ok(());
+                }
+            }
+
+            self.shared.value_received.notified().await;
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().expect("channel state mutex poisoned").senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().expect("channel state mutex poisoned");
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            self.shared.value_sent.notify();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Receives the oldest value in the channel, waiting for one if the channel is empty. Returns `None` once the
+    /// channel is empty and every sender has been dropped.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            {
+                let mut state = self.shared.state.lock().expect("channel state mutex poisoned");
+                if let Some(value) = state.queue.pop_front() {
+                    drop(state);
+                    self.shared.value_received.notify();
+                    return Some(value);
+                }
+
+                if state.senders == 0 {
+                    return None;
+                }
+            }
+
+            self.shared.value_sent.notified().await;
+        }
+    }
+
+    /// Returns how many values have been discarded since the last call and resets the count.
+    pub fn take_dropped(&self) -> u64 {
+        self.shared.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared
+            .state
+            .lock()
+            .expect("channel state mutex poisoned")
+            .receiver_alive = false;
+        self.shared.value_received.notify();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn drop_oldest() {
+        let (tx, mut rx) = channel(2, OverflowStrategy::DropOldest);
+        for i in 0..5 {
+            tx.send(i).await.expect("failed to send");
+        }
+        drop(tx);
+
+        assert_eq!(rx.take_dropped(), 3);
+        assert_eq!(rx.take_dropped(), 0);
+        assert_eq!(rx.recv().await, Some(3));
+        assert_eq!(rx.recv().await, Some(4));
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn backpressure() {
+        let (tx, mut rx) = channel(1, OverflowStrategy::Backpressure);
+        tx.send(0).await.expect("failed to send");
+
+        let sender = tokio::spawn(async move {
+            tx.send(1).await.expect("failed to send");
+        });
+
+        assert_eq!(rx.recv().await, Some(0));
+        sender.await.expect("sender task failed");
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, None);
+        assert_eq!(rx.take_dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn receiver_dropped() {
+        let (tx, rx) = channel(1, OverflowStrategy::Backpressure);
+        drop(rx);
+
+        assert!(tx.send(0).await.is_err());
+    }
+}
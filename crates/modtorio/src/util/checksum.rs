@@ -1,22 +1,136 @@
 //! Provides several functions to calculate checksums of various things with different algorithms.
+//!
+//! Files are hashed incrementally in chunks, so long calculations can report their progress and be cancelled between
+//! chunks with a [`Cancellation`](Cancellation).
 
+use crate::{
+    error::ChecksumError,
+    shutdown,
+    util::async_status::{self, AsyncProgressChannel},
+};
 use blake2::Blake2b;
 use digest::Digest;
+use log::*;
 use sha1::Sha1;
-use std::path::Path;
+use sha2::Sha256;
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{ErrorKind, Read},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::{sync::watch, task};
 
-/// Calculates the checksum of a file using the BLAKE2b algorithm.
-pub fn blake2b_file<P>(path: P) -> anyhow::Result<String>
+/// How many bytes of a file are hashed at a time.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The algorithms checksums can be calculated with.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Algorithm {
+    /// The BLAKE2b algorithm.
+    Blake2b,
+    /// The SHA1 algorithm.
+    Sha1,
+    /// The SHA-256 algorithm.
+    Sha256,
+}
+
+/// Cancels the checksum calculations it's given to. Every clone cancels the same calculations.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    /// Whether the calculations have been cancelled.
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Cancels a given `Cancellation` when dropped.
+struct CancelOnDrop(Cancellation);
+
+impl Cancellation {
+    /// Cancels the calculations. They stop once they finish hashing their current chunk.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the calculations have been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Calculates the checksum of a file with a given algorithm. Calls a given function with the amount of bytes hashed
+/// so far and the file's size after every chunk. Returns [`ChecksumError::Cancelled`][Cancelled] if the given
+/// cancellation is cancelled before the whole file is hashed.
+///
+/// [Cancelled]: crate::error::ChecksumError::Cancelled
+pub fn file<P, F>(algorithm: Algorithm, path: P, cancellation: &Cancellation, progress: F) -> anyhow::Result<String>
 where
     P: AsRef<Path>,
+    F: FnMut(u64, u64),
 {
-    let mut hasher = Blake2b::new();
-    let mut zip = std::fs::File::open(path)?;
+    match algorithm {
+        Algorithm::Blake2b => hash_file::<Blake2b, _, _>(path, cancellation, progress),
+        Algorithm::Sha1 => hash_file::<Sha1, _, _>(path, cancellation, progress),
+        Algorithm::Sha256 => hash_file::<Sha256, _, _>(path, cancellation, progress),
+    }
+}
 
-    std::io::copy(&mut zip, &mut hasher)?;
+/// Calculates the checksum of a file with a given algorithm in a blocking thread. The progress is reported as definite
+/// status updates, counted in kibibytes, with a given message to a given progress channel. The calculation is
+/// cancelled if the returned future is dropped before it finishes, such as when the RPC request it's for is
+/// cancelled, or if the long-running task it's calculated in is cancelled because the program is shutting down.
+pub async fn file_with_progress<P>(
+    algorithm: Algorithm,
+    path: P,
+    message: &str,
+    prog_tx: Option<AsyncProgressChannel>,
+) -> anyhow::Result<String>
+where
+    P: AsRef<Path>,
+{
+    let cancellation = Cancellation::default();
+    let _cancel_on_drop = CancelOnDrop(cancellation.clone());
+    let (progress_tx, mut progress_rx) = watch::channel((0, 0));
+    let path = path.as_ref().to_path_buf();
+    let hashing_cancellation = cancellation.clone();
+    let mut hashing = task::spawn_blocking(move || {
+        file(algorithm, path, &hashing_cancellation, |processed, total| {
+            progress_tx.broadcast((processed, total)).ok();
+        })
+    });
 
-    let result = hasher.finalize();
-    Ok(hex::encode(&result[..]))
+    let mut shutting_down = false;
+    loop {
+        tokio::select! {
+            result = &mut hashing => return result?,
+            _ = shutdown::cancelled(), if !shutting_down => {
+                debug!("Cancelling checksum calculation since the program is shutting down");
+                shutting_down = true;
+                cancellation.cancel();
+            }
+            progress = progress_rx.recv() => match progress {
+                Some((0, _)) => {}
+                Some((processed, total)) => {
+                    async_status::send_status(
+                        prog_tx.clone(),
+                        async_status::definite(message, kibibytes(processed), kibibytes(total)),
+                    )
+                    .await?
+                }
+                // the progress sender is dropped only once the hashing is done
+                None => return hashing.await?,
+            }
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -28,16 +142,81 @@ pub fn blake2b_string(value: &str) -> String {
     hex::encode(&result[..])
 }
 
-/// Calculates the checksum of a file using the SHA1 algorithm.
-pub fn sha1_file<P>(path: P) -> anyhow::Result<String>
+/// Hashes a file in chunks with a given digest like [`file`](file) does.
+fn hash_file<D, P, F>(path: P, cancellation: &Cancellation, mut progress: F) -> anyhow::Result<String>
 where
+    D: Digest,
     P: AsRef<Path>,
+    F: FnMut(u64, u64),
 {
-    let mut hasher = Sha1::new();
-    let mut zip = std::fs::File::open(path)?;
+    let mut file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let mut hasher = D::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut processed = 0;
 
-    std::io::copy(&mut zip, &mut hasher)?;
+    loop {
+        if cancellation.is_cancelled() {
+            return Err(ChecksumError::Cancelled.into());
+        }
+
+        let read = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        hasher.update(&buf[..read]);
+        processed += read as u64;
+        progress(processed, total);
+    }
 
     let result = hasher.finalize();
     Ok(hex::encode(&result[..]))
 }
+
+/// Returns a given amount of bytes in whole kibibytes, saturated to fit a progress status value.
+fn kibibytes(bytes: u64) -> u32 {
+    u32::try_from(bytes / 1024).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn incremental() {
+        let mut zip = tempfile::NamedTempFile::new().unwrap();
+        zip.write_all(&vec![7; CHUNK_SIZE * 2 + 1]).unwrap();
+
+        let mut reports = Vec::new();
+        let checksum = file(
+            Algorithm::Sha1,
+            zip.path(),
+            &Cancellation::default(),
+            |processed, total| reports.push((processed, total)),
+        )
+        .unwrap();
+        assert_eq!(checksum, "295f30a1efc9c8a91f2e0b0fd5143d2f7a3328b2");
+        assert_eq!(reports.len(), 3);
+        assert_eq!(
+            reports.last(),
+            Some(&(CHUNK_SIZE as u64 * 2 + 1, CHUNK_SIZE as u64 * 2 + 1))
+        );
+
+        let cancellation = Cancellation::default();
+        let result = file(Algorithm::Sha1, zip.path(), &cancellation, |_, _| cancellation.cancel());
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<ChecksumError>(),
+            Some(ChecksumError::Cancelled)
+        ));
+
+        let sha256 = file(Algorithm::Sha256, zip.path(), &Cancellation::default(), |_, _| {}).unwrap();
+        assert_eq!(
+            sha256,
+            "a31e7920b0f45b33bf28024ff1ee523c3b6f4a26f8e0a790bbd81441ae59da8c"
+        );
+    }
+}
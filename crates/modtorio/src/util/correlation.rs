@@ -0,0 +1,243 @@
+//! Provides correlation IDs that tie the log lines produced while handling an RPC request to the request. Every
+//! request is assigned an ID, or keeps the one its client sent, which is returned in the response metadata and
+//! included in every log line logged while the request is handled, including in the tasks the request spawns.
+//!
+//! Along with the ID, the current task's [`Context`](Context) carries the request's RPC method and the store ID of the
+//! server the request or task works on, once known, so the log lines can be filtered per server.
+
+use crate::factorio::GameStoreId;
+use hyper::{header::HeaderValue, Request, Response};
+use rand::Rng;
+use std::{
+    cell::RefCell,
+    future::Future,
+    task::{Context as TaskContext, Poll},
+};
+use tokio::task::{self, JoinHandle};
+use tonic::{
+    codegen::{BoxFuture, Service},
+    transport::NamedService,
+};
+
+/// The metadata key the correlation ID is read from in requests and returned in responses.
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+/// The longest client-sent correlation ID that is kept. Longer IDs are replaced with a generated one.
+const MAX_CLIENT_ID_LENGTH: usize = 64;
+
+/// The context of the RPC request or the server the current task is working on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Context {
+    /// The correlation ID of the RPC request being handled, if any.
+    pub id: Option<String>,
+    /// The path of the RPC method being handled, such as `/mod_rpc.ModRpc/InstallMod`, if any.
+    pub method: Option<String>,
+    /// The store ID of the server being worked on, if known.
+    pub server_id: Option<GameStoreId>,
+}
+
+/// Wraps an RPC service so every request it handles is assigned a correlation ID with [`for_request`](for_request) and
+/// handled in its [`scope`](scope).
+#[derive(Debug, Clone)]
+pub struct Correlated<S> {
+    /// The wrapped service.
+    inner: S,
+}
+
+tokio::task_local! {
+    /// The context of the RPC request or the server the current task is working on.
+    static CONTEXT: RefCell<Context>;
+}
+
+/// Returns the correlation ID of the RPC request the current task is handling, or `None` if the task isn't handling
+/// one.
+pub fn current() -> Option<String> {
+    CONTEXT.try_with(|context| context.borrow().id.clone()).ok().flatten()
+}
+
+/// Returns the context of the RPC request or the server the current task is working on, or `None` if the task has no
+/// context.
+pub fn context() -> Option<Context> {
+    CONTEXT.try_with(|context| context.borrow().clone()).ok()
+}
+
+/// Records a given server as the one the current task works on, if the task has a context.
+pub fn set_server(server_id: GameStoreId) {
+    CONTEXT
+        .try_with(|context| context.borrow_mut().server_id = Some(server_id))
+        .ok();
+}
+
+/// Returns the correlation ID for a given request: the one sent by the client if it's valid, otherwise a new random
+/// one.
+pub fn for_request<B>(request: &Request<B>) -> String {
+    request
+        .headers()
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map_or_else(generate, String::from)
+}
+
+/// Runs a given response future of a given RPC method with a given correlation ID as the current one, and adds the ID
+/// to the response's metadata.
+pub async fn scope<F, B, E>(id: String, method: String, response: F) -> Result<Response<B>, E>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    let header = HeaderValue::from_str(&id).ok();
+    let context = Context {
+        id: Some(id),
+        method: Some(method),
+        server_id: None,
+    };
+    let mut response = CONTEXT.scope(RefCell::new(context), response).await?;
+
+    if let Some(header) = header {
+        response.headers_mut().insert(CORRELATION_ID_HEADER, header);
+    }
+    Ok(response)
+}
+
+/// Runs a given future working on a given server, with the current context carried over.
+pub async fn for_server<F>(server_id: GameStoreId, future: F) -> F::Output
+where
+    F: Future,
+{
+    let mut context = context().unwrap_or_default();
+    context.server_id = Some(server_id);
+    CONTEXT.scope(RefCell::new(context), future).await
+}
+
+/// Spawns a given future as a new task like `task::spawn` does, carrying the current context over to the task.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match context() {
+        Some(context) => task::spawn(CONTEXT.scope(RefCell::new(context), future)),
+        None => task::spawn(future),
+    }
+}
+
+impl<S> Correlated<S> {
+    /// Returns a new `Correlated` wrapping a given service.
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, R, B> Service<Request<R>> for Correlated<S>
+where
+    S: Service<Request<R>, Response = Response<B>>,
+    S::Future: Send + 'static,
+    S::Error: 'static,
+    B: 'static,
+{
+    type Response = Response<B>;
+    type Error = S::Error;
+    type Future = BoxFuture<Response<B>, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<R>) -> Self::Future {
+        let id = for_request(&request);
+        let method = request.uri().path().to_owned();
+        Box::pin(scope(id, method, self.inner.call(request)))
+    }
+}
+
+impl<S> NamedService for Correlated<S>
+where
+    S: NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+/// Returns a new random correlation ID.
+fn generate() -> String {
+    format!("{:016x}", rand::thread_rng().gen::<u64>())
+}
+
+/// Returns whether a given client-sent correlation ID is short and consists only of ASCII alphanumerics and dashes.
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_CLIENT_ID_LENGTH && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    /// A service that responds to every request with the correlation ID it's handled with in the response body.
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<Request<()>> for Echo {
+        type Response = Response<Option<String>>;
+        type Error = ();
+        type Future = BoxFuture<Self::Response, ()>;
+
+        fn poll_ready(&mut self, _: &mut TaskContext<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Request<()>) -> Self::Future {
+            // like the generated RPC services, the request is handled in the returned future
+            Box::pin(async { Ok(Response::new(current())) })
+        }
+    }
+
+    #[test]
+    fn client_id() {
+        let request = |id: &str| Request::builder().header(CORRELATION_ID_HEADER, id).body(()).unwrap();
+
+        assert_eq!(for_request(&request("client-123")), "client-123");
+        assert_ne!(for_request(&request("no spaces")), "no spaces");
+        assert_ne!(for_request(&request(&"a".repeat(65))), "a".repeat(65));
+        assert_eq!(for_request(&Request::new(())).len(), 16);
+    }
+
+    #[tokio::test]
+    async fn scoped() {
+        assert_eq!(current(), None);
+
+        let response = scope(String::from("id"), String::from("/mod_rpc.ModRpc/RunServer"), async {
+            assert_eq!(current().as_deref(), Some("id"));
+            set_server(3);
+            let spawned = spawn(async { context() }).await.unwrap().unwrap();
+            assert_eq!(spawned.id.as_deref(), Some("id"));
+            assert_eq!(spawned.method.as_deref(), Some("/mod_rpc.ModRpc/RunServer"));
+            assert_eq!(spawned.server_id, Some(3));
+
+            Ok::<_, ()>(Response::new(()))
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(response.headers()[CORRELATION_ID_HEADER], "id");
+        assert_eq!(current(), None);
+
+        let context = for_server(5, async { context() }).await.unwrap();
+        assert_eq!(context.id, None);
+        assert_eq!(context.server_id, Some(5));
+    }
+
+    #[tokio::test]
+    async fn correlated_service() {
+        let mut service = Correlated::new(Echo);
+
+        let request = Request::builder()
+            .uri("/mod_rpc.ModRpc/GetServerStatus")
+            .header(CORRELATION_ID_HEADER, "client-123")
+            .body(())
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.headers()[CORRELATION_ID_HEADER], "client-123");
+        assert_eq!(response.body().as_deref(), Some("client-123"));
+
+        let response = service.call(Request::new(())).await.unwrap();
+        let generated = response.headers()[CORRELATION_ID_HEADER].to_str().unwrap();
+        assert_eq!(response.body().as_deref(), Some(generated));
+    }
+}
@@ -2,7 +2,7 @@
 //! and path-like objects.
 
 use crate::error::PathError;
-use std::path::Path;
+use std::{ffi::OsString, os::unix::ffi::OsStrExt, path::Path};
 
 /// Collection of common functions used with paths and path-like objects.
 pub trait PathExt {
@@ -19,6 +19,12 @@ pub trait PathExt {
     /// Borrows the path as an `&str`. Returns `PathError::InvalidUnicode` error if the path
     /// contains invalid Unicode
     fn get_str(&self) -> anyhow::Result<&str>;
+    /// Extracts the file name from the path and returns it as an `OsString`, without requiring it to
+    /// be valid Unicode. Returns `PathError::NoFilename` if the path doesn't have a file name.
+    fn get_os_file_name(&self) -> anyhow::Result<OsString>;
+    /// Returns the path's raw bytes. Unlike [`get_str`](#tymethod.get_str), this never fails on
+    /// paths that contain invalid Unicode.
+    fn to_bytes(&self) -> Vec<u8>;
 }
 
 impl<P> PathExt for P
@@ -48,4 +54,12 @@ where
     fn get_str(&self) -> anyhow::Result<&str> {
         Ok(self.as_ref().to_str().ok_or(PathError::InvalidUnicode)?)
     }
+
+    fn get_os_file_name(&self) -> anyhow::Result<OsString> {
+        Ok(self.as_ref().file_name().ok_or(PathError::NoFilename)?.to_owned())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_ref().as_os_str().as_bytes().to_vec()
+    }
 }
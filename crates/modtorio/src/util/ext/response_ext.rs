@@ -8,7 +8,10 @@ use tokio::prelude::*;
 /// Collection of common functions used with HTTP responses.
 #[async_trait]
 pub trait ResponseExt {
-    /// Asynchronously copies the response body to a `Writer` object.
+    /// Asynchronously copies the response body to a `Writer` object. If the response has a `Content-Length` header,
+    /// the body is verified against it: the copy is aborted as soon as the body is longer than expected, and a body
+    /// that ends early or whose connection is cut returns
+    /// [`Err(ResponseError::Truncated)`](crate::error::ResponseError::Truncated).
     async fn to_writer<W>(&mut self, dest: &mut W) -> anyhow::Result<usize>
     where
         W: AsyncWrite + Unpin + Send;
@@ -25,12 +28,31 @@ impl ResponseExt for reqwest::Response {
     where
         W: AsyncWrite + Unpin + Send,
     {
+        let expected = self.content_length();
         let mut written = 0;
-        while let Some(chunk) = self.chunk().await? {
+
+        loop {
+            let chunk = match self.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) => {
+                    return match expected {
+                        Some(expected) => Err(ResponseError::Truncated {
+                            expected,
+                            received: written as u64,
+                        }
+                        .into()),
+                        None => Err(e.into()),
+                    };
+                }
+            };
+
             written += chunk.len();
+            verify_length(expected, written as u64, false)?;
             dest.write_all(&chunk).await?;
         }
 
+        verify_length(expected, written as u64, true)?;
         Ok(written)
     }
 
@@ -42,3 +64,39 @@ impl ResponseExt for reqwest::Response {
             .ok_or_else(|| ResponseError::NoFilename.into())
     }
 }
+
+/// Verifies the amount of received bytes of a response body against the body's expected length, if known. Before the
+/// body has finished, only receiving too many bytes is an error.
+fn verify_length(expected: Option<u64>, received: u64, finished: bool) -> Result<(), ResponseError> {
+    match expected {
+        Some(expected) if received > expected => Err(ResponseError::Oversized { expected, received }),
+        Some(expected) if finished && received < expected => Err(ResponseError::Truncated { expected, received }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn length_verification() {
+        assert!(verify_length(Some(10), 5, false).is_ok());
+        assert!(verify_length(Some(10), 10, true).is_ok());
+        assert!(verify_length(None, 5, true).is_ok());
+        assert!(matches!(
+            verify_length(Some(10), 5, true),
+            Err(ResponseError::Truncated {
+                expected: 10,
+                received: 5
+            })
+        ));
+        assert!(matches!(
+            verify_length(Some(10), 11, false),
+            Err(ResponseError::Oversized {
+                expected: 10,
+                received: 11
+            })
+        ));
+    }
+}
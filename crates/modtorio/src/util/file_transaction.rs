@@ -0,0 +1,325 @@
+//! Provides the [`FileTransaction`](FileTransaction) object, used to replace the contents of multiple files so that
+//! either all of them or none of them are changed, even if the program crashes in the middle.
+//!
+//! A commit happens in stages:
+//! 1. Every new file's contents are written to a temporary file next to its target and flushed to disk.
+//! 2. A journal file is created in the transaction's directory. While the journal exists, the transaction is considered
+//!    uncommitted.
+//! 3. Every existing target is hard-linked to a backup, and every missing target gets a marker file.
+//! 4. The temporary files are renamed over their targets.
+//! 5. The journal is removed, which commits the transaction, after which the backups and markers are removed.
+//!
+//! If a stage fails, the changes made so far are rolled back. If the program crashed while the journal existed, the
+//! next commit with the same files rolls the interrupted transaction back before doing anything else.
+
+use std::{
+    ffi::OsString,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// The suffix appended to a target's file name to get its temporary file's name.
+const TEMP_SUFFIX: &str = ".modtorio-tmp";
+/// The suffix appended to a target's file name to get its backup's file name.
+const BACKUP_SUFFIX: &str = ".modtorio-bak";
+/// The suffix appended to a target's file name to get its marker's file name, used when the target didn't exist.
+const NEW_MARKER_SUFFIX: &str = ".modtorio-new";
+/// The journal's file name.
+const JOURNAL_FILENAME: &str = ".modtorio-transaction";
+
+/// A set of file writes applied atomically.
+#[derive(Debug)]
+pub struct FileTransaction {
+    /// The directory the journal is kept in.
+    directory: PathBuf,
+    /// The target files and their new contents.
+    writes: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// A single file's change in a transaction.
+#[derive(Debug, PartialEq)]
+pub struct FileChange {
+    /// The file's path.
+    pub path: PathBuf,
+    /// Whether the file's contents would change.
+    pub changed: bool,
+    /// A line-based diff from the file's current contents to its new contents. Removed lines are prefixed with `-`,
+    /// added lines with `+` and unchanged lines with a space.
+    pub diff: String,
+}
+
+impl FileTransaction {
+    /// Returns a new empty `FileTransaction` that keeps its journal in a given directory.
+    pub fn new<P>(directory: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Adds a write of given contents to a given file.
+    pub fn with_file<P, C>(mut self, path: P, contents: C) -> Self
+    where
+        P: AsRef<Path>,
+        C: Into<Vec<u8>>,
+    {
+        self.writes.push((path.as_ref().to_path_buf(), contents.into()));
+        self
+    }
+
+    /// Returns the changes the transaction would make without changing anything.
+    pub fn diff(&self) -> anyhow::Result<Vec<FileChange>> {
+        let mut changes = Vec::new();
+
+        for (path, contents) in &self.writes {
+            let current = if path.exists() { fs::read(path)? } else { Vec::new() };
+            let current = String::from_utf8_lossy(&current);
+            let new = String::from_utf8_lossy(contents);
+
+            changes.push(FileChange {
+                path: path.clone(),
+                changed: current != new,
+                diff: line_diff(&current, &new),
+            });
+        }
+
+        Ok(changes)
+    }
+
+    /// Applies the transaction. Either all files are changed, or if an error is returned, none of them are.
+    pub fn commit(self) -> anyhow::Result<()> {
+        self.recover()?;
+
+        for (index, (path, contents)) in self.writes.iter().enumerate() {
+            if let Err(e) = write_synced(&sibling(path, TEMP_SUFFIX), contents) {
+                self.remove_files(&self.writes[..=index], TEMP_SUFFIX);
+                return Err(e);
+            }
+        }
+
+        let journal = self.directory.join(JOURNAL_FILENAME);
+        if let Err(e) = write_synced(&journal, &[]).and_then(|_| sync_directory(&self.directory)) {
+            self.remove_files(&self.writes, TEMP_SUFFIX);
+            let _ = fs::remove_file(&journal);
+            return Err(e);
+        }
+
+        if let Err(e) = self.replace_targets() {
+            self.rollback()?;
+            return Err(e);
+        }
+
+        fs::remove_file(&journal)?;
+        sync_directory(&self.directory)?;
+
+        self.remove_files(&self.writes, BACKUP_SUFFIX);
+        self.remove_files(&self.writes, NEW_MARKER_SUFFIX);
+        Ok(())
+    }
+
+    /// Rolls back a previous transaction with the same files if it was interrupted before it was committed, and
+    /// removes any files left over from a previous transaction.
+    pub fn recover(&self) -> anyhow::Result<()> {
+        if self.directory.join(JOURNAL_FILENAME).exists() {
+            self.rollback()?;
+        }
+
+        self.remove_files(&self.writes, TEMP_SUFFIX);
+        self.remove_files(&self.writes, BACKUP_SUFFIX);
+        self.remove_files(&self.writes, NEW_MARKER_SUFFIX);
+        Ok(())
+    }
+
+    /// Backs up the targets and renames the temporary files over them.
+    fn replace_targets(&self) -> anyhow::Result<()> {
+        for (path, _) in &self.writes {
+            if path.exists() {
+                fs::hard_link(path, sibling(path, BACKUP_SUFFIX))?;
+            } else {
+                write_synced(&sibling(path, NEW_MARKER_SUFFIX), &[])?;
+            }
+        }
+
+        for (path, _) in &self.writes {
+            sync_directory(parent(path))?;
+        }
+
+        for (path, _) in &self.writes {
+            fs::rename(sibling(path, TEMP_SUFFIX), path)?;
+        }
+
+        for (path, _) in &self.writes {
+            sync_directory(parent(path))?;
+        }
+
+        Ok(())
+    }
+
+    /// Restores the targets from their backups, removes targets that didn't exist before and removes the journal.
+    fn rollback(&self) -> anyhow::Result<()> {
+        for (path, _) in &self.writes {
+            let backup = sibling(path, BACKUP_SUFFIX);
+            let marker = sibling(path, NEW_MARKER_SUFFIX);
+
+            if backup.exists() {
+                fs::rename(&backup, path)?;
+            } else if marker.exists() {
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+                fs::remove_file(&marker)?;
+            }
+        }
+
+        self.remove_files(&self.writes, TEMP_SUFFIX);
+        fs::remove_file(self.directory.join(JOURNAL_FILENAME))?;
+        sync_directory(&self.directory)?;
+        Ok(())
+    }
+
+    /// Removes the files with a given suffix next to given targets, ignoring any errors.
+    fn remove_files(&self, writes: &[(PathBuf, Vec<u8>)], suffix: &str) {
+        for (path, _) in writes {
+            let _ = fs::remove_file(sibling(path, suffix));
+        }
+    }
+}
+
+/// Returns the path of a file next to a given path, with a given suffix appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// Returns a given path's parent directory, or the current directory if it has none.
+fn parent(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Writes given contents to a given file and flushes them to disk.
+fn write_synced(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Flushes a given directory's entries to disk, so renames and removals in it are durable.
+fn sync_directory(directory: &Path) -> anyhow::Result<()> {
+    fs::File::open(directory)?.sync_all()?;
+    Ok(())
+}
+
+/// Returns a line-based diff between two given strings.
+fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lengths of the longest common subsequences of the lines' suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push_str(&format!(" {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commit() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let existing = dir.path().join("existing");
+        let new = dir.path().join("new");
+        fs::write(&existing, "old").expect("failed to write file");
+
+        FileTransaction::new(dir.path())
+            .with_file(&existing, "changed")
+            .with_file(&new, "created")
+            .commit()
+            .expect("failed to commit transaction");
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "changed");
+        assert_eq!(fs::read_to_string(&new).unwrap(), "created");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn recover_interrupted() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let existing = dir.path().join("existing");
+        let new = dir.path().join("new");
+        fs::write(&existing, "old").expect("failed to write file");
+
+        // simulate a crash after the first file was replaced
+        fs::hard_link(&existing, sibling(&existing, BACKUP_SUFFIX)).unwrap();
+        fs::write(sibling(&new, NEW_MARKER_SUFFIX), "").unwrap();
+        fs::write(sibling(&new, TEMP_SUFFIX), "created").unwrap();
+        fs::write(sibling(&existing, TEMP_SUFFIX), "changed").unwrap();
+        fs::rename(sibling(&existing, TEMP_SUFFIX), &existing).unwrap();
+        fs::write(dir.path().join(JOURNAL_FILENAME), "").unwrap();
+
+        FileTransaction::new(dir.path())
+            .with_file(&existing, "changed")
+            .with_file(&new, "created")
+            .recover()
+            .expect("failed to recover");
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old");
+        assert!(!new.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn diff() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("file");
+        fs::write(&path, "a\nb\nc\n").expect("failed to write file");
+
+        let changes = FileTransaction::new(dir.path())
+            .with_file(&path, "a\nx\nc\n")
+            .diff()
+            .expect("failed to diff");
+
+        assert_eq!(
+            changes,
+            vec![FileChange {
+                path,
+                changed: true,
+                diff: String::from(" a\n-b\n+x\n c\n"),
+            }]
+        );
+    }
+}
@@ -10,7 +10,7 @@ use rusqlite::{
     types::{self, ToSqlOutput},
     ToSql,
 };
-use serde::{de, de::Visitor, Deserialize};
+use serde::{de, de::Visitor, Deserialize, Serialize};
 use std::{fmt, fmt::Display, str::FromStr};
 use types::{FromSql, FromSqlError, FromSqlResult, Value, ValueRef};
 
@@ -143,7 +143,8 @@ impl FromStr for HumanVersionReq {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"(>=|<=|>|=|<) ?(.*)").unwrap();
+            // == is accepted as well since that's how the equal comparator is displayed
+            static ref RE: Regex = Regex::new(r"(>=|<=|>|==|=|<) ?(.*)").unwrap();
         }
 
         let captures = RE
@@ -153,7 +154,7 @@ impl FromStr for HumanVersionReq {
         let comparator = match captures.get(1).map(|c| c.as_str()) {
             Some(">=") => Comparator::GreaterOrEqual,
             Some(">") => Comparator::Greater,
-            Some("=") => Comparator::Equal,
+            Some("=") | Some("==") => Comparator::Equal,
             Some("<") => Comparator::Less,
             Some("<=") => Comparator::LessOrEqual,
             Some(c) => panic!("impossible case (regex returned {})", c),
@@ -220,6 +221,28 @@ impl Into<rpc::Version> for HumanVersion {
     }
 }
 
+impl From<Comparator> for rpc::version_requirement::Comparator {
+    fn from(comparator: Comparator) -> Self {
+        match comparator {
+            Comparator::GreaterOrEqual => Self::GreaterOrEqual,
+            Comparator::Greater => Self::Greater,
+            Comparator::Equal => Self::Equal,
+            Comparator::Less => Self::Less,
+            Comparator::LessOrEqual => Self::LessOrEqual,
+        }
+    }
+}
+
+impl From<HumanVersionReq> for rpc::VersionRequirement {
+    fn from(requirement: HumanVersionReq) -> Self {
+        Self {
+            comparator: rpc::version_requirement::Comparator::from(requirement.comparator).into(),
+            version: Some(requirement.version.into()),
+            normalized: requirement.to_string(),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for HumanVersion {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -248,6 +271,15 @@ impl<'de> Deserialize<'de> for HumanVersion {
     }
 }
 
+impl Serialize for HumanVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 impl<'de> Deserialize<'de> for HumanVersionReq {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -395,6 +427,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn version_req_round_trip() -> anyhow::Result<()> {
+        for requirement in &[">=1", "> 1.0", "= 01.2.3", "< 2.0.0", "<=0.18"] {
+            let parsed = requirement.parse::<HumanVersionReq>()?;
+            assert_eq!(parsed.to_string().parse::<HumanVersionReq>()?, parsed);
+        }
+
+        assert_eq!("=1.2".parse::<HumanVersionReq>()?.to_string(), "== 1.2.0");
+        Ok(())
+    }
+
     #[test]
     fn compare_version() -> anyhow::Result<()> {
         assert!("1.0.0".parse::<HumanVersion>()? < "2.0.0".parse::<HumanVersion>()?);
@@ -0,0 +1,19 @@
+//! Provides the [`LogFormat`](LogFormat) enum.
+
+use serde::{Deserialize, Serialize};
+
+/// Represents the format the program's log lines are written in.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable text.
+    Text,
+    /// A JSON object per line, with the RPC request and the server a line was logged for in their own fields.
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        LogFormat::Text
+    }
+}
@@ -1,6 +1,6 @@
 //! Provides the [`LogLevel`](LogLevel) enum.
 
-use log::LevelFilter;
+use log::{Level, LevelFilter};
 use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString, EnumVariantNames};
 
@@ -36,6 +36,17 @@ impl LogLevel {
             LogLevel::Error => LevelFilter::Error,
         }
     }
+
+    /// Returns a logging `Level` based on this `LogLevel`.
+    pub fn to_level(self) -> Level {
+        match self {
+            LogLevel::Trace => Level::Trace,
+            LogLevel::Debug => Level::Debug,
+            LogLevel::Info => Level::Info,
+            LogLevel::Warn => Level::Warn,
+            LogLevel::Error => Level::Error,
+        }
+    }
 }
 
 impl Default for LogLevel {
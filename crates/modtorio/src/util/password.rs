@@ -0,0 +1,28 @@
+//! Provides [`generate`](generate), which generates random passwords.
+
+use rand::Rng;
+
+/// The characters generated passwords consist of. Characters easily mistaken for each other (`0`, `O`, `1`, `I` and
+/// `l`) are left out so the passwords can be read out and typed by hand.
+const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz23456789";
+
+/// Returns a new cryptographically random password of a given length.
+pub fn generate(length: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..length)
+        .map(|_| char::from(ALPHABET[rng.gen_range(0, ALPHABET.len())]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated() {
+        let password = generate(20);
+        assert_eq!(password.len(), 20);
+        assert!(password.bytes().all(|c| ALPHABET.contains(&c)));
+        assert_ne!(password, generate(20));
+    }
+}
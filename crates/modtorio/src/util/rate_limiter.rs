@@ -0,0 +1,100 @@
+//! Provides the [`RateLimiter`](RateLimiter) struct.
+
+use std::time::Duration;
+use tokio::{
+    sync::Mutex,
+    time::{self, Instant},
+};
+
+/// A token bucket limiting how often an operation may be done. The bucket holds up to a burst of tokens and is
+/// refilled at a steady rate; each operation takes a token, waiting for one to be refilled if the bucket is empty.
+/// Operations waiting for a token are queued and let through in the order they arrived.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// How many tokens are refilled per second. 0 means the rate is unlimited.
+    rate: u64,
+    /// The largest amount of tokens the bucket holds.
+    burst: u64,
+    /// The bucket's state. Held by the operation first in the queue while it waits for a token.
+    bucket: Mutex<Bucket>,
+}
+
+/// The state of a token bucket.
+#[derive(Debug)]
+struct Bucket {
+    /// The amount of tokens in the bucket, including partially refilled ones.
+    tokens: f64,
+    /// When the bucket was last refilled.
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Returns a new `RateLimiter` with a full bucket of a given burst of tokens, refilled at a given rate of tokens
+    /// per second. A rate of 0 doesn't limit the rate at all. The burst is always at least 1.
+    pub fn new(rate: u64, burst: u64) -> Self {
+        let burst = burst.max(1);
+
+        Self {
+            rate,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token from the bucket, first waiting until one is refilled if the bucket is empty. Returns how long it
+    /// waited.
+    pub async fn acquire(&self) -> Duration {
+        if self.rate == 0 {
+            return Duration::from_secs(0);
+        }
+
+        let mut bucket = self.bucket.lock().await;
+        let mut waited = Duration::from_secs(0);
+        loop {
+            self.refill(&mut bucket);
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+                return waited;
+            }
+
+            let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate as f64);
+            time::delay_for(wait).await;
+            waited += wait;
+        }
+    }
+
+    /// Refills a given bucket with the tokens accumulated since it was last refilled.
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate as f64).min(self.burst as f64);
+        bucket.refilled_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn limits_rate() {
+        let limiter = RateLimiter::new(20, 2);
+
+        let started = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+        // the burst of 2 goes through immediately and the other 2 wait for a token each, 50 ms apiece
+        assert!(started.elapsed() >= Duration::from_millis(90));
+
+        let unlimited = RateLimiter::new(0, 0);
+        let started = Instant::now();
+        for _ in 0..100 {
+            assert_eq!(unlimited.acquire().await, Duration::from_secs(0));
+        }
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+}
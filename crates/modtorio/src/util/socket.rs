@@ -0,0 +1,161 @@
+//! Provides functions to prepare Unix domain socket files the RPC services listen on.
+
+use crate::error::ConfigError;
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+    path::Path,
+};
+
+/// The name of the socket file in the temporary directory a socket is bound in. Short so the temporary path stays
+/// within the socket path length limit.
+const TEMPORARY_SOCKET_NAME: &str = "s";
+
+/// Returns an error if a given socket path's parent directory doesn't exist.
+pub fn check_parent_directory(path: &Path) -> Result<(), ConfigError> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+            Err(ConfigError::MissingSocketDirectory(parent.to_path_buf()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parses a given octal file mode string, such as `"0660"`.
+pub fn parse_mode(mode: &str) -> Result<u32, ConfigError> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| ConfigError::InvalidSocketMode(mode.to_owned()))
+}
+
+/// Binds a socket to a given path with a given bind function, with a given mode, and a given owner and group if given.
+/// The socket is bound in a temporary directory only the current user can access, and linked to the path once its
+/// permissions are set, so it's never accessible with the default permissions. Like binding the socket directly,
+/// fails if the path already exists.
+pub fn bind<F, L>(path: &Path, mode: u32, owner: Option<&str>, group: Option<&str>, bind: F) -> anyhow::Result<L>
+where
+    F: FnOnce(&Path) -> io::Result<L>,
+{
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    // the temporary directory is created accessible only by the current user and removed when dropped
+    let temporary = tempfile::Builder::new().tempdir_in(parent)?;
+    let temporary_path = temporary.path().join(TEMPORARY_SOCKET_NAME);
+    let listener = bind(&temporary_path)?;
+    set_permissions(&temporary_path, mode, owner, group)?;
+
+    // unlike renaming, linking fails if the path already exists
+    fs::hard_link(&temporary_path, path)?;
+    Ok(listener)
+}
+
+/// Sets a given socket file's mode, and its owner and group if given.
+fn set_permissions(path: &Path, mode: u32, owner: Option<&str>, group: Option<&str>) -> anyhow::Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+
+    if owner.is_none() && group.is_none() {
+        return Ok(());
+    }
+
+    // -1 (i.e. the maximum value) leaves the corresponding ID unchanged
+    let uid = owner.map(user_id).transpose()?.unwrap_or(libc::uid_t::MAX);
+    let gid = group.map(group_id).transpose()?.unwrap_or(libc::gid_t::MAX);
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+
+    // SAFETY: the path is a valid NUL-terminated string
+    if unsafe { libc::chown(c_path.as_ptr(), uid, gid) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    Ok(())
+}
+
+/// Returns the user ID of the user with a given name.
+fn user_id(name: &str) -> Result<libc::uid_t, ConfigError> {
+    let c_name = CString::new(name).map_err(|_| ConfigError::NoSuchUser(name.to_owned()))?;
+
+    // SAFETY: the name is a valid NUL-terminated string. the returned pointer is either null or points to a static
+    // buffer, which is read immediately. this is only called while starting the RPC listeners, so nothing else calls
+    // getpwnam concurrently
+    let passwd = unsafe { libc::getpwnam(c_name.as_ptr()) };
+    if passwd.is_null() {
+        Err(ConfigError::NoSuchUser(name.to_owned()))
+    } else {
+        Ok(unsafe { (*passwd).pw_uid })
+    }
+}
+
+/// Returns the group ID of the group with a given name.
+fn group_id(name: &str) -> Result<libc::gid_t, ConfigError> {
+    let c_name = CString::new(name).map_err(|_| ConfigError::NoSuchGroup(name.to_owned()))?;
+
+    // SAFETY: see user_id
+    let group = unsafe { libc::getgrnam(c_name.as_ptr()) };
+    if group.is_null() {
+        Err(ConfigError::NoSuchGroup(name.to_owned()))
+    } else {
+        Ok(unsafe { (*group).gr_gid })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn modes() {
+        assert_eq!(parse_mode("0660").expect("failed to parse mode"), 0o660);
+        assert_eq!(parse_mode("600").expect("failed to parse mode"), 0o600);
+        assert!(parse_mode("0968").is_err());
+        assert!(parse_mode("17777").is_err());
+    }
+
+    #[test]
+    fn missing_parent_directory() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        assert!(check_parent_directory(&dir.path().join("modtorio.sock")).is_ok());
+        assert!(matches!(
+            check_parent_directory(&dir.path().join("missing/modtorio.sock")),
+            Err(ConfigError::MissingSocketDirectory(_))
+        ));
+    }
+
+    #[test]
+    fn socket_permissions() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let path = dir.path().join("modtorio.sock");
+        let _listener = UnixListener::bind(&path).expect("failed to bind socket");
+
+        set_permissions(&path, 0o600, None, None).expect("failed to set permissions");
+        let mode = fs::metadata(&path)
+            .expect("failed to read metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o600);
+
+        assert!(set_permissions(&path, 0o600, Some("no-such-user-for-modtorio"), None).is_err());
+    }
+
+    #[test]
+    fn bind_restricted() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let path = dir.path().join("modtorio.sock");
+
+        let _listener = bind(&path, 0o600, None, None, |path| UnixListener::bind(path)).expect("failed to bind socket");
+        let mode = fs::metadata(&path)
+            .expect("failed to read metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o600);
+        // only the socket is left in the directory
+        assert_eq!(fs::read_dir(dir.path()).expect("failed to read directory").count(), 1);
+
+        assert!(bind(&path, 0o600, None, None, |path| UnixListener::bind(path)).is_err());
+    }
+}
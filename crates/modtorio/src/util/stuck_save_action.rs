@@ -0,0 +1,19 @@
+//! Provides the [`StuckSaveAction`](StuckSaveAction) enum.
+
+use serde::{Deserialize, Serialize};
+
+/// Represents the action taken when a server has been saving its map for longer than the configured timeout.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum StuckSaveAction {
+    /// Only log an alert and flag the server's status.
+    Alert,
+    /// Alert, then kill the server's executable and start it again.
+    Restart,
+}
+
+impl Default for StuckSaveAction {
+    fn default() -> Self {
+        StuckSaveAction::Alert
+    }
+}
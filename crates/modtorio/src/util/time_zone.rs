@@ -0,0 +1,104 @@
+//! Provides the [`TimeZone`](TimeZone) struct.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{de, Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// An IANA time zone (for example `Europe/Helsinki`) timestamps are displayed in. Internally every timestamp is UTC;
+/// converting one to a time zone applies the zone's offset at that instant, so daylight saving time is accounted for.
+///
+/// Parses from and serialises to the zone's IANA name. Defaults to UTC.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct TimeZone(Tz);
+
+impl Default for TimeZone {
+    fn default() -> Self {
+        Self(Tz::UTC)
+    }
+}
+
+impl TimeZone {
+    /// Returns the zone's IANA name.
+    pub fn name(self) -> &'static str {
+        self.0.name()
+    }
+
+    /// Converts a given UTC timestamp to this time zone.
+    pub fn localize(self, timestamp: DateTime<Utc>) -> DateTime<Tz> {
+        timestamp.with_timezone(&self.0)
+    }
+
+    /// Returns a given UTC timestamp converted to this time zone as an RFC 3339 string, including the offset in effect
+    /// at that instant.
+    pub fn format(self, timestamp: DateTime<Utc>) -> String {
+        self.localize(timestamp).to_rfc3339_opts(SecondsFormat::Secs, false)
+    }
+}
+
+impl FromStr for TimeZone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.parse::<Tz>().map_err(anyhow::Error::msg)?))
+    }
+}
+
+impl fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for TimeZone {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeZone {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone as _;
+
+    #[test]
+    fn daylight_saving_time() {
+        let zone: TimeZone = "Europe/Helsinki".parse().unwrap();
+
+        assert_eq!(
+            zone.format(Utc.ymd(2020, 1, 15).and_hms(12, 0, 0)),
+            "2020-01-15T14:00:00+02:00"
+        );
+        assert_eq!(
+            zone.format(Utc.ymd(2020, 7, 15).and_hms(12, 0, 0)),
+            "2020-07-15T15:00:00+03:00"
+        );
+    }
+
+    #[test]
+    fn default_is_utc() {
+        assert_eq!(TimeZone::default().name(), "UTC");
+        assert_eq!(
+            TimeZone::default().format(Utc.ymd(2020, 7, 15).and_hms(12, 0, 0)),
+            "2020-07-15T12:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn unknown_zone() {
+        assert!("Mars/Olympus_Mons".parse::<TimeZone>().is_err());
+    }
+}
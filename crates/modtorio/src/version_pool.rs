@@ -0,0 +1,178 @@
+//! Provides the [`VersionPool`](VersionPool) object, a shared pool of installed Factorio headless server versions
+//! servers can run instead of the executable in their own root directory.
+//!
+//! Each version is installed into its own directory under the configured versions directory, named after the version.
+//! A server set to use a pooled version runs that version's executable with its own root directory as the game's
+//! write data directory, so switching a server between versions only changes which executable it's started with.
+
+use crate::{
+    error::RpcError,
+    factorio::executable,
+    installer::Installer,
+    util::{async_status::AsyncProgressChannel, HumanVersion},
+};
+use log::*;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// The path relative to a server's root directory of the game config file written for running the server with a pooled
+/// version.
+const POOLED_CONFIG_PATH: &str = "config/modtorio-pooled.ini";
+
+/// A shared pool of installed Factorio versions.
+#[derive(Debug, Clone)]
+pub struct VersionPool {
+    /// The directory the versions are installed in.
+    directory: PathBuf,
+}
+
+impl VersionPool {
+    /// Returns a new `VersionPool` in a given directory.
+    pub fn new<P>(directory: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the directory a given version is installed in.
+    pub fn root(&self, version: HumanVersion) -> PathBuf {
+        self.directory.join(version.to_string())
+    }
+
+    /// Returns the location of a given version's server executable.
+    pub fn executable(&self, version: HumanVersion) -> PathBuf {
+        self.root(version).join(executable::DEFAULT_PATH)
+    }
+
+    /// Returns whether a given version is installed in the pool.
+    pub async fn contains(&self, version: HumanVersion) -> bool {
+        fs::metadata(self.executable(version))
+            .await
+            .map_or(false, |meta| meta.is_file())
+    }
+
+    /// Returns the installed versions, sorted from the oldest to the newest. Directories in the pool that aren't named
+    /// after a version or don't have a server executable, such as partially installed versions, are skipped.
+    pub async fn list(&self) -> anyhow::Result<Vec<HumanVersion>> {
+        let mut entries = match fs::read_dir(&self.directory).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut versions = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let version = match entry.file_name().to_str().map(str::parse::<HumanVersion>) {
+                Some(Ok(version)) => version,
+                _ => continue,
+            };
+
+            if self.contains(version).await {
+                versions.push(version);
+            } else {
+                debug!("Skipping incomplete pooled version {}", entry.path().display());
+            }
+        }
+
+        versions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Ok(versions)
+    }
+
+    /// Installs a given version into the pool with a given installer, reporting progress to a given progress channel.
+    /// Returns [`RpcError::PooledVersionExists`](RpcError::PooledVersionExists) if the version is already installed.
+    pub async fn install(
+        &self,
+        installer: &Installer,
+        version: HumanVersion,
+        prog_tx: Option<AsyncProgressChannel>,
+    ) -> anyhow::Result<()> {
+        if self.contains(version).await {
+            return Err(RpcError::PooledVersionExists(version).into());
+        }
+
+        installer.install(version, &self.root(version), prog_tx).await?;
+        info!("Installed Factorio {} into the version pool", version);
+        Ok(())
+    }
+
+    /// Removes a given version from the pool. Returns [`RpcError::NoSuchPooledVersion`](RpcError::NoSuchPooledVersion)
+    /// if the version isn't installed.
+    pub async fn remove(&self, version: HumanVersion) -> anyhow::Result<()> {
+        if !self.contains(version).await {
+            return Err(RpcError::NoSuchPooledVersion(version).into());
+        }
+
+        fs::remove_dir_all(self.root(version)).await?;
+        info!("Removed Factorio {} from the version pool", version);
+        Ok(())
+    }
+}
+
+/// Returns the location of the game config file a server in a given root directory is run with when it uses a pooled
+/// version.
+pub fn pooled_config_path(root: &Path) -> PathBuf {
+    root.join(POOLED_CONFIG_PATH)
+}
+
+/// Writes the game config file a server in a given root directory is run with when it uses a pooled version. The config
+/// makes the game read its data from the pooled version's directory and write everything else into the server's root
+/// directory, instead of the pooled version's directory every server using the version shares.
+pub async fn write_pooled_config(root: &Path) -> anyhow::Result<()> {
+    let path = pooled_config_path(root);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let contents = format!(
+        "; written by Modtorio for running the server with a pooled Factorio version\n[path]\n\
+         read-data=__PATH__executable__/../../data\nwrite-data={}\n",
+        root.display()
+    );
+    fs::write(&path, contents).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn list_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = VersionPool::new(dir.path());
+        assert!(pool.list().await.unwrap().is_empty());
+
+        for version in &["1.1.0", "0.18.47"] {
+            let executable = pool.executable(version.parse().unwrap());
+            std::fs::create_dir_all(executable.parent().unwrap()).unwrap();
+            std::fs::write(executable, "").unwrap();
+        }
+        // partially installed and unrelated directories aren't versions in the pool
+        std::fs::create_dir_all(dir.path().join("1.0.0/data")).unwrap();
+        std::fs::create_dir_all(dir.path().join("scratch/bin/x64")).unwrap();
+
+        let versions = pool.list().await.unwrap();
+        assert_eq!(
+            versions,
+            vec!["0.18.47".parse().unwrap(), "1.1.0".parse::<HumanVersion>().unwrap()]
+        );
+        assert!(!pool.contains("1.0.0".parse().unwrap()).await);
+
+        pool.remove("1.1.0".parse().unwrap()).await.unwrap();
+        assert_eq!(pool.list().await.unwrap(), vec!["0.18.47".parse().unwrap()]);
+        assert!(pool.remove("1.1.0".parse().unwrap()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn pooled_config() {
+        let dir = tempfile::tempdir().unwrap();
+        write_pooled_config(dir.path()).await.unwrap();
+
+        let contents = std::fs::read_to_string(pooled_config_path(dir.path())).unwrap();
+        assert!(contents.contains(&format!("write-data={}\n", dir.path().display())));
+        assert!(contents.contains("read-data=__PATH__executable__/../../data\n"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Provides [`spawn_listener`](spawn_listener), which serves an HTTP endpoint an external notifier or polling pipeline
+//! can post mod release notifications to, so the managed servers learn about new releases soon after they're
+//! published without polling the mod portal aggressively.
+//!
+//! A notification is a `POST` to `/mod-releases` with a JSON body such as `{"mod": "Krastorio2", "version":
+//! "1.1.4"}`. Requests are authorized with the configured shared secret as a bearer token in their `authorization`
+//! header, independently of the RPC tokens, so the webhook can't be used before a secret is configured.
+
+use crate::{
+    error::ConfigError,
+    util::{self, checksum, HumanVersion},
+    Config,
+};
+use anyhow::Context;
+use common::net::NetAddress;
+use futures::{Future, Stream};
+use hyper::{
+    body::HttpBody,
+    server::accept,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use log::*;
+use serde::Deserialize;
+use std::{convert::Infallible, io, sync::Arc};
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, UnixListener},
+    sync::watch,
+    task::{self, JoinHandle},
+};
+
+/// The HTTP path mod release notifications are posted to.
+const RELEASES_PATH: &str = "/mod-releases";
+/// The prefix of the shared secret in the authorization header value.
+const BEARER_PREFIX: &str = "Bearer ";
+/// The largest accepted notification body in bytes.
+const MAX_BODY_SIZE: usize = 16 * 1024;
+
+/// A notification that a mod has released a new version.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct ModRelease {
+    /// The mod's name.
+    #[serde(rename = "mod")]
+    pub name: String,
+    /// The released version.
+    pub version: HumanVersion,
+}
+
+/// Spawns a task that receives mod release notifications over HTTP on a given listen address until a given shutdown
+/// signal receiver receives a value. Every notification that presents the shared secret from a given config is passed
+/// to a given function, which handles it. Unix sockets are bound before the task is spawned, with the file mode and
+/// ownership from the config. Returns the task's `JoinHandle`, or an error if the config has no shared secret.
+pub async fn spawn_listener<F, Fut>(
+    listen: &NetAddress,
+    config: &Config,
+    handle: F,
+    mut shutdown_rx: watch::Receiver<()>,
+) -> anyhow::Result<JoinHandle<()>>
+where
+    F: Fn(ModRelease) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let secret_hash: Arc<str> =
+        checksum::blake2b_string(config.webhook_secret().ok_or(ConfigError::MissingWebhookSecret)?).into();
+    let shutdown_signal = async move {
+        shutdown_rx.recv().await;
+    };
+
+    match listen {
+        NetAddress::TCP(addr) => {
+            debug!("Starting mod release webhook on TCP {}", addr);
+
+            let mut tcp = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind mod release webhook to {}", addr))?;
+            let addr = *addr;
+            Ok(task::spawn(async move {
+                serve(tcp.incoming(), secret_hash, handle, shutdown_signal)
+                    .await
+                    .expect("webhook TCP listener failed");
+                debug!("Webhook TCP listener on {} shut down", addr);
+            }))
+        }
+        NetAddress::Unix(path) => {
+            debug!("Starting mod release webhook on Unix {}", path.display());
+
+            util::socket::check_parent_directory(path)?;
+            let mut unix = util::socket::bind(
+                path,
+                config.socket_mode()?,
+                config.socket_owner(),
+                config.socket_group(),
+                |path| UnixListener::bind(path),
+            )
+            .with_context(|| format!("Failed to bind to Unix socket {}", path.display()))?;
+
+            let path = path.to_owned();
+            Ok(task::spawn(async move {
+                serve(unix.incoming(), secret_hash, handle, shutdown_signal)
+                    .await
+                    .expect("webhook Unix listener failed");
+
+                debug!("Webhook Unix listener on {} shut down, removing socket", path.display());
+                fs::remove_file(&path).await.expect("failed to remove socket");
+            }))
+        }
+    }
+}
+
+/// Receives mod release notifications over HTTP on the connections from a given stream until a given shutdown signal
+/// completes. Notifications are authorized against a given hash of the shared secret.
+async fn serve<I, S, F, Fut>(
+    incoming: I,
+    secret_hash: Arc<str>,
+    handle: F,
+    shutdown_signal: impl Future<Output = ()>,
+) -> hyper::Result<()>
+where
+    I: Stream<Item = io::Result<S>>,
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    F: Fn(ModRelease) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let make_service = make_service_fn(move |_: &S| {
+        let secret_hash = Arc::clone(&secret_hash);
+        let handle = handle.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                respond(req, Arc::clone(&secret_hash), handle.clone())
+            }))
+        }
+    });
+
+    Server::builder(accept::from_stream(incoming))
+        .serve(make_service)
+        .with_graceful_shutdown(shutdown_signal)
+        .await
+}
+
+/// Passes the mod release notification in a given HTTP request to a given function and responds with 202 if it
+/// accepts the notification. Notifications that don't present the shared secret with a given hash are rejected with
+/// 401 and malformed notifications with 400, both without calling the function.
+async fn respond<F, Fut>(req: Request<Body>, secret_hash: Arc<str>, handle: F) -> Result<Response<Body>, Infallible>
+where
+    F: Fn(ModRelease) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let status = if req.uri().path() != RELEASES_PATH {
+        StatusCode::NOT_FOUND
+    } else if req.method() != Method::POST {
+        StatusCode::METHOD_NOT_ALLOWED
+    } else if !is_authorized(&req, &secret_hash) {
+        debug!("Rejected mod release notification without the webhook secret");
+        StatusCode::UNAUTHORIZED
+    } else {
+        match read_body(req.into_body()).await {
+            None => StatusCode::PAYLOAD_TOO_LARGE,
+            Some(body) => match serde_json::from_slice::<ModRelease>(&body) {
+                Ok(release) => match handle(release).await {
+                    Ok(()) => StatusCode::ACCEPTED,
+                    Err(e) => {
+                        warn!("Failed to handle mod release notification: {}", e);
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    }
+                },
+                Err(e) => {
+                    debug!("Rejected malformed mod release notification: {}", e);
+                    StatusCode::BAD_REQUEST
+                }
+            },
+        }
+    };
+
+    Ok(Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("failed to build webhook response"))
+}
+
+/// Returns whether a given request presents the shared secret with a given hash as its bearer token. The hashes are
+/// compared instead of the secrets so the comparison's timing doesn't reveal the secret.
+fn is_authorized(req: &Request<Body>, secret_hash: &str) -> bool {
+    req.headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix(BEARER_PREFIX))
+        .map_or(false, |secret| checksum::blake2b_string(secret) == secret_hash)
+}
+
+/// Reads a given request body. Returns `None` if the body is larger than the maximum body size or can't be read.
+async fn read_body(mut body: Body) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.ok()?;
+        if bytes.len() + chunk.len() > MAX_BODY_SIZE {
+            return None;
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn post(path: &str, authorization: Option<&str>, body: &str) -> StatusCode {
+        let mut request = Request::post(path);
+        if let Some(authorization) = authorization {
+            request = request.header("authorization", authorization);
+        }
+        let request = request.body(Body::from(body.to_owned())).unwrap();
+        let handle = |release: ModRelease| async move {
+            assert_eq!(release.name, "Krastorio2");
+            assert_eq!(release.version, HumanVersion::new(1, 1, 4));
+            Ok(())
+        };
+
+        respond(request, checksum::blake2b_string("secret").into(), handle)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn notifications() {
+        let release = r#"{"mod": "Krastorio2", "version": "1.1.4"}"#;
+        let secret = Some("Bearer secret");
+
+        assert_eq!(post(RELEASES_PATH, secret, release).await, StatusCode::ACCEPTED);
+        assert_eq!(post("/metrics", secret, release).await, StatusCode::NOT_FOUND);
+        assert_eq!(
+            post(RELEASES_PATH, secret, r#"{"mod": "Krastorio2"}"#).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            post(RELEASES_PATH, secret, &" ".repeat(MAX_BODY_SIZE + 1)).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(post(RELEASES_PATH, None, release).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            post(RELEASES_PATH, Some("Bearer wrong"), release).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            post(RELEASES_PATH, Some("secret"), release).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
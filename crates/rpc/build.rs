@@ -1,6 +1,45 @@
+use std::{env, fs, path::Path};
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-changed=proto/mod_rpc.proto");
 
     tonic_build::compile_protos("proto/mod_rpc.proto")?;
+
+    let proto = fs::read_to_string("proto/mod_rpc.proto")?;
+    let dest_path = Path::new(&env::var_os("OUT_DIR").unwrap()).join("methods.rs");
+    fs::write(
+        &dest_path,
+        format!(
+            r##"/// The paths of every RPC method in the services, such as `/mod_rpc.ModRpc/InstallMod`.
+pub const METHODS: &[&str] = &[{}];"##,
+            method_paths(&proto)
+                .iter()
+                .map(|path| format!("{:?}", path))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    )?;
     Ok(())
 }
+
+/// Returns the paths of every RPC method declared in the services of a given protocol buffer specification.
+fn method_paths(proto: &str) -> Vec<String> {
+    let mut package = "";
+    let mut service = None;
+    let mut paths = Vec::new();
+
+    for line in proto.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("package ") {
+            package = name.trim_end_matches(';').trim();
+        } else if let Some(name) = line.strip_prefix("service ") {
+            service = Some(name.trim_end_matches('{').trim());
+        } else if line.starts_with('}') {
+            service = None;
+        } else if let (Some(service), Some(method)) = (service, line.strip_prefix("rpc ")) {
+            let method = method.split('(').next().unwrap_or_default().trim();
+            paths.push(format!("/{}.{}/{}", package, service, method));
+        }
+    }
+
+    paths
+}
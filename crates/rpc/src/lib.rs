@@ -1,4 +1,5 @@
 tonic::include_proto!("mod_rpc");
+include!(concat!(env!("OUT_DIR"), "/methods.rs"));
 
 /// The version of the RPC protocol buffer specification.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -8,3 +9,88 @@ impl Into<Empty> for () {
         Empty {}
     }
 }
+
+/// A request addressed to a single managed server, either by the server's store ID or by its unique name.
+pub trait ServerRequest {
+    /// Returns the server's store ID the request addresses, or 0 if it isn't set.
+    fn server_id(&self) -> i64;
+    /// Returns the server's name the request addresses, or an empty string if it isn't set.
+    fn server_name(&self) -> &str;
+    /// Sets the server's store ID the request addresses.
+    fn set_server_id(&mut self, server_id: i64);
+}
+
+/// Implements [`ServerRequest`](ServerRequest) for given request messages with `server_id` and `server_name` fields.
+macro_rules! impl_server_request {
+    ($($request:ident),* $(,)?) => {
+        $(
+            impl ServerRequest for $request {
+                fn server_id(&self) -> i64 {
+                    self.server_id
+                }
+
+                fn server_name(&self) -> &str {
+                    &self.server_name
+                }
+
+                fn set_server_id(&mut self, server_id: i64) {
+                    self.server_id = server_id;
+                }
+            }
+        )*
+    };
+}
+
+impl_server_request!(
+    ApplyModPresetRequest,
+    ApplyModpackRequest,
+    ApplyServerSettingsRequest,
+    ChatBridgeRequest,
+    CreateBackupRequest,
+    CreateSaveRequest,
+    EnsureModDependenciesRequest,
+    ExportMapExchangeStringRequest,
+    ExportModpackRequest,
+    GenerateGamePasswordRequest,
+    GetModPolicyRequest,
+    GetModUpdateScheduleRequest,
+    GetOffendersRequest,
+    GetPlayersRequest,
+    GetPortalCredentialsRequest,
+    GetRawServerFilesRequest,
+    GetServerEventsRequest,
+    GetServerSettingsRequest,
+    ImportMapExchangeStringRequest,
+    InspectPrototypesRequest,
+    InstallModRequest,
+    ListBackupsRequest,
+    ListModPresetsRequest,
+    ListModsRequest,
+    PlayerCountHistoryRequest,
+    PruneAutosavesRequest,
+    RefreshServerRequest,
+    RemoveModPresetRequest,
+    RemoveModRequest,
+    RemoveServerRequest,
+    ResolveSettingsConflictRequest,
+    RestoreBackupRequest,
+    RetryStoreUpdateRequest,
+    RevertToLastKnownGoodRequest,
+    RunServerRequest,
+    RunToolRequest,
+    SaveMapRequest,
+    SaveModPresetRequest,
+    SendCommandRequest,
+    ServerStatusRequest,
+    SetModPolicyRequest,
+    SetModUpdatePolicyRequest,
+    SetModUpdateScheduleRequest,
+    SetPortalCredentialsRequest,
+    SetServerNameRequest,
+    SetServerSettingsRequest,
+    StopServerRequest,
+    UpdateModsRequest,
+    UpdateStoreRequest,
+    UpgradeServerRequest,
+    VerifyModsRequest,
+);